@@ -1,8 +1,11 @@
 #[cfg(feature = "allocator_api")]
-use std::alloc::Allocator;
-use std::mem::MaybeUninit;
+use alloc::alloc::Allocator;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use alloc::{rc::Rc, string::String, vec::Vec};
+use core::mem::MaybeUninit;
 
-use crate::{buf::*, vec_alloc};
+use crate::*;
 
 /// An IOCP compatible buffer.
 ///
@@ -14,6 +17,19 @@ use crate::{buf::*, vec_alloc};
 /// Buffers passed to IOCP operations must reference a stable memory
 /// region. While the runtime holds ownership to a buffer, the pointer returned
 /// by `as_buf_ptr` must remain valid even if the `IoBuf` value is moved.
+///
+/// # Threads
+///
+/// `IoBuf` doesn't require [`Send`]. An operation is submitted, completed and
+/// dropped on the thread of its runtime, and the kernel only accesses the
+/// bytes, so the buffer itself never moves to another thread. Therefore the
+/// buffers shared by [`Rc`], e.g., a message written to many sockets, are
+/// valid without the atomic reference counts of [`Arc`]. The operations which
+/// run on other threads, e.g., the ones on a thread pool, require [`Send`] on
+/// their buffers by themselves.
+///
+/// The shared buffers are read-only, so their capacity is their length, and
+/// [`IoBuf::slice`] gives a part of them without copying.
 pub unsafe trait IoBuf: 'static {
     /// Returns a raw pointer to the vector’s buffer.
     ///
@@ -42,7 +58,12 @@ pub unsafe trait IoBuf: 'static {
 
     /// Get the initialized part of the buffer.
     fn as_slice(&self) -> &[u8] {
-        unsafe { std::slice::from_raw_parts(self.as_buf_ptr(), self.buf_len()) }
+        unsafe { core::slice::from_raw_parts(self.as_buf_ptr(), self.buf_len()) }
+    }
+
+    /// Get the initialized part of the buffer as an [`IoSlice`].
+    fn as_io_slice(&self) -> IoSlice {
+        IoSlice::new(self.as_buf_ptr(), self.buf_len())
     }
 
     /// Returns a view of the buffer with the specified range.
@@ -53,16 +74,16 @@ pub unsafe trait IoBuf: 'static {
     /// # Examples
     ///
     /// ```
-    /// use compio::buf::IoBuf;
+    /// use compio_buf::IoBuf;
     ///
     /// let buf = b"hello world";
     /// buf.slice(5..10);
     /// ```
-    fn slice(self, range: impl std::ops::RangeBounds<usize>) -> Slice<Self>
+    fn slice(self, range: impl core::ops::RangeBounds<usize>) -> Slice<Self>
     where
         Self: Sized,
     {
-        use std::ops::Bound;
+        use core::ops::Bound;
 
         let begin = match range.start_bound() {
             Bound::Included(&n) => n,
@@ -169,6 +190,64 @@ unsafe impl IoBuf for &'static str {
     }
 }
 
+unsafe impl IoBuf for Rc<Vec<u8>> {
+    fn as_buf_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn buf_len(&self) -> usize {
+        self.len()
+    }
+
+    fn buf_capacity(&self) -> usize {
+        self.len()
+    }
+}
+
+unsafe impl IoBuf for Rc<[u8]> {
+    fn as_buf_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn buf_len(&self) -> usize {
+        self.len()
+    }
+
+    fn buf_capacity(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+unsafe impl IoBuf for Arc<Vec<u8>> {
+    fn as_buf_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn buf_len(&self) -> usize {
+        self.len()
+    }
+
+    fn buf_capacity(&self) -> usize {
+        self.len()
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+unsafe impl IoBuf for Arc<[u8]> {
+    fn as_buf_ptr(&self) -> *const u8 {
+        self.as_ptr()
+    }
+
+    fn buf_len(&self) -> usize {
+        self.len()
+    }
+
+    fn buf_capacity(&self) -> usize {
+        self.len()
+    }
+}
+
 #[cfg(feature = "bytes")]
 unsafe impl IoBuf for bytes::Bytes {
     fn as_buf_ptr(&self) -> *const u8 {
@@ -252,18 +331,35 @@ pub unsafe trait IoBufMut: IoBuf {
     /// Get the uninitialized part of the buffer.
     fn as_uninit_slice(&mut self) -> &mut [MaybeUninit<u8>] {
         unsafe {
-            std::slice::from_raw_parts_mut(
+            core::slice::from_raw_parts_mut(
                 self.as_buf_mut_ptr().add(self.buf_len()) as _,
                 self.buf_capacity() - self.buf_len(),
             )
         }
     }
 
+    /// Get the uninitialized part of the buffer as an [`IoSliceMut`].
+    fn as_uninit_io_slice(&mut self) -> IoSliceMut {
+        let uninit = self.as_uninit_slice();
+        IoSliceMut::new(uninit.as_mut_ptr() as _, uninit.len())
+    }
+
+    /// Get the initialized part of the buffer, mutably.
+    fn filled(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.as_buf_mut_ptr(), self.buf_len()) }
+    }
+
     /// Updates the number of initialized bytes.
     ///
     /// The specified `len` plus [`IoBuf::buf_len`] becomes the new value
     /// returned by [`IoBuf::buf_len`].
     ///
+    /// The runtime calls it once after an op completes, with the count of the
+    /// bytes written into [`IoBufMut::as_uninit_slice`], which follow the
+    /// initialized bytes. Therefore, [`IoBuf::buf_len`] never decreases, and
+    /// each byte is reported as initialized only once. It is not called if the
+    /// op fails.
+    ///
     /// # Safety
     ///
     /// `len` should be less or equal than `buf_capacity() - buf_len()`.
@@ -0,0 +1,132 @@
+/// A raw immutable slice given to the vectored IO.
+///
+/// It has the same layout as `iovec` on unix and `WSABUF` on Windows, like
+/// [`std::io::IoSlice`], but holds no lifetime, so that an op can keep it
+/// together with the buffer it points to.
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+pub struct IoSlice(sys::RawSlice);
+
+impl IoSlice {
+    /// Create a slice of `len` bytes at `ptr`.
+    pub fn new(ptr: *const u8, len: usize) -> Self {
+        Self(sys::RawSlice::new(ptr as _, len))
+    }
+
+    /// The pointer to the bytes.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.0.ptr()
+    }
+
+    /// The count of the bytes.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// If the slice is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Convert to the slice of `std`.
+    ///
+    /// # Safety
+    ///
+    /// The bytes should be valid while the returned slice is used.
+    #[cfg(feature = "std")]
+    pub unsafe fn into_std(self) -> std::io::IoSlice<'static> {
+        std::io::IoSlice::new(core::slice::from_raw_parts(self.as_ptr(), self.len()))
+    }
+}
+
+/// A raw mutable slice given to the vectored IO. See [`IoSlice`].
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+pub struct IoSliceMut(sys::RawSlice);
+
+impl IoSliceMut {
+    /// Create a slice of `len` bytes at `ptr`.
+    pub fn new(ptr: *mut u8, len: usize) -> Self {
+        Self(sys::RawSlice::new(ptr, len))
+    }
+
+    /// The pointer to the bytes.
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.0.ptr()
+    }
+
+    /// The count of the bytes.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// If the slice is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Convert to the slice of `std`.
+    ///
+    /// # Safety
+    ///
+    /// The bytes should be valid and not aliased while the returned slice is
+    /// used.
+    #[cfg(feature = "std")]
+    pub unsafe fn into_std(self) -> std::io::IoSliceMut<'static> {
+        std::io::IoSliceMut::new(core::slice::from_raw_parts_mut(self.as_ptr(), self.len()))
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    // The layout of `WSABUF`.
+    #[derive(Debug, Clone, Copy)]
+    #[repr(C)]
+    pub struct RawSlice {
+        len: u32,
+        ptr: *mut u8,
+    }
+
+    impl RawSlice {
+        pub fn new(ptr: *mut u8, len: usize) -> Self {
+            Self {
+                len: len.try_into().expect("the slice is too long"),
+                ptr,
+            }
+        }
+
+        pub fn ptr(&self) -> *mut u8 {
+            self.ptr
+        }
+
+        pub fn len(&self) -> usize {
+            self.len as usize
+        }
+    }
+}
+
+// The other targets, including the bare metal ones, follow `iovec`.
+#[cfg(not(windows))]
+mod sys {
+    // The layout of `iovec`.
+    #[derive(Debug, Clone, Copy)]
+    #[repr(C)]
+    pub struct RawSlice {
+        ptr: *mut u8,
+        len: usize,
+    }
+
+    impl RawSlice {
+        pub fn new(ptr: *mut u8, len: usize) -> Self {
+            Self { ptr, len }
+        }
+
+        pub fn ptr(&self) -> *mut u8 {
+            self.ptr
+        }
+
+        pub fn len(&self) -> usize {
+            self.len
+        }
+    }
+}
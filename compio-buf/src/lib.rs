@@ -0,0 +1,69 @@
+//! Buffer traits for completion based async IO.
+//!
+//! IOCP and io-uring APIs require passing ownership of buffers to the
+//! runtime. The crate defines [`IoBuf`] and [`IoBufMut`] traits which are
+//! implemented by buffer types that respect the contract.
+//!
+//! The crate is `no_std` with `alloc`. The `std` feature, enabled by default,
+//! adds [`BufResult`] and the conversions to the [`std::io`] slices.
+
+#![no_std]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
+#![cfg_attr(feature = "read_buf", feature(read_buf))]
+#![warn(missing_docs)]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+mod io_buf;
+pub use io_buf::*;
+
+mod io_slice;
+pub use io_slice::*;
+
+mod slice;
+pub use slice::*;
+
+/// A specialized `Result` type for operations with buffers.
+///
+/// This type is used as a return value for asynchronous IOCP methods that
+/// require passing ownership of a buffer to the runtime. When the operation
+/// completes, the buffer is returned whether or not the operation completed
+/// successfully.
+#[cfg(feature = "std")]
+pub type BufResult<T, B> = (std::io::Result<T>, B);
+
+/// Trait to get the inner buffer of an operation or a result.
+pub trait IntoInner {
+    /// The inner type.
+    type Inner;
+
+    /// Get the inner buffer.
+    fn into_inner(self) -> Self::Inner;
+}
+
+#[cfg(feature = "std")]
+impl<T: IntoInner, O> IntoInner for BufResult<O, T> {
+    type Inner = BufResult<O, T::Inner>;
+
+    fn into_inner(self) -> Self::Inner {
+        (self.0, self.1.into_inner())
+    }
+}
+
+#[cfg(not(feature = "allocator_api"))]
+macro_rules! vec_alloc {
+    ($t:ident, $a:ident) => {
+        alloc::vec::Vec<$t>
+    };
+}
+
+#[cfg(feature = "allocator_api")]
+macro_rules! vec_alloc {
+    ($t:ident, $a:ident) => {
+        alloc::vec::Vec<$t, $a>
+    };
+}
+
+pub(crate) use vec_alloc;
@@ -1,6 +1,6 @@
-use std::ops::{Deref, DerefMut};
+use core::ops::{Deref, DerefMut};
 
-use crate::buf::*;
+use crate::*;
 
 /// An owned view into a contiguous sequence of bytes.
 ///
@@ -8,20 +8,23 @@ use crate::buf::*;
 /// This type is useful for performing io-uring read and write operations using
 /// a subset of a buffer.
 ///
-/// Slices are created using [`IoBuf::slice`].
+/// Slices are created using [`IoBuf::slice`]. A slice of a shared buffer, e.g.,
+/// `Rc<Vec<u8>>`, is cloned without copying the bytes, so the same part could
+/// be written to many streams.
 ///
 /// # Examples
 ///
 /// Creating a slice
 ///
 /// ```
-/// use compio::buf::IoBuf;
+/// use compio_buf::IoBuf;
 ///
 /// let buf = b"hello world";
 /// let slice = buf.slice(..5);
 ///
 /// assert_eq!(&slice[..], b"hello");
 /// ```
+#[derive(Clone)]
 pub struct Slice<T> {
     buffer: T,
     begin: usize,
@@ -59,11 +62,11 @@ impl<T> Slice<T> {
 }
 
 fn deref<T: IoBuf>(buffer: &T) -> &[u8] {
-    unsafe { std::slice::from_raw_parts(buffer.as_buf_ptr(), buffer.buf_len()) }
+    unsafe { core::slice::from_raw_parts(buffer.as_buf_ptr(), buffer.buf_len()) }
 }
 
 fn deref_mut<T: IoBufMut>(buffer: &mut T) -> &mut [u8] {
-    unsafe { std::slice::from_raw_parts_mut(buffer.as_buf_mut_ptr(), buffer.buf_len()) }
+    unsafe { core::slice::from_raw_parts_mut(buffer.as_buf_mut_ptr(), buffer.buf_len()) }
 }
 
 impl<T: IoBuf> Deref for Slice<T> {
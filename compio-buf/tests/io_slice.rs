@@ -0,0 +1,40 @@
+use std::mem::{align_of, size_of};
+
+use compio_buf::{IoBuf, IoBufMut, IoSlice, IoSliceMut};
+
+#[test]
+fn layout() {
+    assert_eq!(size_of::<IoSlice>(), size_of::<std::io::IoSlice>());
+    assert_eq!(align_of::<IoSlice>(), align_of::<std::io::IoSlice>());
+    assert_eq!(size_of::<IoSliceMut>(), size_of::<std::io::IoSliceMut>());
+    assert_eq!(align_of::<IoSliceMut>(), align_of::<std::io::IoSliceMut>());
+
+    // The slices of `std` could be given to the syscalls as the raw ones.
+    let bytes = b"hello world";
+    let slice = IoSlice::new(bytes.as_ptr(), bytes.len());
+    let std_slice = std::io::IoSlice::new(bytes);
+    let raw = unsafe { std::mem::transmute::<std::io::IoSlice, IoSlice>(std_slice) };
+    assert_eq!(raw.as_ptr(), slice.as_ptr());
+    assert_eq!(raw.len(), slice.len());
+}
+
+#[test]
+fn buffers() {
+    let buffer = b"hello world".to_vec();
+    let slice = buffer.as_io_slice();
+    assert_eq!(slice.len(), 11);
+    assert_eq!(&*unsafe { slice.into_std() }, b"hello world");
+
+    let mut buffer = Vec::with_capacity(16);
+    buffer.extend_from_slice(b"hello");
+    let slice = buffer.as_uninit_io_slice();
+    assert_eq!(slice.len(), buffer.capacity() - 5);
+    let mut uninit = unsafe { slice.into_std() };
+    uninit[..6].copy_from_slice(b" world");
+    unsafe { buffer.set_buf_init(6) };
+    assert_eq!(buffer, b"hello world");
+
+    let slice = b"hello world".slice(6..).as_io_slice();
+    assert_eq!(&*unsafe { slice.into_std() }, b"world");
+    assert!(IoSlice::new(std::ptr::null(), 0).is_empty());
+}
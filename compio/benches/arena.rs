@@ -0,0 +1,165 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    net::Ipv4Addr,
+    ops::Deref,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use compio::{
+    buf::{
+        arena::{ArenaBuf, BufArena},
+        pool::{self, PooledBuf},
+        IntoInner, IoBufMut,
+    },
+    net::{TcpListener, TcpStream},
+};
+use criterion::{
+    async_executor::AsyncExecutor, criterion_group, criterion_main, measurement::WallTime,
+    BenchmarkGroup, Criterion,
+};
+
+criterion_group!(arena, request);
+criterion_main!(arena);
+
+// Count the allocations to compare the buffer strategies.
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+// The allocations to acquire the buffers of the requests.
+static BUFFER_ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+struct CompioRuntime;
+
+impl AsyncExecutor for CompioRuntime {
+    fn block_on<T>(&self, future: impl std::future::Future<Output = T>) -> T {
+        compio::task::block_on(future)
+    }
+}
+
+const HEADER_LEN: usize = 16;
+const BODY_LEN: usize = 64;
+const REQUESTS: usize = 1000;
+
+// The buffers of the requests.
+trait RequestBuf: IoBufMut + Deref<Target = [u8]> {
+    fn extend(&mut self, data: &[u8]);
+}
+
+impl RequestBuf for Vec<u8> {
+    fn extend(&mut self, data: &[u8]) {
+        self.extend_from_slice(data);
+    }
+}
+
+impl RequestBuf for PooledBuf {
+    fn extend(&mut self, data: &[u8]) {
+        self.extend_from_slice(data);
+    }
+}
+
+impl RequestBuf for ArenaBuf {
+    fn extend(&mut self, data: &[u8]) {
+        self.extend_from_slice(data);
+    }
+}
+
+// Connect to an echo server, which handles each request with 4 buffers: the
+// header, the body, the header scratch and the response.
+fn echo_pair<B: RequestBuf>(new_buffer: impl Fn(usize) -> B + 'static) -> TcpStream {
+    compio::task::block_on(async move {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, (server, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+        let new_buffer = move |len| {
+            let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+            let buffer = new_buffer(len);
+            let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+            BUFFER_ALLOCATIONS.fetch_add(allocations, Ordering::Relaxed);
+            buffer
+        };
+        compio::task::spawn(async move {
+            loop {
+                // The pooled buffers may be larger.
+                let header = new_buffer(HEADER_LEN).slice(..HEADER_LEN);
+                let (res, header) = server.recv_exact(header).await;
+                if res.is_err() {
+                    break;
+                }
+                let body = new_buffer(BODY_LEN).slice(..BODY_LEN);
+                let (res, body) = server.recv_exact(body).await;
+                res.unwrap();
+                let (header, body) = (header.into_inner(), body.into_inner());
+                let mut scratch = new_buffer(HEADER_LEN);
+                scratch.extend(&header);
+                let mut response = new_buffer(HEADER_LEN + BODY_LEN);
+                response.extend(&scratch);
+                response.extend(&body);
+                server.send_all(response).await.0.unwrap();
+            }
+        })
+        .detach();
+        client
+    })
+}
+
+async fn requests(client: &TcpStream) {
+    static REQUEST: &[u8] = &[1u8; HEADER_LEN + BODY_LEN];
+
+    let mut buffer = Vec::with_capacity(REQUEST.len());
+    for _ in 0..REQUESTS {
+        client.send_all(REQUEST).await.0.unwrap();
+        buffer.clear();
+        let res;
+        (res, buffer) = client.recv_exact(buffer).await;
+        res.unwrap();
+        assert_eq!(buffer.as_slice(), REQUEST);
+    }
+}
+
+fn request(c: &mut Criterion) {
+    let mut group = c.benchmark_group("request");
+
+    fn bench<B: RequestBuf>(
+        group: &mut BenchmarkGroup<WallTime>,
+        name: &str,
+        new_buffer: impl Fn(usize) -> B + 'static,
+    ) {
+        let client = echo_pair(new_buffer);
+        group.bench_function(name, |b| {
+            b.to_async(CompioRuntime).iter(|| requests(&client))
+        });
+        // Counted out of the measurement, which allocates for the analysis.
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let buffer_allocations = BUFFER_ALLOCATIONS.load(Ordering::Relaxed);
+        compio::task::block_on(requests(&client));
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+        let buffer_allocations = BUFFER_ALLOCATIONS.load(Ordering::Relaxed) - buffer_allocations;
+        println!(
+            "{name}: {:.2} allocations per request, {:.2} for the buffers",
+            allocations as f64 / REQUESTS as f64,
+            buffer_allocations as f64 / REQUESTS as f64,
+        );
+    }
+
+    bench(&mut group, "vec", Vec::with_capacity);
+    bench(&mut group, "pool", pool::acquire);
+    let arena = BufArena::with_capacity(1024);
+    bench(&mut group, "arena", move |len| arena.alloc(len));
+
+    group.finish();
+}
@@ -0,0 +1,75 @@
+use std::{rc::Rc, sync::Arc, time::Instant};
+
+use compio::{
+    buf::IoBuf,
+    net::{TcpListener, TcpStream},
+};
+use criterion::{Criterion, criterion_group, criterion_main};
+use futures_util::future::join_all;
+
+criterion_group!(broadcast, shared);
+criterion_main!(broadcast);
+
+const SUBSCRIBERS: usize = 256;
+const MESSAGE_LEN: usize = 1024;
+
+async fn subscribers() -> Vec<(TcpStream, TcpStream)> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut pairs = Vec::with_capacity(SUBSCRIBERS);
+    for _ in 0..SUBSCRIBERS {
+        let (tx, (rx, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+        pairs.push((tx, rx));
+    }
+    pairs
+}
+
+// Write the same message to all subscribers, and receive it from them.
+async fn publish<B: IoBuf + Clone>(message: &B, pairs: &[(TcpStream, TcpStream)]) {
+    join_all(pairs.iter().map(|(tx, _)| {
+        let message = message.clone();
+        async move { tx.send_all(message).await.0.unwrap() }
+    }))
+    .await;
+    join_all(pairs.iter().map(|(_, rx)| async move {
+        let (res, _) = rx.recv_exact(Vec::with_capacity(MESSAGE_LEN)).await;
+        res.unwrap()
+    }))
+    .await;
+}
+
+fn bench<B: IoBuf + Clone>(
+    c: &mut Criterion,
+    name: &str,
+    pairs: &[(TcpStream, TcpStream)],
+    message: impl Fn() -> B,
+) {
+    c.bench_function(name, |b| {
+        b.iter_custom(|iters| {
+            compio::task::block_on(async {
+                let message = message();
+                let start = Instant::now();
+                for _ in 0..iters {
+                    publish(&message, pairs).await;
+                }
+                start.elapsed()
+            })
+        })
+    });
+}
+
+// The reference counts of `Rc` are not atomic, which is enough as the ops
+// complete on the thread of the runtime.
+fn shared(c: &mut Criterion) {
+    let pairs = compio::task::block_on(subscribers());
+    bench(c, "broadcast_rc", &pairs, || {
+        Rc::new(vec![1u8; MESSAGE_LEN])
+    });
+    bench(c, "broadcast_arc", &pairs, || {
+        Arc::new(vec![1u8; MESSAGE_LEN])
+    });
+    bench(c, "broadcast_rc_slice", &pairs, || {
+        Rc::<[u8]>::from(vec![1u8; MESSAGE_LEN])
+    });
+}
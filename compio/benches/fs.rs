@@ -1,7 +1,7 @@
 use criterion::{async_executor::AsyncExecutor, criterion_group, criterion_main, Criterion};
 use tempfile::NamedTempFile;
 
-criterion_group!(fs, read, write);
+criterion_group!(fs, read, write, read_ranges);
 criterion_main!(fs);
 
 struct CompioRuntime;
@@ -94,3 +94,48 @@ fn write(c: &mut Criterion) {
 
     group.finish()
 }
+
+fn read_ranges(c: &mut Criterion) {
+    const FILE_SIZE: usize = 64 * 1024 * 1024;
+    const RANGE_SIZE: u64 = 16 * 1024;
+    const RANGES: u64 = 1000;
+
+    let mut group = c.benchmark_group("read_ranges");
+
+    let mut temp_file = NamedTempFile::new().unwrap();
+    {
+        use std::io::Write;
+
+        temp_file.write_all(&vec![1u8; FILE_SIZE]).unwrap();
+    }
+    // Scattered with a fixed stride, which is coprime to the count of the slots.
+    let slots = FILE_SIZE as u64 / RANGE_SIZE;
+    let ranges = (0..RANGES)
+        .map(|i| {
+            let start = (i * 2039 % slots) * RANGE_SIZE;
+            start..start + RANGE_SIZE
+        })
+        .collect::<Vec<_>>();
+
+    group.bench_function("sequential", |b| {
+        let file = compio::fs::File::open(temp_file.path()).unwrap();
+        b.to_async(CompioRuntime).iter(|| async {
+            let mut buffers = Vec::with_capacity(ranges.len());
+            for range in &ranges {
+                let buffer = Vec::with_capacity(RANGE_SIZE as usize);
+                let (res, buffer) = file.read_exact_at(buffer, range.start).await;
+                res.unwrap();
+                buffers.push(buffer);
+            }
+            buffers
+        })
+    });
+
+    group.bench_function("read_ranges", |b| {
+        let file = compio::fs::File::open(temp_file.path()).unwrap();
+        b.to_async(CompioRuntime)
+            .iter(|| async { file.read_ranges(ranges.clone()).await })
+    });
+
+    group.finish()
+}
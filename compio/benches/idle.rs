@@ -0,0 +1,108 @@
+use std::{
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use compio::net::{TcpListener, TcpStream};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+criterion_group!(idle, idle_connections);
+criterion_main!(idle);
+
+const CONNECTIONS: usize = 10_000;
+const PACKET_LEN: usize = 64;
+
+// Raise the limit of fds to the hard one, and return the count of the
+// connections fitting in it. Both ends of a connection are in this process.
+#[cfg(unix)]
+fn connections() -> usize {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    unsafe {
+        libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit);
+        limit.rlim_cur = limit.rlim_max;
+        libc::setrlimit(libc::RLIMIT_NOFILE, &limit);
+    }
+    let fit = (limit.rlim_cur as usize).saturating_sub(64) / 2;
+    if fit < CONNECTIONS {
+        eprintln!("The fd limit only fits {fit} connections.");
+    }
+    CONNECTIONS.min(fit)
+}
+
+#[cfg(not(unix))]
+fn connections() -> usize {
+    CONNECTIONS
+}
+
+// Echo on each server side. The idle ones always wait in a recv.
+fn serve(server: Rc<TcpStream>) {
+    compio::task::spawn(async move {
+        let mut buffer = Vec::with_capacity(PACKET_LEN);
+        loop {
+            let res;
+            (res, buffer) = server.recv(buffer).await;
+            if res.unwrap() == 0 {
+                break;
+            }
+            let res;
+            (res, buffer) = server.send_all(buffer).await;
+            res.unwrap();
+            buffer.clear();
+        }
+    })
+    .detach();
+}
+
+// 1% of the connections are active, and the others are idle.
+fn idle_connections(c: &mut Criterion) {
+    let connections = connections();
+    let (clients, servers) = compio::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut clients = Vec::with_capacity(connections);
+        let mut servers = Vec::with_capacity(connections);
+        for _ in 0..connections {
+            let (client, (server, _)) =
+                futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+            let server = Rc::new(server);
+            serve(server.clone());
+            clients.push(client);
+            servers.push(server);
+        }
+        (clients, servers)
+    });
+    let active = &clients[..(connections / 100).max(1)];
+
+    let mut group = c.benchmark_group("idle_connections");
+    group.measurement_time(Duration::from_secs(10));
+    for poll_first in [false, true] {
+        // The in-flight recvs are not affected, but the next ones are.
+        for server in &servers {
+            server.set_recv_poll_first(poll_first);
+        }
+        let name = if poll_first { "poll_first" } else { "default" };
+        group.bench_function(name, |b| {
+            b.iter_custom(|iters| {
+                compio::task::block_on(async {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        futures_util::future::join_all(active.iter().map(echo)).await;
+                    }
+                    start.elapsed()
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+async fn echo(client: &TcpStream) {
+    let (res, mut packet) = client.send_all(vec![1u8; PACKET_LEN]).await;
+    res.unwrap();
+    packet.clear();
+    let (res, _) = client.recv_exact(packet).await;
+    res.unwrap();
+}
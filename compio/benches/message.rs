@@ -0,0 +1,60 @@
+use std::time::Instant;
+
+use compio::task::{messages, RuntimeHandle};
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures_util::StreamExt;
+
+criterion_group!(message, ping_pong);
+criterion_main!(message);
+
+#[cfg(target_os = "linux")]
+fn pin_to(core: usize) {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        // It's OK to fail to pin, e.g., with only one core.
+        libc::sched_setaffinity(0, std::mem::size_of_val(&set), &set);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to(_core: usize) {}
+
+fn ping_pong(c: &mut Criterion) {
+    const STOP: u64 = u64::MAX;
+
+    pin_to(0);
+    let pong = RuntimeHandle::current().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let thread = std::thread::spawn(move || {
+        pin_to(1);
+        compio::task::block_on(async {
+            tx.send(RuntimeHandle::current().unwrap()).unwrap();
+            let mut messages = messages();
+            while let Some(msg) = messages.next().await {
+                if msg == STOP {
+                    break;
+                }
+                pong.send_msg(msg).await.unwrap();
+            }
+        })
+    });
+    let ping = rx.recv().unwrap();
+
+    c.bench_function("message_ping_pong", |b| {
+        b.iter_custom(|iters| {
+            compio::task::block_on(async {
+                let mut messages = messages();
+                let start = Instant::now();
+                for i in 0..iters {
+                    ping.send_msg(i).await.unwrap();
+                    messages.next().await.unwrap();
+                }
+                start.elapsed()
+            })
+        })
+    });
+
+    compio::task::block_on(ping.send_msg(STOP)).unwrap();
+    thread.join().unwrap();
+}
@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use compio::net::{TcpListener, TcpStream};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+criterion_group!(napi, ping_pong);
+criterion_main!(napi);
+
+// The results depend on the machine and the network devices, so the benchmark
+// only runs if the variable is set to the busy polling timeout in
+// microseconds, e.g., `COMPIO_BENCH_NAPI=50 cargo bench --bench napi`. Only
+// io-uring supports NAPI busy polling.
+const ENV: &str = "COMPIO_BENCH_NAPI";
+
+fn ping_pong(c: &mut Criterion) {
+    const PACKET_LEN: usize = 64;
+
+    let Some(timeout) = std::env::var(ENV).ok().and_then(|v| v.parse().ok()) else {
+        eprintln!("Set {ENV} to the busy polling timeout in microseconds to run the benchmark.");
+        return;
+    };
+    let timeout = Duration::from_micros(timeout);
+
+    let (tx, rx) = compio::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, (rx, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+        (tx, rx)
+    });
+
+    let mut group = c.benchmark_group("napi_ping_pong");
+    for napi in [false, true] {
+        let name = if napi { "enabled" } else { "disabled" };
+        compio::task::set_napi_busy_poll(napi.then_some(timeout), false).unwrap();
+        group.bench_function(name, |b| {
+            b.iter_custom(|iters| {
+                compio::task::block_on(async {
+                    let mut packet = vec![1u8; PACKET_LEN];
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        packet = transfer(&tx, &rx, packet).await;
+                        packet = transfer(&rx, &tx, packet).await;
+                    }
+                    start.elapsed()
+                })
+            })
+        });
+    }
+    group.finish();
+    compio::task::set_napi_busy_poll(None, false).unwrap();
+}
+
+async fn transfer(tx: &TcpStream, rx: &TcpStream, packet: Vec<u8>) -> Vec<u8> {
+    let (res, mut packet) = tx.send_all(packet).await;
+    res.unwrap();
+    packet.clear();
+    let (res, packet) = rx.recv_exact(packet).await;
+    res.unwrap();
+    packet
+}
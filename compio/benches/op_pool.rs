@@ -0,0 +1,116 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    net::Ipv4Addr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use compio::{
+    driver::ProactorBuilder,
+    net::{TcpListener, TcpStream},
+};
+use criterion::{Criterion, async_executor::AsyncExecutor, criterion_group, criterion_main};
+
+criterion_group!(op_pool, send_recv);
+criterion_main!(op_pool);
+
+// Count the allocations to compare with and without caching the ops.
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+struct CompioRuntime;
+
+impl AsyncExecutor for CompioRuntime {
+    fn block_on<T>(&self, future: impl std::future::Future<Output = T>) -> T {
+        compio::task::block_on(future)
+    }
+}
+
+const MESSAGE_LEN: usize = 16;
+const MESSAGES: usize = 1000;
+
+// Connect to an echo server, which reuses its buffer.
+fn echo_pair() -> TcpStream {
+    compio::task::block_on(async move {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, (server, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+        compio::task::spawn(async move {
+            let mut buffer = Vec::with_capacity(MESSAGE_LEN);
+            loop {
+                buffer.clear();
+                let res;
+                (res, buffer) = server.recv(buffer).await;
+                if res.unwrap() == 0 {
+                    break;
+                }
+                let res;
+                (res, buffer) = server.send_all(buffer).await;
+                res.unwrap();
+            }
+        })
+        .detach();
+        client
+    })
+}
+
+async fn ping_pong(client: &TcpStream) {
+    static MESSAGE: &[u8] = &[1u8; MESSAGE_LEN];
+
+    let mut buffer = Vec::with_capacity(MESSAGE_LEN);
+    for _ in 0..MESSAGES {
+        client.send_all(MESSAGE).await.0.unwrap();
+        buffer.clear();
+        let res;
+        (res, buffer) = client.recv_exact(buffer).await;
+        res.unwrap();
+    }
+}
+
+// The allocations of each op after warming up, i.e., of the sends and the
+// receives on both sides.
+fn allocations_per_op(client: &TcpStream) -> f64 {
+    compio::task::block_on(ping_pong(client));
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    compio::task::block_on(ping_pong(client));
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    allocations as f64 / (MESSAGES * 4) as f64
+}
+
+fn send_recv(c: &mut Criterion) {
+    let client = echo_pair();
+    c.bench_function("send_recv", |b| {
+        b.to_async(CompioRuntime).iter(|| ping_pong(&client))
+    });
+    // Counted out of the measurement, which allocates for the analysis.
+    println!(
+        "cached: {:.2} allocations per op, hit rate {:.2}",
+        allocations_per_op(&client),
+        compio::task::op_pool_stats().hit_rate()
+    );
+    drop(client);
+
+    // The runtime is thread local, so build another one without caching.
+    let allocations = std::thread::spawn(|| {
+        compio::task::init_with(&ProactorBuilder::new().op_pool_capacity(0)).unwrap();
+        allocations_per_op(&echo_pair())
+    })
+    .join()
+    .unwrap();
+    println!("not cached: {allocations:.2} allocations per op");
+}
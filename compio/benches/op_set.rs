@@ -0,0 +1,46 @@
+use compio::{driver::AsRawFd, fs::File, op::ReadAt, task::submit_set};
+use criterion::{async_executor::AsyncExecutor, criterion_group, criterion_main, Criterion};
+use futures_util::future::join_all;
+
+criterion_group!(op_set, concurrent_reads);
+criterion_main!(op_set);
+
+struct CompioRuntime;
+
+impl AsyncExecutor for CompioRuntime {
+    fn block_on<T>(&self, future: impl std::future::Future<Output = T>) -> T {
+        compio::task::block_on(future)
+    }
+}
+
+const READS: usize = 10000;
+
+fn concurrent_reads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("concurrent_reads");
+
+    group.bench_function("join_all", |b| {
+        let file = File::open("Cargo.toml").unwrap();
+        b.to_async(CompioRuntime).iter(|| async {
+            join_all((0..READS).map(|i| file.read_at(Vec::with_capacity(16), (i % 1024) as u64)))
+                .await
+        })
+    });
+
+    group.bench_function("submit_set", |b| {
+        let file = File::open("Cargo.toml").unwrap();
+        compio::task::block_on(async { compio::task::attach(file.as_raw_fd()) }).unwrap();
+        b.to_async(CompioRuntime).iter(|| async {
+            let mut set =
+                submit_set((0..READS).map(|i| {
+                    ReadAt::new(file.as_raw_fd(), (i % 1024) as u64, Vec::with_capacity(16))
+                }));
+            let mut results = Vec::with_capacity(READS);
+            while let Some(res) = set.next().await {
+                results.push(res);
+            }
+            results
+        })
+    });
+
+    group.finish();
+}
@@ -0,0 +1,72 @@
+use std::net::Ipv4Addr;
+
+use compio::{
+    buf::{pool, IoBufMut},
+    net::{TcpListener, TcpStream},
+};
+use criterion::{async_executor::AsyncExecutor, criterion_group, criterion_main, Criterion};
+
+criterion_group!(pool, echo);
+criterion_main!(pool);
+
+struct CompioRuntime;
+
+impl AsyncExecutor for CompioRuntime {
+    fn block_on<T>(&self, future: impl std::future::Future<Output = T>) -> T {
+        compio::task::block_on(future)
+    }
+}
+
+const MESSAGE_LEN: usize = 64;
+const MESSAGES: usize = 1000;
+
+// Connect to an echo server, which receives each message into a new buffer.
+fn echo_pair<B: IoBufMut>(new_buffer: impl Fn() -> B + 'static) -> TcpStream {
+    compio::task::block_on(async move {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, (server, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+        compio::task::spawn(async move {
+            loop {
+                let (res, buffer) = server.recv(new_buffer()).await;
+                if res.unwrap() == 0 {
+                    break;
+                }
+                server.send_all(buffer).await.0.unwrap();
+            }
+        })
+        .detach();
+        client
+    })
+}
+
+async fn ping_pong(client: &TcpStream) {
+    static MESSAGE: &[u8] = &[1u8; MESSAGE_LEN];
+
+    let mut buffer = Vec::with_capacity(MESSAGE_LEN);
+    for _ in 0..MESSAGES {
+        client.send_all(MESSAGE).await.0.unwrap();
+        buffer.clear();
+        let res;
+        (res, buffer) = client.recv_exact(buffer).await;
+        res.unwrap();
+        assert_eq!(buffer.as_slice(), MESSAGE);
+    }
+}
+
+fn echo(c: &mut Criterion) {
+    let mut group = c.benchmark_group("echo");
+
+    group.bench_function("vec", |b| {
+        let client = echo_pair(|| Vec::with_capacity(MESSAGE_LEN));
+        b.to_async(CompioRuntime).iter(|| ping_pong(&client));
+    });
+
+    group.bench_function("pool", |b| {
+        let client = echo_pair(|| pool::acquire(MESSAGE_LEN));
+        b.to_async(CompioRuntime).iter(|| ping_pong(&client));
+    });
+
+    group.finish();
+}
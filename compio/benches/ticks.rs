@@ -0,0 +1,134 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use compio::{driver::ProactorBuilder, fs::File};
+use criterion::{Criterion, async_executor::AsyncExecutor, criterion_group, criterion_main};
+
+criterion_group!(ticks, wide, idle);
+criterion_main!(ticks);
+
+// Count the allocations of the ticks of the runtime.
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+struct CompioRuntime;
+
+impl AsyncExecutor for CompioRuntime {
+    fn block_on<T>(&self, future: impl std::future::Future<Output = T>) -> T {
+        compio::task::block_on(future)
+    }
+}
+
+const TICKS: usize = 1000;
+
+// Each yield is a tick without completions.
+async fn yield_ticks() {
+    for _ in 0..TICKS {
+        compio::task::yield_now().await;
+    }
+}
+
+// More completions in a tick than the default capacity.
+const READS: usize = 4096;
+
+// The rounds of the reads, driven by the yields of the runner.
+#[derive(Default)]
+struct Rounds {
+    round: Cell<usize>,
+    done: Cell<usize>,
+    stopped: Cell<bool>,
+}
+
+// Spawn the readers, which read concurrently in each round, so that all the
+// reads complete in a tick.
+fn spawn_readers(file: File) -> Rc<Rounds> {
+    let file = Rc::new(file);
+    let rounds = Rc::new(Rounds::default());
+    for _ in 0..READS {
+        let file = file.clone();
+        let rounds = rounds.clone();
+        compio::task::spawn(async move {
+            let mut buffer = Vec::with_capacity(16);
+            let mut seen = 0;
+            loop {
+                while rounds.round.get() == seen {
+                    if rounds.stopped.get() {
+                        return;
+                    }
+                    compio::task::yield_now().await;
+                }
+                seen = rounds.round.get();
+                let res;
+                (res, buffer) = file.read_at(buffer, 0).await;
+                res.unwrap();
+                rounds.done.set(rounds.done.get() + 1);
+            }
+        })
+        .detach();
+    }
+    rounds
+}
+
+async fn wide_round(rounds: &Rounds) {
+    rounds.done.set(0);
+    rounds.round.set(rounds.round.get() + 1);
+    while rounds.done.get() < READS {
+        compio::task::yield_now().await;
+    }
+}
+
+// Counted out of the measurement, which allocates for the analysis.
+fn allocations(rounds: usize, mut f: impl FnMut()) -> f64 {
+    f();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    (ALLOCATIONS.load(Ordering::Relaxed) - allocations) as f64 / rounds as f64
+}
+
+fn wide(c: &mut Criterion) {
+    // Complete all the reads in a tick. It runs first to initialize the
+    // runtime of the thread.
+    let builder = ProactorBuilder::new()
+        .capacity(READS as _)
+        .op_pool_capacity(READS);
+    compio::task::init_with(&builder).unwrap();
+    let rounds = spawn_readers(File::open("Cargo.toml").unwrap());
+    c.bench_function("wide", |b| {
+        b.to_async(CompioRuntime).iter(|| wide_round(&rounds))
+    });
+    // Including the task spawned by `block_on`.
+    println!(
+        "wide: {:.2} allocations per round of {READS} reads",
+        allocations(1, || compio::task::block_on(wide_round(&rounds)))
+    );
+    // Let the readers exit, so that they don't yield in the other benches.
+    rounds.stopped.set(true);
+    compio::task::block_on(compio::task::yield_now());
+}
+
+fn idle(c: &mut Criterion) {
+    c.bench_function("idle", |b| b.to_async(CompioRuntime).iter(yield_ticks));
+    println!(
+        "idle: {:.2} allocations per tick",
+        allocations(TICKS, || compio::task::block_on(yield_ticks()))
+    );
+}
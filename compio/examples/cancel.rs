@@ -0,0 +1,66 @@
+use std::{
+    net::Ipv4Addr,
+    time::{Duration, Instant},
+};
+
+use compio::{
+    net::{TcpListener, TcpStream},
+    sync::{CancellationExt, CancellationToken},
+};
+
+const CONNECTIONS: usize = 8;
+
+// Each connection echoes until the peer closes, or the server is shut down.
+async fn serve(stream: TcpStream, token: CancellationToken, id: usize) {
+    let mut buffer = Vec::with_capacity(1024);
+    loop {
+        let res;
+        (res, buffer) = stream.recv(buffer).with_cancellation(&token).await;
+        match res {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(_) if token.is_cancelled() => {
+                println!("connection {id} stopped, buffer {} bytes", buffer.capacity());
+                break;
+            }
+            Err(e) => panic!("{e}"),
+        }
+        let res;
+        (res, buffer) = stream.send_all(buffer).await;
+        res.unwrap();
+        buffer.clear();
+    }
+}
+
+fn main() {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = CancellationToken::new();
+
+        // The clients connect, and never send anything.
+        let mut clients = vec![];
+        let mut tasks = vec![];
+        for id in 0..CONNECTIONS {
+            let (client, (server, _)) =
+                futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+            clients.push(client);
+            tasks.push(compio::task::spawn(serve(server, token.child_token(), id)));
+        }
+
+        // Shut down the server from another thread.
+        let shutdown = {
+            let token = token.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(100));
+                token.cancel();
+                Instant::now()
+            })
+        };
+        for task in tasks {
+            task.await;
+        }
+        let elapsed = shutdown.join().unwrap().elapsed();
+        println!("{CONNECTIONS} connections stopped in {elapsed:?}");
+    });
+}
@@ -0,0 +1,253 @@
+//! A live table of the tasks of a process publishing its events with
+//! `compio::task::instrument::serve`.
+//!
+//! ```text
+//! cargo run --example compio-top --features instrumentation,time -- demo /tmp/demo.sock
+//! cargo run --example compio-top --features instrumentation,time -- /tmp/demo.sock
+//! ```
+//!
+//! `demo` runs a small server publishing its events. Otherwise the table of
+//! the process at the socket is rendered every half second, for the optional
+//! count of frames, or until the process exits.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    io::{self, IsTerminal, Write},
+    net::Ipv4Addr,
+    path::Path,
+    rc::Rc,
+    time::Duration,
+};
+
+use compio::{
+    io::BufReader,
+    net::{TcpListener, TcpStream, UnixStream},
+    task::instrument::{Event, read_event, serve},
+    time::{interval, sleep},
+};
+
+#[derive(Default)]
+struct TaskRow {
+    location: String,
+    polls: u64,
+    busy: Duration,
+    scheduled: bool,
+}
+
+#[derive(Default)]
+struct State {
+    tasks: BTreeMap<u64, TaskRow>,
+    // The task of each op in flight.
+    ops: HashMap<u64, Option<u64>>,
+    completed: u64,
+    dropped: u64,
+    closed: bool,
+}
+
+impl State {
+    fn update(&mut self, event: Event) {
+        match event {
+            Event::TaskSpawned { id, location } => {
+                self.tasks.insert(
+                    id,
+                    TaskRow {
+                        location,
+                        ..Default::default()
+                    },
+                );
+            }
+            Event::TaskPolled { id, busy } => {
+                let task = self.tasks.entry(id).or_default();
+                task.polls += 1;
+                task.busy += busy;
+                task.scheduled = false;
+            }
+            Event::TaskWoken { id } => self.tasks.entry(id).or_default().scheduled = true,
+            Event::TaskCompleted { id } => {
+                self.tasks.remove(&id);
+                self.completed += 1;
+            }
+            Event::OpSubmitted { id, task, .. } => {
+                self.ops.insert(id, task);
+            }
+            Event::OpCompleted { id, .. } => {
+                self.ops.remove(&id);
+            }
+            Event::Dropped { count } => self.dropped += count,
+            _ => {}
+        }
+    }
+
+    fn render(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "tasks: {}, completed: {}, ops in flight: {}, events dropped: {}",
+            self.tasks.len(),
+            self.completed,
+            self.ops.len(),
+            self.dropped
+        )?;
+        writeln!(
+            out,
+            "{:>6} {:<10} {:>8} {:>12} {:>4}  LOCATION",
+            "ID", "STATE", "POLLS", "BUSY", "OPS"
+        )?;
+        for (id, task) in &self.tasks {
+            let ops = self.ops.values().filter(|t| **t == Some(*id)).count();
+            let state = if task.scheduled {
+                "scheduled"
+            } else if ops > 0 {
+                "waiting"
+            } else {
+                "idle"
+            };
+            writeln!(
+                out,
+                "{id:>6} {state:<10} {:>8} {:>12} {ops:>4}  {}",
+                task.polls,
+                format!("{:.3?}", task.busy),
+                task.location
+            )?;
+        }
+        Ok(())
+    }
+}
+
+async fn top(path: &Path, frames: Option<usize>) -> io::Result<()> {
+    let stream = UnixStream::connect(path)?;
+    let state = Rc::new(RefCell::new(State::default()));
+    let reader = compio::task::spawn({
+        let state = state.clone();
+        async move {
+            let mut reader = BufReader::new(stream);
+            let res = loop {
+                match read_event(&mut reader).await {
+                    Ok(Some(Event::Hello { version })) if version != 1 => {
+                        break Err(io::Error::other(format!("unknown version {version}")));
+                    }
+                    Ok(Some(event)) => state.borrow_mut().update(event),
+                    Ok(None) => break Ok(()),
+                    Err(e) => break Err(e),
+                }
+            };
+            state.borrow_mut().closed = true;
+            res
+        }
+    });
+
+    let terminal = io::stdout().is_terminal();
+    let mut ticks = interval(Duration::from_millis(500));
+    let mut rendered = 0;
+    while frames.is_none_or(|frames| rendered < frames) && !state.borrow().closed {
+        ticks.tick().await;
+        let mut out = io::stdout().lock();
+        if terminal {
+            // Clear the screen.
+            write!(out, "\x1b[2J\x1b[H")?;
+        } else if rendered > 0 {
+            writeln!(out)?;
+        }
+        state.borrow().render(&mut out)?;
+        out.flush()?;
+        rendered += 1;
+    }
+    if state.borrow().closed {
+        reader.await?;
+    }
+    Ok(())
+}
+
+async fn echo(stream: TcpStream) {
+    let mut buf = Vec::with_capacity(64);
+    loop {
+        buf.clear();
+        let (res, b) = stream.recv(buf).await;
+        if !matches!(res, Ok(n) if n > 0) {
+            break;
+        }
+        let (res, b) = stream.send_all(b).await;
+        if res.is_err() {
+            break;
+        }
+        buf = b;
+    }
+}
+
+async fn demo(path: &Path) -> io::Result<()> {
+    std::fs::remove_file(path).ok();
+    compio::task::spawn({
+        let path = path.to_path_buf();
+        async move {
+            if let Err(e) = serve(&path).await {
+                eprintln!("instrumentation: {e}");
+            }
+        }
+    })
+    .detach();
+    // Let it bind the socket.
+    compio::task::yield_now().await;
+    println!("listening on {}", path.display());
+
+    // An echo server, and a client pinging it.
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let addr = listener.local_addr()?;
+    compio::task::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            compio::task::spawn(echo(stream)).detach();
+        }
+    })
+    .detach();
+    compio::task::spawn(async move {
+        let client = TcpStream::connect(addr).await.unwrap();
+        let mut buf = Vec::with_capacity(4);
+        loop {
+            buf.clear();
+            buf.extend_from_slice(b"ping");
+            buf = client.send_all(buf).await.1;
+            buf = client.recv_exact(buf).await.1;
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .detach();
+
+    // Some CPU-bound work, yielding in between.
+    compio::task::spawn(async {
+        let mut sum = 0u64;
+        loop {
+            for i in 0..10_000 {
+                sum = sum.wrapping_add(i);
+            }
+            compio::task::yield_now().await;
+        }
+    })
+    .detach();
+
+    // Short-lived jobs.
+    let mut ticks = interval(Duration::from_millis(100));
+    loop {
+        ticks.tick().await;
+        compio::task::spawn(sleep(Duration::from_millis(250))).detach();
+    }
+}
+
+fn main() -> io::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    compio::task::block_on(async {
+        match args[..] {
+            ["demo", path] => demo(Path::new(path)).await,
+            [path] => top(Path::new(path), None).await,
+            [path, frames] => {
+                let frames = frames.parse().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "invalid count of frames")
+                })?;
+                top(Path::new(path), Some(frames)).await
+            }
+            _ => {
+                eprintln!("usage: compio-top <socket> [frames] | compio-top demo <socket>");
+                std::process::exit(2);
+            }
+        }
+    })
+}
@@ -0,0 +1,57 @@
+//! Extend the runtime with a syscall which has no dedicated op.
+
+#[cfg(target_os = "linux")]
+mod copy {
+    use std::{io, os::fd::AsRawFd};
+
+    use compio::{buf::IntoInner, fs::File, op::BlockingBufOp};
+
+    /// Copy all remaining bytes of `src` into `dst` in the kernel.
+    ///
+    /// The destination is moved into the op, so that it lives until the copy
+    /// finishes, and it is returned after completion.
+    pub async fn copy_file_range(
+        src: &File,
+        dst: std::fs::File,
+    ) -> (io::Result<usize>, std::fs::File) {
+        let op = BlockingBufOp::new(src.as_raw_fd(), dst, |fd, dst| {
+            let mut copied = 0;
+            loop {
+                let res = unsafe {
+                    libc::copy_file_range(
+                        fd,
+                        std::ptr::null_mut(),
+                        dst.as_raw_fd(),
+                        std::ptr::null_mut(),
+                        1 << 30,
+                        0,
+                    )
+                };
+                match res {
+                    -1 => return Err(io::Error::last_os_error()),
+                    0 => return Ok(copied),
+                    n => copied += n as usize,
+                }
+            }
+        });
+        let (res, op) = compio::task::submit(op).await;
+        (res, op.into_inner())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn main() {
+    use compio::fs::File;
+
+    compio::task::block_on(async {
+        let src = File::open("Cargo.toml").unwrap();
+        let dst = tempfile::tempfile().unwrap();
+        let (res, dst) = copy::copy_file_range(&src, dst).await;
+        let copied = res.unwrap();
+        assert_eq!(copied as u64, dst.metadata().unwrap().len());
+        println!("copied {} bytes", copied);
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {}
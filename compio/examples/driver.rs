@@ -1,7 +1,7 @@
 use arrayvec::ArrayVec;
 use compio::{
     buf::IntoInner,
-    driver::{AsRawFd, Entry, Proactor},
+    driver::{AsRawFd, Entry, Proactor, PushEntry},
     op::ReadAt,
 };
 
@@ -11,17 +11,20 @@ fn main() {
     driver.attach(file.as_raw_fd()).unwrap();
 
     let op = ReadAt::new(file.as_raw_fd(), 0, Vec::with_capacity(4096));
-    let user_data = driver.push(op);
-
-    let mut entries = ArrayVec::<Entry, 1>::new();
-    driver.poll(None, &mut entries).unwrap();
-    let (res, op) = driver.pop(&mut entries.into_iter()).next().unwrap();
+    let (res, op) = match driver.push_entry(op) {
+        PushEntry::Pending(user_data) => {
+            let mut entries = ArrayVec::<Entry, 1>::new();
+            driver.poll(None, &mut entries).unwrap();
+            let (res, op) = driver.pop(&mut entries.into_iter()).next().unwrap();
+            assert_eq!(op.user_data(), user_data);
+            (res, unsafe { op.into_op::<ReadAt<Vec<u8>>>() })
+        }
+        // The read may complete inline.
+        PushEntry::Ready(res) => res,
+    };
     let n = res.unwrap();
-    assert_eq!(op.user_data(), user_data);
 
-    let mut buffer = unsafe { op.into_op::<ReadAt<Vec<u8>>>() }
-        .into_inner()
-        .into_inner();
+    let mut buffer = op.into_inner().into_inner();
     unsafe {
         buffer.set_len(n);
     }
@@ -0,0 +1,225 @@
+//! Transfer files over TCP, resuming the interrupted transfers, and verifying
+//! the checksums.
+//!
+//! ```text
+//! cargo run --example file_transfer -- serve 127.0.0.1:9000 received/
+//! cargo run --example file_transfer -- send 127.0.0.1:9000 Cargo.toml
+//! ```
+//!
+//! The client sends `<name> <size> <checksum>\n`, and the server answers with
+//! the length of the partial file it already has. The client sends the rest of
+//! the file from there, and shuts down its write side. The server answers
+//! `ok\n` if the whole file has the size and the checksum, or `error:
+//! <reason>\n`, and restarts the file on a checksum mismatch.
+//!
+//! `send` takes an optional limit of the bytes to send, to interrupt the
+//! transfer on purpose.
+
+use std::{
+    hash::Hasher,
+    io,
+    net::Shutdown,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use compio::{
+    BufResult,
+    buf::{ChecksummingBuf, IoBuf},
+    fs::{File, OpenOptions},
+    io::{AsyncWrite, BufReader, copy},
+    net::{TcpListener, TcpStream, send_file},
+};
+
+// FNV-1a.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+// The checksum of the whole file, hashed while it is read.
+async fn checksum(file: &File) -> io::Result<u64> {
+    let mut buf = ChecksummingBuf::new(Vec::with_capacity(64 * 1024), Fnv::default());
+    let mut pos = 0;
+    loop {
+        let (res, read) = file.read_at(buf, pos).await;
+        let (mut inner, hasher) = read.into_parts();
+        let n = res?;
+        if n == 0 {
+            return Ok(hasher.finish());
+        }
+        pos += n as u64;
+        inner.clear();
+        buf = ChecksummingBuf::new(inner, hasher);
+    }
+}
+
+// Write a stream to a file from a position, so that `copy` could write to it.
+struct FileWriter<'a> {
+    file: &'a File,
+    pos: u64,
+}
+
+impl AsyncWrite for FileWriter<'_> {
+    async fn write<B: IoBuf>(&mut self, buf: B) -> BufResult<usize, B> {
+        let (res, buf) = self.file.write_at(buf, self.pos).await;
+        if let Ok(n) = res {
+            self.pos += n as u64;
+        }
+        (res, buf)
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        self.file.sync_data().await
+    }
+}
+
+async fn receive(stream: &TcpStream, dir: &Path) -> io::Result<String> {
+    let mut reader = BufReader::new(stream);
+    let (res, header) = reader.read_line(String::new()).await;
+    res?;
+    // The name may contain spaces.
+    let mut fields = header.trim_end().rsplitn(3, ' ');
+    let (Some(hash), Some(size), Some(name)) = (fields.next(), fields.next(), fields.next()) else {
+        return Err(invalid("malformed header"));
+    };
+    let expected = u64::from_str_radix(hash, 16).map_err(|_| invalid("malformed checksum"))?;
+    let size: u64 = size.parse().map_err(|_| invalid("malformed size"))?;
+    if Path::new(name).file_name() != Some(name.as_ref()) {
+        return Err(invalid(format!("invalid name {name:?}")));
+    }
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(dir.join(name))?;
+    let mut offset = file.metadata()?.len();
+    if offset > size {
+        file.set_len(0)?;
+        offset = 0;
+    }
+    stream.send_all(format!("{offset}\n")).await.0?;
+
+    // The bytes after the header may be buffered already.
+    let mut writer = FileWriter {
+        file: &file,
+        pos: offset,
+    };
+    let received = copy(&mut reader, &mut writer).await?;
+    if offset + received != size {
+        return Ok(format!("error: {} of {size} bytes", offset + received));
+    }
+    if checksum(&file).await? != expected {
+        file.set_len(0)?;
+        return Ok("error: checksum mismatch".to_string());
+    }
+    println!("received {name}, {received} of {size} bytes");
+    Ok("ok".to_string())
+}
+
+async fn serve(addr: &str, dir: PathBuf) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let addr = listener.local_addr()?.as_socket().unwrap();
+    println!("listening on {addr}");
+    // One at a time, so that the transfers of the same file don't race.
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let peer = peer.as_socket().unwrap();
+        let reply = match receive(&stream, &dir).await {
+            Ok(reply) => reply,
+            Err(e) => format!("error: {e}"),
+        };
+        if let Err(e) = stream.send_all(format!("{reply}\n")).await.0 {
+            // The client is gone, e.g., interrupted.
+            eprintln!("{peer}: {reply}, {e}");
+        }
+    }
+}
+
+async fn send(addr: &str, path: &Path, limit: Option<u64>) -> io::Result<()> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let checksum = checksum(&file).await?;
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| invalid("invalid file name"))?;
+
+    let stream = TcpStream::connect(addr).await?;
+    let mut reader = BufReader::new(&stream);
+    stream
+        .send_all(format!("{name} {size} {checksum:016x}\n"))
+        .await
+        .0?;
+    let (res, line) = reader.read_line(String::new()).await;
+    res?;
+    let offset: u64 = match line.trim().parse() {
+        Ok(offset) => offset,
+        Err(_) => return Err(io::Error::other(line.trim().to_string())),
+    };
+
+    let len = limit.map_or(size - offset, |limit| limit.min(size - offset));
+    let sent = send_file(&stream, &file, offset, len).await?;
+    println!("sent {sent} bytes from offset {offset}");
+    if offset + sent < size {
+        println!("interrupted");
+        return Ok(());
+    }
+    stream.shutdown(Shutdown::Write)?;
+    let (res, line) = reader.read_line(String::new()).await;
+    res?;
+    match line.trim() {
+        "ok" => {
+            println!("ok");
+            Ok(())
+        }
+        reply => Err(io::Error::other(reply.to_string())),
+    }
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let res = compio::task::block_on(async {
+        match args[..] {
+            ["serve", addr, dir] => serve(addr, dir.into()).await,
+            ["send", addr, path] => send(addr, path.as_ref(), None).await,
+            ["send", addr, path, limit] => match limit.parse() {
+                Ok(limit) => send(addr, path.as_ref(), Some(limit)).await,
+                Err(_) => Err(invalid("invalid limit")),
+            },
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "usage: file_transfer serve <addr> <dir> | send <addr> <file> [<limit>]",
+            )),
+        }
+    });
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
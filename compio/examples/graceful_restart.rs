@@ -0,0 +1,127 @@
+//! Zero-downtime restart: the old process hands its listener over to a new
+//! process, which it spawns with the listening socket inherited across
+//! `exec`. The connections queued during the handover are accepted by the new
+//! process, and no client is refused.
+
+#[cfg(unix)]
+mod imp {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::SocketAddr,
+        os::fd::{AsRawFd, FromRawFd},
+        process::{Command, Stdio},
+        rc::Rc,
+        time::{Duration, Instant},
+    };
+
+    use compio::{net::TcpListener, sync::CancellationToken};
+
+    const LISTEN_FD: &str = "COMPIO_LISTEN_FD";
+
+    // Reply the name of the process to each connection.
+    async fn serve(listener: Rc<TcpListener>, name: &'static str) {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            compio::task::spawn(async move {
+                stream.send_all(name).await.0.unwrap();
+            })
+            .detach();
+        }
+    }
+
+    // Connect repeatedly, and count the replies of each process.
+    fn client(addr: SocketAddr, duration: Duration) -> (usize, usize, usize) {
+        let (mut parent, mut child, mut failed) = (0, 0, 0);
+        let start = Instant::now();
+        while start.elapsed() < duration {
+            let mut reply = String::new();
+            match std::net::TcpStream::connect(addr)
+                .and_then(|mut stream| stream.read_to_string(&mut reply))
+            {
+                Ok(_) if reply == "parent" => parent += 1,
+                Ok(_) if reply == "child" => child += 1,
+                _ => failed += 1,
+            }
+        }
+        (parent, child, failed)
+    }
+
+    fn parent() {
+        compio::task::block_on(async {
+            let listener = Rc::new(TcpListener::bind("127.0.0.1:0").unwrap());
+            let addr = listener.local_addr().unwrap().as_socket().unwrap();
+            let server = compio::task::spawn(serve(listener.clone(), "parent"));
+            let client = std::thread::spawn(move || client(addr, Duration::from_secs(1)));
+            compio::time::sleep(Duration::from_millis(300)).await;
+
+            // Stop accepting, and the connections are queued by the kernel.
+            listener.pause().await;
+            // `dup` clears `FD_CLOEXEC`, so that the new fd is inherited.
+            let fd = unsafe { libc::dup(listener.as_raw_fd()) };
+            assert!(fd >= 0, "{}", std::io::Error::last_os_error());
+            let mut child = Command::new(std::env::current_exe().unwrap())
+                .env(LISTEN_FD, fd.to_string())
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .unwrap();
+            unsafe { libc::close(fd) };
+            let mut ready = String::new();
+            BufReader::new(child.stdout.take().unwrap())
+                .read_line(&mut ready)
+                .unwrap();
+            assert_eq!(ready.trim(), "ready");
+
+            // The child accepts from now on. Close the listener of the parent.
+            server.cancel().await;
+            drop(Rc::into_inner(listener).unwrap());
+            println!("handed over to the child {}", child.id());
+
+            // Keep running the connections accepted before the handover.
+            while !client.is_finished() {
+                compio::time::sleep(Duration::from_millis(10)).await;
+            }
+            let (parent, child_replies, failed) = client.join().unwrap();
+            println!("replies: parent {parent}, child {child_replies}, failed {failed}");
+            // Closing stdin stops the child.
+            drop(child.stdin.take());
+            child.wait().unwrap();
+            assert_eq!(failed, 0);
+        })
+    }
+
+    fn child(fd: i32) {
+        let token = CancellationToken::new();
+        std::thread::spawn({
+            let token = token.clone();
+            move || {
+                std::io::stdin().read_to_end(&mut vec![]).ok();
+                token.cancel();
+            }
+        });
+        compio::task::block_on(async {
+            let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+            let listener = Rc::new(TcpListener::from_std(listener).unwrap());
+            println!("ready");
+            std::io::stdout().flush().unwrap();
+            token.run_until_cancelled(serve(listener, "child")).await;
+        })
+    }
+
+    pub fn main() {
+        match std::env::var(LISTEN_FD) {
+            Ok(fd) => child(fd.parse().unwrap()),
+            Err(_) => parent(),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn main() {
+    imp::main()
+}
+
+#[cfg(not(unix))]
+fn main() {
+    println!("the listener is inherited across `exec`, which is only supported on unix");
+}
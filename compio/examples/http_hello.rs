@@ -0,0 +1,176 @@
+//! A minimal HTTP/1.1 server with keep-alive.
+//!
+//! `GET /` answers a greeting, and `GET /<name>` sends the file `<name>` in the
+//! root directory with the chunked transfer encoding, each chunk sent from the
+//! file with `send_file`.
+//!
+//! ```text
+//! cargo run --example http_hello -- 127.0.0.1:8080 .
+//! curl http://127.0.0.1:8080/Cargo.toml
+//! ```
+
+use std::{
+    io,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
+
+use compio::{
+    fs::File,
+    io::BufReader,
+    net::{TcpListener, TcpStream, send_file},
+};
+
+// The size of the chunks of a file.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+struct Request {
+    method: String,
+    path: String,
+    keep_alive: bool,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// Read the request line and the headers, or `None` if the client closes the
+// connection before a request.
+async fn read_request(reader: &mut BufReader<&TcpStream>) -> io::Result<Option<Request>> {
+    let (res, line) = reader.read_line(String::new()).await;
+    if res? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid("malformed request line"));
+    };
+    // HTTP/1.1 keeps the connection alive by default, and HTTP/1.0 closes it.
+    let mut keep_alive = version == "HTTP/1.1";
+    loop {
+        let (res, line) = reader.read_line(String::new()).await;
+        if res? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(invalid("malformed header"));
+        };
+        if name.eq_ignore_ascii_case("connection") {
+            let value = value.trim();
+            if value.eq_ignore_ascii_case("close") {
+                keep_alive = false;
+            } else if value.eq_ignore_ascii_case("keep-alive") {
+                keep_alive = true;
+            }
+        }
+    }
+    Ok(Some(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        keep_alive,
+    }))
+}
+
+fn connection(keep_alive: bool) -> &'static str {
+    if keep_alive { "keep-alive" } else { "close" }
+}
+
+async fn send_text(
+    stream: &TcpStream,
+    status: &str,
+    body: &str,
+    keep_alive: bool,
+) -> io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: \
+         {}\r\n\r\n{body}",
+        body.len(),
+        connection(keep_alive),
+    );
+    stream.send_all(response).await.0?;
+    Ok(())
+}
+
+// The file of the path in the root, if it is a regular file there.
+fn open(root: &Path, path: &str) -> Option<File> {
+    let name = Path::new(path.trim_start_matches('/'));
+    if !name.components().all(|c| matches!(c, Component::Normal(_))) {
+        return None;
+    }
+    let file = File::open(root.join(name)).ok()?;
+    file.metadata().ok()?.is_file().then_some(file)
+}
+
+async fn send_chunked(stream: &TcpStream, file: &File, keep_alive: bool) -> io::Result<()> {
+    let len = file.metadata()?.len();
+    let header = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nTransfer-Encoding: \
+         chunked\r\nConnection: {}\r\n\r\n",
+        connection(keep_alive),
+    );
+    stream.send_all(header).await.0?;
+    let mut offset = 0;
+    while offset < len {
+        let size = CHUNK_SIZE.min(len - offset);
+        stream.send_all(format!("{size:x}\r\n")).await.0?;
+        if send_file(stream, file, offset, size).await? != size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        stream.send_all("\r\n").await.0?;
+        offset += size;
+    }
+    stream.send_all("0\r\n\r\n").await.0?;
+    Ok(())
+}
+
+async fn serve(stream: TcpStream, root: Rc<PathBuf>) -> io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    while let Some(request) = read_request(&mut reader).await? {
+        // The body of the other methods is not read, so the connection can't
+        // be reused.
+        let keep_alive = request.keep_alive && request.method == "GET";
+        if request.method != "GET" {
+            send_text(&stream, "405 Method Not Allowed", "", false).await?;
+        } else if request.path == "/" {
+            send_text(&stream, "200 OK", "Hello, world!\n", keep_alive).await?;
+        } else if let Some(file) = open(&root, &request.path) {
+            send_chunked(&stream, &file, keep_alive).await?;
+        } else {
+            send_text(&stream, "404 Not Found", "not found\n", keep_alive).await?;
+        }
+        if !keep_alive {
+            break;
+        }
+    }
+    stream.shutdown(std::net::Shutdown::Write)
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let addr = args.next().unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let root = Rc::new(PathBuf::from(
+        args.next().unwrap_or_else(|| ".".to_string()),
+    ));
+
+    compio::task::block_on(async {
+        let listener = TcpListener::bind(&addr).unwrap();
+        let addr = listener.local_addr().unwrap().as_socket().unwrap();
+        println!("listening on http://{addr}");
+        loop {
+            let (stream, peer) = listener.accept().await.unwrap();
+            let peer = peer.as_socket().unwrap();
+            let root = root.clone();
+            compio::task::spawn(async move {
+                if let Err(e) = serve(stream, root).await {
+                    eprintln!("{peer}: {e}");
+                }
+            })
+            .detach();
+        }
+    })
+}
@@ -0,0 +1,70 @@
+//! Print the latency metrics of an echo workload, with many connections in
+//! the same runtime. Compare them with different spin budgets, e.g.,
+//! `cargo run --example latency --features metrics -- 50` spins for 50µs.
+
+use std::{net::Ipv4Addr, time::Duration};
+
+use compio::{
+    driver::ProactorBuilder,
+    net::{TcpListener, TcpStream},
+};
+
+const CONNECTIONS: usize = 16;
+const MESSAGES: usize = 1000;
+
+async fn echo(stream: TcpStream) {
+    loop {
+        let (res, buf) = stream.recv(Vec::with_capacity(64)).await;
+        if res.unwrap() == 0 {
+            break;
+        }
+        stream.send_all(buf).await.0.unwrap();
+    }
+}
+
+async fn ping(stream: TcpStream) {
+    static MESSAGE: &[u8] = &[1u8; 64];
+
+    let mut buf = Vec::with_capacity(64);
+    for _ in 0..MESSAGES {
+        stream.send_all(MESSAGE).await.0.unwrap();
+        buf.clear();
+        let res;
+        (res, buf) = stream.recv_exact(buf).await;
+        res.unwrap();
+    }
+}
+
+fn main() {
+    let spin = std::env::args()
+        .nth(1)
+        .map_or(0, |micros| micros.parse().unwrap());
+    let builder = ProactorBuilder::new()
+        .spin_before_wait(Duration::from_micros(spin))
+        .latency_metrics(true);
+    compio::task::init_with(&builder).unwrap();
+
+    compio::task::block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut clients = vec![];
+        for _ in 0..CONNECTIONS {
+            let (client, (server, _)) =
+                futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+            compio::task::spawn(echo(server)).detach();
+            clients.push(compio::task::spawn(ping(client)));
+        }
+        compio::task::reset_latency_metrics();
+        for client in clients {
+            client.await;
+        }
+    });
+
+    let metrics = compio::task::latency_metrics().unwrap();
+    println!("spin: {spin}µs, {:?}", compio::task::poll_stats());
+    println!("task polls between driver polls: {}", metrics.task_polls);
+    println!("completion delay (ns): {}", metrics.completion_delay);
+    for (low, high, count) in metrics.completion_delay.buckets() {
+        println!("{low:>10} - {high:>10} ns: {count}");
+    }
+}
@@ -0,0 +1,45 @@
+//! Read a file in chunks with the proactor directly, without the runtime.
+//! The reads are owned by a set, which gives them back typed.
+
+use compio::{
+    buf::IntoInner,
+    driver::{AsRawFd, OwnedOps, Proactor, PushEntry},
+    op::ReadAt,
+};
+
+const CHUNK: usize = 256;
+
+fn main() {
+    let mut proactor = Proactor::new().unwrap();
+    let file = compio::fs::File::open("Cargo.toml").unwrap();
+    proactor.attach(file.as_raw_fd()).unwrap();
+    let len = file.metadata().unwrap().len() as usize;
+
+    let mut ops = OwnedOps::new(&mut proactor);
+    let mut chunks = vec![None; len.div_ceil(CHUNK)];
+    let mut done = vec![];
+    for (i, offset) in (0..len).step_by(CHUNK).enumerate() {
+        let op = ReadAt::new(file.as_raw_fd(), offset as u64, Vec::with_capacity(CHUNK));
+        match ops.submit(op) {
+            PushEntry::Pending(id) => chunks[i] = Some(id),
+            // The read may complete inline.
+            PushEntry::Ready(res) => done.push((i, res)),
+        }
+    }
+    while !ops.is_empty() {
+        for (id, res) in ops.poll(None).unwrap() {
+            let i = chunks.iter().position(|c| *c == Some(id)).unwrap();
+            done.push((i, res));
+        }
+    }
+    done.sort_by_key(|(i, _)| *i);
+
+    let mut content = vec![];
+    for (_, (res, op)) in done {
+        let n = res.unwrap();
+        let mut buffer = op.into_inner().into_inner();
+        unsafe { buffer.set_len(n) };
+        content.extend_from_slice(&buffer);
+    }
+    print!("{}", String::from_utf8(content).unwrap());
+}
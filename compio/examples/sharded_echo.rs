@@ -0,0 +1,62 @@
+//! A thread-per-core echo server. Each worker accepts from its own listener,
+//! and the kernel balances the connections among them. The clients report
+//! which worker served them.
+
+use std::{
+    io::{Read, Write},
+    net::SocketAddr,
+};
+
+use compio::net::TcpListener;
+
+// Echo with a prefix of the worker index.
+async fn serve(listener: TcpListener, index: usize) {
+    loop {
+        let (stream, _) = listener.accept().await.unwrap();
+        compio::task::spawn(async move {
+            let prefix = format!("worker {index}: ");
+            stream.send_all(prefix).await.0.unwrap();
+            loop {
+                let (res, buf) = stream.recv(Vec::with_capacity(1024)).await;
+                if res.unwrap() == 0 {
+                    break;
+                }
+                stream.send_all(buf).await.0.unwrap();
+            }
+        })
+        .detach();
+    }
+}
+
+fn client(addr: SocketAddr) -> String {
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    stream.write_all(b"hello").unwrap();
+    stream.shutdown(std::net::Shutdown::Write).unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    reply
+}
+
+fn main() {
+    let workers = std::thread::available_parallelism().map_or(4, |n| n.get());
+    let listeners = TcpListener::bind_reuseport_sharded("127.0.0.1:0", workers).unwrap();
+    let addr = listeners[0].local_addr().unwrap();
+    println!("{} listeners on {addr}", listeners.len());
+    for (index, listener) in listeners.into_iter().enumerate() {
+        std::thread::spawn(move || {
+            compio::task::block_on(serve(TcpListener::from_std(listener).unwrap(), index))
+        });
+    }
+
+    let mut counts = vec![0; workers];
+    for _ in 0..100 {
+        let reply = client(addr);
+        let (worker, echo) = reply.split_once(": ").unwrap();
+        assert_eq!(echo, "hello");
+        let index: usize = worker.strip_prefix("worker ").unwrap().parse().unwrap();
+        counts[index] += 1;
+    }
+    for (index, count) in counts.iter().enumerate() {
+        println!("worker {index}: {count} connections");
+    }
+}
@@ -0,0 +1,45 @@
+//! Echo the keys in raw mode. A ticker and the resize events are printed
+//! concurrently, because reading the terminal doesn't block the runtime.
+
+use std::time::Duration;
+
+use compio::{io::Terminal, time::interval};
+use futures_util::StreamExt;
+
+fn main() {
+    compio::task::block_on(async {
+        let terminal = Terminal::new();
+        if !terminal.is_tty() {
+            println!("the input is not a terminal");
+            return;
+        }
+        let size = terminal.size().unwrap();
+        println!("size: {}x{}", size.columns, size.rows);
+
+        let mut resizes = terminal.resize_events().unwrap();
+        let _resizes = compio::task::spawn(async move {
+            while let Some(size) = resizes.next().await {
+                let size = size.unwrap();
+                // The output isn't processed in raw mode, so "\r" is needed.
+                print!("resized: {}x{}\r\n", size.columns, size.rows);
+            }
+        });
+        let _ticker = compio::task::spawn(async {
+            let mut interval = interval(Duration::from_secs(1));
+            for tick in 0.. {
+                interval.tick().await;
+                print!("tick {tick}\r\n");
+            }
+        });
+
+        let _guard = terminal.enable_raw_mode().unwrap();
+        print!("press any key, \"q\" or ctrl-c to exit\r\n");
+        loop {
+            let (res, keys) = terminal.read(Vec::with_capacity(16)).await;
+            if res.unwrap() == 0 || keys.contains(&b'q') || keys.contains(&3) {
+                break;
+            }
+            print!("keys: {keys:?}\r\n");
+        }
+    })
+}
@@ -33,3 +33,9 @@ impl Attacher {
         Ok(())
     }
 }
+
+impl Default for Attacher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
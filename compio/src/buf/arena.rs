@@ -0,0 +1,329 @@
+//! A bump-allocated arena of request-scoped buffers.
+//!
+//! The buffers of a request, e.g., the header scratch, the body chunks and
+//! the response, usually live and die together. A [`BufArena`] carves them
+//! out of one block by bumping an offset, with no bookkeeping for each buffer,
+//! and the buffers of a request are close to each other in memory. Each
+//! [`ArenaBuf`] keeps the block alive, and the whole block is recycled when
+//! the last of them is dropped.
+//!
+//! A long-lived buffer pins the whole block, so call [`ArenaBuf::detach`] for
+//! the buffers outliving the request.
+//!
+//! ```
+//! use compio::buf::arena::BufArena;
+//!
+//! compio::task::block_on(async {
+//!     let arena = BufArena::with_capacity(4096);
+//!     let file = compio::fs::File::open("Cargo.toml").unwrap();
+//!     let (res, header) = file.read_at(arena.alloc(16), 0).await;
+//!     assert_eq!(res.unwrap(), 16);
+//!     let body = arena.alloc(256);
+//!     assert_eq!(arena.stats().live, 2);
+//!
+//!     // Kept after the request.
+//!     let header = header.detach();
+//!     drop(body);
+//!     assert_eq!(header.len(), 16);
+//!     assert_eq!(arena.stats().used, 0);
+//! })
+//! ```
+
+use std::{
+    cell::Cell,
+    fmt::Debug,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+    rc::Rc,
+};
+
+use crate::buf::{IoBuf, IoBufMut};
+
+/// Statistics of a [`BufArena`], see [`BufArena::stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ArenaStats {
+    /// The size of the block.
+    pub capacity: usize,
+    /// The bytes allocated from the block since it is recycled.
+    pub used: usize,
+    /// The count of the alive buffers allocated from the block.
+    pub live: usize,
+    /// The count of the buffers allocated from the block.
+    pub allocations: u64,
+    /// The count of the buffers allocated on the heap because the block is
+    /// full.
+    pub overflows: u64,
+    /// The count of the times the block is recycled.
+    pub recycles: u64,
+}
+
+impl ArenaStats {
+    /// The ratio of the used bytes to the size of the block.
+    pub fn occupancy(&self) -> f64 {
+        if self.capacity == 0 {
+            0.0
+        } else {
+            self.used as f64 / self.capacity as f64
+        }
+    }
+}
+
+struct Block {
+    ptr: NonNull<MaybeUninit<u8>>,
+    stats: Cell<ArenaStats>,
+}
+
+impl Block {
+    fn new(capacity: usize) -> Self {
+        let block = Box::<[u8]>::new_uninit_slice(capacity);
+        let ptr = NonNull::new(Box::into_raw(block) as *mut MaybeUninit<u8>).unwrap();
+        Self {
+            ptr,
+            stats: Cell::new(ArenaStats {
+                capacity,
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut ArenaStats)) {
+        let mut stats = self.stats.get();
+        f(&mut stats);
+        self.stats.set(stats);
+    }
+
+    fn bump(&self, len: usize) -> Option<NonNull<u8>> {
+        let stats = self.stats.get();
+        if len > stats.capacity - stats.used {
+            return None;
+        }
+        let ptr = unsafe { self.ptr.add(stats.used) }.cast();
+        self.update(|stats| {
+            stats.used += len;
+            stats.live += 1;
+            stats.allocations += 1;
+        });
+        Some(ptr)
+    }
+
+    // Reset the bump offset when the last buffer is dropped.
+    fn release(&self) {
+        self.update(|stats| {
+            stats.live -= 1;
+            if stats.live == 0 {
+                stats.used = 0;
+                stats.recycles += 1;
+            }
+        });
+    }
+}
+
+impl Drop for Block {
+    fn drop(&mut self) {
+        let capacity = self.stats.get().capacity;
+        drop(unsafe {
+            Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+                self.ptr.as_ptr(),
+                capacity,
+            ))
+        });
+    }
+}
+
+/// A block of memory to allocate [`ArenaBuf`]s from. It is cheap to clone,
+/// and the clones share the block.
+///
+/// The block is allocated once. When it is full, the buffers are allocated on
+/// the heap, counted by [`ArenaStats::overflows`].
+#[derive(Clone)]
+pub struct BufArena {
+    block: Rc<Block>,
+}
+
+impl BufArena {
+    /// Allocate a block of `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            block: Rc::new(Block::new(capacity)),
+        }
+    }
+
+    /// Allocate an empty buffer with capacity of `len` bytes from the block,
+    /// or from the heap if the block is full.
+    pub fn alloc(&self, len: usize) -> ArenaBuf {
+        let storage = match self.block.bump(len) {
+            Some(ptr) => Storage::Arena {
+                block: self.block.clone(),
+                ptr,
+                capacity: len,
+            },
+            None => {
+                self.block.update(|stats| stats.overflows += 1);
+                Storage::Heap(Vec::with_capacity(len))
+            }
+        };
+        ArenaBuf { storage, len: 0 }
+    }
+
+    /// The statistics of the block.
+    pub fn stats(&self) -> ArenaStats {
+        self.block.stats.get()
+    }
+}
+
+impl Debug for BufArena {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufArena")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+enum Storage {
+    Arena {
+        block: Rc<Block>,
+        ptr: NonNull<u8>,
+        capacity: usize,
+    },
+    Heap(Vec<u8>),
+}
+
+/// A buffer from [`BufArena::alloc`], which works like a [`Vec<u8>`] with
+/// fixed capacity in the operations.
+///
+/// It keeps the block of the arena alive, and the block is recycled when the
+/// last buffer from it is dropped.
+pub struct ArenaBuf {
+    storage: Storage,
+    // Only for the arena storage.
+    len: usize,
+}
+
+impl ArenaBuf {
+    /// Copy the data out to a [`Vec<u8>`] with the same capacity, and release
+    /// the storage in the arena. The buffers allocated on the heap are not
+    /// copied.
+    pub fn detach(mut self) -> Vec<u8> {
+        match &mut self.storage {
+            Storage::Arena { capacity, .. } => {
+                let mut buffer = Vec::with_capacity(*capacity);
+                buffer.extend_from_slice(&self);
+                buffer
+            }
+            Storage::Heap(buffer) => std::mem::take(buffer),
+        }
+    }
+
+    /// Whether the buffer is allocated from the block of the arena.
+    pub fn is_in_arena(&self) -> bool {
+        matches!(self.storage, Storage::Arena { .. })
+    }
+
+    /// The capacity of the buffer.
+    pub fn capacity(&self) -> usize {
+        match &self.storage {
+            Storage::Arena { capacity, .. } => *capacity,
+            Storage::Heap(buffer) => buffer.capacity(),
+        }
+    }
+
+    /// Clear the buffer, keeping the capacity.
+    pub fn clear(&mut self) {
+        match &mut self.storage {
+            Storage::Arena { .. } => self.len = 0,
+            Storage::Heap(buffer) => buffer.clear(),
+        }
+    }
+
+    /// Append the data to the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the data exceeds the remaining capacity.
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        let len = self.buf_len();
+        assert!(
+            data.len() <= self.capacity() - len,
+            "exceeds the capacity of the arena buffer"
+        );
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr(),
+                self.as_buf_mut_ptr().add(len),
+                data.len(),
+            );
+            self.set_buf_init(data.len());
+        }
+    }
+}
+
+impl Deref for ArenaBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl DerefMut for ArenaBuf {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { std::slice::from_raw_parts_mut(self.as_buf_mut_ptr(), self.buf_len()) }
+    }
+}
+
+impl AsRef<[u8]> for ArenaBuf {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Debug for ArenaBuf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ArenaBuf").field(&self.as_slice()).finish()
+    }
+}
+
+impl Drop for ArenaBuf {
+    fn drop(&mut self) {
+        if let Storage::Arena { block, .. } = &self.storage {
+            block.release();
+        }
+    }
+}
+
+unsafe impl IoBuf for ArenaBuf {
+    fn as_buf_ptr(&self) -> *const u8 {
+        match &self.storage {
+            Storage::Arena { ptr, .. } => ptr.as_ptr(),
+            Storage::Heap(buffer) => buffer.as_ptr(),
+        }
+    }
+
+    fn buf_len(&self) -> usize {
+        match &self.storage {
+            Storage::Arena { .. } => self.len,
+            Storage::Heap(buffer) => buffer.len(),
+        }
+    }
+
+    fn buf_capacity(&self) -> usize {
+        self.capacity()
+    }
+}
+
+unsafe impl IoBufMut for ArenaBuf {
+    fn as_buf_mut_ptr(&mut self) -> *mut u8 {
+        match &mut self.storage {
+            Storage::Arena { ptr, .. } => ptr.as_ptr(),
+            Storage::Heap(buffer) => buffer.as_mut_ptr(),
+        }
+    }
+
+    unsafe fn set_buf_init(&mut self, len: usize) {
+        match &mut self.storage {
+            Storage::Arena { .. } => self.len += len,
+            Storage::Heap(buffer) => buffer.set_len(len + buffer.len()),
+        }
+    }
+}
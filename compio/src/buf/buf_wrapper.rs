@@ -35,17 +35,13 @@ impl<T: IoBufMut> WrapBufMut for BufWrapper<T> {
 
 impl<T: IoBuf> AsIoSlices for BufWrapper<T> {
     unsafe fn as_io_slices(&self) -> OneOrVec<IoSlice<'static>> {
-        OneOrVec::One(IoSlice::new(
-            &*(self.buffer.as_slice() as *const _ as *const _),
-        ))
+        OneOrVec::One(self.buffer.as_io_slice().into_std())
     }
 }
 
 impl<T: IoBufMut> AsIoSlicesMut for BufWrapper<T> {
     unsafe fn as_io_slices_mut(&mut self) -> OneOrVec<IoSliceMut<'static>> {
-        OneOrVec::One(IoSliceMut::new(
-            &mut *(self.buffer.as_uninit_slice() as *mut _ as *mut _),
-        ))
+        OneOrVec::One(self.buffer.as_uninit_io_slice().into_std())
     }
 }
 
@@ -90,7 +86,7 @@ impl<T: IoBuf> AsIoSlices for VectoredBufWrapper<T> {
         OneOrVec::Vec(
             self.buffer
                 .iter()
-                .map(|buf| IoSlice::new(&*(buf.as_slice() as *const _ as *const _)))
+                .map(|buf| buf.as_io_slice().into_std())
                 .collect(),
         )
     }
@@ -99,10 +95,11 @@ impl<T: IoBuf> AsIoSlices for VectoredBufWrapper<T> {
 impl<T: IoBufMut> WrapBufMut for VectoredBufWrapper<T> {
     unsafe fn set_init(&mut self, mut len: usize) {
         for buf in self.buffer.iter_mut() {
-            let capacity = buf.buf_capacity();
-            if len >= capacity {
-                buf.set_buf_init(capacity);
-                len -= capacity;
+            // Only the uninitialized parts are given to the op.
+            let uninit = buf.buf_capacity() - buf.buf_len();
+            if len >= uninit {
+                buf.set_buf_init(uninit);
+                len -= uninit;
             } else {
                 buf.set_buf_init(len);
                 len = 0;
@@ -116,7 +113,7 @@ impl<T: IoBufMut> AsIoSlicesMut for VectoredBufWrapper<T> {
         OneOrVec::Vec(
             self.buffer
                 .iter_mut()
-                .map(|buf| IoSliceMut::new(&mut *(buf.as_uninit_slice() as *mut _ as *mut _)))
+                .map(|buf| buf.as_uninit_io_slice().into_std())
                 .collect(),
         )
     }
@@ -0,0 +1,104 @@
+use std::hash::Hasher;
+
+use crate::buf::*;
+
+/// A buffer wrapper which updates a checksum with the bytes received.
+///
+/// The hasher is updated in [`IoBufMut::set_buf_init`], right after the
+/// runtime reports the bytes initialized by an op, so the data is hashed while
+/// it is still hot in the cache, without another pass over the buffer. Any
+/// [`Hasher`] works, e.g., `crc32fast::Hasher` or the hashers of `twox-hash`.
+///
+/// The bytes initialized before wrapping are not hashed.
+///
+/// ```
+/// use std::hash::Hasher;
+///
+/// use compio::{buf::ChecksummingBuf, fs::File};
+///
+/// # #[derive(Default)]
+/// # struct Sum(u64);
+/// # impl Hasher for Sum {
+/// #     fn finish(&self) -> u64 { self.0 }
+/// #     fn write(&mut self, bytes: &[u8]) {
+/// #         self.0 += bytes.iter().map(|b| *b as u64).sum::<u64>();
+/// #     }
+/// # }
+/// compio::task::block_on(async {
+///     let file = File::open("Cargo.toml").unwrap();
+///     let buffer = ChecksummingBuf::new(Vec::with_capacity(4096), Sum::default());
+///     let (read, buffer) = file.read_at(buffer, 0).await;
+///     let read = read.unwrap();
+///     let expected = buffer.get_ref().iter().map(|b| *b as u64).sum::<u64>();
+///     assert_eq!(buffer.get_ref().len(), read);
+///     assert_eq!(buffer.finish(), expected);
+/// })
+/// ```
+#[derive(Debug)]
+pub struct ChecksummingBuf<B, H> {
+    buffer: B,
+    hasher: H,
+}
+
+impl<B, H> ChecksummingBuf<B, H> {
+    /// Wrap the buffer with the hasher.
+    pub fn new(buffer: B, hasher: H) -> Self {
+        Self { buffer, hasher }
+    }
+
+    /// Get a reference to the buffer.
+    pub fn get_ref(&self) -> &B {
+        &self.buffer
+    }
+
+    /// Get a reference to the hasher.
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// Get the buffer and the hasher.
+    pub fn into_parts(self) -> (B, H) {
+        (self.buffer, self.hasher)
+    }
+}
+
+impl<B, H: Hasher> ChecksummingBuf<B, H> {
+    /// The checksum of the bytes received.
+    pub fn finish(&self) -> u64 {
+        self.hasher.finish()
+    }
+}
+
+unsafe impl<B: IoBuf, H: 'static> IoBuf for ChecksummingBuf<B, H> {
+    fn as_buf_ptr(&self) -> *const u8 {
+        self.buffer.as_buf_ptr()
+    }
+
+    fn buf_len(&self) -> usize {
+        self.buffer.buf_len()
+    }
+
+    fn buf_capacity(&self) -> usize {
+        self.buffer.buf_capacity()
+    }
+}
+
+unsafe impl<B: IoBufMut, H: Hasher + 'static> IoBufMut for ChecksummingBuf<B, H> {
+    fn as_buf_mut_ptr(&mut self) -> *mut u8 {
+        self.buffer.as_buf_mut_ptr()
+    }
+
+    unsafe fn set_buf_init(&mut self, len: usize) {
+        let start = self.buffer.buf_len();
+        self.buffer.set_buf_init(len);
+        self.hasher.write(&self.buffer.as_slice()[start..start + len]);
+    }
+}
+
+impl<B, H> IntoInner for ChecksummingBuf<B, H> {
+    type Inner = B;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout},
+    io,
+    marker::PhantomData,
+    ptr::NonNull,
+};
+
+use bytemuck::AnyBitPattern;
+
+use crate::buf::{IoBuf, IoBufMut};
+
+/// A zeroed allocation with a specified alignment.
+struct RawPod {
+    ptr: NonNull<u8>,
+    layout: Layout,
+    init: usize,
+}
+
+impl RawPod {
+    fn new(layout: Layout) -> Self {
+        let ptr = if layout.size() == 0 {
+            // A dangling pointer with the required alignment.
+            unsafe { NonNull::new_unchecked(layout.align() as *mut u8) }
+        } else {
+            let ptr = unsafe { alloc_zeroed(layout) };
+            NonNull::new(ptr).unwrap_or_else(|| handle_alloc_error(layout))
+        };
+        Self {
+            ptr,
+            layout,
+            init: 0,
+        }
+    }
+
+    fn check_filled(&self) -> io::Result<()> {
+        if self.init < self.layout.size() {
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the buffer is not fully filled",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for RawPod {
+    fn drop(&mut self) {
+        if self.layout.size() != 0 {
+            unsafe { dealloc(self.ptr.as_ptr(), self.layout) }
+        }
+    }
+}
+
+fn pod_layout<T>(len: usize, align: usize) -> Layout {
+    Layout::array::<T>(len)
+        .and_then(|layout| layout.align_to(align))
+        .expect("invalid layout")
+}
+
+macro_rules! impl_pod_buf {
+    ($t:ident) => {
+        unsafe impl<T: AnyBitPattern> IoBuf for $t<T> {
+            fn as_buf_ptr(&self) -> *const u8 {
+                self.raw.ptr.as_ptr()
+            }
+
+            fn buf_len(&self) -> usize {
+                self.raw.init
+            }
+
+            fn buf_capacity(&self) -> usize {
+                self.raw.layout.size()
+            }
+        }
+
+        unsafe impl<T: AnyBitPattern> IoBufMut for $t<T> {
+            fn as_buf_mut_ptr(&mut self) -> *mut u8 {
+                self.raw.ptr.as_ptr()
+            }
+
+            unsafe fn set_buf_init(&mut self, len: usize) {
+                self.raw.init += len;
+            }
+        }
+
+        unsafe impl<T: AnyBitPattern + Send> Send for $t<T> {}
+        unsafe impl<T: AnyBitPattern + Sync> Sync for $t<T> {}
+    };
+}
+
+/// A buffer to read a plain-old-data value directly.
+///
+/// The memory is aligned for `T`, or a larger alignment specified with
+/// [`PodBuf::with_align`], e.g., the block size required by `O_DIRECT`. The
+/// value is returned by [`PodBuf::into_inner`] only if all bytes of it are
+/// filled. The endianness of the fields is not handled.
+///
+/// ```
+/// use bytemuck::{Pod, Zeroable};
+/// use compio::{buf::PodBuf, fs::File};
+///
+/// #[derive(Clone, Copy, Pod, Zeroable)]
+/// #[repr(C, packed)]
+/// struct Header {
+///     magic: [u8; 4],
+///     version: u16,
+///     len: u32,
+/// }
+///
+/// # let temp = tempfile::NamedTempFile::new().unwrap();
+/// # std::fs::write(temp.path(), b"PODB\x01\x00\x10\x00\x00\x00").unwrap();
+/// # let path = temp.path();
+/// compio::task::block_on(async {
+///     let file = File::open(path).unwrap();
+///     let (res, buffer) = file.read_exact_at(PodBuf::<Header>::new(), 0).await;
+///     res.unwrap();
+///     let header = buffer.into_inner().unwrap();
+///     assert_eq!(&header.magic, b"PODB");
+/// })
+/// ```
+pub struct PodBuf<T: AnyBitPattern> {
+    raw: RawPod,
+    _p: PhantomData<T>,
+}
+
+impl<T: AnyBitPattern> PodBuf<T> {
+    /// Create [`PodBuf`] aligned for `T`.
+    pub fn new() -> Self {
+        Self::with_align(std::mem::align_of::<T>())
+    }
+
+    /// Create [`PodBuf`] aligned to the larger one of `align` and the
+    /// alignment of `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    pub fn with_align(align: usize) -> Self {
+        Self {
+            raw: RawPod::new(pod_layout::<T>(1, align)),
+            _p: PhantomData,
+        }
+    }
+
+    /// Get the value. Returns an error of the kind
+    /// [`io::ErrorKind::UnexpectedEof`] if it is not fully filled.
+    pub fn into_inner(self) -> io::Result<T> {
+        self.raw.check_filled()?;
+        Ok(unsafe { self.raw.ptr.cast::<T>().as_ptr().read() })
+    }
+}
+
+impl<T: AnyBitPattern> Default for PodBuf<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl_pod_buf!(PodBuf);
+
+/// A buffer to read an array of plain-old-data values directly.
+///
+/// See [`PodBuf`].
+pub struct PodVec<T: AnyBitPattern> {
+    raw: RawPod,
+    len: usize,
+    _p: PhantomData<T>,
+}
+
+impl<T: AnyBitPattern> PodVec<T> {
+    /// Create [`PodVec`] with `len` values, aligned for `T`.
+    pub fn new(len: usize) -> Self {
+        Self::with_align(len, std::mem::align_of::<T>())
+    }
+
+    /// Create [`PodVec`] with `len` values, aligned to the larger one of
+    /// `align` and the alignment of `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two, or the size overflows.
+    pub fn with_align(len: usize, align: usize) -> Self {
+        Self {
+            raw: RawPod::new(pod_layout::<T>(len, align)),
+            len,
+            _p: PhantomData,
+        }
+    }
+
+    /// Get the values. Returns an error of the kind
+    /// [`io::ErrorKind::UnexpectedEof`] if they are not fully filled.
+    pub fn into_inner(self) -> io::Result<Vec<T>> {
+        self.raw.check_filled()?;
+        let layout = self.raw.layout;
+        if layout.size() != 0 && layout.align() == std::mem::align_of::<T>() {
+            // The layout is the same as the one used by `Vec`, so the
+            // allocation could be transferred.
+            let this = std::mem::ManuallyDrop::new(self);
+            Ok(unsafe { Vec::from_raw_parts(this.raw.ptr.cast().as_ptr(), this.len, this.len) })
+        } else {
+            let values =
+                unsafe { std::slice::from_raw_parts(self.raw.ptr.cast().as_ptr(), self.len) };
+            Ok(values.to_vec())
+        }
+    }
+}
+
+impl_pod_buf!(PodVec);
@@ -0,0 +1,253 @@
+//! A thread-local pool of buffers.
+//!
+//! There is only one runtime in each thread, so the pool belongs to the
+//! runtime of the current thread, and needs no synchronization. The buffers
+//! are segregated by size classes of powers of two. A [`PooledBuf`] is
+//! returned to the pool of the thread where it is dropped, if the pool is not
+//! full.
+//!
+//! ```
+//! use compio::buf::pool;
+//!
+//! compio::task::block_on(async {
+//!     let file = compio::fs::File::open("Cargo.toml").unwrap();
+//!     let (res, buf) = file.read_at(pool::acquire(64), 0).await;
+//!     assert_eq!(res.unwrap(), buf.len());
+//!     drop(buf);
+//!
+//!     // The storage is reused.
+//!     let buf = pool::acquire(50);
+//!     assert_eq!(buf.capacity(), 64);
+//!     assert_eq!(pool::stats().hits, 1);
+//! })
+//! ```
+
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+};
+
+use crate::buf::{IoBuf, IoBufMut};
+
+// The smallest size class.
+const MIN_CLASS: usize = 64;
+const DEFAULT_MAX_POOLED_BYTES: usize = 16 * 1024 * 1024;
+
+thread_local! {
+    static POOL: RefCell<Pool> = RefCell::new(Pool::new());
+}
+
+/// Statistics of the pool of the current thread, see [`stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStats {
+    /// The count of the buffers acquired from the pool.
+    pub hits: u64,
+    /// The count of the buffers allocated because the pool has no buffer of
+    /// the size class.
+    pub misses: u64,
+    /// The count of the buffers freed on drop because the pool is full.
+    pub discards: u64,
+    /// The total capacity of the buffers in the pool.
+    pub pooled_bytes: usize,
+}
+
+impl PoolStats {
+    /// The ratio of the hits to all acquisitions.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+struct Pool {
+    // Indexed by the exponent of the size class.
+    classes: Vec<Vec<Vec<u8>>>,
+    max_pooled_bytes: usize,
+    stats: PoolStats,
+}
+
+impl Pool {
+    fn new() -> Self {
+        Self {
+            classes: Vec::new(),
+            max_pooled_bytes: DEFAULT_MAX_POOLED_BYTES,
+            stats: PoolStats::default(),
+        }
+    }
+
+    fn acquire(&mut self, class: usize) -> Vec<u8> {
+        let buffers = self.classes.get_mut(class.trailing_zeros() as usize);
+        match buffers.and_then(|buffers| buffers.pop()) {
+            Some(buffer) => {
+                self.stats.hits += 1;
+                self.stats.pooled_bytes -= buffer.capacity();
+                buffer
+            }
+            None => {
+                self.stats.misses += 1;
+                Vec::with_capacity(class)
+            }
+        }
+    }
+
+    fn release(&mut self, mut buffer: Vec<u8>) {
+        let class = buffer.capacity();
+        if !class.is_power_of_two() || self.stats.pooled_bytes + class > self.max_pooled_bytes {
+            self.stats.discards += 1;
+            return;
+        }
+        let index = class.trailing_zeros() as usize;
+        if self.classes.len() <= index {
+            self.classes.resize_with(index + 1, Vec::new);
+        }
+        buffer.clear();
+        self.classes[index].push(buffer);
+        self.stats.pooled_bytes += class;
+    }
+
+    // Free the largest buffers first.
+    fn trim(&mut self) {
+        for buffers in self.classes.iter_mut().rev() {
+            while self.stats.pooled_bytes > self.max_pooled_bytes {
+                let Some(buffer) = buffers.pop() else {
+                    break;
+                };
+                self.stats.pooled_bytes -= buffer.capacity();
+            }
+        }
+    }
+}
+
+/// Acquire a buffer with capacity of at least `size` bytes from the pool of
+/// the current thread, or allocate one if the pool has none. The buffer is
+/// empty, and its capacity is `size` rounded up to a power of two.
+pub fn acquire(size: usize) -> PooledBuf {
+    let class = size.max(MIN_CLASS).next_power_of_two();
+    let buffer = POOL
+        .try_with(|pool| pool.borrow_mut().acquire(class))
+        .unwrap_or_else(|_| Vec::with_capacity(class));
+    PooledBuf { buffer }
+}
+
+/// Limit the total capacity of the buffers in the pool of the current thread.
+/// The buffers dropped when the pool is full are freed. Defaults to 16 MiB.
+pub fn set_max_pooled_bytes(bytes: usize) {
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        pool.max_pooled_bytes = bytes;
+        pool.trim();
+    })
+}
+
+/// The statistics of the pool of the current thread.
+pub fn stats() -> PoolStats {
+    POOL.with(|pool| pool.borrow().stats)
+}
+
+/// A buffer from [`acquire`], which works like a [`Vec<u8>`] with fixed
+/// capacity in the operations.
+///
+/// The storage is returned to the pool of the current thread when it is
+/// dropped. It is fine to drop it after the thread-local pool is destroyed,
+/// and the storage is freed then. Call [`PooledBuf::into_vec`] to keep the
+/// storage.
+pub struct PooledBuf {
+    buffer: Vec<u8>,
+}
+
+impl PooledBuf {
+    /// Take the storage out of the pool.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer)
+    }
+
+    /// The capacity of the buffer, which is a power of two.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Clear the buffer, keeping the capacity.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Append the data to the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the data exceeds the remaining capacity.
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        assert!(
+            data.len() <= self.buffer.capacity() - self.buffer.len(),
+            "exceeds the capacity of the pooled buffer"
+        );
+        self.buffer.extend_from_slice(data);
+    }
+}
+
+impl Deref for PooledBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+impl DerefMut for PooledBuf {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buffer
+    }
+}
+
+impl AsRef<[u8]> for PooledBuf {
+    fn as_ref(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+impl Debug for PooledBuf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("PooledBuf").field(&self.buffer).finish()
+    }
+}
+
+impl Drop for PooledBuf {
+    fn drop(&mut self) {
+        if self.buffer.capacity() == 0 {
+            return;
+        }
+        let buffer = std::mem::take(&mut self.buffer);
+        // The pool may have been destroyed with the thread locals, and the buffer
+        // is freed here.
+        POOL.try_with(|pool| pool.borrow_mut().release(buffer)).ok();
+    }
+}
+
+unsafe impl IoBuf for PooledBuf {
+    fn as_buf_ptr(&self) -> *const u8 {
+        self.buffer.as_ptr()
+    }
+
+    fn buf_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn buf_capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+}
+
+unsafe impl IoBufMut for PooledBuf {
+    fn as_buf_mut_ptr(&mut self) -> *mut u8 {
+        self.buffer.as_mut_ptr()
+    }
+
+    unsafe fn set_buf_init(&mut self, len: usize) {
+        self.buffer.set_len(len + self.buffer.len());
+    }
+}
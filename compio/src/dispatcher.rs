@@ -0,0 +1,590 @@
+//! Dispatch the jobs to a pool of worker threads, each running its own
+//! runtime, e.g., one per core.
+//!
+//! Each worker starts with [`DispatcherBuilder::on_worker_start`], whose
+//! output is the state of the worker, e.g., a shard of a cache or a listener
+//! of [`TcpListener::bind_reuseport_sharded`]. The state is passed by
+//! reference to every job dispatched to the worker, and to
+//! [`DispatcherBuilder::on_worker_stop`] at last, before the runtime of the
+//! worker is dropped.
+//!
+//! ```
+//! use std::cell::Cell;
+//!
+//! use compio::dispatcher::Dispatcher;
+//!
+//! let dispatcher = Dispatcher::builder()
+//!     .worker_threads(2)
+//!     .on_worker_start(|_ctx| async { Cell::new(0) })
+//!     .on_worker_stop(|ctx, count: Cell<u32>| async move {
+//!         println!("worker {} served {} jobs", ctx.index(), count.get());
+//!     })
+//!     .build()
+//!     .unwrap();
+//! let handles = (0..4)
+//!     .map(|i| {
+//!         dispatcher
+//!             .dispatch(move |count: &Cell<u32>| {
+//!                 count.set(count.get() + 1);
+//!                 async move { i * 2 }
+//!             })
+//!             .unwrap()
+//!     })
+//!     .collect::<Vec<_>>();
+//! let sum = handles.into_iter().map(|h| h.join_blocking()).sum::<i32>();
+//! assert_eq!(sum, 12);
+//! dispatcher.join().unwrap();
+//! ```
+//!
+//! [`TcpListener::bind_reuseport_sharded`]: crate::net::TcpListener::bind_reuseport_sharded
+
+use std::{
+    any::Any,
+    collections::VecDeque,
+    error::Error,
+    fmt::{Debug, Display},
+    future::Future,
+    io,
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Condvar, Mutex,
+    },
+    task::{Context, Poll},
+    thread,
+};
+
+use async_task::Task;
+use futures_util::{future::LocalBoxFuture, FutureExt};
+
+use crate::{
+    event::{Event, EventHandle},
+    task::{block_on, runtime::running_block_on, spawn},
+};
+
+type StartFn<S> = dyn Fn(WorkerContext) -> LocalBoxFuture<'static, S> + Send + Sync;
+type StopFn<S> = dyn Fn(WorkerContext, S) -> LocalBoxFuture<'static, ()> + Send + Sync;
+type Job<S> = Box<dyn FnOnce(&S) -> LocalBoxFuture<'static, ()> + Send>;
+
+/// The worker passed to the hooks of [`DispatcherBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkerContext {
+    index: usize,
+    workers: usize,
+}
+
+impl WorkerContext {
+    /// The index of the worker, from 0.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The count of the workers of the dispatcher.
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+}
+
+/// The builder of [`Dispatcher`], with the type of the state of the workers.
+pub struct DispatcherBuilder<S = ()> {
+    workers: usize,
+    names: Option<Arc<dyn Fn(usize) -> String + Send + Sync>>,
+    on_start: Arc<StartFn<S>>,
+    on_stop: Option<Arc<StopFn<S>>>,
+}
+
+impl DispatcherBuilder {
+    /// Create the builder with a worker per core, and no state.
+    pub fn new() -> Self {
+        Self {
+            workers: thread::available_parallelism().map_or(1, |n| n.get()),
+            names: None,
+            on_start: Arc::new(|_: WorkerContext| async {}.boxed_local()),
+            on_stop: None,
+        }
+    }
+}
+
+impl Default for DispatcherBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: 'static> DispatcherBuilder<S> {
+    /// Set the count of the worker threads.
+    pub fn worker_threads(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Set the names of the worker threads by their indices. They are named
+    /// `compio-worker-{index}` by default.
+    pub fn thread_names(mut self, f: impl Fn(usize) -> String + Send + Sync + 'static) -> Self {
+        self.names = Some(Arc::new(f));
+        self
+    }
+
+    /// Set the hook awaited by each worker in its runtime when it starts. The
+    /// output is the state of the worker, passed by reference to the jobs
+    /// dispatched to it.
+    ///
+    /// [`DispatcherBuilder::build`] waits for all the workers to start, and
+    /// fails if any of them panics in the hook. It replaces the hook set by
+    /// [`DispatcherBuilder::on_worker_stop`], because the type of the state
+    /// changes.
+    pub fn on_worker_start<T, F, Fut>(self, f: F) -> DispatcherBuilder<T>
+    where
+        F: Fn(WorkerContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = T> + 'static,
+    {
+        DispatcherBuilder {
+            workers: self.workers,
+            names: self.names,
+            on_start: Arc::new(move |ctx| f(ctx).boxed_local()),
+            on_stop: None,
+        }
+    }
+
+    /// Set the hook awaited by each worker in its runtime when it stops, with
+    /// the state of the worker. It runs after the jobs dispatched to the
+    /// worker complete, and before the runtime is dropped, e.g., to flush the
+    /// buffered data.
+    pub fn on_worker_stop<F, Fut>(mut self, f: F) -> Self
+    where
+        F: Fn(WorkerContext, S) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.on_stop = Some(Arc::new(move |ctx, state| f(ctx, state).boxed_local()));
+        self
+    }
+
+    /// Spawn the workers, and wait for them to start.
+    ///
+    /// It fails with [`io::ErrorKind::InvalidInput`] if there is no worker,
+    /// and with a [`WorkerError`] if a worker panics when it starts. The
+    /// started workers are stopped then.
+    pub fn build(self) -> io::Result<Dispatcher<S>> {
+        if self.workers == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the dispatcher needs at least one worker",
+            ));
+        }
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let mut dispatcher = Dispatcher {
+            workers: Vec::with_capacity(self.workers),
+            next: AtomicUsize::new(0),
+        };
+        for index in 0..self.workers {
+            let ctx = WorkerContext {
+                index,
+                workers: self.workers,
+            };
+            let name = match &self.names {
+                Some(names) => names(index),
+                None => format!("compio-worker-{index}"),
+            };
+            let inbox = Arc::new(Inbox::default());
+            let worker = {
+                let inbox = inbox.clone();
+                let on_start = self.on_start.clone();
+                let on_stop = self.on_stop.clone();
+                let ready = ready_tx.clone();
+                thread::Builder::new()
+                    .name(name)
+                    .spawn(move || run_worker(ctx, &inbox, &*on_start, on_stop.as_deref(), ready))?
+            };
+            dispatcher.workers.push(Worker {
+                inbox,
+                thread: Some(worker),
+            });
+        }
+        drop(ready_tx);
+
+        let mut error = None;
+        for (index, res) in ready_rx {
+            if let Err(message) = res {
+                error.get_or_insert(WorkerError { index, message });
+            }
+        }
+        match error {
+            Some(e) => {
+                dispatcher.join().ok();
+                Err(io::Error::other(e))
+            }
+            None => Ok(dispatcher),
+        }
+    }
+}
+
+impl<S> Debug for DispatcherBuilder<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DispatcherBuilder")
+            .field("workers", &self.workers)
+            .field("on_stop", &self.on_stop.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+// The jobs waiting for a worker, and the event it waits on when idle.
+struct Inbox<S> {
+    state: Mutex<InboxState<S>>,
+}
+
+struct InboxState<S> {
+    jobs: VecDeque<Job<S>>,
+    stopped: bool,
+    waiting: Option<EventHandle>,
+}
+
+impl<S> Default for Inbox<S> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(InboxState {
+                jobs: VecDeque::new(),
+                stopped: false,
+                waiting: None,
+            }),
+        }
+    }
+}
+
+impl<S> Inbox<S> {
+    // Wake the worker if it is idle.
+    fn push(&self, f: impl FnOnce(&mut InboxState<S>)) {
+        let mut state = self.state.lock().unwrap();
+        f(&mut state);
+        let waiting = state.waiting.take();
+        drop(state);
+        if let Some(handle) = waiting {
+            handle.notify().ok();
+        }
+    }
+
+    // Take the jobs, or wait for them. It returns `None` when stopped.
+    async fn recv(&self) -> Option<VecDeque<Job<S>>> {
+        loop {
+            let event = Event::new().expect("cannot create the event of the worker");
+            let handle = event
+                .handle()
+                .expect("cannot create the event of the worker");
+            {
+                let mut state = self.state.lock().unwrap();
+                if !state.jobs.is_empty() {
+                    return Some(std::mem::take(&mut state.jobs));
+                }
+                if state.stopped {
+                    return None;
+                }
+                state.waiting = Some(handle);
+            }
+            event.wait().await.expect("cannot wait for the jobs");
+        }
+    }
+}
+
+fn run_worker<S: 'static>(
+    ctx: WorkerContext,
+    inbox: &Inbox<S>,
+    on_start: &StartFn<S>,
+    on_stop: Option<&StopFn<S>>,
+    ready: mpsc::Sender<(usize, Result<(), String>)>,
+) {
+    let state = match catch_unwind(AssertUnwindSafe(|| block_on(on_start(ctx)))) {
+        Ok(state) => state,
+        Err(payload) => {
+            ready.send((ctx.index, Err(panic_message(&*payload)))).ok();
+            return;
+        }
+    };
+    ready.send((ctx.index, Ok(()))).ok();
+    drop(ready);
+
+    block_on(async {
+        let mut tasks: Vec<Task<()>> = vec![];
+        while let Some(jobs) = inbox.recv().await {
+            tasks.retain(|task| !task.is_finished());
+            tasks.extend(jobs.into_iter().map(|job| spawn(job(&state))));
+        }
+        for task in tasks {
+            task.await;
+        }
+        if let Some(on_stop) = on_stop {
+            on_stop(ctx, state).await;
+        }
+    })
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+struct Worker<S> {
+    inbox: Arc<Inbox<S>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+/// A pool of worker threads, each running its own runtime, see the
+/// [module-level documentation](self).
+///
+/// Dropping the dispatcher stops the workers without waiting for them, see
+/// [`Dispatcher::join`].
+pub struct Dispatcher<S = ()> {
+    workers: Vec<Worker<S>>,
+    next: AtomicUsize,
+}
+
+impl Dispatcher {
+    /// Create the builder, see [`DispatcherBuilder::new`].
+    pub fn builder() -> DispatcherBuilder {
+        DispatcherBuilder::new()
+    }
+}
+
+impl<S> Dispatcher<S> {
+    /// The count of the workers.
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Dispatch a job to a worker, in turn. `f` is called in the worker with
+    /// its state, and the future returned is spawned in the runtime of the
+    /// worker. The output is sent back with the returned [`JoinHandle`].
+    ///
+    /// It fails with [`io::ErrorKind::BrokenPipe`] if the worker has
+    /// stopped, e.g., because it panicked.
+    pub fn dispatch<F, Fut>(&self, f: F) -> io::Result<JoinHandle<Fut::Output>>
+    where
+        F: FnOnce(&S) -> Fut + Send + 'static,
+        Fut: Future + 'static,
+        Fut::Output: Send + 'static,
+    {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.workers.len();
+        let worker = &self.workers[index];
+        if worker.thread.as_ref().is_none_or(|t| t.is_finished()) {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                format!("the worker {index} has stopped"),
+            ));
+        }
+        let slot = Arc::new(Slot::default());
+        let completer = Completer(Some(slot.clone()));
+        let job: Job<S> = Box::new(move |state| {
+            let future = AssertUnwindSafe(f(state)).catch_unwind();
+            async move { completer.complete(future.await) }.boxed_local()
+        });
+        worker.inbox.push(|state| state.jobs.push_back(job));
+        Ok(JoinHandle { slot, wait: None })
+    }
+
+    /// Stop the workers, and block current thread until they exit. The jobs
+    /// dispatched before are completed, and then the stop hooks are awaited.
+    ///
+    /// It fails with a [`WorkerError`] if a worker panicked, e.g., in the
+    /// stop hook.
+    pub fn join(mut self) -> io::Result<()> {
+        self.stop();
+        let mut error = None;
+        for (index, worker) in self.workers.iter_mut().enumerate() {
+            if let Some(Err(payload)) = worker.thread.take().map(|t| t.join()) {
+                error.get_or_insert(WorkerError {
+                    index,
+                    message: panic_message(&*payload),
+                });
+            }
+        }
+        match error {
+            Some(e) => Err(io::Error::other(e)),
+            None => Ok(()),
+        }
+    }
+
+    fn stop(&self) {
+        for worker in &self.workers {
+            worker.inbox.push(|state| state.stopped = true);
+        }
+    }
+}
+
+impl<S> Drop for Dispatcher<S> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl<S> Debug for Dispatcher<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dispatcher")
+            .field("workers", &self.workers.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A worker panicked, when it starts or stops.
+#[derive(Debug)]
+pub struct WorkerError {
+    index: usize,
+    message: String,
+}
+
+impl WorkerError {
+    /// The index of the worker.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The message of the panic.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for WorkerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the worker {} panicked: {}", self.index, self.message)
+    }
+}
+
+impl Error for WorkerError {}
+
+// The output of a job, and the event of the runtime waiting for it.
+struct Slot<T> {
+    state: Mutex<SlotState<T>>,
+    done: Condvar,
+}
+
+struct SlotState<T> {
+    output: Option<thread::Result<T>>,
+    waiting: Option<EventHandle>,
+}
+
+impl<T> Default for Slot<T> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(SlotState {
+                output: None,
+                waiting: None,
+            }),
+            done: Condvar::new(),
+        }
+    }
+}
+
+// Stores the output of a job, or a panic if the job is dropped without
+// completing, e.g., the worker panicked.
+struct Completer<T>(Option<Arc<Slot<T>>>);
+
+impl<T> Completer<T> {
+    fn complete(mut self, output: thread::Result<T>) {
+        if let Some(slot) = self.0.take() {
+            let mut state = slot.state.lock().unwrap();
+            state.output = Some(output);
+            let waiting = state.waiting.take();
+            drop(state);
+            slot.done.notify_all();
+            if let Some(handle) = waiting {
+                handle.notify().ok();
+            }
+        }
+    }
+}
+
+impl<T> Drop for Completer<T> {
+    fn drop(&mut self) {
+        if self.0.is_some() {
+            let payload: Box<dyn Any + Send> = Box::new("the job is dropped by the worker");
+            Completer(self.0.take()).complete(Err(payload));
+        }
+    }
+}
+
+/// The handle of a job dispatched by [`Dispatcher::dispatch`], which could be
+/// awaited in any runtime, or joined by [`JoinHandle::join_blocking`] outside
+/// of the runtimes. It resumes the panic of the job, if any.
+///
+/// Dropping the handle doesn't cancel the job.
+pub struct JoinHandle<T> {
+    slot: Arc<Slot<T>>,
+    wait: Option<Pin<Box<dyn Future<Output = io::Result<()>>>>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Whether the job has completed.
+    pub fn is_finished(&self) -> bool {
+        self.slot.state.lock().unwrap().output.is_some()
+    }
+
+    /// Block current thread until the job completes, and return its output.
+    ///
+    /// # Panics
+    ///
+    /// It panics if called inside [`block_on`], because blocking the thread
+    /// stalls all the tasks of the runtime. Await the handle there instead.
+    pub fn join_blocking(self) -> T {
+        if let Some(location) = running_block_on() {
+            panic!(
+                "cannot call `join_blocking` in the `block_on` at {location}, which blocks the \
+                 runtime of current thread; await the handle instead"
+            );
+        }
+        let mut state = self.slot.state.lock().unwrap();
+        let res = loop {
+            match state.output.take() {
+                Some(res) => break res,
+                None => state = self.slot.done.wait(state).unwrap(),
+            }
+        };
+        match res {
+            Ok(output) => output,
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            if let Some(wait) = &mut self.wait {
+                match wait.as_mut().poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(res) => res.expect("cannot wait for the dispatched job"),
+                }
+                self.wait = None;
+            }
+            let mut state = self.slot.state.lock().unwrap();
+            if let Some(res) = state.output.take() {
+                return match res {
+                    Ok(output) => Poll::Ready(output),
+                    Err(payload) => resume_unwind(payload),
+                };
+            }
+            // Wait for the event notified after the output is stored.
+            let event = Event::new().expect("cannot create the event of the dispatched job");
+            state.waiting = Some(
+                event
+                    .handle()
+                    .expect("cannot create the event of the dispatched job"),
+            );
+            drop(state);
+            self.wait = Some(Box::pin(async move { event.wait().await }));
+        }
+    }
+}
+
+impl<T> Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JoinHandle")
+            .field("finished", &self.is_finished())
+            .finish_non_exhaustive()
+    }
+}
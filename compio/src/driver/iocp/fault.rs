@@ -0,0 +1,85 @@
+//! The errors injected into the syscalls, to test the handling of the errors
+//! hard to reproduce, e.g., the transient errors of winsock.
+
+use std::{cell::RefCell, collections::HashMap, io};
+
+use windows_sys::Win32::Networking::WinSock::WSAEWOULDBLOCK;
+
+type RandomFn = Box<dyn FnMut() -> u64>;
+
+thread_local! {
+    static INJECTED: RefCell<HashMap<&'static str, (i32, usize)>> = RefCell::new(HashMap::new());
+    static SEEDED: RefCell<Option<RandomFn>> = RefCell::new(None);
+}
+
+// The receives and sends failing with the seeded transient errors.
+const SEEDED_SYSCALLS: [&str; 4] = ["WSARecv", "WSASend", "WSARecvFrom", "WSASendTo"];
+
+// The error injected into the next call of the syscall, if any.
+pub(crate) fn take_injected_error(name: &'static str) -> Option<io::Error> {
+    let code = INJECTED.with_borrow_mut(|injected| {
+        let (code, times) = injected.get_mut(name)?;
+        let code = *code;
+        *times -= 1;
+        if *times == 0 {
+            injected.remove(name);
+        }
+        Some(code)
+    });
+    code.or_else(|| seeded_error(name))
+        .map(io::Error::from_raw_os_error)
+}
+
+fn seeded_error(name: &'static str) -> Option<i32> {
+    if !SEEDED_SYSCALLS.contains(&name) {
+        return None;
+    }
+    SEEDED.with_borrow_mut(|next| {
+        let next = next.as_mut()?;
+        (next() % 8 == 0).then_some(WSAEWOULDBLOCK)
+    })
+}
+
+// Fail one in 8 calls of the socket receives and sends on the current thread
+// with `WSAEWOULDBLOCK`, chosen by the random numbers of `next`, for the
+// deterministic mode of the runtime. They are performed again after a backoff
+// by default, see `TransientErrors`, so the latencies follow the seed.
+#[cfg(feature = "runtime")]
+pub(crate) fn inject_seeded_errors(next: impl FnMut() -> u64 + 'static) {
+    SEEDED.set(Some(Box::new(next)));
+}
+
+/// Make the next `times` calls of the syscall on the current thread fail with
+/// the raw error `code`, without being performed. The syscall is named by the
+/// function called, e.g., `"WSASendTo"`, the same as the syscall counters.
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use compio::net::UdpSocket;
+/// use windows_sys::Win32::Networking::WinSock::WSAEACCES;
+///
+/// compio::task::block_on(async {
+///     let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     let addr = socket.local_addr().unwrap();
+///     compio::driver::inject_syscall_error("WSASendTo", WSAEACCES, 1);
+///     let e = socket.send_to("ping", addr).await.0.unwrap_err();
+///     assert_eq!(e.raw_os_error(), Some(WSAEACCES));
+///     socket.send_to("ping", addr).await.0.unwrap();
+/// })
+/// ```
+pub fn inject_syscall_error(name: &'static str, code: i32, times: usize) {
+    INJECTED.with_borrow_mut(|injected| {
+        if times == 0 {
+            injected.remove(name);
+        } else {
+            injected.insert(name, (code, times));
+        }
+    })
+}
+
+/// Clear the errors injected on the current thread, see
+/// [`inject_syscall_error`].
+pub fn clear_injected_errors() {
+    INJECTED.with_borrow_mut(HashMap::clear)
+}
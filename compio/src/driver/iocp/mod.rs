@@ -1,5 +1,7 @@
 use std::{
-    collections::HashSet,
+    alloc::Layout,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Debug,
     io,
     mem::ManuallyDrop,
     os::windows::prelude::{
@@ -8,8 +10,10 @@ use std::{
     },
     pin::Pin,
     ptr::{null_mut, NonNull},
+    rc::Rc,
+    sync::{Arc, Mutex},
     task::Poll,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use arrayvec::ArrayVec;
@@ -30,13 +34,25 @@ use windows_sys::Win32::{
     },
 };
 
-use crate::{driver::Entry, syscall};
+use crate::{
+    driver::{op_pool::OpPool, Entry, PollingMode, ProactorBuilder},
+    syscall,
+};
 
+#[cfg(feature = "fault-injection")]
+mod fault;
+#[cfg(all(feature = "fault-injection", feature = "runtime"))]
+pub(crate) use fault::inject_seeded_errors;
+#[cfg(feature = "fault-injection")]
+pub(crate) use fault::take_injected_error;
+#[cfg(feature = "fault-injection")]
+pub use fault::{clear_injected_errors, inject_syscall_error};
 pub(crate) mod op;
+mod transient;
+pub use transient::{TransientError, TransientErrors};
 
-pub(crate) use windows_sys::Win32::Networking::WinSock::{
-    socklen_t, SOCKADDR_STORAGE as sockaddr_storage,
-};
+pub(crate) use socket2::SockAddrStorage as sockaddr_storage;
+pub(crate) use windows_sys::Win32::Networking::WinSock::socklen_t;
 
 /// On windows, handle and socket are in the same size.
 /// Both of them could be attached to an IOCP.
@@ -125,23 +141,115 @@ pub trait OpCode {
     ///
     /// * Should not use [`Overlapped::op`].
     unsafe fn cancel(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> io::Result<()>;
+
+    /// Get the result of the operation from the completion entry. By default,
+    /// the result of the entry is returned unchanged.
+    fn on_complete(self: Pin<&mut Self>, result: io::Result<usize>) -> io::Result<usize> {
+        result
+    }
+
+    /// The kind of the raw error if it is transient, and the operation could
+    /// be performed again, see [`TransientErrors`]. By default, no error is
+    /// transient.
+    fn transient_error(&self, _code: i32) -> Option<io::ErrorKind> {
+        None
+    }
+}
+
+/// A handle to post messages to a driver from other threads.
+#[derive(Debug, Clone)]
+pub struct MessageSender {
+    port: Arc<OwnedHandle>,
+    mailbox: Arc<Mutex<VecDeque<u64>>>,
+}
+
+impl MessageSender {
+    pub(crate) fn send(&self, msg: u64) -> io::Result<()> {
+        self.mailbox.lock().unwrap().push_back(msg);
+        syscall!(
+            BOOL,
+            PostQueuedCompletionStatus(
+                self.port.as_raw_handle() as _,
+                0,
+                Driver::MESSAGE,
+                null_mut()
+            )
+        )?;
+        Ok(())
+    }
+}
+
+/// The callback of the completion packets not posted by compio, see
+/// [`ProactorBuilder::on_foreign_completion`].
+#[derive(Clone)]
+pub(crate) struct ForeignCompletion(Arc<dyn Fn(OVERLAPPED_ENTRY) + Send + Sync>);
+
+impl ForeignCompletion {
+    pub(crate) fn new(f: impl Fn(OVERLAPPED_ENTRY) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+impl Debug for ForeignCompletion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ForeignCompletion").finish_non_exhaustive()
+    }
+}
+
+// An operation failed with a transient error, and performed again.
+struct Retry {
+    // The retries performed, including the one waiting.
+    retries: u32,
+    // The time to perform it again, or `None` if it is in flight.
+    at: Option<Instant>,
 }
 
 /// Low-level driver of IOCP.
 pub(crate) struct Driver {
-    port: OwnedHandle,
+    port: Arc<OwnedHandle>,
     cancelled: HashSet<usize>,
+    mailbox: Arc<Mutex<VecDeque<u64>>>,
+    on_foreign_completion: Option<ForeignCompletion>,
+    transient_errors: TransientErrors,
+    retries: HashMap<usize, Retry>,
 }
 
 impl Driver {
     const DEFAULT_CAPACITY: usize = 1024;
-
-    pub fn new(_entries: u32) -> io::Result<Self> {
-        let port = syscall!(BOOL, CreateIoCompletionPort(INVALID_HANDLE_VALUE, 0, 0, 0))?;
-        let port = unsafe { OwnedHandle::from_raw_handle(port as _) };
+    // The completion keys reserved by the driver. The port may be shared with
+    // other code, and the packets with other keys are foreign.
+    //
+    // The completion key posted by `MessageSender`.
+    const MESSAGE: usize = usize::MAX;
+    // The completion key posted by `post_driver_nop`, with the user-defined data
+    // as the overlapped pointer.
+    const NOTIFY: usize = usize::MAX - 1;
+    // The completion key of the attached handles, and `post_driver_raw`. The
+    // overlapped pointer is always an `Overlapped`.
+    const OPERATION: usize = usize::MAX - 2;
+
+    pub fn new(builder: &ProactorBuilder) -> io::Result<Self> {
+        if builder.polling_mode != PollingMode::Auto {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the polling mode is only chosen by the polling driver",
+            ));
+        }
+        let port = match &builder.existing_port {
+            Some(port) => port.clone(),
+            None => {
+                let port =
+                    syscall!(BOOL, CreateIoCompletionPort(INVALID_HANDLE_VALUE, 0, 0, 0))?;
+                Arc::new(unsafe { OwnedHandle::from_raw_handle(port as _) })
+            }
+        };
         Ok(Self {
             port,
             cancelled: HashSet::default(),
+            mailbox: Arc::default(),
+            on_foreign_completion: builder.on_foreign_completion.clone(),
+            transient_errors: builder.transient_errors,
+            retries: HashMap::default(),
         })
     }
 
@@ -173,47 +281,210 @@ impl Driver {
         Ok(())
     }
 
-    fn create_entry(&mut self, iocp_entry: OVERLAPPED_ENTRY) -> Option<Entry> {
-        if iocp_entry.lpOverlapped.is_null() {
-            // This entry is posted by `post_driver_nop`.
-            let user_data = iocp_entry.lpCompletionKey;
-            let result = if self.cancelled.remove(&user_data) {
-                Err(io::Error::from_raw_os_error(ERROR_OPERATION_ABORTED as _))
-            } else {
-                Ok(0)
-            };
-            Some(Entry::new(user_data, result))
+    fn create_entry(
+        &mut self,
+        iocp_entry: OVERLAPPED_ENTRY,
+        registry: &mut Slab<RawOp>,
+    ) -> Option<Entry> {
+        match iocp_entry.lpCompletionKey {
+            // The messages are in the mailbox.
+            Self::MESSAGE => None,
+            Self::NOTIFY => {
+                // This entry is posted by `post_driver_nop`.
+                let user_data = iocp_entry.lpOverlapped as usize;
+                let result = if self.cancelled.remove(&user_data) {
+                    Err(io::Error::from_raw_os_error(ERROR_OPERATION_ABORTED as _))
+                } else {
+                    Ok(0)
+                };
+                Some(Entry::new(user_data, result))
+            }
+            Self::OPERATION if !iocp_entry.lpOverlapped.is_null() => {
+                self.create_op_entry(iocp_entry, registry)
+            }
+            // The overlapped pointer is not an `Overlapped`, and shouldn't be
+            // touched.
+            _ => {
+                if let Some(f) = &self.on_foreign_completion {
+                    (f.0)(iocp_entry);
+                }
+                None
+            }
+        }
+    }
+
+    fn create_op_entry(
+        &mut self,
+        iocp_entry: OVERLAPPED_ENTRY,
+        registry: &mut Slab<RawOp>,
+    ) -> Option<Entry> {
+        let transferred = iocp_entry.dwNumberOfBytesTransferred;
+        // Any thin pointer is OK because we don't use the type of opcode.
+        let overlapped_ptr: *mut Overlapped<()> = iocp_entry.lpOverlapped.cast();
+        let overlapped = unsafe { &*overlapped_ptr };
+        let res = if matches!(
+            overlapped.base.Internal as NTSTATUS,
+            STATUS_SUCCESS | STATUS_PENDING
+        ) {
+            Ok(transferred as _)
         } else {
-            let transferred = iocp_entry.dwNumberOfBytesTransferred;
-            // Any thin pointer is OK because we don't use the type of opcode.
-            let overlapped_ptr: *mut Overlapped<()> = iocp_entry.lpOverlapped.cast();
-            let overlapped = unsafe { &*overlapped_ptr };
-            let res = if matches!(
-                overlapped.base.Internal as NTSTATUS,
-                STATUS_SUCCESS | STATUS_PENDING
-            ) {
-                Ok(transferred as _)
-            } else {
-                let error = unsafe { RtlNtStatusToDosError(overlapped.base.Internal as _) };
-                match error {
-                    ERROR_IO_INCOMPLETE | ERROR_HANDLE_EOF | ERROR_NO_DATA => Ok(0),
-                    _ => Err(io::Error::from_raw_os_error(error as _)),
+            let error = unsafe { RtlNtStatusToDosError(overlapped.base.Internal as _) };
+            match error {
+                ERROR_IO_INCOMPLETE | ERROR_HANDLE_EOF | ERROR_NO_DATA => Ok(0),
+                _ => Err(io::Error::from_raw_os_error(error as _)),
+            }
+        };
+        // The operation has been performed, and the cancellation is done.
+        let cancelled = self.cancelled.remove(&overlapped.user_data);
+        let res = match registry.get_mut(overlapped.user_data) {
+            Some(op) => self.complete(overlapped.user_data, op, res, !cancelled)?,
+            None => res,
+        };
+        Some(Entry::new(overlapped.user_data, res))
+    }
+
+    // Complete the operation with the result. A transient error is retried, or
+    // mapped, following `TransientErrors`, and `None` is returned if it will
+    // be performed again.
+    fn complete(
+        &mut self,
+        user_data: usize,
+        op: &mut RawOp,
+        res: io::Result<usize>,
+        retry: bool,
+    ) -> Option<io::Result<usize>> {
+        let kind = match &res {
+            Err(e) => e
+                .raw_os_error()
+                .and_then(|code| op.as_op_pin().transient_error(code)),
+            Ok(_) => None,
+        };
+        let retries = self.retries.remove(&user_data).map_or(0, |r| r.retries);
+        let res = match (kind, res) {
+            (Some(kind), Err(e)) if self.transient_errors != TransientErrors::Raw => {
+                match self.transient_errors.backoff(retries).filter(|_| retry) {
+                    Some(backoff) => {
+                        self.retries.insert(
+                            user_data,
+                            Retry {
+                                retries: retries + 1,
+                                at: Some(Instant::now() + backoff),
+                            },
+                        );
+                        return None;
+                    }
+                    None => Err(TransientError::wrap(kind, retries, e)),
                 }
+            }
+            (_, res) => res,
+        };
+        Some(op.as_op_pin().on_complete(res))
+    }
+
+    // The timeout of the wait, not later than the next retry.
+    fn retry_timeout(&self, timeout: Option<Duration>) -> Option<Duration> {
+        let now = Instant::now();
+        self.retries
+            .values()
+            .filter_map(|retry| retry.at)
+            .map(|at| at.saturating_duration_since(now))
+            .chain(timeout)
+            .min()
+    }
+
+    // Perform the operations whose retry is due again.
+    fn retry_due(&mut self, entries: &mut impl Extend<Entry>, registry: &mut Slab<RawOp>) {
+        let now = Instant::now();
+        let due = self
+            .retries
+            .iter_mut()
+            .filter(|(_, retry)| retry.at.is_some_and(|at| at <= now))
+            .map(|(user_data, retry)| {
+                retry.at = None;
+                *user_data
+            })
+            .collect::<Vec<_>>();
+        for user_data in due {
+            let Some(op) = registry.get_mut(user_data) else {
+                self.retries.remove(&user_data);
+                continue;
             };
-            Some(Entry::new(overlapped.user_data, res))
+            let overlapped_ptr = op.as_mut_ptr();
+            let res = unsafe {
+                (*overlapped_ptr).base.Internal = 0;
+                (*overlapped_ptr).base.InternalHigh = 0;
+                op.as_op_pin().operate(overlapped_ptr.cast())
+            };
+            if let Poll::Ready(res) = res {
+                let cancelled = self.cancelled.remove(&user_data);
+                if let Some(res) = self.complete(user_data, op, res, !cancelled) {
+                    entries.extend(Some(Entry::new(user_data, res)));
+                }
+            }
+        }
+    }
+
+    pub fn set_napi_busy_poll(
+        &mut self,
+        timeout: Option<Duration>,
+        _prefer_busy_poll: bool,
+    ) -> io::Result<()> {
+        if timeout.is_some() {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "NAPI busy polling is only supported by io-uring",
+            ))
+        } else {
+            Ok(())
         }
     }
 
+    pub fn polling_mode(&self) -> Option<PollingMode> {
+        None
+    }
+
+    pub fn max_poll_fds(&self) -> usize {
+        0
+    }
+
     pub fn attach(&mut self, fd: RawFd) -> io::Result<()> {
         syscall!(
             BOOL,
-            CreateIoCompletionPort(fd as _, self.port.as_raw_handle() as _, 0, 0)
+            CreateIoCompletionPort(
+                fd as _,
+                self.port.as_raw_handle() as _,
+                Self::OPERATION,
+                0
+            )
         )?;
         Ok(())
     }
 
+    pub fn message_sender(&self) -> io::Result<MessageSender> {
+        Ok(MessageSender {
+            port: self.port.clone(),
+            mailbox: self.mailbox.clone(),
+        })
+    }
+
+    pub fn pop_message(&mut self) -> Option<u64> {
+        self.mailbox.lock().unwrap().pop_front()
+    }
+
     pub fn cancel(&mut self, user_data: usize, registry: &mut Slab<RawOp>) {
         self.cancelled.insert(user_data);
+        // The operation waiting for a retry is not in flight, and completed as
+        // cancelled at once. If it fails to post, the operation is performed
+        // again, and completes without more retries.
+        if self
+            .retries
+            .get(&user_data)
+            .is_some_and(|retry| retry.at.is_some())
+            && post_driver_nop(self.port.as_raw_handle(), user_data).is_ok()
+        {
+            self.retries.remove(&user_data);
+            return;
+        }
         if let Some(op) = registry.get_mut(user_data) {
             let overlapped_ptr = op.as_mut_ptr();
             let op = op.as_op_pin();
@@ -222,38 +493,53 @@ impl Driver {
         }
     }
 
+    pub fn is_full(&self) -> bool {
+        false
+    }
+
+    // The operation is performed at once. If it completes without posting to
+    // the port, the result is returned.
+    pub fn push(&mut self, user_data: usize, op: &mut RawOp) -> Poll<io::Result<usize>> {
+        let overlapped_ptr = op.as_mut_ptr();
+        let res = unsafe {
+            (*overlapped_ptr).driver = self.port.as_raw_handle();
+            op.as_op_pin().operate(overlapped_ptr.cast())
+        };
+        match res {
+            Poll::Ready(res) => match self.complete(user_data, op, res, true) {
+                Some(res) => Poll::Ready(res),
+                None => Poll::Pending,
+            },
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
     pub unsafe fn poll(
         &mut self,
         timeout: Option<Duration>,
-        ops: &mut impl Iterator<Item = usize>,
         entries: &mut impl Extend<Entry>,
         registry: &mut Slab<RawOp>,
     ) -> io::Result<()> {
-        for user_data in ops {
-            let overlapped_ptr = registry[user_data].as_mut_ptr();
-            let op = registry[user_data].as_op_pin();
-            let result = if self.cancelled.remove(&user_data) {
-                Poll::Ready(Err(io::Error::from_raw_os_error(
-                    ERROR_OPERATION_ABORTED as _,
-                )))
-            } else {
-                op.operate(overlapped_ptr.cast())
-            };
-            if let Poll::Ready(result) = result {
-                post_driver_raw(self.port.as_raw_handle(), result, overlapped_ptr.cast())?;
-            }
-        }
-
         // Prevent stack growth.
         let mut iocp_entries = ArrayVec::<OVERLAPPED_ENTRY, { Self::DEFAULT_CAPACITY }>::new();
-        self.poll_impl(timeout, &mut iocp_entries)?;
-        entries.extend(iocp_entries.drain(..).filter_map(|e| self.create_entry(e)));
+        let res = self.poll_impl(self.retry_timeout(timeout), &mut iocp_entries);
+        self.retry_due(entries, registry);
+        res?;
+        entries.extend(
+            iocp_entries
+                .drain(..)
+                .filter_map(|e| self.create_entry(e, registry)),
+        );
 
         // See if there are remaining entries.
         loop {
             match self.poll_impl(Some(Duration::ZERO), &mut iocp_entries) {
                 Ok(()) => {
-                    entries.extend(iocp_entries.drain(..).filter_map(|e| self.create_entry(e)));
+                    entries.extend(
+                        iocp_entries
+                            .drain(..)
+                            .filter_map(|e| self.create_entry(e, registry)),
+                    );
                 }
                 Err(e) => match e.kind() {
                     io::ErrorKind::TimedOut => break,
@@ -289,7 +575,7 @@ unsafe fn post_driver_raw(
         PostQueuedCompletionStatus(
             handle as _,
             result.unwrap_or_default() as _,
-            0,
+            Driver::OPERATION,
             overlapped_ptr,
         )
     )?;
@@ -300,7 +586,7 @@ unsafe fn post_driver_raw(
 pub(crate) fn post_driver_nop(handle: RawFd, user_data: usize) -> io::Result<()> {
     syscall!(
         BOOL,
-        PostQueuedCompletionStatus(handle as _, 0, user_data, null_mut())
+        PostQueuedCompletionStatus(handle as _, 0, Driver::NOTIFY, user_data as _)
     )?;
     Ok(())
 }
@@ -320,6 +606,8 @@ pub struct Overlapped<T: ?Sized> {
     pub base: OVERLAPPED,
     /// The registered user defined data.
     pub user_data: usize,
+    /// The handle of the driver, set before the operation is performed.
+    pub driver: RawFd,
     /// The opcode.
     /// The user should guarantee the type is correct.
     pub op: T,
@@ -330,18 +618,20 @@ impl<T> Overlapped<T> {
         Self {
             base: unsafe { std::mem::zeroed() },
             user_data,
+            driver: null_mut(),
             op,
         }
     }
 }
 
-pub(crate) struct RawOp(NonNull<Overlapped<dyn OpCode>>);
+// The allocation is taken from the pool, and given back when the op is moved
+// out.
+pub(crate) struct RawOp(NonNull<Overlapped<dyn OpCode>>, Rc<OpPool>);
 
 impl RawOp {
-    pub(crate) fn new(user_data: usize, op: impl OpCode + 'static) -> Self {
+    pub(crate) fn new(user_data: usize, op: impl OpCode + 'static, pool: &Rc<OpPool>) -> Self {
         let op = Overlapped::new(user_data, op);
-        let op = Box::new(op) as Box<Overlapped<dyn OpCode>>;
-        Self(unsafe { NonNull::new_unchecked(Box::into_raw(op)) })
+        Self(pool.alloc(op), pool.clone())
     }
 
     pub(crate) fn as_op_pin(&mut self) -> Pin<&mut dyn OpCode> {
@@ -354,7 +644,9 @@ impl RawOp {
 
     pub unsafe fn into_inner<T: OpCode>(self) -> T {
         let this = ManuallyDrop::new(self);
-        let this: Box<Overlapped<T>> = Box::from_raw(this.0.cast().as_ptr());
-        this.op
+        let op = this.0.cast::<Overlapped<T>>().as_ptr().read();
+        this.1.release(this.0.cast(), Layout::new::<Overlapped<T>>());
+        drop(std::ptr::read(&this.1));
+        op.op
     }
 }
@@ -1,9 +1,11 @@
 #[cfg(feature = "once_cell_try")]
 use std::sync::OnceLock;
 use std::{
+    ffi::c_void,
     io,
     pin::Pin,
     ptr::{null, null_mut},
+    sync::atomic::{AtomicBool, Ordering},
     task::Poll,
 };
 
@@ -14,27 +16,36 @@ use windows_sys::{
     core::GUID,
     Win32::{
         Foundation::{
-            GetLastError, ERROR_HANDLE_EOF, ERROR_IO_INCOMPLETE, ERROR_IO_PENDING, ERROR_NOT_FOUND,
-            ERROR_NO_DATA, ERROR_PIPE_CONNECTED,
+            GetLastError, BOOLEAN, ERROR_HANDLE_EOF, ERROR_IO_INCOMPLETE, ERROR_IO_PENDING,
+            ERROR_NOT_FOUND, ERROR_NO_DATA, ERROR_OPERATION_ABORTED, ERROR_PIPE_CONNECTED, HANDLE,
+            INVALID_HANDLE_VALUE, RtlNtStatusToDosError,
         },
         Networking::WinSock::{
-            setsockopt, socklen_t, WSAIoctl, WSARecv, WSARecvFrom, WSASend, WSASendTo,
-            LPFN_ACCEPTEX, LPFN_CONNECTEX, LPFN_GETACCEPTEXSOCKADDRS,
+            setsockopt, socklen_t, TransmitFile, WSAIoctl, WSARecv, WSARecvFrom, WSASend,
+            WSASendTo, LPFN_ACCEPTEX, LPFN_CONNECTEX, LPFN_GETACCEPTEXSOCKADDRS,
             SIO_GET_EXTENSION_FUNCTION_POINTER, SOCKADDR, SOCKADDR_STORAGE, SOL_SOCKET,
             SO_UPDATE_ACCEPT_CONTEXT, SO_UPDATE_CONNECT_CONTEXT, WSAID_ACCEPTEX, WSAID_CONNECTEX,
             WSAID_GETACCEPTEXSOCKADDRS,
         },
-        Storage::FileSystem::{FlushFileBuffers, ReadFile, WriteFile},
+        Security::Cryptography::{BCryptGenRandom, BCRYPT_USE_SYSTEM_PREFERRED_RNG},
+        Storage::FileSystem::{FlushFileBuffers, ReadDirectoryChangesW, ReadFile, WriteFile},
         System::{
             Pipes::ConnectNamedPipe,
+            Threading::{
+                RegisterWaitForSingleObject, UnregisterWaitEx, INFINITE, WT_EXECUTEONLYONCE,
+            },
             IO::{CancelIoEx, OVERLAPPED},
         },
     },
 };
 
+use super::transient::transient_kind;
 use crate::{
-    buf::{AsIoSlices, AsIoSlicesMut, IntoInner, IoBuf, IoBufMut},
-    driver::{OpCode, RawFd},
+    buf::{
+        AsIoSlices, AsIoSlicesMut, BufWrapper, IntoInner, IoBuf, IoBufMut, VectoredBufWrapper,
+        WrapBuf,
+    },
+    driver::{sockaddr_storage, OpCode, Overlapped, RawFd},
     op::*,
     syscall,
 };
@@ -43,6 +54,11 @@ use crate::{
 fn winapi_result(transferred: u32) -> Poll<io::Result<usize>> {
     let error = unsafe { GetLastError() };
     assert_ne!(error, 0);
+    error_result(error, transferred)
+}
+
+#[inline]
+fn error_result(error: u32, transferred: u32) -> Poll<io::Result<usize>> {
     match error {
         ERROR_IO_PENDING => Poll::Pending,
         ERROR_IO_INCOMPLETE | ERROR_HANDLE_EOF | ERROR_PIPE_CONNECTED | ERROR_NO_DATA => {
@@ -75,11 +91,10 @@ fn win32_pending_result(res: i32) -> Poll<io::Result<usize>> {
 }
 
 #[inline]
-fn winsock_result(res: i32, transferred: u32) -> Poll<io::Result<usize>> {
-    if res != 0 {
-        winapi_result(transferred)
-    } else {
-        Poll::Pending
+fn winsock_result(res: io::Result<i32>, transferred: u32) -> Poll<io::Result<usize>> {
+    match res {
+        Ok(_) => Poll::Pending,
+        Err(e) => error_result(e.raw_os_error().unwrap_or_default() as _, transferred),
     }
 }
 
@@ -117,21 +132,24 @@ fn get_wsa_fn<F>(handle: RawFd, fguid: GUID) -> io::Result<Option<F>> {
     Ok(fptr)
 }
 
+// The length of `ReadFile` and `WriteFile` is `u32`, and the rest of a larger
+// buffer is left to the next call, like a short read or write.
+fn clamp_len(len: usize) -> u32 {
+    len.try_into().unwrap_or(u32::MAX)
+}
+
 impl<T: IoBufMut> OpCode for ReadAt<T> {
     unsafe fn operate(mut self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
         if let Some(overlapped) = optr.as_mut() {
             overlapped.Anonymous.Anonymous.Offset = (self.offset & 0xFFFFFFFF) as _;
-            #[cfg(target_pointer_width = "64")]
-            {
-                overlapped.Anonymous.Anonymous.OffsetHigh = (self.offset >> 32) as _;
-            }
+            overlapped.Anonymous.Anonymous.OffsetHigh = (self.offset >> 32) as _;
         }
         let fd = self.fd as _;
         let slice = self.buffer.as_uninit_slice();
         let res = ReadFile(
             fd,
             slice.as_mut_ptr() as _,
-            slice.len() as _,
+            clamp_len(slice.len()),
             null_mut(),
             optr,
         );
@@ -147,16 +165,68 @@ impl<T: IoBuf> OpCode for WriteAt<T> {
     unsafe fn operate(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
         if let Some(overlapped) = optr.as_mut() {
             overlapped.Anonymous.Anonymous.Offset = (self.offset & 0xFFFFFFFF) as _;
-            #[cfg(target_pointer_width = "64")]
-            {
-                overlapped.Anonymous.Anonymous.OffsetHigh = (self.offset >> 32) as _;
-            }
+            overlapped.Anonymous.Anonymous.OffsetHigh = (self.offset >> 32) as _;
         }
         let slice = self.buffer.as_slice();
         let res = WriteFile(
             self.fd as _,
             slice.as_ptr() as _,
-            slice.len() as _,
+            clamp_len(slice.len()),
+            null_mut(),
+            optr,
+        );
+        win32_pending_result(res)
+    }
+
+    unsafe fn cancel(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> io::Result<()> {
+        cancel(self.fd, optr)
+    }
+}
+
+/// Write a file at specified position from specified buffers.
+pub struct WriteVectoredAt<T: IoBuf> {
+    pub(crate) fd: RawFd,
+    pub(crate) offset: u64,
+    pub(crate) buffer: VectoredBufWrapper<T>,
+}
+
+impl<T: IoBuf> WriteVectoredAt<T> {
+    /// Create [`WriteVectoredAt`].
+    pub fn new(fd: RawFd, offset: u64, buffer: Vec<T>) -> Self {
+        Self {
+            fd,
+            offset,
+            buffer: VectoredBufWrapper::new(buffer),
+        }
+    }
+}
+
+impl<T: IoBuf> IntoInner for WriteVectoredAt<T> {
+    type Inner = VectoredBufWrapper<T>;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+// `WriteFileGather` only accepts the page aligned buffers of the files opened
+// without buffering, so only the first non-empty buffer is written, like a
+// short write.
+impl<T: IoBuf> OpCode for WriteVectoredAt<T> {
+    unsafe fn operate(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
+        if let Some(overlapped) = optr.as_mut() {
+            overlapped.Anonymous.Anonymous.Offset = (self.offset & 0xFFFFFFFF) as _;
+            overlapped.Anonymous.Anonymous.OffsetHigh = (self.offset >> 32) as _;
+        }
+        let slices = self.buffer.as_io_slices();
+        let slice = slices.iter().find(|slice| !slice.is_empty());
+        let Some(slice) = slice else {
+            return Poll::Ready(Ok(0));
+        };
+        let res = WriteFile(
+            self.fd as _,
+            slice.as_ptr() as _,
+            clamp_len(slice.len()),
             null_mut(),
             optr,
         );
@@ -182,11 +252,16 @@ impl OpCode for Sync {
 static ACCEPT_EX: OnceLock<LPFN_ACCEPTEX> = OnceLock::new();
 static GET_ADDRS: OnceLock<LPFN_GETACCEPTEXSOCKADDRS> = OnceLock::new();
 
+// `AcceptEx` requires 16 more bytes than the maximum address length for each
+// of the local and remote addresses.
+const ACCEPT_ADDR_LEN: usize = std::mem::size_of::<SOCKADDR_STORAGE>() + 16;
+const ACCEPT_BUFFER_LEN: usize = ACCEPT_ADDR_LEN * 2;
+
 /// Accept a connection.
 pub struct Accept {
     pub(crate) fd: RawFd,
     pub(crate) accept_fd: RawFd,
-    pub(crate) buffer: SOCKADDR_STORAGE,
+    pub(crate) buffer: [u8; ACCEPT_BUFFER_LEN],
 }
 
 impl Accept {
@@ -195,56 +270,156 @@ impl Accept {
         Self {
             fd,
             accept_fd,
-            buffer: unsafe { std::mem::zeroed() },
+            buffer: [0; ACCEPT_BUFFER_LEN],
         }
     }
 
     /// Update accept context.
     pub fn update_context(&self) -> io::Result<()> {
-        syscall!(
-            SOCKET,
-            setsockopt(
-                self.accept_fd as _,
-                SOL_SOCKET,
-                SO_UPDATE_ACCEPT_CONTEXT,
-                &self.fd as *const _ as _,
-                std::mem::size_of_val(&self.fd) as _,
-            )
-        )?;
-        Ok(())
+        update_accept_context(self.fd, self.accept_fd)
     }
 
     /// Get the remote address from the inner buffer.
     pub fn into_addr(self) -> io::Result<SockAddr> {
-        let get_addrs_fn = GET_ADDRS
-            .get_or_try_init(|| get_wsa_fn(self.fd, WSAID_GETACCEPTEXSOCKADDRS))?
+        accept_remote_addr(self.fd, &self.buffer, 0)
+    }
+}
+
+// Without it, the accepted socket doesn't inherit the properties of the
+// listener, and `getpeername` and `shutdown` fail on it.
+fn update_accept_context(fd: RawFd, accept_fd: RawFd) -> io::Result<()> {
+    syscall!(
+        SOCKET,
+        setsockopt(
+            accept_fd as _,
+            SOL_SOCKET,
+            SO_UPDATE_ACCEPT_CONTEXT,
+            &fd as *const _ as _,
+            std::mem::size_of_val(&fd) as _,
+        )
+    )?;
+    Ok(())
+}
+
+// Get the remote address from the output buffer of `AcceptEx`, where the
+// addresses follow the received data of `data_len`.
+fn accept_remote_addr(fd: RawFd, buffer: &[u8], data_len: usize) -> io::Result<SockAddr> {
+    let get_addrs_fn = GET_ADDRS
+        .get_or_try_init(|| get_wsa_fn(fd, WSAID_GETACCEPTEXSOCKADDRS))?
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "cannot retrieve GetAcceptExSockAddrs",
+            )
+        })?;
+    debug_assert!(buffer.len() >= data_len + ACCEPT_BUFFER_LEN);
+    let mut local_addr: *mut SOCKADDR = null_mut();
+    let mut local_addr_len = 0;
+    let mut remote_addr: *mut SOCKADDR = null_mut();
+    let mut remote_addr_len = 0;
+    unsafe {
+        get_addrs_fn(
+            buffer.as_ptr() as _,
+            data_len as _,
+            ACCEPT_ADDR_LEN as _,
+            ACCEPT_ADDR_LEN as _,
+            &mut local_addr,
+            &mut local_addr_len,
+            &mut remote_addr,
+            &mut remote_addr_len,
+        );
+    }
+    // The remote address points into the buffer and may be not aligned, so
+    // only copy the valid bytes.
+    let mut storage = sockaddr_storage::zeroed();
+    unsafe {
+        std::ptr::copy_nonoverlapping(
+            remote_addr.cast::<u8>(),
+            std::ptr::addr_of_mut!(storage).cast::<u8>(),
+            remote_addr_len as _,
+        );
+    }
+    Ok(unsafe { SockAddr::new(storage, remote_addr_len) })
+}
+
+impl OpCode for Accept {
+    unsafe fn operate(mut self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
+        let accept_fn = ACCEPT_EX
+            .get_or_try_init(|| get_wsa_fn(self.fd, WSAID_ACCEPTEX))?
             .ok_or_else(|| {
-                io::Error::new(
-                    io::ErrorKind::Unsupported,
-                    "cannot retrieve GetAcceptExSockAddrs",
-                )
+                io::Error::new(io::ErrorKind::Unsupported, "cannot retrieve AcceptEx")
             })?;
-        let mut local_addr: *mut SOCKADDR = null_mut();
-        let mut local_addr_len = 0;
-        let mut remote_addr: *mut SOCKADDR = null_mut();
-        let mut remote_addr_len = 0;
+        let mut received = 0;
+        let res = accept_fn(
+            self.fd as _,
+            self.accept_fd as _,
+            self.buffer.as_mut_ptr() as _,
+            0,
+            ACCEPT_ADDR_LEN as _,
+            ACCEPT_ADDR_LEN as _,
+            &mut received,
+            optr,
+        );
+        win32_result(res, received)
+    }
+
+    unsafe fn cancel(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> io::Result<()> {
+        cancel(self.fd, optr)
+    }
+}
+
+/// Accept a connection, and receive the first bytes of it with `AcceptEx`.
+/// It completes only when the data arrives, or the connection is closed.
+pub struct AcceptWithData<T: IoBufMut> {
+    pub(crate) fd: RawFd,
+    pub(crate) accept_fd: RawFd,
+    pub(crate) buffer: BufWrapper<T>,
+    // The received data, followed by the addresses.
+    output: Vec<u8>,
+    data_len: usize,
+}
+
+impl<T: IoBufMut> AcceptWithData<T> {
+    /// Create [`AcceptWithData`]. `accept_fd` should not be bound. The data
+    /// is received into the uninitialized part of `buffer`.
+    pub fn new(fd: RawFd, accept_fd: RawFd, buffer: T) -> Self {
+        let mut buffer = BufWrapper::new(buffer);
+        let data_len = buffer.as_uninit_slice().len().min(u32::MAX as usize / 2);
+        Self {
+            fd,
+            accept_fd,
+            buffer,
+            output: vec![0; data_len + ACCEPT_BUFFER_LEN],
+            data_len,
+        }
+    }
+
+    /// Update accept context.
+    pub fn update_context(&self) -> io::Result<()> {
+        update_accept_context(self.fd, self.accept_fd)
+    }
+
+    /// Get the remote address from the inner buffer.
+    pub fn remote_addr(&self) -> io::Result<SockAddr> {
+        accept_remote_addr(self.fd, &self.output, self.data_len)
+    }
+
+    /// Get the buffer with the `received` bytes returned by the op.
+    pub fn into_buffer(mut self, received: usize) -> T {
+        let received = received.min(self.data_len);
         unsafe {
-            get_addrs_fn(
-                &self.buffer as *const _ as *const _,
-                0,
-                0,
-                std::mem::size_of_val(&self.buffer) as _,
-                &mut local_addr,
-                &mut local_addr_len,
-                &mut remote_addr,
-                &mut remote_addr_len,
+            std::ptr::copy_nonoverlapping(
+                self.output.as_ptr(),
+                self.buffer.as_uninit_slice().as_mut_ptr().cast(),
+                received,
             );
+            self.buffer.set_buf_init(received);
         }
-        Ok(unsafe { SockAddr::new(*remote_addr.cast::<SOCKADDR_STORAGE>(), remote_addr_len) })
+        self.buffer.into_inner()
     }
 }
 
-impl OpCode for Accept {
+impl<T: IoBufMut> OpCode for AcceptWithData<T> {
     unsafe fn operate(mut self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
         let accept_fn = ACCEPT_EX
             .get_or_try_init(|| get_wsa_fn(self.fd, WSAID_ACCEPTEX))?
@@ -255,10 +430,10 @@ impl OpCode for Accept {
         let res = accept_fn(
             self.fd as _,
             self.accept_fd as _,
-            &mut self.buffer as *mut _ as *mut _,
-            0,
-            0,
-            std::mem::size_of_val(&self.buffer) as _,
+            self.output.as_mut_ptr() as _,
+            self.data_len as _,
+            ACCEPT_ADDR_LEN as _,
+            ACCEPT_ADDR_LEN as _,
             &mut received,
             optr,
         );
@@ -272,20 +447,18 @@ impl OpCode for Accept {
 
 static CONNECT_EX: OnceLock<LPFN_CONNECTEX> = OnceLock::new();
 
+fn update_connect_context(fd: RawFd) -> io::Result<()> {
+    syscall!(
+        SOCKET,
+        setsockopt(fd as _, SOL_SOCKET, SO_UPDATE_CONNECT_CONTEXT, null(), 0)
+    )?;
+    Ok(())
+}
+
 impl Connect {
     /// Update connect context.
     pub fn update_context(&self) -> io::Result<()> {
-        syscall!(
-            SOCKET,
-            setsockopt(
-                self.fd as _,
-                SOL_SOCKET,
-                SO_UPDATE_CONNECT_CONTEXT,
-                null(),
-                0,
-            )
-        )?;
-        Ok(())
+        update_connect_context(self.fd)
     }
 }
 
@@ -299,7 +472,7 @@ impl OpCode for Connect {
         let mut sent = 0;
         let res = connect_fn(
             self.fd as _,
-            self.addr.as_ptr(),
+            self.addr.as_ptr().cast(),
             self.addr.len(),
             null(),
             0,
@@ -314,6 +487,39 @@ impl OpCode for Connect {
     }
 }
 
+impl<T: IoBuf> ConnectWithData<T> {
+    /// Update connect context.
+    pub fn update_context(&self) -> io::Result<()> {
+        update_connect_context(self.fd)
+    }
+}
+
+impl<T: IoBuf> OpCode for ConnectWithData<T> {
+    unsafe fn operate(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
+        let connect_fn = CONNECT_EX
+            .get_or_try_init(|| get_wsa_fn(self.fd, WSAID_CONNECTEX))?
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::Unsupported, "cannot retrieve ConnectEx")
+            })?;
+        let slice = self.buffer.as_slice();
+        let mut sent = 0;
+        let res = connect_fn(
+            self.fd as _,
+            self.addr.as_ptr().cast(),
+            self.addr.len(),
+            slice.as_ptr().cast(),
+            slice.len() as _,
+            &mut sent,
+            optr,
+        );
+        win32_result(res, sent)
+    }
+
+    unsafe fn cancel(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> io::Result<()> {
+        cancel(self.fd, optr)
+    }
+}
+
 /// Receive data from remote.
 pub struct RecvImpl<T: AsIoSlicesMut + Unpin> {
     pub(crate) fd: RawFd,
@@ -328,6 +534,11 @@ impl<T: AsIoSlicesMut + Unpin> RecvImpl<T> {
             buffer: T::new(buffer),
         }
     }
+
+    /// Wait for the data before trying to receive. It is ignored by IOCP.
+    pub fn poll_first(self, _poll_first: bool) -> Self {
+        self
+    }
 }
 
 impl<T: AsIoSlicesMut + Unpin> IntoInner for RecvImpl<T> {
@@ -343,14 +554,17 @@ impl<T: AsIoSlicesMut + Unpin> OpCode for RecvImpl<T> {
         let slices = self.buffer.as_io_slices_mut();
         let mut flags = 0;
         let mut received = 0;
-        let res = WSARecv(
-            self.fd as _,
-            slices.as_ptr() as _,
-            slices.len() as _,
-            &mut received,
-            &mut flags,
-            optr,
-            None,
+        let res = syscall!(
+            SOCKET,
+            WSARecv(
+                self.fd as _,
+                slices.as_ptr() as _,
+                slices.len() as _,
+                &mut received,
+                &mut flags,
+                optr,
+                None,
+            )
         );
         winsock_result(res, received)
     }
@@ -358,6 +572,10 @@ impl<T: AsIoSlicesMut + Unpin> OpCode for RecvImpl<T> {
     unsafe fn cancel(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> io::Result<()> {
         cancel(self.fd, optr)
     }
+
+    fn transient_error(&self, code: i32) -> Option<io::ErrorKind> {
+        transient_kind(code, false)
+    }
 }
 
 /// Send data to remote.
@@ -388,14 +606,17 @@ impl<T: AsIoSlices + Unpin> OpCode for SendImpl<T> {
     unsafe fn operate(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
         let slices = self.buffer.as_io_slices();
         let mut sent = 0;
-        let res = WSASend(
-            self.fd as _,
-            slices.as_ptr() as _,
-            slices.len() as _,
-            &mut sent,
-            0,
-            optr,
-            None,
+        let res = syscall!(
+            SOCKET,
+            WSASend(
+                self.fd as _,
+                slices.as_ptr() as _,
+                slices.len() as _,
+                &mut sent,
+                0,
+                optr,
+                None,
+            )
         );
         winsock_result(res, sent)
     }
@@ -403,13 +624,17 @@ impl<T: AsIoSlices + Unpin> OpCode for SendImpl<T> {
     unsafe fn cancel(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> io::Result<()> {
         cancel(self.fd, optr)
     }
+
+    fn transient_error(&self, code: i32) -> Option<io::ErrorKind> {
+        transient_kind(code, true)
+    }
 }
 
 /// Receive data and source address.
 pub struct RecvFromImpl<T: AsIoSlicesMut + Unpin> {
     pub(crate) fd: RawFd,
     pub(crate) buffer: T,
-    pub(crate) addr: SOCKADDR_STORAGE,
+    pub(crate) addr: sockaddr_storage,
     pub(crate) addr_len: socklen_t,
 }
 
@@ -419,14 +644,14 @@ impl<T: AsIoSlicesMut + Unpin> RecvFromImpl<T> {
         Self {
             fd,
             buffer: T::new(buffer),
-            addr: unsafe { std::mem::zeroed() },
-            addr_len: std::mem::size_of::<SOCKADDR_STORAGE>() as _,
+            addr: sockaddr_storage::zeroed(),
+            addr_len: std::mem::size_of::<sockaddr_storage>() as _,
         }
     }
 }
 
 impl<T: AsIoSlicesMut + Unpin> IntoInner for RecvFromImpl<T> {
-    type Inner = (T, SOCKADDR_STORAGE, socklen_t);
+    type Inner = (T, sockaddr_storage, socklen_t);
 
     fn into_inner(self) -> Self::Inner {
         (self.buffer, self.addr, self.addr_len)
@@ -438,16 +663,19 @@ impl<T: AsIoSlicesMut + Unpin> OpCode for RecvFromImpl<T> {
         let buffer = self.buffer.as_io_slices_mut();
         let mut flags = 0;
         let mut received = 0;
-        let res = WSARecvFrom(
-            self.fd as _,
-            buffer.as_ptr() as _,
-            buffer.len() as _,
-            &mut received,
-            &mut flags,
-            &mut self.addr as *mut _ as *mut SOCKADDR,
-            &mut self.addr_len,
-            optr,
-            None,
+        let res = syscall!(
+            SOCKET,
+            WSARecvFrom(
+                self.fd as _,
+                buffer.as_ptr() as _,
+                buffer.len() as _,
+                &mut received,
+                &mut flags,
+                &mut self.addr as *mut _ as *mut SOCKADDR,
+                &mut self.addr_len,
+                optr,
+                None,
+            )
         );
         winsock_result(res, received)
     }
@@ -455,6 +683,10 @@ impl<T: AsIoSlicesMut + Unpin> OpCode for RecvFromImpl<T> {
     unsafe fn cancel(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> io::Result<()> {
         cancel(self.fd, optr)
     }
+
+    fn transient_error(&self, code: i32) -> Option<io::ErrorKind> {
+        transient_kind(code, false)
+    }
 }
 
 /// Send data to specified address.
@@ -487,16 +719,19 @@ impl<T: AsIoSlices> OpCode for SendToImpl<T> {
     unsafe fn operate(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
         let buffer = self.buffer.as_io_slices();
         let mut sent = 0;
-        let res = WSASendTo(
-            self.fd as _,
-            buffer.as_ptr() as _,
-            buffer.len() as _,
-            &mut sent,
-            0,
-            self.addr.as_ptr(),
-            self.addr.len(),
-            optr,
-            None,
+        let res = syscall!(
+            SOCKET,
+            WSASendTo(
+                self.fd as _,
+                buffer.as_ptr() as _,
+                buffer.len() as _,
+                &mut sent,
+                0,
+                self.addr.as_ptr().cast(),
+                self.addr.len(),
+                optr,
+                None,
+            )
         );
         winsock_result(res, sent)
     }
@@ -504,6 +739,10 @@ impl<T: AsIoSlices> OpCode for SendToImpl<T> {
     unsafe fn cancel(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> io::Result<()> {
         cancel(self.fd, optr)
     }
+
+    fn transient_error(&self, code: i32) -> Option<io::ErrorKind> {
+        transient_kind(code, true)
+    }
 }
 
 /// Connect a named pipe server.
@@ -528,3 +767,275 @@ impl OpCode for ConnectNamedPipe {
         cancel(self.fd, optr)
     }
 }
+
+struct WaitContext {
+    port: RawFd,
+    optr: *mut OVERLAPPED,
+    fired: AtomicBool,
+}
+
+unsafe extern "system" fn wait_callback(context: *mut c_void, _timed_out: BOOLEAN) {
+    let context = &*(context as *const WaitContext);
+    if !context.fired.swap(true, Ordering::AcqRel) {
+        super::post_driver_raw(context.port, Ok(0), context.optr).ok();
+    }
+}
+
+/// Wait for a handle to be signaled, e.g., a process, an event, or a waitable
+/// timer.
+///
+/// The wait is registered to the system thread pool, and the completion is
+/// posted to the driver. Cancelling it unregisters the wait.
+pub struct WaitHandle {
+    pub(crate) fd: RawFd,
+    wait: HANDLE,
+    // Boxed because the callback may run after the op moves.
+    context: Option<Box<WaitContext>>,
+}
+
+impl WaitHandle {
+    /// Create [`WaitHandle`].
+    pub fn new(fd: RawFd) -> Self {
+        Self {
+            fd,
+            wait: 0,
+            context: None,
+        }
+    }
+
+    /// Unregister the wait, and block until the running callback returns.
+    /// Returns whether the callback has been called.
+    fn unregister(&mut self) -> bool {
+        if self.wait != 0 {
+            unsafe { UnregisterWaitEx(self.wait, INVALID_HANDLE_VALUE) };
+            self.wait = 0;
+        }
+        match &self.context {
+            Some(context) => context.fired.swap(true, Ordering::AcqRel),
+            None => true,
+        }
+    }
+}
+
+impl OpCode for WaitHandle {
+    unsafe fn operate(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let port = (*optr.cast::<Overlapped<()>>()).driver;
+        let context = this.context.insert(Box::new(WaitContext {
+            port,
+            optr,
+            fired: AtomicBool::new(false),
+        }));
+        let context = &**context as *const WaitContext as *const c_void;
+        // The callback is called at once if the handle is already signaled.
+        let res = RegisterWaitForSingleObject(
+            &mut this.wait,
+            this.fd as _,
+            Some(wait_callback),
+            context,
+            INFINITE,
+            WT_EXECUTEONLYONCE,
+        );
+        if res == 0 {
+            Poll::Ready(Err(io::Error::last_os_error()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    unsafe fn cancel(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> io::Result<()> {
+        let this = self.get_mut();
+        if !this.unregister() {
+            // The callback won't post the completion.
+            let port = (*optr.cast::<Overlapped<()>>()).driver;
+            super::post_driver_raw(
+                port,
+                Err(io::Error::from_raw_os_error(ERROR_OPERATION_ABORTED as _)),
+                optr,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for WaitHandle {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+/// Read the changes of a directory.
+pub struct ReadDirectoryChanges<T: IoBufMut> {
+    pub(crate) fd: RawFd,
+    pub(crate) buffer: BufWrapper<T>,
+    pub(crate) recursive: bool,
+    pub(crate) filter: u32,
+}
+
+impl<T: IoBufMut> ReadDirectoryChanges<T> {
+    /// Create [`ReadDirectoryChanges`]. `fd` should be a directory opened with
+    /// `FILE_FLAG_BACKUP_SEMANTICS`, and `filter` is a combination of
+    /// `FILE_NOTIFY_CHANGE_*`. The buffer should be aligned to 4 bytes.
+    ///
+    /// If the operation succeeds with zero bytes, or fails with
+    /// `ERROR_NOTIFY_ENUM_DIR`, the changes overflowed the buffer and were
+    /// dropped.
+    pub fn new(fd: RawFd, buffer: T, recursive: bool, filter: u32) -> Self {
+        Self {
+            fd,
+            buffer: BufWrapper::new(buffer),
+            recursive,
+            filter,
+        }
+    }
+}
+
+impl<T: IoBufMut> IntoInner for ReadDirectoryChanges<T> {
+    type Inner = BufWrapper<T>;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+impl<T: IoBufMut> OpCode for ReadDirectoryChanges<T> {
+    unsafe fn operate(mut self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
+        let fd = self.fd as _;
+        let recursive = self.recursive as _;
+        let filter = self.filter;
+        let slice = self.buffer.as_uninit_slice();
+        if slice.as_ptr().align_offset(4) != 0 {
+            return Poll::Ready(Err(io::Error::from(io::ErrorKind::InvalidInput)));
+        }
+        let res = ReadDirectoryChangesW(
+            fd,
+            slice.as_mut_ptr() as _,
+            slice.len() as _,
+            recursive,
+            filter,
+            null_mut(),
+            optr,
+            None,
+        );
+        win32_pending_result(res)
+    }
+
+    unsafe fn cancel(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> io::Result<()> {
+        cancel(self.fd, optr)
+    }
+}
+
+impl OpCode for MsgRing {
+    unsafe fn operate(self: Pin<&mut Self>, _optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.sender.send(self.msg).map(|_| 0))
+    }
+
+    unsafe fn cancel(self: Pin<&mut Self>, _optr: *mut OVERLAPPED) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl OpCode for SendFile {
+    unsafe fn operate(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
+        if let Some(overlapped) = optr.as_mut() {
+            overlapped.Anonymous.Anonymous.Offset = (self.offset & 0xFFFFFFFF) as _;
+            overlapped.Anonymous.Anonymous.OffsetHigh = (self.offset >> 32) as _;
+        }
+        let res = TransmitFile(
+            self.fd as _,
+            self.file as _,
+            self.len.min(i32::MAX as usize - 1) as _,
+            0,
+            optr,
+            null(),
+            0,
+        );
+        win32_pending_result(res)
+    }
+
+    unsafe fn cancel(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> io::Result<()> {
+        cancel(self.fd, optr)
+    }
+}
+
+// Fill at most `u32::MAX` bytes, like a short read.
+fn gen_random(ptr: *mut u8, len: usize) -> io::Result<usize> {
+    let len = len.min(u32::MAX as usize);
+    let status =
+        unsafe { BCryptGenRandom(0 as _, ptr, len as _, BCRYPT_USE_SYSTEM_PREFERRED_RNG) };
+    if status < 0 {
+        Err(io::Error::from_raw_os_error(
+            unsafe { RtlNtStatusToDosError(status) } as _,
+        ))
+    } else {
+        Ok(len)
+    }
+}
+
+impl<T: IoBufMut> OpCode for GetRandom<T> {
+    unsafe fn operate(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
+        let this = self.get_unchecked_mut();
+        // The buffer is pinned from now on, so the pointer is valid until the op
+        // completes.
+        let slice = this.buffer.as_uninit_slice();
+        let buffer = SendWrapper((slice.as_mut_ptr() as *mut u8, slice.len()));
+        let op = this.op.insert(BlockingBufOp::new(null_mut(), buffer, |_, buffer| {
+            let (ptr, len) = buffer.get();
+            gen_random(ptr, len)
+        }));
+        Pin::new(op).operate(optr)
+    }
+
+    unsafe fn cancel(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> io::Result<()> {
+        match &mut self.get_unchecked_mut().op {
+            Some(op) => Pin::new(op).cancel(optr),
+            None => Ok(()),
+        }
+    }
+
+    fn on_complete(self: Pin<&mut Self>, result: io::Result<usize>) -> io::Result<usize> {
+        // The buffer is never moved.
+        match &mut unsafe { self.get_unchecked_mut() }.op {
+            Some(op) => Pin::new(op).on_complete(result),
+            None => result,
+        }
+    }
+}
+
+impl<B: std::marker::Send + 'static> OpCode for BlockingBufOp<B> {
+    unsafe fn operate(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
+        let driver = SendWrapper((*optr.cast::<Overlapped<()>>()).driver);
+        let optr = SendWrapper(optr);
+        // The buffer is never pinned.
+        self.get_unchecked_mut().spawn(move || {
+            // The real result is taken in `on_complete`.
+            super::post_driver_raw(driver.get(), Ok(0), optr.get()).ok();
+        });
+        Poll::Pending
+    }
+
+    unsafe fn cancel(self: Pin<&mut Self>, _optr: *mut OVERLAPPED) -> io::Result<()> {
+        self.interrupt();
+        Ok(())
+    }
+
+    fn on_complete(self: Pin<&mut Self>, result: io::Result<usize>) -> io::Result<usize> {
+        result?;
+        self.take_result()
+            .expect("the result should be set before notifying")
+    }
+}
+
+impl OpCode for BlockingOp {
+    unsafe fn operate(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).operate(optr)
+    }
+
+    unsafe fn cancel(self: Pin<&mut Self>, optr: *mut OVERLAPPED) -> io::Result<()> {
+        Pin::new(&mut self.get_mut().inner).cancel(optr)
+    }
+
+    fn on_complete(self: Pin<&mut Self>, result: io::Result<usize>) -> io::Result<usize> {
+        Pin::new(&mut self.get_mut().inner).on_complete(result)
+    }
+}
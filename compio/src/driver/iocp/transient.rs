@@ -0,0 +1,123 @@
+use std::{error::Error, fmt::Display, io, time::Duration};
+
+use windows_sys::Win32::{
+    Foundation::ERROR_NO_SYSTEM_RESOURCES,
+    Networking::WinSock::{WSAEINTR, WSAENOBUFS, WSAEWOULDBLOCK},
+};
+
+/// The handling of the transient errors of the socket receives and sends, see
+/// [`ProactorBuilder::transient_errors`]. The errors, and the kinds they are
+/// mapped to, are
+///
+/// | Error                       | Operations         | Kind                           |
+/// |-----------------------------|--------------------|--------------------------------|
+/// | `WSAEWOULDBLOCK`            | receives and sends | [`io::ErrorKind::WouldBlock`]  |
+/// | `WSAENOBUFS`                | receives and sends | [`io::ErrorKind::WouldBlock`]  |
+/// | `ERROR_NO_SYSTEM_RESOURCES` | sends              | [`io::ErrorKind::WouldBlock`]  |
+/// | `WSAEINTR`                  | receives and sends | [`io::ErrorKind::Interrupted`] |
+///
+/// `ERROR_NO_SYSTEM_RESOURCES` is the error of a completion with
+/// `STATUS_INSUFFICIENT_RESOURCES`. The kinds are the ones of `EAGAIN` and
+/// `EINTR` on Unix, so that the same retry loops work on all platforms. The
+/// mapped error is a [`TransientError`] with the raw error.
+///
+/// The operations are the receives and sends of [`op::Recv`], [`op::Send`],
+/// [`op::RecvFrom`], [`op::SendTo`] and their vectored variants, which are
+/// used by the TCP and UDP sockets, and the raw sockets created by
+/// `socket2`.
+///
+/// [`ProactorBuilder::transient_errors`]: crate::driver::ProactorBuilder::transient_errors
+/// [`op::Recv`]: crate::op::Recv
+/// [`op::Send`]: crate::op::Send
+/// [`op::RecvFrom`]: crate::op::RecvFrom
+/// [`op::SendTo`]: crate::op::SendTo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransientErrors {
+    /// Perform the operation again, up to `attempts` times, waiting `backoff`
+    /// before the first retry and twice as long before each of the next. The
+    /// error is mapped if it still fails.
+    Retry {
+        /// The most retries of an operation.
+        attempts: u32,
+        /// The wait before the first retry.
+        backoff: Duration,
+    },
+    /// Map the errors at once.
+    Map,
+    /// Return the errors unchanged.
+    Raw,
+}
+
+impl Default for TransientErrors {
+    /// Retry 3 times, waiting 1ms before the first retry.
+    fn default() -> Self {
+        Self::Retry {
+            attempts: 3,
+            backoff: Duration::from_millis(1),
+        }
+    }
+}
+
+impl TransientErrors {
+    // The wait before the retry, or `None` if it shouldn't be retried.
+    pub(crate) fn backoff(&self, retries: u32) -> Option<Duration> {
+        match *self {
+            Self::Retry { attempts, backoff } if retries < attempts => {
+                Some(backoff.saturating_mul(1 << retries.min(16)))
+            }
+            _ => None,
+        }
+    }
+}
+
+// The kind of a transient error of a receive or a send, see the table of
+// `TransientErrors`.
+pub(crate) fn transient_kind(code: i32, send: bool) -> Option<io::ErrorKind> {
+    match code {
+        WSAEWOULDBLOCK | WSAENOBUFS => Some(io::ErrorKind::WouldBlock),
+        WSAEINTR => Some(io::ErrorKind::Interrupted),
+        _ if send && code == ERROR_NO_SYSTEM_RESOURCES as i32 => Some(io::ErrorKind::WouldBlock),
+        _ => None,
+    }
+}
+
+/// A transient error of a socket operation, mapped to
+/// [`io::ErrorKind::WouldBlock`] or [`io::ErrorKind::Interrupted`], see
+/// [`TransientErrors`].
+#[derive(Debug)]
+pub struct TransientError {
+    retries: u32,
+    source: io::Error,
+}
+
+impl TransientError {
+    pub(crate) fn wrap(kind: io::ErrorKind, retries: u32, source: io::Error) -> io::Error {
+        io::Error::new(kind, Self { retries, source })
+    }
+
+    /// The raw error of the last attempt, e.g., `WSAENOBUFS`.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.source.raw_os_error()
+    }
+
+    /// The retries performed before the error is returned.
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+}
+
+impl Display for TransientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "transient error after {} retries: {}",
+            self.retries, self.source
+        )
+    }
+}
+
+impl Error for TransientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
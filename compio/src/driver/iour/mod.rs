@@ -1,58 +1,364 @@
 #[doc(no_inline)]
 pub use std::os::fd::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
-use std::{collections::VecDeque, io, pin::Pin, time::Duration};
+use std::{
+    collections::{HashSet, VecDeque},
+    io,
+    os::fd::{BorrowedFd, OwnedFd},
+    pin::Pin,
+    sync::Arc,
+    task::Poll,
+    time::Duration,
+};
 
 use io_uring::{
     cqueue,
-    opcode::AsyncCancel,
-    squeue,
-    types::{SubmitArgs, Timespec},
-    IoUring,
+    opcode::{self, AsyncCancel},
+    squeue, IoUring, Probe,
 };
-pub(crate) use libc::{sockaddr_storage, socklen_t};
+pub(crate) use libc::socklen_t;
 use slab::Slab;
+pub(crate) use socket2::SockAddrStorage as sockaddr_storage;
 
-use crate::driver::Entry;
+use crate::{
+    driver::{Entry, Notifier, PollingMode, ProactorBuilder, SetupError},
+    syscall,
+};
 
 pub(crate) mod op;
+pub use crate::driver::unix::Interest;
 pub(crate) use crate::driver::unix::RawOp;
 
 /// Abstraction of io-uring operations.
 pub trait OpCode {
     /// Create submission entry.
     fn create_entry(self: Pin<&mut Self>) -> squeue::Entry;
+
+    /// Get the result of the operation from the completion entry. By default,
+    /// the result of the entry is returned unchanged.
+    fn on_complete(self: Pin<&mut Self>, result: io::Result<usize>) -> io::Result<usize> {
+        result
+    }
+
+    /// Cancel the operation by itself, e.g., interrupt the function running
+    /// on a thread. Returns `true` if it still completes with an entry, and
+    /// the driver shouldn't cancel the entry. By default, it returns `false`.
+    ///
+    /// The cancellation races with the operation, so it may be lost, e.g., an
+    /// interruption sent before the function enters its blocking syscall
+    /// doesn't wake the syscall. The operation then completes only when the
+    /// syscall returns by itself.
+    fn cancel(self: Pin<&mut Self>) -> bool {
+        false
+    }
+}
+
+/// The syscalls made by the io-uring driver, to generate a seccomp
+/// allowlist. The IO is performed by the kernel in the ring, so the ops need
+/// no more syscalls, except the blocking ops running on the thread pool with
+/// the syscalls of their own. The threads of the pool, std and the allocator
+/// need more, e.g., `brk`.
+///
+/// The driver makes them when it is built, and fails with [`SetupError`] if
+/// one is blocked.
+pub fn required_syscalls() -> &'static [&'static str] {
+    &[
+        // The ring.
+        "io_uring_setup",
+        "io_uring_enter",
+        "io_uring_register",
+        "mmap",
+        "munmap",
+        // The notifications of the blocking ops, and the cross-thread
+        // messages with a duplicated ring fd.
+        "eventfd2",
+        "read",
+        "write",
+        "fcntl",
+        "close",
+        // The thread pool.
+        "clone3",
+        "clone",
+        "futex",
+        "mprotect",
+        "madvise",
+        "sigaltstack",
+        "rt_sigprocmask",
+        "sched_getaffinity",
+        "set_robust_list",
+        "rseq",
+        "exit",
+        // The cancellation of the blocking ops.
+        "rt_sigaction",
+        "getpid",
+        "tgkill",
+    ]
+}
+
+/// A handle to post messages to a driver from other threads.
+#[derive(Debug, Clone)]
+pub struct MessageSender {
+    // A duplicated ring fd keeps the target ring alive.
+    pub(crate) fd: Arc<OwnedFd>,
+}
+
+// The opcodes of `io_uring_register`, which are not supported by `io-uring`
+// yet.
+const IORING_REGISTER_RING_FDS: libc::c_uint = 20;
+const IORING_UNREGISTER_RING_FDS: libc::c_uint = 21;
+const IORING_REGISTER_NAPI: libc::c_uint = 27;
+const IORING_UNREGISTER_NAPI: libc::c_uint = 28;
+
+// The flags of `io_uring_enter`, which is made by the driver itself to pass
+// the registered ring fd.
+const IORING_ENTER_GETEVENTS: libc::c_uint = 1 << 0;
+const IORING_ENTER_EXT_ARG: libc::c_uint = 1 << 3;
+const IORING_ENTER_REGISTERED_RING: libc::c_uint = 1 << 4;
+
+// The opcodes not supported by `io-uring` yet, since Linux 6.11.
+pub(crate) const IORING_OP_BIND: u8 = 56;
+pub(crate) const IORING_OP_LISTEN: u8 = 57;
+
+/// A direct descriptor, i.e., an index into the file table registered to the
+/// ring, instead of a file descriptor of the process. It is only valid for
+/// the ops of the ring, and can't be used by the other syscalls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DirectFd(pub u32);
+
+// `struct io_uring_napi`.
+#[repr(C)]
+#[derive(Default)]
+struct IoUringNapi {
+    busy_poll_to: u32,
+    prefer_busy_poll: u8,
+    pad: [u8; 3],
+    resv: u64,
+}
+
+// `struct io_uring_rsrc_update`.
+#[repr(C)]
+struct IoUringRsrcUpdate {
+    offset: u32,
+    resv: u32,
+    data: u64,
+}
+
+// `struct io_uring_getevents_arg`.
+#[repr(C)]
+struct IoUringGeteventsArg {
+    sigmask: u64,
+    sigmask_sz: u32,
+    pad: u32,
+    ts: u64,
+}
+
+// `struct __kernel_timespec`.
+#[repr(C)]
+struct KernelTimespec {
+    tv_sec: i64,
+    tv_nsec: i64,
 }
 
 /// Low-level driver of io-uring.
 pub(crate) struct Driver {
     inner: IoUring,
+    squeue: VecDeque<usize>,
     cancel_queue: VecDeque<u64>,
+    // The ops cancelled and not completed, which are not resubmitted after
+    // interrupted.
+    cancelled: HashSet<usize>,
+    messages: VecDeque<u64>,
+    probe: Probe,
+    napi: bool,
+    direct_table: bool,
+    direct_table_size: u32,
+    // The index of the ring fd registered to the thread, which saves the
+    // lookup of the fd in each enter. The registration is only valid on the
+    // thread, and the driver is not sent to the others; the messages from
+    // the other threads are posted with a duplicated fd instead.
+    registered_ring: Option<u32>,
 }
 
 impl Driver {
     const CANCEL: u64 = u64::MAX;
+    // The high 32 bits of the user data of the entries posted by `MsgRing`.
+    // The low 32 bits of the message are stored in the user data, and the high
+    // 32 bits in the result.
+    const MESSAGE_TAG: u64 = 0xFFFF_FFFE;
+
+    pub(crate) fn encode_message(msg: u64) -> (i32, u64) {
+        (
+            (msg >> 32) as i32,
+            Self::MESSAGE_TAG << 32 | (msg & 0xFFFF_FFFF),
+        )
+    }
 
-    pub fn new(entries: u32) -> io::Result<Self> {
-        Ok(Self {
-            inner: IoUring::new(entries)?,
+    pub fn new(builder: &ProactorBuilder) -> io::Result<Self> {
+        if builder.polling_mode != PollingMode::Auto {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the polling mode is only chosen by the polling driver",
+            ));
+        }
+        let mut inner = IoUring::builder();
+        if builder.io_poll {
+            inner.setup_iopoll();
+        }
+        if let Some(entries) = builder.cq_entries {
+            inner.setup_cqsize(entries);
+        }
+        if let Some(fd) = builder.workqueue {
+            inner.setup_attach_wq(fd);
+        }
+        let inner = inner
+            .build(builder.capacity)
+            .map_err(|e| SetupError::wrap("io_uring_setup", e))?;
+        // Make the syscalls the first ops need, so that a restricted
+        // environment fails here instead of at an arbitrary op.
+        let mut probe = Probe::new();
+        inner
+            .submitter()
+            .register_probe(&mut probe)
+            .map_err(|e| SetupError::wrap("io_uring_register", e))?;
+        inner
+            .submit()
+            .map_err(|e| SetupError::wrap("io_uring_enter", e))?;
+        // The blocking ops are waited by their own eventfds.
+        Notifier::new().map_err(|e| SetupError::wrap(Notifier::SYSCALL, e))?;
+        let mut this = Self {
+            inner,
+            squeue: VecDeque::with_capacity(builder.capacity as _),
             cancel_queue: VecDeque::default(),
-        })
+            cancelled: HashSet::default(),
+            messages: VecDeque::default(),
+            probe,
+            napi: false,
+            direct_table: false,
+            direct_table_size: builder.direct_table_size,
+            registered_ring: None,
+        };
+        this.registered_ring = this.register_ring_fd();
+        if let Some((timeout, prefer_busy_poll)) = builder.napi_busy_poll {
+            this.set_napi_busy_poll(Some(timeout), prefer_busy_poll)?;
+        }
+        if let Some((bounded, unbounded)) = builder.max_kernel_workers {
+            this.inner
+                .submitter()
+                .register_iowq_max_workers(&mut [bounded, unbounded])?;
+        }
+        Ok(this)
     }
 
-    // Auto means that it choose to wait or not automatically.
-    fn submit_auto(&mut self, timeout: Option<Duration>, wait: bool) -> io::Result<()> {
-        let res = if wait {
-            // Last part of submission queue, wait till timeout.
-            if let Some(duration) = timeout {
-                let timespec = timespec(duration);
-                let args = SubmitArgs::new().timespec(&timespec);
-                self.inner.submitter().submit_with_args(1, &args)
-            } else {
-                self.inner.submit_and_wait(1)
-            }
+    pub fn set_napi_busy_poll(
+        &mut self,
+        timeout: Option<Duration>,
+        prefer_busy_poll: bool,
+    ) -> io::Result<()> {
+        let fd = self.inner.as_raw_fd();
+        let res = if let Some(timeout) = timeout {
+            let mut napi = IoUringNapi {
+                busy_poll_to: timeout.as_micros().min(u32::MAX as _) as _,
+                prefer_busy_poll: prefer_busy_poll as _,
+                ..Default::default()
+            };
+            syscall!(syscall(
+                libc::SYS_io_uring_register,
+                fd,
+                IORING_REGISTER_NAPI,
+                &mut napi as *mut IoUringNapi,
+                1
+            ))
+        } else if self.napi {
+            syscall!(syscall(
+                libc::SYS_io_uring_register,
+                fd,
+                IORING_UNREGISTER_NAPI,
+                std::ptr::null_mut::<IoUringNapi>(),
+                1
+            ))
         } else {
-            self.inner.submit()
+            Ok(0)
+        };
+        match res {
+            Ok(_) => {
+                self.napi = timeout.is_some();
+                Ok(())
+            }
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "NAPI busy polling requires Linux 6.9 or later",
+            )),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Register the ring fd if the kernel supports it, since Linux 5.18. It
+    // fails silently, e.g., when the thread has registered too many rings.
+    fn register_ring_fd(&self) -> Option<u32> {
+        let mut update = IoUringRsrcUpdate {
+            // Allocate an index.
+            offset: u32::MAX,
+            resv: 0,
+            data: self.inner.as_raw_fd() as _,
         };
+        let res = syscall!(syscall(
+            libc::SYS_io_uring_register,
+            self.inner.as_raw_fd(),
+            IORING_REGISTER_RING_FDS,
+            &mut update as *mut IoUringRsrcUpdate,
+            1
+        ));
+        match res {
+            Ok(1) => Some(update.offset),
+            _ => None,
+        }
+    }
+
+    pub fn registered_ring(&self) -> bool {
+        self.registered_ring.is_some()
+    }
+
+    /// Whether the sockets could be created, bound and listened as direct
+    /// descriptors, i.e., the kernel supports the socket, bind and listen ops.
+    pub fn supports_direct_sockets(&self) -> bool {
+        self.probe.is_supported(opcode::Socket::CODE)
+            && self.probe.is_supported(IORING_OP_BIND)
+            && self.probe.is_supported(IORING_OP_LISTEN)
+    }
+
+    /// Whether the connections could be accepted as direct descriptors
+    /// allocated by the kernel, i.e., the kernel supports
+    /// `IORING_FILE_INDEX_ALLOC`, along with the socket op.
+    pub fn supports_direct_accept(&self) -> bool {
+        self.probe.is_supported(opcode::Socket::CODE)
+    }
+
+    /// The slots of the file table for the direct descriptors.
+    pub fn direct_table_size(&self) -> u32 {
+        self.direct_table_size
+    }
+
+    /// Register a sparse file table for the direct descriptors, if not yet.
+    pub fn register_direct_table(&mut self) -> io::Result<()> {
+        if !self.direct_table {
+            self.inner
+                .submitter()
+                .register_files_sparse(self.direct_table_size)?;
+            self.direct_table = true;
+        }
+        Ok(())
+    }
+
+    /// Remove the direct descriptor from the file table. The file is closed
+    /// after the ops in flight on it complete.
+    pub fn remove_direct(&mut self, fd: DirectFd) -> io::Result<()> {
+        self.inner.submitter().register_files_update(fd.0, &[-1])?;
+        Ok(())
+    }
+
+    // Auto means that it choose to wait or not automatically.
+    fn submit_auto(&mut self, timeout: Option<Duration>, wait: bool) -> io::Result<()> {
+        crate::count_syscall!("io_uring_enter");
+        let res = self.enter(timeout, wait);
         match res {
             Ok(_) => Ok(()),
             Err(e) => match e.raw_os_error() {
@@ -63,18 +369,71 @@ impl Driver {
         }
     }
 
-    fn flush_submissions(
-        &mut self,
-        ops: &mut impl Iterator<Item = usize>,
-        registry: &mut Slab<RawOp>,
-    ) -> bool {
+    // Submit the entries in the submission queue, and wait for a completion
+    // till `timeout` if `wait`. It is the same as `Submitter::submit_with_args`
+    // of `io-uring`, except for the registered ring fd.
+    fn enter(&mut self, timeout: Option<Duration>, wait: bool) -> io::Result<libc::c_long> {
+        let io_poll = self.inner.params().is_setup_iopoll();
+        let squeue = self.inner.submission();
+        let to_submit = squeue.len() as libc::c_uint;
+        let mut flags = 0;
+        if wait || io_poll || squeue.cq_overflow() {
+            flags |= IORING_ENTER_GETEVENTS;
+        }
+        drop(squeue);
+        let timespec = timeout.filter(|_| wait).map(|timeout| KernelTimespec {
+            tv_sec: timeout.as_secs() as _,
+            tv_nsec: timeout.subsec_nanos() as _,
+        });
+        let arg = timespec.as_ref().map(|timespec| IoUringGeteventsArg {
+            sigmask: 0,
+            sigmask_sz: 0,
+            pad: 0,
+            ts: timespec as *const KernelTimespec as u64,
+        });
+        let (arg, arg_size) = match &arg {
+            Some(arg) => {
+                flags |= IORING_ENTER_EXT_ARG;
+                (
+                    arg as *const IoUringGeteventsArg as *const libc::c_void,
+                    std::mem::size_of::<IoUringGeteventsArg>(),
+                )
+            }
+            None => (std::ptr::null(), 0),
+        };
+        let fd = match self.registered_ring {
+            Some(index) => {
+                flags |= IORING_ENTER_REGISTERED_RING;
+                index as libc::c_int
+            }
+            None => self.inner.as_raw_fd(),
+        };
+        let res = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_enter,
+                fd,
+                to_submit,
+                wait as libc::c_uint,
+                flags,
+                arg,
+                arg_size,
+            )
+        };
+        if res < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(res)
+        }
+    }
+
+    fn flush_submissions(&mut self, registry: &mut Slab<RawOp>) -> bool {
         let mut ended_ops = false;
         let mut ended_cancel = false;
 
         let mut inner_squeue = self.inner.submission();
 
         while !inner_squeue.is_full() {
-            if let Some(user_data) = ops.next() {
+            if let Some(user_data) = self.squeue.pop_front() {
                 let op = registry[user_data].as_pin();
                 let entry = op.create_entry().user_data(user_data as _);
                 unsafe { inner_squeue.push(&entry) }.expect("queue has enough space");
@@ -98,40 +457,107 @@ impl Driver {
         ended_ops && ended_cancel
     }
 
-    fn poll_entries(&mut self, entries: &mut impl Extend<Entry>) {
+    // Returns whether any entry or message is received.
+    fn poll_entries(
+        &mut self,
+        entries: &mut impl Extend<Entry>,
+        registry: &mut Slab<RawOp>,
+    ) -> bool {
+        let messages = &mut self.messages;
+        let squeue = &mut self.squeue;
+        let cancelled = &mut self.cancelled;
+        let mut received = false;
         let completed_entries =
             self.inner
                 .completion()
                 .filter_map(|entry| match entry.user_data() {
                     Self::CANCEL => None,
-                    _ => Some(create_entry(entry)),
+                    user_data if user_data >> 32 == Self::MESSAGE_TAG => {
+                        let msg = (entry.result() as u32 as u64) << 32 | (user_data & 0xFFFF_FFFF);
+                        messages.push_back(msg);
+                        received = true;
+                        None
+                    }
+                    user_data => {
+                        received = true;
+                        let user_data = user_data as usize;
+                        let cancelled = cancelled.remove(&user_data);
+                        // The op interrupted by a signal is submitted again,
+                        // unless it is cancelled.
+                        if entry.result() == -libc::EINTR && !cancelled {
+                            squeue.push_back(user_data);
+                            None
+                        } else {
+                            Some(create_entry(entry, registry))
+                        }
+                    }
                 });
         entries.extend(completed_entries);
+        received
+    }
+
+    pub fn message_sender(&self) -> io::Result<MessageSender> {
+        let fd = unsafe { BorrowedFd::borrow_raw(self.inner.as_raw_fd()) }.try_clone_to_owned()?;
+        Ok(MessageSender { fd: Arc::new(fd) })
+    }
+
+    pub fn pop_message(&mut self) -> Option<u64> {
+        self.messages.pop_front()
+    }
+
+    pub fn polling_mode(&self) -> Option<PollingMode> {
+        None
+    }
+
+    pub fn max_poll_fds(&self) -> usize {
+        0
     }
 
     pub fn attach(&mut self, _fd: RawFd) -> io::Result<()> {
         Ok(())
     }
 
-    pub fn cancel(&mut self, user_data: usize, _registry: &mut Slab<RawOp>) {
+    pub fn cancel(&mut self, user_data: usize, registry: &mut Slab<RawOp>) {
+        if registry
+            .get_mut(user_data)
+            .is_some_and(|op| op.as_pin().cancel())
+        {
+            return;
+        }
+        self.cancelled.insert(user_data);
         self.cancel_queue.push_back(user_data as _);
     }
 
+    // The operations pushed beyond the submission queue take more submissions
+    // in the next poll.
+    pub fn is_full(&self) -> bool {
+        self.squeue.len() >= self.inner.params().sq_entries() as usize
+    }
+
+    // The operations are submitted in the next poll.
+    pub fn push(&mut self, user_data: usize, _op: &mut RawOp) -> Poll<io::Result<usize>> {
+        // The key may be reused from an op cancelled after completion.
+        self.cancelled.remove(&user_data);
+        self.squeue.push_back(user_data);
+        Poll::Pending
+    }
+
     pub unsafe fn poll(
         &mut self,
         timeout: Option<Duration>,
-        ops: &mut impl Iterator<Item = usize>,
         entries: &mut impl Extend<Entry>,
         registry: &mut Slab<RawOp>,
     ) -> io::Result<()> {
-        let mut ops = ops.fuse();
         // Anyway we need to submit once, no matter there are entries in squeue.
+        let mut received = false;
         loop {
-            let ended = self.flush_submissions(&mut ops, registry);
+            let ended = self.flush_submissions(registry);
 
-            self.submit_auto(timeout, ended)?;
+            // Don't wait if the entries have completed while flushing a full
+            // submission queue.
+            self.submit_auto(timeout, ended && !received)?;
 
-            self.poll_entries(entries);
+            received |= self.poll_entries(entries, registry);
 
             if ended {
                 break;
@@ -147,7 +573,32 @@ impl AsRawFd for Driver {
     }
 }
 
-fn create_entry(entry: cqueue::Entry) -> Entry {
+impl Drop for Driver {
+    fn drop(&mut self) {
+        if self.napi {
+            self.set_napi_busy_poll(None, false).ok();
+        }
+        // The registration holds a reference of the ring until the thread
+        // exits, so it is released with the driver.
+        if let Some(index) = self.registered_ring {
+            let mut update = IoUringRsrcUpdate {
+                offset: index,
+                resv: 0,
+                data: 0,
+            };
+            syscall!(syscall(
+                libc::SYS_io_uring_register,
+                self.inner.as_raw_fd(),
+                IORING_UNREGISTER_RING_FDS,
+                &mut update as *mut IoUringRsrcUpdate,
+                1
+            ))
+            .ok();
+        }
+    }
+}
+
+fn create_entry(entry: cqueue::Entry, registry: &mut Slab<RawOp>) -> Entry {
     let result = entry.result();
     let result = if result < 0 {
         let result = if result == -libc::ECANCELED {
@@ -159,11 +610,10 @@ fn create_entry(entry: cqueue::Entry) -> Entry {
     } else {
         Ok(result as _)
     };
-    Entry::new(entry.user_data() as _, result)
-}
-
-fn timespec(duration: std::time::Duration) -> Timespec {
-    Timespec::new()
-        .sec(duration.as_secs())
-        .nsec(duration.subsec_nanos())
+    let user_data = entry.user_data() as usize;
+    let result = match registry.get_mut(user_data) {
+        Some(op) => op.as_pin().on_complete(result),
+        None => result,
+    };
+    Entry::new(user_data, result)
 }
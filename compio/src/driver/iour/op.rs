@@ -1,25 +1,41 @@
-use std::pin::Pin;
+use std::{
+    ffi::CString,
+    io,
+    os::fd::AsRawFd,
+    pin::Pin,
+    sync::{atomic::AtomicU32, Arc},
+};
 
 use io_uring::{
     opcode,
-    squeue::Entry,
-    types::{Fd, FsyncFlags},
+    squeue::{self, Entry},
+    types::{DestinationSlot, Fd, Fixed, FsyncFlags},
 };
-use libc::sockaddr_storage;
+use socket2::{Domain, Protocol, SockAddr, Type};
 
 pub use crate::driver::unix::op::*;
 use crate::{
-    buf::{AsIoSlices, AsIoSlicesMut, IoBuf, IoBufMut},
-    driver::OpCode,
+    buf::{AsIoSlices, AsIoSlicesMut, BufWrapper, IntoInner, IoBuf, IoBufMut, WrapBuf},
+    driver::{
+        sockaddr_storage, DirectFd, Driver, Interest, OpCode, RawFd, IORING_OP_BIND,
+        IORING_OP_LISTEN,
+    },
     op::*,
 };
 
+// The length of a single read or write is `u32`, and the rest of a larger
+// buffer is left to the next call, like a short read or write.
+fn clamp_len(len: usize) -> u32 {
+    len.try_into().unwrap_or(u32::MAX)
+}
+
 impl<T: IoBufMut> OpCode for ReadAt<T> {
     fn create_entry(mut self: Pin<&mut Self>) -> Entry {
         let fd = Fd(self.fd);
         let slice = self.buffer.as_uninit_slice();
-        opcode::Read::new(fd, slice.as_mut_ptr() as _, slice.len() as _)
+        opcode::Read::new(fd, slice.as_mut_ptr() as _, clamp_len(slice.len()))
             .offset(self.offset as _)
+            .rw_flags(self.rw_flags)
             .build()
     }
 }
@@ -27,8 +43,19 @@ impl<T: IoBufMut> OpCode for ReadAt<T> {
 impl<T: IoBuf> OpCode for WriteAt<T> {
     fn create_entry(self: Pin<&mut Self>) -> Entry {
         let slice = self.buffer.as_slice();
-        opcode::Write::new(Fd(self.fd), slice.as_ptr(), slice.len() as _)
+        opcode::Write::new(Fd(self.fd), slice.as_ptr(), clamp_len(slice.len()))
             .offset(self.offset as _)
+            .rw_flags(self.rw_flags)
+            .build()
+    }
+}
+
+impl<T: IoBuf> OpCode for WriteVectoredAt<T> {
+    fn create_entry(mut self: Pin<&mut Self>) -> Entry {
+        let (fd, offset) = (Fd(self.fd), self.offset);
+        let slices = self.set_slices();
+        opcode::Writev::new(fd, slices.as_ptr() as _, slices.len() as _)
+            .offset(offset as _)
             .build()
     }
 }
@@ -52,25 +79,47 @@ impl OpCode for Accept {
             &mut self.buffer as *mut sockaddr_storage as *mut libc::sockaddr,
             &mut self.addr_len,
         )
+        .flags(libc::SOCK_CLOEXEC)
         .build()
     }
 }
 
 impl OpCode for Connect {
     fn create_entry(self: Pin<&mut Self>) -> Entry {
-        opcode::Connect::new(Fd(self.fd), self.addr.as_ptr(), self.addr.len()).build()
+        opcode::Connect::new(Fd(self.fd), self.addr.as_ptr().cast(), self.addr.len()).build()
     }
 }
 
+impl<T: IoBuf> OpCode for ConnectWithData<T> {
+    fn create_entry(mut self: Pin<&mut Self>) -> Entry {
+        self.set_msg();
+        opcode::SendMsg::new(Fd(self.fd), &self.msg)
+            .flags(libc::MSG_FASTOPEN as _)
+            .build()
+    }
+}
+
+// The flag in `ioprio` of the recv and send entries, which is not exported by
+// `io-uring`. `Recv` doesn't support `ioprio` yet, so `RecvMsg` is used.
+const IORING_RECVSEND_POLL_FIRST: u16 = 1;
+
 impl<T: AsIoSlicesMut + Unpin> OpCode for RecvImpl<T> {
     fn create_entry(mut self: Pin<&mut Self>) -> Entry {
         self.slices = unsafe { self.buffer.as_io_slices_mut() };
-        opcode::Readv::new(
-            Fd(self.fd),
-            self.slices.as_ptr() as _,
-            self.slices.len() as _,
-        )
-        .build()
+        if self.poll_first {
+            self.msg.msg_iov = self.slices.as_mut_ptr() as _;
+            self.msg.msg_iovlen = self.slices.len() as _;
+            opcode::RecvMsg::new(Fd(self.fd), &mut self.msg)
+                .ioprio(IORING_RECVSEND_POLL_FIRST)
+                .build()
+        } else {
+            opcode::Readv::new(
+                Fd(self.fd),
+                self.slices.as_ptr() as _,
+                self.slices.len() as _,
+            )
+            .build()
+        }
     }
 }
 
@@ -90,7 +139,9 @@ impl<T: AsIoSlicesMut + Unpin> OpCode for RecvFromImpl<T> {
     #[allow(clippy::no_effect)]
     fn create_entry(mut self: Pin<&mut Self>) -> Entry {
         self.set_msg();
-        opcode::RecvMsg::new(Fd(self.fd), &mut self.msg).build()
+        opcode::RecvMsg::new(Fd(self.fd), &mut self.msg)
+            .flags(self.flags as _)
+            .build()
     }
 }
 
@@ -101,3 +152,632 @@ impl<T: AsIoSlices + Unpin> OpCode for SendToImpl<T> {
         opcode::SendMsg::new(Fd(self.fd), &self.msg).build()
     }
 }
+
+impl OpCode for MsgRing {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        let (result, user_data) = Driver::encode_message(self.msg);
+        opcode::MsgRingData::new(Fd(self.sender.fd.as_raw_fd()), result, user_data, None).build()
+    }
+}
+
+// The flags of futex2, not exported by libc yet.
+const FUTEX2_SIZE_U32: u32 = 0x02;
+const FUTEX2_PRIVATE: u32 = 128;
+const FUTEX_BITSET_MATCH_ANY: u64 = 0xFFFF_FFFF;
+
+/// Wait on a futex, if its value equals `expected`. It completes when woken
+/// by [`FutexWake`] or `futex(2)` with `FUTEX_WAKE_PRIVATE`, and fails with
+/// `EAGAIN` if the value doesn't match.
+///
+/// It requires Linux 6.7 or later.
+pub struct FutexWait {
+    pub(crate) futex: Arc<AtomicU32>,
+    pub(crate) expected: u32,
+}
+
+impl FutexWait {
+    /// Create [`FutexWait`].
+    pub fn new(futex: Arc<AtomicU32>, expected: u32) -> Self {
+        Self { futex, expected }
+    }
+}
+
+impl OpCode for FutexWait {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        opcode::FutexWait::new(
+            self.futex.as_ptr(),
+            self.expected as _,
+            FUTEX_BITSET_MATCH_ANY,
+            FUTEX2_SIZE_U32 | FUTEX2_PRIVATE,
+        )
+        .build()
+    }
+}
+
+/// Wake at most `count` waiters of a futex, and return the number of the woken
+/// ones.
+///
+/// It requires Linux 6.7 or later.
+pub struct FutexWake {
+    pub(crate) futex: Arc<AtomicU32>,
+    pub(crate) count: u32,
+}
+
+impl FutexWake {
+    /// Create [`FutexWake`].
+    pub fn new(futex: Arc<AtomicU32>, count: u32) -> Self {
+        Self { futex, count }
+    }
+}
+
+impl OpCode for FutexWake {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        opcode::FutexWake::new(
+            self.futex.as_ptr(),
+            self.count as _,
+            FUTEX_BITSET_MATCH_ANY,
+            FUTEX2_SIZE_U32 | FUTEX2_PRIVATE,
+        )
+        .build()
+    }
+}
+
+impl<T: IoBufMut> OpCode for GetRandom<T> {
+    fn create_entry(mut self: Pin<&mut Self>) -> Entry {
+        let fd = Fd(self.fd);
+        let slice = self.buffer.as_uninit_slice();
+        // The offset is ignored by `/dev/urandom`.
+        opcode::Read::new(fd, slice.as_mut_ptr() as _, clamp_len(slice.len())).build()
+    }
+}
+
+impl OpCode for SendFile {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        opcode::PollAdd::new(Fd(self.fd), libc::POLLOUT as _).build()
+    }
+
+    fn on_complete(self: Pin<&mut Self>, result: io::Result<usize>) -> io::Result<usize> {
+        result?;
+        self.send()
+    }
+}
+
+impl OpCode for Splice {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        let flags = match self.interest {
+            Interest::Readable => libc::POLLIN,
+            Interest::Writable => libc::POLLOUT,
+        };
+        opcode::PollAdd::new(Fd(self.fd()), flags as _).build()
+    }
+
+    fn on_complete(self: Pin<&mut Self>, result: io::Result<usize>) -> io::Result<usize> {
+        result?;
+        self.splice()
+    }
+}
+
+impl OpCode for WaitProcess {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        opcode::PollAdd::new(Fd(self.fd), libc::POLLIN as _).build()
+    }
+
+    fn on_complete(self: Pin<&mut Self>, result: io::Result<usize>) -> io::Result<usize> {
+        result?;
+        Ok(0)
+    }
+}
+
+impl OpCode for PollOnce {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        let flags = match self.interest {
+            Interest::Readable => libc::POLLIN,
+            Interest::Writable => libc::POLLOUT,
+        };
+        opcode::PollAdd::new(Fd(self.fd), flags as _).build()
+    }
+
+    fn on_complete(self: Pin<&mut Self>, result: io::Result<usize>) -> io::Result<usize> {
+        result?;
+        Ok(0)
+    }
+}
+
+impl<B: std::marker::Send + 'static> OpCode for BlockingBufOp<B> {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        // The buffer is never pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        match this.start() {
+            Ok(fd) => opcode::PollAdd::new(Fd(fd), libc::POLLIN as _).build(),
+            Err(e) => {
+                // Complete at once, and report the error in `on_complete`.
+                this.set_failed(e);
+                opcode::Nop::new().build()
+            }
+        }
+    }
+
+    fn on_complete(self: Pin<&mut Self>, result: io::Result<usize>) -> io::Result<usize> {
+        // Failed if the poll is cancelled.
+        result?;
+        self.take_result()
+            .expect("the result should be set before notifying")
+    }
+
+    fn cancel(self: Pin<&mut Self>) -> bool {
+        self.interrupt();
+        true
+    }
+}
+
+impl OpCode for BlockingOp {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        Pin::new(&mut self.get_mut().inner).create_entry()
+    }
+
+    fn on_complete(self: Pin<&mut Self>, result: io::Result<usize>) -> io::Result<usize> {
+        Pin::new(&mut self.get_mut().inner).on_complete(result)
+    }
+
+    fn cancel(self: Pin<&mut Self>) -> bool {
+        Pin::new(&mut self.get_mut().inner).cancel()
+    }
+}
+
+// `struct io_uring_sqe`, to build the entries of the opcodes not supported by
+// `io-uring` yet.
+#[repr(C)]
+#[derive(Default)]
+struct RawSqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    op_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    file_index: u32,
+    addr3: u64,
+    pad: u64,
+}
+
+impl RawSqe {
+    fn build(self) -> Entry {
+        // Safety: `Entry` is a `repr(C)` wrapper of `struct io_uring_sqe`.
+        unsafe { std::mem::transmute::<RawSqe, Entry>(self) }
+    }
+}
+
+/// Create a socket as a direct descriptor, which is given back as the result.
+/// The file table should be registered with
+/// [`Proactor::register_direct_table`](crate::driver::Proactor::register_direct_table).
+pub struct SocketDirect {
+    pub(crate) domain: i32,
+    pub(crate) ty: i32,
+    pub(crate) protocol: i32,
+}
+
+impl SocketDirect {
+    /// Create [`SocketDirect`].
+    pub fn new(domain: Domain, ty: Type, protocol: Option<Protocol>) -> Self {
+        Self {
+            domain: domain.into(),
+            ty: ty.into(),
+            protocol: protocol.map_or(0, Into::into),
+        }
+    }
+}
+
+impl OpCode for SocketDirect {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        opcode::Socket::new(self.domain, self.ty, self.protocol)
+            .file_index(Some(DestinationSlot::auto_target()))
+            .build()
+    }
+}
+
+/// Bind a direct descriptor to the address.
+pub struct BindDirect {
+    pub(crate) fd: DirectFd,
+    pub(crate) addr: SockAddr,
+}
+
+impl BindDirect {
+    /// Create [`BindDirect`].
+    pub fn new(fd: DirectFd, addr: SockAddr) -> Self {
+        Self { fd, addr }
+    }
+}
+
+impl OpCode for BindDirect {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        RawSqe {
+            opcode: IORING_OP_BIND,
+            flags: squeue::Flags::FIXED_FILE.bits(),
+            fd: self.fd.0 as _,
+            addr: self.addr.as_ptr() as _,
+            off: self.addr.len() as _,
+            ..Default::default()
+        }
+        .build()
+    }
+}
+
+/// Listen on a direct descriptor.
+pub struct ListenDirect {
+    pub(crate) fd: DirectFd,
+    pub(crate) backlog: i32,
+}
+
+impl ListenDirect {
+    /// Create [`ListenDirect`].
+    pub fn new(fd: DirectFd, backlog: i32) -> Self {
+        Self { fd, backlog }
+    }
+}
+
+impl OpCode for ListenDirect {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        RawSqe {
+            opcode: IORING_OP_LISTEN,
+            flags: squeue::Flags::FIXED_FILE.bits(),
+            fd: self.fd.0 as _,
+            len: self.backlog as _,
+            ..Default::default()
+        }
+        .build()
+    }
+}
+
+/// Accept a connection on a direct descriptor, or on a file descriptor, as a
+/// direct descriptor given back as the result. The slot is allocated by the
+/// kernel, i.e., `IORING_FILE_INDEX_ALLOC`, which requires Linux 5.19.
+pub struct AcceptDirect {
+    pub(crate) fd: Listener,
+    pub(crate) buffer: sockaddr_storage,
+    pub(crate) addr_len: libc::socklen_t,
+}
+
+// The listener of `AcceptDirect`.
+pub(crate) enum Listener {
+    Direct(DirectFd),
+    Fd(RawFd),
+}
+
+impl AcceptDirect {
+    /// Create [`AcceptDirect`].
+    pub fn new(fd: DirectFd) -> Self {
+        Self::with_listener(Listener::Direct(fd))
+    }
+
+    /// Create [`AcceptDirect`] on a normal listener.
+    pub fn from_fd(fd: RawFd) -> Self {
+        Self::with_listener(Listener::Fd(fd))
+    }
+
+    fn with_listener(fd: Listener) -> Self {
+        Self {
+            fd,
+            buffer: unsafe { std::mem::zeroed() },
+            addr_len: std::mem::size_of::<sockaddr_storage>() as _,
+        }
+    }
+
+    /// Get the remote address from the inner buffer.
+    pub fn into_addr(self) -> SockAddr {
+        unsafe { SockAddr::new(self.buffer, self.addr_len) }
+    }
+}
+
+impl OpCode for AcceptDirect {
+    fn create_entry(mut self: Pin<&mut Self>) -> Entry {
+        let addr = &mut self.buffer as *mut sockaddr_storage as *mut libc::sockaddr;
+        let addr_len = &mut self.addr_len as *mut libc::socklen_t;
+        match self.fd {
+            Listener::Direct(fd) => opcode::Accept::new(Fixed(fd.0), addr, addr_len),
+            Listener::Fd(fd) => opcode::Accept::new(Fd(fd), addr, addr_len),
+        }
+        .file_index(Some(DestinationSlot::auto_target()))
+        .build()
+    }
+}
+
+/// Receive data from a direct descriptor.
+pub struct RecvDirect<T: IoBufMut> {
+    pub(crate) fd: DirectFd,
+    pub(crate) buffer: BufWrapper<T>,
+}
+
+impl<T: IoBufMut> RecvDirect<T> {
+    /// Create [`RecvDirect`].
+    pub fn new(fd: DirectFd, buffer: T) -> Self {
+        Self {
+            fd,
+            buffer: BufWrapper::new(buffer),
+        }
+    }
+}
+
+impl<T: IoBufMut> IntoInner for RecvDirect<T> {
+    type Inner = BufWrapper<T>;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+impl<T: IoBufMut> OpCode for RecvDirect<T> {
+    fn create_entry(mut self: Pin<&mut Self>) -> Entry {
+        let fd = Fixed(self.fd.0);
+        let slice = self.buffer.as_uninit_slice();
+        opcode::Recv::new(fd, slice.as_mut_ptr() as _, clamp_len(slice.len())).build()
+    }
+}
+
+/// Send data to a direct descriptor.
+pub struct SendDirect<T: IoBuf> {
+    pub(crate) fd: DirectFd,
+    pub(crate) buffer: BufWrapper<T>,
+}
+
+impl<T: IoBuf> SendDirect<T> {
+    /// Create [`SendDirect`].
+    pub fn new(fd: DirectFd, buffer: T) -> Self {
+        Self {
+            fd,
+            buffer: BufWrapper::new(buffer),
+        }
+    }
+}
+
+impl<T: IoBuf> IntoInner for SendDirect<T> {
+    type Inner = BufWrapper<T>;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+impl<T: IoBuf> OpCode for SendDirect<T> {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        let slice = self.buffer.as_slice();
+        opcode::Send::new(Fixed(self.fd.0), slice.as_ptr(), clamp_len(slice.len()))
+            .flags(libc::MSG_NOSIGNAL)
+            .build()
+    }
+}
+
+/// Shut down a direct descriptor, see `shutdown(2)`.
+pub struct ShutdownDirect {
+    pub(crate) fd: DirectFd,
+    pub(crate) how: i32,
+}
+
+impl ShutdownDirect {
+    /// Create [`ShutdownDirect`].
+    pub fn new(fd: DirectFd, how: std::net::Shutdown) -> Self {
+        let how = match how {
+            std::net::Shutdown::Write => libc::SHUT_WR,
+            std::net::Shutdown::Read => libc::SHUT_RD,
+            std::net::Shutdown::Both => libc::SHUT_RDWR,
+        };
+        Self { fd, how }
+    }
+}
+
+impl OpCode for ShutdownDirect {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        opcode::Shutdown::new(Fixed(self.fd.0), self.how).build()
+    }
+}
+
+/// Close a direct descriptor, and free its slot in the file table when it
+/// completes.
+pub struct CloseDirect {
+    pub(crate) fd: DirectFd,
+}
+
+impl CloseDirect {
+    /// Create [`CloseDirect`].
+    pub fn new(fd: DirectFd) -> Self {
+        Self { fd }
+    }
+}
+
+impl OpCode for CloseDirect {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        opcode::Close::new(Fixed(self.fd.0)).build()
+    }
+}
+
+/// Open a file relative to a directory, and return the fd. See `openat(2)`.
+///
+/// It requires Linux 5.6 or later.
+pub struct OpenAt {
+    pub(crate) dirfd: RawFd,
+    pub(crate) path: CString,
+    pub(crate) flags: i32,
+    pub(crate) mode: libc::mode_t,
+}
+
+impl OpenAt {
+    /// Create [`OpenAt`].
+    pub fn new(dirfd: RawFd, path: CString, flags: i32, mode: libc::mode_t) -> Self {
+        Self {
+            dirfd,
+            path,
+            flags,
+            mode,
+        }
+    }
+}
+
+impl OpCode for OpenAt {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        opcode::OpenAt::new(Fd(self.dirfd), self.path.as_ptr())
+            .flags(self.flags)
+            .mode(self.mode)
+            .build()
+    }
+}
+
+/// Create a directory relative to a directory. See `mkdirat(2)`.
+///
+/// It requires Linux 5.15 or later.
+pub struct MkdirAt {
+    pub(crate) dirfd: RawFd,
+    pub(crate) path: CString,
+    pub(crate) mode: libc::mode_t,
+}
+
+impl MkdirAt {
+    /// Create [`MkdirAt`].
+    pub fn new(dirfd: RawFd, path: CString, mode: libc::mode_t) -> Self {
+        Self { dirfd, path, mode }
+    }
+}
+
+impl OpCode for MkdirAt {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        opcode::MkDirAt::new(Fd(self.dirfd), self.path.as_ptr())
+            .mode(self.mode)
+            .build()
+    }
+}
+
+/// Remove a file, or a directory with `AT_REMOVEDIR`, relative to a
+/// directory. See `unlinkat(2)`.
+///
+/// It requires Linux 5.11 or later.
+pub struct UnlinkAt {
+    pub(crate) dirfd: RawFd,
+    pub(crate) path: CString,
+    pub(crate) flags: i32,
+}
+
+impl UnlinkAt {
+    /// Create [`UnlinkAt`].
+    pub fn new(dirfd: RawFd, path: CString, flags: i32) -> Self {
+        Self { dirfd, path, flags }
+    }
+}
+
+impl OpCode for UnlinkAt {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        opcode::UnlinkAt::new(Fd(self.dirfd), self.path.as_ptr())
+            .flags(self.flags)
+            .build()
+    }
+}
+
+/// Rename a file relative to a directory to another one relative to another
+/// directory. See `renameat(2)`.
+///
+/// It requires Linux 5.11 or later.
+pub struct RenameAt {
+    pub(crate) old_dirfd: RawFd,
+    pub(crate) old_path: CString,
+    pub(crate) new_dirfd: RawFd,
+    pub(crate) new_path: CString,
+}
+
+impl RenameAt {
+    /// Create [`RenameAt`].
+    pub fn new(old_dirfd: RawFd, old_path: CString, new_dirfd: RawFd, new_path: CString) -> Self {
+        Self {
+            old_dirfd,
+            old_path,
+            new_dirfd,
+            new_path,
+        }
+    }
+}
+
+impl OpCode for RenameAt {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        opcode::RenameAt::new(
+            Fd(self.old_dirfd),
+            self.old_path.as_ptr(),
+            Fd(self.new_dirfd),
+            self.new_path.as_ptr(),
+        )
+        .build()
+    }
+}
+
+/// Create a symbolic link to `target` relative to a directory. See
+/// `symlinkat(2)`.
+///
+/// It requires Linux 5.15 or later.
+pub struct SymlinkAt {
+    pub(crate) target: CString,
+    pub(crate) dirfd: RawFd,
+    pub(crate) path: CString,
+}
+
+impl SymlinkAt {
+    /// Create [`SymlinkAt`].
+    pub fn new(target: CString, dirfd: RawFd, path: CString) -> Self {
+        Self {
+            target,
+            dirfd,
+            path,
+        }
+    }
+}
+
+impl OpCode for SymlinkAt {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        opcode::SymlinkAt::new(Fd(self.dirfd), self.target.as_ptr(), self.path.as_ptr()).build()
+    }
+}
+
+/// Get the status of a file relative to a directory, or of `dirfd` itself
+/// with `AT_EMPTY_PATH` and an empty path. See `statx(2)`.
+///
+/// It requires Linux 5.6 or later.
+pub struct Statx {
+    pub(crate) dirfd: RawFd,
+    pub(crate) path: CString,
+    pub(crate) flags: i32,
+    pub(crate) mask: u32,
+    pub(crate) statx: libc::statx,
+}
+
+impl Statx {
+    /// Create [`Statx`].
+    pub fn new(dirfd: RawFd, path: CString, flags: i32, mask: u32) -> Self {
+        Self {
+            dirfd,
+            path,
+            flags,
+            mask,
+            statx: unsafe { std::mem::zeroed() },
+        }
+    }
+
+    /// The status filled by the kernel after the op succeeds. Only the fields
+    /// in `stx_mask` are valid, which may differ from the requested mask.
+    pub fn statx(&self) -> &libc::statx {
+        &self.statx
+    }
+}
+
+impl OpCode for Statx {
+    fn create_entry(self: Pin<&mut Self>) -> Entry {
+        // The status is never moved out of the op.
+        let this = unsafe { self.get_unchecked_mut() };
+        opcode::Statx::new(
+            Fd(this.dirfd),
+            this.path.as_ptr(),
+            (&mut this.statx as *mut libc::statx).cast(),
+        )
+        .flags(this.flags)
+        .mask(this.mask)
+        .build()
+    }
+}
@@ -8,7 +8,13 @@
 ))]
 compile_error!("You must choose one of these features: [\"io-uring\", \"polling\"]");
 
-use std::{collections::VecDeque, io, time::Duration};
+use std::{
+    collections::VecDeque,
+    io,
+    rc::Rc,
+    task::Poll,
+    time::{Duration, Instant},
+};
 
 use slab::Slab;
 
@@ -16,6 +22,23 @@ use crate::BufResult;
 
 #[cfg(unix)]
 mod unix;
+#[cfg(unix)]
+pub(crate) use unix::Notifier;
+#[cfg(unix)]
+pub use unix::SetupError;
+
+mod op_pool;
+pub use op_pool::OpPoolStats;
+use op_pool::OpPool;
+mod owned;
+pub use owned::*;
+pub(crate) mod pool;
+#[cfg(feature = "syscall-count")]
+mod syscall_count;
+#[cfg(feature = "syscall-count")]
+pub(crate) use syscall_count::record_syscall;
+#[cfg(feature = "syscall-count")]
+pub use syscall_count::{reset_syscall_counts, syscall_counts, SyscallCounts};
 
 cfg_if::cfg_if! {
     if #[cfg(target_os = "windows")] {
@@ -41,7 +64,7 @@ cfg_if::cfg_if! {
 /// use arrayvec::ArrayVec;
 /// use compio::{
 ///     buf::IntoInner,
-///     driver::{AsRawFd, Entry, Proactor},
+///     driver::{AsRawFd, Entry, Proactor, PushEntry},
 ///     net::UdpSocket,
 ///     op,
 /// };
@@ -65,12 +88,16 @@ cfg_if::cfg_if! {
 ///
 /// // write data
 /// let op_write = op::Send::new(socket.as_raw_fd(), "hello world");
-/// let key_write = driver.push(op_write);
+/// let PushEntry::Pending(key_write) = driver.push_entry(op_write) else {
+///     unreachable!("sockets are always waited")
+/// };
 ///
 /// // read data
 /// let buf = Vec::with_capacity(32);
 /// let op_read = op::Recv::new(other_socket.as_raw_fd(), buf);
-/// let key_read = driver.push(op_read);
+/// let PushEntry::Pending(key_read) = driver.push_entry(op_read) else {
+///     unreachable!("sockets are always waited")
+/// };
 ///
 /// let mut entries = ArrayVec::<Entry, 2>::new();
 ///
@@ -101,22 +128,31 @@ cfg_if::cfg_if! {
 pub struct Proactor {
     driver: Driver,
     ops: Slab<RawOp>,
-    squeue: VecDeque<usize>,
+    op_pool: Rc<OpPool>,
+    // The entries of the operations pushed by the deprecated `push`, and
+    // completed inline.
+    ready: VecDeque<Entry>,
+    spin: Duration,
+    stats: PollStats,
+    capacity: u32,
+    #[cfg(feature = "metrics")]
+    latency_metrics: bool,
+    #[cfg(feature = "time")]
+    timer_resolution: Duration,
+    #[cfg(feature = "test-util")]
+    start_paused: bool,
+    max_ops_per_fd: u32,
 }
 
 impl Proactor {
     /// Create [`Proactor`] with 1024 entries.
     pub fn new() -> io::Result<Self> {
-        Self::with_entries(1024)
+        ProactorBuilder::new().build()
     }
 
     /// Create [`Proactor`] with specified entries.
     pub fn with_entries(entries: u32) -> io::Result<Self> {
-        Ok(Self {
-            driver: Driver::new(entries)?,
-            ops: Slab::with_capacity(entries as _),
-            squeue: VecDeque::with_capacity(entries as _),
-        })
+        ProactorBuilder::new().capacity(entries).build()
     }
 
     /// Attach an fd to the driver.
@@ -137,36 +173,288 @@ impl Proactor {
     /// but just don't return from [`Proactor::poll`]. Therefore, although an
     /// operation is cancelled, you should not reuse its `user_data`.
     ///
-    /// It is well-defined to cancel before polling. If the submitted operation
-    /// contains a cancelled user-defined data, the operation will be ignored.
+    /// It is well-defined to cancel before polling, even if the operation
+    /// hasn't been submitted to the kernel yet.
+    ///
+    /// The operations performed synchronously in the driver, e.g., [`Sync`]
+    /// with IOCP or polling, couldn't be interrupted. If such an operation has
+    /// started, its real result is returned exactly once, instead of a
+    /// cancellation error. The blocking syscall of a running [`BlockingBufOp`]
+    /// is interrupted instead, and it completes after the function returns.
+    ///
+    /// [`Sync`]: crate::op::Sync
+    /// [`BlockingBufOp`]: crate::op::BlockingBufOp
     pub fn cancel(&mut self, user_data: usize) {
         self.driver.cancel(user_data, &mut self.ops);
     }
 
+    /// Whether the driver would block on submitting more operations. The
+    /// operations pushed then are still accepted, but the next
+    /// [`Proactor::poll`] submits them in batches, and it only waits after
+    /// all are submitted. A caller applying backpressure should wait for a
+    /// poll before pushing more.
+    ///
+    /// ## Platform specific
+    /// * io-uring: the pushed operations fill the submission queue, whose
+    ///   size is the capacity.
+    /// * IOCP/polling: it is always `false`, because the operations are
+    ///   submitted when pushed.
+    pub fn is_full(&self) -> bool {
+        self.driver.is_full()
+    }
+
+    /// Push an operation into the driver.
+    ///
+    /// If the operation completes inline, it is given back with the result in
+    /// [`PushEntry::Ready`], and won't be returned from [`Proactor::poll`].
+    /// Otherwise, the unique key, called user-defined data, associated with it
+    /// is returned in [`PushEntry::Pending`].
+    ///
+    /// The proactor owns the pending operation until its entry is given to
+    /// [`Proactor::pop`], so the operation is never freed while the kernel
+    /// uses it. The key is valid till then, and it may be reused by a later
+    /// operation after that. The caller should remember the type of the
+    /// operation of each key to restore it with [`Operation::into_op`]; see
+    /// [`OwnedOps`] to track them safely.
+    ///
+    /// ## Platform specific
+    /// * io-uring: the operations are always pending, and submitted in the
+    ///   next [`Proactor::poll`].
+    /// * polling: the operations are tried at once. E.g., the reads and writes
+    ///   of files usually complete inline.
+    /// * IOCP: the operations are performed at once. The ones completing
+    ///   without posting to the port, e.g., the blocking ones, complete inline.
+    pub fn push_entry<T: OpCode + 'static>(
+        &mut self,
+        op: T,
+    ) -> PushEntry<usize, BufResult<usize, T>> {
+        let entry = self.ops.vacant_entry();
+        let user_data = entry.key();
+        let mut op = RawOp::new(user_data, op, &self.op_pool);
+        match self.driver.push(user_data, &mut op) {
+            Poll::Pending => {
+                entry.insert(op);
+                PushEntry::Pending(user_data)
+            }
+            Poll::Ready(res) => PushEntry::Ready((res, unsafe { op.into_inner::<T>() })),
+        }
+    }
+
     /// Push an operation into the driver, and return the unique key, called
     /// user-defined data, associated with it.
+    ///
+    /// The operations completed inline are returned from the next
+    /// [`Proactor::poll`], which doesn't wait then.
+    #[deprecated(
+        since = "0.8.0",
+        note = "use `Proactor::push_entry`, which gives back the operations completed inline"
+    )]
     pub fn push(&mut self, op: impl OpCode + 'static) -> usize {
         let entry = self.ops.vacant_entry();
         let user_data = entry.key();
-        let op = RawOp::new(user_data, op);
-        entry.insert(op);
-        self.squeue.push_back(user_data);
+        let op = entry.insert(RawOp::new(user_data, op, &self.op_pool));
+        if let Poll::Ready(res) = self.driver.push(user_data, op) {
+            self.ready.push_back(Entry::new(user_data, res));
+        }
         user_data
     }
 
     /// Poll the driver and get completed entries.
     /// You need to call [`Proactor::pop`] to get the pushed operations.
+    ///
+    /// If a spin budget is set by [`ProactorBuilder::spin_before_wait`], the
+    /// driver is polled without blocking until some entries complete or the
+    /// budget expires, before blocking till `timeout`.
+    ///
+    /// A wait interrupted by a signal is continued till `timeout`, and the
+    /// operations interrupted by a signal are retried by the driver, so they
+    /// complete with [`io::ErrorKind::Interrupted`] only if cancelled.
     pub fn poll(
         &mut self,
         timeout: Option<Duration>,
         entries: &mut impl Extend<Entry>,
     ) -> io::Result<()> {
-        let mut iter = std::iter::from_fn(|| self.squeue.pop_front());
-        unsafe {
-            self.driver
-                .poll(timeout, &mut iter, entries, &mut self.ops)?;
+        self.stats.polls += 1;
+        if !self.ready.is_empty() {
+            entries.extend(self.ready.drain(..));
+            return match self.poll_driver(Some(Duration::ZERO), entries) {
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => Ok(()),
+                res => res,
+            };
+        }
+        if timeout == Some(Duration::ZERO) {
+            return self.poll_driver(timeout, entries);
+        }
+        let start = Instant::now();
+        if !self.spin.is_zero() {
+            let budget = timeout.map_or(self.spin, |timeout| timeout.min(self.spin));
+            loop {
+                let mut counter = CountExtend::new(entries);
+                match self.poll_driver(Some(Duration::ZERO), &mut counter) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+                    Err(e) => return Err(e),
+                }
+                if counter.count > 0 {
+                    self.stats.spin_completions += 1;
+                    return Ok(());
+                }
+                if start.elapsed() >= budget {
+                    break;
+                }
+                std::hint::spin_loop();
+            }
+        }
+        self.stats.blocking_waits += 1;
+        let timeout = timeout.map(|timeout| timeout.saturating_sub(start.elapsed()));
+        self.poll_driver(timeout, entries)
+    }
+
+    // The wait interrupted by a signal is retried with the rest of the timeout,
+    // so that a signal handler doesn't wake the runtime early.
+    fn poll_driver(
+        &mut self,
+        mut timeout: Option<Duration>,
+        entries: &mut impl Extend<Entry>,
+    ) -> io::Result<()> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            match unsafe { self.driver.poll(timeout, entries, &mut self.ops) } {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                    timeout = deadline
+                        .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+                }
+                res => return res,
+            }
         }
-        Ok(())
+    }
+
+    /// Get a handle to post messages to this proactor from other threads. See
+    /// [`MsgRing`](crate::op::MsgRing).
+    pub fn message_sender(&self) -> io::Result<MessageSender> {
+        self.driver.message_sender()
+    }
+
+    /// Pop a message received during [`Proactor::poll`].
+    pub fn pop_message(&mut self) -> Option<u64> {
+        self.driver.pop_message()
+    }
+
+    /// Enable NAPI busy polling with `timeout`, or disable it with `None`,
+    /// e.g., only in a latency-critical phase. See
+    /// [`ProactorBuilder::napi_busy_poll`].
+    ///
+    /// ## Platform specific
+    /// * io-uring: it fails with [`io::ErrorKind::Unsupported`] before Linux
+    ///   6.9.
+    /// * IOCP/polling: enabling fails with [`io::ErrorKind::Unsupported`].
+    pub fn set_napi_busy_poll(
+        &mut self,
+        timeout: Option<Duration>,
+        prefer_busy_poll: bool,
+    ) -> io::Result<()> {
+        self.driver.set_napi_busy_poll(timeout, prefer_busy_poll)
+    }
+
+    /// Whether the sockets could be created, bound and listened as direct
+    /// descriptors. It requires Linux 6.11.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn supports_direct_sockets(&self) -> bool {
+        self.driver.supports_direct_sockets()
+    }
+
+    /// Whether the connections could be accepted as direct descriptors
+    /// allocated by the kernel. It requires Linux 5.19.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn supports_direct_accept(&self) -> bool {
+        self.driver.supports_direct_accept()
+    }
+
+    /// The slots of the file table for the direct descriptors, see
+    /// [`ProactorBuilder::direct_table_size`].
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn direct_table_size(&self) -> u32 {
+        self.driver.direct_table_size()
+    }
+
+    /// Whether the ring fd is registered to the thread, so that the driver
+    /// enters the ring with the registered index instead of the fd, which
+    /// saves the lookup of the fd in each `io_uring_enter`, a few percent of
+    /// the overhead at high submission rates. The syscalls made are the same.
+    ///
+    /// It is registered when the driver is built if the kernel supports it,
+    /// since Linux 5.18, and each thread could register 16 rings at most;
+    /// otherwise the fd is used.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn registered_ring(&self) -> bool {
+        self.driver.registered_ring()
+    }
+
+    /// Register the file table for the direct descriptors, e.g., before
+    /// submitting [`SocketDirect`](crate::op::SocketDirect). It is registered
+    /// only once.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn register_direct_table(&mut self) -> io::Result<()> {
+        self.driver.register_direct_table()
+    }
+
+    /// Remove the direct descriptor from the file table, which closes it.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn remove_direct(&mut self, fd: DirectFd) -> io::Result<()> {
+        self.driver.remove_direct(fd)
+    }
+
+    /// The mechanism waiting for the readiness, or `None` if the driver is
+    /// completion-based. See [`ProactorBuilder::polling_mode`].
+    pub fn polling_mode(&self) -> Option<PollingMode> {
+        self.driver.polling_mode()
+    }
+
+    /// The most fds waited at once with [`PollingMode::Poll`], which scans all
+    /// of them in each wait. A large number, e.g., above 1024, suggests
+    /// [`PollingMode::Auto`], which scales better. It is 0 with the other
+    /// modes, the native `poll` of the platforms without a better one, and
+    /// the completion-based drivers.
+    pub fn max_poll_fds(&self) -> usize {
+        self.driver.max_poll_fds()
+    }
+
+    /// The statistics of [`Proactor::poll`], to tune the spin budget.
+    pub fn poll_stats(&self) -> PollStats {
+        self.stats
+    }
+
+    /// The statistics of the freelists of the operations, see
+    /// [`ProactorBuilder::op_pool_capacity`].
+    pub fn op_pool_stats(&self) -> OpPoolStats {
+        self.op_pool.stats()
+    }
+
+    // The capacity of the inner queues, which bounds the entries completed in
+    // a poll in most cases.
+    #[cfg_attr(not(feature = "runtime"), allow(dead_code))]
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity as _
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn latency_metrics(&self) -> bool {
+        self.latency_metrics
+    }
+
+    #[cfg(feature = "time")]
+    pub(crate) fn timer_resolution(&self) -> Duration {
+        self.timer_resolution
+    }
+
+    #[cfg(feature = "test-util")]
+    pub(crate) fn start_paused(&self) -> bool {
+        self.start_paused
+    }
+
+    #[cfg_attr(not(feature = "runtime"), allow(dead_code))]
+    pub(crate) fn max_ops_per_fd(&self) -> u32 {
+        self.max_ops_per_fd
     }
 
     /// Get the pushed operations from the completion entries.
@@ -187,12 +475,474 @@ impl Proactor {
     }
 }
 
+/// Builder for [`Proactor`].
+#[derive(Debug, Clone)]
+pub struct ProactorBuilder {
+    capacity: u32,
+    #[cfg_attr(not(all(target_os = "linux", feature = "io-uring")), allow(dead_code))]
+    cq_entries: Option<u32>,
+    spin: Duration,
+    #[cfg_attr(not(all(target_os = "linux", feature = "io-uring")), allow(dead_code))]
+    io_poll: bool,
+    #[cfg_attr(not(all(target_os = "linux", feature = "io-uring")), allow(dead_code))]
+    napi_busy_poll: Option<(Duration, bool)>,
+    #[cfg_attr(not(all(target_os = "linux", feature = "io-uring")), allow(dead_code))]
+    workqueue: Option<RawFd>,
+    #[cfg_attr(not(all(target_os = "linux", feature = "io-uring")), allow(dead_code))]
+    max_kernel_workers: Option<(u32, u32)>,
+    #[cfg_attr(not(all(target_os = "linux", feature = "io-uring")), allow(dead_code))]
+    direct_table_size: u32,
+    #[cfg(feature = "metrics")]
+    latency_metrics: bool,
+    #[cfg(feature = "time")]
+    timer_resolution: Duration,
+    #[cfg(feature = "test-util")]
+    start_paused: bool,
+    max_ops_per_fd: u32,
+    op_pool_capacity: usize,
+    polling_mode: PollingMode,
+    #[cfg(target_os = "windows")]
+    existing_port: Option<std::sync::Arc<std::os::windows::io::OwnedHandle>>,
+    #[cfg(target_os = "windows")]
+    on_foreign_completion: Option<ForeignCompletion>,
+    #[cfg(target_os = "windows")]
+    transient_errors: TransientErrors,
+}
+
+impl ProactorBuilder {
+    /// Create the builder with default config: 1024 entries, no spinning.
+    pub fn new() -> Self {
+        Self {
+            capacity: 1024,
+            cq_entries: None,
+            spin: Duration::ZERO,
+            io_poll: false,
+            napi_busy_poll: None,
+            workqueue: None,
+            max_kernel_workers: None,
+            direct_table_size: 4096,
+            #[cfg(feature = "metrics")]
+            latency_metrics: false,
+            #[cfg(feature = "time")]
+            timer_resolution: Duration::ZERO,
+            #[cfg(feature = "test-util")]
+            start_paused: false,
+            max_ops_per_fd: 0,
+            op_pool_capacity: 64,
+            polling_mode: PollingMode::Auto,
+            #[cfg(target_os = "windows")]
+            existing_port: None,
+            #[cfg(target_os = "windows")]
+            on_foreign_completion: None,
+            #[cfg(target_os = "windows")]
+            transient_errors: TransientErrors::default(),
+        }
+    }
+
+    /// Set the capacity of the inner queues.
+    pub fn capacity(mut self, capacity: u32) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Set the size of the completion queue, which could be larger than the
+    /// submission queue for the bursts of completions, e.g., many connections
+    /// becoming readable at once. Default to twice the capacity.
+    ///
+    /// ## Platform specific
+    /// * io-uring: `IORING_SETUP_CQSIZE`. The size is rounded up to a power of
+    ///   two, and [`build`] fails if it is smaller than the capacity.
+    /// * IOCP/polling: it is ignored.
+    ///
+    /// [`build`]: ProactorBuilder::build
+    pub fn cq_entries(mut self, entries: u32) -> Self {
+        self.cq_entries = Some(entries);
+        self
+    }
+
+    /// Set the busy-poll budget before waiting for completions in
+    /// [`Proactor::poll`]. It lowers the wakeup latency by burning CPU.
+    /// Default to zero, which means no spinning.
+    pub fn spin_before_wait(mut self, duration: Duration) -> Self {
+        self.spin = duration;
+        self
+    }
+
+    /// Enable busy polling in the kernel, i.e., `IORING_SETUP_IOPOLL`.
+    /// Default to `false`.
+    ///
+    /// ## Platform specific
+    /// * io-uring: only files opened with `O_DIRECT` on devices supporting
+    ///   polling are allowed, and the other operations, e.g., the socket ones,
+    ///   fail.
+    /// * IOCP/polling: it is ignored.
+    pub fn io_poll(mut self, io_poll: bool) -> Self {
+        self.io_poll = io_poll;
+        self
+    }
+
+    /// Enable NAPI busy polling of the network devices for the sockets
+    /// waited by the driver, i.e., `IORING_REGISTER_NAPI`. The devices are
+    /// polled for at most `timeout`, at microsecond granularity, instead of
+    /// waiting for the interrupts. It lowers the receive latency by burning
+    /// CPU. If `prefer_busy_poll` is `true`, the interrupts are deferred while
+    /// busy polling. Default to disabled.
+    ///
+    /// It could be toggled later by [`Proactor::set_napi_busy_poll`].
+    ///
+    /// ## Platform specific
+    /// * io-uring: Linux 6.9 or later is required, and [`build`] fails with
+    ///   [`io::ErrorKind::Unsupported`] on the older kernels. The busy polling
+    ///   happens when waiting for the completions. With `IORING_SETUP_SQPOLL`,
+    ///   it would happen in the submission thread instead, but the driver
+    ///   doesn't set it up.
+    /// * IOCP/polling: it is ignored.
+    ///
+    /// [`build`]: ProactorBuilder::build
+    pub fn napi_busy_poll(mut self, timeout: Duration, prefer_busy_poll: bool) -> Self {
+        self.napi_busy_poll = Some((timeout, prefer_busy_poll));
+        self
+    }
+
+    /// Share the kernel worker pool of another driver, i.e.,
+    /// `IORING_SETUP_ATTACH_WQ`, instead of spawning a new one. It bounds the
+    /// count of the kernel threads when there are many drivers, e.g., one per
+    /// core, and the blocking operations, e.g., the buffered writes, are
+    /// offloaded to the workers. The `fd` is [`Proactor::as_raw_fd`] of the
+    /// other driver, which should be alive when the driver is built.
+    ///
+    /// ## Platform specific
+    /// * io-uring: [`build`] fails if `fd` is not an io-uring instance. Since
+    ///   Linux 5.12, the workers belong to the threads instead of the rings,
+    ///   and only the drivers on the same thread share them. Limit them with
+    ///   [`max_kernel_workers`] then.
+    /// * IOCP/polling: it is ignored.
+    ///
+    /// [`build`]: ProactorBuilder::build
+    /// [`max_kernel_workers`]: ProactorBuilder::max_kernel_workers
+    pub fn share_workqueue_with(mut self, fd: RawFd) -> Self {
+        self.workqueue = Some(fd);
+        self
+    }
+
+    /// Limit the count of the kernel workers per NUMA node, i.e.,
+    /// `IORING_REGISTER_IOWQ_MAX_WORKERS`. The `bounded` workers perform the
+    /// operations expected to complete in time, e.g., the ones on regular
+    /// files, and the `unbounded` ones may never complete, e.g., the ones on
+    /// sockets. Zero keeps the current limit. The limits apply to the pool
+    /// shared by [`share_workqueue_with`]. Default to the kernel limits.
+    ///
+    /// ## Platform specific
+    /// * io-uring: Linux 5.15 or later is required, and [`build`] fails on the
+    ///   older kernels.
+    /// * IOCP/polling: it is ignored.
+    ///
+    /// [`share_workqueue_with`]: ProactorBuilder::share_workqueue_with
+    /// [`build`]: ProactorBuilder::build
+    pub fn max_kernel_workers(mut self, bounded: u32, unbounded: u32) -> Self {
+        self.max_kernel_workers = Some((bounded, unbounded));
+        self
+    }
+
+    /// Set the slots of the file table for the direct descriptors, i.e., the
+    /// count of the direct sockets open at the same time, including the
+    /// listeners. When the table is full, the sockets wait for a free slot
+    /// instead of failing, see [`DirectTcpListener`]. Default to 4096.
+    ///
+    /// ## Platform specific
+    /// * io-uring: the sparse file table is registered with the size when the
+    ///   first direct socket is created.
+    /// * IOCP/polling: it is ignored.
+    ///
+    /// [`DirectTcpListener`]: crate::net::DirectTcpListener
+    pub fn direct_table_size(mut self, size: u32) -> Self {
+        self.direct_table_size = size;
+        self
+    }
+
+    /// Record the latency metrics in the runtime created by
+    /// [`init_with`], i.e., the delay of the completions before their tasks
+    /// take them, and the count of the task polls between the polls of the
+    /// driver. They are got by [`latency_metrics`]. It costs a timestamp for
+    /// each completion and each poll. Default to `false`.
+    ///
+    /// [`init_with`]: crate::task::init_with
+    /// [`latency_metrics`]: crate::task::latency_metrics
+    #[cfg(feature = "metrics")]
+    pub fn latency_metrics(mut self, enable: bool) -> Self {
+        self.latency_metrics = enable;
+        self
+    }
+
+    /// Round the deadlines of the timers in the runtime created by
+    /// [`init_with`] up to the multiples of `resolution`, so that the timers
+    /// close to each other expire in one wakeup of the driver, e.g., the idle
+    /// timeouts of many connections. A timer fires at most `resolution` later
+    /// than requested: `sleep(1ms)` with a 10ms resolution may wait for 10ms
+    /// or so. Default to zero, which means no rounding.
+    ///
+    /// See also [`sleep_coarse`] to opt in for some timers only.
+    ///
+    /// [`init_with`]: crate::task::init_with
+    /// [`sleep_coarse`]: crate::time::sleep_coarse
+    #[cfg(feature = "time")]
+    pub fn timer_resolution(mut self, resolution: Duration) -> Self {
+        self.timer_resolution = resolution;
+        self
+    }
+
+    /// Start the runtime created by [`init_with`] with the clock of the timers
+    /// paused, for the tests of the timeouts and the backoffs. The clock only
+    /// moves forward by [`advance`], or jumps to the nearest timer when the
+    /// runtime would otherwise wait, i.e., no task is ready and no operation
+    /// is in flight. The sleeps of minutes then complete at once. Default to
+    /// `false`.
+    ///
+    /// All the timers of [`compio::time`] use the paused clock, and
+    /// [`time::now`] gives the time of it. The operations are still performed
+    /// in the real time, and their timeouts in the driver, e.g., the timeouts
+    /// of the sockets set by the options, aren't affected. [`timeout`] of an
+    /// operation in flight expires only after [`advance`], because the clock
+    /// doesn't jump while it is in flight.
+    ///
+    /// ```
+    /// use std::time::{Duration, Instant};
+    ///
+    /// use compio::driver::ProactorBuilder;
+    ///
+    /// std::thread::spawn(|| {
+    ///     compio::task::init_with(&ProactorBuilder::new().start_paused(true)).unwrap();
+    ///     compio::task::block_on(async {
+    ///         let start = Instant::now();
+    ///         let virtual_start = compio::time::now();
+    ///         compio::time::sleep(Duration::from_secs(3600)).await;
+    ///         assert!(compio::time::now() - virtual_start >= Duration::from_secs(3600));
+    ///         assert!(start.elapsed() < Duration::from_secs(60));
+    ///     })
+    /// })
+    /// .join()
+    /// .unwrap();
+    /// ```
+    ///
+    /// [`init_with`]: crate::task::init_with
+    /// [`advance`]: crate::time::advance
+    /// [`compio::time`]: crate::time
+    /// [`time::now`]: crate::time::now
+    /// [`timeout`]: crate::time::timeout
+    #[cfg(feature = "test-util")]
+    pub fn start_paused(mut self, paused: bool) -> Self {
+        self.start_paused = paused;
+        self
+    }
+
+    /// Limit the operations submitted for the same socket in a tick of the
+    /// runtime created by [`init_with`], i.e., between two polls of the
+    /// driver, so that a busy connection doesn't starve the others. When the
+    /// limit is reached, the next operation on the socket waits for the next
+    /// tick, after the tasks woken by the completions of that poll. The
+    /// starting completion of each poll is also rotated, so that the order of
+    /// the completions doesn't favor the same operations. Default to zero,
+    /// which means no limit.
+    ///
+    /// With polling, the operations completed inline are counted too, so a
+    /// socket that always has data to read doesn't block the driver.
+    ///
+    /// [`init_with`]: crate::task::init_with
+    pub fn max_ops_per_fd(mut self, max: u32) -> Self {
+        self.max_ops_per_fd = max;
+        self
+    }
+
+    /// Set the count of the allocations cached for each layout of the
+    /// operations. When an operation is given back, its allocation is kept to
+    /// be reused by the next pushed operation of the same size and alignment,
+    /// so that the repeated operations, e.g., the receives on a connection,
+    /// don't allocate. The user-defined data is still assigned freshly.
+    /// Default to 64, and zero disables caching.
+    ///
+    /// The hit rate is got by [`Proactor::op_pool_stats`].
+    pub fn op_pool_capacity(mut self, capacity: usize) -> Self {
+        self.op_pool_capacity = capacity;
+        self
+    }
+
+    /// Choose the mechanism of the polling driver to wait for the readiness.
+    /// Default to [`PollingMode::Auto`], the native one of the platform.
+    ///
+    /// [`PollingMode::Poll`] is available on all Unix platforms, e.g., when
+    /// the native one is buggy for the workload. It scans all fds waited in
+    /// each wait, see [`Proactor::max_poll_fds`].
+    ///
+    /// ## Platform specific
+    /// * polling: [`build`] fails with [`io::ErrorKind::Unsupported`] if the
+    ///   mode is not available on the platform.
+    /// * io-uring/IOCP: [`build`] fails with [`io::ErrorKind::Unsupported`]
+    ///   except for [`PollingMode::Auto`].
+    ///
+    /// [`build`]: ProactorBuilder::build
+    pub fn polling_mode(mut self, mode: PollingMode) -> Self {
+        self.polling_mode = mode;
+        self
+    }
+
+    /// Use an existing IOCP instead of creating one, which may be shared with
+    /// other overlapped IO code. The port is closed when the driver is
+    /// dropped, and a duplicated handle should be passed to keep using it.
+    ///
+    /// The driver reserves the completion keys `usize::MAX`, `usize::MAX - 1`
+    /// and `usize::MAX - 2`. The handles attached by other code should use
+    /// other keys, and the packets with them are passed to the callback
+    /// registered by [`on_foreign_completion`], without touching the
+    /// overlapped pointers. The port should only be waited by the driver,
+    /// otherwise the completions of the driver may be lost.
+    ///
+    /// [`on_foreign_completion`]: ProactorBuilder::on_foreign_completion
+    #[cfg(target_os = "windows")]
+    pub fn with_existing_port(mut self, port: std::os::windows::io::OwnedHandle) -> Self {
+        self.existing_port = Some(std::sync::Arc::new(port));
+        self
+    }
+
+    /// Set the callback of the completion packets not posted by the driver,
+    /// when the port is shared with [`with_existing_port`]. It is called in
+    /// [`Proactor::poll`]. The packets are dropped if it is not set.
+    ///
+    /// [`with_existing_port`]: ProactorBuilder::with_existing_port
+    #[cfg(target_os = "windows")]
+    pub fn on_foreign_completion(
+        mut self,
+        f: impl Fn(windows_sys::Win32::System::IO::OVERLAPPED_ENTRY) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_foreign_completion = Some(ForeignCompletion::new(f));
+        self
+    }
+
+    /// Set how the transient errors of the socket receives and sends are
+    /// handled, e.g., `WSAENOBUFS`, see [`TransientErrors`] for the errors and
+    /// the kinds they are mapped to. By default, an operation is retried 3
+    /// times, and the error is mapped if it still fails. Use
+    /// [`TransientErrors::Raw`] to get the raw errors.
+    #[cfg(target_os = "windows")]
+    pub fn transient_errors(mut self, policy: TransientErrors) -> Self {
+        self.transient_errors = policy;
+        self
+    }
+
+    /// Build the [`Proactor`].
+    pub fn build(&self) -> io::Result<Proactor> {
+        Ok(Proactor {
+            driver: Driver::new(self)?,
+            ops: Slab::with_capacity(self.capacity as _),
+            op_pool: OpPool::new(self.op_pool_capacity),
+            ready: VecDeque::new(),
+            spin: self.spin,
+            stats: PollStats::default(),
+            capacity: self.capacity,
+            #[cfg(feature = "metrics")]
+            latency_metrics: self.latency_metrics,
+            #[cfg(feature = "time")]
+            timer_resolution: self.timer_resolution,
+            #[cfg(feature = "test-util")]
+            start_paused: self.start_paused,
+            max_ops_per_fd: self.max_ops_per_fd,
+        })
+    }
+}
+
+impl Default for ProactorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Statistics of [`Proactor::poll`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PollStats {
+    /// The count of all polls, including the ones not waiting, e.g., with a
+    /// zero timeout.
+    pub polls: u64,
+    /// The count of polls completed during spinning.
+    pub spin_completions: u64,
+    /// The count of polls falling back to a blocking wait.
+    pub blocking_waits: u64,
+}
+
+/// The mechanism of the polling driver, see [`ProactorBuilder::polling_mode`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PollingMode {
+    /// The native one of the platform: epoll on Linux and Android, event ports
+    /// on illumos and Solaris, kqueue on macOS and the BSDs, and `poll` on the
+    /// others.
+    #[default]
+    Auto,
+    /// `epoll`, on Linux and Android.
+    Epoll,
+    /// `kqueue`, on macOS, iOS, FreeBSD, NetBSD, OpenBSD and DragonFly.
+    Kqueue,
+    /// Event ports, on illumos and Solaris.
+    EventPorts,
+    /// `poll`, on all Unix platforms. It costs linear time to the fds waited.
+    Poll,
+}
+
+/// Counts the entries extended.
+struct CountExtend<'a, E> {
+    inner: &'a mut E,
+    count: usize,
+}
+
+impl<'a, E> CountExtend<'a, E> {
+    fn new(inner: &'a mut E) -> Self {
+        Self { inner, count: 0 }
+    }
+}
+
+impl<E: Extend<Entry>> Extend<Entry> for CountExtend<'_, E> {
+    fn extend<I: IntoIterator<Item = Entry>>(&mut self, iter: I) {
+        let count = &mut self.count;
+        self.inner.extend(iter.into_iter().inspect(|_| *count += 1));
+    }
+}
+
 impl AsRawFd for Proactor {
     fn as_raw_fd(&self) -> RawFd {
         self.driver.as_raw_fd()
     }
 }
 
+/// The result of [`Proactor::push_entry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushEntry<K, R> {
+    /// The operation is pushed, and will be returned from [`Proactor::poll`]
+    /// with the key.
+    Pending(K),
+    /// The operation completes inline.
+    Ready(R),
+}
+
+impl<K, R> PushEntry<K, R> {
+    /// If the operation completes inline.
+    pub fn is_ready(&self) -> bool {
+        matches!(self, Self::Ready(_))
+    }
+
+    /// Map the pending key.
+    pub fn map_pending<L>(self, f: impl FnOnce(K) -> L) -> PushEntry<L, R> {
+        match self {
+            Self::Pending(key) => PushEntry::Pending(f(key)),
+            Self::Ready(res) => PushEntry::Ready(res),
+        }
+    }
+
+    /// Map the ready result.
+    pub fn map_ready<S>(self, f: impl FnOnce(R) -> S) -> PushEntry<K, S> {
+        match self {
+            Self::Pending(key) => PushEntry::Pending(key),
+            Self::Ready(res) => PushEntry::Ready(f(res)),
+        }
+    }
+}
+
 /// Contains the operation and the user_data.
 pub struct Operation {
     op: RawOp,
@@ -235,7 +985,7 @@ impl Entry {
         Self { user_data, result }
     }
 
-    /// The user-defined data returned by [`Proactor::push`].
+    /// The user-defined data returned by [`Proactor::push_entry`].
     pub fn user_data(&self) -> usize {
         self.user_data
     }
@@ -0,0 +1,118 @@
+//! The freelists of the allocations of the operations, to avoid allocating
+//! for each submission.
+
+use std::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    ptr::NonNull,
+    rc::Rc,
+};
+
+/// Statistics of the freelists of the operations, see
+/// [`ProactorBuilder::op_pool_capacity`].
+///
+/// [`ProactorBuilder::op_pool_capacity`]: crate::driver::ProactorBuilder::op_pool_capacity
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OpPoolStats {
+    /// The count of the operations reusing a cached allocation.
+    pub hits: u64,
+    /// The count of the operations allocating.
+    pub misses: u64,
+    /// The count of the allocations cached now.
+    pub cached: usize,
+}
+
+impl OpPoolStats {
+    /// The ratio of the operations reusing a cached allocation, or 0 if no
+    /// operation is pushed.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+// The allocations released by the completed ops, grouped by layout. It is
+// shared by the ops, which may be dropped out of the driver, e.g., by the
+// runtime.
+pub(crate) struct OpPool {
+    capacity: usize,
+    free: RefCell<HashMap<Layout, Vec<NonNull<u8>>>>,
+    hits: Cell<u64>,
+    misses: Cell<u64>,
+    cached: Cell<usize>,
+}
+
+impl OpPool {
+    pub fn new(capacity: usize) -> Rc<Self> {
+        Rc::new(Self {
+            capacity,
+            free: RefCell::default(),
+            hits: Cell::new(0),
+            misses: Cell::new(0),
+            cached: Cell::new(0),
+        })
+    }
+
+    // Move `value` into a cached allocation of the same layout, or a new one.
+    pub fn alloc<T>(&self, value: T) -> NonNull<T> {
+        let layout = Layout::new::<T>();
+        let ptr: NonNull<T> = if layout.size() == 0 {
+            NonNull::dangling()
+        } else if let Some(ptr) = self.free.borrow_mut().get_mut(&layout).and_then(Vec::pop) {
+            self.hits.set(self.hits.get() + 1);
+            self.cached.set(self.cached.get() - 1);
+            ptr.cast()
+        } else {
+            self.misses.set(self.misses.get() + 1);
+            let ptr = unsafe { std::alloc::alloc(layout) };
+            match NonNull::new(ptr) {
+                Some(ptr) => ptr.cast(),
+                None => std::alloc::handle_alloc_error(layout),
+            }
+        };
+        unsafe { ptr.as_ptr().write(value) };
+        ptr
+    }
+
+    // Release the allocation, whose value has been dropped or moved out.
+    //
+    // # Safety
+    //
+    // `ptr` should be allocated by `alloc` with `layout`.
+    pub unsafe fn release(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() == 0 {
+            return;
+        }
+        let mut free = self.free.borrow_mut();
+        let list = free.entry(layout).or_default();
+        if list.len() < self.capacity {
+            list.push(ptr);
+            self.cached.set(self.cached.get() + 1);
+        } else {
+            std::alloc::dealloc(ptr.as_ptr(), layout);
+        }
+    }
+
+    pub fn stats(&self) -> OpPoolStats {
+        OpPoolStats {
+            hits: self.hits.get(),
+            misses: self.misses.get(),
+            cached: self.cached.get(),
+        }
+    }
+}
+
+impl Drop for OpPool {
+    fn drop(&mut self) {
+        for (layout, list) in self.free.get_mut().drain() {
+            for ptr in list {
+                unsafe { std::alloc::dealloc(ptr.as_ptr(), layout) };
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::{collections::HashSet, io, marker::PhantomData, time::Duration};
+
+use crate::{
+    driver::{Entry, OpCode, Proactor, PushEntry},
+    BufResult,
+};
+
+/// The id of an operation in [`OwnedOps`]. It is the user-defined data of the
+/// operation in the [`Proactor`], and it is reused after the operation
+/// completes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OpId(usize);
+
+impl OpId {
+    /// The user-defined data of the operation in the [`Proactor`].
+    pub fn user_data(&self) -> usize {
+        self.0
+    }
+}
+
+/// A set of the in-flight operations of the same type pushed into a
+/// [`Proactor`], for an event loop without the runtime.
+///
+/// The operations are typed by the set, so that the completed ones are given
+/// back without [`Operation::into_op`](crate::driver::Operation::into_op).
+/// The set borrows the proactor, and it cancels the in-flight operations, and
+/// polls the proactor till all of them complete when dropped, so that no
+/// operation outlives its set. The entries of the operations pushed into the
+/// proactor directly, through [`OwnedOps::proactor`], are given back by
+/// [`OwnedOps::complete`] unchanged, but they are discarded if they complete
+/// while the set is dropped.
+///
+/// ```
+/// use compio::{
+///     buf::IntoInner,
+///     driver::{AsRawFd, OwnedOps, Proactor, PushEntry},
+///     op::ReadAt,
+/// };
+///
+/// let mut proactor = Proactor::new().unwrap();
+/// let file = compio::fs::File::open("Cargo.toml").unwrap();
+/// proactor.attach(file.as_raw_fd()).unwrap();
+///
+/// let mut ops = OwnedOps::new(&mut proactor);
+/// let op = ReadAt::new(file.as_raw_fd(), 0, Vec::with_capacity(9));
+/// let (res, op) = match ops.submit(op) {
+///     PushEntry::Pending(id) => {
+///         let (done, res) = ops.poll(None).unwrap().pop().unwrap();
+///         assert_eq!(done, id);
+///         res
+///     }
+///     PushEntry::Ready(res) => res,
+/// };
+/// let n = res.unwrap();
+/// let mut buffer = op.into_inner().into_inner();
+/// unsafe { buffer.set_len(n) };
+/// assert_eq!(buffer, b"[package]");
+/// ```
+pub struct OwnedOps<'a, T: OpCode + 'static> {
+    proactor: &'a mut Proactor,
+    ids: HashSet<usize>,
+    _p: PhantomData<fn(T) -> T>,
+}
+
+impl<'a, T: OpCode + 'static> OwnedOps<'a, T> {
+    /// Create an empty set of the proactor.
+    pub fn new(proactor: &'a mut Proactor) -> Self {
+        Self {
+            proactor,
+            ids: HashSet::new(),
+            _p: PhantomData,
+        }
+    }
+
+    /// The proactor, e.g., to attach an fd, or to poll it with the other
+    /// operations.
+    pub fn proactor(&mut self) -> &mut Proactor {
+        self.proactor
+    }
+
+    /// The count of the in-flight operations.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Whether there is no in-flight operation.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Whether the operation is in flight.
+    pub fn contains(&self, id: OpId) -> bool {
+        self.ids.contains(&id.0)
+    }
+
+    /// Push an operation into the proactor. See [`Proactor::push_entry`].
+    pub fn submit(&mut self, op: T) -> PushEntry<OpId, BufResult<usize, T>> {
+        let res = self.proactor.push_entry(op);
+        if let PushEntry::Pending(user_data) = res {
+            self.ids.insert(user_data);
+        }
+        res.map_pending(OpId)
+    }
+
+    /// Cancel an in-flight operation. It still completes, maybe with an
+    /// error, and is given back by [`OwnedOps::complete`]. See
+    /// [`Proactor::cancel`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operation is not in flight.
+    pub fn cancel(&mut self, id: OpId) {
+        assert!(self.contains(id), "the operation is not in flight");
+        self.proactor.cancel(id.0);
+    }
+
+    /// Give back the operation of the completion entry. The entry is returned
+    /// as the error if it is not of an in-flight operation in the set, e.g.,
+    /// of an operation pushed into the proactor directly.
+    pub fn complete(&mut self, entry: Entry) -> Result<(OpId, BufResult<usize, T>), Entry> {
+        if !self.ids.remove(&entry.user_data()) {
+            return Err(entry);
+        }
+        let (res, op) = self
+            .proactor
+            .pop(&mut std::iter::once(entry))
+            .next()
+            .expect("the entry should be popped");
+        let id = OpId(op.user_data());
+        // SAFETY: only the operations of `T` are in the set.
+        Ok((id, (res, unsafe { op.into_op::<T>() })))
+    }
+
+    /// Poll the proactor, and give back the completed operations. See
+    /// [`Proactor::poll`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if an operation pushed into the proactor directly completes.
+    pub fn poll(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> io::Result<Vec<(OpId, BufResult<usize, T>)>> {
+        let mut entries = vec![];
+        self.proactor.poll(timeout, &mut entries)?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| match self.complete(entry) {
+                Ok(res) => res,
+                Err(entry) => panic!("the operation {} is not in the set", entry.user_data()),
+            })
+            .collect())
+    }
+}
+
+impl<T: OpCode + 'static> Drop for OwnedOps<'_, T> {
+    fn drop(&mut self) {
+        for user_data in &self.ids {
+            self.proactor.cancel(*user_data);
+        }
+        let mut entries = vec![];
+        while !self.ids.is_empty() {
+            if self.proactor.poll(None, &mut entries).is_err() {
+                // The operations stay in the proactor, and are dropped with it.
+                break;
+            }
+            let ids = &mut self.ids;
+            let mut entries = entries
+                .drain(..)
+                .filter(|entry| ids.remove(&entry.user_data()));
+            // SAFETY: only the operations of `T` are in the set.
+            for (_, op) in self.proactor.pop(&mut entries) {
+                drop(unsafe { op.into_op::<T>() });
+            }
+        }
+    }
+}
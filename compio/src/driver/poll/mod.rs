@@ -1,22 +1,27 @@
 #[doc(no_inline)]
 pub use std::os::fd::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{HashMap, VecDeque},
     io,
     num::NonZeroUsize,
     os::fd::BorrowedFd,
     pin::Pin,
+    sync::{Arc, Mutex},
     task::Poll,
     time::Duration,
 };
 
-pub(crate) use libc::{sockaddr_storage, socklen_t};
-use polling::{Event, Events, Poller};
+pub(crate) use libc::socklen_t;
+use polling::{Event, Events};
 use slab::Slab;
+pub(crate) use socket2::SockAddrStorage as sockaddr_storage;
 
-use crate::driver::Entry;
+use crate::driver::{Entry, Notifier, PollingMode, ProactorBuilder, SetupError};
 
 pub(crate) mod op;
+mod poller;
+use poller::Poller;
+pub use crate::driver::unix::Interest;
 pub(crate) use crate::driver::unix::RawOp;
 
 /// Abstraction of operations.
@@ -28,6 +33,34 @@ pub trait OpCode {
     /// Perform the operation after received corresponding
     /// event.
     fn on_event(self: Pin<&mut Self>, event: &Event) -> Poll<io::Result<usize>>;
+
+    /// Whether the operation waits for the error queue of the fd, instead of
+    /// the same interest, after [`OpCode::on_event`] returns
+    /// [`Poll::Pending`], e.g., for the notifications of `MSG_ZEROCOPY`. It
+    /// couldn't be cancelled then, because the kernel still uses the buffer,
+    /// unless [`OpCode::cancel_error_queue`] tells otherwise.
+    fn wait_error_queue(&self) -> bool {
+        false
+    }
+
+    /// Whether the operation could be cancelled while waiting for the error
+    /// queue, i.e., the kernel doesn't use its buffers then.
+    fn cancel_error_queue(&self) -> bool {
+        false
+    }
+
+    /// Cancel the operation by itself, e.g., interrupt the function running
+    /// on a thread. Returns `true` if it still completes with an event, and
+    /// the driver shouldn't stop waiting for it. By default, it returns
+    /// `false`.
+    ///
+    /// The cancellation races with the operation, so it may be lost, e.g., an
+    /// interruption sent before the function enters its blocking syscall
+    /// doesn't wake the syscall. The operation then completes only when the
+    /// syscall returns by itself.
+    fn cancel(self: Pin<&mut Self>) -> bool {
+        false
+    }
 }
 
 /// Result of [`OpCode::pre_submit`].
@@ -64,19 +97,14 @@ pub struct WaitArg {
     pub interest: Interest,
 }
 
-/// The interest of the operation
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Interest {
-    /// Represents a read operation.
-    Readable,
-    /// Represents a write operation.
-    Writable,
-}
-
 #[derive(Debug, Default)]
 struct FdQueue {
     read_queue: VecDeque<usize>,
     write_queue: VecDeque<usize>,
+    // The operations waiting for the error queue. The errors are always
+    // reported by the poller, so no interest is registered for them. Each is
+    // paired with whether it could be cancelled.
+    error_queue: VecDeque<(usize, bool)>,
 }
 
 impl FdQueue {
@@ -101,97 +129,196 @@ impl FdQueue {
         event
     }
 
-    pub fn pop_interest(&mut self, event: &Event) -> (usize, Interest) {
+    pub fn remove(&mut self, user_data: usize) -> bool {
+        let remove_from = |queue: &mut VecDeque<usize>| {
+            if let Some(index) = queue.iter().position(|key| *key == user_data) {
+                queue.remove(index);
+                true
+            } else {
+                false
+            }
+        };
+        if remove_from(&mut self.read_queue) || remove_from(&mut self.write_queue) {
+            return true;
+        }
+        let cancellable = self
+            .error_queue
+            .iter()
+            .position(|(key, cancellable)| *key == user_data && *cancellable);
+        cancellable
+            .and_then(|index| self.error_queue.remove(index))
+            .is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read_queue.is_empty() && self.write_queue.is_empty() && self.error_queue.is_empty()
+    }
+
+    pub fn pop_interest(&mut self, event: &Event) -> Option<(usize, Interest)> {
         if event.readable {
             if let Some(user_data) = self.read_queue.pop_front() {
-                return (user_data, Interest::Readable);
+                return Some((user_data, Interest::Readable));
             }
         }
         if event.writable {
             if let Some(user_data) = self.write_queue.pop_front() {
-                return (user_data, Interest::Writable);
+                return Some((user_data, Interest::Writable));
             }
         }
-        unreachable!("should receive event when no interest")
+        None
+    }
+}
+
+/// The syscalls made by the polling driver on Linux, to generate a seccomp
+/// allowlist. The ops are performed in userspace after the fds are ready, so
+/// the list includes the syscalls of all ops. The threads of the pool, std and
+/// the allocator need more, e.g., `brk`.
+///
+/// The driver creates the poller when it is built, and fails with
+/// [`SetupError`] if it is blocked.
+pub fn required_syscalls() -> &'static [&'static str] {
+    &[
+        // The poller.
+        "epoll_create1",
+        "epoll_ctl",
+        "epoll_wait",
+        "epoll_pwait",
+        "epoll_pwait2",
+        // `PollingMode::Poll`.
+        "ppoll",
+        "eventfd2",
+        "timerfd_create",
+        "timerfd_settime",
+        // The ops.
+        "read",
+        "readv",
+        "pread64",
+        "preadv",
+        "preadv2",
+        "write",
+        "writev",
+        "pwrite64",
+        "pwritev",
+        "pwritev2",
+        "recvfrom",
+        "recvmsg",
+        "sendto",
+        "sendmsg",
+        "socket",
+        "bind",
+        "listen",
+        "accept4",
+        "connect",
+        "shutdown",
+        "getsockopt",
+        "setsockopt",
+        "getsockname",
+        "getpeername",
+        "ioctl",
+        "fcntl",
+        "fsync",
+        "fdatasync",
+        "sync_file_range",
+        "fallocate",
+        "splice",
+        "sendfile",
+        "openat",
+        "statx",
+        "fstat",
+        "newfstatat",
+        "close",
+        // The thread pool.
+        "clone3",
+        "clone",
+        "futex",
+        "mmap",
+        "munmap",
+        "mprotect",
+        "madvise",
+        "sigaltstack",
+        "rt_sigprocmask",
+        "sched_getaffinity",
+        "set_robust_list",
+        "rseq",
+        "exit",
+        // The cancellation of the blocking ops.
+        "rt_sigaction",
+        "getpid",
+        "tgkill",
+    ]
+}
+
+/// A handle to post messages to a driver from other threads.
+#[derive(Debug, Clone)]
+pub struct MessageSender {
+    poll: Arc<Poller>,
+    mailbox: Arc<Mutex<VecDeque<u64>>>,
+}
+
+impl MessageSender {
+    pub(crate) fn send(&self, msg: u64) -> io::Result<()> {
+        self.mailbox.lock().unwrap().push_back(msg);
+        self.poll.notify()
     }
 }
 
 /// Low-level driver of polling.
 pub(crate) struct Driver {
     events: Events,
-    poll: Poller,
+    ready: Vec<(Event, bool)>,
+    poll: Arc<Poller>,
+    mailbox: Arc<Mutex<VecDeque<u64>>>,
     registry: HashMap<RawFd, FdQueue>,
-    cancelled: HashSet<usize>,
+    cancel_queue: VecDeque<usize>,
 }
 
 impl Driver {
-    pub fn new(entries: u32) -> io::Result<Self> {
-        let entries = entries as usize; // for the sake of consistency, use u32 like iour
+    pub fn new(builder: &ProactorBuilder) -> io::Result<Self> {
+        let entries = builder.capacity as usize; // for the sake of consistency, use u32 like iour
         let events = if entries == 0 {
             Events::new()
         } else {
             Events::with_capacity(NonZeroUsize::new(entries).unwrap())
         };
 
+        let poll = Poller::new(builder.polling_mode)?;
+        // The blocking ops are waited by their own eventfds.
+        Notifier::new().map_err(|e| SetupError::wrap(Notifier::SYSCALL, e))?;
+
         Ok(Self {
             events,
-            poll: Poller::new()?,
+            ready: Vec::new(),
+            poll: Arc::new(poll),
+            mailbox: Arc::default(),
             registry: HashMap::new(),
-            cancelled: HashSet::new(),
+            cancel_queue: VecDeque::new(),
         })
     }
 
-    fn submit(&mut self, user_data: usize, arg: WaitArg) -> io::Result<bool> {
-        if self.cancelled.remove(&user_data) {
-            Ok(false)
-        } else {
-            let need_add = !self.registry.contains_key(&arg.fd);
-            let queue = self.registry.entry(arg.fd).or_default();
-            queue.push_back_interest(user_data, arg.interest);
-            // We use fd as the key.
-            let event = queue.event(arg.fd as usize);
-            unsafe {
-                if need_add {
-                    self.poll.add(arg.fd, event)?;
-                } else {
-                    let fd = BorrowedFd::borrow_raw(arg.fd);
-                    self.poll.modify(fd, event)?;
-                }
+    fn submit(&mut self, user_data: usize, arg: WaitArg) -> io::Result<()> {
+        let need_add = !self.registry.contains_key(&arg.fd);
+        let queue = self.registry.entry(arg.fd).or_default();
+        queue.push_back_interest(user_data, arg.interest);
+        // We use fd as the key.
+        let event = queue.event(arg.fd as usize);
+        let res = unsafe {
+            if need_add {
+                crate::count_syscall!("poller_add");
+                self.poll.add(arg.fd, event)
+            } else {
+                let fd = BorrowedFd::borrow_raw(arg.fd);
+                crate::count_syscall!("poller_modify");
+                self.poll.modify(fd, event)
             }
-            Ok(true)
-        }
-    }
-
-    /// Register all operations in the squeue to polling.
-    fn submit_squeue(
-        &mut self,
-        ops: &mut impl Iterator<Item = usize>,
-        entries: &mut impl Extend<Entry>,
-        registry: &mut Slab<RawOp>,
-    ) -> io::Result<bool> {
-        let mut extended = false;
-        for user_data in ops {
-            let op = registry[user_data].as_pin();
-            match op.pre_submit() {
-                Ok(Decision::Wait(arg)) => {
-                    let succeeded = self.submit(user_data, arg)?;
-                    if !succeeded {
-                        entries.extend(Some(entry_cancelled(user_data)));
-                        extended = true;
-                    }
-                }
-                Ok(Decision::Completed(res)) => {
-                    entries.extend(Some(Entry::new(user_data, Ok(res))));
-                    extended = true;
-                }
-                Err(err) => {
-                    entries.extend(Some(Entry::new(user_data, Err(err))));
-                    extended = true;
-                }
+        };
+        if res.is_err() {
+            // The operation completes with the error.
+            queue.remove(user_data);
+            if queue.is_empty() {
+                self.registry.remove(&arg.fd);
             }
         }
-
-        Ok(extended)
+        res
     }
 
     /// Poll all events from polling, call `perform` on op and push them into
@@ -202,61 +329,166 @@ impl Driver {
         entries: &mut impl Extend<Entry>,
         registry: &mut Slab<RawOp>,
     ) -> io::Result<()> {
-        self.poll.wait(&mut self.events, timeout)?;
-        if self.events.is_empty() && timeout.is_some() {
+        self.ready.clear();
+        crate::count_syscall!("poller_wait");
+        self.poll.wait(&mut self.events, &mut self.ready, timeout)?;
+        if self.ready.is_empty() && timeout.is_some() {
             return Err(io::Error::from_raw_os_error(libc::ETIMEDOUT));
         }
-        for event in self.events.iter() {
+        // The fds are renewed once after all events are handled. kqueue reports
+        // the readiness of reading and writing as two events, and both filters
+        // are oneshot, so renewing on each event may re-arm a filter whose
+        // event is still in the list.
+        let mut renewed = Vec::with_capacity(self.ready.len());
+        for &(event, error) in &self.ready {
             let fd = event.key as RawFd;
-            let queue = self
-                .registry
-                .get_mut(&fd)
-                .expect("the fd should be attached");
-            let (user_data, interest) = queue.pop_interest(&event);
-            if self.cancelled.remove(&user_data) {
-                entries.extend(Some(entry_cancelled(user_data)));
-            } else {
-                let op = registry[user_data].as_pin();
-                let res = match op.on_event(&event) {
-                    Poll::Pending => {
-                        // The operation should go back to the front.
-                        queue.push_front_interest(user_data, interest);
-                        None
-                    }
-                    Poll::Ready(res) => Some(res),
-                };
-                if let Some(res) = res {
-                    let entry = Entry::new(user_data, res);
-                    entries.extend(Some(entry));
+            // The registration in the poller lives until all fds duplicated from
+            // the same file are closed, so the event may come from a closed fd,
+            // and its number may have been reused.
+            let Some(queue) = self.registry.get_mut(&fd) else {
+                continue;
+            };
+            renewed.push(fd);
+            // All operations waiting for the readiness try once, e.g., the
+            // readiness waits on the same fd are all woken. The ones still
+            // pending go back to the front, in the same order.
+            let mut pending = vec![];
+            while let Some((user_data, interest)) = queue.pop_interest(&event) {
+                let mut op = registry[user_data].as_pin();
+                match on_event(op.as_mut(), &event) {
+                    Poll::Pending if op.wait_error_queue() => queue
+                        .error_queue
+                        .push_back((user_data, op.cancel_error_queue())),
+                    Poll::Pending => pending.push((user_data, interest)),
+                    Poll::Ready(res) => entries.extend(Some(Entry::new(user_data, res))),
                 }
             }
-            let renew_event = queue.event(fd as _);
-            unsafe {
-                let fd = BorrowedFd::borrow_raw(fd);
-                self.poll.modify(fd, renew_event)?;
+            for (user_data, interest) in pending.into_iter().rev() {
+                queue.push_front_interest(user_data, interest);
+            }
+            // Any of the waiting operations may read the notifications of the
+            // others from the error queue, so all of them are tried.
+            if error {
+                for (user_data, cancellable) in std::mem::take(&mut queue.error_queue) {
+                    match on_event(registry[user_data].as_pin(), &event) {
+                        Poll::Pending => queue.error_queue.push_back((user_data, cancellable)),
+                        Poll::Ready(res) => entries.extend(Some(Entry::new(user_data, res))),
+                    }
+                }
             }
         }
+        renewed.sort_unstable();
+        renewed.dedup();
+        for fd in renewed {
+            Self::renew(&mut self.registry, &self.poll, fd)?;
+        }
         Ok(())
     }
 
+    // Update the interest of the fd. If there is no interest, the fd is removed
+    // from the poller, because it may be closed and the number be reused.
+    fn renew(
+        registry: &mut HashMap<RawFd, FdQueue>,
+        poll: &Poller,
+        fd: RawFd,
+    ) -> io::Result<()> {
+        let queue = registry.get(&fd).expect("the fd should be attached");
+        let fd_borrowed = unsafe { BorrowedFd::borrow_raw(fd) };
+        if queue.is_empty() {
+            registry.remove(&fd);
+            crate::count_syscall!("poller_delete");
+            poll.delete(fd_borrowed)
+        } else {
+            crate::count_syscall!("poller_modify");
+            poll.modify(fd_borrowed, queue.event(fd as _))
+        }
+    }
+
+    pub fn set_napi_busy_poll(
+        &mut self,
+        timeout: Option<Duration>,
+        _prefer_busy_poll: bool,
+    ) -> io::Result<()> {
+        if timeout.is_some() {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "NAPI busy polling is only supported by io-uring",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn attach(&mut self, _fd: RawFd) -> io::Result<()> {
         Ok(())
     }
 
-    pub fn cancel(&mut self, user_data: usize, _registry: &mut Slab<RawOp>) {
-        self.cancelled.insert(user_data);
+    pub fn polling_mode(&self) -> Option<PollingMode> {
+        Some(self.poll.mode())
+    }
+
+    pub fn max_poll_fds(&self) -> usize {
+        self.poll.max_poll_fds()
+    }
+
+    pub fn message_sender(&self) -> io::Result<MessageSender> {
+        Ok(MessageSender {
+            poll: self.poll.clone(),
+            mailbox: self.mailbox.clone(),
+        })
+    }
+
+    pub fn pop_message(&mut self) -> Option<u64> {
+        self.mailbox.lock().unwrap().pop_front()
+    }
+
+    pub fn cancel(&mut self, user_data: usize, registry: &mut Slab<RawOp>) {
+        if registry
+            .get_mut(user_data)
+            .is_some_and(|op| op.as_pin().cancel())
+        {
+            return;
+        }
+        // If the operation is waiting for an event, remove it from the fd queue
+        // and complete it in the next poll, because the event may never come.
+        // Otherwise, it has completed.
+        let fd = self
+            .registry
+            .iter_mut()
+            .find_map(|(fd, queue)| queue.remove(user_data).then_some(*fd));
+        if let Some(fd) = fd {
+            Self::renew(&mut self.registry, &self.poll, fd).ok();
+            self.cancel_queue.push_back(user_data);
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        false
+    }
+
+    // The operation is tried at once, and registered to polling if it would
+    // block.
+    pub fn push(&mut self, user_data: usize, op: &mut RawOp) -> Poll<io::Result<usize>> {
+        match retry_interrupted(|| op.as_pin().pre_submit()) {
+            Ok(Decision::Wait(arg)) => match self.submit(user_data, arg) {
+                Ok(()) => Poll::Pending,
+                Err(e) => Poll::Ready(Err(e)),
+            },
+            Ok(Decision::Completed(res)) => Poll::Ready(Ok(res)),
+            Err(e) => Poll::Ready(Err(e)),
+        }
     }
 
     pub unsafe fn poll(
         &mut self,
         timeout: Option<Duration>,
-        ops: &mut impl Iterator<Item = usize>,
         entries: &mut impl Extend<Entry>,
         registry: &mut Slab<RawOp>,
     ) -> io::Result<()> {
-        let extended = self.submit_squeue(ops, entries, registry)?;
-        if !extended {
+        if self.cancel_queue.is_empty() {
             self.poll_impl(timeout, entries, registry)?;
+        } else {
+            entries.extend(self.cancel_queue.drain(..).map(entry_cancelled));
         }
         Ok(())
     }
@@ -279,6 +511,26 @@ impl Drop for Driver {
     }
 }
 
+// The syscall interrupted by a signal is tried again, so that the ops never
+// complete with `ErrorKind::Interrupted`.
+fn retry_interrupted<T>(mut f: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    loop {
+        match f() {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            res => return res,
+        }
+    }
+}
+
+fn on_event(mut op: Pin<&mut dyn OpCode>, event: &Event) -> Poll<io::Result<usize>> {
+    loop {
+        match op.as_mut().on_event(event) {
+            Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::Interrupted => {}
+            res => return res,
+        }
+    }
+}
+
 fn entry_cancelled(user_data: usize) -> Entry {
     Entry::new(
         user_data,
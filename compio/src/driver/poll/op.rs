@@ -1,8 +1,21 @@
-use std::{io, pin::Pin, task::Poll};
+use std::{
+    io::{self, IoSlice},
+    mem::MaybeUninit,
+    os::fd::RawFd,
+    pin::Pin,
+    task::Poll,
+};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::sync::Arc;
 
 use polling::Event;
 
 pub use crate::driver::unix::op::*;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use crate::{
+    buf::IntoInner,
+    net::{drain_zerocopy_notifications, ZeroCopyTracker},
+};
 use crate::{
     buf::{AsIoSlices, AsIoSlicesMut, IoBuf, IoBufMut},
     driver::{Decision, OpCode},
@@ -10,6 +23,133 @@ use crate::{
     syscall,
 };
 
+// `off_t` is 32-bit on the 32-bit Linux targets, e.g., armv7 and i686, so
+// the 64-bit variants are called there. The offsets above `i64::MAX` become
+// negative, and fail with `EINVAL`.
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "musl")
+))]
+fn pread_at(fd: RawFd, slice: &mut [MaybeUninit<u8>], offset: u64) -> io::Result<usize> {
+    let res = syscall!(pread64(
+        fd,
+        slice.as_mut_ptr() as _,
+        slice.len(),
+        offset as _
+    ))?;
+    Ok(res as _)
+}
+
+#[cfg(not(all(
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "musl")
+)))]
+fn pread_at(fd: RawFd, slice: &mut [MaybeUninit<u8>], offset: u64) -> io::Result<usize> {
+    let res = syscall!(pread(fd, slice.as_mut_ptr() as _, slice.len(), offset as _))?;
+    Ok(res as _)
+}
+
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "musl")
+))]
+fn pwrite_at(fd: RawFd, slice: &[u8], offset: u64) -> io::Result<usize> {
+    let res = syscall!(pwrite64(fd, slice.as_ptr() as _, slice.len(), offset as _))?;
+    Ok(res as _)
+}
+
+#[cfg(not(all(
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "musl")
+)))]
+fn pwrite_at(fd: RawFd, slice: &[u8], offset: u64) -> io::Result<usize> {
+    let res = syscall!(pwrite(fd, slice.as_ptr() as _, slice.len(), offset as _))?;
+    Ok(res as _)
+}
+
+// With the `RWF_*` flags. A read or write without flags is left to the plain
+// `pread` or `pwrite` above, which works on the kernels before 4.6. The
+// flags are always empty on the other platforms.
+#[cfg(target_os = "linux")]
+fn preadv2_at(
+    fd: RawFd,
+    slice: &mut [MaybeUninit<u8>],
+    offset: u64,
+    flags: libc::c_int,
+) -> io::Result<usize> {
+    if flags == 0 {
+        return pread_at(fd, slice, offset);
+    }
+    let iov = libc::iovec {
+        iov_base: slice.as_mut_ptr() as _,
+        iov_len: slice.len(),
+    };
+    #[cfg(not(target_env = "musl"))]
+    let res = syscall!(preadv64v2(fd, &iov, 1, offset as _, flags))?;
+    #[cfg(target_env = "musl")]
+    let res = syscall!(preadv2(fd, &iov, 1, offset as _, flags))?;
+    Ok(res as _)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn preadv2_at(
+    fd: RawFd,
+    slice: &mut [MaybeUninit<u8>],
+    offset: u64,
+    _flags: libc::c_int,
+) -> io::Result<usize> {
+    pread_at(fd, slice, offset)
+}
+
+#[cfg(target_os = "linux")]
+fn pwritev2_at(fd: RawFd, slice: &[u8], offset: u64, flags: libc::c_int) -> io::Result<usize> {
+    if flags == 0 {
+        return pwrite_at(fd, slice, offset);
+    }
+    let iov = libc::iovec {
+        iov_base: slice.as_ptr() as _,
+        iov_len: slice.len(),
+    };
+    #[cfg(not(target_env = "musl"))]
+    let res = syscall!(pwritev64v2(fd, &iov, 1, offset as _, flags))?;
+    #[cfg(target_env = "musl")]
+    let res = syscall!(pwritev2(fd, &iov, 1, offset as _, flags))?;
+    Ok(res as _)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pwritev2_at(fd: RawFd, slice: &[u8], offset: u64, _flags: libc::c_int) -> io::Result<usize> {
+    pwrite_at(fd, slice, offset)
+}
+
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "musl")
+))]
+fn pwritev_at(fd: RawFd, slices: &[IoSlice], offset: u64) -> io::Result<usize> {
+    let res = syscall!(pwritev64(
+        fd,
+        slices.as_ptr() as _,
+        slices.len() as _,
+        offset as _
+    ))?;
+    Ok(res as _)
+}
+
+#[cfg(not(all(
+    any(target_os = "linux", target_os = "android"),
+    not(target_env = "musl")
+)))]
+fn pwritev_at(fd: RawFd, slices: &[IoSlice], offset: u64) -> io::Result<usize> {
+    let res = syscall!(pwritev(
+        fd,
+        slices.as_ptr() as _,
+        slices.len() as _,
+        offset as _
+    ))?;
+    Ok(res as _)
+}
+
 impl<T: IoBufMut> OpCode for ReadAt<T> {
     fn pre_submit(mut self: Pin<&mut Self>) -> io::Result<Decision> {
         if cfg!(any(
@@ -17,14 +157,14 @@ impl<T: IoBufMut> OpCode for ReadAt<T> {
             target_os = "android",
             target_os = "illumos"
         )) {
-            let fd = self.fd;
-            let slice = self.buffer.as_uninit_slice();
-            Ok(Decision::Completed(syscall!(pread(
+            // The `WouldBlock` of `RWF_NOWAIT` is returned as is.
+            let (fd, offset, flags) = (self.fd, self.offset, self.rw_flags);
+            Ok(Decision::Completed(preadv2_at(
                 fd,
-                slice.as_mut_ptr() as _,
-                slice.len() as _,
-                self.offset as _
-            ))? as _))
+                self.buffer.as_uninit_slice(),
+                offset,
+                flags,
+            )?))
         } else {
             Ok(Decision::wait_readable(self.fd))
         }
@@ -33,17 +173,11 @@ impl<T: IoBufMut> OpCode for ReadAt<T> {
     fn on_event(mut self: Pin<&mut Self>, event: &Event) -> Poll<io::Result<usize>> {
         debug_assert!(event.readable);
 
-        let fd = self.fd;
-        let slice = self.buffer.as_uninit_slice();
-
-        syscall!(
-            break pread(
-                fd,
-                slice.as_mut_ptr() as _,
-                slice.len() as _,
-                self.offset as _
-            )
-        )
+        let (fd, offset, flags) = (self.fd, self.offset, self.rw_flags);
+        match preadv2_at(fd, self.buffer.as_uninit_slice(), offset, flags) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            res => Poll::Ready(res),
+        }
     }
 }
 
@@ -54,13 +188,12 @@ impl<T: IoBuf> OpCode for WriteAt<T> {
             target_os = "android",
             target_os = "illumos"
         )) {
-            let slice = self.buffer.as_slice();
-            Ok(Decision::Completed(syscall!(pwrite(
+            Ok(Decision::Completed(pwritev2_at(
                 self.fd,
-                slice.as_ptr() as _,
-                slice.len() as _,
-                self.offset as _
-            ))? as _))
+                self.buffer.as_slice(),
+                self.offset,
+                self.rw_flags,
+            )?))
         } else {
             Ok(Decision::wait_writable(self.fd))
         }
@@ -69,16 +202,39 @@ impl<T: IoBuf> OpCode for WriteAt<T> {
     fn on_event(self: Pin<&mut Self>, event: &Event) -> Poll<io::Result<usize>> {
         debug_assert!(event.writable);
 
-        let slice = self.buffer.as_slice();
+        match pwritev2_at(self.fd, self.buffer.as_slice(), self.offset, self.rw_flags) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            res => Poll::Ready(res),
+        }
+    }
+}
 
-        syscall!(
-            break pwrite(
-                self.fd,
-                slice.as_ptr() as _,
-                slice.len() as _,
-                self.offset as _
-            )
-        )
+impl<T: IoBuf> OpCode for WriteVectoredAt<T> {
+    fn pre_submit(mut self: Pin<&mut Self>) -> io::Result<Decision> {
+        if cfg!(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "illumos"
+        )) {
+            let (fd, offset) = (self.fd, self.offset);
+            Ok(Decision::Completed(pwritev_at(
+                fd,
+                self.set_slices(),
+                offset,
+            )?))
+        } else {
+            Ok(Decision::wait_writable(self.fd))
+        }
+    }
+
+    fn on_event(mut self: Pin<&mut Self>, event: &Event) -> Poll<io::Result<usize>> {
+        debug_assert!(event.writable);
+
+        let (fd, offset) = (self.fd, self.offset);
+        match pwritev_at(fd, self.set_slices(), offset) {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            res => Poll::Ready(res),
+        }
     }
 }
 
@@ -92,34 +248,59 @@ impl OpCode for Sync {
     }
 }
 
-impl OpCode for Accept {
-    fn pre_submit(mut self: Pin<&mut Self>) -> io::Result<Decision> {
+impl Accept {
+    // The accepted sockets are close-on-exec, so that they are not leaked to
+    // the child processes.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn accept(&mut self) -> Poll<io::Result<usize>> {
         syscall!(
-            accept(
+            break accept4(
                 self.fd,
                 &mut self.buffer as *mut _ as *mut _,
-                &mut self.addr_len
-            ) or wait_readable(self.fd)
+                &mut self.addr_len,
+                libc::SOCK_CLOEXEC
+            )
         )
     }
 
-    fn on_event(mut self: Pin<&mut Self>, event: &Event) -> Poll<io::Result<usize>> {
-        debug_assert!(event.readable);
-
-        syscall!(
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn accept(&mut self) -> Poll<io::Result<usize>> {
+        let res = syscall!(
             break accept(
                 self.fd,
                 &mut self.buffer as *mut _ as *mut _,
                 &mut self.addr_len
             )
-        )
+        );
+        if let Poll::Ready(Ok(fd)) = res {
+            if let Err(e) = syscall!(fcntl(fd as _, libc::F_SETFD, libc::FD_CLOEXEC)) {
+                unsafe { libc::close(fd as _) };
+                return Poll::Ready(Err(e));
+            }
+        }
+        res
+    }
+}
+
+impl OpCode for Accept {
+    fn pre_submit(mut self: Pin<&mut Self>) -> io::Result<Decision> {
+        match self.accept() {
+            Poll::Pending => Ok(Decision::wait_readable(self.fd)),
+            Poll::Ready(res) => res.map(Decision::Completed),
+        }
+    }
+
+    fn on_event(mut self: Pin<&mut Self>, event: &Event) -> Poll<io::Result<usize>> {
+        debug_assert!(event.readable);
+
+        self.accept()
     }
 }
 
 impl OpCode for Connect {
     fn pre_submit(self: Pin<&mut Self>) -> io::Result<Decision> {
         syscall!(
-            connect(self.fd, self.addr.as_ptr(), self.addr.len()) or wait_writable(self.fd)
+            connect(self.fd, self.addr.as_ptr().cast(), self.addr.len()) or wait_writable(self.fd)
         )
     }
 
@@ -146,6 +327,52 @@ impl OpCode for Connect {
     }
 }
 
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl<T: IoBuf> OpCode for ConnectWithData<T> {
+    fn pre_submit(self: Pin<&mut Self>) -> io::Result<Decision> {
+        let slice = self.buffer.as_slice();
+        syscall!(
+            sendto(
+                self.fd,
+                slice.as_ptr() as _,
+                slice.len(),
+                libc::MSG_FASTOPEN | libc::MSG_NOSIGNAL,
+                self.addr.as_ptr().cast(),
+                self.addr.len()
+            ) or wait_writable(self.fd)
+        )
+    }
+
+    fn on_event(self: Pin<&mut Self>, event: &Event) -> Poll<io::Result<usize>> {
+        debug_assert!(event.writable);
+
+        // The SYN carries no data, and the connection is established now.
+        let mut err: libc::c_int = 0;
+        let mut err_len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+
+        syscall!(getsockopt(
+            self.fd,
+            libc::SOL_SOCKET,
+            libc::SO_ERROR,
+            &mut err as *mut _ as *mut _,
+            &mut err_len
+        ))?;
+        if err != 0 {
+            return Poll::Ready(Err(io::Error::from_raw_os_error(err)));
+        }
+
+        let slice = self.buffer.as_slice();
+        syscall!(
+            break send(
+                self.fd,
+                slice.as_ptr() as _,
+                slice.len(),
+                libc::MSG_NOSIGNAL
+            )
+        )
+    }
+}
+
 impl<T: AsIoSlicesMut + Unpin> OpCode for RecvImpl<T> {
     fn pre_submit(self: Pin<&mut Self>) -> io::Result<Decision> {
         Ok(Decision::wait_readable(self.fd))
@@ -175,13 +402,24 @@ impl<T: AsIoSlices + Unpin> OpCode for SendImpl<T> {
 impl<T: AsIoSlicesMut + Unpin> OpCode for RecvFromImpl<T> {
     fn pre_submit(mut self: Pin<&mut Self>) -> io::Result<Decision> {
         self.set_msg();
-        syscall!(recvmsg(self.fd, &mut self.msg, 0) or wait_readable(self.fd))
+        syscall!(recvmsg(self.fd, &mut self.msg, self.flags) or wait_readable(self.fd))
     }
 
     fn on_event(mut self: Pin<&mut Self>, event: &Event) -> Poll<io::Result<usize>> {
-        debug_assert!(event.readable);
+        debug_assert!(event.readable || self.wait_error_queue());
+
+        syscall!(break recvmsg(self.fd, &mut self.msg, self.flags))
+    }
 
-        syscall!(break recvmsg(self.fd, &mut self.msg, 0))
+    // The error queue doesn't make the fd readable, so it waits for the
+    // errors instead, or it would be woken by the data again and again.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn wait_error_queue(&self) -> bool {
+        self.flags & libc::MSG_ERRQUEUE != 0
+    }
+
+    fn cancel_error_queue(&self) -> bool {
+        true
     }
 }
 
@@ -197,3 +435,215 @@ impl<T: AsIoSlices + Unpin> OpCode for SendToImpl<T> {
         syscall!(break sendmsg(self.fd, &self.msg, 0))
     }
 }
+
+/// Send data to remote with `MSG_ZEROCOPY`, and complete after the kernel
+/// notifies that the buffer is released, so the buffer is not returned while
+/// the kernel may still read it. The socket should have `SO_ZEROCOPY` enabled.
+///
+/// If the kernel runs out of the locked memory for pinning, the data is sent
+/// by copying.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub struct SendZeroCopy<T: IoBuf> {
+    pub(crate) fd: RawFd,
+    pub(crate) buffer: T,
+    tracker: Arc<ZeroCopyTracker>,
+    // The id and length of the send, after it succeeds.
+    sent: Option<(u32, usize)>,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl<T: IoBuf> SendZeroCopy<T> {
+    /// Create [`SendZeroCopy`]. The tracker should be shared by all the sends
+    /// of the socket, because the notifications are numbered per socket.
+    pub fn new(fd: RawFd, buffer: T, tracker: Arc<ZeroCopyTracker>) -> Self {
+        Self {
+            fd,
+            buffer,
+            tracker,
+            sent: None,
+        }
+    }
+
+    fn poll_notified(&self, id: u32, len: usize) -> Poll<io::Result<usize>> {
+        drain_zerocopy_notifications(self.fd, &self.tracker)?;
+        if self.tracker.take(id) {
+            Poll::Ready(Ok(len))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl<T: IoBuf> IntoInner for SendZeroCopy<T> {
+    type Inner = T;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl<T: IoBuf> OpCode for SendZeroCopy<T> {
+    fn pre_submit(self: Pin<&mut Self>) -> io::Result<Decision> {
+        Ok(Decision::wait_writable(self.fd))
+    }
+
+    fn on_event(self: Pin<&mut Self>, _: &Event) -> Poll<io::Result<usize>> {
+        // The buffer is never moved.
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Some((id, len)) = this.sent {
+            return this.poll_notified(id, len);
+        }
+        let slice = this.buffer.as_slice();
+        let res = syscall!(send(
+            this.fd,
+            slice.as_ptr() as _,
+            slice.len(),
+            libc::MSG_ZEROCOPY | libc::MSG_NOSIGNAL
+        ));
+        match res {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            Err(e) if e.raw_os_error() == Some(libc::ENOBUFS) => syscall!(
+                break send(
+                    this.fd,
+                    slice.as_ptr() as _,
+                    slice.len(),
+                    libc::MSG_NOSIGNAL
+                )
+            ),
+            Err(e) => Poll::Ready(Err(e)),
+            Ok(len) => {
+                let id = this.tracker.next_id();
+                this.sent = Some((id, len as _));
+                this.poll_notified(id, len as _)
+            }
+        }
+    }
+
+    fn wait_error_queue(&self) -> bool {
+        self.sent.is_some()
+    }
+}
+
+impl OpCode for MsgRing {
+    fn pre_submit(self: Pin<&mut Self>) -> io::Result<Decision> {
+        self.sender.send(self.msg)?;
+        Ok(Decision::Completed(0))
+    }
+
+    fn on_event(self: Pin<&mut Self>, _: &Event) -> Poll<io::Result<usize>> {
+        unreachable!("MsgRing operation should not be submitted to polling")
+    }
+}
+
+impl<T: IoBufMut> OpCode for GetRandom<T> {
+    fn pre_submit(mut self: Pin<&mut Self>) -> io::Result<Decision> {
+        // Reading `/dev/urandom` never blocks.
+        let fd = self.fd;
+        let slice = self.buffer.as_uninit_slice();
+        let res = syscall!(read(fd, slice.as_mut_ptr() as _, slice.len()))?;
+        Ok(Decision::Completed(res as _))
+    }
+
+    fn on_event(self: Pin<&mut Self>, _: &Event) -> Poll<io::Result<usize>> {
+        unreachable!("GetRandom operation should not be submitted to polling")
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl OpCode for SendFile {
+    fn pre_submit(self: Pin<&mut Self>) -> io::Result<Decision> {
+        match self.send() {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(Decision::wait_writable(self.fd)),
+            res => res.map(Decision::Completed),
+        }
+    }
+
+    fn on_event(self: Pin<&mut Self>, event: &Event) -> Poll<io::Result<usize>> {
+        debug_assert!(event.writable);
+
+        match self.send() {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            res => Poll::Ready(res),
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl OpCode for Splice {
+    fn pre_submit(self: Pin<&mut Self>) -> io::Result<Decision> {
+        match self.splice() {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Ok(Decision::wait_for(self.fd(), self.interest))
+            }
+            res => res.map(Decision::Completed),
+        }
+    }
+
+    fn on_event(self: Pin<&mut Self>, _: &Event) -> Poll<io::Result<usize>> {
+        match self.splice() {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            res => Poll::Ready(res),
+        }
+    }
+}
+
+impl OpCode for WaitProcess {
+    fn pre_submit(self: Pin<&mut Self>) -> io::Result<Decision> {
+        Ok(Decision::wait_readable(self.fd))
+    }
+
+    fn on_event(self: Pin<&mut Self>, event: &Event) -> Poll<io::Result<usize>> {
+        debug_assert!(event.readable);
+
+        Poll::Ready(Ok(0))
+    }
+}
+
+impl OpCode for PollOnce {
+    fn pre_submit(self: Pin<&mut Self>) -> io::Result<Decision> {
+        Ok(Decision::wait_for(self.fd, self.interest))
+    }
+
+    fn on_event(self: Pin<&mut Self>, _: &Event) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(0))
+    }
+}
+
+impl<B: std::marker::Send + 'static> OpCode for BlockingBufOp<B> {
+    fn pre_submit(self: Pin<&mut Self>) -> io::Result<Decision> {
+        // The buffer is never pinned.
+        let fd = unsafe { self.get_unchecked_mut() }.start()?;
+        Ok(Decision::wait_readable(fd))
+    }
+
+    fn on_event(self: Pin<&mut Self>, _: &Event) -> Poll<io::Result<usize>> {
+        if let Some(notifier) = &self.notifier {
+            notifier.clear()?;
+        }
+        Poll::Ready(
+            self.take_result()
+                .expect("the result should be set before notifying"),
+        )
+    }
+
+    fn cancel(self: Pin<&mut Self>) -> bool {
+        self.interrupt();
+        true
+    }
+}
+
+impl OpCode for BlockingOp {
+    fn pre_submit(self: Pin<&mut Self>) -> io::Result<Decision> {
+        Pin::new(&mut self.get_mut().inner).pre_submit()
+    }
+
+    fn on_event(self: Pin<&mut Self>, event: &Event) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).on_event(event)
+    }
+
+    fn cancel(self: Pin<&mut Self>) -> bool {
+        Pin::new(&mut self.get_mut().inner).cancel()
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    collections::HashMap,
+    io,
+    os::fd::{AsRawFd, BorrowedFd, RawFd},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use polling::{Event, Events};
+
+use crate::{
+    driver::{Notifier, PollingMode, SetupError},
+    syscall,
+};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const NATIVE: (PollingMode, &str) = (PollingMode::Epoll, "epoll_create1");
+#[cfg(any(target_os = "illumos", target_os = "solaris"))]
+const NATIVE: (PollingMode, &str) = (PollingMode::EventPorts, "port_create");
+#[cfg(any(
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly",
+))]
+const NATIVE: (PollingMode, &str) = (PollingMode::Kqueue, "kqueue");
+// `polling` falls back to `poll` itself, with a pipe to notify.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "illumos",
+    target_os = "solaris",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly",
+)))]
+const NATIVE: (PollingMode, &str) = (PollingMode::Poll, "pipe");
+
+/// The poller of the driver, the native one of `polling`, or the fallback with
+/// `poll` on the platforms where `polling` chooses another one.
+#[derive(Debug)]
+pub(super) enum Poller {
+    Native(polling::Poller),
+    Poll(PollFds),
+}
+
+impl Poller {
+    pub fn new(mode: PollingMode) -> io::Result<Self> {
+        match mode {
+            PollingMode::Auto => Self::native(),
+            _ if mode == NATIVE.0 => Self::native(),
+            PollingMode::Poll => Ok(Self::Poll(PollFds::new()?)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("the polling mode {mode:?} is not supported on this platform"),
+            )),
+        }
+    }
+
+    fn native() -> io::Result<Self> {
+        let poller = polling::Poller::new().map_err(|e| SetupError::wrap(NATIVE.1, e))?;
+        Ok(Self::Native(poller))
+    }
+
+    pub fn mode(&self) -> PollingMode {
+        match self {
+            Self::Native(_) => NATIVE.0,
+            Self::Poll(_) => PollingMode::Poll,
+        }
+    }
+
+    // The most fds waited at once by `poll`, or 0 for the native pollers.
+    pub fn max_poll_fds(&self) -> usize {
+        match self {
+            Self::Native(_) => 0,
+            Self::Poll(fds) => fds.max_fds.load(Ordering::Relaxed),
+        }
+    }
+
+    pub unsafe fn add(&self, fd: RawFd, interest: Event) -> io::Result<()> {
+        match self {
+            Self::Native(poller) => poller.add(fd, interest),
+            Self::Poll(fds) => fds.add(fd, interest),
+        }
+    }
+
+    pub fn modify(&self, fd: BorrowedFd, interest: Event) -> io::Result<()> {
+        match self {
+            Self::Native(poller) => poller.modify(fd, interest),
+            Self::Poll(fds) => fds.modify(fd.as_raw_fd(), interest),
+        }
+    }
+
+    pub fn delete(&self, fd: BorrowedFd) -> io::Result<()> {
+        match self {
+            Self::Native(poller) => poller.delete(fd),
+            Self::Poll(fds) => fds.delete(fd.as_raw_fd()),
+        }
+    }
+
+    /// Wait for the events, and append them to `ready`, with whether the fd
+    /// has an error. `events` is the buffer of the native poller.
+    pub fn wait(
+        &self,
+        events: &mut Events,
+        ready: &mut Vec<(Event, bool)>,
+        timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        match self {
+            Self::Native(poller) => {
+                events.clear();
+                poller.wait(events, timeout)?;
+                ready.extend(events.iter().map(|event| (event, event.is_err() == Some(true))));
+                Ok(())
+            }
+            Self::Poll(fds) => fds.wait(ready, timeout),
+        }
+    }
+
+    pub fn notify(&self) -> io::Result<()> {
+        match self {
+            Self::Native(poller) => poller.notify(),
+            Self::Poll(fds) => fds.notifier.notify(),
+        }
+    }
+}
+
+impl AsRawFd for Poller {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Self::Native(poller) => poller.as_raw_fd(),
+            Self::Poll(fds) => fds.notifier.as_raw_fd(),
+        }
+    }
+}
+
+/// The fds waited by `poll`. The interests are oneshot like the native
+/// pollers: an fd is disarmed after its event is reported, until it is
+/// modified.
+#[derive(Debug)]
+pub(super) struct PollFds {
+    interests: Mutex<HashMap<RawFd, Option<Event>>>,
+    // The buffer of `poll`, reused by the waits.
+    fds: Mutex<Vec<libc::pollfd>>,
+    notifier: Notifier,
+    // The most fds waited at once, without the notifier.
+    max_fds: AtomicUsize,
+}
+
+impl PollFds {
+    fn new() -> io::Result<Self> {
+        Ok(Self {
+            interests: Mutex::default(),
+            fds: Mutex::default(),
+            notifier: Notifier::new().map_err(|e| SetupError::wrap(Notifier::SYSCALL, e))?,
+            max_fds: AtomicUsize::new(0),
+        })
+    }
+
+    fn add(&self, fd: RawFd, interest: Event) -> io::Result<()> {
+        match self.interests.lock().unwrap().entry(fd) {
+            std::collections::hash_map::Entry::Occupied(_) => {
+                Err(io::Error::from_raw_os_error(libc::EEXIST))
+            }
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(Some(interest));
+                Ok(())
+            }
+        }
+    }
+
+    fn modify(&self, fd: RawFd, interest: Event) -> io::Result<()> {
+        match self.interests.lock().unwrap().get_mut(&fd) {
+            Some(armed) => {
+                *armed = Some(interest);
+                Ok(())
+            }
+            None => Err(io::Error::from_raw_os_error(libc::ENOENT)),
+        }
+    }
+
+    fn delete(&self, fd: RawFd) -> io::Result<()> {
+        match self.interests.lock().unwrap().remove(&fd) {
+            Some(_) => Ok(()),
+            None => Err(io::Error::from_raw_os_error(libc::ENOENT)),
+        }
+    }
+
+    fn wait(&self, ready: &mut Vec<(Event, bool)>, timeout: Option<Duration>) -> io::Result<()> {
+        let mut fds = self.fds.lock().unwrap();
+        fds.clear();
+        fds.push(libc::pollfd {
+            fd: self.notifier.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        });
+        fds.extend(
+            self.interests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter_map(|(fd, interest)| {
+                    let interest = (*interest)?;
+                    let mut events = 0;
+                    if interest.readable {
+                        events |= libc::POLLIN | libc::POLLPRI;
+                    }
+                    if interest.writable {
+                        events |= libc::POLLOUT;
+                    }
+                    // The errors are reported without the interests.
+                    Some(libc::pollfd {
+                        fd: *fd,
+                        events,
+                        revents: 0,
+                    })
+                }),
+        );
+        self.max_fds.fetch_max(fds.len() - 1, Ordering::Relaxed);
+        match poll(&mut fds, timeout) {
+            // Like the native pollers, it returns without events.
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => return Ok(()),
+            res => res?,
+        };
+        if fds[0].revents != 0 {
+            self.notifier.clear()?;
+        }
+        let mut interests = self.interests.lock().unwrap();
+        for fd in &fds[1..] {
+            if fd.revents == 0 {
+                continue;
+            }
+            let Some(armed) = interests.get_mut(&fd.fd) else {
+                continue;
+            };
+            let Some(interest) = armed.take() else {
+                continue;
+            };
+            // A closed fd is reported as ready, so that its ops fail instead of
+            // waiting forever.
+            let failed = libc::POLLHUP | libc::POLLERR | libc::POLLNVAL;
+            let readable = fd.revents & (libc::POLLIN | libc::POLLPRI | failed) != 0;
+            let writable = fd.revents & (libc::POLLOUT | failed) != 0;
+            ready.push((
+                Event::new(interest.key, readable, writable),
+                fd.revents & libc::POLLERR != 0,
+            ));
+        }
+        Ok(())
+    }
+}
+
+// `ppoll` waits with the precision of nanoseconds.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn poll(fds: &mut [libc::pollfd], timeout: Option<Duration>) -> io::Result<libc::c_int> {
+    let timeout = timeout.map(|timeout| libc::timespec {
+        tv_sec: timeout.as_secs().min(libc::time_t::MAX as u64) as _,
+        tv_nsec: timeout.subsec_nanos() as _,
+    });
+    syscall!(ppoll(
+        fds.as_mut_ptr(),
+        fds.len() as _,
+        timeout.as_ref().map_or(std::ptr::null(), |timeout| timeout),
+        std::ptr::null(),
+    ))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn poll(fds: &mut [libc::pollfd], timeout: Option<Duration>) -> io::Result<libc::c_int> {
+    // Round up, so that the wait doesn't return before the timeout.
+    let timeout = timeout.map_or(-1, |timeout| {
+        timeout.as_nanos().div_ceil(1_000_000).min(libc::c_int::MAX as u128) as libc::c_int
+    });
+    syscall!(poll(fds.as_mut_ptr(), fds.len() as _, timeout))
+}
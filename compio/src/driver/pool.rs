@@ -0,0 +1,218 @@
+//! A lazily grown thread pool to run the blocking operations.
+//!
+//! On Unix, all signals but [`interrupt_signal`] are blocked on the threads of
+//! the pool, so that the signal handlers run on the other threads, and the
+//! blocking syscalls in the jobs are only interrupted with `EINTR` by
+//! [`RunningThread::interrupt`].
+
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Condvar, Mutex, OnceLock},
+    time::Duration,
+};
+#[cfg(windows)]
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+
+#[cfg(windows)]
+use windows_sys::Win32::{
+    Foundation::{DuplicateHandle, DUPLICATE_SAME_ACCESS},
+    System::{
+        Threading::{GetCurrentProcess, GetCurrentThread},
+        IO::CancelSynchronousIo,
+    },
+};
+
+#[cfg(windows)]
+use crate::syscall;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+const MAX_THREADS: usize = 256;
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<Job>,
+    threads: usize,
+    idle: usize,
+}
+
+#[derive(Default)]
+struct ThreadPool {
+    queue: Mutex<Queue>,
+    cond: Condvar,
+}
+
+impl ThreadPool {
+    fn spawn(&'static self, job: Job) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.jobs.push_back(job);
+        if queue.idle >= queue.jobs.len() || queue.threads >= MAX_THREADS {
+            self.cond.notify_one();
+            return;
+        }
+        let res = std::thread::Builder::new()
+            .name("compio-blocking".into())
+            .spawn(move || {
+                #[cfg(unix)]
+                block_signals();
+                self.run()
+            });
+        match res {
+            Ok(_) => queue.threads += 1,
+            Err(_) if queue.threads > 0 => self.cond.notify_one(),
+            // No thread could run the job, so run it here.
+            Err(_) => {
+                let job = queue.jobs.pop_back().expect("the job was just pushed");
+                drop(queue);
+                job();
+            }
+        }
+    }
+
+    fn run(&self) {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            if let Some(job) = queue.jobs.pop_front() {
+                drop(queue);
+                job();
+                queue = self.queue.lock().unwrap();
+                continue;
+            }
+            queue.idle += 1;
+            let (guard, res) = self.cond.wait_timeout(queue, IDLE_TIMEOUT).unwrap();
+            queue = guard;
+            queue.idle -= 1;
+            if res.timed_out() && queue.jobs.is_empty() {
+                queue.threads -= 1;
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn block_signals() {
+    unsafe {
+        let mut set = std::mem::MaybeUninit::uninit();
+        libc::sigfillset(set.as_mut_ptr());
+        libc::sigdelset(set.as_mut_ptr(), interrupt_signal());
+        libc::pthread_sigmask(libc::SIG_BLOCK, set.as_ptr(), std::ptr::null_mut());
+    }
+}
+
+/// The signal sent to a thread of the pool to interrupt its blocking syscall.
+///
+/// On Linux and Android, it is a real-time signal, which is never sent by the
+/// system. Otherwise, it is `SIGURG`, which is ignored by default.
+#[cfg(unix)]
+pub(crate) fn interrupt_signal() -> libc::c_int {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        // The lower ones are more likely taken by the other libraries.
+        libc::SIGRTMAX() - 2
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        libc::SIGURG
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn interrupt_handler(_: libc::c_int) {}
+
+// Install a handler doing nothing without `SA_RESTART`, so that the signal
+// interrupts the syscalls. Returns `false` if the signal is handled by others,
+// and it shouldn't be sent then.
+#[cfg(unix)]
+fn install_interrupt_handler() -> bool {
+    let handler = interrupt_handler as *const () as usize;
+    unsafe {
+        let mut old: libc::sigaction = std::mem::zeroed();
+        if libc::sigaction(interrupt_signal(), std::ptr::null(), &mut old) != 0 {
+            return false;
+        }
+        if old.sa_sigaction == handler {
+            return true;
+        }
+        if old.sa_sigaction != libc::SIG_DFL && old.sa_sigaction != libc::SIG_IGN {
+            return false;
+        }
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handler;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(interrupt_signal(), &action, std::ptr::null_mut()) == 0
+    }
+}
+
+/// A thread of the pool running a job, to interrupt the blocking syscall in
+/// it.
+pub(crate) struct RunningThread {
+    #[cfg(unix)]
+    thread: libc::pthread_t,
+    #[cfg(windows)]
+    thread: OwnedHandle,
+}
+
+impl RunningThread {
+    /// The current thread, which should be a thread of the pool.
+    pub fn current() -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            Ok(Self {
+                thread: unsafe { libc::pthread_self() },
+            })
+        }
+        #[cfg(windows)]
+        {
+            // The handle from `GetCurrentThread` is a pseudo one, which only
+            // refers to the current thread.
+            let mut handle = 0;
+            syscall!(
+                BOOL,
+                DuplicateHandle(
+                    GetCurrentProcess(),
+                    GetCurrentThread(),
+                    GetCurrentProcess(),
+                    &mut handle,
+                    0,
+                    0,
+                    DUPLICATE_SAME_ACCESS,
+                )
+            )?;
+            Ok(Self {
+                thread: unsafe { OwnedHandle::from_raw_handle(handle as _) },
+            })
+        }
+    }
+
+    /// Interrupt the blocking syscall of the thread. On Unix, it fails with
+    /// `EINTR` by [`interrupt_signal`]. On Windows, the synchronous IO fails
+    /// with `ERROR_OPERATION_ABORTED` by `CancelSynchronousIo`.
+    ///
+    /// A syscall entered after it returns is not interrupted. Returns `false`
+    /// if the thread isn't interrupted, because the signal is handled by
+    /// others.
+    pub fn interrupt(&self) -> bool {
+        #[cfg(unix)]
+        {
+            if !install_interrupt_handler() {
+                return false;
+            }
+            unsafe { libc::pthread_kill(self.thread, interrupt_signal()) == 0 }
+        }
+        #[cfg(windows)]
+        unsafe {
+            // Fails with `ERROR_NOT_FOUND` if there is no synchronous IO.
+            CancelSynchronousIo(self.thread.as_raw_handle() as _);
+            true
+        }
+    }
+}
+
+/// Run the job on the blocking thread pool.
+pub(crate) fn spawn_blocking(job: impl FnOnce() + Send + 'static) {
+    static POOL: OnceLock<ThreadPool> = OnceLock::new();
+    POOL.get_or_init(ThreadPool::default).spawn(Box::new(job))
+}
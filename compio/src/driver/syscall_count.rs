@@ -0,0 +1,78 @@
+//! The counters of the syscalls performed by the driver and the IO types, for
+//! the regression benchmarks.
+
+use std::{cell::RefCell, collections::BTreeMap, fmt::Display};
+
+thread_local! {
+    static COUNTS: RefCell<BTreeMap<&'static str, u64>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+pub(crate) fn record_syscall(name: &'static str) {
+    COUNTS.with_borrow_mut(|counts| *counts.entry(name).or_default() += 1);
+}
+
+/// A snapshot of the syscall counters of the current thread, see
+/// [`syscall_counts`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SyscallCounts(BTreeMap<&'static str, u64>);
+
+impl SyscallCounts {
+    /// The count of the syscall of the name, e.g., `"recv"`.
+    pub fn get(&self, name: &str) -> u64 {
+        self.0.get(name).copied().unwrap_or_default()
+    }
+
+    /// The count of all syscalls.
+    pub fn total(&self) -> u64 {
+        self.0.values().sum()
+    }
+
+    /// The names and the counts of the syscalls performed, in the order of
+    /// the names.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, u64)> + '_ {
+        self.0.iter().map(|(name, count)| (*name, *count))
+    }
+}
+
+impl Display for SyscallCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, count) in self.iter() {
+            writeln!(f, "{name}: {count}")?;
+        }
+        write!(f, "total: {}", self.total())
+    }
+}
+
+/// The syscalls performed on the current thread since it starts, or since the
+/// last [`reset_syscall_counts`]. The syscalls are named by the functions
+/// called, e.g., `"recv"`, and the waits of the driver are `"io_uring_enter"`
+/// with io-uring, `"poller_wait"` with polling and
+/// `"GetQueuedCompletionStatusEx"` with IOCP.
+///
+/// Only the syscalls of compio itself are counted, e.g., not the ones in the
+/// blocking functions running on the thread pool.
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use compio::net::UdpSocket;
+///
+/// compio::task::block_on(async {
+///     let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     socket.connect(socket.local_addr().unwrap()).unwrap();
+///     compio::driver::reset_syscall_counts();
+///     socket.send("ping").await.0.unwrap();
+///     socket.recv(Vec::with_capacity(4)).await.0.unwrap();
+///     let counts = compio::driver::syscall_counts();
+///     println!("{counts}");
+///     assert!(counts.total() > 0);
+/// })
+/// ```
+pub fn syscall_counts() -> SyscallCounts {
+    COUNTS.with_borrow(|counts| SyscallCounts(counts.clone()))
+}
+
+/// Clear the syscall counters of the current thread, see [`syscall_counts`].
+pub fn reset_syscall_counts() {
+    COUNTS.with_borrow_mut(BTreeMap::clear)
+}
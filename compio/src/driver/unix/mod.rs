@@ -3,16 +3,37 @@
 
 pub(crate) mod op;
 
-use std::{mem::ManuallyDrop, pin::Pin, ptr::NonNull};
+use std::{
+    alloc::Layout,
+    io,
+    mem::ManuallyDrop,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    pin::Pin,
+    ptr::NonNull,
+    rc::Rc,
+};
 
-use crate::driver::OpCode;
+use crate::{
+    driver::{op_pool::OpPool, OpCode},
+    syscall,
+};
 
-pub(crate) struct RawOp(NonNull<dyn OpCode>);
+/// The interest of the operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interest {
+    /// Represents a read operation.
+    Readable,
+    /// Represents a write operation.
+    Writable,
+}
+
+// The allocation is taken from the pool, and given back when the op is
+// dropped or moved out.
+pub(crate) struct RawOp(NonNull<dyn OpCode>, Rc<OpPool>);
 
 impl RawOp {
-    pub(crate) fn new(_user_data: usize, op: impl OpCode + 'static) -> Self {
-        let op = Box::new(op);
-        Self(unsafe { NonNull::new_unchecked(Box::into_raw(op as Box<dyn OpCode>)) })
+    pub(crate) fn new(_user_data: usize, op: impl OpCode + 'static, pool: &Rc<OpPool>) -> Self {
+        Self(pool.alloc(op), pool.clone())
     }
 
     pub(crate) fn as_pin(&mut self) -> Pin<&mut dyn OpCode> {
@@ -21,12 +42,181 @@ impl RawOp {
 
     pub unsafe fn into_inner<T: OpCode>(self) -> T {
         let this = ManuallyDrop::new(self);
-        *Box::from_raw(this.0.cast().as_ptr())
+        let op = this.0.cast::<T>().as_ptr().read();
+        this.1.release(this.0.cast(), Layout::new::<T>());
+        drop(std::ptr::read(&this.1));
+        op
     }
 }
 
 impl Drop for RawOp {
     fn drop(&mut self) {
-        drop(unsafe { Box::from_raw(self.0.as_ptr()) })
+        unsafe {
+            let layout = Layout::for_value(self.0.as_ref());
+            std::ptr::drop_in_place(self.0.as_ptr());
+            self.1.release(self.0.cast(), layout);
+        }
+    }
+}
+
+/// A syscall failed while setting up the driver, e.g., it is blocked by a
+/// seccomp filter. It is the inner error of the [`io::Error`] returned by
+/// [`ProactorBuilder::build`], which has the same kind.
+///
+/// The driver makes the syscalls it needs when it is built, so that a
+/// restricted environment fails at once instead of at the first op. See
+/// [`required_syscalls`] for the allowlist.
+///
+/// ```
+/// use compio::driver::{Proactor, SetupError};
+///
+/// if let Err(e) = Proactor::new() {
+///     if let Some(e) = e.get_ref().and_then(|e| e.downcast_ref::<SetupError>()) {
+///         eprintln!("`{}` is not allowed", e.syscall());
+///     }
+/// }
+/// ```
+///
+/// [`ProactorBuilder::build`]: crate::driver::ProactorBuilder::build
+/// [`required_syscalls`]: crate::driver::required_syscalls
+#[derive(Debug)]
+pub struct SetupError {
+    syscall: &'static str,
+    source: io::Error,
+}
+
+impl SetupError {
+    pub(crate) fn wrap(syscall: &'static str, source: io::Error) -> io::Error {
+        io::Error::new(source.kind(), Self { syscall, source })
+    }
+
+    /// The name of the syscall failed.
+    pub fn syscall(&self) -> &'static str {
+        self.syscall
+    }
+
+    /// The OS error of the syscall.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.source.raw_os_error()
+    }
+}
+
+impl std::fmt::Display for SetupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` failed when setting up the driver: {}",
+            self.syscall, self.source
+        )?;
+        if matches!(
+            self.source.raw_os_error(),
+            Some(libc::EPERM | libc::EACCES | libc::ENOSYS)
+        ) {
+            f.write_str(", it may be blocked by seccomp or not supported by the kernel")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SetupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Wakes the driver from other threads by making an fd readable. It is an
+/// eventfd if supported, otherwise a pipe.
+#[derive(Debug)]
+pub(crate) struct Notifier {
+    receiver: OwnedFd,
+    sender: OwnedFd,
+}
+
+impl Notifier {
+    /// The syscall creating the fd.
+    #[cfg(any(
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "illumos",
+        target_os = "linux",
+    ))]
+    pub const SYSCALL: &'static str = "eventfd2";
+    #[cfg(not(any(
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "illumos",
+        target_os = "linux",
+    )))]
+    pub const SYSCALL: &'static str = "pipe";
+
+    #[cfg(any(
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "illumos",
+        target_os = "linux",
+    ))]
+    pub fn new() -> io::Result<Self> {
+        let fd = syscall!(eventfd(0, libc::EFD_CLOEXEC))?;
+        let receiver = unsafe { OwnedFd::from_raw_fd(fd) };
+        let sender = receiver.try_clone()?;
+        Ok(Self { receiver, sender })
+    }
+
+    #[cfg(not(any(
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "illumos",
+        target_os = "linux",
+    )))]
+    pub fn new() -> io::Result<Self> {
+        let mut fds = [-1, -1];
+        syscall!(pipe(fds.as_mut_ptr()))?;
+        let receiver = unsafe { OwnedFd::from_raw_fd(fds[0]) };
+        let sender = unsafe { OwnedFd::from_raw_fd(fds[1]) };
+        // Both ends are nonblocking: a full pipe is notified already, and the
+        // reader drains it until empty.
+        for fd in [&receiver, &sender] {
+            syscall!(fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC))?;
+            syscall!(fcntl(fd.as_raw_fd(), libc::F_SETFL, libc::O_NONBLOCK))?;
+        }
+        Ok(Self { receiver, sender })
+    }
+
+    /// The fd to wait for readable.
+    pub fn as_raw_fd(&self) -> RawFd {
+        self.receiver.as_raw_fd()
+    }
+
+    pub fn notify(&self) -> io::Result<()> {
+        // The eventfd needs 8 bytes, and the pipe receives them all.
+        let data = 1u64;
+        match syscall!(write(
+            self.sender.as_raw_fd(),
+            &data as *const _ as *const _,
+            std::mem::size_of::<u64>(),
+        )) {
+            // The pipe is full of the notifications not cleared yet.
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            res => res.map(|_| ()),
+        }
+    }
+
+    /// Consume the notifications after the fd is readable.
+    #[cfg_attr(all(target_os = "linux", feature = "io-uring"), allow(dead_code))]
+    pub fn clear(&self) -> io::Result<()> {
+        // The eventfd is read at once, and the pipe until a short read.
+        let mut data = [0u64; 16];
+        loop {
+            match syscall!(read(
+                self.receiver.as_raw_fd(),
+                data.as_mut_ptr().cast(),
+                std::mem::size_of_val(&data),
+            )) {
+                Ok(len) if (len as usize) < std::mem::size_of_val(&data) => return Ok(()),
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
     }
 }
@@ -1,13 +1,17 @@
-use std::io::{IoSlice, IoSliceMut};
+use std::{
+    io::{self, IoSlice, IoSliceMut},
+    sync::Arc,
+};
 
-use libc::{sockaddr_storage, socklen_t};
+use libc::socklen_t;
 use socket2::SockAddr;
 
 #[cfg(doc)]
 use crate::op::*;
 use crate::{
-    buf::{AsIoSlices, AsIoSlicesMut, IntoInner, OneOrVec},
-    driver::RawFd,
+    buf::{AsIoSlices, AsIoSlicesMut, IntoInner, IoBuf, OneOrVec, VectoredBufWrapper, WrapBuf},
+    driver::{sockaddr_storage, Interest, Notifier, RawFd},
+    op::BlockingBufOp,
 };
 
 /// Accept a connection.
@@ -38,6 +42,10 @@ pub struct RecvImpl<T: AsIoSlicesMut + Unpin> {
     pub(crate) fd: RawFd,
     pub(crate) buffer: T,
     pub(crate) slices: OneOrVec<IoSliceMut<'static>>,
+    #[cfg_attr(not(all(target_os = "linux", feature = "io-uring")), allow(dead_code))]
+    pub(crate) poll_first: bool,
+    #[cfg_attr(not(all(target_os = "linux", feature = "io-uring")), allow(dead_code))]
+    pub(crate) msg: libc::msghdr,
 }
 
 impl<T: AsIoSlicesMut + Unpin> RecvImpl<T> {
@@ -47,8 +55,23 @@ impl<T: AsIoSlicesMut + Unpin> RecvImpl<T> {
             fd,
             buffer: T::new(buffer),
             slices: OneOrVec::One(IoSliceMut::new(&mut [])),
+            poll_first: false,
+            msg: unsafe { std::mem::zeroed() },
         }
     }
+
+    /// Wait for the data before trying to receive, i.e.,
+    /// `IORING_RECVSEND_POLL_FIRST`. It saves the first attempt if the data is
+    /// unlikely to be there, e.g., on a mostly idle socket.
+    ///
+    /// ## Platform specific
+    /// * io-uring: Linux 5.19 or later is required, and it is ignored by the
+    ///   older kernels.
+    /// * IOCP/polling: it is ignored.
+    pub fn poll_first(mut self, poll_first: bool) -> Self {
+        self.poll_first = poll_first;
+        self
+    }
 }
 
 impl<T: AsIoSlicesMut + Unpin> IntoInner for RecvImpl<T> {
@@ -85,11 +108,50 @@ impl<T: AsIoSlices + Unpin> IntoInner for SendImpl<T> {
     }
 }
 
+// The limit of the slices of a vectored op on Linux, macOS and the BSDs. The
+// rest of the buffers is left to the next call, like a short write.
+pub(crate) const IOV_MAX: usize = 1024;
+
+/// Write a file at specified position from specified buffers.
+pub struct WriteVectoredAt<T: IoBuf> {
+    pub(crate) fd: RawFd,
+    pub(crate) offset: u64,
+    pub(crate) buffer: VectoredBufWrapper<T>,
+    pub(crate) slices: OneOrVec<IoSlice<'static>>,
+}
+
+impl<T: IoBuf> WriteVectoredAt<T> {
+    /// Create [`WriteVectoredAt`].
+    pub fn new(fd: RawFd, offset: u64, buffer: Vec<T>) -> Self {
+        Self {
+            fd,
+            offset,
+            buffer: VectoredBufWrapper::new(buffer),
+            slices: OneOrVec::One(IoSlice::new(&[])),
+        }
+    }
+
+    pub(crate) fn set_slices(&mut self) -> &[IoSlice<'static>] {
+        self.slices = unsafe { self.buffer.as_io_slices() };
+        &self.slices[..self.slices.len().min(IOV_MAX)]
+    }
+}
+
+impl<T: IoBuf> IntoInner for WriteVectoredAt<T> {
+    type Inner = VectoredBufWrapper<T>;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
 /// Receive data and source address.
 pub struct RecvFromImpl<T: AsIoSlicesMut + Unpin> {
     pub(crate) fd: RawFd,
     pub(crate) buffer: T,
     pub(crate) addr: sockaddr_storage,
+    pub(crate) control: Vec<u8>,
+    pub(crate) flags: i32,
     pub(crate) slices: OneOrVec<IoSliceMut<'static>>,
     pub(crate) msg: libc::msghdr,
 }
@@ -97,15 +159,37 @@ pub struct RecvFromImpl<T: AsIoSlicesMut + Unpin> {
 impl<T: AsIoSlicesMut + Unpin> RecvFromImpl<T> {
     /// Create [`RecvFrom`] or [`RecvFromVectored`].
     pub fn new(fd: RawFd, buffer: T::Inner) -> Self {
+        Self::with_control(fd, buffer, Vec::new())
+    }
+
+    /// Create [`RecvFrom`] or [`RecvFromVectored`] with a buffer to receive
+    /// ancillary data. The length of `control` is the space available, and
+    /// the received control messages could be walked with
+    /// [`RecvFromImpl::msg`] after the operation completes.
+    pub fn with_control(fd: RawFd, buffer: T::Inner, control: Vec<u8>) -> Self {
         Self {
             fd,
             buffer: T::new(buffer),
             addr: unsafe { std::mem::zeroed() },
+            control,
+            flags: 0,
             slices: OneOrVec::One(IoSliceMut::new(&mut [])),
             msg: unsafe { std::mem::zeroed() },
         }
     }
 
+    /// Set the flags passed to `recvmsg`, e.g., `MSG_ERRQUEUE` on Linux to
+    /// receive from the error queue of the socket.
+    pub fn with_flags(mut self, flags: i32) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// The message header filled by the operation.
+    pub fn msg(&self) -> &libc::msghdr {
+        &self.msg
+    }
+
     pub(crate) fn set_msg(&mut self) {
         self.slices = unsafe { self.buffer.as_io_slices_mut() };
         self.msg = libc::msghdr {
@@ -113,8 +197,12 @@ impl<T: AsIoSlicesMut + Unpin> RecvFromImpl<T> {
             msg_namelen: std::mem::size_of_val(&self.addr) as _,
             msg_iov: self.slices.as_mut_ptr() as _,
             msg_iovlen: self.slices.len() as _,
-            msg_control: std::ptr::null_mut(),
-            msg_controllen: 0,
+            msg_control: if self.control.is_empty() {
+                std::ptr::null_mut()
+            } else {
+                self.control.as_mut_ptr() as _
+            },
+            msg_controllen: self.control.len() as _,
             msg_flags: 0,
         };
     }
@@ -133,6 +221,7 @@ pub struct SendToImpl<T: AsIoSlices + Unpin> {
     pub(crate) fd: RawFd,
     pub(crate) buffer: T,
     pub(crate) addr: SockAddr,
+    pub(crate) control: Vec<u8>,
     pub(crate) slices: OneOrVec<IoSlice<'static>>,
     pub(crate) msg: libc::msghdr,
 }
@@ -140,10 +229,17 @@ pub struct SendToImpl<T: AsIoSlices + Unpin> {
 impl<T: AsIoSlices + Unpin> SendToImpl<T> {
     /// Create [`SendTo`] or [`SendToVectored`].
     pub fn new(fd: RawFd, buffer: T::Inner, addr: SockAddr) -> Self {
+        Self::with_control(fd, buffer, addr, Vec::new())
+    }
+
+    /// Create [`SendTo`] or [`SendToVectored`] with ancillary data. The
+    /// `control` buffer should contain properly aligned `cmsghdr` entries.
+    pub fn with_control(fd: RawFd, buffer: T::Inner, addr: SockAddr, control: Vec<u8>) -> Self {
         Self {
             fd,
             buffer: T::new(buffer),
             addr,
+            control,
             slices: OneOrVec::One(IoSlice::new(&[])),
             msg: unsafe { std::mem::zeroed() },
         }
@@ -156,8 +252,12 @@ impl<T: AsIoSlices + Unpin> SendToImpl<T> {
             msg_namelen: self.addr.len(),
             msg_iov: self.slices.as_mut_ptr() as _,
             msg_iovlen: self.slices.len() as _,
-            msg_control: std::ptr::null_mut(),
-            msg_controllen: 0,
+            msg_control: if self.control.is_empty() {
+                std::ptr::null_mut()
+            } else {
+                self.control.as_mut_ptr() as _
+            },
+            msg_controllen: self.control.len() as _,
             msg_flags: 0,
         };
     }
@@ -170,3 +270,134 @@ impl<T: AsIoSlices + Unpin> IntoInner for SendToImpl<T> {
         self.buffer
     }
 }
+
+/// Wait for a child process to exit, without reaping it.
+///
+/// The fd should be readable after the process exits: a pidfd on Linux, or a
+/// kqueue with `EVFILT_PROC` and `NOTE_EXIT` registered on macOS and BSD.
+///
+/// ## Platform specific
+///
+/// * io-uring: `IORING_OP_POLL_ADD`.
+/// * polling: it waits for the fd to be readable.
+pub struct WaitProcess {
+    pub(crate) fd: RawFd,
+}
+
+impl WaitProcess {
+    /// Create [`WaitProcess`].
+    pub fn new(fd: RawFd) -> Self {
+        Self { fd }
+    }
+}
+
+/// Wait for an fd to be ready for the interest, without performing any IO.
+///
+/// ## Platform specific
+///
+/// * io-uring: `IORING_OP_POLL_ADD`, which is one-shot.
+/// * polling: it waits for the fd with the other operations on it.
+pub struct PollOnce {
+    pub(crate) fd: RawFd,
+    pub(crate) interest: Interest,
+}
+
+impl PollOnce {
+    /// Create [`PollOnce`].
+    pub fn new(fd: RawFd, interest: Interest) -> Self {
+        Self { fd, interest }
+    }
+}
+
+/// Move data between two fds in the kernel with `splice`, where one of them
+/// is a pipe, after the fd of the interest is ready.
+///
+/// It may move fewer bytes than requested, and `0` means the EOF of `fd_in`.
+///
+/// ## Platform specific
+///
+/// * io-uring: it waits for the fd with `IORING_OP_POLL_ADD`, and calls
+///   `splice`. The other fd than the pipe should be nonblocking, otherwise the
+///   thread may be blocked.
+/// * polling: `splice` when the fd is ready.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub struct Splice {
+    pub(crate) fd_in: RawFd,
+    pub(crate) fd_out: RawFd,
+    pub(crate) len: usize,
+    pub(crate) interest: Interest,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl Splice {
+    /// Create [`Splice`], which moves `len` bytes from `fd_in` to `fd_out`.
+    /// It waits for `fd_in` to be readable, or `fd_out` to be writable.
+    pub fn new(fd_in: RawFd, fd_out: RawFd, len: usize, interest: Interest) -> Self {
+        Self {
+            fd_in,
+            fd_out,
+            len,
+            interest,
+        }
+    }
+
+    /// The fd to wait for.
+    pub(crate) fn fd(&self) -> RawFd {
+        match self.interest {
+            Interest::Readable => self.fd_in,
+            Interest::Writable => self.fd_out,
+        }
+    }
+
+    /// Call `splice` once, without waiting for the fds.
+    pub(crate) fn splice(&self) -> io::Result<usize> {
+        let res = unsafe {
+            libc::splice(
+                self.fd_in,
+                std::ptr::null_mut(),
+                self.fd_out,
+                std::ptr::null_mut(),
+                self.len,
+                libc::SPLICE_F_MOVE | libc::SPLICE_F_NONBLOCK,
+            )
+        };
+        if res == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(res as _)
+        }
+    }
+}
+
+impl<B: std::marker::Send + 'static> BlockingBufOp<B> {
+    /// Spawn the function, and return the fd to wait for readable.
+    pub(crate) fn start(&mut self) -> io::Result<RawFd> {
+        let notifier = Arc::new(Notifier::new()?);
+        let fd = notifier.as_raw_fd();
+        self.notifier = Some(notifier.clone());
+        self.spawn(move || {
+            notifier.notify().ok();
+        });
+        Ok(fd)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl crate::op::SendFile {
+    /// Call `sendfile` once, without waiting for the socket.
+    pub(crate) fn send(&self) -> io::Result<usize> {
+        // `off_t` is 32-bit on the 32-bit targets except musl.
+        #[cfg(not(target_env = "musl"))]
+        use libc::{off64_t as off_t, sendfile64 as sendfile};
+        #[cfg(target_env = "musl")]
+        use libc::{off_t, sendfile};
+
+        let mut offset = self.offset as off_t;
+        let res = unsafe { sendfile(self.fd, self.file, &mut offset, self.len) };
+        if res == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(res as _)
+        }
+    }
+}
@@ -3,7 +3,7 @@ use std::{io, pin::Pin, task::Poll};
 use windows_sys::Win32::System::IO::OVERLAPPED;
 
 use crate::{
-    driver::{post_driver_nop, OpCode, RawFd},
+    driver::{post_driver_nop, OpCode, PushEntry, RawFd},
     key::Key,
     task::{op::OpFuture, RUNTIME},
 };
@@ -18,7 +18,10 @@ pub struct Event {
 impl Event {
     /// Create [`Event`].
     pub fn new() -> io::Result<Self> {
-        let user_data = RUNTIME.with(|runtime| runtime.submit_raw(NopPending::new()));
+        let user_data = match RUNTIME.with(|runtime| runtime.submit_raw(NopPending::new())) {
+            PushEntry::Pending(key) => key,
+            PushEntry::Ready(_) => unreachable!("`NopPending` never completes inline"),
+        };
         Ok(Self { user_data })
     }
 
@@ -46,12 +49,10 @@ unsafe impl Send for EventHandle {}
 unsafe impl Sync for EventHandle {}
 
 impl EventHandle {
-    fn new(user_data: &Key<NopPending>) -> Self {
-        let handle = RUNTIME.with(|runtime| runtime.raw_driver());
-        Self {
-            user_data: **user_data,
-            handle,
-        }
+    fn new(key: &Key<NopPending>) -> Self {
+        let (handle, user_data) =
+            RUNTIME.with(|runtime| (runtime.raw_driver(), runtime.user_data(**key)));
+        Self { user_data, handle }
     }
 
     /// Notify the event.
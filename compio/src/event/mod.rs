@@ -1,6 +1,10 @@
 //! Asynchronous events.
 //!
-//! Only for waking up the driver.
+//! [`Event`] is only for waking up the driver. [`OnceEvent`] and
+//! [`SharedOnceEvent`] carry a value set once to many waiters.
+
+mod once;
+pub use once::*;
 
 cfg_if::cfg_if! {
     if #[cfg(target_os = "windows")] {
@@ -0,0 +1,253 @@
+use std::{
+    cell::{OnceCell, RefCell},
+    fmt::Debug,
+    future::Future,
+    io,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex, OnceLock},
+    task::{Context, Poll, Waker},
+};
+
+use slab::Slab;
+
+use super::{Event, EventHandle};
+
+/// A value set once, and observed by any number of tasks of current runtime,
+/// e.g., "the config is loaded", or "the shutdown is initiated with a reason".
+///
+/// The waiters registered before [`OnceEvent::set`] are woken exactly once
+/// when it is set, and the ones after complete at once. It isn't [`Send`]:
+/// use [`SharedOnceEvent`] to signal the tasks of other runtimes.
+///
+/// ```
+/// use std::rc::Rc;
+///
+/// use compio::event::OnceEvent;
+///
+/// compio::task::block_on(async {
+///     let loaded = Rc::new(OnceEvent::<String>::new());
+///     let tasks = (0..4)
+///         .map(|i| {
+///             let loaded = loaded.clone();
+///             compio::task::spawn(async move { loaded.wait().await.len() + i })
+///         })
+///         .collect::<Vec<_>>();
+///     loaded.set("config".to_string()).unwrap();
+///     assert!(loaded.set("other".to_string()).is_err());
+///     for (i, task) in tasks.into_iter().enumerate() {
+///         assert_eq!(task.await, 6 + i);
+///     }
+/// })
+/// ```
+pub struct OnceEvent<T> {
+    value: OnceCell<T>,
+    waiters: RefCell<Slab<Waker>>,
+    // The wakers of the tasks of current runtime.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<T> OnceEvent<T> {
+    /// Create [`OnceEvent`] which is not set.
+    pub fn new() -> Self {
+        Self {
+            value: OnceCell::new(),
+            waiters: RefCell::default(),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Set the value, and wake the waiters. Only the first call succeeds, and
+    /// the value is given back afterwards.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        self.value.set(value)?;
+        // Taken before waking, in case a waiter is dropped by it.
+        let waiters = std::mem::take(&mut *self.waiters.borrow_mut());
+        for (_, waker) in waiters {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// The value, if it is set.
+    pub fn get(&self) -> Option<&T> {
+        self.value.get()
+    }
+
+    /// Wait until the value is set. It is cancel safe.
+    pub fn wait(&self) -> WaitOnce<'_, T> {
+        WaitOnce {
+            event: self,
+            key: None,
+        }
+    }
+}
+
+impl<T> Default for OnceEvent<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Debug> Debug for OnceEvent<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OnceEvent")
+            .field("value", &self.value.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The future returned by [`OnceEvent::wait`].
+#[must_use = "futures do nothing unless polled"]
+pub struct WaitOnce<'a, T> {
+    event: &'a OnceEvent<T>,
+    // The key in the waiters, once registered.
+    key: Option<usize>,
+}
+
+impl<'a, T> Future for WaitOnce<'a, T> {
+    type Output = &'a T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if let Some(value) = this.event.value.get() {
+            // The waiters are removed when it is set.
+            this.key = None;
+            return Poll::Ready(value);
+        }
+        let mut waiters = this.event.waiters.borrow_mut();
+        match this.key {
+            Some(key) => waiters[key] = cx.waker().clone(),
+            None => this.key = Some(waiters.insert(cx.waker().clone())),
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for WaitOnce<'_, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            if self.event.value.get().is_none() {
+                self.event.waiters.borrow_mut().remove(key);
+            }
+        }
+    }
+}
+
+/// A value set once, and observed by any number of tasks, which could be
+/// shared between the runtimes of different threads. The clones share the
+/// same value.
+///
+/// Each waiter waits on an [`Event`], which is notified when the value is
+/// set. The waiters registered before [`SharedOnceEvent::set`] are woken
+/// exactly once, and the ones after complete at once.
+///
+/// ```
+/// use compio::event::SharedOnceEvent;
+///
+/// let handshake = SharedOnceEvent::new();
+/// let thread = std::thread::spawn({
+///     let handshake = handshake.clone();
+///     move || compio::task::block_on(async { *handshake.wait().await.unwrap() })
+/// });
+/// handshake.set(42).unwrap();
+/// assert_eq!(thread.join().unwrap(), 42);
+/// ```
+pub struct SharedOnceEvent<T> {
+    inner: Arc<SharedInner<T>>,
+}
+
+struct SharedInner<T> {
+    value: OnceLock<T>,
+    // Locked to set the value, so that a waiter is either registered before
+    // it is set, or sees it.
+    waiters: Mutex<Slab<EventHandle>>,
+}
+
+impl<T> SharedOnceEvent<T> {
+    /// Create [`SharedOnceEvent`] which is not set.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(SharedInner {
+                value: OnceLock::new(),
+                waiters: Mutex::default(),
+            }),
+        }
+    }
+
+    /// Set the value, and notify the waiters. Only the first call succeeds,
+    /// and the value is given back afterwards.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let mut waiters = self.inner.waiters.lock().unwrap();
+        self.inner.value.set(value)?;
+        for handle in waiters.drain() {
+            handle.notify().ok();
+        }
+        Ok(())
+    }
+
+    /// The value, if it is set.
+    pub fn get(&self) -> Option<&T> {
+        self.inner.value.get()
+    }
+
+    /// Wait until the value is set. It is cancel safe.
+    ///
+    /// An error is returned only if the task fails to wait, e.g., the
+    /// [`Event`] cannot be created.
+    pub async fn wait(&self) -> io::Result<&T> {
+        let (event, key) = {
+            let mut waiters = self.inner.waiters.lock().unwrap();
+            if let Some(value) = self.inner.value.get() {
+                return Ok(value);
+            }
+            let event = Event::new()?;
+            let key = waiters.insert(event.handle()?);
+            (event, key)
+        };
+        let _waiting = SharedWaiting {
+            inner: &self.inner,
+            key,
+        };
+        event.wait().await?;
+        Ok(self.inner.value.get().expect("notified before set"))
+    }
+}
+
+impl<T> Clone for SharedOnceEvent<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Default for SharedOnceEvent<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Debug> Debug for SharedOnceEvent<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedOnceEvent")
+            .field("value", &self.inner.value.get())
+            .finish_non_exhaustive()
+    }
+}
+
+// Remove the waiter if the future is dropped before notified.
+struct SharedWaiting<'a, T> {
+    inner: &'a SharedInner<T>,
+    key: usize,
+}
+
+impl<T> Drop for SharedWaiting<'_, T> {
+    fn drop(&mut self) {
+        let mut waiters = self.inner.waiters.lock().unwrap();
+        // All the waiters are removed when it is set, and no more are added.
+        if self.inner.value.get().is_none() {
+            waiters.remove(self.key);
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use std::{io, path::Path};
+
+use crate::fs::File;
+
+// The version of the format of `ChangeToken::to_bytes`.
+const FORMAT_VERSION: u8 = 1;
+
+// The fields present in a token, in the order they are encoded.
+const HAS_LEN: u8 = 1 << 0;
+const HAS_MODIFIED: u8 = 1 << 1;
+const HAS_CHANGED: u8 = 1 << 2;
+const HAS_ID: u8 = 1 << 3;
+const ALL_FIELDS: u8 = HAS_LEN | HAS_MODIFIED | HAS_CHANGED | HAS_ID;
+
+/// An opaque token of the version of a file, to tell cheaply whether it has
+/// changed since, e.g., to invalidate a cache of its parsed contents. See
+/// [`File::change_token`] and [`changed_since`].
+///
+/// It consists of the size and the modification time, and where supported,
+/// the status change time and the identity of the file, i.e., the device and
+/// the inode, so that a file replaced by a rename is detected even with the
+/// same size and modification time. Two tokens are compared on the fields
+/// present in both, so a token taken where a field isn't available doesn't
+/// look changed for that field alone.
+///
+/// It could be persisted with [`to_bytes`] and restored with [`from_bytes`],
+/// e.g., with a cache on the disk.
+///
+/// ## Platform specific
+/// * Linux: the status is taken with `statx`. The kernel doesn't expose the
+///   change cookie of the filesystems to the userspace, so the status change
+///   time stands in for it: it is updated on every change of the data or the
+///   metadata, and can't be set back by `utimensat`.
+/// * Other Unix: the status is taken with `fstat` or `stat`, with the same
+///   fields.
+/// * Windows: only the size and the last write time are available.
+///
+/// [`to_bytes`]: ChangeToken::to_bytes
+/// [`from_bytes`]: ChangeToken::from_bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeToken {
+    len: Option<u64>,
+    // The seconds and the nanoseconds since the Unix epoch.
+    modified: Option<(i64, u32)>,
+    changed: Option<(i64, u32)>,
+    // The device and the inode.
+    id: Option<(u64, u64)>,
+}
+
+impl ChangeToken {
+    /// Whether the file has changed from the `earlier` token, i.e., any field
+    /// present in both tokens differs.
+    pub fn changed_from(&self, earlier: &ChangeToken) -> bool {
+        fn differ<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+            matches!((a, b), (Some(a), Some(b)) if a != b)
+        }
+
+        differ(&self.len, &earlier.len)
+            || differ(&self.modified, &earlier.modified)
+            || differ(&self.changed, &earlier.changed)
+            || differ(&self.id, &earlier.id)
+    }
+
+    /// Encode the token into bytes, which are decoded by
+    /// [`from_bytes`](ChangeToken::from_bytes).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut fields = 0;
+        let mut bytes = vec![FORMAT_VERSION, 0];
+        if let Some(len) = self.len {
+            fields |= HAS_LEN;
+            bytes.extend_from_slice(&len.to_le_bytes());
+        }
+        for (time, field) in [(self.modified, HAS_MODIFIED), (self.changed, HAS_CHANGED)] {
+            if let Some((secs, nanos)) = time {
+                fields |= field;
+                bytes.extend_from_slice(&secs.to_le_bytes());
+                bytes.extend_from_slice(&nanos.to_le_bytes());
+            }
+        }
+        if let Some((dev, ino)) = self.id {
+            fields |= HAS_ID;
+            bytes.extend_from_slice(&dev.to_le_bytes());
+            bytes.extend_from_slice(&ino.to_le_bytes());
+        }
+        bytes[1] = fields;
+        bytes
+    }
+
+    /// Decode the token from the bytes of
+    /// [`to_bytes`](ChangeToken::to_bytes).
+    ///
+    /// It fails with [`io::ErrorKind::InvalidData`] if the bytes are
+    /// malformed, or encoded by an unknown version.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut rest = bytes;
+        let [version, fields] = take(&mut rest)?;
+        if version != FORMAT_VERSION || fields & !ALL_FIELDS != 0 {
+            return Err(invalid_token());
+        }
+        let has = |field: u8| fields & field != 0;
+        let len = if has(HAS_LEN) {
+            Some(u64::from_le_bytes(take(&mut rest)?))
+        } else {
+            None
+        };
+        let mut time = |field: u8| -> io::Result<Option<(i64, u32)>> {
+            if !has(field) {
+                return Ok(None);
+            }
+            let secs = i64::from_le_bytes(take(&mut rest)?);
+            let nanos = u32::from_le_bytes(take(&mut rest)?);
+            Ok(Some((secs, nanos)))
+        };
+        let modified = time(HAS_MODIFIED)?;
+        let changed = time(HAS_CHANGED)?;
+        let id = if has(HAS_ID) {
+            let dev = u64::from_le_bytes(take(&mut rest)?);
+            let ino = u64::from_le_bytes(take(&mut rest)?);
+            Some((dev, ino))
+        } else {
+            None
+        };
+        if !rest.is_empty() {
+            return Err(invalid_token());
+        }
+        Ok(Self {
+            len,
+            modified,
+            changed,
+            id,
+        })
+    }
+}
+
+fn invalid_token() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed change token")
+}
+
+// Take the next `N` bytes of an encoded token.
+fn take<const N: usize>(rest: &mut &[u8]) -> io::Result<[u8; N]> {
+    let (chunk, tail) = rest.split_first_chunk::<N>().ok_or_else(invalid_token)?;
+    *rest = tail;
+    Ok(*chunk)
+}
+
+impl File {
+    /// Take a [`ChangeToken`] of the file, to check whether it has changed
+    /// later with [`changed_since`].
+    ///
+    /// ```
+    /// use compio::fs::File;
+    ///
+    /// compio::task::block_on(async {
+    ///     let file = File::open("Cargo.toml").unwrap();
+    ///     let token = file.change_token().await.unwrap();
+    ///     assert!(!compio::fs::changed_since("Cargo.toml", &token).await.unwrap());
+    /// })
+    /// ```
+    pub async fn change_token(&self) -> io::Result<ChangeToken> {
+        self.attach()?;
+        sys::file_token(self).await
+    }
+}
+
+/// Whether the file at `path` has changed since `token` was taken. A removed
+/// file is changed.
+///
+/// It follows the symlinks, so a token of the target is compared.
+///
+/// ## Platform specific
+/// * Linux: the path is stat-ed with `statx` without opening the file.
+/// * Others: the file is opened to be stat-ed on the thread pool.
+pub async fn changed_since(path: impl AsRef<Path>, token: &ChangeToken) -> io::Result<bool> {
+    match sys::path_token(path.as_ref()).await {
+        Ok(now) => Ok(now.changed_from(token)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(true),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod sys {
+    use std::{ffi::CString, io, os::unix::ffi::OsStrExt, path::Path};
+
+    use super::ChangeToken;
+    use crate::{driver::AsRawFd, fs::File, op::Statx, task::submit};
+
+    const MASK: u32 = libc::STATX_SIZE | libc::STATX_MTIME | libc::STATX_CTIME | libc::STATX_INO;
+
+    fn token(statx: &libc::statx) -> ChangeToken {
+        let has = |field: u32| statx.stx_mask & field != 0;
+        let time = |t: &libc::statx_timestamp| (t.tv_sec, t.tv_nsec);
+        let dev = libc::makedev(statx.stx_dev_major, statx.stx_dev_minor);
+        ChangeToken {
+            len: has(libc::STATX_SIZE).then_some(statx.stx_size),
+            modified: has(libc::STATX_MTIME).then(|| time(&statx.stx_mtime)),
+            changed: has(libc::STATX_CTIME).then(|| time(&statx.stx_ctime)),
+            id: has(libc::STATX_INO).then_some((dev, statx.stx_ino)),
+        }
+    }
+
+    async fn statx(dirfd: libc::c_int, path: CString, flags: i32) -> io::Result<ChangeToken> {
+        let op = Statx::new(dirfd, path, flags | libc::AT_STATX_SYNC_AS_STAT, MASK);
+        let (res, op) = submit(op).await;
+        res?;
+        Ok(token(op.statx()))
+    }
+
+    pub async fn file_token(file: &File) -> io::Result<ChangeToken> {
+        statx(file.as_raw_fd(), CString::default(), libc::AT_EMPTY_PATH).await
+    }
+
+    pub async fn path_token(path: &Path) -> io::Result<ChangeToken> {
+        let path = CString::new(path.as_os_str().as_bytes()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the path should not contain a nul byte",
+            )
+        })?;
+        statx(libc::AT_FDCWD, path, 0).await
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+mod sys {
+    use std::{fs::Metadata, io, mem::ManuallyDrop, path::Path};
+
+    use super::ChangeToken;
+    use crate::{
+        driver::{AsRawFd, FromRawFd},
+        fs::{dir::blocking, File},
+    };
+
+    #[cfg(unix)]
+    fn token(metadata: &Metadata) -> ChangeToken {
+        use std::os::unix::fs::MetadataExt;
+
+        ChangeToken {
+            len: Some(metadata.size()),
+            modified: Some((metadata.mtime(), metadata.mtime_nsec() as u32)),
+            changed: Some((metadata.ctime(), metadata.ctime_nsec() as u32)),
+            id: Some((metadata.dev(), metadata.ino())),
+        }
+    }
+
+    #[cfg(windows)]
+    fn token(metadata: &Metadata) -> ChangeToken {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let timestamp = |time: SystemTime| match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+            Err(e) => {
+                let d = e.duration();
+                match d.subsec_nanos() {
+                    0 => (-(d.as_secs() as i64), 0),
+                    nanos => (-(d.as_secs() as i64) - 1, 1_000_000_000 - nanos),
+                }
+            }
+        };
+        ChangeToken {
+            len: Some(metadata.len()),
+            modified: metadata.modified().ok().map(timestamp),
+            changed: None,
+            id: None,
+        }
+    }
+
+    pub async fn file_token(file: &File) -> io::Result<ChangeToken> {
+        blocking(file.as_raw_fd(), |fd| {
+            // The file is owned by the caller.
+            let file = ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) });
+            Ok(token(&file.metadata()?))
+        })
+        .await
+    }
+
+    pub async fn path_token(path: &Path) -> io::Result<ChangeToken> {
+        // The status of a path is taken without a fd, so the file is opened to
+        // run on the thread pool.
+        file_token(&File::open(path)?).await
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    io,
+    ops::{Deref, DerefMut},
+    rc::{Rc, Weak},
+};
+
+use futures_util::{Stream, StreamExt};
+
+use crate::{
+    buf::{IntoInner, IoBuf},
+    fs::File,
+};
+
+type Slot = RefCell<Option<Vec<u8>>>;
+
+/// A chunk yielded by [`File::read_chunks`].
+///
+/// The buffer is given back to the stream when the chunk is dropped, and
+/// reused for the next read. Call [`Chunk::into_vec`] to keep the buffer,
+/// and the stream allocates a new one.
+pub struct Chunk {
+    buffer: Vec<u8>,
+    slot: Weak<Slot>,
+}
+
+impl Chunk {
+    /// Take the buffer out of the chunk.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.slot = Weak::new();
+        std::mem::take(&mut self.buffer)
+    }
+}
+
+impl Deref for Chunk {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+impl DerefMut for Chunk {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buffer
+    }
+}
+
+impl AsRef<[u8]> for Chunk {
+    fn as_ref(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+impl Debug for Chunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Chunk").field(&self.buffer).finish()
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot.upgrade() {
+            slot.borrow_mut()
+                .get_or_insert_with(|| std::mem::take(&mut self.buffer));
+        }
+    }
+}
+
+struct ReadChunks<'a> {
+    file: &'a File,
+    offset: u64,
+    chunk_size: usize,
+    slot: Rc<Slot>,
+    done: bool,
+}
+
+impl File {
+    /// Read the file from `offset` until EOF, in chunks of at most
+    /// `chunk_size` bytes, with one read in flight.
+    ///
+    /// Unlike [`File::read_to_end_at`], the memory is bounded if the chunks
+    /// are dropped before the next one is polled, because the buffer is reused.
+    /// The stream ends after a zero-length read, or after yielding the first
+    /// error.
+    ///
+    /// ```
+    /// use compio::fs::File;
+    /// use futures_util::StreamExt;
+    ///
+    /// compio::task::block_on(async {
+    ///     let file = File::open("Cargo.toml").unwrap();
+    ///     let mut chunks = std::pin::pin!(file.read_chunks(0, 64));
+    ///     let mut len = 0;
+    ///     while let Some(chunk) = chunks.next().await {
+    ///         len += chunk.unwrap().len();
+    ///     }
+    ///     assert_eq!(len as u64, file.metadata().unwrap().len());
+    /// })
+    /// ```
+    pub fn read_chunks(
+        &self,
+        offset: u64,
+        chunk_size: usize,
+    ) -> impl Stream<Item = io::Result<Chunk>> + '_ {
+        let state = ReadChunks {
+            file: self,
+            offset,
+            chunk_size: chunk_size.max(1),
+            slot: Rc::default(),
+            done: false,
+        };
+        let stream = futures_util::stream::unfold(state, |mut state| async move {
+            if state.done {
+                return None;
+            }
+            let buffer = state.slot.borrow_mut().take();
+            let mut buffer = buffer.unwrap_or_else(|| Vec::with_capacity(state.chunk_size));
+            buffer.clear();
+            buffer.reserve_exact(state.chunk_size);
+            let (res, buffer) = state
+                .file
+                .read_at(buffer.slice(..state.chunk_size), state.offset)
+                .await
+                .into_inner();
+            match res {
+                Ok(0) => None,
+                Ok(n) => {
+                    state.offset += n as u64;
+                    let chunk = Chunk {
+                        buffer,
+                        slot: Rc::downgrade(&state.slot),
+                    };
+                    Some((Ok(chunk), state))
+                }
+                Err(e) => {
+                    state.done = true;
+                    Some((Err(e), state))
+                }
+            }
+        });
+        stream.fuse()
+    }
+}
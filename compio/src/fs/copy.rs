@@ -0,0 +1,310 @@
+use std::{io, path::Path};
+
+use crate::{
+    buf::{IntoInner, IoBuf},
+    driver::AsRawFd,
+    fs::File,
+    op::BlockingBufOp,
+    task::submit,
+};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Copies the contents of one file to another, and returns the number of
+/// bytes copied. The destination is created if it doesn't exist, and
+/// truncated if it does. The permission bits are copied, too.
+///
+/// The data is copied in the kernel if possible, and the filesystems
+/// supporting reflinks may share the extents instead of copying them:
+///
+/// * Linux: `copy_file_range`.
+/// * macOS: `fclonefileat` and `fcopyfile`, through [`std::fs::copy`].
+/// * Windows: `FSCTL_DUPLICATE_EXTENTS_TO_FILE`, then `CopyFileExW`.
+///
+/// If the fast path is not supported, e.g., for the copies across
+/// filesystems, the data is copied through userspace buffers.
+///
+/// ```
+/// use compio::fs::copy;
+///
+/// compio::task::block_on(async {
+///     let dir = tempfile::tempdir().unwrap();
+///     let to = dir.path().join("Cargo.toml");
+///     let copied = copy("Cargo.toml", &to).await.unwrap();
+///     assert_eq!(copied, std::fs::metadata(&to).unwrap().len());
+/// })
+/// ```
+pub async fn copy(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<u64> {
+    let src = File::open(&from)?;
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    {
+        copy_std(&src, from.as_ref(), to.as_ref()).await
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let metadata = src.metadata()?;
+        let dst = File::create(&to)?;
+        let (copied, fallback) = copy_range_fast(&src, &dst, 0, 0, metadata.len()).await?;
+        if !fallback {
+            dst.set_permissions(metadata.permissions())?;
+            return Ok(copied);
+        }
+        drop(dst);
+        copy_std(&src, from.as_ref(), to.as_ref()).await
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "ios", target_os = "windows")))]
+    {
+        let metadata = src.metadata()?;
+        let dst = File::create(to)?;
+        let copied = copy_range(&src, &dst, 0, 0, metadata.len()).await?;
+        dst.set_permissions(metadata.permissions())?;
+        Ok(copied)
+    }
+}
+
+/// Run [`std::fs::copy`] on the blocking thread pool.
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "windows"))]
+async fn copy_std(src: &File, from: &Path, to: &Path) -> io::Result<u64> {
+    // The count is returned through the buffer, as it may not fit in `usize`.
+    let paths = (from.to_path_buf(), to.to_path_buf(), 0);
+    let op = BlockingBufOp::new(src.as_raw_fd(), paths, |_, (from, to, copied)| {
+        *copied = std::fs::copy(from, to)?;
+        Ok(0)
+    });
+    let (res, op) = submit(op).await;
+    res?;
+    let (_, _, copied) = op.into_inner();
+    Ok(copied)
+}
+
+/// Copy a range of `src` to `dst`, in the kernel if possible, and fall back to
+/// the userspace buffers.
+pub(crate) async fn copy_range(
+    src: &File,
+    dst: &File,
+    src_off: u64,
+    dst_off: u64,
+    len: u64,
+) -> io::Result<u64> {
+    let (copied, fallback) = copy_range_fast(src, dst, src_off, dst_off, len).await?;
+    if !fallback {
+        return Ok(copied);
+    }
+    let rest =
+        copy_range_buffered(src, dst, src_off + copied, dst_off + copied, len - copied).await?;
+    Ok(copied + rest)
+}
+
+// The files are cloned into the ops, so that they live until the functions
+// return, even if the futures are cancelled.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "windows"))]
+fn clone_pair(src: &File, dst: &File) -> io::Result<(std::fs::File, std::fs::File)> {
+    Ok((src.try_clone_std()?, dst.try_clone_std()?))
+}
+
+/// Returns the bytes copied, and whether the rest should be copied by the
+/// userspace buffers.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+async fn copy_range_fast(
+    src: &File,
+    dst: &File,
+    src_off: u64,
+    dst_off: u64,
+    len: u64,
+) -> io::Result<(u64, bool)> {
+    // Like `copy_std`, the count is returned in the buffer.
+    let files = (clone_pair(src, dst)?, false, 0);
+    let op = BlockingBufOp::new(
+        src.as_raw_fd(),
+        files,
+        move |_, ((src, dst), fallback, copied)| {
+            *copied = 0;
+            while *copied < len {
+                let mut src_off = (src_off + *copied) as libc::loff_t;
+                let mut dst_off = (dst_off + *copied) as libc::loff_t;
+                let res = unsafe {
+                    libc::copy_file_range(
+                        src.as_raw_fd(),
+                        &mut src_off,
+                        dst.as_raw_fd(),
+                        &mut dst_off,
+                        (len - *copied).min(1 << 30) as usize,
+                        0,
+                    )
+                };
+                match res {
+                    -1 => {
+                        let e = io::Error::last_os_error();
+                        match e.raw_os_error() {
+                            Some(
+                                libc::EXDEV
+                                | libc::ENOSYS
+                                | libc::EOPNOTSUPP
+                                | libc::EINVAL
+                                | libc::EPERM,
+                            ) => {
+                                *fallback = true;
+                                break;
+                            }
+                            Some(libc::EINTR) => {}
+                            _ => return Err(e),
+                        }
+                    }
+                    // EOF of `src`.
+                    0 => break,
+                    n => *copied += n as u64,
+                }
+            }
+            Ok(0)
+        },
+    );
+    let (res, op) = submit(op).await;
+    res?;
+    let (_, fallback, copied) = op.into_inner();
+    Ok((copied, fallback))
+}
+
+/// Returns the bytes copied, and whether the rest should be copied by the
+/// userspace buffers.
+#[cfg(target_os = "windows")]
+async fn copy_range_fast(
+    src: &File,
+    dst: &File,
+    src_off: u64,
+    dst_off: u64,
+    len: u64,
+) -> io::Result<(u64, bool)> {
+    // Don't clone beyond the EOF of `src`.
+    let src_len = src.metadata()?.len();
+    let len = len.min(src_len.saturating_sub(src_off));
+    if len == 0 {
+        return Ok((0, false));
+    }
+    let files = clone_pair(src, dst)?;
+    let op = BlockingBufOp::new(src.as_raw_fd(), files, move |_, (src, dst)| {
+        duplicate_extents(src, dst, src_off, dst_off, len).map(|_| 0)
+    });
+    match submit(op).await.0 {
+        Ok(_) => Ok((len, false)),
+        // The filesystem doesn't support block cloning, or the range is not
+        // aligned to the clusters.
+        Err(_) => Ok((0, true)),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn duplicate_extents(
+    src: &std::fs::File,
+    dst: &std::fs::File,
+    src_off: u64,
+    dst_off: u64,
+    len: u64,
+) -> io::Result<()> {
+    use std::{
+        os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle},
+        ptr::{null, null_mut},
+    };
+
+    use windows_sys::Win32::{
+        Foundation::{GetLastError, ERROR_IO_PENDING},
+        System::{
+            Ioctl::{DUPLICATE_EXTENTS_DATA, FSCTL_DUPLICATE_EXTENTS_TO_FILE},
+            Threading::CreateEventW,
+            IO::{DeviceIoControl, GetOverlappedResult, OVERLAPPED},
+        },
+    };
+
+    use crate::syscall;
+
+    // The target range should be allocated.
+    if dst.metadata()?.len() < dst_off + len {
+        dst.set_len(dst_off + len)?;
+    }
+    let event = syscall!(CreateEventW(null(), 1, 0, null()), == 0)?;
+    let event = unsafe { OwnedHandle::from_raw_handle(event as _) };
+    let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+    // The low bit prevents posting the completion to the attached port.
+    overlapped.hEvent = event.as_raw_handle() as isize | 1;
+    let data = DUPLICATE_EXTENTS_DATA {
+        FileHandle: src.as_raw_handle() as _,
+        SourceFileOffset: src_off as _,
+        TargetFileOffset: dst_off as _,
+        ByteCount: len as _,
+    };
+    let res = unsafe {
+        DeviceIoControl(
+            dst.as_raw_handle() as _,
+            FSCTL_DUPLICATE_EXTENTS_TO_FILE,
+            &data as *const _ as _,
+            std::mem::size_of::<DUPLICATE_EXTENTS_DATA>() as _,
+            null_mut(),
+            0,
+            null_mut(),
+            &mut overlapped,
+        )
+    };
+    if res == 0 {
+        let error = unsafe { GetLastError() };
+        if error != ERROR_IO_PENDING {
+            return Err(io::Error::from_raw_os_error(error as _));
+        }
+    }
+    let mut transferred = 0;
+    syscall!(
+        BOOL,
+        GetOverlappedResult(dst.as_raw_handle() as _, &overlapped, &mut transferred, 1)
+    )?;
+    Ok(())
+}
+
+/// There is no fast path for the ranges.
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "windows")))]
+async fn copy_range_fast(
+    _src: &File,
+    _dst: &File,
+    _src_off: u64,
+    _dst_off: u64,
+    _len: u64,
+) -> io::Result<(u64, bool)> {
+    Ok((0, true))
+}
+
+/// Copy through two buffers: one is written while the other is being filled.
+async fn copy_range_buffered(
+    src: &File,
+    dst: &File,
+    src_off: u64,
+    dst_off: u64,
+    len: u64,
+) -> io::Result<u64> {
+    let chunk = len.min(CHUNK_SIZE as u64) as usize;
+    let mut copied = 0;
+    let (read, filled) = src
+        .read_at(Vec::with_capacity(chunk).slice(..chunk), src_off)
+        .await;
+    let mut read = read?;
+    let mut filled = filled.into_inner();
+    let mut spare = Vec::with_capacity(chunk);
+    while read > 0 {
+        let next = copied + read as u64;
+        let read_next = async {
+            if next < len {
+                spare.clear();
+                let spare = std::mem::take(&mut spare);
+                let buffer = spare.slice(..(len - next).min(chunk as u64) as usize);
+                let (res, buffer) = src.read_at(buffer, src_off + next).await;
+                (res, buffer.into_inner())
+            } else {
+                (Ok(0), std::mem::take(&mut spare))
+            }
+        };
+        let ((written, buffer), (res, next_buffer)) =
+            futures_util::join!(dst.write_all_at(filled, dst_off + copied), read_next);
+        written?;
+        copied = next;
+        read = res?;
+        spare = buffer;
+        filled = next_buffer;
+    }
+    Ok(copied)
+}
@@ -0,0 +1,181 @@
+use std::{
+    ffi::OsString,
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    driver::RawFd,
+    fs::{File, OpenOptions},
+    impl_raw_fd,
+    op::BlockingBufOp,
+    task::submit,
+};
+
+cfg_if::cfg_if! {
+    if #[cfg(target_os = "windows")] {
+        mod windows;
+        use windows as sys;
+    } else if #[cfg(unix)] {
+        mod unix;
+        use unix as sys;
+    }
+}
+
+mod walk;
+pub use walk::*;
+
+/// A handle to an open directory. The files and directories are opened,
+/// created, removed and renamed relative to it, so that the path of the
+/// directory is not resolved again, and a tree could be walked without being
+/// redirected by the concurrent renames and symlinks above it.
+///
+/// The names could be relative paths of several components. They are
+/// resolved relative to the directory like `openat(2)`, and an absolute name
+/// ignores the directory.
+///
+/// ## Platform specific
+/// * io-uring: the operations are submitted as `openat`, `mkdirat`,
+///   `unlinkat`, `renameat` and `symlinkat`, which require Linux 5.15 or
+///   later. The others are run on the blocking thread pool.
+/// * Other Unix: the `*at` syscalls are run on the blocking thread pool.
+/// * Windows: the relative opens are not feasible with the Win32 API, so the
+///   operations are run on the blocking thread pool, with the full path
+///   reconstructed from the handle by `GetFinalPathNameByHandleW`. The
+///   directory couldn't be removed or renamed while it is open, but the
+///   directories above it could be replaced.
+///
+/// ```
+/// use compio::fs::{Dir, OpenOptions};
+///
+/// compio::task::block_on(async {
+///     let root = std::env::temp_dir().join("compio-dir-doc");
+///     std::fs::create_dir_all(&root).unwrap();
+///
+///     let dir = Dir::open(&root).unwrap();
+///     dir.create_dir_at("logs").await.unwrap();
+///     let logs = dir.open_dir_at("logs").await.unwrap();
+///     let opts = OpenOptions::new().write(true).create(true);
+///     let file = logs.open_file_at("1.log", opts).await.unwrap();
+///     file.write_all_at("hello", 0).await.0.unwrap();
+///     assert_eq!(logs.read_dir().await.unwrap(), ["1.log"]);
+///
+///     logs.remove_file_at("1.log").await.unwrap();
+///     dir.remove_dir_at("logs").await.unwrap();
+///     std::fs::remove_dir(&root).unwrap();
+/// })
+/// ```
+#[derive(Debug)]
+pub struct Dir {
+    inner: std::fs::File,
+}
+
+impl Dir {
+    /// Open the directory at `path`.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            inner: sys::open(path.as_ref())?,
+        })
+    }
+
+    /// Open a subdirectory.
+    pub async fn open_dir_at(&self, name: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            inner: sys::open_dir_at(&self.inner, name.as_ref()).await?,
+        })
+    }
+
+    /// Open a file with the options, like [`OpenOptions::open`].
+    pub async fn open_file_at(
+        &self,
+        name: impl AsRef<Path>,
+        opts: OpenOptions,
+    ) -> io::Result<File> {
+        sys::open_file_at(&self.inner, name.as_ref(), opts).await
+    }
+
+    /// Create a subdirectory, like [`std::fs::create_dir`].
+    pub async fn create_dir_at(&self, name: impl AsRef<Path>) -> io::Result<()> {
+        sys::create_dir_at(&self.inner, name.as_ref()).await
+    }
+
+    /// Remove a file, like [`std::fs::remove_file`].
+    pub async fn remove_file_at(&self, name: impl AsRef<Path>) -> io::Result<()> {
+        sys::remove_at(&self.inner, name.as_ref(), false).await
+    }
+
+    /// Remove an empty subdirectory, like [`std::fs::remove_dir`].
+    pub async fn remove_dir_at(&self, name: impl AsRef<Path>) -> io::Result<()> {
+        sys::remove_at(&self.inner, name.as_ref(), true).await
+    }
+
+    /// Rename `from` in this directory to `to` in `to_dir`, which could be
+    /// this directory, like [`std::fs::rename`]. Both directories should be on
+    /// the same filesystem.
+    pub async fn rename_at(
+        &self,
+        from: impl AsRef<Path>,
+        to_dir: &Dir,
+        to: impl AsRef<Path>,
+    ) -> io::Result<()> {
+        sys::rename_at(&self.inner, from.as_ref(), &to_dir.inner, to.as_ref()).await
+    }
+
+    /// Create a symbolic link `name` pointing to `target`. A relative target
+    /// is resolved relative to the directory of the link when it is
+    /// followed.
+    ///
+    /// ## Platform specific
+    /// * Windows: a directory symlink is created if the target is a directory
+    ///   now, otherwise a file symlink. It requires the privilege or the
+    ///   developer mode.
+    pub async fn symlink_at(
+        &self,
+        target: impl AsRef<Path>,
+        name: impl AsRef<Path>,
+    ) -> io::Result<()> {
+        sys::symlink_at(target.as_ref(), &self.inner, name.as_ref()).await
+    }
+
+    /// Read the target of a symbolic link, like [`std::fs::read_link`].
+    pub async fn read_link_at(&self, name: impl AsRef<Path>) -> io::Result<PathBuf> {
+        sys::read_link_at(&self.inner, name.as_ref()).await
+    }
+
+    /// Query the metadata of an entry. A symbolic link is followed if
+    /// `follow` is true, like [`std::fs::metadata`], otherwise the metadata
+    /// of the link itself is returned, like [`std::fs::symlink_metadata`].
+    ///
+    /// ## Platform specific
+    /// * Linux and Android: the entry is opened with `O_PATH`, and queried
+    ///   with `fstat`.
+    /// * Other Unix: the entry is opened for reading, so it should be
+    ///   readable. The link itself couldn't be opened except on macOS and
+    ///   iOS, and it fails with `ELOOP` if `follow` is false.
+    pub async fn metadata_at(&self, name: impl AsRef<Path>, follow: bool) -> io::Result<Metadata> {
+        sys::metadata_at(&self.inner, name.as_ref(), follow).await
+    }
+
+    /// The names of the entries in the directory, without `.` and `..`, in no
+    /// particular order.
+    pub async fn read_dir(&self) -> io::Result<Vec<OsString>> {
+        sys::read_dir(&self.inner).await
+    }
+}
+
+impl_raw_fd!(Dir, inner);
+
+// Run `f` with the fd on the blocking thread pool.
+pub(crate) async fn blocking<T: Send + 'static>(
+    fd: RawFd,
+    f: impl FnOnce(RawFd) -> io::Result<T> + Send + 'static,
+) -> io::Result<T> {
+    let op = BlockingBufOp::new(fd, None, move |fd, out| {
+        *out = Some(f(fd)?);
+        Ok(0)
+    });
+    let (res, op) = submit(op).await;
+    res?;
+    Ok(crate::buf::IntoInner::into_inner(op).expect("the output should be set on success"))
+}
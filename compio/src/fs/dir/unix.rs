@@ -0,0 +1,238 @@
+use std::{
+    ffi::{CStr, CString, OsString},
+    fs::Metadata,
+    io,
+    os::{
+        fd::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd},
+        unix::{
+            ffi::{OsStrExt, OsStringExt},
+            fs::OpenOptionsExt,
+        },
+    },
+    path::{Path, PathBuf},
+};
+
+use super::blocking;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+use crate::{
+    op::{MkdirAt, OpenAt, RenameAt, SymlinkAt, UnlinkAt},
+    task::submit,
+};
+use crate::{
+    fs::{File, OpenOptions},
+    syscall,
+};
+
+// Like `std`, the created files and directories are masked by `umask`.
+const FILE_MODE: libc::mode_t = 0o666;
+const DIR_MODE: libc::mode_t = 0o777;
+
+pub fn open(path: &Path) -> io::Result<std::fs::File> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECTORY)
+        .open(path)
+}
+
+fn cstr(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the path should not contain a nul byte",
+        )
+    })
+}
+
+async fn open_at(
+    dir: &std::fs::File,
+    name: &Path,
+    flags: libc::c_int,
+    mode: libc::mode_t,
+) -> io::Result<OwnedFd> {
+    let name = cstr(name)?;
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    let fd = submit(OpenAt::new(dir.as_raw_fd(), name, flags, mode))
+        .await
+        .0?;
+    #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+    let fd = blocking(dir.as_raw_fd(), move |fd| {
+        syscall!(openat(fd, name.as_ptr(), flags, mode as libc::c_uint))
+    })
+    .await?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as _) })
+}
+
+pub async fn open_dir_at(dir: &std::fs::File, name: &Path) -> io::Result<std::fs::File> {
+    let flags = libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC;
+    Ok(open_at(dir, name, flags, 0).await?.into())
+}
+
+pub async fn open_file_at(
+    dir: &std::fs::File,
+    name: &Path,
+    opts: OpenOptions,
+) -> io::Result<File> {
+    let fd = open_at(dir, name, opts.flags.to_unix()?, FILE_MODE).await?;
+    Ok(unsafe { File::from_raw_fd(fd.into_raw_fd()) })
+}
+
+pub async fn create_dir_at(dir: &std::fs::File, name: &Path) -> io::Result<()> {
+    let name = cstr(name)?;
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    submit(MkdirAt::new(dir.as_raw_fd(), name, DIR_MODE))
+        .await
+        .0?;
+    #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+    blocking(dir.as_raw_fd(), move |fd| {
+        syscall!(mkdirat(fd, name.as_ptr(), DIR_MODE))
+    })
+    .await?;
+    Ok(())
+}
+
+pub async fn remove_at(dir: &std::fs::File, name: &Path, is_dir: bool) -> io::Result<()> {
+    let name = cstr(name)?;
+    let flags = if is_dir { libc::AT_REMOVEDIR } else { 0 };
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    submit(UnlinkAt::new(dir.as_raw_fd(), name, flags)).await.0?;
+    #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+    blocking(dir.as_raw_fd(), move |fd| {
+        syscall!(unlinkat(fd, name.as_ptr(), flags))
+    })
+    .await?;
+    Ok(())
+}
+
+pub async fn rename_at(
+    dir: &std::fs::File,
+    from: &Path,
+    to_dir: &std::fs::File,
+    to: &Path,
+) -> io::Result<()> {
+    let (from, to) = (cstr(from)?, cstr(to)?);
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    submit(RenameAt::new(dir.as_raw_fd(), from, to_dir.as_raw_fd(), to))
+        .await
+        .0?;
+    #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+    {
+        let to_fd = to_dir.as_raw_fd();
+        blocking(dir.as_raw_fd(), move |fd| {
+            syscall!(renameat(fd, from.as_ptr(), to_fd, to.as_ptr()))
+        })
+        .await?;
+    }
+    Ok(())
+}
+
+pub async fn symlink_at(target: &Path, dir: &std::fs::File, name: &Path) -> io::Result<()> {
+    let (target, name) = (cstr(target)?, cstr(name)?);
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    submit(SymlinkAt::new(target, dir.as_raw_fd(), name))
+        .await
+        .0?;
+    #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+    blocking(dir.as_raw_fd(), move |fd| {
+        syscall!(symlinkat(target.as_ptr(), fd, name.as_ptr()))
+    })
+    .await?;
+    Ok(())
+}
+
+pub async fn read_link_at(dir: &std::fs::File, name: &Path) -> io::Result<PathBuf> {
+    let name = cstr(name)?;
+    blocking(dir.as_raw_fd(), move |fd| {
+        let mut buffer = Vec::<u8>::with_capacity(256);
+        loop {
+            let len = syscall!(readlinkat(
+                fd,
+                name.as_ptr(),
+                buffer.as_mut_ptr().cast(),
+                buffer.capacity()
+            ))? as usize;
+            // The target may be truncated.
+            if len < buffer.capacity() {
+                unsafe { buffer.set_len(len) };
+                return Ok(PathBuf::from(OsString::from_vec(buffer)));
+            }
+            buffer.reserve(buffer.capacity() + 1);
+        }
+    })
+    .await
+}
+
+pub async fn metadata_at(dir: &std::fs::File, name: &Path, follow: bool) -> io::Result<Metadata> {
+    let nofollow = if follow { 0 } else { nofollow_flag() };
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let flags = libc::O_PATH | libc::O_CLOEXEC | nofollow;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let flags = libc::O_RDONLY | libc::O_NONBLOCK | libc::O_CLOEXEC | nofollow;
+    let fd = open_at(dir, name, flags, 0).await?;
+    std::fs::File::from(fd).metadata()
+}
+
+// Open the link itself, instead of the target.
+fn nofollow_flag() -> libc::c_int {
+    cfg_if::cfg_if! {
+        if #[cfg(target_vendor = "apple")] {
+            libc::O_SYMLINK
+        } else {
+            libc::O_NOFOLLOW
+        }
+    }
+}
+
+pub async fn read_dir(dir: &std::fs::File) -> io::Result<Vec<OsString>> {
+    blocking(dir.as_raw_fd(), |fd| {
+        // The stream owns the fd, and the offset is shared with `dir`.
+        let fd = syscall!(fcntl(fd, libc::F_DUPFD_CLOEXEC, 0))?;
+        let stream = unsafe { libc::fdopendir(fd) };
+        if stream.is_null() {
+            let e = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(e);
+        }
+        let stream = DirStream(stream);
+        unsafe { libc::rewinddir(stream.0) };
+        let mut names = vec![];
+        loop {
+            clear_errno();
+            let entry = unsafe { libc::readdir(stream.0) };
+            if entry.is_null() {
+                let e = io::Error::last_os_error();
+                return match e.raw_os_error() {
+                    Some(0) => Ok(names),
+                    _ => Err(e),
+                };
+            }
+            let name = unsafe { CStr::from_ptr((*entry).d_name.as_ptr()) }.to_bytes();
+            if name != b"." && name != b".." {
+                names.push(OsString::from_vec(name.to_vec()));
+            }
+        }
+    })
+    .await
+}
+
+struct DirStream(*mut libc::DIR);
+
+impl Drop for DirStream {
+    fn drop(&mut self) {
+        unsafe { libc::closedir(self.0) };
+    }
+}
+
+// `readdir` returns null both at the end and on errors.
+fn clear_errno() {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "linux")] {
+            unsafe { *libc::__errno_location() = 0 };
+        } else if #[cfg(any(target_os = "android", target_os = "netbsd", target_os = "openbsd"))] {
+            unsafe { *libc::__errno() = 0 };
+        } else if #[cfg(any(target_os = "illumos", target_os = "solaris"))] {
+            unsafe { *libc::___errno() = 0 };
+        } else {
+            unsafe { *libc::__error() = 0 };
+        }
+    }
+}
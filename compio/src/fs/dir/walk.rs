@@ -0,0 +1,527 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    ffi::{OsStr, OsString},
+    fmt::Debug,
+    fs::{FileType, Metadata},
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, ready},
+};
+
+use futures_util::{Stream, StreamExt, future::LocalBoxFuture, stream::FuturesUnordered};
+
+use super::Dir;
+
+/// The options of [`walk_dir`].
+#[derive(Clone)]
+pub struct WalkOptions {
+    concurrency: usize,
+    follow_links: bool,
+    min_depth: usize,
+    max_depth: usize,
+    sorted: bool,
+    #[allow(clippy::type_complexity)]
+    filter: Option<Rc<dyn Fn(&DirEntry) -> bool>>,
+}
+
+impl WalkOptions {
+    /// Create [`WalkOptions`] with the default values: 16 directories read
+    /// concurrently, the symbolic links not followed, all the depths, and not
+    /// sorted.
+    pub fn new() -> Self {
+        Self {
+            concurrency: 16,
+            follow_links: false,
+            min_depth: 0,
+            max_depth: usize::MAX,
+            sorted: false,
+            filter: None,
+        }
+    }
+
+    /// The maximum number of directories read at the same time. It is at
+    /// least one.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Whether to follow the symbolic links. The entry of a followed link
+    /// has the metadata of its target, and a link to a directory is
+    /// descended, unless it points back to a directory above it, which is
+    /// reported as an error. A broken link is yielded as the link itself.
+    pub fn follow_links(mut self, follow: bool) -> Self {
+        self.follow_links = follow;
+        self
+    }
+
+    /// The entries shallower than `depth` are not yielded, but still
+    /// descended. The root is at depth 0.
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+    /// The directories at `depth` are not descended, so the entries deeper
+    /// than it are not yielded.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Whether to sort the entries of each directory by name. Otherwise they
+    /// are in the order the system lists them.
+    pub fn sorted(mut self, sorted: bool) -> Self {
+        self.sorted = sorted;
+        self
+    }
+
+    /// Only the entries `f` returns true for are yielded and descended, so
+    /// the skipped directories are not opened at all. It is evaluated for all
+    /// the entries, including the root and the ones shallower than the
+    /// minimum depth.
+    pub fn filter(mut self, f: impl Fn(&DirEntry) -> bool + 'static) -> Self {
+        self.filter = Some(Rc::new(f));
+        self
+    }
+
+    fn accepts(&self, entry: &DirEntry) -> bool {
+        self.filter.as_ref().is_none_or(|f| f(entry))
+    }
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for WalkOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WalkOptions")
+            .field("concurrency", &self.concurrency)
+            .field("follow_links", &self.follow_links)
+            .field("min_depth", &self.min_depth)
+            .field("max_depth", &self.max_depth)
+            .field("sorted", &self.sorted)
+            .field("filter", &self.filter.is_some())
+            .finish()
+    }
+}
+
+/// An entry yielded by [`walk_dir`].
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    path: PathBuf,
+    depth: usize,
+    metadata: Metadata,
+    is_symlink: bool,
+}
+
+impl DirEntry {
+    /// The path of the entry, which is the root joined with the names below
+    /// it.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Convert into the path of the entry.
+    pub fn into_path(self) -> PathBuf {
+        self.path
+    }
+
+    /// The name of the entry, or the whole path for the root.
+    pub fn file_name(&self) -> &OsStr {
+        self.path.file_name().unwrap_or(self.path.as_os_str())
+    }
+
+    /// The depth below the root, which is at depth 0.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The type of the entry, or of the target if it is a followed link.
+    pub fn file_type(&self) -> FileType {
+        self.metadata.file_type()
+    }
+
+    /// The metadata of the entry, or of the target if it is a followed link.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Whether the entry itself is a symbolic link, followed or not.
+    pub fn path_is_symlink(&self) -> bool {
+        self.is_symlink
+    }
+}
+
+/// The error of an entry of [`walk_dir`], which is the inner error of the
+/// [`io::Error`] yielded. The walk goes on after it.
+///
+/// ```
+/// use compio::fs::WalkError;
+///
+/// fn failed_path(e: &std::io::Error) -> Option<&std::path::Path> {
+///     Some(e.get_ref()?.downcast_ref::<WalkError>()?.path())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct WalkError {
+    path: PathBuf,
+    ancestor: Option<PathBuf>,
+    source: io::Error,
+}
+
+impl WalkError {
+    fn wrap(path: PathBuf, source: io::Error) -> io::Error {
+        io::Error::new(
+            source.kind(),
+            Self {
+                path,
+                ancestor: None,
+                source,
+            },
+        )
+    }
+
+    /// The path failed to read or query.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The directory above the path it points back to, if it is a link
+    /// making a loop.
+    pub fn loop_ancestor(&self) -> Option<&Path> {
+        self.ancestor.as_deref()
+    }
+
+    /// The OS error, if any.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.source.raw_os_error()
+    }
+}
+
+impl std::fmt::Display for WalkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to walk {}: {}", self.path.display(), self.source)
+    }
+}
+
+impl std::error::Error for WalkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Walk the tree at `root` recursively, yielding the root and all the
+/// entries below it. Up to [`WalkOptions::concurrency`] directories are
+/// read at the same time, ahead of the entries yielded.
+///
+/// The tree is walked depth first, and a directory is yielded right before
+/// the entries in it, like a recursive walk with [`std::fs::read_dir`]. The
+/// order only depends on the order the system lists the entries, unless
+/// [`WalkOptions::sorted`] is set, and not on how the reads are scheduled.
+///
+/// An error to read a directory or to query an entry is yielded as a
+/// [`WalkError`] in place of the entries, and the walk goes on.
+///
+/// ```
+/// use compio::fs::{walk_dir, WalkOptions};
+/// use futures_util::StreamExt;
+///
+/// compio::task::block_on(async {
+///     let root = tempfile::tempdir().unwrap();
+///     std::fs::create_dir_all(root.path().join("a/b")).unwrap();
+///     std::fs::write(root.path().join("a/b/c.txt"), "hello").unwrap();
+///     std::fs::write(root.path().join("d.txt"), "world").unwrap();
+///
+///     let opts = WalkOptions::new().min_depth(1).sorted(true);
+///     let entries = walk_dir(root.path(), opts)
+///         .map(|entry| entry.unwrap().into_path())
+///         .collect::<Vec<_>>()
+///         .await;
+///     let expected = ["a", "a/b", "a/b/c.txt", "d.txt"].map(|path| root.path().join(path));
+///     assert_eq!(entries, expected);
+/// })
+/// ```
+pub fn walk_dir(root: impl AsRef<Path>, opts: WalkOptions) -> WalkDir {
+    let mut walk = WalkDir {
+        opts,
+        frames: vec![],
+        queue: vec![],
+        next_id: 0,
+        running: FuturesUnordered::new(),
+        done: HashMap::new(),
+    };
+    let root = walk_root(root.as_ref().to_path_buf(), &walk.opts);
+    walk.push_frame(root);
+    walk
+}
+
+/// The stream of the entries returned by [`walk_dir`].
+#[must_use = "streams do nothing unless polled"]
+pub struct WalkDir {
+    opts: WalkOptions,
+    // The entries of the directories being yielded, the innermost last.
+    frames: Vec<VecDeque<Item>>,
+    // The directories to read, the next one last, which is the same order as
+    // they are yielded.
+    queue: Vec<(u64, Pending)>,
+    next_id: u64,
+    running: FuturesUnordered<LocalBoxFuture<'static, (u64, Vec<Walked>)>>,
+    // The directories read ahead of the entries yielded.
+    done: HashMap<u64, Vec<Walked>>,
+}
+
+impl WalkDir {
+    // Assign the directories to read, and push the entries as the innermost
+    // frame.
+    fn push_frame(&mut self, walked: Vec<Walked>) {
+        let mut frame = VecDeque::with_capacity(walked.len());
+        let mut dirs = vec![];
+        for walked in walked {
+            match walked {
+                Walked::Entry(entry) => frame.push_back(Item::Entry(entry)),
+                Walked::Dir(pending) => {
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    frame.push_back(Item::Dir(id));
+                    dirs.push((id, pending));
+                }
+            }
+        }
+        self.queue.extend(dirs.into_iter().rev());
+        self.frames.push(frame);
+    }
+}
+
+impl Stream for WalkDir {
+    type Item = io::Result<DirEntry>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            while this.running.len() < this.opts.concurrency {
+                let Some((id, pending)) = this.queue.pop() else {
+                    break;
+                };
+                let opts = this.opts.clone();
+                this.running
+                    .push(Box::pin(async move { (id, read(pending, &opts).await) }));
+            }
+            let Some(frame) = this.frames.last_mut() else {
+                return Poll::Ready(None);
+            };
+            match frame.pop_front() {
+                Some(Item::Entry(entry)) => return Poll::Ready(Some(entry)),
+                Some(Item::Dir(id)) => match this.done.remove(&id) {
+                    Some(walked) => this.push_frame(walked),
+                    None => {
+                        frame.push_front(Item::Dir(id));
+                        // The directory is either read or reading, so it
+                        // could not be empty.
+                        let (id, walked) = ready!(this.running.poll_next_unpin(cx))
+                            .expect("the directory should be reading");
+                        this.done.insert(id, walked);
+                    }
+                },
+                None => {
+                    this.frames.pop();
+                }
+            }
+        }
+    }
+}
+
+impl Debug for WalkDir {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WalkDir")
+            .field("opts", &self.opts)
+            .finish_non_exhaustive()
+    }
+}
+
+// An item of a frame, where a directory is replaced by its entries once read.
+#[allow(clippy::large_enum_variant)]
+enum Item {
+    Entry(io::Result<DirEntry>),
+    Dir(u64),
+}
+
+// An item of a directory read.
+enum Walked {
+    Entry(io::Result<DirEntry>),
+    Dir(Pending),
+}
+
+// A directory to read, opened relative to its parent unless it is the root.
+struct Pending {
+    parent: Option<Rc<Dir>>,
+    name: OsString,
+    path: PathBuf,
+    depth: usize,
+    ancestors: Option<Rc<Ancestor>>,
+}
+
+// The directories above, to detect the loops of the links followed.
+struct Ancestor {
+    id: (u64, u64),
+    path: PathBuf,
+    parent: Option<Rc<Ancestor>>,
+}
+
+fn walk_root(path: PathBuf, opts: &WalkOptions) -> Vec<Walked> {
+    let metadata = match std::fs::metadata(&path) {
+        Ok(metadata) => metadata,
+        Err(e) => return vec![Walked::Entry(Err(WalkError::wrap(path, e)))],
+    };
+    let is_symlink = std::fs::symlink_metadata(&path).is_ok_and(|m| m.is_symlink());
+    let entry = DirEntry {
+        path: path.clone(),
+        depth: 0,
+        metadata,
+        is_symlink,
+    };
+    let mut walked = vec![];
+    if !opts.accepts(&entry) {
+        return walked;
+    }
+    let is_dir = entry.metadata.is_dir();
+    if opts.min_depth == 0 {
+        walked.push(Walked::Entry(Ok(entry)));
+    }
+    if is_dir && opts.max_depth > 0 {
+        walked.push(Walked::Dir(Pending {
+            parent: None,
+            name: path.clone().into_os_string(),
+            path,
+            depth: 0,
+            ancestors: None,
+        }));
+    }
+    walked
+}
+
+async fn read(pending: Pending, opts: &WalkOptions) -> Vec<Walked> {
+    let error = |path, e| vec![Walked::Entry(Err(WalkError::wrap(path, e)))];
+    let dir = match &pending.parent {
+        Some(parent) => parent.open_dir_at(&pending.name).await,
+        None => Dir::open(&pending.path),
+    };
+    let dir = match dir {
+        Ok(dir) => Rc::new(dir),
+        Err(e) => return error(pending.path, e),
+    };
+    let ancestors = if opts.follow_links {
+        let id = match file_id(&dir) {
+            Ok(id) => id,
+            Err(e) => return error(pending.path, e),
+        };
+        let mut ancestor = pending.ancestors.as_deref();
+        while let Some(a) = ancestor {
+            if a.id == id {
+                let e = WalkError {
+                    path: pending.path,
+                    ancestor: Some(a.path.clone()),
+                    source: io::Error::other("a filesystem loop of symbolic links"),
+                };
+                return vec![Walked::Entry(Err(io::Error::other(e)))];
+            }
+            ancestor = a.parent.as_deref();
+        }
+        Some(Rc::new(Ancestor {
+            id,
+            path: pending.path.clone(),
+            parent: pending.ancestors,
+        }))
+    } else {
+        None
+    };
+
+    let mut names = match dir.read_dir().await {
+        Ok(names) => names,
+        Err(e) => return error(pending.path, e),
+    };
+    if opts.sorted {
+        names.sort_unstable();
+    }
+    let depth = pending.depth + 1;
+    let mut walked = vec![];
+    for name in names {
+        let path = pending.path.join(&name);
+        let (metadata, is_symlink) = match entry_metadata(&dir, &name, opts.follow_links).await {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                walked.push(Walked::Entry(Err(WalkError::wrap(path, e))));
+                continue;
+            }
+        };
+        let entry = DirEntry {
+            path,
+            depth,
+            metadata,
+            is_symlink,
+        };
+        if !opts.accepts(&entry) {
+            continue;
+        }
+        let pending = (entry.metadata.is_dir() && depth < opts.max_depth).then(|| Pending {
+            parent: Some(dir.clone()),
+            name,
+            path: entry.path.clone(),
+            depth,
+            ancestors: ancestors.clone(),
+        });
+        if depth >= opts.min_depth {
+            walked.push(Walked::Entry(Ok(entry)));
+        }
+        walked.extend(pending.map(Walked::Dir));
+    }
+    walked
+}
+
+// The metadata of an entry, or of its target if it is a link to follow, and
+// whether it is a link.
+async fn entry_metadata(dir: &Dir, name: &OsStr, follow: bool) -> io::Result<(Metadata, bool)> {
+    let metadata = dir.metadata_at(name, false).await?;
+    if !(follow && metadata.is_symlink()) {
+        let is_symlink = metadata.is_symlink();
+        return Ok((metadata, is_symlink));
+    }
+    match dir.metadata_at(name, true).await {
+        Ok(target) => Ok((target, true)),
+        // A broken link.
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok((metadata, true)),
+        Err(e) => Err(e),
+    }
+}
+
+// The device and the inode of a directory.
+#[cfg(unix)]
+fn file_id(dir: &Dir) -> io::Result<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = dir.inner.metadata()?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+// The volume serial number and the file index of a directory.
+#[cfg(windows)]
+fn file_id(dir: &Dir) -> io::Result<(u64, u64)> {
+    use std::os::windows::io::AsRawHandle;
+
+    use windows_sys::Win32::Storage::FileSystem::{
+        BY_HANDLE_FILE_INFORMATION, GetFileInformationByHandle,
+    };
+
+    let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+    crate::syscall!(
+        BOOL,
+        GetFileInformationByHandle(dir.inner.as_raw_handle() as _, &mut info)
+    )?;
+    let index = ((info.nFileIndexHigh as u64) << 32) | info.nFileIndexLow as u64;
+    Ok((info.dwVolumeSerialNumber as u64, index))
+}
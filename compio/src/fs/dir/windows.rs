@@ -0,0 +1,150 @@
+use std::{
+    ffi::OsString,
+    fs::Metadata,
+    io,
+    os::windows::{
+        ffi::OsStringExt,
+        fs::OpenOptionsExt,
+        io::{AsRawHandle, IntoRawHandle},
+    },
+    path::{Path, PathBuf},
+};
+
+use windows_sys::Win32::Storage::FileSystem::{
+    GetFinalPathNameByHandleW, FILE_FLAG_BACKUP_SEMANTICS, FILE_NAME_NORMALIZED,
+};
+
+use super::blocking;
+use crate::{
+    driver::FromRawFd,
+    fs::{file_with_options, File, OpenOptions},
+    syscall,
+};
+
+pub fn open(path: &Path) -> io::Result<std::fs::File> {
+    // Opening a directory requires the backup semantics.
+    std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+}
+
+// The full path of the directory now, which is followed by the names. The
+// separators are normalized by pushing the components, because the verbatim
+// paths are not normalized by the system.
+fn full_path(dir: &std::fs::File, name: &Path) -> io::Result<PathBuf> {
+    let handle = dir.as_raw_handle() as _;
+    let mut buffer = Vec::<u16>::with_capacity(260);
+    let len = loop {
+        let len = syscall!(
+            GetFinalPathNameByHandleW(
+                handle,
+                buffer.as_mut_ptr(),
+                buffer.capacity() as _,
+                FILE_NAME_NORMALIZED
+            ),
+            == 0
+        )? as usize;
+        // The required size including the nul is returned if it is too small.
+        if len < buffer.capacity() {
+            break len;
+        }
+        buffer.reserve(len);
+    };
+    unsafe { buffer.set_len(len) };
+    let mut path = PathBuf::from(OsString::from_wide(&buffer));
+    for component in name.components() {
+        path.push(component);
+    }
+    Ok(path)
+}
+
+pub async fn open_dir_at(dir: &std::fs::File, name: &Path) -> io::Result<std::fs::File> {
+    let path = full_path(dir, name)?;
+    blocking(dir.as_raw_handle() as _, move |_| open(&path)).await
+}
+
+pub async fn open_file_at(
+    dir: &std::fs::File,
+    name: &Path,
+    opts: OpenOptions,
+) -> io::Result<File> {
+    let path = full_path(dir, name)?;
+    let file = blocking(dir.as_raw_handle() as _, move |_| {
+        file_with_options(path, opts.std)
+    })
+    .await?;
+    Ok(unsafe { File::from_raw_fd(file.into_raw_handle() as _) })
+}
+
+pub async fn create_dir_at(dir: &std::fs::File, name: &Path) -> io::Result<()> {
+    let path = full_path(dir, name)?;
+    blocking(dir.as_raw_handle() as _, move |_| std::fs::create_dir(path)).await
+}
+
+pub async fn remove_at(dir: &std::fs::File, name: &Path, is_dir: bool) -> io::Result<()> {
+    let path = full_path(dir, name)?;
+    blocking(dir.as_raw_handle() as _, move |_| {
+        if is_dir {
+            std::fs::remove_dir(path)
+        } else {
+            std::fs::remove_file(path)
+        }
+    })
+    .await
+}
+
+pub async fn rename_at(
+    dir: &std::fs::File,
+    from: &Path,
+    to_dir: &std::fs::File,
+    to: &Path,
+) -> io::Result<()> {
+    let (from, to) = (full_path(dir, from)?, full_path(to_dir, to)?);
+    blocking(dir.as_raw_handle() as _, move |_| std::fs::rename(from, to)).await
+}
+
+pub async fn symlink_at(target: &Path, dir: &std::fs::File, name: &Path) -> io::Result<()> {
+    let path = full_path(dir, name)?;
+    let target = target.to_path_buf();
+    blocking(dir.as_raw_handle() as _, move |_| {
+        // The relative target is resolved relative to the link.
+        let resolved = match path.parent() {
+            Some(parent) => parent.join(&target),
+            None => target.clone(),
+        };
+        if resolved.is_dir() {
+            std::os::windows::fs::symlink_dir(target, path)
+        } else {
+            std::os::windows::fs::symlink_file(target, path)
+        }
+    })
+    .await
+}
+
+pub async fn read_link_at(dir: &std::fs::File, name: &Path) -> io::Result<PathBuf> {
+    let path = full_path(dir, name)?;
+    blocking(dir.as_raw_handle() as _, move |_| std::fs::read_link(path)).await
+}
+
+pub async fn metadata_at(dir: &std::fs::File, name: &Path, follow: bool) -> io::Result<Metadata> {
+    let path = full_path(dir, name)?;
+    blocking(dir.as_raw_handle() as _, move |_| {
+        if follow {
+            std::fs::metadata(path)
+        } else {
+            std::fs::symlink_metadata(path)
+        }
+    })
+    .await
+}
+
+pub async fn read_dir(dir: &std::fs::File) -> io::Result<Vec<OsString>> {
+    let path = full_path(dir, Path::new(""))?;
+    blocking(dir.as_raw_handle() as _, move |_| {
+        std::fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect()
+    })
+    .await
+}
@@ -1,12 +1,17 @@
 #[cfg(feature = "allocator_api")]
 use std::alloc::Allocator;
-use std::{fs::Metadata, io, path::Path};
+use std::{
+    fs::{Metadata, Permissions},
+    io,
+    path::Path,
+};
 
 #[cfg(feature = "runtime")]
 use crate::{
     buf::{IntoInner, IoBuf, IoBufMut},
     buf_try,
     driver::AsRawFd,
+    fs::{ReadAtOpts, WriteAtOpts},
     op::{BufResultExt, ReadAt, Sync, WriteAt},
     task::submit,
     vec_alloc, Attacher, BufResult,
@@ -27,7 +32,7 @@ pub struct File {
 }
 
 #[cfg(target_os = "windows")]
-fn file_with_options(
+pub(crate) fn file_with_options(
     path: impl AsRef<Path>,
     mut options: std::fs::OpenOptions,
 ) -> io::Result<std::fs::File> {
@@ -57,10 +62,63 @@ fn file_with_options(
     options.open(path)
 }
 
+#[cfg(target_os = "windows")]
+fn clone_file(file: &std::fs::File) -> io::Result<std::fs::File> {
+    use std::{
+        os::windows::prelude::{AsRawHandle, FromRawHandle},
+        ptr::null_mut,
+    };
+
+    use windows_sys::Win32::{
+        Foundation::RtlNtStatusToDosError,
+        Storage::FileSystem::{
+            ReOpenFile, FILE_FLAG_OVERLAPPED, FILE_SHARE_DELETE, FILE_SHARE_READ,
+            FILE_SHARE_WRITE,
+        },
+        System::WindowsProgramming::{
+            NtQueryObject, ObjectBasicInformation, PUBLIC_OBJECT_BASIC_INFORMATION,
+        },
+    };
+
+    use crate::syscall;
+
+    // Reopen with the same access.
+    let mut info: PUBLIC_OBJECT_BASIC_INFORMATION = unsafe { std::mem::zeroed() };
+    let status = unsafe {
+        NtQueryObject(
+            file.as_raw_handle() as _,
+            ObjectBasicInformation,
+            &mut info as *mut _ as _,
+            std::mem::size_of::<PUBLIC_OBJECT_BASIC_INFORMATION>() as _,
+            null_mut(),
+        )
+    };
+    if status < 0 {
+        return Err(io::Error::from_raw_os_error(
+            unsafe { RtlNtStatusToDosError(status) } as _,
+        ));
+    }
+    let handle = syscall!(
+        HANDLE,
+        ReOpenFile(
+            file.as_raw_handle() as _,
+            info.GrantedAccess,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            FILE_FLAG_OVERLAPPED
+        )
+    )?;
+    Ok(unsafe { std::fs::File::from_raw_handle(handle as _) })
+}
+
+#[cfg(not(target_os = "windows"))]
+fn clone_file(file: &std::fs::File) -> io::Result<std::fs::File> {
+    file.try_clone()
+}
+
 impl File {
     pub(crate) fn with_options(path: impl AsRef<Path>, options: OpenOptions) -> io::Result<Self> {
         let this = Self {
-            inner: file_with_options(path, options.0)?,
+            inner: file_with_options(path, options.std)?,
             #[cfg(feature = "runtime")]
             attacher: Attacher::new(),
         };
@@ -93,23 +151,43 @@ impl File {
         self.attacher.attach(self)
     }
 
-    /// Creates a new `File` instance that shares the same underlying file
-    /// handle as the existing `File` instance.
-    ///
-    /// It does not clear the attach state.
+    /// Creates a new `File` instance that refers to the same file as the
+    /// existing `File` instance.
+    ///
+    /// The new instance shares nothing with the existing one in the runtime,
+    /// and it is attached to the driver of current thread lazily, on its first
+    /// IO. The file is duplicated with `dup` on Unix, and reopened with
+    /// `ReOpenFile` on Windows, because the duplicated handles could only be
+    /// attached once to one completion port.
     pub fn try_clone(&self) -> io::Result<Self> {
         Ok(Self {
-            inner: self.inner.try_clone()?,
+            inner: clone_file(&self.inner)?,
             #[cfg(feature = "runtime")]
-            attacher: self.attacher.clone(),
+            attacher: Attacher::new(),
         })
     }
 
+    #[cfg(feature = "runtime")]
+    pub(crate) fn try_clone_std(&self) -> io::Result<std::fs::File> {
+        self.inner.try_clone()
+    }
+
     /// Queries metadata about the underlying file.
     pub fn metadata(&self) -> io::Result<Metadata> {
         self.inner.metadata()
     }
 
+    /// Changes the permissions on the underlying file.
+    pub fn set_permissions(&self, perm: Permissions) -> io::Result<()> {
+        self.inner.set_permissions(perm)
+    }
+
+    /// Truncates or extends the underlying file, updating the size of this
+    /// file to become `size`. The extended part is filled with zeros.
+    pub fn set_len(&self, size: u64) -> io::Result<()> {
+        self.inner.set_len(size)
+    }
+
     /// Read some bytes at the specified offset from the file into the specified
     /// buffer, returning how many bytes were read.
     ///
@@ -133,9 +211,33 @@ impl File {
     /// If this function encounters any form of I/O or other error, an error
     /// variant will be returned. The buffer is returned on error.
     #[cfg(feature = "runtime")]
-    pub async fn read_at<T: IoBufMut>(&self, buffer: T, pos: usize) -> BufResult<usize, T> {
+    pub async fn read_at<T: IoBufMut>(&self, buffer: T, pos: u64) -> BufResult<usize, T> {
+        let ((), buffer) = buf_try!(self.attach(), buffer);
+        let op = ReadAt::new(self.as_raw_fd(), pos, buffer);
+        submit(op).await.into_inner().map_advanced().into_inner()
+    }
+
+    /// Read some bytes at the specified offset from the file into the specified
+    /// buffer, with the flags in `opts`. See [`File::read_at`] and
+    /// [`ReadAtOpts`].
+    ///
+    /// A read with [`RwFlag::NoWait`] fails with
+    /// [`io::ErrorKind::WouldBlock`] if the data is not in the page cache, so
+    /// that the caller could schedule a blocking read elsewhere.
+    ///
+    /// [`RwFlag::NoWait`]: crate::fs::RwFlag::NoWait
+    #[cfg(feature = "runtime")]
+    pub async fn read_at_with<T: IoBufMut>(
+        &self,
+        buffer: T,
+        pos: u64,
+        opts: &ReadAtOpts,
+    ) -> BufResult<usize, T> {
         let ((), buffer) = buf_try!(self.attach(), buffer);
+        let (_flags, buffer) = buf_try!(opts.raw_flags(), buffer);
         let op = ReadAt::new(self.as_raw_fd(), pos, buffer);
+        #[cfg(target_os = "linux")]
+        let op = op.with_rw_flags(_flags);
         submit(op).await.into_inner().map_advanced().into_inner()
     }
 
@@ -160,16 +262,12 @@ impl File {
     ///
     /// [`ErrorKind::UnexpectedEof`]: io::ErrorKind::UnexpectedEof
     #[cfg(feature = "runtime")]
-    pub async fn read_exact_at<T: IoBufMut>(
-        &self,
-        mut buffer: T,
-        pos: usize,
-    ) -> BufResult<usize, T> {
+    pub async fn read_exact_at<T: IoBufMut>(&self, mut buffer: T, pos: u64) -> BufResult<usize, T> {
         let need = buffer.as_uninit_slice().len();
         let mut total_read = 0;
         let mut read;
         while total_read < need {
-            (read, buffer) = buf_try!(self.read_at(buffer, pos + total_read).await);
+            (read, buffer) = buf_try!(self.read_at(buffer, pos + total_read as u64).await);
             if read == 0 {
                 break;
             } else {
@@ -200,12 +298,12 @@ impl File {
     pub async fn read_to_end_at<#[cfg(feature = "allocator_api")] A: Allocator + 'static>(
         &self,
         mut buffer: vec_alloc!(u8, A),
-        pos: usize,
+        pos: u64,
     ) -> BufResult<usize, vec_alloc!(u8, A)> {
         let mut total_read = 0;
         let mut read;
         loop {
-            (read, buffer) = buf_try!(self.read_at(buffer, pos + total_read).await);
+            (read, buffer) = buf_try!(self.read_at(buffer, pos + total_read as u64).await);
             if read == 0 {
                 break;
             } else {
@@ -241,12 +339,34 @@ impl File {
     /// It is **not** considered an error if the entire buffer could not be
     /// written to this writer.
     #[cfg(feature = "runtime")]
-    pub async fn write_at<T: IoBuf>(&self, buffer: T, pos: usize) -> BufResult<usize, T> {
+    pub async fn write_at<T: IoBuf>(&self, buffer: T, pos: u64) -> BufResult<usize, T> {
         let ((), buffer) = buf_try!(self.attach(), buffer);
         let op = WriteAt::new(self.as_raw_fd(), pos, buffer);
         submit(op).await.into_inner().into_inner()
     }
 
+    /// Write a buffer into this file at the specified offset, with the flags in
+    /// `opts`. See [`File::write_at`] and [`WriteAtOpts`].
+    ///
+    /// With [`RwFlag::Append`], the data is written to the end of the file
+    /// and `pos` is ignored.
+    ///
+    /// [`RwFlag::Append`]: crate::fs::RwFlag::Append
+    #[cfg(feature = "runtime")]
+    pub async fn write_at_with<T: IoBuf>(
+        &self,
+        buffer: T,
+        pos: u64,
+        opts: &WriteAtOpts,
+    ) -> BufResult<usize, T> {
+        let ((), buffer) = buf_try!(self.attach(), buffer);
+        let (_flags, buffer) = buf_try!(opts.raw_flags(), buffer);
+        let op = WriteAt::new(self.as_raw_fd(), pos, buffer);
+        #[cfg(target_os = "linux")]
+        let op = op.with_rw_flags(_flags);
+        submit(op).await.into_inner().into_inner()
+    }
+
     /// Attempts to write an entire buffer into this writer.
     ///
     /// This method will continuously call [`write_at`] until there is no more
@@ -257,13 +377,13 @@ impl File {
     ///
     /// [`write_at`]: File::write_at
     #[cfg(feature = "runtime")]
-    pub async fn write_all_at<T: IoBuf>(&self, mut buffer: T, pos: usize) -> BufResult<usize, T> {
+    pub async fn write_all_at<T: IoBuf>(&self, mut buffer: T, pos: u64) -> BufResult<usize, T> {
         let buf_len = buffer.buf_len();
         let mut total_written = 0;
         let mut written;
         while total_written < buf_len {
             (written, buffer) = buf_try!(self
-                .write_at(buffer.slice(total_written..), pos + total_written)
+                .write_at(buffer.slice(total_written..), pos + total_written as u64)
                 .await
                 .into_inner());
             total_written += written;
@@ -271,6 +391,27 @@ impl File {
         (Ok(total_written), buffer)
     }
 
+    /// Copy `len` bytes from the offset `src_off` of this file to the offset
+    /// `dst_off` of `dst`, returning the number of bytes copied. It is less
+    /// than `len` only if the EOF of this file is reached. The length of `dst`
+    /// is extended if the range exceeds its end.
+    ///
+    /// The data is copied in the kernel if possible, see [`copy`] for the
+    /// platform specific behaviors. Only the Linux and Windows fast paths
+    /// support ranges, and the others copy through userspace buffers.
+    ///
+    /// [`copy`]: crate::fs::copy
+    #[cfg(feature = "runtime")]
+    pub async fn copy_range_to(
+        &self,
+        dst: &File,
+        src_off: u64,
+        dst_off: u64,
+        len: u64,
+    ) -> io::Result<u64> {
+        super::copy::copy_range(self, dst, src_off, dst_off, len).await
+    }
+
     #[cfg(feature = "runtime")]
     async fn sync_impl(&self, datasync: bool) -> io::Result<()> {
         self.attach()?;
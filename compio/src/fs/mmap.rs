@@ -0,0 +1,422 @@
+use std::{io, ops::RangeBounds, ptr::NonNull, sync::Arc};
+
+use crate::{
+    buf::{IoBuf, IoBufMut},
+    driver::AsRawFd,
+    fs::File,
+    op::BlockingBufOp,
+    task::submit,
+};
+
+/// How the writes to a [`MmapMut`] reach the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapMode {
+    /// The writes are visible to the other mappings of the file, and written
+    /// back to the file. The file should be opened for reading and writing.
+    Shared,
+    /// The writes are private to the mapping, and never written back. The file
+    /// should be opened for reading.
+    CopyOnWrite,
+}
+
+struct Mapping {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+// The mapping is only accessed by the owner, or by the ops of the regions.
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    fn empty() -> Self {
+        Self {
+            ptr: NonNull::dangling(),
+            len: 0,
+        }
+    }
+}
+
+/// A writable memory map of a file.
+///
+/// The mapped bytes are accessed by [`MmapMut::as_slice`] and
+/// [`MmapMut::as_mut_slice`], or passed to the IO operations as
+/// [`MmapRegion`], so that the data received could be written into the file
+/// without copying. The regions keep the mapping alive, and the mapping could
+/// not be accessed or remapped until they are dropped, i.e., until the ops
+/// using them complete.
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use compio::{
+///     fs::{tempfile, MapMode, MmapMut},
+///     net::{TcpListener, TcpStream},
+/// };
+///
+/// compio::task::block_on(async {
+///     let file = tempfile().unwrap();
+///     file.set_len(5).unwrap();
+///     let mut map = unsafe { MmapMut::map(&file, 5, MapMode::Shared) }.unwrap();
+///
+///     let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     let addr = listener.local_addr().unwrap();
+///     let (client, (server, _)) =
+///         futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+///     client.send_all("hello").await.0.unwrap();
+///     server.recv_exact(map.region(..)).await.0.unwrap();
+///     map.flush_range(0, 5).await.unwrap();
+///
+///     let (_, buffer) = file.read_to_end_at(Vec::with_capacity(5), 0).await;
+///     assert_eq!(buffer, b"hello");
+/// })
+/// ```
+pub struct MmapMut {
+    mapping: Arc<Mapping>,
+    file: std::fs::File,
+    mode: MapMode,
+}
+
+impl MmapMut {
+    /// Maps the first `len` bytes of the file, which should not be larger than
+    /// the file.
+    ///
+    /// # Safety
+    ///
+    /// The file should not be truncated while it is mapped, and with
+    /// [`MapMode::Shared`], the mapped bytes should not be modified by the
+    /// other processes or mappings while they are accessed by the slices.
+    pub unsafe fn map(file: &File, len: usize, mode: MapMode) -> io::Result<Self> {
+        let file = file.try_clone_std()?;
+        let mapping = map_file(&file, len, mode)?;
+        Ok(Self {
+            mapping: Arc::new(mapping),
+            file,
+            mode,
+        })
+    }
+
+    /// The length of the mapping.
+    pub fn len(&self) -> usize {
+        self.mapping.len
+    }
+
+    /// Whether the mapping is empty.
+    pub fn is_empty(&self) -> bool {
+        self.mapping.len == 0
+    }
+
+    /// The mode of the mapping.
+    pub fn mode(&self) -> MapMode {
+        self.mode
+    }
+
+    /// The mapped bytes, or `None` if some regions are alive.
+    pub fn as_slice(&self) -> Option<&[u8]> {
+        self.is_unique().then(|| unsafe {
+            std::slice::from_raw_parts(self.mapping.ptr.as_ptr(), self.mapping.len)
+        })
+    }
+
+    /// The mapped bytes, mutably, or `None` if some regions are alive.
+    pub fn as_mut_slice(&mut self) -> Option<&mut [u8]> {
+        self.is_unique().then(|| unsafe {
+            std::slice::from_raw_parts_mut(self.mapping.ptr.as_ptr(), self.mapping.len)
+        })
+    }
+
+    /// A region of the mapping to be passed to the IO operations. It is empty,
+    /// and the bytes received are written from its start.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of the mapping.
+    pub fn region(&mut self, range: impl RangeBounds<usize>) -> MmapRegion {
+        use std::ops::Bound;
+
+        let begin = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&n) => n + 1,
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => self.mapping.len,
+        };
+        assert!(
+            begin <= end && end <= self.mapping.len,
+            "the range {begin}..{end} is out of the mapping of {}",
+            self.mapping.len
+        );
+        MmapRegion {
+            mapping: self.mapping.clone(),
+            offset: begin,
+            capacity: end - begin,
+            len: 0,
+        }
+    }
+
+    /// Maps the first `len` bytes of the file again, usually after the file is
+    /// extended by [`File::set_len`]. The address of the mapping may change.
+    ///
+    /// It fails with [`io::ErrorKind::ResourceBusy`] if some regions are alive,
+    /// or a flush is pending, because they refer to the old mapping. The old
+    /// mapping is kept if it fails.
+    pub fn remap(&mut self, len: usize) -> io::Result<()> {
+        if !self.is_unique() {
+            return Err(io::Error::new(
+                io::ErrorKind::ResourceBusy,
+                "the mapping is used by the regions or the pending flushes",
+            ));
+        }
+        self.mapping = Arc::new(map_file(&self.file, len, self.mode)?);
+        Ok(())
+    }
+
+    /// Writes the modified bytes in the range back to the file, and waits
+    /// until they reach the disk, on the blocking thread pool. It does nothing
+    /// with [`MapMode::CopyOnWrite`].
+    ///
+    /// ## Platform specific
+    /// * Unix: `msync` with `MS_SYNC`.
+    /// * Windows: `FlushViewOfFile` and `FlushFileBuffers`.
+    pub async fn flush_range(&self, offset: usize, len: usize) -> io::Result<()> {
+        let (addr, len) = self.flush_args(offset, len)?;
+        if len == 0 {
+            return Ok(());
+        }
+        let op = BlockingBufOp::new(
+            self.file.as_raw_fd(),
+            self.mapping.clone(),
+            move |fd, _| {
+                flush(fd, addr, len, true)?;
+                Ok(0)
+            },
+        );
+        submit(op).await.0?;
+        Ok(())
+    }
+
+    /// Starts writing the modified bytes in the range back to the file,
+    /// without waiting for them. It does nothing with [`MapMode::CopyOnWrite`].
+    ///
+    /// ## Platform specific
+    /// * Unix: `msync` with `MS_ASYNC`.
+    /// * Windows: `FlushViewOfFile`.
+    pub fn start_flush_range(&self, offset: usize, len: usize) -> io::Result<()> {
+        let (addr, len) = self.flush_args(offset, len)?;
+        flush(self.file.as_raw_fd(), addr, len, false)
+    }
+
+    fn flush_args(&self, offset: usize, len: usize) -> io::Result<(usize, usize)> {
+        if offset.checked_add(len).is_none_or(|end| end > self.mapping.len) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the range is out of the mapping",
+            ));
+        }
+        if self.mode == MapMode::CopyOnWrite || len == 0 {
+            return Ok((0, 0));
+        }
+        // `msync` requires the address aligned to the pages.
+        let start = self.mapping.ptr.as_ptr() as usize + offset;
+        let aligned = start - start % page_size();
+        Ok((aligned, len + (start - aligned)))
+    }
+
+    fn is_unique(&self) -> bool {
+        Arc::strong_count(&self.mapping) == 1
+    }
+}
+
+impl std::fmt::Debug for MmapMut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MmapMut")
+            .field("ptr", &self.mapping.ptr)
+            .field("len", &self.mapping.len)
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+/// A region of [`MmapMut`], as a buffer of the IO operations. The initialized
+/// length starts from zero, and grows as the bytes are received. The mapping
+/// is kept alive by the region.
+pub struct MmapRegion {
+    mapping: Arc<Mapping>,
+    offset: usize,
+    capacity: usize,
+    len: usize,
+}
+
+impl MmapRegion {
+    /// The offset of the region in the mapping.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl std::fmt::Debug for MmapRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MmapRegion")
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+unsafe impl IoBuf for MmapRegion {
+    fn as_buf_ptr(&self) -> *const u8 {
+        unsafe { self.mapping.ptr.as_ptr().add(self.offset) }
+    }
+
+    fn buf_len(&self) -> usize {
+        self.len
+    }
+
+    fn buf_capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+unsafe impl IoBufMut for MmapRegion {
+    fn as_buf_mut_ptr(&mut self) -> *mut u8 {
+        unsafe { self.mapping.ptr.as_ptr().add(self.offset) }
+    }
+
+    unsafe fn set_buf_init(&mut self, len: usize) {
+        self.len += len;
+    }
+}
+
+fn check_len(file: &std::fs::File, len: usize) -> io::Result<()> {
+    if file.metadata()?.len() < len as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the file is smaller than the mapping",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+#[cfg(unix)]
+fn map_file(file: &std::fs::File, len: usize, mode: MapMode) -> io::Result<Mapping> {
+    check_len(file, len)?;
+    if len == 0 {
+        return Ok(Mapping::empty());
+    }
+    let flags = match mode {
+        MapMode::Shared => libc::MAP_SHARED,
+        MapMode::CopyOnWrite => libc::MAP_PRIVATE,
+    };
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            flags,
+            file.as_raw_fd(),
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Mapping {
+        ptr: NonNull::new(ptr.cast()).expect("mmap returns null"),
+        len,
+    })
+}
+
+#[cfg(unix)]
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.len) };
+        }
+    }
+}
+
+#[cfg(unix)]
+fn flush(_fd: crate::driver::RawFd, addr: usize, len: usize, wait: bool) -> io::Result<()> {
+    if len == 0 {
+        return Ok(());
+    }
+    let flags = if wait { libc::MS_SYNC } else { libc::MS_ASYNC };
+    crate::syscall!(msync(addr as _, len, flags))?;
+    Ok(())
+}
+
+// The views are mapped from the start of the file, so the address needs no
+// alignment.
+#[cfg(windows)]
+fn page_size() -> usize {
+    1
+}
+
+#[cfg(windows)]
+fn map_file(file: &std::fs::File, len: usize, mode: MapMode) -> io::Result<Mapping> {
+    use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+
+    use windows_sys::Win32::System::Memory::{
+        CreateFileMappingW, MapViewOfFile, FILE_MAP_COPY, FILE_MAP_WRITE, PAGE_READWRITE,
+        PAGE_WRITECOPY,
+    };
+
+    check_len(file, len)?;
+    if len == 0 {
+        return Ok(Mapping::empty());
+    }
+    let (protect, access) = match mode {
+        MapMode::Shared => (PAGE_READWRITE, FILE_MAP_WRITE),
+        MapMode::CopyOnWrite => (PAGE_WRITECOPY, FILE_MAP_COPY),
+    };
+    let handle = crate::syscall!(
+        BOOL,
+        CreateFileMappingW(
+            file.as_raw_fd() as _,
+            std::ptr::null(),
+            protect,
+            (len as u64 >> 32) as u32,
+            len as u32,
+            std::ptr::null(),
+        )
+    )?;
+    // The view keeps the section alive.
+    let handle = unsafe { OwnedHandle::from_raw_handle(handle as _) };
+    let view = unsafe { MapViewOfFile(handle.as_raw_handle() as _, access, 0, 0, len) };
+    let ptr = NonNull::new(view as *mut u8).ok_or_else(io::Error::last_os_error)?;
+    Ok(Mapping { ptr, len })
+}
+
+#[cfg(windows)]
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        use windows_sys::Win32::System::Memory::UnmapViewOfFile;
+
+        if self.len > 0 {
+            unsafe { UnmapViewOfFile(self.ptr.as_ptr() as _) };
+        }
+    }
+}
+
+#[cfg(windows)]
+fn flush(fd: crate::driver::RawFd, addr: usize, len: usize, wait: bool) -> io::Result<()> {
+    use windows_sys::Win32::{Storage::FileSystem::FlushFileBuffers, System::Memory::FlushViewOfFile};
+
+    if len == 0 {
+        return Ok(());
+    }
+    crate::syscall!(BOOL, FlushViewOfFile(addr as _, len))?;
+    if wait {
+        crate::syscall!(BOOL, FlushFileBuffers(fd as _))?;
+    }
+    Ok(())
+}
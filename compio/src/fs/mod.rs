@@ -1,7 +1,65 @@
 //! Filesystem manipulation operations.
 
+#[cfg(feature = "runtime")]
+mod change;
+#[cfg(feature = "runtime")]
+pub use change::*;
+
+#[cfg(feature = "runtime")]
+mod chunks;
+#[cfg(feature = "runtime")]
+pub use chunks::*;
+
+#[cfg(feature = "runtime")]
+mod copy;
+#[cfg(feature = "runtime")]
+pub use copy::*;
+
+#[cfg(feature = "runtime")]
+mod dir;
+#[cfg(feature = "runtime")]
+pub use dir::*;
+
 mod file;
 pub use file::*;
 
+#[cfg(feature = "runtime")]
+mod mmap;
+#[cfg(feature = "runtime")]
+pub use mmap::*;
+
 mod open_options;
 pub use open_options::*;
+
+#[cfg(feature = "runtime")]
+mod ranges;
+#[cfg(feature = "runtime")]
+pub use ranges::*;
+
+#[cfg(feature = "runtime")]
+mod random;
+#[cfg(feature = "runtime")]
+pub use random::*;
+
+mod temp;
+pub use temp::*;
+
+#[cfg(feature = "runtime")]
+mod rw_opts;
+#[cfg(feature = "runtime")]
+pub use rw_opts::*;
+
+#[cfg(feature = "runtime")]
+mod utils;
+#[cfg(feature = "runtime")]
+pub use utils::*;
+
+#[cfg(feature = "runtime")]
+mod vectored;
+#[cfg(feature = "runtime")]
+pub use vectored::*;
+
+#[cfg(feature = "runtime")]
+mod watch;
+#[cfg(feature = "runtime")]
+pub use watch::*;
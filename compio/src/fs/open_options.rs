@@ -39,14 +39,25 @@ use crate::fs::File;
 ///     .unwrap();
 /// ```
 #[derive(Debug, Clone)]
-pub struct OpenOptions(pub(crate) StdOpenOptions);
+pub struct OpenOptions {
+    pub(crate) std: StdOpenOptions,
+    pub(crate) flags: OpenFlags,
+}
 
 impl OpenOptions {
     /// Creates a blank new set of options ready for configuration.
     #[allow(clippy::new_without_default)]
     #[must_use]
     pub fn new() -> Self {
-        Self(StdOpenOptions::new())
+        Self::from_std(StdOpenOptions::new())
+    }
+
+    // The options with custom flags, which are only used to open a path.
+    pub(crate) fn from_std(std: StdOpenOptions) -> Self {
+        Self {
+            std,
+            flags: OpenFlags::default(),
+        }
     }
 
     /// Sets the option for read access.
@@ -54,7 +65,8 @@ impl OpenOptions {
     /// This option, when true, will indicate that the file should be
     /// `read`-able if opened.
     pub fn read(mut self, read: bool) -> Self {
-        self.0.read(read);
+        self.std.read(read);
+        self.flags.read = read;
         self
     }
 
@@ -63,7 +75,8 @@ impl OpenOptions {
     /// This option, when true, will indicate that the file should be
     /// `write`-able if opened.
     pub fn write(mut self, write: bool) -> Self {
-        self.0.write(write);
+        self.std.write(write);
+        self.flags.write = write;
         self
     }
 
@@ -74,7 +87,8 @@ impl OpenOptions {
     ///
     /// The file must be opened with write access for truncate to work.
     pub fn truncate(mut self, truncate: bool) -> Self {
-        self.0.truncate(truncate);
+        self.std.truncate(truncate);
+        self.flags.truncate = truncate;
         self
     }
 
@@ -83,7 +97,8 @@ impl OpenOptions {
     /// In order for the file to be created, [`OpenOptions::write`] access must
     /// be used.
     pub fn create(mut self, create: bool) -> Self {
-        self.0.create(create);
+        self.std.create(create);
+        self.flags.create = create;
         self
     }
 
@@ -106,7 +121,8 @@ impl OpenOptions {
     /// [`.create()`]: OpenOptions::create
     /// [`.truncate()`]: OpenOptions::truncate
     pub fn create_new(mut self, create_new: bool) -> Self {
-        self.0.create_new(create_new);
+        self.std.create_new(create_new);
+        self.flags.create_new = create_new;
         self
     }
 
@@ -117,3 +133,50 @@ impl OpenOptions {
         File::with_options(path, self)
     }
 }
+
+// The options mirrored to open a file relative to a directory, see
+// [`Dir::open_file_at`](crate::fs::Dir::open_file_at).
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(windows, allow(dead_code))]
+pub(crate) struct OpenFlags {
+    read: bool,
+    write: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+}
+
+impl OpenFlags {
+    // The flags of `openat`, checked like `std::fs::OpenOptions::open`.
+    #[cfg(unix)]
+    #[cfg_attr(not(feature = "runtime"), allow(dead_code))]
+    pub fn to_unix(self) -> io::Result<libc::c_int> {
+        let access = match (self.read, self.write) {
+            (true, false) => libc::O_RDONLY,
+            (false, true) => libc::O_WRONLY,
+            (true, true) => libc::O_RDWR,
+            (false, false) => return Err(io::Error::from_raw_os_error(libc::EINVAL)),
+        };
+        if !self.write && (self.truncate || self.create || self.create_new) {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+        let creation = if self.create_new {
+            libc::O_CREAT | libc::O_EXCL
+        } else {
+            let create = if self.create { libc::O_CREAT } else { 0 };
+            let truncate = if self.truncate { libc::O_TRUNC } else { 0 };
+            create | truncate
+        };
+        // Don't set nonblocking with epoll, like opening a path.
+        let nonblocking = if cfg!(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "illumos"
+        )) {
+            0
+        } else {
+            libc::O_NONBLOCK
+        };
+        Ok(access | creation | nonblocking | libc::O_CLOEXEC)
+    }
+}
@@ -0,0 +1,48 @@
+use std::io;
+
+use crate::{
+    buf::{IntoInner, IoBufMut},
+    buf_try,
+    op::{BufResultExt, GetRandom},
+    task::submit,
+    BufResult,
+};
+
+/// Fill the buffer with random bytes from the entropy source of the OS.
+///
+/// The uninitialized part of the buffer, from its length to its capacity, is
+/// filled fully, and the number of the bytes is returned. The bytes are
+/// suitable for the keys and the tokens. See [`GetRandom`] for the sources of
+/// the platforms.
+///
+/// ```
+/// compio::task::block_on(async {
+///     let (res, key) = compio::fs::random(Vec::with_capacity(32)).await;
+///     assert_eq!(res.unwrap(), 32);
+///     assert_eq!(key.len(), 32);
+/// })
+/// ```
+pub async fn random<T: IoBufMut>(buffer: T) -> BufResult<usize, T> {
+    // Open the source first, so that the buffer is returned on failure.
+    #[cfg(unix)]
+    let (_, buffer) = buf_try!(crate::op::urandom(), buffer);
+    let mut buffer = buffer;
+    let need = buffer.as_uninit_slice().len();
+    let mut total = 0;
+    let mut read;
+    while total < need {
+        let op = GetRandom::new(buffer).expect("the entropy source should be opened");
+        (read, buffer) = buf_try!(submit(op).await.into_inner().map_advanced().into_inner());
+        if read == 0 {
+            return (
+                Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the entropy source is exhausted",
+                )),
+                buffer,
+            );
+        }
+        total += read;
+    }
+    (Ok(total), buffer)
+}
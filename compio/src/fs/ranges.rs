@@ -0,0 +1,179 @@
+use std::{io, ops::Range};
+
+use futures_util::StreamExt;
+
+use crate::fs::File;
+
+/// The options of [`File::read_ranges_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadRangesOptions {
+    coalesce_gap: u64,
+    max_coalesced: u64,
+    max_in_flight: usize,
+}
+
+impl ReadRangesOptions {
+    /// Create the default options: only the adjacent or overlapping ranges
+    /// are coalesced, up to 1 MiB, and at most 64 reads are in flight.
+    pub fn new() -> Self {
+        Self {
+            coalesce_gap: 0,
+            max_coalesced: 1024 * 1024,
+            max_in_flight: 64,
+        }
+    }
+
+    /// Coalesce the ranges separated by at most `gap` bytes into one read,
+    /// which is then split. The bytes in the gaps are read and dropped, which is
+    /// usually cheaper than another read for small gaps. Default to 0.
+    pub fn coalesce_gap(mut self, gap: u64) -> Self {
+        self.coalesce_gap = gap;
+        self
+    }
+
+    /// The largest size of a coalesced read. A range larger than it is still
+    /// read as a whole. Default to 1 MiB.
+    pub fn max_coalesced(mut self, size: u64) -> Self {
+        self.max_coalesced = size;
+        self
+    }
+
+    /// The most reads in flight. Default to 64.
+    pub fn max_in_flight(mut self, count: usize) -> Self {
+        self.max_in_flight = count.max(1);
+        self
+    }
+}
+
+impl Default for ReadRangesOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A read covering the requested ranges of `members`.
+struct Coalesced {
+    range: Range<u64>,
+    members: Vec<usize>,
+}
+
+fn coalesce(ranges: &[Range<u64>], options: &ReadRangesOptions) -> Vec<Coalesced> {
+    let mut indices = (0..ranges.len())
+        .filter(|&i| ranges[i].start < ranges[i].end)
+        .collect::<Vec<_>>();
+    indices.sort_by_key(|&i| ranges[i].start);
+    let mut reads: Vec<Coalesced> = vec![];
+    for i in indices {
+        let range = &ranges[i];
+        if let Some(read) = reads.last_mut() {
+            let end = read.range.end.max(range.end);
+            if range.start <= read.range.end.saturating_add(options.coalesce_gap)
+                && end - read.range.start <= options.max_coalesced
+            {
+                read.range.end = end;
+                read.members.push(i);
+                continue;
+            }
+        }
+        reads.push(Coalesced {
+            range: range.clone(),
+            members: vec![i],
+        });
+    }
+    reads
+}
+
+// The error is not cloneable, so the ones for the other ranges are recreated.
+fn copy_error(e: &io::Error) -> io::Error {
+    match e.raw_os_error() {
+        Some(code) => io::Error::from_raw_os_error(code),
+        None => io::Error::new(e.kind(), e.to_string()),
+    }
+}
+
+impl File {
+    /// Read the byte ranges of the file, e.g., the column chunks of a
+    /// columnar file, with the default [`ReadRangesOptions`]. See
+    /// [`File::read_ranges_with`].
+    ///
+    /// ```
+    /// use compio::fs::File;
+    ///
+    /// compio::task::block_on(async {
+    ///     let file = File::open("Cargo.toml").unwrap();
+    ///     let len = file.metadata().unwrap().len();
+    ///     let results = file.read_ranges(vec![0..7, len - 1..len + 10, 3..5]).await;
+    ///     assert_eq!(results[0].as_ref().unwrap(), b"[packag");
+    ///     // Shorter at the end of the file.
+    ///     assert_eq!(results[1].as_ref().unwrap(), b"\n");
+    ///     assert_eq!(results[2].as_ref().unwrap(), b"ck");
+    /// })
+    /// ```
+    pub async fn read_ranges(&self, ranges: Vec<Range<u64>>) -> Vec<io::Result<Vec<u8>>> {
+        self.read_ranges_with(ranges, &ReadRangesOptions::new())
+            .await
+    }
+
+    /// Read the byte ranges of the file, returning the data of each range in
+    /// the order of `ranges`.
+    ///
+    /// The reads are submitted together, so that the driver submits them in
+    /// one batch. The ranges close to each other are coalesced into one read
+    /// by the options. A range extending past the end of the file gives the
+    /// bytes before the end, and an empty range gives an empty buffer. If a
+    /// read fails, all ranges of it fail with the error.
+    pub async fn read_ranges_with(
+        &self,
+        ranges: Vec<Range<u64>>,
+        options: &ReadRangesOptions,
+    ) -> Vec<io::Result<Vec<u8>>> {
+        let mut results = ranges.iter().map(|_| Ok(vec![])).collect::<Vec<_>>();
+        let reads = futures_util::stream::iter(coalesce(&ranges, options))
+            .map(|read| async move {
+                let len = (read.range.end - read.range.start) as usize;
+                let buffer = Vec::with_capacity(len);
+                let (res, buffer) = self.read_full_at(buffer, read.range.start).await;
+                (read, res.map(|()| buffer))
+            })
+            .buffer_unordered(options.max_in_flight);
+        let mut reads = std::pin::pin!(reads);
+        while let Some((read, res)) = reads.next().await {
+            let mut buffer = match res {
+                Ok(buffer) => buffer,
+                Err(e) => {
+                    for &i in &read.members {
+                        results[i] = Err(copy_error(&e));
+                    }
+                    continue;
+                }
+            };
+            if let [i] = read.members[..] {
+                // Not coalesced, and the buffer is given as is.
+                buffer.truncate((ranges[i].end - ranges[i].start) as usize);
+                results[i] = Ok(buffer);
+                continue;
+            }
+            for &i in &read.members {
+                let start = ((ranges[i].start - read.range.start) as usize).min(buffer.len());
+                let end = ((ranges[i].end - read.range.start) as usize).min(buffer.len());
+                results[i] = Ok(buffer[start..end].to_vec());
+            }
+        }
+        results
+    }
+
+    // Fill the buffer, or read until EOF.
+    async fn read_full_at(&self, mut buffer: Vec<u8>, pos: u64) -> (io::Result<()>, Vec<u8>) {
+        while buffer.len() < buffer.capacity() {
+            let offset = pos + buffer.len() as u64;
+            let res;
+            (res, buffer) = self.read_at(buffer, offset).await;
+            match res {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) => return (Err(e), buffer),
+            }
+        }
+        (Ok(()), buffer)
+    }
+}
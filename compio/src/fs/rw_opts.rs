@@ -0,0 +1,176 @@
+use std::io;
+
+/// A flag of a single positional read or write, see [`ReadAtOpts`] and
+/// [`WriteAtOpts`].
+///
+/// ## Platform specific
+/// * Linux: the `RWF_*` flags of `preadv2` and `pwritev2`, or the `rw_flags`
+///   of an io-uring read or write. A flag unknown to the kernel fails the
+///   operation with the error of the kernel, e.g., `EOPNOTSUPP`.
+/// * Others: unsupported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RwFlag {
+    /// Poll the device for the completion instead of waiting for an interrupt,
+    /// `RWF_HIPRI`. It only works on a file opened with `O_DIRECT` on a
+    /// device with polling queues.
+    HighPriority,
+    /// Fail with [`io::ErrorKind::WouldBlock`] instead of blocking, e.g., when
+    /// the data of a read is not in the page cache, `RWF_NOWAIT`. The error is
+    /// returned as is, and the operation is not retried.
+    NoWait,
+    /// Write the data as if the file is opened with `O_DSYNC`, `RWF_DSYNC`.
+    /// Write only.
+    DataSync,
+    /// Write the data and the metadata as if the file is opened with
+    /// `O_SYNC`, `RWF_SYNC`. Write only.
+    Sync,
+    /// Append the data to the end of the file, ignoring the offset,
+    /// `RWF_APPEND`. Write only.
+    Append,
+    /// Drop the pages from the page cache after the operation,
+    /// `RWF_DONTCACHE`. It requires Linux 6.14.
+    DontCache,
+}
+
+impl RwFlag {
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+
+    fn is_write_only(self) -> bool {
+        matches!(self, Self::DataSync | Self::Sync | Self::Append)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn to_raw(self) -> io::Result<libc::c_int> {
+        Ok(match self {
+            Self::HighPriority => libc::RWF_HIPRI,
+            Self::NoWait => libc::RWF_NOWAIT,
+            Self::DataSync => libc::RWF_DSYNC,
+            Self::Sync => libc::RWF_SYNC,
+            Self::Append => libc::RWF_APPEND,
+            Self::DontCache => libc::RWF_DONTCACHE,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn to_raw(self) -> io::Result<i32> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{self:?} is not supported on this platform"),
+        ))
+    }
+}
+
+const ALL_FLAGS: [RwFlag; 6] = [
+    RwFlag::HighPriority,
+    RwFlag::NoWait,
+    RwFlag::DataSync,
+    RwFlag::Sync,
+    RwFlag::Append,
+    RwFlag::DontCache,
+];
+
+fn raw_flags(bits: u8, read: bool) -> io::Result<i32> {
+    let mut raw = 0;
+    for flag in ALL_FLAGS {
+        if bits & flag.bit() == 0 {
+            continue;
+        }
+        if read && flag.is_write_only() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{flag:?} is only valid for writes"),
+            ));
+        }
+        raw |= flag.to_raw()?;
+    }
+    Ok(raw)
+}
+
+/// The options of a positional read, see [`File::read_at_with`].
+///
+/// A flag not available on the platform fails the read with
+/// [`io::ErrorKind::Unsupported`], and a write only flag fails it with
+/// [`io::ErrorKind::InvalidInput`].
+///
+/// ```
+/// use compio::fs::{File, ReadAtOpts, RwFlag};
+///
+/// # compio::task::block_on(async {
+/// let file = File::open("Cargo.toml").unwrap();
+/// let opts = ReadAtOpts::new().flag(RwFlag::NoWait);
+/// let (res, buffer) = file.read_at_with(Vec::with_capacity(1024), 0, &opts).await;
+/// match res {
+///     Ok(_) => assert!(buffer.starts_with(b"[package]")),
+///     // Not in the page cache, or not supported.
+///     Err(_) => {}
+/// }
+/// # })
+/// ```
+///
+/// [`File::read_at_with`]: crate::fs::File::read_at_with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReadAtOpts {
+    flags: u8,
+}
+
+impl ReadAtOpts {
+    /// No flags, the same as [`File::read_at`].
+    ///
+    /// [`File::read_at`]: crate::fs::File::read_at
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a flag to the read.
+    pub fn flag(mut self, flag: RwFlag) -> Self {
+        self.flags |= flag.bit();
+        self
+    }
+
+    /// Check whether the flag is set.
+    pub fn has_flag(&self, flag: RwFlag) -> bool {
+        self.flags & flag.bit() != 0
+    }
+
+    pub(crate) fn raw_flags(&self) -> io::Result<i32> {
+        raw_flags(self.flags, true)
+    }
+}
+
+/// The options of a positional write, see [`File::write_at_with`].
+///
+/// A flag not available on the platform fails the write with
+/// [`io::ErrorKind::Unsupported`].
+///
+/// [`File::write_at_with`]: crate::fs::File::write_at_with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WriteAtOpts {
+    flags: u8,
+}
+
+impl WriteAtOpts {
+    /// No flags, the same as [`File::write_at`].
+    ///
+    /// [`File::write_at`]: crate::fs::File::write_at
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a flag to the write.
+    pub fn flag(mut self, flag: RwFlag) -> Self {
+        self.flags |= flag.bit();
+        self
+    }
+
+    /// Check whether the flag is set.
+    pub fn has_flag(&self, flag: RwFlag) -> bool {
+        self.flags & flag.bit() != 0
+    }
+
+    pub(crate) fn raw_flags(&self) -> io::Result<i32> {
+        raw_flags(self.flags, false)
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    hash::{BuildHasher, Hasher},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::fs::{File, OpenOptions};
+
+/// Creates an anonymous temporary file in [`std::env::temp_dir`].
+///
+/// See [`tempfile_in`].
+pub fn tempfile() -> io::Result<File> {
+    tempfile_in(std::env::temp_dir())
+}
+
+/// Creates an anonymous temporary file in `dir`, opened for reading and
+/// writing. It is deleted when closed, unless [`File::persist`] is called.
+///
+/// ## Platform specific
+///
+/// * Linux: the file is created with `O_TMPFILE`, and has no name. If the
+///   filesystem doesn't support it, the file is created as other Unix
+///   platforms.
+/// * Windows: the file has a hidden random name, and the attribute
+///   `FILE_ATTRIBUTE_TEMPORARY`. The delete disposition is set at once, which
+///   is like `FILE_FLAG_DELETE_ON_CLOSE`, but could be revoked.
+/// * Other Unix platforms: the file is created with a random name, and unlinked
+///   at once.
+///
+/// ```
+/// use compio::fs::tempfile;
+///
+/// compio::task::block_on(async {
+///     let file = tempfile().unwrap();
+///     file.write_all_at("hello", 0).await.0.unwrap();
+///     let (read, buffer) = file.read_to_end_at(Vec::with_capacity(5), 0).await;
+///     assert_eq!(read.unwrap(), 5);
+///     assert_eq!(buffer, b"hello");
+/// })
+/// ```
+pub fn tempfile_in(dir: impl AsRef<Path>) -> io::Result<File> {
+    tempfile_impl(dir.as_ref())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn tempfile_impl(dir: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut options = std::fs::OpenOptions::new();
+    options
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_TMPFILE)
+        .mode(0o600);
+    match File::with_options(dir, OpenOptions::from_std(options)) {
+        Ok(file) => Ok(file),
+        // The filesystem or the kernel doesn't support `O_TMPFILE`.
+        Err(e)
+            if matches!(
+                e.raw_os_error(),
+                Some(libc::EOPNOTSUPP | libc::EISDIR | libc::EINVAL)
+            ) =>
+        {
+            tempfile_unlinked(dir)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn tempfile_impl(dir: &Path) -> io::Result<File> {
+    tempfile_unlinked(dir)
+}
+
+// Like `mkstemp`, and the file is unlinked at once.
+#[cfg(unix)]
+fn tempfile_unlinked(dir: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    create_named(dir, |path| {
+        let mut options = std::fs::OpenOptions::new();
+        options.read(true).write(true).create_new(true).mode(0o600);
+        let file = File::with_options(path, OpenOptions::from_std(options))?;
+        std::fs::remove_file(path)?;
+        Ok(file)
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn tempfile_impl(dir: &Path) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    use windows_sys::Win32::{
+        Foundation::{GENERIC_READ, GENERIC_WRITE},
+        Storage::FileSystem::{DELETE, FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_TEMPORARY},
+    };
+
+    create_named(dir, |path| {
+        let mut options = std::fs::OpenOptions::new();
+        options
+            .access_mode(GENERIC_READ | GENERIC_WRITE | DELETE)
+            .create_new(true)
+            .attributes(FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_TEMPORARY);
+        let file = File::with_options(path, OpenOptions::from_std(options))?;
+        set_delete_disposition(&file, true)?;
+        Ok(file)
+    })
+}
+
+// Try the random names until the file is created.
+pub(crate) fn create_named(dir: &Path, mut create: impl FnMut(&Path) -> io::Result<File>) -> io::Result<File> {
+    const RETRIES: usize = 1 << 16;
+
+    for _ in 0..RETRIES {
+        let path = dir.join(random_name());
+        match create(&path) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            res => return res,
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "too many temporary files exist",
+    ))
+}
+
+fn random_name() -> PathBuf {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    format!(".tmp{:016x}", hasher.finish()).into()
+}
+
+#[cfg(target_os = "windows")]
+fn set_delete_disposition(file: &File, delete: bool) -> io::Result<()> {
+    use windows_sys::Win32::Storage::FileSystem::{
+        FileDispositionInfo, SetFileInformationByHandle, FILE_DISPOSITION_INFO,
+    };
+
+    use crate::{driver::AsRawFd, syscall};
+
+    let info = FILE_DISPOSITION_INFO {
+        DeleteFile: delete as _,
+    };
+    syscall!(
+        BOOL,
+        SetFileInformationByHandle(
+            file.as_raw_fd() as _,
+            FileDispositionInfo,
+            &info as *const _ as _,
+            std::mem::size_of::<FILE_DISPOSITION_INFO>() as _
+        )
+    )?;
+    Ok(())
+}
+
+impl File {
+    /// Gives the file created by [`tempfile_in`] a name, and it is not deleted
+    /// when closed. It fails with [`io::ErrorKind::AlreadyExists`] if `path`
+    /// exists.
+    ///
+    /// ## Platform specific
+    ///
+    /// * Linux: the file is linked to `path` with `linkat`, atomically.
+    /// * Windows: the file is renamed to `path` by the handle.
+    /// * Other Unix platforms: the unlinked file couldn't be linked again, and
+    ///   it fails with [`io::ErrorKind::Unsupported`].
+    pub fn persist(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.persist_impl(path.as_ref())
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn persist_impl(&self, path: &Path) -> io::Result<()> {
+        use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+        use crate::{driver::AsRawFd, syscall};
+
+        let fd = self.as_raw_fd();
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        // `AT_EMPTY_PATH` requires `CAP_DAC_READ_SEARCH`, so the procfs is
+        // tried first.
+        let proc_path = CString::new(format!("/proc/self/fd/{fd}"))?;
+        let res = syscall!(linkat(
+            libc::AT_FDCWD,
+            proc_path.as_ptr(),
+            libc::AT_FDCWD,
+            path.as_ptr(),
+            libc::AT_SYMLINK_FOLLOW
+        ));
+        match res {
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !Path::new("/proc/self").exists() => {
+                syscall!(linkat(
+                    fd,
+                    c"".as_ptr(),
+                    libc::AT_FDCWD,
+                    path.as_ptr(),
+                    libc::AT_EMPTY_PATH
+                ))?;
+            }
+            res => {
+                res?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    fn persist_impl(&self, path: &Path) -> io::Result<()> {
+        use std::os::windows::ffi::OsStrExt;
+
+        use windows_sys::Win32::Storage::FileSystem::{
+            FileRenameInfo, SetFileInformationByHandle, FILE_RENAME_INFO,
+        };
+
+        use crate::{driver::AsRawFd, syscall};
+
+        // The file with the delete disposition couldn't be renamed.
+        set_delete_disposition(self, false)?;
+
+        let path = std::path::absolute(path)?;
+        let name = path.as_os_str().encode_wide().collect::<Vec<_>>();
+        let name_size = std::mem::size_of_val(name.as_slice());
+        // `FILE_RENAME_INFO` ends with a variable-sized array of the name.
+        let size = std::mem::size_of::<FILE_RENAME_INFO>() + name_size;
+        let mut buffer = vec![0u64; size.div_ceil(8)];
+        let info = buffer.as_mut_ptr() as *mut FILE_RENAME_INFO;
+        let res = unsafe {
+            (*info).Anonymous.ReplaceIfExists = 0;
+            (*info).RootDirectory = 0;
+            (*info).FileNameLength = name_size as _;
+            std::ptr::copy_nonoverlapping(
+                name.as_ptr(),
+                (*info).FileName.as_mut_ptr(),
+                name.len(),
+            );
+            syscall!(
+                BOOL,
+                SetFileInformationByHandle(
+                    self.as_raw_fd() as _,
+                    FileRenameInfo,
+                    info as _,
+                    size as _
+                )
+            )
+        };
+        if let Err(e) = res {
+            set_delete_disposition(self, true).ok();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    #[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+    fn persist_impl(&self, _path: &Path) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the unlinked file couldn't be linked again",
+        ))
+    }
+}
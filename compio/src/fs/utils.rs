@@ -0,0 +1,199 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    buf::IoBuf,
+    driver::{AsRawFd, RawFd},
+    fs::{create_named, File, OpenOptions},
+    op::BlockingOp,
+    task::submit,
+};
+
+/// Read the entire contents of a file into a bytes vector.
+///
+/// ```
+/// compio::task::block_on(async {
+///     let data = compio::fs::read("Cargo.toml").await.unwrap();
+///     assert!(data.starts_with(b"[package]"));
+/// })
+/// ```
+pub async fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let file = File::open(path)?;
+    // The size is only a hint, and the file may grow.
+    let len = file
+        .metadata()
+        .map(|m| m.len() as usize)
+        .unwrap_or_default();
+    let (res, buffer) = file.read_to_end_at(Vec::with_capacity(len), 0).await;
+    res?;
+    Ok(buffer)
+}
+
+/// Read the entire contents of a file into a string. It fails with
+/// [`io::ErrorKind::InvalidData`] if the contents are not valid UTF-8.
+pub async fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    let data = read(path).await?;
+    String::from_utf8(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Write the buffer as the entire contents of a file. The file is created if
+/// it doesn't exist, and truncated if it does.
+///
+/// The file is truncated before writing, so a crash may leave it partially
+/// written. See [`write_atomic`] for the crash-safe version.
+pub async fn write<T: IoBuf>(path: impl AsRef<Path>, contents: T) -> io::Result<()> {
+    let file = File::create(path)?;
+    file.write_all_at(contents, 0).await.0?;
+    Ok(())
+}
+
+/// Write the buffer as the entire contents of a file atomically. After a
+/// crash, the file at `path` has either the old contents or the new ones,
+/// and is never partially written.
+///
+/// The contents are written to a temporary file with a random name in the
+/// same directory, and synced to the disk. Then it is renamed to `path`, and
+/// the directory is synced. If `path` exists, its permissions are copied to
+/// the new file. If `path` is a symlink, the link itself is replaced.
+///
+/// The temporary file is removed on failure, or when the future is dropped
+/// before the rename.
+///
+/// ## Platform specific
+/// * Unix: the directory is synced with `fsync` after the rename.
+/// * Windows: the file is renamed with `MoveFileExW`, and
+///   `MOVEFILE_WRITE_THROUGH` waits until the rename is flushed to the disk.
+///
+/// ```
+/// compio::task::block_on(async {
+///     let dir = std::env::temp_dir().join("compio-write-atomic-doc");
+///     std::fs::create_dir_all(&dir).unwrap();
+///     let path = dir.join("config.toml");
+///
+///     compio::fs::write_atomic(&path, "answer = 42").await.unwrap();
+///     let config = compio::fs::read_to_string(&path).await.unwrap();
+///     assert_eq!(config, "answer = 42");
+///     std::fs::remove_dir_all(&dir).unwrap();
+/// })
+/// ```
+pub async fn write_atomic<T: IoBuf>(path: impl AsRef<Path>, contents: T) -> io::Result<()> {
+    let path = path.as_ref();
+    if path.file_name().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the path should name a file",
+        ));
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let permissions = match std::fs::metadata(path) {
+        Ok(metadata) => Some(metadata.permissions()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    // `O_TMPFILE` is not used, because the file couldn't be linked to replace
+    // an existing one.
+    let mut temp_path = PathBuf::new();
+    let file = create_named(dir, |p| {
+        let file = OpenOptions::new().write(true).create_new(true).open(p)?;
+        temp_path = p.to_path_buf();
+        Ok(file)
+    })?;
+    let temp = RemoveOnDrop(Some(temp_path));
+
+    file.write_all_at(contents, 0).await.0?;
+    if let Some(permissions) = permissions {
+        file.set_permissions(permissions)?;
+    }
+    file.sync_all().await?;
+    rename(
+        file.as_raw_fd(),
+        temp.path().to_path_buf(),
+        path.to_path_buf(),
+    )
+    .await?;
+    temp.disarm();
+    sync_dir(dir).await
+}
+
+// Remove the temporary file unless it is renamed.
+struct RemoveOnDrop(Option<PathBuf>);
+
+impl RemoveOnDrop {
+    fn path(&self) -> &Path {
+        self.0
+            .as_deref()
+            .expect("the path should exist before disarmed")
+    }
+
+    fn disarm(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        if let Some(path) = &self.0 {
+            std::fs::remove_file(path).ok();
+        }
+    }
+}
+
+// Rename on the blocking thread pool. The fd is not used.
+#[cfg(unix)]
+async fn rename(fd: RawFd, from: PathBuf, to: PathBuf) -> io::Result<()> {
+    let op = BlockingOp::new(fd, move |_| {
+        std::fs::rename(&from, &to)?;
+        Ok(0)
+    });
+    submit(op).await.0?;
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn rename(fd: RawFd, from: PathBuf, to: PathBuf) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+
+    use windows_sys::Win32::Storage::FileSystem::{
+        MoveFileExW, MOVEFILE_REPLACE_EXISTING, MOVEFILE_WRITE_THROUGH,
+    };
+
+    use crate::syscall;
+
+    let wide = |path: &Path| {
+        path.as_os_str()
+            .encode_wide()
+            .chain(Some(0))
+            .collect::<Vec<_>>()
+    };
+    let (from, to) = (wide(&from), wide(&to));
+    let op = BlockingOp::new(fd, move |_| {
+        syscall!(
+            BOOL,
+            MoveFileExW(
+                from.as_ptr(),
+                to.as_ptr(),
+                MOVEFILE_REPLACE_EXISTING | MOVEFILE_WRITE_THROUGH
+            )
+        )?;
+        Ok(0)
+    });
+    submit(op).await.0?;
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all().await
+}
+
+#[cfg(windows)]
+async fn sync_dir(_dir: &Path) -> io::Result<()> {
+    // The rename is written through.
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use std::{error::Error, fmt::Display, io};
+
+use crate::{
+    buf::{IntoInner, IoBuf},
+    buf_try,
+    driver::AsRawFd,
+    fs::File,
+    op::WriteVectoredAt,
+    task::submit,
+    BufResult,
+};
+
+/// Error returned by [`File::write_vectored_all_at`], with the count of the
+/// bytes written before the error.
+#[derive(Debug)]
+pub struct PartialWriteError {
+    written: usize,
+    error: io::Error,
+}
+
+impl PartialWriteError {
+    /// The count of the bytes written from the start of the buffers. They are
+    /// requested to be written at the position, but are not synced.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// The error of the failed write.
+    pub fn error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Get the error of the failed write.
+    pub fn into_error(self) -> io::Error {
+        self.error
+    }
+}
+
+impl Display for PartialWriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} after {} bytes written", self.error, self.written)
+    }
+}
+
+impl Error for PartialWriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<PartialWriteError> for io::Error {
+    fn from(e: PartialWriteError) -> Self {
+        e.error
+    }
+}
+
+impl File {
+    /// Like [`write_at`], except that it writes from the buffers in order.
+    ///
+    /// The count of the buffers written in one call is limited by the
+    /// platform, and the rest is left to the next call, like a short write.
+    ///
+    /// ## Platform specific
+    /// * Windows: only the first non-empty buffer is written.
+    ///
+    /// [`write_at`]: File::write_at
+    pub async fn write_vectored_at<T: IoBuf>(
+        &self,
+        buffer: Vec<T>,
+        pos: u64,
+    ) -> BufResult<usize, Vec<T>> {
+        let ((), buffer) = buf_try!(self.attach(), buffer);
+        let op = WriteVectoredAt::new(self.as_raw_fd(), pos, buffer);
+        submit(op).await.into_inner().into_inner()
+    }
+
+    /// Attempts to write all the buffers in order, returning the count of the
+    /// bytes written.
+    ///
+    /// After a short write, the next write resumes from the first buffer not
+    /// fully written, which is sliced by the written bytes, at the advanced
+    /// position. The buffers are returned either way, and on error, the count
+    /// of the bytes written before is carried by [`PartialWriteError`].
+    ///
+    /// ```
+    /// use compio::fs::tempfile;
+    ///
+    /// compio::task::block_on(async {
+    ///     let file = tempfile().unwrap();
+    ///     let (res, _) = file
+    ///         .write_vectored_all_at(vec!["hello", " ", "world"], 0)
+    ///         .await;
+    ///     assert_eq!(res.unwrap(), 11);
+    ///     let (res, buffer) = file.read_to_end_at(Vec::with_capacity(64), 0).await;
+    ///     res.unwrap();
+    ///     assert_eq!(buffer, b"hello world");
+    /// })
+    /// ```
+    pub async fn write_vectored_all_at<T: IoBuf>(
+        &self,
+        buffer: Vec<T>,
+        pos: u64,
+    ) -> (Result<usize, PartialWriteError>, Vec<T>) {
+        let mut buffers = buffer.into_iter().map(Some).collect::<Vec<_>>();
+        let len = |buffer: &Option<T>| buffer.as_ref().map_or(0, IoBuf::buf_len);
+        let mut written = 0;
+        // The first buffer not fully written, and the bytes written of it.
+        let (mut next, mut begin) = (0, 0);
+        let error = loop {
+            while next < buffers.len() && begin >= len(&buffers[next]) {
+                next += 1;
+                begin = 0;
+            }
+            if next == buffers.len() {
+                break None;
+            }
+
+            // The empty buffers are not given to the op, as they couldn't be
+            // sliced without capacity.
+            let mut indices = vec![];
+            let mut slices = vec![];
+            for (i, buffer) in buffers.iter_mut().enumerate().skip(next) {
+                if len(buffer) > 0 {
+                    let start = if i == next { begin } else { 0 };
+                    indices.push(i);
+                    slices.push(buffer.take().unwrap().slice(start..));
+                }
+            }
+            let (res, slices) = self.write_vectored_at(slices, pos + written as u64).await;
+            for (i, slice) in indices.into_iter().zip(slices) {
+                buffers[i] = Some(slice.into_inner());
+            }
+
+            let mut advanced = match res {
+                Ok(0) => break Some(io::Error::from(io::ErrorKind::WriteZero)),
+                Ok(n) => n,
+                Err(e) => break Some(e),
+            };
+            written += advanced;
+            while advanced > 0 {
+                let rest = len(&buffers[next]) - begin;
+                if advanced >= rest {
+                    advanced -= rest;
+                    next += 1;
+                    begin = 0;
+                } else {
+                    begin += advanced;
+                    advanced = 0;
+                }
+            }
+        };
+        let buffers = buffers.into_iter().map(Option::unwrap).collect();
+        match error {
+            None => (Ok(written), buffers),
+            Some(error) => (Err(PartialWriteError { written, error }), buffers),
+        }
+    }
+}
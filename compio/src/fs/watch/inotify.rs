@@ -0,0 +1,188 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    ffi::{CString, OsStr},
+    io,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+};
+
+use super::WatchEvent;
+use crate::{
+    buf::IntoInner,
+    op::{BufResultExt, Recv},
+    syscall,
+    task::submit,
+};
+
+const MASK: u32 = libc::IN_CREATE
+    | libc::IN_MODIFY
+    | libc::IN_ATTRIB
+    | libc::IN_DELETE
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVE_SELF;
+
+const BUFFER_LEN: usize = 4096;
+
+pub struct Watcher {
+    fd: OwnedFd,
+    recursive: bool,
+    root: i32,
+    watches: HashMap<i32, PathBuf>,
+    events: VecDeque<WatchEvent>,
+    buffer: Option<Vec<u8>>,
+}
+
+impl Watcher {
+    pub fn new(path: &Path, recursive: bool) -> io::Result<Self> {
+        let fd = syscall!(inotify_init1(libc::IN_CLOEXEC))?;
+        let mut this = Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            recursive,
+            root: -1,
+            watches: HashMap::new(),
+            events: VecDeque::new(),
+            buffer: Some(Vec::with_capacity(BUFFER_LEN)),
+        };
+        this.root = this.add_watch(path)?;
+        if recursive && path.is_dir() {
+            this.add_subdirs(path, false);
+        }
+        Ok(this)
+    }
+
+    fn add_watch(&mut self, path: &Path) -> io::Result<i32> {
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        let wd = syscall!(inotify_add_watch(
+            self.fd.as_raw_fd(),
+            c_path.as_ptr(),
+            MASK
+        ))?;
+        self.watches.insert(wd, path.to_path_buf());
+        Ok(wd)
+    }
+
+    // Watch the subdirectories. If `report` is `true`, the existing entries are
+    // reported as created, because they may be created before watching.
+    fn add_subdirs(&mut self, path: &Path, report: bool) {
+        let Ok(entries) = std::fs::read_dir(path) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if report {
+                self.events.push_back(WatchEvent::Created(path.clone()));
+            }
+            if entry.file_type().map(|ty| ty.is_dir()).unwrap_or_default() {
+                // The directory may be removed already.
+                if self.add_watch(&path).is_ok() {
+                    self.add_subdirs(&path, report);
+                }
+            }
+        }
+    }
+
+    fn rename_watches(&mut self, from: &Path, to: &Path) {
+        for path in self.watches.values_mut() {
+            if let Ok(suffix) = path.strip_prefix(from) {
+                *path = to.join(suffix);
+            }
+        }
+    }
+
+    fn parse(&mut self, buffer: &[u8]) {
+        let mut moved_from: Option<(u32, PathBuf)> = None;
+        let mut offset = 0;
+        while offset + std::mem::size_of::<libc::inotify_event>() <= buffer.len() {
+            let event = unsafe {
+                std::ptr::read_unaligned(buffer[offset..].as_ptr().cast::<libc::inotify_event>())
+            };
+            let name_start = offset + std::mem::size_of::<libc::inotify_event>();
+            let name = &buffer[name_start..name_start + event.len as usize];
+            offset = name_start + event.len as usize;
+
+            if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                self.events.push_back(WatchEvent::Rescan);
+                continue;
+            }
+            let Some(base) = self.watches.get(&event.wd) else {
+                continue;
+            };
+            // The name is padded with NUL.
+            let name = name.split(|c| *c == 0).next().unwrap_or_default();
+            let path = if name.is_empty() {
+                base.clone()
+            } else {
+                base.join(OsStr::from_bytes(name))
+            };
+
+            if event.mask & libc::IN_MOVED_TO == 0 {
+                if let Some((_, from)) = moved_from.take() {
+                    self.events.push_back(WatchEvent::Removed(from));
+                }
+            }
+            let is_dir = event.mask & libc::IN_ISDIR != 0;
+            if event.mask & libc::IN_IGNORED != 0 {
+                self.watches.remove(&event.wd);
+            } else if event.mask & libc::IN_CREATE != 0 {
+                self.events.push_back(WatchEvent::Created(path.clone()));
+                if self.recursive && is_dir && self.add_watch(&path).is_ok() {
+                    self.add_subdirs(&path, true);
+                }
+            } else if event.mask & (libc::IN_MODIFY | libc::IN_ATTRIB) != 0 {
+                self.events.push_back(WatchEvent::Modified(path));
+            } else if event.mask & libc::IN_DELETE != 0 {
+                self.events.push_back(WatchEvent::Removed(path));
+            } else if event.mask & libc::IN_MOVED_FROM != 0 {
+                moved_from = Some((event.cookie, path));
+            } else if event.mask & libc::IN_MOVED_TO != 0 {
+                match moved_from.take() {
+                    Some((cookie, from)) if cookie == event.cookie => {
+                        if self.recursive && is_dir {
+                            self.rename_watches(&from, &path);
+                        }
+                        self.events
+                            .push_back(WatchEvent::Renamed { from, to: path });
+                    }
+                    other => {
+                        if let Some((_, from)) = other {
+                            self.events.push_back(WatchEvent::Removed(from));
+                        }
+                        self.events.push_back(WatchEvent::Created(path.clone()));
+                        if self.recursive && is_dir && self.add_watch(&path).is_ok() {
+                            self.add_subdirs(&path, true);
+                        }
+                    }
+                }
+            } else if event.mask & (libc::IN_DELETE_SELF | libc::IN_MOVE_SELF) != 0
+                && event.wd == self.root
+            {
+                // The others are reported by their parents.
+                self.events.push_back(WatchEvent::Removed(path));
+            }
+        }
+        if let Some((_, from)) = moved_from {
+            self.events.push_back(WatchEvent::Removed(from));
+        }
+    }
+
+    pub async fn next_event(&mut self) -> io::Result<WatchEvent> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+            let mut buffer = self.buffer.take().unwrap_or_default();
+            buffer.clear();
+            // Recv uses readv, which doesn't need an offset.
+            let op = Recv::new(self.fd.as_raw_fd(), buffer);
+            let (res, buffer) = submit(op).await.into_inner().map_advanced().into_inner();
+            self.parse(&buffer);
+            self.buffer = Some(buffer);
+            res?;
+        }
+    }
+}
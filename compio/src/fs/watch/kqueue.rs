@@ -0,0 +1,259 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    ffi::{CString, OsString},
+    io,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+    pin::Pin,
+    ptr::{null, null_mut},
+    task::Poll,
+};
+
+use polling::Event;
+
+use super::WatchEvent;
+use crate::{
+    driver::{Decision, OpCode},
+    syscall,
+    task::submit,
+};
+
+const NOTE: u32 = libc::NOTE_WRITE
+    | libc::NOTE_EXTEND
+    | libc::NOTE_ATTRIB
+    | libc::NOTE_DELETE
+    | libc::NOTE_RENAME;
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+const OPEN_FLAGS: libc::c_int = libc::O_EVTONLY | libc::O_CLOEXEC;
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+const OPEN_FLAGS: libc::c_int = libc::O_RDONLY | libc::O_CLOEXEC;
+
+const EVENTS_LEN: usize = 64;
+
+// Fetch the ready events of the kqueue. The kqueue fd is readable when there
+// are pending events.
+struct ReadEvents {
+    kq: RawFd,
+    events: Vec<libc::kevent>,
+}
+
+impl ReadEvents {
+    fn read(&mut self) -> io::Result<usize> {
+        let timeout = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        let len = syscall!(kevent(
+            self.kq,
+            null(),
+            0,
+            self.events.as_mut_ptr(),
+            self.events.capacity() as _,
+            &timeout
+        ))? as usize;
+        unsafe { self.events.set_len(len) };
+        Ok(len)
+    }
+}
+
+impl OpCode for ReadEvents {
+    fn pre_submit(mut self: Pin<&mut Self>) -> io::Result<Decision> {
+        match self.read()? {
+            0 => Ok(Decision::wait_readable(self.kq)),
+            len => Ok(Decision::Completed(len)),
+        }
+    }
+
+    fn on_event(mut self: Pin<&mut Self>, event: &Event) -> Poll<io::Result<usize>> {
+        debug_assert!(event.readable);
+
+        match self.read() {
+            Ok(0) => Poll::Pending,
+            res => Poll::Ready(res),
+        }
+    }
+}
+
+struct Watch {
+    // Closing the fd removes the registered event.
+    _fd: OwnedFd,
+    path: PathBuf,
+    // The names of the entries, if it is a directory.
+    children: Option<HashSet<OsString>>,
+}
+
+pub struct Watcher {
+    kq: OwnedFd,
+    recursive: bool,
+    // The fds may be reused, so the watches are identified by the udata.
+    next_id: usize,
+    watches: HashMap<usize, Watch>,
+    events: VecDeque<WatchEvent>,
+    buffer: Option<Vec<libc::kevent>>,
+}
+
+impl Watcher {
+    pub fn new(path: &Path, recursive: bool) -> io::Result<Self> {
+        let kq = syscall!(kqueue())?;
+        let kq = unsafe { OwnedFd::from_raw_fd(kq) };
+        syscall!(fcntl(kq.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC))?;
+        let mut this = Self {
+            kq,
+            recursive,
+            next_id: 0,
+            watches: HashMap::new(),
+            events: VecDeque::new(),
+            buffer: Some(Vec::with_capacity(EVENTS_LEN)),
+        };
+        this.add_watch(path, true, false)?;
+        Ok(this)
+    }
+
+    // Watch the path. The entries of a directory are also watched, and the
+    // subdirectories only if `recursive`. If `report` is `true`, the existing
+    // entries are reported as created, because they may be created before
+    // watching.
+    fn add_watch(&mut self, path: &Path, is_root: bool, report: bool) -> io::Result<()> {
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        let fd = syscall!(open(c_path.as_ptr(), OPEN_FLAGS))?;
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut event: libc::kevent = unsafe { std::mem::zeroed() };
+        event.ident = fd.as_raw_fd() as _;
+        event.filter = libc::EVFILT_VNODE as _;
+        event.flags = (libc::EV_ADD | libc::EV_ENABLE | libc::EV_CLEAR) as _;
+        event.fflags = NOTE as _;
+        event.udata = self.next_id as _;
+        syscall!(kevent(
+            self.kq.as_raw_fd(),
+            &event,
+            1,
+            null_mut(),
+            0,
+            null()
+        ))?;
+
+        let is_dir = std::fs::metadata(path)?.is_dir();
+        let children = if is_dir && (is_root || self.recursive) {
+            Some(HashSet::new())
+        } else {
+            None
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        self.watches.insert(
+            id,
+            Watch {
+                _fd: fd,
+                path: path.to_path_buf(),
+                children,
+            },
+        );
+        self.scan(id, report);
+        Ok(())
+    }
+
+    fn remove_watches(&mut self, path: &Path) {
+        self.watches
+            .retain(|_, watch| !watch.path.starts_with(path));
+    }
+
+    // Compare the entries of a directory with the known ones.
+    fn scan(&mut self, id: usize, report: bool) {
+        let Some(Watch {
+            path,
+            children: Some(children),
+            ..
+        }) = self.watches.get(&id)
+        else {
+            return;
+        };
+        let Ok(entries) = std::fs::read_dir(path) else {
+            return;
+        };
+        let path = path.clone();
+        let current = entries
+            .flatten()
+            .map(|entry| entry.file_name())
+            .collect::<HashSet<_>>();
+        let removed = children.difference(&current).cloned().collect::<Vec<_>>();
+        let created = current.difference(children).cloned().collect::<Vec<_>>();
+
+        for name in removed {
+            let child = path.join(name);
+            self.remove_watches(&child);
+            self.events.push_back(WatchEvent::Removed(child));
+        }
+        for name in created {
+            let child = path.join(&name);
+            if report {
+                self.events.push_back(WatchEvent::Created(child.clone()));
+            }
+            match self.add_watch(&child, false, report) {
+                Ok(()) => {}
+                // The entry may be removed already.
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                // Probably running out of fds, and the changes of the entry are lost.
+                Err(_) => self.events.push_back(WatchEvent::Rescan),
+            }
+        }
+        if let Some(Watch {
+            children: Some(children),
+            ..
+        }) = self.watches.get_mut(&id)
+        {
+            *children = current;
+        }
+    }
+
+    fn parse(&mut self, events: &[libc::kevent]) {
+        for event in events {
+            let id = event.udata as usize;
+            let Some(watch) = self.watches.get(&id) else {
+                continue;
+            };
+            let flags = event.fflags as u32;
+            if flags & (libc::NOTE_DELETE | libc::NOTE_RENAME) != 0 {
+                let path = watch.path.clone();
+                // The root is the first watch. The others are reported by their
+                // parents.
+                if id == 0 {
+                    self.events.push_back(WatchEvent::Removed(path.clone()));
+                }
+                self.remove_watches(&path);
+            } else if watch.children.is_some() {
+                if flags & libc::NOTE_WRITE != 0 {
+                    self.scan(id, true);
+                } else if flags & libc::NOTE_ATTRIB != 0 {
+                    self.events
+                        .push_back(WatchEvent::Modified(watch.path.clone()));
+                }
+            } else if flags & (libc::NOTE_WRITE | libc::NOTE_EXTEND | libc::NOTE_ATTRIB) != 0 {
+                self.events
+                    .push_back(WatchEvent::Modified(watch.path.clone()));
+            }
+        }
+    }
+
+    pub async fn next_event(&mut self) -> io::Result<WatchEvent> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+            let mut events = self.buffer.take().unwrap_or_default();
+            events.clear();
+            let op = ReadEvents {
+                kq: self.kq.as_raw_fd(),
+                events,
+            };
+            let (res, op) = submit(op).await;
+            self.parse(&op.events);
+            self.buffer = Some(op.events);
+            res?;
+        }
+    }
+}
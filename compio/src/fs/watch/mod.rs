@@ -0,0 +1,122 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_util::{FutureExt, Stream, future::LocalBoxFuture};
+
+cfg_if::cfg_if! {
+    if #[cfg(target_os = "windows")] {
+        mod windows;
+        use windows as sys;
+    } else if #[cfg(any(target_os = "linux", target_os = "android"))] {
+        mod inotify;
+        use inotify as sys;
+    } else if #[cfg(unix)] {
+        mod kqueue;
+        use kqueue as sys;
+    }
+}
+
+/// An event of the watched path, returned by [`WatchStream`].
+///
+/// The paths are joined with the watched path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// A file or directory is created.
+    Created(PathBuf),
+    /// The content or the metadata of a file or directory is modified.
+    Modified(PathBuf),
+    /// A file or directory is removed.
+    Removed(PathBuf),
+    /// A file or directory is renamed inside the watched path.
+    Renamed {
+        /// The old path.
+        from: PathBuf,
+        /// The new path.
+        to: PathBuf,
+    },
+    /// Some events are dropped because the queue overflowed. The watched path
+    /// should be scanned again.
+    Rescan,
+}
+
+/// Watch the changes of a file or directory. If `recursive` is `true`, the
+/// subdirectories are also watched.
+///
+/// ## Platform specific
+/// * Linux: it uses `inotify`. A directory renamed from outside of the watched
+///   path is reported as [`WatchEvent::Created`], and its contents are not
+///   reported.
+/// * Windows: it uses `ReadDirectoryChangesW`. If a file is watched, its
+///   parent directory is watched and the events are filtered.
+/// * macOS and BSD: it uses `kqueue`, which requires an open fd for each
+///   watched file and directory, so recursive watching of a big tree may run
+///   out of fds. The renames are reported as [`WatchEvent::Removed`] and
+///   [`WatchEvent::Created`].
+///
+/// ```
+/// use compio::fs::{watch, WatchEvent};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// compio::task::block_on(async {
+///     let mut stream = watch(dir.path(), false).unwrap();
+///     std::fs::write(dir.path().join("foo"), "bar").unwrap();
+///     let event = stream.next_event().await.unwrap();
+///     assert_eq!(event, WatchEvent::Created(dir.path().join("foo")));
+/// })
+/// ```
+pub fn watch(path: impl AsRef<Path>, recursive: bool) -> io::Result<WatchStream> {
+    Ok(WatchStream {
+        watcher: Some(sys::Watcher::new(path.as_ref(), recursive)?),
+        future: None,
+    })
+}
+
+/// A stream of [`WatchEvent`], created by [`watch`].
+pub struct WatchStream {
+    watcher: Option<sys::Watcher>,
+    #[allow(clippy::type_complexity)]
+    future: Option<LocalBoxFuture<'static, (sys::Watcher, io::Result<WatchEvent>)>>,
+}
+
+impl WatchStream {
+    /// Wait for the next event.
+    pub async fn next_event(&mut self) -> io::Result<WatchEvent> {
+        std::future::poll_fn(|cx| self.poll_event(cx)).await
+    }
+
+    fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<WatchEvent>> {
+        let mut future = match self.future.take() {
+            Some(future) => future,
+            None => {
+                let mut watcher = self.watcher.take().expect("the watcher should exist");
+                async move {
+                    let res = watcher.next_event().await;
+                    (watcher, res)
+                }
+                .boxed_local()
+            }
+        };
+        match future.poll_unpin(cx) {
+            Poll::Ready((watcher, res)) => {
+                self.watcher = Some(watcher);
+                Poll::Ready(res)
+            }
+            Poll::Pending => {
+                self.future = Some(future);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Stream for WatchStream {
+    type Item = io::Result<WatchEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_event(cx).map(Some)
+    }
+}
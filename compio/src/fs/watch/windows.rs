@@ -0,0 +1,182 @@
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    fs::File,
+    io,
+    os::windows::{ffi::OsStringExt, fs::OpenOptionsExt, io::AsRawHandle},
+    path::{Path, PathBuf},
+};
+
+use windows_sys::Win32::{
+    Foundation::ERROR_NOTIFY_ENUM_DIR,
+    Storage::FileSystem::{
+        FILE_ACTION_ADDED, FILE_ACTION_MODIFIED, FILE_ACTION_REMOVED, FILE_ACTION_RENAMED_NEW_NAME,
+        FILE_ACTION_RENAMED_OLD_NAME, FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OVERLAPPED,
+        FILE_LIST_DIRECTORY, FILE_NOTIFY_CHANGE_ATTRIBUTES, FILE_NOTIFY_CHANGE_CREATION,
+        FILE_NOTIFY_CHANGE_DIR_NAME, FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE,
+        FILE_NOTIFY_CHANGE_SIZE, FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE,
+    },
+};
+
+use super::WatchEvent;
+use crate::{
+    buf::IntoInner,
+    op::ReadDirectoryChanges,
+    task::{attach, submit},
+};
+
+const FILTER: u32 = FILE_NOTIFY_CHANGE_FILE_NAME
+    | FILE_NOTIFY_CHANGE_DIR_NAME
+    | FILE_NOTIFY_CHANGE_ATTRIBUTES
+    | FILE_NOTIFY_CHANGE_SIZE
+    | FILE_NOTIFY_CHANGE_LAST_WRITE
+    | FILE_NOTIFY_CHANGE_CREATION;
+
+const BUFFER_LEN: usize = 16384;
+
+pub struct Watcher {
+    dir: File,
+    root: PathBuf,
+    // Only report the events of this file, if a file is watched.
+    file: Option<PathBuf>,
+    recursive: bool,
+    events: VecDeque<WatchEvent>,
+    buffer: Option<Vec<u8>>,
+}
+
+impl Watcher {
+    pub fn new(path: &Path, recursive: bool) -> io::Result<Self> {
+        let (root, file) = if path.is_dir() {
+            (path.to_path_buf(), None)
+        } else {
+            let parent = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            let file = path
+                .file_name()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+            let file = parent.join(file);
+            (parent, Some(file))
+        };
+        let dir = std::fs::OpenOptions::new()
+            .access_mode(FILE_LIST_DIRECTORY)
+            .share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE)
+            .custom_flags(FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OVERLAPPED)
+            .open(&root)?;
+        attach(dir.as_raw_handle())?;
+        Ok(Self {
+            dir,
+            root,
+            recursive: recursive && file.is_none(),
+            file,
+            events: VecDeque::new(),
+            buffer: Some(Vec::with_capacity(BUFFER_LEN)),
+        })
+    }
+
+    fn push_event(&mut self, event: WatchEvent) {
+        if let Some(file) = &self.file {
+            let event = match event {
+                WatchEvent::Renamed { from, to } => {
+                    if &from == file {
+                        WatchEvent::Removed(from)
+                    } else if &to == file {
+                        WatchEvent::Created(to)
+                    } else {
+                        return;
+                    }
+                }
+                WatchEvent::Created(p) | WatchEvent::Modified(p) | WatchEvent::Removed(p)
+                    if &p != file =>
+                {
+                    return;
+                }
+                event => event,
+            };
+            self.events.push_back(event);
+        } else {
+            self.events.push_back(event);
+        }
+    }
+
+    fn parse(&mut self, buffer: &[u8]) {
+        // NextEntryOffset, Action and FileNameLength.
+        const HEADER_LEN: usize = std::mem::size_of::<u32>() * 3;
+        let read_u32 =
+            |offset: usize| u32::from_ne_bytes(buffer[offset..offset + 4].try_into().unwrap());
+        let mut renamed_from = None;
+        let mut offset = 0;
+        while offset + HEADER_LEN <= buffer.len() {
+            let next_entry_offset = read_u32(offset);
+            let action = read_u32(offset + 4);
+            let name_len = read_u32(offset + 8);
+            let name_start = offset + HEADER_LEN;
+            let name_end = (name_start + name_len as usize).min(buffer.len());
+            let name = buffer[name_start..name_end]
+                .chunks_exact(2)
+                .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+                .collect::<Vec<_>>();
+            let path = self.root.join(OsString::from_wide(&name));
+
+            if action != FILE_ACTION_RENAMED_NEW_NAME {
+                if let Some(from) = renamed_from.take() {
+                    self.push_event(WatchEvent::Removed(from));
+                }
+            }
+            match action {
+                FILE_ACTION_ADDED => self.push_event(WatchEvent::Created(path)),
+                FILE_ACTION_REMOVED => self.push_event(WatchEvent::Removed(path)),
+                FILE_ACTION_MODIFIED => self.push_event(WatchEvent::Modified(path)),
+                FILE_ACTION_RENAMED_OLD_NAME => renamed_from = Some(path),
+                FILE_ACTION_RENAMED_NEW_NAME => match renamed_from.take() {
+                    Some(from) => self.push_event(WatchEvent::Renamed { from, to: path }),
+                    None => self.push_event(WatchEvent::Created(path)),
+                },
+                _ => {}
+            }
+
+            if next_entry_offset == 0 {
+                break;
+            }
+            offset += next_entry_offset as usize;
+        }
+        if let Some(from) = renamed_from {
+            self.push_event(WatchEvent::Removed(from));
+        }
+    }
+
+    pub async fn next_event(&mut self) -> io::Result<WatchEvent> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+            let mut buffer = self.buffer.take().unwrap_or_default();
+            buffer.clear();
+            let op = ReadDirectoryChanges::new(
+                self.dir.as_raw_handle() as _,
+                buffer,
+                self.recursive,
+                FILTER,
+            );
+            let (res, op) = submit(op).await;
+            let mut buffer = op.into_inner().into_inner();
+            match res {
+                // Zero bytes means the changes overflowed the buffer.
+                Ok(0) => self.events.push_back(WatchEvent::Rescan),
+                Ok(len) => {
+                    unsafe { buffer.set_len(len) };
+                    self.parse(&buffer);
+                }
+                Err(e) if e.raw_os_error() == Some(ERROR_NOTIFY_ENUM_DIR as _) => {
+                    self.events.push_back(WatchEvent::Rescan)
+                }
+                Err(e) => {
+                    self.buffer = Some(buffer);
+                    return Err(e);
+                }
+            }
+            self.buffer = Some(buffer);
+        }
+    }
+}
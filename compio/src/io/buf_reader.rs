@@ -0,0 +1,217 @@
+use std::{future::Future, io};
+
+use crate::{
+    buf::{IoBuf, IoBufMut},
+    io::{AsyncRead, AsyncWrite},
+    BufResult,
+};
+
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// A reader which reads the stream in batches into a buffer, and serves the
+/// small reads and the line parsing from it.
+///
+/// The buffered data is lost if it is dropped, so use [`buffer`] to get the
+/// data read ahead, e.g., the body after the header of a protocol, before
+/// calling [`into_inner`].
+///
+/// [`buffer`]: BufReader::buffer
+/// [`into_inner`]: BufReader::into_inner
+///
+/// ```
+/// use compio::{
+///     io::{AsyncWrite, BufReader},
+///     net::{TcpListener, TcpStream},
+/// };
+///
+/// compio::task::block_on(async {
+///     let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+///     let addr = listener.local_addr().unwrap();
+///     let (mut client, (server, _)) =
+///         futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+///
+///     client.write_all("hello\r\nworld\n").await.0.unwrap();
+///     client.shutdown(std::net::Shutdown::Write).unwrap();
+///
+///     let mut reader = BufReader::new(server);
+///     let (res, line) = reader.read_line(String::new()).await;
+///     assert_eq!(res.unwrap(), 7);
+///     assert_eq!(line, "hello\r\n");
+///     let (res, line) = reader.read_line(String::new()).await;
+///     assert_eq!(res.unwrap(), 6);
+///     assert_eq!(line, "world\n");
+///     let (res, line) = reader.read_line(String::new()).await;
+///     assert_eq!(res.unwrap(), 0);
+///     assert!(line.is_empty());
+/// })
+/// ```
+pub struct BufReader<R> {
+    inner: R,
+    // It is `None` only when given to an op.
+    buf: Option<Vec<u8>>,
+    // The start of the bytes not consumed in the buffer.
+    pos: usize,
+    capacity: usize,
+}
+
+impl<R> BufReader<R> {
+    /// Create [`BufReader`] with 8KiB buffer.
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, inner)
+    }
+
+    /// Create [`BufReader`] with specified capacity of the buffer, which is
+    /// also the most bytes read from the stream at once.
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            inner,
+            buf: Some(Vec::with_capacity(capacity)),
+            pos: 0,
+            capacity,
+        }
+    }
+
+    /// The capacity of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The bytes read from the stream but not consumed yet.
+    pub fn buffer(&self) -> &[u8] {
+        self.buf.as_deref().map_or(&[], |buf| &buf[self.pos..])
+    }
+
+    /// Mark the first `amt` bytes of [`buffer`](Self::buffer) as consumed, so
+    /// that they are not returned again. It is clamped to the bytes buffered.
+    pub fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buf.as_ref().map_or(0, Vec::len));
+    }
+
+    /// Get a reference to the stream.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the stream.
+    ///
+    /// Reading from the stream directly skips the buffered data.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Consume the reader, returning the stream. The buffered data is dropped.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead> BufReader<R> {
+    /// Return the buffered data, reading from the stream if the buffer is
+    /// empty. An empty slice means EOF.
+    ///
+    /// The data is not consumed, call [`consume`](Self::consume) after using
+    /// it.
+    pub async fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.buffer().is_empty() {
+            let mut buf = self.buf.take().unwrap_or_default();
+            buf.clear();
+            buf.reserve_exact(self.capacity);
+            self.pos = 0;
+            let (res, buf) = self.inner.read(buf).await;
+            self.buf = Some(buf);
+            res?;
+        }
+        Ok(self.buffer())
+    }
+
+    /// Read the bytes until `byte` or EOF, and append them to `buf`, with
+    /// `byte` if found. Returns the bytes appended, and zero means EOF.
+    ///
+    /// The bytes read are kept in `buf` on failure.
+    pub async fn read_until(&mut self, byte: u8, mut buf: Vec<u8>) -> BufResult<usize, Vec<u8>> {
+        let mut read = 0;
+        loop {
+            let (done, used) = match self.fill_buf().await {
+                Ok(available) => match available.iter().position(|b| *b == byte) {
+                    Some(i) => {
+                        buf.extend_from_slice(&available[..=i]);
+                        (true, i + 1)
+                    }
+                    None => {
+                        buf.extend_from_slice(available);
+                        (available.is_empty(), available.len())
+                    }
+                },
+                Err(e) => return (Err(e), buf),
+            };
+            self.consume(used);
+            read += used;
+            if done {
+                return (Ok(read), buf);
+            }
+        }
+    }
+
+    /// Read a line with the trailing `\n` if any, and append it to `buf`.
+    /// Returns the bytes appended, and zero means EOF.
+    ///
+    /// It fails with [`io::ErrorKind::InvalidData`] if the line is not valid
+    /// UTF-8, and `buf` is left unchanged, but the line is consumed.
+    pub async fn read_line(&mut self, buf: String) -> BufResult<usize, String> {
+        let start = buf.len();
+        let (res, bytes) = self.read_until(b'\n', buf.into_bytes()).await;
+        match String::from_utf8(bytes) {
+            Ok(buf) => (res, buf),
+            Err(e) => {
+                let mut bytes = e.into_bytes();
+                bytes.truncate(start);
+                // SAFETY: the bytes before `start` are from a `String`.
+                let buf = unsafe { String::from_utf8_unchecked(bytes) };
+                let res = res.and(Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                )));
+                (res, buf)
+            }
+        }
+    }
+}
+
+impl<R: AsyncRead> AsyncRead for BufReader<R> {
+    async fn read<B: IoBufMut>(&mut self, mut buf: B) -> BufResult<usize, B> {
+        let len = buf.as_uninit_slice().len();
+        if len == 0 {
+            return (Ok(0), buf);
+        }
+        if self.buffer().is_empty() && len >= self.capacity {
+            // Bypass the buffer, which is empty.
+            return self.inner.read(buf).await;
+        }
+        let available = match self.fill_buf().await {
+            Ok(available) => available,
+            Err(e) => return (Err(e), buf),
+        };
+        let n = available.len().min(len);
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                available.as_ptr(),
+                buf.as_uninit_slice().as_mut_ptr().cast(),
+                n,
+            );
+            buf.set_buf_init(n);
+        }
+        self.consume(n);
+        (Ok(n), buf)
+    }
+}
+
+impl<R: AsyncWrite> AsyncWrite for BufReader<R> {
+    fn write<B: IoBuf>(&mut self, buf: B) -> impl Future<Output = BufResult<usize, B>> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> impl Future<Output = io::Result<()>> {
+        self.inner.flush()
+    }
+}
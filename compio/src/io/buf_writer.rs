@@ -0,0 +1,155 @@
+use std::{future::Future, io};
+
+use crate::{
+    buf::{IntoInner, IoBuf, IoBufMut},
+    io::{AsyncRead, AsyncWrite},
+    BufResult,
+};
+
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// A writer which buffers the small writes, and writes them to the stream in
+/// batches.
+///
+/// The capacity is the high-water mark of the buffer: a write which doesn't
+/// fit waits until the buffered data is written to the stream, so the memory
+/// is bounded when the peer stops reading. [`AsyncWrite::flush`] completes
+/// only when the buffer is empty and the last write to the stream completes.
+///
+/// The buffered data is lost if it is dropped or a write is cancelled, so
+/// remember to flush it.
+///
+/// ```
+/// use compio::{
+///     io::{AsyncRead, AsyncWrite, BufWriter},
+///     net::{TcpListener, TcpStream},
+/// };
+///
+/// compio::task::block_on(async {
+///     let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+///     let addr = listener.local_addr().unwrap();
+///     let (client, (mut server, _)) =
+///         futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+///
+///     let mut writer = BufWriter::new(client);
+///     writer.write_all("hello ").await.0.unwrap();
+///     writer.write_all("world").await.0.unwrap();
+///     assert_eq!(writer.buffer(), b"hello world");
+///     writer.flush().await.unwrap();
+///
+///     let (res, buf) = server.read_exact(Vec::with_capacity(11)).await;
+///     res.unwrap();
+///     assert_eq!(buf, b"hello world");
+/// })
+/// ```
+pub struct BufWriter<W> {
+    inner: W,
+    // It is `None` only when given to an op.
+    buf: Option<Vec<u8>>,
+    capacity: usize,
+}
+
+impl<W> BufWriter<W> {
+    /// Create [`BufWriter`] with 8KiB buffer.
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, inner)
+    }
+
+    /// Create [`BufWriter`] with specified capacity of the buffer, which is
+    /// also the high-water mark.
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            inner,
+            buf: Some(Vec::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// The capacity of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The bytes buffered but not written yet.
+    pub fn buffer(&self) -> &[u8] {
+        self.buf.as_deref().unwrap_or_default()
+    }
+
+    /// Get a reference to the stream.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the stream.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Consume the writer, returning the stream. The buffered data is dropped.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: AsyncWrite> BufWriter<W> {
+    // Write all buffered data to the stream. The data written is removed from
+    // the buffer even on failure.
+    async fn flush_buf(&mut self) -> io::Result<()> {
+        let mut buf = self.buf.take().unwrap_or_default();
+        let mut written = 0;
+        let mut res = Ok(());
+        while written < buf.len() {
+            let result;
+            (result, buf) = self.inner.write(buf.slice(written..)).await.into_inner();
+            match result {
+                Ok(0) => {
+                    res = Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write the buffered data",
+                    ));
+                    break;
+                }
+                Ok(n) => written += n,
+                Err(e) => {
+                    res = Err(e);
+                    break;
+                }
+            }
+        }
+        buf.drain(..written);
+        self.buf = Some(buf);
+        res
+    }
+}
+
+impl<W: AsyncWrite> AsyncWrite for BufWriter<W> {
+    async fn write<B: IoBuf>(&mut self, buf: B) -> BufResult<usize, B> {
+        let len = buf.buf_len();
+        if self.buffer().len() + len > self.capacity {
+            if let Err(e) = self.flush_buf().await {
+                return (Err(e), buf);
+            }
+        }
+        if len >= self.capacity {
+            // Bypass the buffer, which is empty now.
+            self.inner.write(buf).await
+        } else {
+            self.buf
+                .get_or_insert_with(Vec::new)
+                .extend_from_slice(buf.as_slice());
+            (Ok(len), buf)
+        }
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        self.flush_buf().await?;
+        self.inner.flush().await
+    }
+}
+
+impl<W: AsyncRead> AsyncRead for BufWriter<W> {
+    fn read<B: IoBufMut>(&mut self, buf: B) -> impl Future<Output = BufResult<usize, B>> {
+        self.inner.read(buf)
+    }
+}
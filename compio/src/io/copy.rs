@@ -0,0 +1,59 @@
+use std::io;
+
+use crate::io::{AsyncRead, AsyncWrite};
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Copy all data from `reader` to `writer` until EOF, and flush `writer`,
+/// returning the bytes copied.
+///
+/// The data is copied through a userspace buffer, so it works with any
+/// stream, e.g., a [`BufReader`](crate::io::BufReader) with the rest of a
+/// request. Between two sockets, prefer
+/// [`copy_bidirectional`](crate::net::copy_bidirectional), which moves the
+/// data in the kernel if possible.
+///
+/// The bytes copied before a failure are not reported.
+///
+/// ```
+/// use compio::{
+///     io::{copy, AsyncRead, AsyncWrite},
+///     net::{TcpListener, TcpStream},
+/// };
+///
+/// compio::task::block_on(async {
+///     let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+///     let addr = listener.local_addr().unwrap();
+///     let (mut client, (server, _)) =
+///         futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+///
+///     // Echo until the client shuts down its write side.
+///     client.write_all("hello").await.0.unwrap();
+///     client.shutdown(std::net::Shutdown::Write).unwrap();
+///     let (mut reader, mut writer) = (&server, &server);
+///     assert_eq!(copy(&mut reader, &mut writer).await.unwrap(), 5);
+///     let (res, echoed) = client.read_exact(Vec::with_capacity(5)).await;
+///     res.unwrap();
+///     assert_eq!(echoed, b"hello");
+/// })
+/// ```
+pub async fn copy<R: AsyncRead + ?Sized, W: AsyncWrite + ?Sized>(
+    reader: &mut R,
+    writer: &mut W,
+) -> io::Result<u64> {
+    let mut buf = Vec::with_capacity(BUFFER_SIZE);
+    let mut copied = 0;
+    loop {
+        let (res, read) = reader.read(buf).await;
+        buf = read;
+        if res? == 0 {
+            break;
+        }
+        let (res, written) = writer.write_all(buf).await;
+        buf = written;
+        copied += res? as u64;
+        buf.clear();
+    }
+    writer.flush().await?;
+    Ok(copied)
+}
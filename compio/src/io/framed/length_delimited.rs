@@ -0,0 +1,103 @@
+use std::io;
+
+use bytes::{Buf, BufMut, BytesMut};
+
+use super::{Decoder, Encoder};
+
+const HEADER_LEN: usize = std::mem::size_of::<u32>();
+
+/// A codec of the frames prefixed with their length, as a big-endian `u32`.
+#[derive(Debug, Clone, Copy)]
+pub struct LengthDelimitedCodec {
+    max_frame_len: usize,
+    // The length of the frame being decoded, if the header is consumed.
+    frame_len: Option<usize>,
+}
+
+impl LengthDelimitedCodec {
+    /// Create [`LengthDelimitedCodec`] with the max frame length of 8MiB.
+    pub fn new() -> Self {
+        Self::with_max_frame_len(8 * 1024 * 1024)
+    }
+
+    /// Create [`LengthDelimitedCodec`] with the specified max frame length.
+    /// The longer frames fail with [`io::ErrorKind::InvalidData`].
+    pub fn with_max_frame_len(max_frame_len: usize) -> Self {
+        Self {
+            max_frame_len,
+            frame_len: None,
+        }
+    }
+
+    /// The max frame length.
+    pub fn max_frame_len(&self) -> usize {
+        self.max_frame_len
+    }
+
+    fn check_len(&self, len: usize) -> io::Result<()> {
+        if len > self.max_frame_len || len > u32::MAX as usize {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "frame exceeds the max frame length",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for LengthDelimitedCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for LengthDelimitedCodec {
+    type Error = io::Error;
+    type Item = BytesMut;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        let len = match self.frame_len {
+            Some(len) => len,
+            None => {
+                if src.len() < HEADER_LEN {
+                    return Ok(None);
+                }
+                let len = src.get_u32() as usize;
+                self.check_len(len)?;
+                self.frame_len = Some(len);
+                len
+            }
+        };
+        if src.len() < len {
+            src.reserve(len - src.len());
+            return Ok(None);
+        }
+        self.frame_len = None;
+        Ok(Some(src.split_to(len)))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        match self.decode(src)? {
+            Some(frame) => Ok(Some(frame)),
+            None if src.is_empty() && self.frame_len.is_none() => Ok(None),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "bytes remaining on stream",
+            )),
+        }
+    }
+}
+
+impl<B: AsRef<[u8]>> Encoder<B> for LengthDelimitedCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: B, dst: &mut BytesMut) -> io::Result<()> {
+        let item = item.as_ref();
+        self.check_len(item.len())?;
+        dst.reserve(HEADER_LEN + item.len());
+        dst.put_u32(item.len() as u32);
+        dst.extend_from_slice(item);
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use std::io;
+
+use bytes::{Buf, BytesMut};
+
+use super::{Decoder, Encoder};
+
+/// A codec of the lines split by `\n`. The trailing `\r` is also removed when
+/// decoding.
+#[derive(Debug, Clone, Copy)]
+pub struct LinesCodec {
+    max_len: usize,
+    // The bytes before it are searched already.
+    next_index: usize,
+}
+
+impl LinesCodec {
+    /// Create [`LinesCodec`] without limits of the line length.
+    pub fn new() -> Self {
+        Self::with_max_len(usize::MAX)
+    }
+
+    /// Create [`LinesCodec`] with the specified max line length, excluding the
+    /// line ending. The longer lines fail with [`io::ErrorKind::InvalidData`].
+    pub fn with_max_len(max_len: usize) -> Self {
+        Self {
+            max_len,
+            next_index: 0,
+        }
+    }
+
+    /// The max line length.
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    fn check_len(&self, len: usize) -> io::Result<()> {
+        if len > self.max_len {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "line exceeds the max length",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for LinesCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn without_cr(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+fn to_string(line: &[u8]) -> io::Result<String> {
+    String::from_utf8(line.to_vec()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+impl Decoder for LinesCodec {
+    type Error = io::Error;
+    type Item = String;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<String>> {
+        match src[self.next_index..].iter().position(|b| *b == b'\n') {
+            Some(offset) => {
+                let end = self.next_index + offset;
+                self.next_index = 0;
+                let line = src.split_to(end);
+                src.advance(1);
+                let line = without_cr(&line);
+                self.check_len(line.len())?;
+                to_string(line).map(Some)
+            }
+            None => {
+                self.next_index = src.len();
+                // The `\r` may be the line ending.
+                self.check_len(without_cr(src).len())?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// The remaining bytes are decoded as the last line.
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<String>> {
+        match self.decode(src)? {
+            Some(line) => Ok(Some(line)),
+            None if src.is_empty() => Ok(None),
+            None => {
+                self.next_index = 0;
+                let line = src.split();
+                to_string(without_cr(&line)).map(Some)
+            }
+        }
+    }
+}
+
+impl<T: AsRef<str>> Encoder<T> for LinesCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> io::Result<()> {
+        let line = item.as_ref();
+        self.check_len(line.len())?;
+        dst.reserve(line.len() + 1);
+        dst.extend_from_slice(line.as_bytes());
+        dst.extend_from_slice(b"\n");
+        Ok(())
+    }
+}
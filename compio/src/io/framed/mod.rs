@@ -0,0 +1,306 @@
+//! Encode and decode frames over the streams.
+//!
+//! [`Framed`] owns a read buffer filled by [`AsyncRead`], and a write buffer
+//! flushed by [`AsyncWrite`]. The codecs work on the buffers directly, like
+//! the codecs of `tokio-util`.
+//!
+//! ```
+//! use compio::{
+//!     io::framed::{Framed, LinesCodec},
+//!     net::{TcpListener, TcpStream},
+//! };
+//!
+//! compio::task::block_on(async {
+//!     let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//!     let addr = listener.local_addr().unwrap();
+//!
+//!     let (client, (server, _)) =
+//!         futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+//!     let mut client = Framed::new(client, LinesCodec::new());
+//!     let mut server = Framed::new(server, LinesCodec::new());
+//!
+//!     client.send("hello").await.unwrap();
+//!     client.send("world").await.unwrap();
+//!     assert_eq!(server.next().await.unwrap().unwrap(), "hello");
+//!     assert_eq!(server.next().await.unwrap().unwrap(), "world");
+//! })
+//! ```
+
+mod length_delimited;
+pub use length_delimited::*;
+
+mod lines;
+pub use lines::*;
+
+mod udp;
+pub use udp::*;
+
+use std::io;
+
+use bytes::{Buf, BytesMut};
+
+use crate::io::{AsyncRead, AsyncWrite};
+
+const INITIAL_CAPACITY: usize = 8 * 1024;
+const DEFAULT_MAX_BUFFER_LEN: usize = 8 * 1024 * 1024;
+const DEFAULT_WRITE_HIGH_WATER_MARK: usize = 128 * 1024;
+
+/// Decode frames from the bytes received.
+pub trait Decoder {
+    /// The type of the decoded frames.
+    type Item;
+
+    /// The type of the decoding errors.
+    type Error: From<io::Error>;
+
+    /// Decode a frame from the buffer, and remove the consumed bytes from it.
+    ///
+    /// Returns `Ok(None)` if the frame is not complete. The buffer will be
+    /// extended with more data before the next call.
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error>;
+
+    /// Decode a frame after the stream reaches EOF. It is called until it
+    /// returns `Ok(None)`.
+    ///
+    /// By default, it calls [`Decoder::decode`], and fails with
+    /// [`io::ErrorKind::UnexpectedEof`] if the remaining bytes are not a
+    /// complete frame.
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(src)? {
+            Some(frame) => Ok(Some(frame)),
+            None if src.is_empty() => Ok(None),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "bytes remaining on stream",
+            )
+            .into()),
+        }
+    }
+}
+
+/// Encode frames into the bytes to send.
+pub trait Encoder<Item> {
+    /// The type of the encoding errors.
+    type Error: From<io::Error>;
+
+    /// Encode a frame and append it to the buffer.
+    fn encode(&mut self, item: Item, dst: &mut BytesMut) -> Result<(), Self::Error>;
+}
+
+/// A stream of frames, with the bytes encoded and decoded by a codec.
+///
+/// The buffers are given to the stream during receiving and sending. The
+/// buffered data is lost if [`Framed::next`], [`Framed::feed`] or
+/// [`Framed::flush`] is cancelled.
+///
+/// The write buffer is bounded by a high-water mark: [`Framed::feed`] waits
+/// until the stream accepts enough bytes, so the memory doesn't grow without
+/// bound when the peer stops reading.
+pub struct Framed<S, C> {
+    io: S,
+    codec: C,
+    // They are `None` only when given to an op.
+    read_buf: Option<BytesMut>,
+    write_buf: Option<BytesMut>,
+    capacity: usize,
+    max_buffer_len: usize,
+    write_high_water_mark: usize,
+    eof: bool,
+    // All frames are decoded after EOF.
+    terminated: bool,
+}
+
+impl<S, C> Framed<S, C> {
+    /// Create [`Framed`] with 8KiB read buffer.
+    pub fn new(io: S, codec: C) -> Self {
+        Self::with_capacity(io, codec, INITIAL_CAPACITY)
+    }
+
+    /// Create [`Framed`] with specified capacity of the read buffer. The
+    /// buffer grows when a frame is larger than it.
+    pub fn with_capacity(io: S, codec: C, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            io,
+            codec,
+            read_buf: Some(BytesMut::with_capacity(capacity)),
+            write_buf: Some(BytesMut::new()),
+            capacity,
+            max_buffer_len: DEFAULT_MAX_BUFFER_LEN.max(capacity),
+            write_high_water_mark: DEFAULT_WRITE_HIGH_WATER_MARK,
+            eof: false,
+            terminated: false,
+        }
+    }
+
+    /// The max length the read buffer could grow to, 8MiB by default. When a
+    /// frame is larger than it, [`Framed::next`] fails with
+    /// [`io::ErrorKind::InvalidData`].
+    pub fn max_buffer_len(&self) -> usize {
+        self.max_buffer_len
+    }
+
+    /// Set the max length the read buffer could grow to.
+    pub fn set_max_buffer_len(&mut self, len: usize) {
+        self.max_buffer_len = len.max(1);
+    }
+
+    /// The high-water mark of the write buffer, 128KiB by default.
+    pub fn write_high_water_mark(&self) -> usize {
+        self.write_high_water_mark
+    }
+
+    /// Set the high-water mark of the write buffer. Before a frame is encoded,
+    /// [`Framed::feed`] sends the buffered bytes until no more than `len`
+    /// bytes remain, so the buffer holds at most `len` bytes and a frame.
+    pub fn set_write_high_water_mark(&mut self, len: usize) {
+        self.write_high_water_mark = len;
+    }
+
+    /// Get a reference to the stream.
+    pub fn get_ref(&self) -> &S {
+        &self.io
+    }
+
+    /// Get a mutable reference to the stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.io
+    }
+
+    /// Get a reference to the codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Get a mutable reference to the codec.
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// The bytes received but not decoded yet.
+    pub fn read_buffer(&self) -> &[u8] {
+        self.read_buf.as_deref().unwrap_or_default()
+    }
+
+    /// The bytes encoded but not sent yet.
+    pub fn write_buffer(&self) -> &[u8] {
+        self.write_buf.as_deref().unwrap_or_default()
+    }
+
+    /// Consume the adapter, returning the stream and the codec. The buffered
+    /// data is dropped.
+    pub fn into_inner(self) -> (S, C) {
+        (self.io, self.codec)
+    }
+}
+
+impl<S: AsyncRead, C: Decoder> Framed<S, C> {
+    /// Receive and decode the next frame.
+    ///
+    /// Returns `None` when the stream reaches EOF and all frames are decoded.
+    /// If there are remaining bytes of an incomplete frame, an error is
+    /// returned, see [`Decoder::decode_eof`].
+    pub async fn next(&mut self) -> Option<Result<C::Item, C::Error>> {
+        loop {
+            if self.terminated {
+                return None;
+            }
+            let buf = self.read_buf.get_or_insert_with(BytesMut::new);
+            if self.eof {
+                let res = self.codec.decode_eof(buf).transpose();
+                // Don't report the same error again.
+                self.terminated = !matches!(res, Some(Ok(_)));
+                return res;
+            }
+            match self.codec.decode(buf) {
+                Ok(Some(frame)) => return Some(Ok(frame)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            if let Err(e) = self.fill().await {
+                return Some(Err(e.into()));
+            }
+        }
+    }
+
+    async fn fill(&mut self) -> io::Result<()> {
+        let mut buf = self.read_buf.take().unwrap_or_default();
+        if buf.len() == buf.capacity() {
+            if buf.len() >= self.max_buffer_len {
+                self.read_buf = Some(buf);
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "frame exceeds the max buffer length",
+                ));
+            }
+            // Double the buffer, but no more than the max length.
+            let additional = buf
+                .len()
+                .max(self.capacity)
+                .min(self.max_buffer_len - buf.len());
+            buf.reserve(additional);
+        }
+        let (res, buf) = self.io.read(buf).await;
+        self.read_buf = Some(buf);
+        if res? == 0 {
+            self.eof = true;
+        }
+        Ok(())
+    }
+}
+
+impl<S: AsyncWrite, C> Framed<S, C> {
+    /// Encode a frame into the write buffer. The frame is not sent, unless
+    /// the buffer is over the high-water mark, and then it waits until the
+    /// stream accepts enough bytes, see [`Framed::set_write_high_water_mark`].
+    pub async fn feed<Item>(&mut self, item: Item) -> Result<(), C::Error>
+    where
+        C: Encoder<Item>,
+    {
+        self.write_until(self.write_high_water_mark).await?;
+        let buf = self.write_buf.get_or_insert_with(BytesMut::new);
+        self.codec.encode(item, buf)
+    }
+
+    /// Encode a frame and send all buffered frames.
+    pub async fn send<Item>(&mut self, item: Item) -> Result<(), C::Error>
+    where
+        C: Encoder<Item>,
+    {
+        self.feed(item).await?;
+        self.flush().await?;
+        Ok(())
+    }
+
+    /// Send all buffered frames, and flush the stream. It completes only when
+    /// the buffer is empty and the last send completes.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.write_until(0).await?;
+        self.io.flush().await
+    }
+
+    // Send the buffered bytes until no more than `len` bytes remain.
+    async fn write_until(&mut self, len: usize) -> io::Result<()> {
+        let mut buf = self.write_buf.take().unwrap_or_default();
+        while buf.len() > len {
+            let res;
+            (res, buf) = self.io.write(buf).await;
+            match res {
+                Ok(0) => {
+                    self.write_buf = Some(buf);
+                    return Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    ));
+                }
+                Ok(n) => buf.advance(n),
+                Err(e) => {
+                    self.write_buf = Some(buf);
+                    return Err(e);
+                }
+            }
+        }
+        self.write_buf = Some(buf);
+        Ok(())
+    }
+}
@@ -0,0 +1,443 @@
+use std::{
+    io,
+    mem,
+    net::SocketAddr,
+    pin::Pin,
+    rc::Rc,
+    task::{ready, Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use futures_util::{future::LocalBoxFuture, FutureExt, Sink, Stream};
+
+use super::{Decoder, Encoder};
+use crate::net::UdpSocket;
+
+const DEFAULT_MAX_DATAGRAM_SIZE: usize = 64 * 1024;
+const MAX_BATCH: usize = 64;
+
+/// A datagram is larger than the max datagram size of [`UdpFramed`] or
+/// [`ConnectedUdpFramed`]. It is the inner error of the [`io::Error`] yielded
+/// instead of the truncated frames, with [`io::ErrorKind::InvalidData`]. The
+/// datagram is dropped, and the following ones are received as usual.
+///
+/// ```
+/// use compio::io::framed::DatagramTruncated;
+///
+/// fn is_truncated(e: &std::io::Error) -> bool {
+///     e.get_ref()
+///         .is_some_and(|e| e.is::<DatagramTruncated>())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DatagramTruncated {
+    max_size: usize,
+    addr: Option<SocketAddr>,
+}
+
+impl DatagramTruncated {
+    fn error(max_size: usize, addr: Option<SocketAddr>) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, Self { max_size, addr })
+    }
+
+    /// The max datagram size it exceeds.
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// The origin of the datagram. It is `None` for the connected sockets, or
+    /// if the platform reports the truncation without the origin.
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.addr
+    }
+}
+
+impl std::fmt::Display for DatagramTruncated {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "datagram exceeds the max size {}", self.max_size)?;
+        if let Some(addr) = self.addr {
+            write!(f, ", from {addr}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DatagramTruncated {}
+
+// The truncated datagrams are detected by the extra byte of the buffer, but
+// Windows fails the receive instead.
+#[cfg(windows)]
+fn is_truncated(e: &io::Error) -> bool {
+    use windows_sys::Win32::{Foundation::ERROR_MORE_DATA, Networking::WinSock::WSAEMSGSIZE};
+
+    matches!(e.raw_os_error(), Some(code) if code == WSAEMSGSIZE || code == ERROR_MORE_DATA as i32)
+}
+
+#[cfg(unix)]
+fn is_truncated(_: &io::Error) -> bool {
+    false
+}
+
+type RecvFuture = LocalBoxFuture<'static, (io::Result<(usize, Option<SocketAddr>)>, BytesMut)>;
+
+// The shared part of the framed sockets. The address is `None` for the
+// connected ones.
+struct Inner<C> {
+    socket: Rc<UdpSocket>,
+    codec: C,
+    connected: bool,
+    max_datagram_size: usize,
+    // `None` when given to the receive.
+    read_buf: Option<BytesMut>,
+    // The origin of the datagram being decoded, or `None` if all frames of it
+    // are decoded.
+    current: Option<Option<SocketAddr>>,
+    recv: Option<RecvFuture>,
+    write_buf: BytesMut,
+    queue: Vec<(Bytes, Option<SocketAddr>)>,
+    flush: Option<LocalBoxFuture<'static, io::Result<()>>>,
+}
+
+impl<C> Inner<C> {
+    fn new(socket: UdpSocket, codec: C, connected: bool) -> Self {
+        Self {
+            socket: Rc::new(socket),
+            codec,
+            connected,
+            max_datagram_size: DEFAULT_MAX_DATAGRAM_SIZE,
+            read_buf: Some(BytesMut::new()),
+            current: None,
+            recv: None,
+            write_buf: BytesMut::new(),
+            queue: Vec::new(),
+            flush: None,
+        }
+    }
+
+    fn into_inner(mut self) -> (UdpSocket, C) {
+        // Drop the futures holding the socket.
+        self.recv = None;
+        self.flush = None;
+        let socket = Rc::try_unwrap(self.socket)
+            .unwrap_or_else(|_| unreachable!("the socket should be owned by the framed socket"));
+        (socket, self.codec)
+    }
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<SocketAddr>, io::Error>> {
+        let mut recv = match self.recv.take() {
+            Some(recv) => recv,
+            None => {
+                let mut buf = self.read_buf.take().unwrap_or_default();
+                buf.clear();
+                // One more byte to detect the truncation.
+                buf.reserve(self.max_datagram_size + 1);
+                let socket = self.socket.clone();
+                let connected = self.connected;
+                async move {
+                    if connected {
+                        let (res, buf) = socket.recv(buf).await;
+                        (res.map(|n| (n, None)), buf)
+                    } else {
+                        let (res, buf) = socket.recv_from(buf).await;
+                        (res.map(|(n, addr)| (n, addr.as_socket())), buf)
+                    }
+                }
+                .boxed_local()
+            }
+        };
+        match recv.poll_unpin(cx) {
+            Poll::Ready((res, buf)) => {
+                self.read_buf = Some(buf);
+                match res {
+                    Ok((len, addr)) if len > self.max_datagram_size => {
+                        Poll::Ready(Err(DatagramTruncated::error(self.max_datagram_size, addr)))
+                    }
+                    Ok((_, addr)) => Poll::Ready(Ok(addr)),
+                    Err(e) if is_truncated(&e) => {
+                        Poll::Ready(Err(DatagramTruncated::error(self.max_datagram_size, None)))
+                    }
+                    Err(e) => Poll::Ready(Err(e)),
+                }
+            }
+            Poll::Pending => {
+                self.recv = Some(recv);
+                Poll::Pending
+            }
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn poll_next(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(C::Item, Option<SocketAddr>), C::Error>>
+    where
+        C: Decoder,
+    {
+        loop {
+            if let Some(addr) = self.current {
+                let buf = self.read_buf.get_or_insert_with(BytesMut::new);
+                // A datagram is a whole message, so the remaining bytes are
+                // decoded as EOF.
+                match self.codec.decode_eof(buf) {
+                    Ok(Some(frame)) => return Poll::Ready(Ok((frame, addr))),
+                    Ok(None) => self.current = None,
+                    Err(e) => {
+                        self.current = None;
+                        return Poll::Ready(Err(e));
+                    }
+                }
+            }
+            self.current = Some(ready!(self.poll_recv(cx))?);
+        }
+    }
+
+    fn start_send<Item>(&mut self, item: Item, addr: Option<SocketAddr>) -> Result<(), C::Error>
+    where
+        C: Encoder<Item>,
+    {
+        self.codec.encode(item, &mut self.write_buf)?;
+        self.queue.push((self.write_buf.split().freeze(), addr));
+        Ok(())
+    }
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.queue.len() >= MAX_BATCH {
+            self.poll_flush(cx)
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            if let Some(flush) = &mut self.flush {
+                let res = ready!(flush.poll_unpin(cx));
+                self.flush = None;
+                res?;
+            }
+            if self.queue.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            let batch = mem::take(&mut self.queue);
+            let socket = self.socket.clone();
+            // The sends are submitted together when the future is polled the
+            // first time.
+            self.flush = Some(
+                async move {
+                    let sends = batch.into_iter().map(|(buf, addr)| {
+                        let socket = &socket;
+                        async move {
+                            let len = buf.len();
+                            let (res, _) = match addr {
+                                Some(addr) => socket.send_to(buf, addr).await,
+                                None => socket.send(buf).await,
+                            };
+                            if res? < len {
+                                return Err(io::Error::new(
+                                    io::ErrorKind::WriteZero,
+                                    "failed to send the whole datagram",
+                                ));
+                            }
+                            Ok(())
+                        }
+                    });
+                    futures_util::future::join_all(sends)
+                        .await
+                        .into_iter()
+                        .collect()
+                }
+                .boxed_local(),
+            );
+        }
+    }
+}
+
+macro_rules! impl_framed {
+    ($t:ident) => {
+        impl<C> $t<C> {
+            /// The max size of the datagrams, 64KiB by default. The larger
+            /// datagrams are dropped with [`DatagramTruncated`].
+            pub fn max_datagram_size(&self) -> usize {
+                self.inner.max_datagram_size
+            }
+
+            /// Set the max size of the datagrams.
+            pub fn set_max_datagram_size(&mut self, size: usize) {
+                self.inner.max_datagram_size = size;
+            }
+
+            /// Get a reference to the socket.
+            pub fn get_ref(&self) -> &UdpSocket {
+                &self.inner.socket
+            }
+
+            /// Get a reference to the codec.
+            pub fn codec(&self) -> &C {
+                &self.inner.codec
+            }
+
+            /// Get a mutable reference to the codec.
+            pub fn codec_mut(&mut self) -> &mut C {
+                &mut self.inner.codec
+            }
+
+            /// Consume the adapter, returning the socket and the codec. The
+            /// pending operations are cancelled, and the frames not flushed
+            /// are dropped.
+            pub fn into_inner(self) -> (UdpSocket, C) {
+                self.inner.into_inner()
+            }
+        }
+
+        impl<C> Unpin for $t<C> {}
+    };
+}
+
+/// A [`Stream`] and [`Sink`] of the frames tagged with the addresses, over a
+/// [`UdpSocket`]. Each datagram is decoded as a whole message by
+/// [`Decoder::decode_eof`], and each frame sent is encoded into a datagram.
+///
+/// The frames are queued by [`Sink::start_send`], and sent together when the
+/// sink is flushed. It is flushed when 64 frames are queued.
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use compio::{
+///     io::framed::{LinesCodec, UdpFramed},
+///     net::UdpSocket,
+/// };
+/// use futures_util::{SinkExt, StreamExt};
+///
+/// compio::task::block_on(async {
+///     let server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     let server_addr = server.local_addr().unwrap().as_socket().unwrap();
+///     let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     let client_addr = client.local_addr().unwrap().as_socket().unwrap();
+///
+///     let mut server = UdpFramed::new(server, LinesCodec::new());
+///     let mut client = UdpFramed::new(client, LinesCodec::new());
+///     client.send(("hello", server_addr)).await.unwrap();
+///     let (line, addr) = server.next().await.unwrap().unwrap();
+///     assert_eq!(line, "hello");
+///     assert_eq!(addr, client_addr);
+/// })
+/// ```
+pub struct UdpFramed<C> {
+    inner: Inner<C>,
+}
+
+impl<C> UdpFramed<C> {
+    /// Create [`UdpFramed`] receiving and sending with the addresses.
+    pub fn new(socket: UdpSocket, codec: C) -> Self {
+        Self {
+            inner: Inner::new(socket, codec, false),
+        }
+    }
+}
+
+impl_framed!(UdpFramed);
+
+impl<C: Decoder> Stream for UdpFramed<C> {
+    type Item = Result<(C::Item, SocketAddr), C::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let res = ready!(self.get_mut().inner.poll_next(cx));
+        Poll::Ready(Some(res.and_then(|(frame, addr)| {
+            let addr = addr.ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "the origin is not an IP address")
+            })?;
+            Ok((frame, addr))
+        })))
+    }
+}
+
+impl<C: Encoder<Item>, Item> Sink<(Item, SocketAddr)> for UdpFramed<C> {
+    type Error = C::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn start_send(self: Pin<&mut Self>, (item, addr): (Item, SocketAddr)) -> Result<(), Self::Error> {
+        self.get_mut().inner.start_send(item, Some(addr))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().inner.poll_flush(cx).map_err(Into::into)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// A [`Stream`] and [`Sink`] of the frames over a connected [`UdpSocket`],
+/// without the addresses. See [`UdpFramed`].
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use compio::{
+///     io::framed::{ConnectedUdpFramed, LinesCodec},
+///     net::UdpSocket,
+/// };
+/// use futures_util::{SinkExt, StreamExt};
+///
+/// compio::task::block_on(async {
+///     let first = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     let second = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     first.connect(second.local_addr().unwrap()).unwrap();
+///     second.connect(first.local_addr().unwrap()).unwrap();
+///
+///     let mut first = ConnectedUdpFramed::new(first, LinesCodec::new());
+///     let mut second = ConnectedUdpFramed::new(second, LinesCodec::new());
+///     first.send("hello").await.unwrap();
+///     assert_eq!(second.next().await.unwrap().unwrap(), "hello");
+/// })
+/// ```
+pub struct ConnectedUdpFramed<C> {
+    inner: Inner<C>,
+}
+
+impl<C> ConnectedUdpFramed<C> {
+    /// Create [`ConnectedUdpFramed`]. The socket should be connected by
+    /// [`UdpSocket::connect`].
+    pub fn new(socket: UdpSocket, codec: C) -> Self {
+        Self {
+            inner: Inner::new(socket, codec, true),
+        }
+    }
+}
+
+impl_framed!(ConnectedUdpFramed);
+
+impl<C: Decoder> Stream for ConnectedUdpFramed<C> {
+    type Item = Result<C::Item, C::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let res = ready!(self.get_mut().inner.poll_next(cx));
+        Poll::Ready(Some(res.map(|(frame, _)| frame)))
+    }
+}
+
+impl<C: Encoder<Item>, Item> Sink<Item> for ConnectedUdpFramed<C> {
+    type Error = C::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Item) -> Result<(), Self::Error> {
+        self.get_mut().inner.start_send(item, None)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().inner.poll_flush(cx).map_err(Into::into)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
@@ -0,0 +1,399 @@
+//! Traits of the IO objects with owned buffers.
+//!
+//! * [`AsyncRead`] and [`AsyncWrite`] for the streams, e.g., [`TcpStream`],
+//!   connected [`UdpSocket`], and the named pipes on Windows.
+//! * [`AsyncReadAt`] and [`AsyncWriteAt`] for the positional IO, e.g.,
+//!   [`File`].
+//! * [`AsyncRecv`] and [`AsyncSend`] for the sockets shared by reference.
+//!
+//! [`BufWriter`] batches the small writes to a stream, with bounded memory when
+//! the peer stops reading. [`BufReader`] batches the reads, and parses the
+//! lines with [`BufReader::read_line`]. [`copy`] copies a stream to another
+//! until EOF.
+//!
+//! [`Terminal`] sets the attributes of the terminal, e.g., raw mode, and reads
+//! its input without blocking the runtime.
+//!
+//! The combinators like [`AsyncRead::read_exact`] and
+//! [`AsyncWrite::write_all`] are provided by the traits, so generic code works
+//! with any implementor.
+//!
+//! ## Design
+//!
+//! * The buffers are passed by value and given back with the results, because
+//!   the kernel owns them until the operations complete. They are generic
+//!   parameters of the methods instead of associated types, so no GAT is
+//!   needed, and an object could be used with different buffers.
+//! * The methods return `impl Future`, the same as `async fn` in traits. The
+//!   futures are not required to be `Send`, which fits the thread-per-core
+//!   runtime, and the downstream crates couldn't require it through these
+//!   traits. The traits are not object safe, so use generics instead of
+//!   `dyn`.
+//! * [`AsyncRead`], [`AsyncWrite`] and [`AsyncWriteAt`] take `&mut self`, so
+//!   the wrappers with state, e.g., buffered ones, could implement them. The
+//!   sockets, pipes and files don't need exclusive access, and the traits are
+//!   also implemented for their shared references. [`AsyncReadAt`] takes
+//!   `&self`, as reading at a position doesn't change the state.
+//!
+//! ```
+//! use compio::{
+//!     buf::IoBufMut,
+//!     io::{AsyncRead, AsyncWrite},
+//!     net::{TcpListener, TcpStream},
+//!     BufResult,
+//! };
+//!
+//! // Works with any stream.
+//! async fn echo_once<S: AsyncRead + AsyncWrite>(stream: &mut S) -> std::io::Result<usize> {
+//!     let (res, buf) = stream.read(Vec::with_capacity(64)).await;
+//!     res?;
+//!     stream.write_all(buf).await.0
+//! }
+//!
+//! compio::task::block_on(async {
+//!     let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//!     let addr = listener.local_addr().unwrap();
+//!     let (mut client, (mut server, _)) =
+//!         futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+//!
+//!     client.write_all("hello").await.0.unwrap();
+//!     assert_eq!(echo_once(&mut server).await.unwrap(), 5);
+//!     let (res, buf) = client.read_exact(Vec::with_capacity(5)).await;
+//!     res.unwrap();
+//!     assert_eq!(buf, b"hello");
+//! })
+//! ```
+
+#[cfg(feature = "framed")]
+pub mod framed;
+
+mod buf_reader;
+pub use buf_reader::*;
+
+mod buf_writer;
+pub use buf_writer::*;
+
+mod copy;
+pub use copy::*;
+
+mod terminal;
+pub use terminal::*;
+
+use std::{future::Future, io};
+
+#[cfg(target_os = "windows")]
+use crate::named_pipe::{NamedPipeClient, NamedPipeServer};
+use crate::{
+    buf::{IntoInner, IoBuf, IoBufMut},
+    buf_try,
+    fs::File,
+    net::{TcpStream, UdpSocket, UnixStream},
+    BufResult,
+};
+
+/// A stream which reads data into owned buffers.
+pub trait AsyncRead {
+    /// Read some data into the uninitialized part of the buffer, returning
+    /// the buffer and quantity of data read. The initialized length of the
+    /// buffer is advanced. Zero means EOF, or the buffer has no uninitialized
+    /// part.
+    fn read<B: IoBufMut>(&mut self, buf: B) -> impl Future<Output = BufResult<usize, B>>;
+
+    /// Read the exact number of bytes to fill the uninitialized part of the
+    /// buffer.
+    ///
+    /// It fails with [`io::ErrorKind::UnexpectedEof`] if the stream reaches
+    /// EOF before the buffer is filled, and the data read is kept in the
+    /// buffer.
+    fn read_exact<B: IoBufMut>(&mut self, mut buf: B) -> impl Future<Output = BufResult<usize, B>> {
+        async move {
+            let need = buf.as_uninit_slice().len();
+            let mut total_read = 0;
+            while total_read < need {
+                let read;
+                (read, buf) = buf_try!(self.read(buf).await);
+                if read == 0 {
+                    return (
+                        Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "failed to fill whole buffer",
+                        )),
+                        buf,
+                    );
+                }
+                total_read += read;
+            }
+            (Ok(total_read), buf)
+        }
+    }
+}
+
+/// A stream which writes data from owned buffers.
+pub trait AsyncWrite {
+    /// Write some data from the initialized part of the buffer, returning the
+    /// buffer and quantity of data written.
+    fn write<B: IoBuf>(&mut self, buf: B) -> impl Future<Output = BufResult<usize, B>>;
+
+    /// Write the whole initialized part of the buffer.
+    ///
+    /// It fails with [`io::ErrorKind::WriteZero`] if the stream couldn't accept
+    /// more data.
+    fn write_all<B: IoBuf>(&mut self, mut buf: B) -> impl Future<Output = BufResult<usize, B>> {
+        async move {
+            let buf_len = buf.buf_len();
+            let mut total_written = 0;
+            while total_written < buf_len {
+                let written;
+                (written, buf) =
+                    buf_try!(self.write(buf.slice(total_written..)).await.into_inner());
+                if written == 0 {
+                    return (
+                        Err(io::Error::new(
+                            io::ErrorKind::WriteZero,
+                            "failed to write whole buffer",
+                        )),
+                        buf,
+                    );
+                }
+                total_written += written;
+            }
+            (Ok(total_written), buf)
+        }
+    }
+
+    /// Flush the buffered data, if any. The unbuffered streams do nothing by
+    /// default.
+    fn flush(&mut self) -> impl Future<Output = io::Result<()>> {
+        std::future::ready(Ok(()))
+    }
+}
+
+/// An object which reads data at the specified positions into owned buffers.
+pub trait AsyncReadAt {
+    /// Read some data at `pos` into the uninitialized part of the buffer,
+    /// returning the buffer and quantity of data read. The initialized length
+    /// of the buffer is advanced. Zero means EOF, or the buffer has no
+    /// uninitialized part.
+    fn read_at<B: IoBufMut>(&self, buf: B, pos: u64) -> impl Future<Output = BufResult<usize, B>>;
+
+    /// Read the exact number of bytes at `pos` to fill the uninitialized part
+    /// of the buffer.
+    ///
+    /// It fails with [`io::ErrorKind::UnexpectedEof`] if EOF is reached before
+    /// the buffer is filled, and the data read is kept in the buffer.
+    fn read_exact_at<B: IoBufMut>(
+        &self,
+        mut buf: B,
+        pos: u64,
+    ) -> impl Future<Output = BufResult<usize, B>> {
+        async move {
+            let need = buf.as_uninit_slice().len();
+            let mut total_read = 0;
+            while total_read < need {
+                let read;
+                (read, buf) = buf_try!(self.read_at(buf, pos + total_read as u64).await);
+                if read == 0 {
+                    return (
+                        Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "failed to fill whole buffer",
+                        )),
+                        buf,
+                    );
+                }
+                total_read += read;
+            }
+            (Ok(total_read), buf)
+        }
+    }
+}
+
+/// An object which writes data at the specified positions from owned
+/// buffers.
+pub trait AsyncWriteAt {
+    /// Write some data from the initialized part of the buffer at `pos`,
+    /// returning the buffer and quantity of data written.
+    fn write_at<B: IoBuf>(
+        &mut self,
+        buf: B,
+        pos: u64,
+    ) -> impl Future<Output = BufResult<usize, B>>;
+
+    /// Write the whole initialized part of the buffer at `pos`.
+    ///
+    /// It fails with [`io::ErrorKind::WriteZero`] if no more data could be
+    /// written.
+    fn write_all_at<B: IoBuf>(
+        &mut self,
+        mut buf: B,
+        pos: u64,
+    ) -> impl Future<Output = BufResult<usize, B>> {
+        async move {
+            let buf_len = buf.buf_len();
+            let mut total_written = 0;
+            while total_written < buf_len {
+                let written;
+                (written, buf) = buf_try!(self
+                    .write_at(buf.slice(total_written..), pos + total_written as u64)
+                    .await
+                    .into_inner());
+                if written == 0 {
+                    return (
+                        Err(io::Error::new(
+                            io::ErrorKind::WriteZero,
+                            "failed to write whole buffer",
+                        )),
+                        buf,
+                    );
+                }
+                total_written += written;
+            }
+            (Ok(total_written), buf)
+        }
+    }
+}
+
+macro_rules! impl_read_write {
+    ($($t:ty: $read:ident, $write:ident),*) => {
+        $(
+            impl AsyncRead for $t {
+                fn read<B: IoBufMut>(&mut self, buf: B) -> impl Future<Output = BufResult<usize, B>> {
+                    <$t>::$read(self, buf)
+                }
+            }
+
+            impl AsyncRead for &$t {
+                fn read<B: IoBufMut>(&mut self, buf: B) -> impl Future<Output = BufResult<usize, B>> {
+                    <$t>::$read(*self, buf)
+                }
+            }
+
+            impl AsyncWrite for $t {
+                fn write<B: IoBuf>(&mut self, buf: B) -> impl Future<Output = BufResult<usize, B>> {
+                    <$t>::$write(self, buf)
+                }
+            }
+
+            impl AsyncWrite for &$t {
+                fn write<B: IoBuf>(&mut self, buf: B) -> impl Future<Output = BufResult<usize, B>> {
+                    <$t>::$write(*self, buf)
+                }
+            }
+        )*
+    };
+}
+
+impl_read_write!(TcpStream: recv, send, UnixStream: recv, send, UdpSocket: recv, send);
+
+#[cfg(target_os = "windows")]
+impl_read_write!(NamedPipeServer: read, write, NamedPipeClient: read, write);
+
+impl<S: AsyncRead + ?Sized> AsyncRead for &mut S {
+    fn read<B: IoBufMut>(&mut self, buf: B) -> impl Future<Output = BufResult<usize, B>> {
+        (**self).read(buf)
+    }
+}
+
+impl<S: AsyncWrite + ?Sized> AsyncWrite for &mut S {
+    fn write<B: IoBuf>(&mut self, buf: B) -> impl Future<Output = BufResult<usize, B>> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> impl Future<Output = io::Result<()>> {
+        (**self).flush()
+    }
+}
+
+impl AsyncReadAt for File {
+    fn read_at<B: IoBufMut>(&self, buf: B, pos: u64) -> impl Future<Output = BufResult<usize, B>> {
+        File::read_at(self, buf, pos)
+    }
+}
+
+impl AsyncWriteAt for File {
+    fn write_at<B: IoBuf>(
+        &mut self,
+        buf: B,
+        pos: u64,
+    ) -> impl Future<Output = BufResult<usize, B>> {
+        File::write_at(self, buf, pos)
+    }
+}
+
+impl AsyncWriteAt for &File {
+    fn write_at<B: IoBuf>(
+        &mut self,
+        buf: B,
+        pos: u64,
+    ) -> impl Future<Output = BufResult<usize, B>> {
+        File::write_at(self, buf, pos)
+    }
+}
+
+impl<A: AsyncReadAt + ?Sized> AsyncReadAt for &A {
+    fn read_at<B: IoBufMut>(&self, buf: B, pos: u64) -> impl Future<Output = BufResult<usize, B>> {
+        (**self).read_at(buf, pos)
+    }
+}
+
+impl<A: AsyncReadAt + ?Sized> AsyncReadAt for &mut A {
+    fn read_at<B: IoBufMut>(&self, buf: B, pos: u64) -> impl Future<Output = BufResult<usize, B>> {
+        (**self).read_at(buf, pos)
+    }
+}
+
+impl<A: AsyncWriteAt + ?Sized> AsyncWriteAt for &mut A {
+    fn write_at<B: IoBuf>(
+        &mut self,
+        buf: B,
+        pos: u64,
+    ) -> impl Future<Output = BufResult<usize, B>> {
+        (**self).write_at(buf, pos)
+    }
+}
+
+/// A stream which receives data into owned buffers.
+pub trait AsyncRecv {
+    /// Receive some data into the uninitialized part of the buffer, returning
+    /// the buffer and quantity of data received. Zero means EOF.
+    fn recv<T: IoBufMut>(&self, buffer: T) -> impl Future<Output = BufResult<usize, T>>;
+}
+
+/// A stream which sends data from owned buffers.
+pub trait AsyncSend {
+    /// Send some data from the initialized part of the buffer, returning the
+    /// buffer and quantity of data sent.
+    fn send<T: IoBuf>(&self, buffer: T) -> impl Future<Output = BufResult<usize, T>>;
+}
+
+macro_rules! impl_async_stream {
+    ($($t:ty),*) => {
+        $(
+            impl AsyncRecv for $t {
+                fn recv<T: IoBufMut>(&self, buffer: T) -> impl Future<Output = BufResult<usize, T>> {
+                    <$t>::recv(self, buffer)
+                }
+            }
+
+            impl AsyncSend for $t {
+                fn send<T: IoBuf>(&self, buffer: T) -> impl Future<Output = BufResult<usize, T>> {
+                    <$t>::send(self, buffer)
+                }
+            }
+        )*
+    };
+}
+
+impl_async_stream!(TcpStream, UnixStream);
+
+impl<S: AsyncRecv + ?Sized> AsyncRecv for &S {
+    fn recv<T: IoBufMut>(&self, buffer: T) -> impl Future<Output = BufResult<usize, T>> {
+        (**self).recv(buffer)
+    }
+}
+
+impl<S: AsyncSend + ?Sized> AsyncSend for &S {
+    fn send<T: IoBuf>(&self, buffer: T) -> impl Future<Output = BufResult<usize, T>> {
+        (**self).send(buffer)
+    }
+}
@@ -0,0 +1,188 @@
+use std::io;
+#[cfg(feature = "signal")]
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "signal")]
+use futures_util::{future::LocalBoxFuture, FutureExt, Stream};
+
+use crate::{buf::IoBufMut, BufResult};
+
+cfg_if::cfg_if! {
+    if #[cfg(target_os = "windows")] {
+        mod windows;
+        use windows as sys;
+    } else if #[cfg(unix)] {
+        mod unix;
+        use unix as sys;
+    }
+}
+
+/// The size of a terminal, in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalSize {
+    /// The count of the columns.
+    pub columns: u16,
+    /// The count of the rows.
+    pub rows: u16,
+}
+
+/// The terminal of the process, to query and set its attributes, and to read
+/// the input without blocking the runtime.
+///
+/// It doesn't own the standard input and output, and the attributes are
+/// shared by all handles to the same terminal.
+///
+/// ## Platform specific
+/// * Unix: the attributes are set by `tcsetattr` on the input. The input is
+///   read by the driver, which waits for it to be readable first.
+/// * Windows: the attributes are the console modes. The console input doesn't
+///   support overlapped IO, so it is read on the thread pool, and the key
+///   events are converted to UTF-8.
+///
+/// ```no_run
+/// use compio::io::Terminal;
+///
+/// compio::task::block_on(async {
+///     let terminal = Terminal::new();
+///     if terminal.is_tty() {
+///         let _guard = terminal.enable_raw_mode().unwrap();
+///         // Returns after a key is pressed, instead of a whole line.
+///         let (res, key) = terminal.read(Vec::with_capacity(16)).await;
+///         res.unwrap();
+///         println!("{key:?}\r");
+///     }
+/// })
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Terminal {
+    inner: sys::Terminal,
+}
+
+impl Terminal {
+    /// The terminal of the standard input and output.
+    pub fn new() -> Self {
+        Self {
+            inner: sys::Terminal::new(),
+        }
+    }
+
+    /// Use a terminal fd, e.g., the slave of a pseudo terminal, as both the
+    /// input and the output.
+    ///
+    /// # Safety
+    ///
+    /// The fd should stay open while the [`Terminal`], its
+    /// [`RawModeGuard`]s and resize event streams are alive.
+    #[cfg(unix)]
+    pub unsafe fn borrow_raw(fd: crate::driver::RawFd) -> Self {
+        Self {
+            inner: sys::Terminal::borrow_raw(fd),
+        }
+    }
+
+    /// Whether the input is a terminal. It is `false` if the input is
+    /// redirected from a file or a pipe.
+    pub fn is_tty(&self) -> bool {
+        self.inner.is_tty()
+    }
+
+    /// The current size of the terminal. The output is queried first, and
+    /// then the input, so that it works if either of them is redirected.
+    pub fn size(&self) -> io::Result<TerminalSize> {
+        self.inner.size()
+    }
+
+    /// Put the terminal into raw mode: the input is not echoed, and it is
+    /// available byte by byte instead of line by line. The control keys like
+    /// "ctrl-c" are read as input instead of generating signals.
+    ///
+    /// The attributes are restored when the returned guard is dropped. The
+    /// guard keeps the fd or handle itself, so it works even if the runtime is
+    /// shutting down.
+    pub fn enable_raw_mode(&self) -> io::Result<RawModeGuard> {
+        Ok(RawModeGuard {
+            _inner: self.inner.enable_raw_mode()?,
+        })
+    }
+
+    /// Read the input. In raw mode it completes as soon as any key is
+    /// pressed, otherwise after a line is entered. It returns `0` at the end
+    /// of the input.
+    pub async fn read<T: IoBufMut>(&self, buffer: T) -> BufResult<usize, T> {
+        self.inner.read(buffer).await
+    }
+
+    /// A stream of the new sizes of the terminal.
+    ///
+    /// The resizes are coalesced: a burst of them is yielded once, with the
+    /// size when the stream is polled, and the unchanged sizes are skipped.
+    ///
+    /// ## Platform specific
+    /// * Unix: it listens to `SIGWINCH`.
+    /// * Windows: it listens to the window buffer size events of the console
+    ///   input. They are read with the keys, so they are only reported while
+    ///   the terminal is being read by [`Terminal::read`].
+    #[cfg(feature = "signal")]
+    pub fn resize_events(&self) -> io::Result<ResizeEvents> {
+        Ok(ResizeEvents {
+            terminal: self.inner,
+            last: self.inner.size()?,
+            future: None,
+        })
+    }
+}
+
+impl Default for Terminal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Restores the attributes of the terminal when dropped. Created by
+/// [`Terminal::enable_raw_mode`].
+#[must_use = "the attributes are restored when the guard is dropped"]
+pub struct RawModeGuard {
+    // Restores the attributes on drop.
+    _inner: sys::RawModeGuard,
+}
+
+/// A stream of [`TerminalSize`], created by [`Terminal::resize_events`].
+#[cfg(feature = "signal")]
+pub struct ResizeEvents {
+    terminal: sys::Terminal,
+    last: TerminalSize,
+    future: Option<LocalBoxFuture<'static, io::Result<TerminalSize>>>,
+}
+
+#[cfg(feature = "signal")]
+impl ResizeEvents {
+    /// Wait for the next size of the terminal.
+    pub async fn next_size(&mut self) -> io::Result<TerminalSize> {
+        std::future::poll_fn(|cx| self.poll_size(cx)).await
+    }
+
+    fn poll_size(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<TerminalSize>> {
+        let (terminal, last) = (self.terminal, self.last);
+        let future = self
+            .future
+            .get_or_insert_with(|| terminal.wait_resize(last).boxed_local());
+        let res = futures_util::ready!(future.poll_unpin(cx));
+        self.future = None;
+        if let Ok(size) = &res {
+            self.last = *size;
+        }
+        Poll::Ready(res)
+    }
+}
+
+#[cfg(feature = "signal")]
+impl Stream for ResizeEvents {
+    type Item = io::Result<TerminalSize>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_size(cx).map(Some)
+    }
+}
@@ -0,0 +1,113 @@
+use std::{io, mem::MaybeUninit};
+
+use super::TerminalSize;
+#[cfg(feature = "signal")]
+use crate::signal::unix::SignalFd;
+use crate::{
+    buf::{IntoInner, IoBufMut},
+    driver::RawFd,
+    op::{BufResultExt, Recv},
+    syscall,
+    task::submit,
+    BufResult,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Terminal {
+    input: RawFd,
+    output: RawFd,
+}
+
+impl Terminal {
+    pub fn new() -> Self {
+        Self {
+            input: libc::STDIN_FILENO,
+            output: libc::STDOUT_FILENO,
+        }
+    }
+
+    pub unsafe fn borrow_raw(fd: RawFd) -> Self {
+        Self {
+            input: fd,
+            output: fd,
+        }
+    }
+
+    pub fn is_tty(&self) -> bool {
+        unsafe { libc::isatty(self.input) == 1 }
+    }
+
+    pub fn size(&self) -> io::Result<TerminalSize> {
+        size(self.output).or_else(|_| size(self.input))
+    }
+
+    pub fn enable_raw_mode(&self) -> io::Result<RawModeGuard> {
+        let mut termios = MaybeUninit::uninit();
+        syscall!(tcgetattr(self.input, termios.as_mut_ptr()))?;
+        let termios = unsafe { termios.assume_init() };
+        // The same as `cfmakeraw`, which is not available on all platforms.
+        let mut raw = termios;
+        raw.c_iflag &= !(libc::IGNBRK
+            | libc::BRKINT
+            | libc::PARMRK
+            | libc::ISTRIP
+            | libc::INLCR
+            | libc::IGNCR
+            | libc::ICRNL
+            | libc::IXON);
+        raw.c_oflag &= !libc::OPOST;
+        raw.c_lflag &= !(libc::ECHO | libc::ECHONL | libc::ICANON | libc::ISIG | libc::IEXTEN);
+        raw.c_cflag &= !(libc::CSIZE | libc::PARENB);
+        raw.c_cflag |= libc::CS8;
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        syscall!(tcsetattr(self.input, libc::TCSANOW, &raw))?;
+        Ok(RawModeGuard {
+            fd: self.input,
+            termios,
+        })
+    }
+
+    pub async fn read<T: IoBufMut>(&self, buffer: T) -> BufResult<usize, T> {
+        // The input is usually blocking, and the polling driver waits for it to
+        // be readable before reading.
+        submit(Recv::new(self.input, buffer))
+            .await
+            .into_inner()
+            .map_advanced()
+            .into_inner()
+    }
+
+    #[cfg(feature = "signal")]
+    pub async fn wait_resize(self, last: TerminalSize) -> io::Result<TerminalSize> {
+        loop {
+            // Listen before getting the size, so that no resize is missed.
+            let listener = SignalFd::new(libc::SIGWINCH)?;
+            let size = self.size()?;
+            if size != last {
+                return Ok(size);
+            }
+            listener.wait().await?;
+        }
+    }
+}
+
+fn size(fd: RawFd) -> io::Result<TerminalSize> {
+    let mut winsize: libc::winsize = unsafe { std::mem::zeroed() };
+    syscall!(ioctl(fd, libc::TIOCGWINSZ, &mut winsize))?;
+    Ok(TerminalSize {
+        columns: winsize.ws_col,
+        rows: winsize.ws_row,
+    })
+}
+
+pub struct RawModeGuard {
+    fd: RawFd,
+    termios: libc::termios,
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        syscall!(tcsetattr(self.fd, libc::TCSANOW, &self.termios)).ok();
+    }
+}
@@ -0,0 +1,173 @@
+use std::io;
+#[cfg(feature = "signal")]
+use std::sync::Mutex;
+
+#[cfg(feature = "signal")]
+use windows_sys::Win32::System::Console::WINDOW_BUFFER_SIZE_EVENT;
+use windows_sys::Win32::{
+    Foundation::HANDLE,
+    System::Console::{
+        GetConsoleMode, GetConsoleScreenBufferInfo, GetStdHandle, ReadConsoleInputW,
+        SetConsoleMode, CONSOLE_MODE, CONSOLE_SCREEN_BUFFER_INFO, ENABLE_ECHO_INPUT,
+        ENABLE_LINE_INPUT, ENABLE_PROCESSED_INPUT, ENABLE_WINDOW_INPUT, INPUT_RECORD, KEY_EVENT,
+        STD_INPUT_HANDLE, STD_OUTPUT_HANDLE,
+    },
+};
+
+use super::TerminalSize;
+#[cfg(feature = "signal")]
+use crate::event::{Event, EventHandle};
+use crate::{
+    buf::{IntoInner, IoBufMut},
+    driver::RawFd,
+    op::BlockingBufOp,
+    syscall,
+    task::submit,
+    BufResult,
+};
+
+// The listeners of the resize events. They are notified and removed when the
+// reader of the console input meets a resize event.
+#[cfg(feature = "signal")]
+static LISTENERS: Mutex<Vec<EventHandle>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Copy)]
+pub struct Terminal {
+    input: HANDLE,
+    output: HANDLE,
+}
+
+impl Terminal {
+    pub fn new() -> Self {
+        unsafe {
+            Self {
+                input: GetStdHandle(STD_INPUT_HANDLE),
+                output: GetStdHandle(STD_OUTPUT_HANDLE),
+            }
+        }
+    }
+
+    pub fn is_tty(&self) -> bool {
+        let mut mode = 0;
+        unsafe { GetConsoleMode(self.input, &mut mode) != 0 }
+    }
+
+    pub fn size(&self) -> io::Result<TerminalSize> {
+        let mut info: CONSOLE_SCREEN_BUFFER_INFO = unsafe { std::mem::zeroed() };
+        syscall!(BOOL, GetConsoleScreenBufferInfo(self.output, &mut info))?;
+        let window = info.srWindow;
+        Ok(TerminalSize {
+            columns: (window.Right - window.Left + 1) as u16,
+            rows: (window.Bottom - window.Top + 1) as u16,
+        })
+    }
+
+    pub fn enable_raw_mode(&self) -> io::Result<RawModeGuard> {
+        let mut mode: CONSOLE_MODE = 0;
+        syscall!(BOOL, GetConsoleMode(self.input, &mut mode))?;
+        let raw = (mode & !(ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT | ENABLE_PROCESSED_INPUT))
+            | ENABLE_WINDOW_INPUT;
+        syscall!(BOOL, SetConsoleMode(self.input, raw))?;
+        Ok(RawModeGuard {
+            handle: self.input,
+            mode,
+        })
+    }
+
+    pub async fn read<T: IoBufMut>(&self, mut buffer: T) -> BufResult<usize, T> {
+        let len = buffer.as_uninit_slice().len();
+        if len == 0 {
+            return (Ok(0), buffer);
+        }
+        let op = BlockingBufOp::new(self.input as RawFd, Vec::with_capacity(len), read_keys);
+        let (res, op) = submit(op).await;
+        let res = res.map(|read| {
+            let keys = op.into_inner();
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    keys.as_ptr(),
+                    buffer.as_uninit_slice().as_mut_ptr() as *mut u8,
+                    read,
+                );
+                buffer.set_buf_init(read);
+            }
+            read
+        });
+        (res, buffer)
+    }
+
+    #[cfg(feature = "signal")]
+    pub async fn wait_resize(self, last: TerminalSize) -> io::Result<TerminalSize> {
+        loop {
+            // Listen before getting the size, so that no resize is missed.
+            let listener = Event::new()?;
+            LISTENERS.lock().unwrap().push(listener.handle()?);
+            let size = self.size()?;
+            if size != last {
+                return Ok(size);
+            }
+            listener.wait().await?;
+        }
+    }
+}
+
+// Read the console input records until some keys are read, and encode the
+// characters of the pressed keys as UTF-8. The resize events notify the
+// listeners, and the other events are ignored.
+fn read_keys(fd: RawFd, keys: &mut Vec<u8>) -> io::Result<usize> {
+    let handle = fd as HANDLE;
+    // A UTF-16 unit is encoded to at most 3 bytes.
+    let mut records: Vec<INPUT_RECORD> = Vec::with_capacity((keys.capacity() / 3).max(1));
+    while keys.is_empty() {
+        let mut read = 0;
+        syscall!(
+            BOOL,
+            ReadConsoleInputW(
+                handle,
+                records.as_mut_ptr(),
+                records.capacity() as _,
+                &mut read
+            )
+        )?;
+        unsafe { records.set_len(read as _) };
+        let mut units = vec![];
+        for record in &records {
+            match record.EventType as u32 {
+                KEY_EVENT => {
+                    let key = unsafe { record.Event.KeyEvent };
+                    let unit = unsafe { key.uChar.UnicodeChar };
+                    if key.bKeyDown != 0 && unit != 0 {
+                        units.push(unit);
+                    }
+                }
+                #[cfg(feature = "signal")]
+                WINDOW_BUFFER_SIZE_EVENT => {
+                    for listener in std::mem::take(&mut *LISTENERS.lock().unwrap()) {
+                        listener.notify().ok();
+                    }
+                }
+                _ => {}
+            }
+        }
+        for c in char::decode_utf16(units) {
+            let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+            if keys.len() + c.len_utf8() > keys.capacity() {
+                break;
+            }
+            let mut bytes = [0; 4];
+            keys.extend_from_slice(c.encode_utf8(&mut bytes).as_bytes());
+        }
+    }
+    Ok(keys.len())
+}
+
+pub struct RawModeGuard {
+    handle: HANDLE,
+    mode: CONSOLE_MODE,
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        unsafe { SetConsoleMode(self.handle, self.mode) };
+    }
+}
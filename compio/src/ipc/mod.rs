@@ -0,0 +1,535 @@
+//! Inter-process communication between compio processes on the same host.
+//!
+//! A [`ShmChannel`] carries messages in two ring buffers in a mapping shared
+//! by two processes. The messages are copied into and out of the rings without
+//! syscalls when there is space or data. Only a side that has to wait sends a
+//! wakeup through a Unix socket, and the other side wakes it after it moves
+//! the ring forward. The sockets also tell the death of the peer: the waits
+//! fail with [`io::ErrorKind::BrokenPipe`] once the peer closes its side, or
+//! it crashes.
+//!
+//! The mapping is passed over the Unix socket at `name`:
+//!
+//! * Unix: a `memfd` (or an unlinked POSIX shared memory object other than
+//!   Linux and Android), passed with `SCM_RIGHTS`.
+//! * Windows: a named section `Local\compio-ipc-{pid}-{token}`, whose name
+//!   is sent over the socket.
+//!
+//! ```
+//! use compio::ipc::ShmChannel;
+//! use tempfile::tempdir;
+//!
+//! let dir = tempdir().unwrap();
+//! let name = dir.path().join("compio-ipc.sock");
+//!
+//! compio::task::block_on(async move {
+//!     let server = compio::task::spawn({
+//!         let name = name.clone();
+//!         async move { ShmChannel::create(name, 4096).await }
+//!     });
+//!     // Wait for the server to listen.
+//!     while !name.exists() {
+//!         compio::task::yield_now().await;
+//!     }
+//!     let mut client = ShmChannel::open(&name).await.unwrap();
+//!     let mut server = server.await.unwrap();
+//!
+//!     client.send("ping").await.0.unwrap();
+//!     let (res, buf) = server.recv(Vec::with_capacity(16)).await;
+//!     assert_eq!(res.unwrap(), 4);
+//!     assert_eq!(buf, b"ping");
+//! })
+//! ```
+
+cfg_if::cfg_if! {
+    if #[cfg(windows)] {
+        #[path = "windows.rs"]
+        mod sys;
+    } else if #[cfg(unix)] {
+        #[path = "unix.rs"]
+        mod sys;
+    }
+}
+
+use std::{
+    io,
+    mem::size_of,
+    path::Path,
+    ptr::NonNull,
+    rc::Rc,
+    sync::atomic::{fence, AtomicU32, AtomicU64, Ordering},
+};
+
+use crate::{
+    buf::{IoBuf, IoBufMut},
+    net::{UnixListener, UnixStream},
+    BufResult,
+};
+
+const MAGIC: u64 = u64::from_le_bytes(*b"compioSH");
+
+// The size of the header before each message. The messages are padded to it,
+// so that the headers are aligned and never wrap around the end of a ring.
+const RECORD_ALIGN: usize = 8;
+
+// The hello sent by the creator on the first connection: the magic, the
+// capacity of each ring, the token, and the pid of the creator.
+const HELLO_LEN: usize = 32;
+
+#[repr(C, align(64))]
+struct CacheLine<T>(T);
+
+// The indices are the counts of the bytes ever written and read, so the ring
+// is empty if they are equal. The producer publishes the records with a
+// release store of `head`, and the consumer gives the space back with a
+// release store of `tail`.
+#[repr(C)]
+struct RingHeader {
+    head: CacheLine<AtomicU64>,
+    tail: CacheLine<AtomicU64>,
+    // Set by the consumer before it waits for `head` to move.
+    reader_waiting: CacheLine<AtomicU32>,
+    // Set by the producer before it waits for `tail` to move.
+    writer_waiting: CacheLine<AtomicU32>,
+}
+
+#[repr(C)]
+struct Header {
+    magic: CacheLine<AtomicU64>,
+    // The creator writes the ring from the creator to the opener first.
+    rings: [RingHeader; 2],
+}
+
+const DATA_OFFSET: usize = size_of::<Header>();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Hello {
+    capacity: usize,
+    token: u64,
+    pid: u32,
+}
+
+impl Hello {
+    fn encode(&self) -> [u8; HELLO_LEN] {
+        let mut buf = [0; HELLO_LEN];
+        buf[..8].copy_from_slice(&MAGIC.to_le_bytes());
+        buf[8..16].copy_from_slice(&(self.capacity as u64).to_le_bytes());
+        buf[16..24].copy_from_slice(&self.token.to_le_bytes());
+        buf[24..28].copy_from_slice(&self.pid.to_le_bytes());
+        buf
+    }
+
+    fn decode(buf: &[u8]) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid shared memory hello");
+        if buf.len() != HELLO_LEN || buf[..8] != MAGIC.to_le_bytes() {
+            return Err(invalid());
+        }
+        let capacity = u64::from_le_bytes(buf[8..16].try_into().unwrap());
+        let capacity = usize::try_from(capacity)
+            .ok()
+            .filter(|capacity| capacity % RECORD_ALIGN == 0 && *capacity > RECORD_ALIGN)
+            .ok_or_else(invalid)?;
+        Ok(Self {
+            capacity,
+            token: u64::from_le_bytes(buf[16..24].try_into().unwrap()),
+            pid: u32::from_le_bytes(buf[24..28].try_into().unwrap()),
+        })
+    }
+
+    fn mapping_len(&self) -> usize {
+        DATA_OFFSET + self.capacity * 2
+    }
+}
+
+// A token to match the second connection with the first one.
+fn new_token() -> u64 {
+    use std::{
+        collections::hash_map::RandomState,
+        hash::{BuildHasher, Hasher},
+    };
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    hasher.finish()
+}
+
+struct Shared {
+    mapping: sys::Mapping,
+    capacity: usize,
+}
+
+impl Shared {
+    fn new(mapping: sys::Mapping, capacity: usize) -> io::Result<Rc<Self>> {
+        if mapping.len() < DATA_OFFSET + capacity * 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the shared memory is smaller than its capacity",
+            ));
+        }
+        Ok(Rc::new(Self { mapping, capacity }))
+    }
+
+    fn header(&self) -> &Header {
+        // Safety: the mapping is page aligned, and only atomics are accessed
+        // through the reference.
+        unsafe { &*self.mapping.as_ptr().as_ptr().cast::<Header>() }
+    }
+
+    fn ring(&self, index: usize) -> &RingHeader {
+        &self.header().rings[index]
+    }
+
+    fn data(&self, index: usize) -> NonNull<u8> {
+        unsafe {
+            self.mapping
+                .as_ptr()
+                .add(DATA_OFFSET + self.capacity * index)
+        }
+    }
+
+    // Copy `src` into the ring at the position, wrapping around the end.
+    //
+    // # Safety
+    //
+    // The bytes should be owned by the producer.
+    unsafe fn write(&self, index: usize, pos: u64, src: &[u8]) {
+        let start = (pos % self.capacity as u64) as usize;
+        let first = src.len().min(self.capacity - start);
+        let data = self.data(index).as_ptr();
+        std::ptr::copy_nonoverlapping(src.as_ptr(), data.add(start), first);
+        std::ptr::copy_nonoverlapping(src.as_ptr().add(first), data, src.len() - first);
+    }
+
+    // Copy the ring at the position into `dst`, wrapping around the end.
+    //
+    // # Safety
+    //
+    // The bytes should be published to the consumer.
+    unsafe fn read(&self, index: usize, pos: u64, dst: *mut u8, len: usize) {
+        let start = (pos % self.capacity as u64) as usize;
+        let first = len.min(self.capacity - start);
+        let data = self.data(index).as_ptr();
+        std::ptr::copy_nonoverlapping(data.add(start), dst, first);
+        std::ptr::copy_nonoverlapping(data, dst.add(first), len - first);
+    }
+}
+
+fn record_len(len: usize) -> usize {
+    RECORD_ALIGN + len.div_ceil(RECORD_ALIGN) * RECORD_ALIGN
+}
+
+fn broken_pipe() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "the peer has closed the channel")
+}
+
+fn corrupted() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "the shared ring is corrupted")
+}
+
+// Wait for a wakeup from the peer. The wakeups carry no information, and the
+// stale ones are drained together.
+async fn wait_wakeup(socket: &UnixStream, buffer: &mut Vec<u8>) -> io::Result<()> {
+    let mut wakeups = std::mem::take(buffer);
+    wakeups.clear();
+    let (res, wakeups) = socket.recv(wakeups).await;
+    *buffer = wakeups;
+    match res? {
+        0 => Err(broken_pipe()),
+        _ => Ok(()),
+    }
+}
+
+async fn send_wakeup(socket: &UnixStream) -> io::Result<()> {
+    socket.send_all(&b"\x01"[..]).await.0?;
+    Ok(())
+}
+
+/// The sending half of a [`ShmChannel`].
+pub struct ShmSender {
+    shared: Rc<Shared>,
+    ring: usize,
+    socket: UnixStream,
+    head: u64,
+    wakeups: Vec<u8>,
+}
+
+impl ShmSender {
+    fn new(shared: Rc<Shared>, ring: usize, socket: UnixStream) -> Self {
+        let head = shared.ring(ring).head.0.load(Ordering::Relaxed);
+        Self {
+            shared,
+            ring,
+            socket,
+            head,
+            wakeups: Vec::with_capacity(64),
+        }
+    }
+
+    /// The largest message that could be sent.
+    pub fn max_message_len(&self) -> usize {
+        (self.shared.capacity - RECORD_ALIGN).min(u32::MAX as usize)
+    }
+
+    /// Send the buffer as one message. It waits for the peer to receive the
+    /// earlier messages if the ring is full.
+    ///
+    /// It fails with [`io::ErrorKind::InvalidInput`] if the message is longer
+    /// than [`ShmSender::max_message_len`], and with
+    /// [`io::ErrorKind::BrokenPipe`] if it has to wait after the peer closed
+    /// its receiver.
+    pub async fn send<T: IoBuf>(&mut self, buffer: T) -> BufResult<usize, T> {
+        let len = buffer.buf_len();
+        if len > self.max_message_len() {
+            return (
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the message is longer than the ring",
+                )),
+                buffer,
+            );
+        }
+        let need = record_len(len) as u64;
+        let ring = self.shared.ring(self.ring);
+        loop {
+            let tail = ring.tail.0.load(Ordering::Acquire);
+            if self.head.wrapping_sub(tail) > self.shared.capacity as u64 {
+                return (Err(corrupted()), buffer);
+            }
+            if self.shared.capacity as u64 - self.head.wrapping_sub(tail) >= need {
+                break;
+            }
+            // Tell the consumer to wake us, and check again, in case it has
+            // read before seeing the flag.
+            ring.writer_waiting.0.store(1, Ordering::SeqCst);
+            fence(Ordering::SeqCst);
+            let tail = ring.tail.0.load(Ordering::SeqCst);
+            if self.shared.capacity as u64 - self.head.wrapping_sub(tail) >= need {
+                ring.writer_waiting.0.store(0, Ordering::Relaxed);
+                continue;
+            }
+            let res = wait_wakeup(&self.socket, &mut self.wakeups).await;
+            if let Err(e) = res {
+                return (Err(e), buffer);
+            }
+        }
+        // Safety: the space between `head` and `tail + capacity` is owned by
+        // the producer.
+        unsafe {
+            let mut header = [0u8; RECORD_ALIGN];
+            header[..4].copy_from_slice(&(len as u32).to_le_bytes());
+            self.shared.write(self.ring, self.head, &header);
+            self.shared.write(
+                self.ring,
+                self.head + RECORD_ALIGN as u64,
+                buffer.as_slice(),
+            );
+        }
+        self.head += need;
+        ring.head.0.store(self.head, Ordering::Release);
+        fence(Ordering::SeqCst);
+        if ring.reader_waiting.0.load(Ordering::SeqCst) != 0
+            && ring.reader_waiting.0.swap(0, Ordering::SeqCst) != 0
+        {
+            if let Err(e) = send_wakeup(&self.socket).await {
+                return (Err(e), buffer);
+            }
+        }
+        (Ok(len), buffer)
+    }
+}
+
+/// The receiving half of a [`ShmChannel`].
+pub struct ShmReceiver {
+    shared: Rc<Shared>,
+    ring: usize,
+    socket: UnixStream,
+    tail: u64,
+    wakeups: Vec<u8>,
+}
+
+impl ShmReceiver {
+    fn new(shared: Rc<Shared>, ring: usize, socket: UnixStream) -> Self {
+        let tail = shared.ring(ring).tail.0.load(Ordering::Relaxed);
+        Self {
+            shared,
+            ring,
+            socket,
+            tail,
+            wakeups: Vec::with_capacity(64),
+        }
+    }
+
+    /// Receive one message into the uninitialized part of the buffer. It
+    /// waits for the peer to send if the ring is empty.
+    ///
+    /// It fails with [`io::ErrorKind::InvalidInput`] without consuming the
+    /// message if it doesn't fit in the buffer, and with
+    /// [`io::ErrorKind::BrokenPipe`] once the peer closed its sender and all
+    /// its messages are received.
+    pub async fn recv<T: IoBufMut>(&mut self, mut buffer: T) -> BufResult<usize, T> {
+        let ring = self.shared.ring(self.ring);
+        let mut closed = false;
+        let head = loop {
+            let head = ring.head.0.load(Ordering::Acquire);
+            if head != self.tail {
+                break head;
+            }
+            if closed {
+                return (Err(broken_pipe()), buffer);
+            }
+            // Tell the producer to wake us, and check again, in case it has
+            // written before seeing the flag.
+            ring.reader_waiting.0.store(1, Ordering::SeqCst);
+            fence(Ordering::SeqCst);
+            if ring.head.0.load(Ordering::SeqCst) != self.tail {
+                ring.reader_waiting.0.store(0, Ordering::Relaxed);
+                continue;
+            }
+            match wait_wakeup(&self.socket, &mut self.wakeups).await {
+                Ok(()) => {}
+                // The messages sent before closing are still received.
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => closed = true,
+                Err(e) => return (Err(e), buffer),
+            }
+        };
+        let available = head.wrapping_sub(self.tail);
+        if available > self.shared.capacity as u64 || available < RECORD_ALIGN as u64 {
+            return (Err(corrupted()), buffer);
+        }
+        // Safety: the bytes between `tail` and `head` are published.
+        let len = unsafe {
+            let mut header = [0u8; RECORD_ALIGN];
+            self.shared
+                .read(self.ring, self.tail, header.as_mut_ptr(), RECORD_ALIGN);
+            u32::from_le_bytes(header[..4].try_into().unwrap()) as usize
+        };
+        let need = record_len(len) as u64;
+        if need > available {
+            return (Err(corrupted()), buffer);
+        }
+        let spare = buffer.as_uninit_slice();
+        if len > spare.len() {
+            return (
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the buffer is too small for the message",
+                )),
+                buffer,
+            );
+        }
+        unsafe {
+            self.shared.read(
+                self.ring,
+                self.tail + RECORD_ALIGN as u64,
+                spare.as_mut_ptr().cast(),
+                len,
+            );
+            buffer.set_buf_init(len);
+        }
+        self.tail += need;
+        ring.tail.0.store(self.tail, Ordering::Release);
+        fence(Ordering::SeqCst);
+        if ring.writer_waiting.0.load(Ordering::SeqCst) != 0
+            && ring.writer_waiting.0.swap(0, Ordering::SeqCst) != 0
+        {
+            // The peer may have closed its sender after filling the ring.
+            match send_wakeup(&self.socket).await {
+                Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return (Err(e), buffer),
+                _ => {}
+            }
+        }
+        (Ok(len), buffer)
+    }
+}
+
+/// A bidirectional message channel in shared memory, between two processes
+/// on the same host. See the [module level documentation](self).
+///
+/// Each direction has its own ring of the capacity. A message takes its
+/// length rounded up to 8 bytes, plus a header of 8 bytes. The sender waits
+/// if the ring is full, so the capacity limits the messages in flight.
+pub struct ShmChannel {
+    sender: ShmSender,
+    receiver: ShmReceiver,
+}
+
+impl ShmChannel {
+    /// Listen on the Unix socket at `name`, and create the channel with the
+    /// first process opening it. The socket file is removed after that.
+    ///
+    /// The capacity is rounded up to 8 bytes. The permissions of the socket
+    /// file decide the processes able to open the channel.
+    pub async fn create(name: impl AsRef<Path>, capacity: usize) -> io::Result<Self> {
+        let name = name.as_ref();
+        let listener = UnixListener::bind(name)?;
+        let res = Self::accept(&listener, capacity).await;
+        std::fs::remove_file(name).ok();
+        res
+    }
+
+    async fn accept(listener: &UnixListener, capacity: usize) -> io::Result<Self> {
+        let capacity = capacity.max(RECORD_ALIGN * 2).next_multiple_of(RECORD_ALIGN);
+        let hello = Hello {
+            capacity,
+            token: new_token(),
+            pid: std::process::id(),
+        };
+        let mapping = sys::Mapping::create(&hello)?;
+        let shared = Shared::new(mapping, capacity)?;
+        shared.header().magic.0.store(MAGIC, Ordering::Release);
+
+        let (first, _) = listener.accept().await?;
+        sys::send_hello(&first, &hello, &shared.mapping).await?;
+        // Other processes may connect in between. Only the one knowing the
+        // token is taken.
+        let second = loop {
+            let (second, _) = listener.accept().await?;
+            let (res, token) = second.recv_exact(Vec::with_capacity(8)).await;
+            if res.is_ok() && token == hello.token.to_le_bytes() {
+                break second;
+            }
+        };
+        Ok(Self {
+            sender: ShmSender::new(shared.clone(), 0, first),
+            receiver: ShmReceiver::new(shared, 1, second),
+        })
+    }
+
+    /// Open the channel created by [`ShmChannel::create`] with `name`.
+    pub async fn open(name: impl AsRef<Path>) -> io::Result<Self> {
+        let name = name.as_ref();
+        let first = UnixStream::connect(name)?;
+        let (hello, mapping) = sys::recv_hello(&first).await?;
+        let shared = Shared::new(mapping, hello.capacity)?;
+        if shared.header().magic.0.load(Ordering::Acquire) != MAGIC {
+            return Err(corrupted());
+        }
+        let second = UnixStream::connect(name)?;
+        second.send_all(hello.token.to_le_bytes().to_vec()).await.0?;
+        Ok(Self {
+            sender: ShmSender::new(shared.clone(), 1, second),
+            receiver: ShmReceiver::new(shared, 0, first),
+        })
+    }
+
+    /// The capacity of each ring.
+    pub fn capacity(&self) -> usize {
+        self.sender.shared.capacity
+    }
+
+    /// Send the buffer as one message, see [`ShmSender::send`].
+    pub async fn send<T: IoBuf>(&mut self, buffer: T) -> BufResult<usize, T> {
+        self.sender.send(buffer).await
+    }
+
+    /// Receive one message, see [`ShmReceiver::recv`].
+    pub async fn recv<T: IoBufMut>(&mut self, buffer: T) -> BufResult<usize, T> {
+        self.receiver.recv(buffer).await
+    }
+
+    /// Split the channel to send and receive concurrently.
+    pub fn into_split(self) -> (ShmSender, ShmReceiver) {
+        (self.sender, self.receiver)
+    }
+}
+
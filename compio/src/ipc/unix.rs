@@ -0,0 +1,182 @@
+use std::{
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    ptr::NonNull,
+};
+
+use super::{Hello, HELLO_LEN};
+use crate::{buf::IntoInner, net::UnixStream, op::RecvFrom, syscall, task::submit};
+
+pub struct Mapping {
+    ptr: NonNull<u8>,
+    len: usize,
+    fd: OwnedFd,
+}
+
+impl Mapping {
+    pub fn create(hello: &Hello) -> io::Result<Self> {
+        let fd = anonymous_memory()?;
+        syscall!(ftruncate(fd.as_raw_fd(), hello.mapping_len() as _))?;
+        Self::map(fd, hello.mapping_len())
+    }
+
+    fn map(fd: OwnedFd, len: usize) -> io::Result<Self> {
+        let mut stat = unsafe { std::mem::zeroed::<libc::stat>() };
+        syscall!(fstat(fd.as_raw_fd(), &mut stat))?;
+        if (stat.st_size as u64) < len as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the shared memory is smaller than its capacity",
+            ));
+        }
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            ptr: NonNull::new(ptr.cast()).expect("mmap returns null"),
+            len,
+            fd,
+        })
+    }
+
+    pub fn as_ptr(&self) -> NonNull<u8> {
+        self.ptr
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.len) };
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn anonymous_memory() -> io::Result<OwnedFd> {
+    let fd = syscall!(memfd_create(c"compio-ipc".as_ptr(), libc::MFD_CLOEXEC))?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+// Create a POSIX shared memory object, and unlink it at once, so that it is
+// only reachable by the fds.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn anonymous_memory() -> io::Result<OwnedFd> {
+    use std::ffi::CString;
+
+    loop {
+        let name = CString::new(format!(
+            "/compio-ipc-{}-{:x}",
+            std::process::id(),
+            super::new_token()
+        ))
+        .unwrap();
+        match syscall!(shm_open(
+            name.as_ptr(),
+            libc::O_RDWR | libc::O_CREAT | libc::O_EXCL | libc::O_CLOEXEC,
+            0o600 as libc::c_uint
+        )) {
+            Ok(fd) => {
+                let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+                syscall!(shm_unlink(name.as_ptr()))?;
+                return Ok(fd);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn control_len() -> usize {
+    unsafe { libc::CMSG_SPACE(std::mem::size_of::<RawFd>() as _) as usize }
+}
+
+// Send the hello with the fd of the mapping. It is small enough to fit in the
+// empty buffer of a new connection, so it doesn't wait.
+pub async fn send_hello(stream: &UnixStream, hello: &Hello, mapping: &Mapping) -> io::Result<()> {
+    let data = hello.encode();
+    let mut iov = libc::iovec {
+        iov_base: data.as_ptr() as *mut _,
+        iov_len: data.len(),
+    };
+    let mut control = vec![0u8; control_len()];
+    unsafe {
+        let mut msg: libc::msghdr = std::mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = control.len() as _;
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(std::mem::size_of::<RawFd>() as _) as _;
+        std::ptr::write_unaligned(
+            libc::CMSG_DATA(cmsg).cast::<RawFd>(),
+            mapping.fd.as_raw_fd(),
+        );
+        let sent = syscall!(sendmsg(stream.as_raw_fd(), &msg, 0))?;
+        if sent as usize != data.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "failed to send the shared memory hello",
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub async fn recv_hello(stream: &UnixStream) -> io::Result<(Hello, Mapping)> {
+    stream.attach()?;
+    let op = RecvFrom::with_control(
+        stream.as_raw_fd(),
+        Vec::with_capacity(HELLO_LEN),
+        vec![0; control_len()],
+    );
+    let (res, op) = submit(op).await;
+    let len = res?;
+    let fd = unsafe { received_fd(op.msg()) };
+    let (buffer, ..) = op.into_inner();
+    let buffer = buffer.into_inner();
+    let fd = fd.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "no shared memory is received with the hello",
+        )
+    })?;
+    let hello = Hello::decode(unsafe { std::slice::from_raw_parts(buffer.as_ptr(), len) })?;
+    let mapping = Mapping::map(fd, hello.mapping_len())?;
+    Ok((hello, mapping))
+}
+
+// Take the fds passed in the message. The extra ones are closed.
+unsafe fn received_fd(msg: &libc::msghdr) -> Option<OwnedFd> {
+    let mut received = None;
+    let mut cmsg = libc::CMSG_FIRSTHDR(msg);
+    while !cmsg.is_null() {
+        if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+            let data = libc::CMSG_DATA(cmsg);
+            let count = ((*cmsg).cmsg_len as usize - (data as usize - cmsg as usize))
+                / std::mem::size_of::<RawFd>();
+            for i in 0..count {
+                let fd = std::ptr::read_unaligned(data.cast::<RawFd>().add(i));
+                let fd = OwnedFd::from_raw_fd(fd);
+                libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC);
+                received.get_or_insert(fd);
+            }
+        }
+        cmsg = libc::CMSG_NXTHDR(msg, cmsg);
+    }
+    received
+}
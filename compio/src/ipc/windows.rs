@@ -0,0 +1,99 @@
+use std::{
+    io,
+    os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle},
+    ptr::NonNull,
+};
+
+use widestring::U16CString;
+use windows_sys::Win32::{
+    Foundation::INVALID_HANDLE_VALUE,
+    System::Memory::{
+        CreateFileMappingW, MapViewOfFile, OpenFileMappingW, UnmapViewOfFile, FILE_MAP_ALL_ACCESS,
+        PAGE_READWRITE,
+    },
+};
+
+use super::{Hello, HELLO_LEN};
+use crate::{net::UnixStream, syscall};
+
+pub struct Mapping {
+    ptr: NonNull<u8>,
+    len: usize,
+    _handle: OwnedHandle,
+}
+
+// The section is named by the creator, and the name is sent in the hello.
+fn section_name(hello: &Hello) -> io::Result<U16CString> {
+    U16CString::from_str(format!(
+        "Local\\compio-ipc-{}-{:x}",
+        hello.pid, hello.token
+    ))
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+impl Mapping {
+    pub fn create(hello: &Hello) -> io::Result<Self> {
+        let name = section_name(hello)?;
+        let len = hello.mapping_len() as u64;
+        let handle = syscall!(
+            BOOL,
+            CreateFileMappingW(
+                INVALID_HANDLE_VALUE,
+                std::ptr::null(),
+                PAGE_READWRITE,
+                (len >> 32) as u32,
+                len as u32,
+                name.as_ptr(),
+            )
+        )?;
+        let handle = unsafe { OwnedHandle::from_raw_handle(handle as _) };
+        Self::map(handle, hello.mapping_len())
+    }
+
+    fn open(hello: &Hello) -> io::Result<Self> {
+        let name = section_name(hello)?;
+        let handle = syscall!(BOOL, OpenFileMappingW(FILE_MAP_ALL_ACCESS, 0, name.as_ptr()))?;
+        let handle = unsafe { OwnedHandle::from_raw_handle(handle as _) };
+        Self::map(handle, hello.mapping_len())
+    }
+
+    fn map(handle: OwnedHandle, len: usize) -> io::Result<Self> {
+        let view =
+            unsafe { MapViewOfFile(handle.as_raw_handle() as _, FILE_MAP_ALL_ACCESS, 0, 0, len) };
+        let ptr = NonNull::new(view as *mut u8).ok_or_else(io::Error::last_os_error)?;
+        Ok(Self {
+            ptr,
+            len,
+            _handle: handle,
+        })
+    }
+
+    pub fn as_ptr(&self) -> NonNull<u8> {
+        self.ptr
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { UnmapViewOfFile(self.ptr.as_ptr() as _) };
+    }
+}
+
+// The creator keeps the section open until the channel is dropped, so the
+// opener could open it by the name.
+pub async fn send_hello(stream: &UnixStream, hello: &Hello, _mapping: &Mapping) -> io::Result<()> {
+    stream.send_all(hello.encode().to_vec()).await.0?;
+    Ok(())
+}
+
+pub async fn recv_hello(stream: &UnixStream) -> io::Result<(Hello, Mapping)> {
+    let (res, buffer) = stream.recv_exact(Vec::with_capacity(HELLO_LEN)).await;
+    res?;
+    let hello = Hello::decode(&buffer)?;
+    let mapping = Mapping::open(&hello)?;
+    Ok((hello, mapping))
+}
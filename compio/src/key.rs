@@ -1,4 +1,4 @@
-/// A typed wrapper for key of Ops submitted into driver
+/// A typed wrapper for key of Ops submitted into runtime
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct Key<T> {
     user_data: usize,
@@ -16,16 +16,15 @@ impl<T> Clone for Key<T> {
 impl<T> Copy for Key<T> {}
 
 impl<T> Key<T> {
-    /// Create a new `Key` with the given user data. If this `Key` won't be used
-    /// to poll data from drivers, use [`Key::new_dummy`] instead.
+    /// Create a new `Key` with the given key of the runtime.
     ///
     /// # Safety
     ///
-    /// Caller needs to ensure that `T` does correspond to `user_data` in driver
+    /// Caller needs to ensure that `T` does correspond to `key` in the runtime
     /// this `Key` is created with.
-    pub unsafe fn new(user_data: usize) -> Self {
+    pub unsafe fn new(key: usize) -> Self {
         Self {
-            user_data,
+            user_data: key,
             _p: std::marker::PhantomData,
         }
     }
@@ -15,9 +15,15 @@ pub mod op;
 #[cfg(target_os = "windows")]
 pub mod named_pipe;
 
+#[cfg(feature = "dispatcher")]
+pub mod dispatcher;
 #[cfg(feature = "event")]
 pub mod event;
 #[cfg(feature = "runtime")]
+pub mod io;
+#[cfg(feature = "ipc")]
+pub mod ipc;
+#[cfg(feature = "runtime")]
 mod key;
 #[cfg(feature = "runtime")]
 pub(crate) use key::Key;
@@ -25,20 +31,21 @@ pub(crate) use key::Key;
 mod attacher;
 #[cfg(feature = "runtime")]
 pub(crate) use attacher::Attacher;
+#[cfg(all(target_os = "windows", feature = "runtime"))]
+pub mod os;
+#[cfg(feature = "runtime")]
+pub mod process;
 #[cfg(feature = "signal")]
 pub mod signal;
+#[cfg(feature = "sync")]
+pub mod sync;
 #[cfg(feature = "runtime")]
 pub mod task;
 #[cfg(feature = "time")]
 pub mod time;
 
-/// A specialized `Result` type for operations with buffers.
-///
-/// This type is used as a return value for asynchronous IOCP methods that
-/// require passing ownership of a buffer to the runtime. When the operation
-/// completes, the buffer is returned whether or not the operation completed
-/// successfully.
-pub type BufResult<T, B> = (std::io::Result<T>, B);
+#[doc(no_inline)]
+pub use compio_buf::BufResult;
 
 #[cfg(feature = "runtime")]
 macro_rules! buf_try {
@@ -61,7 +68,7 @@ macro_rules! buf_try {
 pub(crate) use buf_try;
 
 macro_rules! impl_raw_fd {
-    ($t:ty, $inner:ident $(, $attacher:ident)?) => {
+    ($t:ty, $inner:ident $(, $field:ident)*) => {
         impl crate::driver::AsRawFd for $t {
             fn as_raw_fd(&self) -> crate::driver::RawFd {
                 self.$inner.as_raw_fd()
@@ -73,8 +80,8 @@ macro_rules! impl_raw_fd {
                     $inner: crate::driver::FromRawFd::from_raw_fd(fd),
                     $(
                         #[cfg(feature = "runtime")]
-                        $attacher: crate::Attacher::new(),
-                    )?
+                        $field: Default::default(),
+                    )*
                 }
             }
         }
@@ -88,15 +95,54 @@ macro_rules! impl_raw_fd {
 
 pub(crate) use impl_raw_fd;
 
+// Count the syscall with the feature `syscall-count`, otherwise nothing.
+#[cfg(feature = "syscall-count")]
+macro_rules! count_syscall {
+    ($name: expr) => {
+        $crate::driver::record_syscall($name)
+    };
+}
+
+#[cfg(not(feature = "syscall-count"))]
+macro_rules! count_syscall {
+    ($name: expr) => {};
+}
+
+pub(crate) use count_syscall;
+
+// The error injected into the syscall with the feature `fault-injection`,
+// otherwise nothing.
+#[cfg(all(target_os = "windows", feature = "fault-injection"))]
+macro_rules! injected_error {
+    ($name: expr) => {
+        $crate::driver::take_injected_error($name)
+    };
+}
+
+#[cfg(all(target_os = "windows", not(feature = "fault-injection")))]
+macro_rules! injected_error {
+    ($name: expr) => {
+        None::<::std::io::Error>
+    };
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) use injected_error;
+
 #[cfg(target_os = "windows")]
 macro_rules! syscall {
     ($fn: ident ( $($arg: expr),* $(,)* ), $op: tt $rhs: expr) => {{
-        #[allow(unused_unsafe)]
-        let res = unsafe { $fn($($arg, )*) };
-        if res $op $rhs {
-            Err(::std::io::Error::last_os_error())
+        $crate::count_syscall!(stringify!($fn));
+        if let Some(e) = $crate::injected_error!(stringify!($fn)) {
+            Err(e)
         } else {
-            Ok(res)
+            #[allow(unused_unsafe)]
+            let res = unsafe { $fn($($arg, )*) };
+            if res $op $rhs {
+                Err(::std::io::Error::last_os_error())
+            } else {
+                Ok(res)
+            }
         }
     }};
     (BOOL, $fn: ident ( $($arg: expr),* $(,)* )) => {
@@ -115,6 +161,7 @@ macro_rules! syscall {
 #[allow(unused_macros)]
 macro_rules! syscall {
     ($fn: ident ( $($arg: expr),* $(,)* ) ) => {{
+        $crate::count_syscall!(stringify!($fn));
         #[allow(unused_unsafe)]
         let res = unsafe { ::libc::$fn($($arg, )*) };
         if res == -1 {
@@ -0,0 +1,175 @@
+use crate::{
+    buf::pool::{self, PooledBuf},
+    net::StreamSocket,
+    BufResult,
+};
+
+// The sizes are tracked by the size classes of the buffer pool, the powers of
+// two from 64 bytes.
+const MIN_CLASS_SHIFT: u32 = 6;
+const CLASSES: usize = (usize::BITS - MIN_CLASS_SHIFT) as usize;
+
+// The weight of the old samples after each recv. A size falls out of the
+// percentile after about 50 smaller recvs.
+const DECAY: f64 = 0.95;
+
+fn class_of(size: usize) -> usize {
+    (size.max(1).next_power_of_two().trailing_zeros())
+        .saturating_sub(MIN_CLASS_SHIFT)
+        .min(CLASSES as u32 - 1) as usize
+}
+
+fn class_size(class: usize) -> usize {
+    1 << (class as u32 + MIN_CLASS_SHIFT)
+}
+
+/// A stream socket receiving into the pooled buffers sized by the recent
+/// receives.
+///
+/// It keeps a decayed histogram of the sizes received by the connection, and
+/// sizes the next buffer to cover a percentile of them, 90% by default. A
+/// receive filling the whole buffer may have left data in the socket, so the
+/// size grows at once, to the bytes queued in the socket if they are known by
+/// `FIONREAD`. The size shrinks slowly, by half at most for each receive, as
+/// the large sizes decay out of the histogram. It is always between
+/// [`AdaptiveRecv::min_size`] and [`AdaptiveRecv::max_size`].
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use compio::net::{AdaptiveRecv, TcpListener, TcpStream};
+///
+/// compio::task::block_on(async {
+///     let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     let addr = listener.local_addr().unwrap();
+///     let (client, (server, _)) =
+///         futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+///
+///     let mut server = AdaptiveRecv::new(server).max_size(64 * 1024);
+///     for _ in 0..100 {
+///         client.send_all(vec![0u8; 200]).await.0.unwrap();
+///         let (res, buf) = server.recv().await;
+///         assert_eq!(res.unwrap(), buf.len());
+///     }
+///     // Sized for the small messages.
+///     assert_eq!(server.next_size(), 256);
+/// })
+/// ```
+#[derive(Debug)]
+pub struct AdaptiveRecv<S> {
+    stream: S,
+    histogram: [f64; CLASSES],
+    total: f64,
+    percentile: f64,
+    min_size: usize,
+    max_size: usize,
+    size: usize,
+}
+
+impl<S: StreamSocket> AdaptiveRecv<S> {
+    /// Wrap the stream, with the sizes from 64 bytes to 1 MiB, starting from
+    /// 4 KiB.
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            histogram: [0.0; CLASSES],
+            total: 0.0,
+            percentile: 0.9,
+            min_size: 64,
+            max_size: 1024 * 1024,
+            size: 4096,
+        }
+    }
+
+    /// The smallest buffer size. Default to 64 bytes.
+    pub fn min_size(mut self, size: usize) -> Self {
+        self.min_size = size.max(1);
+        self.max_size = self.max_size.max(self.min_size);
+        self.size = self.size.clamp(self.min_size, self.max_size);
+        self
+    }
+
+    /// The largest buffer size, which caps the growth. Default to 1 MiB.
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.max_size = size.max(1);
+        self.min_size = self.min_size.min(self.max_size);
+        self.size = self.size.clamp(self.min_size, self.max_size);
+        self
+    }
+
+    /// The ratio of the recent receives the buffers should cover. Default to
+    /// 0.9.
+    pub fn percentile(mut self, percentile: f64) -> Self {
+        self.percentile = percentile.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The size of the buffer of the next receive.
+    pub fn next_size(&self) -> usize {
+        self.size
+    }
+
+    /// Receive into a buffer of [`AdaptiveRecv::next_size`] from the buffer
+    /// pool, and update the size. Zero means EOF.
+    pub async fn recv(&mut self) -> BufResult<usize, PooledBuf> {
+        let buffer = pool::acquire(self.size);
+        // The capacity may be larger because of the size class.
+        let capacity = buffer.capacity();
+        let (res, buffer) = self.stream.recv(buffer).await;
+        if let Ok(len) = res {
+            if len > 0 {
+                self.update(len, len >= capacity);
+            }
+        }
+        (res, buffer)
+    }
+
+    fn update(&mut self, len: usize, filled: bool) {
+        for weight in &mut self.histogram {
+            *weight *= DECAY;
+        }
+        self.total *= DECAY;
+        // A filled buffer means a larger message.
+        let class = if filled {
+            class_of(len) + 1
+        } else {
+            class_of(len)
+        };
+        self.histogram[class.min(CLASSES - 1)] += 1.0;
+        self.total += 1.0;
+
+        let target = self.percentile * self.total;
+        let mut covered = 0.0;
+        let mut size = class_size(CLASSES - 1);
+        for (class, weight) in self.histogram.iter().enumerate() {
+            covered += weight;
+            if covered >= target {
+                size = class_size(class);
+                break;
+            }
+        }
+        let size = if filled {
+            // Read the queued bytes in one receive, if they are known.
+            let queued = self.stream.recv_queued().unwrap_or(0);
+            size.max(self.size * 2).max(queued)
+        } else {
+            size.max(self.size / 2)
+        };
+        self.size = size.clamp(self.min_size, self.max_size);
+    }
+
+    /// Get the reference of the stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Get the mutable reference of the stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Unwrap the stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
@@ -0,0 +1,321 @@
+#[cfg(feature = "time")]
+use std::time::Duration;
+use std::{cell::Cell, io, net::Shutdown, time::Instant};
+
+use crate::net::StreamSocket;
+
+/// The options of [`copy_bidirectional_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyOptions {
+    buffer_size: usize,
+    splice: bool,
+    #[cfg(feature = "time")]
+    idle_timeout: Option<Duration>,
+}
+
+impl CopyOptions {
+    /// Create the default options: 64 KiB buffers, with the splice fast path,
+    /// and without the idle timeout.
+    pub fn new() -> Self {
+        Self {
+            buffer_size: 64 * 1024,
+            splice: true,
+            #[cfg(feature = "time")]
+            idle_timeout: None,
+        }
+    }
+
+    /// The size of the buffers of each direction, or the size of the pipes of
+    /// the splice fast path. Default to 64 KiB.
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size.max(1);
+        self
+    }
+
+    /// Use the splice fast path if possible. Default to `true`.
+    pub fn splice(mut self, splice: bool) -> Self {
+        self.splice = splice;
+        self
+    }
+
+    /// Fail with [`io::ErrorKind::TimedOut`] if no data is copied in either
+    /// direction for the duration. Default to `None`, which never times out.
+    #[cfg(feature = "time")]
+    pub fn idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Copy data between two stream sockets in both directions concurrently, with
+/// the default [`CopyOptions`], see [`copy_bidirectional_with`].
+pub async fn copy_bidirectional(
+    a: &impl StreamSocket,
+    b: &impl StreamSocket,
+) -> io::Result<(u64, u64)> {
+    copy_bidirectional_with(a, b, &CopyOptions::new()).await
+}
+
+/// Copy data between two stream sockets in both directions concurrently,
+/// e.g., for a proxy, and returns the bytes copied from `a` to `b`, and from
+/// `b` to `a`.
+///
+/// When one direction reaches the EOF, the write side of its destination is
+/// shut down, and the other direction continues until its EOF. If either
+/// direction fails, the other one is aborted, and the error is returned.
+///
+/// The data is moved in the kernel if possible:
+///
+/// * Linux: `splice` through a pipe of each direction.
+///
+/// Otherwise, each direction copies through two userspace buffers: one is
+/// sent while the other is being filled.
+///
+/// ```
+/// use std::net::{Ipv4Addr, Shutdown};
+///
+/// use compio::net::{copy_bidirectional, TcpListener, TcpStream};
+///
+/// compio::task::block_on(async {
+///     let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     let addr = listener.local_addr().unwrap();
+///     let (client, (a, _)) =
+///         futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+///     let (b, (server, _)) =
+///         futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+///
+///     let peers = async {
+///         client.send_all("hello").await.0?;
+///         client.shutdown(Shutdown::Write)?;
+///         let (res, request) = server.recv_to_end(vec![]).await;
+///         res?;
+///         server.send_all(request).await.0?;
+///         server.shutdown(Shutdown::Write)?;
+///         let (res, response) = client.recv_to_end(vec![]).await;
+///         res?;
+///         assert_eq!(response, b"hello");
+///         Ok(())
+///     };
+///     let (copied, ()) = futures_util::try_join!(copy_bidirectional(&a, &b), peers).unwrap();
+///     assert_eq!(copied, (5, 5));
+/// })
+/// ```
+pub async fn copy_bidirectional_with(
+    a: &impl StreamSocket,
+    b: &impl StreamSocket,
+    options: &CopyOptions,
+) -> io::Result<(u64, u64)> {
+    a.attach()?;
+    b.attach()?;
+    let activity = Cell::new(now());
+    let copy = futures_util::future::try_join(
+        copy_one(a, b, options, &activity),
+        copy_one(b, a, options, &activity),
+    );
+    #[cfg(feature = "time")]
+    if let Some(timeout) = options.idle_timeout {
+        use futures_util::{select, FutureExt};
+
+        return select! {
+            res = copy.fuse() => res,
+            e = idle(timeout, &activity).fuse() => Err(e),
+        };
+    }
+    copy.await
+}
+
+// The time of the idle timeout, which follows the clock of the timers.
+fn now() -> Instant {
+    #[cfg(feature = "time")]
+    {
+        crate::time::now()
+    }
+    #[cfg(not(feature = "time"))]
+    {
+        Instant::now()
+    }
+}
+
+#[cfg(feature = "time")]
+async fn idle(timeout: Duration, activity: &Cell<Instant>) -> io::Error {
+    loop {
+        let deadline = activity.get() + timeout;
+        if now() >= deadline {
+            return io::Error::new(io::ErrorKind::TimedOut, "no data is copied");
+        }
+        crate::time::sleep_until(deadline).await;
+    }
+}
+
+/// Copy from `src` to `dst` until the EOF of `src`, and shut down the write
+/// side of `dst`.
+async fn copy_one(
+    src: &impl StreamSocket,
+    dst: &impl StreamSocket,
+    options: &CopyOptions,
+    activity: &Cell<Instant>,
+) -> io::Result<u64> {
+    let spliced = if options.splice {
+        copy_spliced(src, dst, options, activity).await?
+    } else {
+        None
+    };
+    let copied = match spliced {
+        Some(copied) => copied,
+        None => copy_buffered(src, dst, options, activity).await?,
+    };
+    match dst.shutdown(Shutdown::Write) {
+        // The peer has gone, and there is nothing to shut down.
+        Err(e) if e.kind() == io::ErrorKind::NotConnected => {}
+        res => res?,
+    }
+    Ok(copied)
+}
+
+fn is_retry_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+    )
+}
+
+/// Returns the bytes copied, or `None` if the fast path is not supported, and
+/// nothing is copied.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+async fn copy_spliced(
+    src: &impl StreamSocket,
+    dst: &impl StreamSocket,
+    options: &CopyOptions,
+    activity: &Cell<Instant>,
+) -> io::Result<Option<u64>> {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    use crate::{driver::Interest, net::send_file::is_unsupported_error, op::Splice, task::submit};
+
+    let mut fds = [0; 2];
+    crate::syscall!(pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK))?;
+    let (pipe_read, pipe_write) =
+        unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    // The pipe is resized on a best-effort basis, and the requested size may
+    // exceed the limit of the unprivileged users.
+    crate::syscall!(fcntl(
+        pipe_write.as_raw_fd(),
+        libc::F_SETPIPE_SZ,
+        options.buffer_size as libc::c_int
+    ))
+    .ok();
+
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    {
+        crate::net::send_file::set_nonblocking(src)?;
+        crate::net::send_file::set_nonblocking(dst)?;
+    }
+
+    let mut copied = 0;
+    loop {
+        let op = Splice::new(
+            src.as_raw_fd(),
+            pipe_write.as_raw_fd(),
+            options.buffer_size,
+            Interest::Readable,
+        );
+        let read = match submit(op).await.0 {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if is_retry_error(&e) => continue,
+            Err(e) if copied == 0 && is_unsupported_error(&e) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        activity.set(now());
+        let mut rest = read;
+        while rest > 0 {
+            let op = Splice::new(
+                pipe_read.as_raw_fd(),
+                dst.as_raw_fd(),
+                rest,
+                Interest::Writable,
+            );
+            match submit(op).await.0 {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => rest -= n,
+                Err(e) if is_retry_error(&e) => {}
+                Err(e) => return Err(e),
+            }
+            activity.set(now());
+        }
+        copied += read as u64;
+    }
+    Ok(Some(copied))
+}
+
+/// There is no fast path.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+async fn copy_spliced(
+    _src: &impl StreamSocket,
+    _dst: &impl StreamSocket,
+    _options: &CopyOptions,
+    _activity: &Cell<Instant>,
+) -> io::Result<Option<u64>> {
+    Ok(None)
+}
+
+/// Send the whole buffer.
+async fn send_all(stream: &impl StreamSocket, mut buffer: Vec<u8>) -> io::Result<Vec<u8>> {
+    use crate::buf::{IntoInner, IoBuf};
+
+    let mut sent = 0;
+    while sent < buffer.len() {
+        let (res, slice) = stream.send(buffer.slice(sent..)).await;
+        buffer = slice.into_inner();
+        match res {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => sent += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(buffer)
+}
+
+async fn recv(stream: &impl StreamSocket, mut buffer: Vec<u8>) -> io::Result<(usize, Vec<u8>)> {
+    loop {
+        let res;
+        (res, buffer) = stream.recv(buffer).await;
+        match res {
+            Ok(n) => return Ok((n, buffer)),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Copy through two buffers: one is sent while the other is being filled.
+async fn copy_buffered(
+    src: &impl StreamSocket,
+    dst: &impl StreamSocket,
+    options: &CopyOptions,
+    activity: &Cell<Instant>,
+) -> io::Result<u64> {
+    let (mut read, mut filled) = recv(src, Vec::with_capacity(options.buffer_size)).await?;
+    let mut spare = Vec::with_capacity(options.buffer_size);
+    let mut copied = 0;
+    while read > 0 {
+        activity.set(now());
+        spare.clear();
+        // Either failure aborts the other.
+        let (buffer, (next_read, next_filled)) =
+            futures_util::try_join!(send_all(dst, filled), recv(src, spare))?;
+        activity.set(now());
+        copied += read as u64;
+        read = next_read;
+        spare = buffer;
+        filled = next_filled;
+    }
+    Ok(copied)
+}
@@ -0,0 +1,310 @@
+use std::{io, net::Shutdown};
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+use std::{marker::PhantomData, pin::Pin};
+
+use socket2::SockAddr;
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+use crate::{
+    driver::{AsRawFd, DirectFd, OpCode},
+    op::{AcceptDirect, BufResultExt, CloseDirect, RecvDirect, SendDirect, ShutdownDirect},
+    task::{submit, RUNTIME},
+};
+use crate::{
+    buf::{IntoInner, IoBuf, IoBufMut},
+    buf_try,
+    net::{TcpListener, TcpStream},
+    BufResult,
+};
+
+// A slot of the file table reserved for a direct descriptor, given back when
+// dropped.
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+#[derive(Debug)]
+struct Slot {
+    // The slots are counted by the runtime of current thread.
+    _not_send: PhantomData<*const ()>,
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+impl Slot {
+    // Reserve a slot, waiting until one is free if the table is full.
+    async fn reserve() -> io::Result<Self> {
+        let mut waited = false;
+        std::future::poll_fn(|cx| RUNTIME.with(|runtime| runtime.poll_direct_slot(cx, &mut waited)))
+            .await?;
+        Ok(Self {
+            _not_send: PhantomData,
+        })
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+impl Drop for Slot {
+    fn drop(&mut self) {
+        RUNTIME
+            .try_with(|runtime| runtime.release_direct_slot())
+            .ok();
+    }
+}
+
+// A socket as a direct descriptor of the ring of current thread. It is closed
+// by removing it from the file table, unless closed by `close`.
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+#[derive(Debug)]
+struct DirectSocket {
+    fd: DirectFd,
+    // Released after the descriptor is removed.
+    slot: Option<Slot>,
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+impl DirectSocket {
+    fn new(fd: DirectFd, slot: Slot) -> Self {
+        Self {
+            fd,
+            slot: Some(slot),
+        }
+    }
+
+    // Accept a connection into a reserved slot.
+    async fn accept(op: AcceptDirect) -> io::Result<(Self, SockAddr)> {
+        let slot = Slot::reserve().await?;
+        let (res, op) = submit(op).await;
+        let socket = Self::new(DirectFd(res? as _), slot);
+        Ok((socket, op.into_addr()))
+    }
+
+    async fn close(mut self) -> io::Result<()> {
+        // The slot is released with the op when it completes, even if the
+        // future is cancelled before.
+        let op = CloseSlot {
+            op: CloseDirect::new(self.fd),
+            _slot: self.slot.take(),
+        };
+        std::mem::forget(self);
+        submit(op).await.0?;
+        Ok(())
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+impl Drop for DirectSocket {
+    fn drop(&mut self) {
+        RUNTIME
+            .try_with(|runtime| runtime.remove_direct(self.fd))
+            .ok();
+    }
+}
+
+// Close a direct descriptor, holding its slot until the completion.
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+struct CloseSlot {
+    op: CloseDirect,
+    _slot: Option<Slot>,
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+impl OpCode for CloseSlot {
+    fn create_entry(self: Pin<&mut Self>) -> io_uring::squeue::Entry {
+        Pin::new(&mut self.get_mut().op).create_entry()
+    }
+}
+
+enum ListenerInner {
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    Direct(DirectSocket),
+    Fallback(Box<TcpListener>),
+}
+
+/// A TCP listener created by [`TcpListener::bind_direct`].
+///
+/// With io-uring on Linux 6.11 or later, the socket is created, bound and
+/// listened by the ring as a direct descriptor, an index into the file table
+/// of the ring, and never exists in the file descriptor table of the process.
+/// The accepted connections are direct descriptors, too. Otherwise it wraps a
+/// normal [`TcpListener`].
+///
+/// A direct descriptor is only valid for the ring of the thread creating it,
+/// so the listener and the streams are neither [`Send`] nor convertible to
+/// `RawFd`, and the syscalls taking a file descriptor, e.g., `getsockname` and
+/// `setsockopt`, are not available.
+pub struct DirectTcpListener {
+    inner: ListenerInner,
+}
+
+impl DirectTcpListener {
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub(crate) async fn bind_direct(addr: &SockAddr, backlog: i32) -> io::Result<Option<Self>> {
+        use socket2::{Protocol, Type};
+
+        use crate::op::{BindDirect, ListenDirect, SocketDirect};
+
+        if !RUNTIME.with(|runtime| runtime.supports_direct_sockets()) {
+            return Ok(None);
+        }
+        let slot = Slot::reserve().await?;
+        let op = SocketDirect::new(addr.domain(), Type::STREAM, Some(Protocol::TCP));
+        let fd = submit(op).await.0?;
+        let socket = DirectSocket::new(DirectFd(fd as _), slot);
+        submit(BindDirect::new(socket.fd, addr.clone())).await.0?;
+        submit(ListenDirect::new(socket.fd, backlog)).await.0?;
+        Ok(Some(Self {
+            inner: ListenerInner::Direct(socket),
+        }))
+    }
+
+    pub(crate) fn fallback(listener: TcpListener) -> Self {
+        Self {
+            inner: ListenerInner::Fallback(Box::new(listener)),
+        }
+    }
+
+    /// Whether the listener is a direct descriptor, or a normal socket as the
+    /// fallback.
+    pub fn is_direct(&self) -> bool {
+        !matches!(self.inner, ListenerInner::Fallback(_))
+    }
+
+    /// Accepts a new incoming connection as a [`DirectTcpStream`], which is a
+    /// direct descriptor if the listener is. If the file table is full, it
+    /// waits until a direct socket is closed, see
+    /// [`ProactorBuilder::direct_table_size`].
+    ///
+    /// [`ProactorBuilder::direct_table_size`]: crate::driver::ProactorBuilder::direct_table_size
+    pub async fn accept_direct(&self) -> io::Result<(DirectTcpStream, SockAddr)> {
+        match &self.inner {
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            ListenerInner::Direct(socket) => {
+                let (stream, addr) = DirectSocket::accept(AcceptDirect::new(socket.fd)).await?;
+                Ok((
+                    DirectTcpStream {
+                        inner: StreamInner::Direct(stream),
+                    },
+                    addr,
+                ))
+            }
+            ListenerInner::Fallback(listener) => {
+                let (stream, addr) = listener.accept().await?;
+                Ok((
+                    DirectTcpStream {
+                        inner: StreamInner::Fallback(Box::new(stream)),
+                    },
+                    addr,
+                ))
+            }
+        }
+    }
+}
+
+enum StreamInner {
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    Direct(DirectSocket),
+    Fallback(Box<TcpStream>),
+}
+
+/// A TCP stream accepted by [`DirectTcpListener::accept_direct`], or by
+/// `TcpListener::accept_direct` with io-uring, see [`DirectTcpListener`] for
+/// the limits of a direct descriptor.
+///
+/// The stream is closed when dropped, or by [`DirectTcpStream::close`] to
+/// wait for it.
+pub struct DirectTcpStream {
+    inner: StreamInner,
+}
+
+impl DirectTcpStream {
+    // Accept a connection of a normal listener as a direct descriptor, or as
+    // a normal stream if the kernel doesn't support it.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub(crate) async fn accept_from(listener: &TcpListener) -> io::Result<(Self, SockAddr)> {
+        if !RUNTIME.with(|runtime| runtime.supports_direct_accept()) {
+            let (stream, addr) = listener.accept().await?;
+            return Ok((
+                Self {
+                    inner: StreamInner::Fallback(Box::new(stream)),
+                },
+                addr,
+            ));
+        }
+        let op = AcceptDirect::from_fd(listener.as_raw_fd());
+        let (socket, addr) = DirectSocket::accept(op).await?;
+        Ok((
+            Self {
+                inner: StreamInner::Direct(socket),
+            },
+            addr,
+        ))
+    }
+
+    /// Whether the stream is a direct descriptor, or a normal socket as the
+    /// fallback.
+    pub fn is_direct(&self) -> bool {
+        !matches!(self.inner, StreamInner::Fallback(_))
+    }
+
+    /// Receives a packet of data from the socket into the buffer, returning
+    /// the original buffer and quantity of data received.
+    pub async fn recv<T: IoBufMut>(&self, buffer: T) -> BufResult<usize, T> {
+        match &self.inner {
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            StreamInner::Direct(socket) => submit(RecvDirect::new(socket.fd, buffer))
+                .await
+                .into_inner()
+                .map_advanced()
+                .into_inner(),
+            StreamInner::Fallback(stream) => stream.recv(buffer).await,
+        }
+    }
+
+    /// Sends some data to the socket from the buffer, returning the original
+    /// buffer and quantity of data sent.
+    pub async fn send<T: IoBuf>(&self, buffer: T) -> BufResult<usize, T> {
+        match &self.inner {
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            StreamInner::Direct(socket) => submit(SendDirect::new(socket.fd, buffer))
+                .await
+                .into_inner()
+                .into_inner(),
+            StreamInner::Fallback(stream) => stream.send(buffer).await,
+        }
+    }
+
+    /// Sends all the data in the buffer to the socket.
+    pub async fn send_all<T: IoBuf>(&self, mut buffer: T) -> BufResult<usize, T> {
+        let buf_len = buffer.buf_len();
+        let mut total_written = 0;
+        let mut written;
+        while total_written < buf_len {
+            (written, buffer) =
+                buf_try!(self.send(buffer.slice(total_written..)).await.into_inner());
+            total_written += written;
+        }
+        (Ok(total_written), buffer)
+    }
+    /// Shuts down the read, write, or both halves of this connection.
+    pub async fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match &self.inner {
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            StreamInner::Direct(socket) => {
+                submit(ShutdownDirect::new(socket.fd, how)).await.0?;
+                Ok(())
+            }
+            StreamInner::Fallback(stream) => stream.shutdown(how),
+        }
+    }
+
+    /// Closes the stream, and waits for it. The slot of a direct descriptor
+    /// is given back to the file table only after the close completes, so
+    /// that the accepts waiting for a free slot never find the table full.
+    pub async fn close(self) -> io::Result<()> {
+        match self.inner {
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            StreamInner::Direct(socket) => socket.close().await,
+            StreamInner::Fallback(stream) => {
+                drop(stream);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::io;
+
+/// The connection is torn down because the peer stopped acknowledging, i.e.,
+/// the keepalive probes are not answered, or the data sent is not
+/// acknowledged within the user timeout or the retransmission limit. It tells
+/// a dead peer from an idle one.
+///
+/// It is the inner error of the [`io::Error`] returned by the pending, or the
+/// next, receive or send of [`TcpStream`], with [`io::ErrorKind::TimedOut`].
+/// The error is reported once by the system, and the later ones fail with a
+/// broken pipe. See [`TcpStream::set_keepalive`] and
+/// [`TcpStream::set_user_timeout`] to detect it earlier.
+///
+/// ```
+/// use compio::net::PeerUnresponsive;
+///
+/// fn is_dead_peer(e: &std::io::Error) -> bool {
+///     e.get_ref().is_some_and(|e| e.is::<PeerUnresponsive>())
+/// }
+/// ```
+///
+/// ## Platform specific
+/// * Unix: `ETIMEDOUT`.
+/// * Windows: `WSAETIMEDOUT`, `WSAENETRESET`, or `ERROR_SEM_TIMEOUT` from the
+///   completion port.
+///
+/// [`TcpStream`]: crate::net::TcpStream
+/// [`TcpStream::set_keepalive`]: crate::net::TcpStream::set_keepalive
+/// [`TcpStream::set_user_timeout`]: crate::net::TcpStream::set_user_timeout
+#[derive(Debug)]
+pub struct PeerUnresponsive {
+    source: io::Error,
+}
+
+impl PeerUnresponsive {
+    /// The OS error reported by the socket.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.source.raw_os_error()
+    }
+}
+
+impl std::fmt::Display for PeerUnresponsive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the peer stopped acknowledging, detected by the keepalive probes or the \
+             retransmission timeout: {}",
+            self.source
+        )
+    }
+}
+
+impl std::error::Error for PeerUnresponsive {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+#[cfg(all(feature = "runtime", unix))]
+fn is_unresponsive(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(libc::ETIMEDOUT)
+}
+
+#[cfg(all(feature = "runtime", windows))]
+fn is_unresponsive(e: &io::Error) -> bool {
+    use windows_sys::Win32::{
+        Foundation::ERROR_SEM_TIMEOUT,
+        Networking::WinSock::{WSAENETRESET, WSAETIMEDOUT},
+    };
+
+    matches!(
+        e.raw_os_error(),
+        Some(code) if code == WSAETIMEDOUT || code == WSAENETRESET || code == ERROR_SEM_TIMEOUT as i32
+    )
+}
+
+// Wrap the error of an established connection if the peer is unresponsive.
+#[cfg(feature = "runtime")]
+pub(crate) fn map_unresponsive(e: io::Error) -> io::Error {
+    if is_unresponsive(&e) {
+        io::Error::new(io::ErrorKind::TimedOut, PeerUnresponsive { source: e })
+    } else {
+        e
+    }
+}
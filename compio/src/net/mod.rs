@@ -2,10 +2,37 @@
 //!
 //! Currently, TCP/UDP/Unix socket are implemented.
 
+#[cfg(feature = "runtime")]
+mod adaptive;
+#[cfg(feature = "runtime")]
+mod copy;
+#[cfg(feature = "runtime")]
+mod direct;
+mod keepalive;
+mod mtu;
+mod opts;
+#[cfg(feature = "time")]
+mod paced;
+#[cfg(feature = "time")]
+pub mod pool;
+#[cfg(feature = "time")]
+mod reconnect;
+mod recv_meta;
+#[cfg(feature = "runtime")]
+mod resolve;
+#[cfg(feature = "runtime")]
+mod send_file;
+#[cfg(feature = "runtime")]
+mod serve;
+#[cfg(feature = "runtime")]
+mod shared;
 mod socket;
 mod tcp;
+mod tcp_info;
+mod timestamp;
 mod udp;
 mod unix;
+mod zerocopy;
 
 use std::{
     future::Future,
@@ -13,11 +40,36 @@ use std::{
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs},
 };
 
+#[cfg(feature = "runtime")]
+pub use adaptive::*;
+#[cfg(feature = "runtime")]
+pub use copy::*;
+#[cfg(feature = "runtime")]
+pub use direct::*;
+pub use keepalive::*;
+pub use mtu::*;
+pub use opts::*;
+#[cfg(feature = "time")]
+pub use paced::*;
+#[cfg(feature = "time")]
+pub use reconnect::*;
+pub use recv_meta::*;
+#[cfg(feature = "runtime")]
+pub use resolve::*;
+#[cfg(feature = "runtime")]
+pub use send_file::*;
+#[cfg(feature = "runtime")]
+pub use serve::*;
+#[cfg(feature = "runtime")]
+pub use shared::*;
 pub(crate) use socket::*;
 use socket2::SockAddr;
 pub use tcp::*;
+pub use tcp_info::*;
+pub use timestamp::*;
 pub use udp::*;
 pub use unix::*;
+pub use zerocopy::*;
 
 use crate::BufResult;
 
@@ -0,0 +1,234 @@
+use std::io;
+
+/// How the path MTU is discovered, and whether the datagrams sent could be
+/// fragmented, see [`UdpSocket::set_mtu_discovery`].
+///
+/// ## Platform specific
+/// * Linux: `IP_MTU_DISCOVER` and `IPV6_MTU_DISCOVER`, with all the modes.
+/// * macOS, FreeBSD and Windows: `IP_DONTFRAG` and `IPV6_DONTFRAG`, where
+///   [`MtuDiscovery::Do`] and [`MtuDiscovery::Probe`] set the "don't
+///   fragment" bit, and the others clear it.
+///
+/// [`UdpSocket::set_mtu_discovery`]: crate::net::UdpSocket::set_mtu_discovery
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MtuDiscovery {
+    /// Fragment the datagrams larger than the path MTU locally, and set the
+    /// "don't fragment" bit otherwise. It is the default on Linux.
+    Want,
+    /// Set the "don't fragment" bit, and never fragment. Sending a datagram
+    /// larger than the known path MTU fails with [`DatagramTooLarge`].
+    Do,
+    /// Never set the "don't fragment" bit, so the routers could fragment the
+    /// datagrams.
+    Dont,
+    /// Set the "don't fragment" bit, and ignore the known path MTU, so that
+    /// the datagrams up to the MTU of the interface are sent, e.g., to probe
+    /// a larger path MTU.
+    Probe,
+}
+
+/// The datagram is larger than the path MTU, or than the socket could send
+/// at all, and it is not sent.
+///
+/// It is the inner error of the [`io::Error`] returned by the sends of
+/// [`UdpSocket`], with [`io::ErrorKind::InvalidInput`]. The path MTU is
+/// attached if the socket is connected and the platform reports it, so that
+/// the datagrams could be resized, e.g., by the path MTU probing of QUIC.
+///
+/// ```
+/// use compio::net::DatagramTooLarge;
+///
+/// fn path_mtu(e: &std::io::Error) -> Option<u32> {
+///     e.get_ref()?.downcast_ref::<DatagramTooLarge>()?.mtu()
+/// }
+/// ```
+///
+/// ## Platform specific
+/// * Unix: `EMSGSIZE`.
+/// * Windows: `WSAEMSGSIZE`.
+///
+/// [`UdpSocket`]: crate::net::UdpSocket
+#[derive(Debug)]
+pub struct DatagramTooLarge {
+    mtu: Option<u32>,
+    source: io::Error,
+}
+
+impl DatagramTooLarge {
+    /// The path MTU when the error is reported, if it could be retrieved.
+    pub fn mtu(&self) -> Option<u32> {
+        self.mtu
+    }
+
+    /// The OS error reported by the socket.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        self.source.raw_os_error()
+    }
+}
+
+impl std::fmt::Display for DatagramTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the datagram is too large to send")?;
+        if let Some(mtu) = self.mtu {
+            write!(f, " with the path MTU {mtu}")?;
+        }
+        write!(f, ": {}", self.source)
+    }
+}
+
+impl std::error::Error for DatagramTooLarge {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+#[cfg(all(feature = "runtime", unix))]
+fn is_too_large(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(libc::EMSGSIZE)
+}
+
+#[cfg(all(feature = "runtime", windows))]
+fn is_too_large(e: &io::Error) -> bool {
+    use windows_sys::Win32::Networking::WinSock::WSAEMSGSIZE;
+
+    e.raw_os_error() == Some(WSAEMSGSIZE)
+}
+
+// Wrap the error of a send if the datagram is too large, with the path MTU
+// queried only then.
+#[cfg(feature = "runtime")]
+pub(crate) fn map_too_large(e: io::Error, mtu: impl FnOnce() -> Option<u32>) -> io::Error {
+    if is_too_large(&e) {
+        let mtu = mtu();
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            DatagramTooLarge { mtu, source: e },
+        )
+    } else {
+        e
+    }
+}
+
+/// Where an [`ErrorEvent`] comes from.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorOrigin {
+    /// The local network stack, e.g., a datagram larger than the known path
+    /// MTU is sent.
+    Local,
+    /// An ICMP message.
+    Icmp,
+    /// An ICMPv6 message.
+    Icmp6,
+    /// Other origins, e.g., the notifications of the timestamps.
+    Other(u8),
+}
+
+/// An error of the datagrams sent before, received from the error queue of
+/// the socket, see [`UdpSocket::error_events`].
+///
+/// [`UdpSocket::error_events`]: crate::net::UdpSocket::error_events
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ErrorEvent {
+    /// The OS error code, e.g., `EMSGSIZE` for "packet too big" or
+    /// "fragmentation needed", and `ECONNREFUSED` for "port unreachable".
+    pub errno: i32,
+    /// Where the error comes from.
+    pub origin: ErrorOrigin,
+    /// The type of the ICMP message.
+    pub icmp_type: u8,
+    /// The code of the ICMP message.
+    pub icmp_code: u8,
+    /// The path MTU reported, if the datagram is too large.
+    pub mtu: Option<u32>,
+    /// The address of the node reporting the error, e.g., a router on the
+    /// path.
+    pub offender: Option<socket2::SockAddr>,
+    /// The destination of the datagram causing the error.
+    pub destination: Option<socket2::SockAddr>,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl ErrorEvent {
+    /// The error as an [`io::Error`].
+    pub fn error(&self) -> io::Error {
+        io::Error::from_raw_os_error(self.errno)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[cfg_attr(not(feature = "runtime"), allow(dead_code))]
+mod sys {
+    use std::mem::size_of;
+
+    use socket2::{SockAddr, SockAddrStorage};
+
+    use super::{ErrorEvent, ErrorOrigin};
+
+    /// The space of the control message of the extended error, followed by
+    /// the address of the offender.
+    pub fn control_len() -> usize {
+        let len = size_of::<libc::sock_extended_err>() + size_of::<libc::sockaddr_in6>();
+        unsafe { libc::CMSG_SPACE(len as _) as usize }
+    }
+
+    pub fn parse(msg: &libc::msghdr, destination: Option<SockAddr>) -> Option<ErrorEvent> {
+        let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(msg) };
+        while !cmsg.is_null() {
+            let (level, ty) = unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) };
+            if matches!(
+                (level, ty),
+                (libc::SOL_IP, libc::IP_RECVERR) | (libc::SOL_IPV6, libc::IPV6_RECVERR)
+            ) {
+                let data = unsafe { libc::CMSG_DATA(cmsg) };
+                let err =
+                    unsafe { std::ptr::read_unaligned(data.cast::<libc::sock_extended_err>()) };
+                let origin = match err.ee_origin {
+                    libc::SO_EE_ORIGIN_LOCAL => ErrorOrigin::Local,
+                    libc::SO_EE_ORIGIN_ICMP => ErrorOrigin::Icmp,
+                    libc::SO_EE_ORIGIN_ICMP6 => ErrorOrigin::Icmp6,
+                    origin => ErrorOrigin::Other(origin),
+                };
+                return Some(ErrorEvent {
+                    errno: err.ee_errno as _,
+                    origin,
+                    icmp_type: err.ee_type,
+                    icmp_code: err.ee_code,
+                    mtu: (err.ee_errno == libc::EMSGSIZE as u32).then_some(err.ee_info),
+                    offender: unsafe { offender(cmsg, data) },
+                    destination,
+                });
+            }
+            cmsg = unsafe { libc::CMSG_NXTHDR(msg, cmsg) };
+        }
+        None
+    }
+
+    // The address after the extended error, `SO_EE_OFFENDER`, which is
+    // `AF_UNSPEC` if unknown.
+    #[allow(clippy::unnecessary_cast)]
+    unsafe fn offender(cmsg: *const libc::cmsghdr, data: *const u8) -> Option<SockAddr> {
+        let start = size_of::<libc::sock_extended_err>();
+        let len = ((*cmsg).cmsg_len as usize - (data as usize - cmsg as usize))
+            .checked_sub(start)?
+            .min(size_of::<libc::sockaddr_in6>());
+        let mut storage = SockAddrStorage::zeroed();
+        std::ptr::copy_nonoverlapping(
+            data.add(start),
+            std::ptr::addr_of_mut!(storage).cast::<u8>(),
+            len,
+        );
+        if len < size_of::<libc::sa_family_t>()
+            || storage.view_as::<libc::sockaddr_storage>().ss_family as libc::c_int
+                == libc::AF_UNSPEC
+        {
+            return None;
+        }
+        Some(SockAddr::new(storage, len as _))
+    }
+}
+
+#[cfg(all(feature = "runtime", any(target_os = "linux", target_os = "android")))]
+pub(crate) use sys::*;
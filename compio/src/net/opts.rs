@@ -0,0 +1,294 @@
+#[cfg(feature = "time")]
+use std::time::Duration;
+use std::{
+    io,
+    net::{IpAddr, SocketAddr, SocketAddrV4},
+};
+
+use socket2::{Domain, SockAddr};
+
+use crate::net::Socket;
+
+/// The options applied to a socket before it is bound or connected, see
+/// [`TcpListener::bind_with`](crate::net::TcpListener::bind_with),
+/// [`TcpStream::connect_with`](crate::net::TcpStream::connect_with) and
+/// [`UdpSocket::bind_with`](crate::net::UdpSocket::bind_with).
+///
+/// An option not available on the platform fails the bind or the connection
+/// with [`io::ErrorKind::Unsupported`].
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use compio::net::{SocketOpts, UdpSocket};
+///
+/// let opts = SocketOpts::new().bind_device(Some("lo"));
+/// # if cfg!(target_os = "linux") {
+/// let socket = UdpSocket::bind_with((Ipv4Addr::LOCALHOST, 0), &opts).unwrap();
+/// # }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SocketOpts {
+    device: Option<Device>,
+    freebind: bool,
+    only_v6: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Device {
+    Name(String),
+    Index(u32),
+}
+
+impl SocketOpts {
+    /// No options, the same as [`TcpListener::bind`] and others.
+    ///
+    /// [`TcpListener::bind`]: crate::net::TcpListener::bind
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind the socket to the interface of the name, so that only the packets
+    /// of the interface are received, and the packets are sent through it.
+    /// `None` leaves the socket unbound.
+    ///
+    /// ## Platform specific
+    /// * Linux: `SO_BINDTODEVICE`. It requires `CAP_NET_RAW` before Linux 5.7.
+    /// * macOS, iOS, Solaris and illumos: `IP_BOUND_IF` or `IPV6_BOUND_IF` with
+    ///   the index of the interface.
+    /// * Windows: `IP_UNICAST_IF` or `IPV6_UNICAST_IF` with the index of the
+    ///   interface, which only restricts the sending.
+    pub fn bind_device(mut self, name: Option<&str>) -> Self {
+        self.device = name.map(|name| Device::Name(name.to_string()));
+        self
+    }
+
+    /// Bind the socket to the interface of the index, see
+    /// [`SocketOpts::bind_device`]. The index 0 leaves the socket unbound.
+    pub fn bind_device_by_index(mut self, index: u32) -> Self {
+        self.device = (index != 0).then_some(Device::Index(index));
+        self
+    }
+
+    /// Allow binding to an address which is not configured yet, e.g., an
+    /// address to be assigned to an interface later.
+    ///
+    /// ## Platform specific
+    /// * Linux: `IP_FREEBIND` or `IPV6_FREEBIND`.
+    /// * FreeBSD: `IP_BINDANY` or `IPV6_BINDANY`, which requires privilege.
+    /// * Others: unsupported.
+    pub fn freebind(mut self, freebind: bool) -> Self {
+        self.freebind = freebind;
+        self
+    }
+
+    /// Accept only IPv6 on an IPv6 socket, `IPV6_V6ONLY`, or also accept IPv4
+    /// as the IPv4-mapped IPv6 addresses, e.g., `::ffff:127.0.0.1`. It is
+    /// ignored for the other addresses.
+    ///
+    /// The default differs among the platforms: dual-stack on Linux unless
+    /// `net.ipv6.bindv6only` is set, and IPv6-only on Windows and OpenBSD. Set
+    /// it explicitly for the same behavior everywhere.
+    pub fn only_v6(mut self, only_v6: bool) -> Self {
+        self.only_v6 = Some(only_v6);
+        self
+    }
+
+    pub(crate) fn apply(&self, socket: &Socket, domain: Domain) -> io::Result<()> {
+        if let (Some(only_v6), Domain::IPV6) = (self.only_v6, domain) {
+            socket.set_only_v6(only_v6)?;
+        }
+        match &self.device {
+            Some(Device::Name(name)) => socket.bind_device(Some(name), domain)?,
+            Some(Device::Index(index)) => socket.bind_device_by_index(*index, domain)?,
+            None => {}
+        }
+        if self.freebind {
+            socket.set_freebind(true, domain)?;
+        }
+        Ok(())
+    }
+}
+
+/// The options of an outgoing TCP connection, see
+/// [`TcpStream::connect_with`](crate::net::TcpStream::connect_with). They are
+/// applied after the socket is created and before it is connected, and the
+/// same options are applied to each attempt when the host resolves to several
+/// addresses, so they could be reused across connections.
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use compio::net::{TcpConnectOpts, TcpListener, TcpStream};
+///
+/// compio::task::block_on(async {
+///     let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     let addr = listener.local_addr().unwrap();
+///
+///     let opts = TcpConnectOpts::new()
+///         .local_addr((Ipv4Addr::LOCALHOST, 0).into())
+///         .nodelay(true);
+///     let (client, accepted) =
+///         futures_util::join!(TcpStream::connect_with(&addr, &opts), listener.accept());
+///     let client = client.unwrap();
+///     let (_, remote) = accepted.unwrap();
+///     assert_eq!(client.local_addr().unwrap(), remote);
+/// })
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TcpConnectOpts {
+    socket: SocketOpts,
+    local_addr: Option<SocketAddr>,
+    mark: Option<u32>,
+    tos: Option<u32>,
+    nodelay: bool,
+    #[cfg(feature = "time")]
+    timeout: Option<Duration>,
+}
+
+impl TcpConnectOpts {
+    /// No options, the same as
+    /// [`TcpStream::connect`](crate::net::TcpStream::connect).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply the options shared with the listeners and the UDP sockets.
+    pub fn socket_opts(mut self, opts: SocketOpts) -> Self {
+        self.socket = opts;
+        self
+    }
+
+    /// Bind the local end to the address before connecting, e.g., to choose
+    /// the source IP on a multi-homed host. The port 0 lets the system choose
+    /// one. An address not available fails the connection before the
+    /// connecting attempt, with [`io::ErrorKind::AddrNotAvailable`].
+    ///
+    /// On Linux, `IP_BIND_ADDRESS_NO_PORT` is set for the port 0, so that the
+    /// port is chosen at connecting, and could be shared with the
+    /// connections to the other remote addresses.
+    pub fn local_addr(mut self, addr: SocketAddr) -> Self {
+        self.local_addr = Some(addr);
+        self
+    }
+
+    /// Bind the socket to the interface of the name, see
+    /// [`SocketOpts::bind_device`].
+    pub fn bind_device(mut self, name: Option<&str>) -> Self {
+        self.socket = self.socket.bind_device(name);
+        self
+    }
+
+    /// Set the mark of the packets for the policy routing, i.e., `SO_MARK`. It
+    /// requires `CAP_NET_ADMIN`.
+    ///
+    /// ## Platform specific
+    /// * Linux and Android: `SO_MARK`.
+    /// * Others: unsupported.
+    pub fn mark(mut self, mark: u32) -> Self {
+        self.mark = Some(mark);
+        self
+    }
+
+    /// Set the type of service of the packets, e.g., the DSCP marking shifted
+    /// by 2 bits, i.e., `IP_TOS` or `IPV6_TCLASS`.
+    ///
+    /// ## Platform specific
+    /// * Windows: only IPv4 is supported, and it may be ignored by the system.
+    pub fn tos(mut self, tos: u32) -> Self {
+        self.tos = Some(tos);
+        self
+    }
+
+    /// Disable the Nagle algorithm, i.e., `TCP_NODELAY`. Default to `false`.
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    /// Fail each connecting attempt with [`io::ErrorKind::TimedOut`] if it
+    /// doesn't complete in `timeout`. Default to no timeout.
+    #[cfg(feature = "time")]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    #[cfg(feature = "time")]
+    pub(crate) fn connect_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub(crate) fn local(&self) -> Option<SockAddr> {
+        self.local_addr.map(SockAddr::from)
+    }
+
+    pub(crate) fn apply(&self, socket: &Socket, domain: Domain) -> io::Result<()> {
+        self.socket.apply(socket, domain)?;
+        if let Some(mark) = self.mark {
+            socket.set_mark(mark)?;
+        }
+        if let Some(tos) = self.tos {
+            socket.set_tos(tos, domain)?;
+        }
+        if self.nodelay {
+            socket.set_nodelay(true)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<SocketOpts> for TcpConnectOpts {
+    fn from(opts: SocketOpts) -> Self {
+        Self::new().socket_opts(opts)
+    }
+}
+
+impl From<&SocketOpts> for TcpConnectOpts {
+    fn from(opts: &SocketOpts) -> Self {
+        Self::from(opts.clone())
+    }
+}
+
+impl From<&TcpConnectOpts> for TcpConnectOpts {
+    fn from(opts: &TcpConnectOpts) -> Self {
+        opts.clone()
+    }
+}
+
+/// How the IPv4-mapped IPv6 addresses are given back, e.g., by
+/// [`TcpListener::accept`] and [`UdpSocket::recv_from`] on a dual-stack
+/// socket, see [`SocketOpts::only_v6`]. The policy is applied by compio
+/// itself, so it is the same with all the drivers.
+///
+/// [`TcpListener::accept`]: crate::net::TcpListener::accept
+/// [`UdpSocket::recv_from`]: crate::net::UdpSocket::recv_from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MappedAddrPolicy {
+    /// Give back the addresses as is, e.g., `[::ffff:127.0.0.1]:80`.
+    #[default]
+    Keep,
+    /// Convert the IPv4-mapped addresses to IPv4, e.g., `127.0.0.1:80`. The
+    /// flow info and the scope ID are dropped with them.
+    Unmap,
+}
+
+impl MappedAddrPolicy {
+    pub(crate) fn apply(self, addr: SockAddr) -> SockAddr {
+        match (self, addr.as_socket()) {
+            (Self::Unmap, Some(SocketAddr::V6(v6))) => match v6.ip().to_ipv4_mapped() {
+                Some(ip) => SocketAddrV4::new(ip, v6.port()).into(),
+                None => addr,
+            },
+            _ => addr,
+        }
+    }
+
+    #[cfg(all(feature = "runtime", unix))]
+    pub(crate) fn apply_ip(self, ip: IpAddr) -> IpAddr {
+        match (self, ip) {
+            (Self::Unmap, IpAddr::V6(v6)) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            _ => ip,
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use socket2::SockAddr;
+
+use crate::{
+    buf::IoBuf,
+    driver::AsRawFd,
+    net::{each_addr, ToSockAddrs, UdpSocket},
+    op::SendTo,
+    task::submit,
+    time::sleep_until,
+};
+
+#[cfg(target_os = "linux")]
+mod txtime {
+    use std::{io, mem::MaybeUninit, time::Instant};
+
+    use crate::{driver::RawFd, syscall};
+
+    // Not exported by libc yet.
+    const SO_EE_ORIGIN_TXTIME: u8 = 6;
+    const SO_EE_CODE_TXTIME_MISSED: u8 = 2;
+
+    /// Enable `SO_TXTIME` with `CLOCK_MONOTONIC`, and ask the kernel to report
+    /// the dropped packets in the error queue.
+    pub fn enable(fd: RawFd) -> io::Result<()> {
+        let config = libc::sock_txtime {
+            clockid: libc::CLOCK_MONOTONIC,
+            flags: libc::SOF_TXTIME_REPORT_ERRORS,
+        };
+        syscall!(setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_TXTIME,
+            std::ptr::addr_of!(config).cast(),
+            std::mem::size_of::<libc::sock_txtime>() as _,
+        ))?;
+        Ok(())
+    }
+
+    /// Build the `SCM_TXTIME` control message for the deadline.
+    pub fn control(deadline: Instant) -> io::Result<Vec<u8>> {
+        let mut now = MaybeUninit::<libc::timespec>::uninit();
+        syscall!(clock_gettime(libc::CLOCK_MONOTONIC, now.as_mut_ptr()))?;
+        let now = unsafe { now.assume_init() };
+        let txtime = (now.tv_sec as u64 * 1_000_000_000 + now.tv_nsec as u64)
+            + deadline
+                .saturating_duration_since(Instant::now())
+                .as_nanos() as u64;
+
+        let space = unsafe { libc::CMSG_SPACE(std::mem::size_of::<u64>() as _) } as usize;
+        // Aligned for `cmsghdr`.
+        let mut control = [0u64; 4];
+        debug_assert!(space <= std::mem::size_of_val(&control));
+        unsafe {
+            let mut msg: libc::msghdr = std::mem::zeroed();
+            msg.msg_control = control.as_mut_ptr().cast();
+            msg.msg_controllen = space as _;
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_TXTIME;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(std::mem::size_of::<u64>() as _) as _;
+            std::ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<u64>(), txtime);
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(control.as_ptr().cast::<u8>(), space) };
+        Ok(bytes.to_vec())
+    }
+
+    /// Drain the error queue of the socket. Returns the number of packets
+    /// dropped because their deadlines were missed.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn drain_errors(fd: RawFd) -> io::Result<usize> {
+        let err_len =
+            unsafe { libc::CMSG_LEN(std::mem::size_of::<libc::sock_extended_err>() as _) } as usize;
+        let mut missed = 0;
+        loop {
+            let mut data = [0u8; 1];
+            let mut control = [0u64; 64];
+            let mut iov = libc::iovec {
+                iov_base: data.as_mut_ptr().cast(),
+                iov_len: data.len(),
+            };
+            let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            msg.msg_control = control.as_mut_ptr().cast();
+            msg.msg_controllen = std::mem::size_of_val(&control) as _;
+            let res = syscall!(recvmsg(
+                fd,
+                &mut msg,
+                libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT
+            ));
+            match res {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+            let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+            while !cmsg.is_null() {
+                let (level, ty, len) =
+                    unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type, (*cmsg).cmsg_len) };
+                if !matches!(
+                    (level, ty),
+                    (libc::SOL_IP, libc::IP_RECVERR) | (libc::SOL_IPV6, libc::IPV6_RECVERR)
+                ) || (len as usize) < err_len
+                {
+                    cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+                    continue;
+                }
+                let err = unsafe {
+                    std::ptr::read_unaligned(
+                        libc::CMSG_DATA(cmsg).cast::<libc::sock_extended_err>(),
+                    )
+                };
+                if err.ee_origin == SO_EE_ORIGIN_TXTIME {
+                    if err.ee_code == SO_EE_CODE_TXTIME_MISSED {
+                        missed += 1;
+                    } else {
+                        return Err(io::Error::from_raw_os_error(err.ee_errno as _));
+                    }
+                }
+                cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+            }
+        }
+        Ok(missed)
+    }
+}
+
+fn send_time_passed() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::TimedOut,
+        "the scheduled send time has passed",
+    )
+}
+
+/// A UDP sender which sends packets at scheduled times.
+///
+/// On Linux, it tries to enable `SO_TXTIME`, and the packets are handed to the
+/// kernel immediately, with the send time attached as an `SCM_TXTIME` control
+/// message. The kernel only respects it with a time-based qdisc, e.g., `etf`
+/// or `fq`; otherwise the packets are sent without delay.
+///
+/// If `SO_TXTIME` is not available, the packets are paced by the runtime
+/// timer. The packets sharing the same send time are queued as a batch, and
+/// the batch is sent when a packet with a different send time is scheduled, or
+/// when [`flush`](PacedUdpSender::flush) is called.
+///
+/// A packet whose send time has passed by more than the
+/// [tolerance](PacedUdpSender::set_tolerance) is rejected with
+/// [`io::ErrorKind::TimedOut`].
+///
+/// ```
+/// use std::{
+///     net::Ipv4Addr,
+///     time::{Duration, Instant},
+/// };
+///
+/// use compio::net::{PacedUdpSender, UdpSocket};
+///
+/// compio::task::block_on(async {
+///     let receiver = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     let addr = receiver.local_addr().unwrap();
+///
+///     let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     let mut sender = PacedUdpSender::new(socket);
+///     let start = Instant::now();
+///     for i in 0..3 {
+///         let send_time = start + Duration::from_millis(10 * i);
+///         sender
+///             .send_scheduled(b"hello".to_vec(), &addr, send_time)
+///             .await
+///             .unwrap();
+///     }
+///     sender.flush().await.unwrap();
+/// })
+/// ```
+pub struct PacedUdpSender<T: IoBuf = Vec<u8>> {
+    socket: UdpSocket,
+    #[cfg(target_os = "linux")]
+    txtime: bool,
+    tolerance: Duration,
+    deadline: Option<Instant>,
+    batch: Vec<(T, SockAddr)>,
+}
+
+impl<T: IoBuf> PacedUdpSender<T> {
+    const DEFAULT_TOLERANCE: Duration = Duration::from_millis(1);
+
+    /// Create [`PacedUdpSender`], using `SO_TXTIME` if available.
+    pub fn new(socket: UdpSocket) -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            let txtime = txtime::enable(socket.as_raw_fd()).is_ok();
+            let mut this = Self::with_timer(socket);
+            this.txtime = txtime;
+            this
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Self::with_timer(socket)
+        }
+    }
+
+    /// Create [`PacedUdpSender`] which always paces the packets by the runtime
+    /// timer.
+    pub fn with_timer(socket: UdpSocket) -> Self {
+        Self {
+            socket,
+            #[cfg(target_os = "linux")]
+            txtime: false,
+            tolerance: Self::DEFAULT_TOLERANCE,
+            deadline: None,
+            batch: vec![],
+        }
+    }
+
+    /// Returns `true` if the packets are paced by the kernel with
+    /// `SO_TXTIME`.
+    pub fn is_kernel_paced(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            self.txtime
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            false
+        }
+    }
+
+    /// The maximum delay of the send time before a packet is rejected.
+    pub fn tolerance(&self) -> Duration {
+        self.tolerance
+    }
+
+    /// Set the maximum delay of the send time before a packet is rejected.
+    /// The default value is 1ms.
+    pub fn set_tolerance(&mut self, tolerance: Duration) {
+        self.tolerance = tolerance;
+    }
+
+    /// Gets a reference to the underlying socket.
+    pub fn get_ref(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    /// Consumes the sender, returning the underlying socket. The queued
+    /// packets are dropped, call [`flush`](PacedUdpSender::flush) before it.
+    pub fn into_inner(self) -> UdpSocket {
+        self.socket
+    }
+
+    /// Schedule the buffer to be sent to the address at `send_time`.
+    ///
+    /// If the packets are paced by the timer, the previous batch is sent first
+    /// if its send time is different, so this method waits until then.
+    pub async fn send_scheduled(
+        &mut self,
+        buffer: T,
+        addr: impl ToSockAddrs,
+        send_time: Instant,
+    ) -> io::Result<()> {
+        let addr = each_addr(addr, Ok)?;
+        if send_time + self.tolerance < Instant::now() {
+            return Err(send_time_passed());
+        }
+        #[cfg(target_os = "linux")]
+        if self.txtime {
+            let control = txtime::control(send_time)?;
+            return self.send_to(buffer, addr, control).await;
+        }
+        if self.deadline.is_some_and(|deadline| deadline != send_time) {
+            self.flush_batch().await?;
+        }
+        self.deadline = Some(send_time);
+        self.batch.push((buffer, addr));
+        Ok(())
+    }
+
+    /// Send all the queued packets, waiting for their send time.
+    ///
+    /// If the packets are paced by the kernel, it checks the error queue of
+    /// the socket instead, and returns an error if the kernel dropped packets
+    /// because their send time had passed.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        if self.txtime {
+            return match txtime::drain_errors(self.socket.as_raw_fd())? {
+                0 => Ok(()),
+                _ => Err(send_time_passed()),
+            };
+        }
+        self.flush_batch().await
+    }
+
+    async fn flush_batch(&mut self) -> io::Result<()> {
+        let Some(deadline) = self.deadline.take() else {
+            return Ok(());
+        };
+        sleep_until(deadline).await;
+        let mut res = Ok(());
+        for (buffer, addr) in std::mem::take(&mut self.batch) {
+            // Send the rest of the batch even if one of them fails.
+            if let Err(e) = self.send_to(buffer, addr, vec![]).await {
+                res = res.and(Err(e));
+            }
+        }
+        res
+    }
+
+    #[allow(unused_variables)]
+    async fn send_to(&self, buffer: T, addr: SockAddr, control: Vec<u8>) -> io::Result<()> {
+        self.socket.attach()?;
+        #[cfg(unix)]
+        let op = SendTo::with_control(self.socket.as_raw_fd(), buffer, addr, control);
+        #[cfg(not(unix))]
+        let op = SendTo::new(self.socket.as_raw_fd(), buffer, addr);
+        submit(op).await.0?;
+        Ok(())
+    }
+}
@@ -0,0 +1,677 @@
+//! A pool of the outbound connections, reused across the requests to the
+//! same endpoints.
+//!
+//! The pool belongs to the runtime of the current thread, like the other
+//! types of the runtime. The connections are created by a connector closure,
+//! so that any connection type could be pooled, e.g., a TLS stream wrapping a
+//! [`TcpStream`](crate::net::TcpStream).
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use compio::net::{pool::ConnectionPool, TcpStream};
+//!
+//! compio::task::block_on(async {
+//!     let pool = ConnectionPool::builder(|addr: &String| {
+//!         Box::pin(async move { TcpStream::connect(addr.as_str()).await })
+//!     })
+//!     .max_connections(4)
+//!     .max_idle(Some(Duration::from_secs(30)))
+//!     .check_readiness()
+//!     .build();
+//!
+//!     let conn = pool.get("127.0.0.1:8080".to_string()).await.unwrap();
+//!     conn.send_all("GET key\n").await.0.unwrap();
+//!     // The connection is returned to the pool when dropped.
+//!     drop(conn);
+//!     pool.shutdown().await;
+//! })
+//! ```
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Debug,
+    future::poll_fn,
+    hash::Hash,
+    io,
+    ops::{Deref, DerefMut},
+    rc::{Rc, Weak},
+    task::{Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use futures_util::future::{join_all, LocalBoxFuture};
+
+use crate::{
+    net::TcpStream,
+    time::{now, sleep_until},
+};
+
+type Connector<E, C> = Box<dyn for<'a> Fn(&'a E) -> LocalBoxFuture<'a, io::Result<C>>>;
+type HealthCheck<C> = Box<dyn for<'a> Fn(&'a C) -> LocalBoxFuture<'a, bool>>;
+type Closer<C> = Box<dyn Fn(C) -> LocalBoxFuture<'static, ()>>;
+
+/// The builder of [`ConnectionPool`].
+pub struct PoolBuilder<E, C> {
+    connector: Connector<E, C>,
+    health_check: Option<HealthCheck<C>>,
+    closer: Option<Closer<C>>,
+    max_connections: usize,
+    max_idle: Option<Duration>,
+}
+
+impl<E: Eq + Hash + Clone + 'static, C: 'static> PoolBuilder<E, C> {
+    /// Set the maximum count of the connections to each endpoint, including
+    /// the idle ones and the ones being connected. The callers of
+    /// [`ConnectionPool::get`] wait in order when the limit is reached.
+    /// Default to 8.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero.
+    pub fn max_connections(mut self, max: usize) -> Self {
+        assert!(max > 0, "the pool should allow at least one connection");
+        self.max_connections = max;
+        self
+    }
+
+    /// Set how long a connection may stay idle in the pool before it is
+    /// closed. `None` means keeping them forever. Default to 90 seconds.
+    pub fn max_idle(mut self, duration: Option<Duration>) -> Self {
+        self.max_idle = duration;
+        self
+    }
+
+    /// Set the check of an idle connection before it is handed out. The
+    /// connection is closed if it returns `false`, and another one is used.
+    /// It isn't called on the connections handed from a returning guard to a
+    /// waiter directly.
+    ///
+    /// See [`is_idle_healthy`] for a readiness probe of the TCP connections.
+    pub fn health_check(
+        mut self,
+        f: impl for<'a> Fn(&'a C) -> LocalBoxFuture<'a, bool> + 'static,
+    ) -> Self {
+        self.health_check = Some(Box::new(f));
+        self
+    }
+
+    /// Set how a connection is closed, e.g., with a graceful shutdown. The
+    /// connections are dropped by default. The closes out of
+    /// [`ConnectionPool::shutdown`] are spawned as detached tasks.
+    pub fn close_with(mut self, f: impl Fn(C) -> LocalBoxFuture<'static, ()> + 'static) -> Self {
+        self.closer = Some(Box::new(f));
+        self
+    }
+
+    /// Build the pool.
+    pub fn build(self) -> ConnectionPool<E, C> {
+        ConnectionPool {
+            shared: Rc::new(Shared {
+                connector: self.connector,
+                health_check: self.health_check,
+                closer: self.closer,
+                max_connections: self.max_connections,
+                max_idle: self.max_idle,
+                state: RefCell::new(State {
+                    endpoints: HashMap::new(),
+                    next_ticket: 0,
+                    closed: false,
+                }),
+                reaping: Cell::new(false),
+            }),
+        }
+    }
+}
+
+impl<E: Eq + Hash + Clone + 'static> PoolBuilder<E, TcpStream> {
+    /// Check the idle connections with [`is_idle_healthy`] before they are
+    /// handed out.
+    pub fn check_readiness(self) -> Self {
+        self.health_check(|stream| Box::pin(std::future::ready(is_idle_healthy(stream))))
+    }
+}
+
+/// The readiness probe of an idle connection of a request-response protocol,
+/// e.g., a keep-alive HTTP connection. The connection shouldn't be readable
+/// while idle, otherwise the peer has closed it, the connection is reset, or
+/// the peer has sent something unexpected. A wrapped stream, e.g., a TLS one,
+/// could be checked with its underlying [`TcpStream`] in
+/// [`PoolBuilder::health_check`], if the wrapper buffers nothing.
+///
+/// It is a probe without waiting, so a half-dead connection whose peer has
+/// vanished without a FIN or RST isn't detected.
+pub fn is_idle_healthy(stream: &TcpStream) -> bool {
+    matches!(stream.is_readable(), Ok(false))
+}
+
+/// A pool of the connections keyed by the endpoints, with a limit of the
+/// connections to each endpoint.
+///
+/// The idle connections are reused in the order of the most recently
+/// returned one first, and they are closed by a task on the runtime timer
+/// after the max idle duration. A connection is handed to only one
+/// [`PooledConnection`] at a time. When the limit of an endpoint is reached,
+/// the callers of [`ConnectionPool::get`] wait in order, and a returned
+/// connection or a released slot goes to the first of them.
+pub struct ConnectionPool<E, C> {
+    shared: Rc<Shared<E, C>>,
+}
+
+impl<E: Eq + Hash + Clone + 'static, C: 'static> ConnectionPool<E, C> {
+    /// Create the builder with the connector, which creates a new connection
+    /// to the endpoint.
+    pub fn builder(
+        connector: impl for<'a> Fn(&'a E) -> LocalBoxFuture<'a, io::Result<C>> + 'static,
+    ) -> PoolBuilder<E, C> {
+        PoolBuilder {
+            connector: Box::new(connector),
+            health_check: None,
+            closer: None,
+            max_connections: 8,
+            max_idle: Some(Duration::from_secs(90)),
+        }
+    }
+
+    /// Get a connection to the endpoint: a healthy idle one, or a new one if
+    /// the limit isn't reached, otherwise wait for one to be returned.
+    ///
+    /// It fails if the connector fails, or the pool is shut down.
+    pub async fn get(&self, endpoint: E) -> io::Result<PooledConnection<E, C>> {
+        let shared = &self.shared;
+        let mut acquire = Acquire {
+            shared,
+            endpoint: &endpoint,
+            ticket: None,
+        };
+        let handoff = poll_fn(|cx| acquire.poll(cx.waker())).await?;
+        drop(acquire);
+        // The slot is released if the connector or the check fails, or the
+        // future is cancelled.
+        let slot = Slot {
+            shared: shared.clone(),
+            endpoint: Some(endpoint.clone()),
+        };
+        let mut candidate = match handoff {
+            Handoff::Conn(conn) => return Ok(slot.into_pooled(conn)),
+            Handoff::Idle(conn) => Some(conn),
+            Handoff::Slot => None,
+        };
+        loop {
+            match candidate.take() {
+                Some(conn) => {
+                    let healthy = match &shared.health_check {
+                        Some(check) => check(&conn).await,
+                        None => true,
+                    };
+                    if healthy {
+                        return Ok(slot.into_pooled(conn));
+                    }
+                    shared.close(conn);
+                    // Take another idle connection with the same slot.
+                    candidate = shared.take_idle(&endpoint);
+                }
+                None => {
+                    let conn = (shared.connector)(&endpoint).await?;
+                    return Ok(slot.into_pooled(conn));
+                }
+            }
+        }
+    }
+
+    /// The count of the connections to the endpoint, including the idle
+    /// ones, the ones in use, and the ones being connected.
+    pub fn connections(&self, endpoint: &E) -> usize {
+        self.shared
+            .state
+            .borrow()
+            .endpoints
+            .get(endpoint)
+            .map_or(0, |ep| ep.idle.len() + ep.busy)
+    }
+
+    /// The count of the idle connections to the endpoint.
+    pub fn idle_connections(&self, endpoint: &E) -> usize {
+        self.shared
+            .state
+            .borrow()
+            .endpoints
+            .get(endpoint)
+            .map_or(0, |ep| ep.idle.len())
+    }
+
+    /// Whether [`ConnectionPool::shutdown`] is called.
+    pub fn is_shutdown(&self) -> bool {
+        self.shared.state.borrow().closed
+    }
+
+    /// Shut down the pool, and close the idle connections concurrently. The
+    /// waiting and the later [`ConnectionPool::get`] fail, and the
+    /// connections in use are closed when returned.
+    pub async fn shutdown(&self) {
+        let (idle, waiters) = {
+            let mut state = self.shared.state.borrow_mut();
+            state.closed = true;
+            let mut idle = vec![];
+            let mut waiters = vec![];
+            for ep in state.endpoints.values_mut() {
+                idle.extend(ep.idle.drain(..).map(|idle| idle.conn));
+                waiters.extend(ep.waiters.values_mut().filter_map(|w| w.waker.take()));
+            }
+            state.endpoints.retain(|_, ep| !ep.is_empty());
+            (idle, waiters)
+        };
+        for waker in waiters {
+            waker.wake();
+        }
+        match &self.shared.closer {
+            Some(closer) => {
+                join_all(idle.into_iter().map(closer)).await;
+            }
+            None => drop(idle),
+        }
+    }
+}
+
+impl<E, C> Debug for ConnectionPool<E, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.shared.state.borrow();
+        f.debug_struct("ConnectionPool")
+            .field("endpoints", &state.endpoints.len())
+            .field("max_connections", &self.shared.max_connections)
+            .field("max_idle", &self.shared.max_idle)
+            .field("closed", &state.closed)
+            .finish()
+    }
+}
+
+/// A connection taken from [`ConnectionPool`]. It is returned to the pool
+/// when dropped, unless detached or marked broken.
+pub struct PooledConnection<E: Eq + Hash + Clone + 'static, C: 'static> {
+    shared: Rc<Shared<E, C>>,
+    endpoint: E,
+    conn: Option<C>,
+    broken: bool,
+}
+
+impl<E: Eq + Hash + Clone + 'static, C: 'static> PooledConnection<E, C> {
+    /// The endpoint of the connection.
+    pub fn endpoint(&self) -> &E {
+        &self.endpoint
+    }
+
+    /// Mark the connection broken, e.g., after an IO error or a protocol
+    /// violation. It is closed instead of returned to the pool when dropped.
+    pub fn mark_broken(&mut self) {
+        self.broken = true;
+    }
+
+    /// Whether the connection is marked broken.
+    pub fn is_broken(&self) -> bool {
+        self.broken
+    }
+
+    /// Take the connection out of the pool. It no longer counts to the limit
+    /// of the endpoint.
+    pub fn detach(mut self) -> C {
+        let conn = self.conn.take().expect("the connection should exist");
+        self.shared.release(&self.endpoint, Handoff::Slot);
+        conn
+    }
+}
+
+impl<E: Eq + Hash + Clone + 'static, C: 'static> Deref for PooledConnection<E, C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        self.conn.as_ref().expect("the connection should exist")
+    }
+}
+
+impl<E: Eq + Hash + Clone + 'static, C: 'static> DerefMut for PooledConnection<E, C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.conn.as_mut().expect("the connection should exist")
+    }
+}
+
+impl<E: Eq + Hash + Clone + Debug + 'static, C: Debug + 'static> Debug for PooledConnection<E, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PooledConnection")
+            .field("endpoint", &self.endpoint)
+            .field("conn", &self.conn)
+            .field("broken", &self.broken)
+            .finish()
+    }
+}
+
+impl<E: Eq + Hash + Clone + 'static, C: 'static> Drop for PooledConnection<E, C> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            if self.broken {
+                self.shared.close(conn);
+                self.shared.release(&self.endpoint, Handoff::Slot);
+            } else {
+                self.shared.release(&self.endpoint, Handoff::Conn(conn));
+            }
+        }
+    }
+}
+
+// What a caller of `get` acquires: an idle connection to check, a connection
+// just returned, or a slot to connect.
+enum Handoff<C> {
+    Idle(C),
+    Conn(C),
+    Slot,
+}
+
+struct IdleConn<C> {
+    conn: C,
+    since: Instant,
+}
+
+struct Waiter<C> {
+    waker: Option<Waker>,
+    handoff: Option<Handoff<C>>,
+}
+
+struct Endpoint<C> {
+    // The most recently returned one is at the back.
+    idle: VecDeque<IdleConn<C>>,
+    // The connections in use, being connected or checked.
+    busy: usize,
+    // Ordered by the tickets.
+    waiters: BTreeMap<u64, Waiter<C>>,
+}
+
+impl<C> Endpoint<C> {
+    fn new() -> Self {
+        Self {
+            idle: VecDeque::new(),
+            busy: 0,
+            waiters: BTreeMap::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.idle.is_empty() && self.busy == 0 && self.waiters.is_empty()
+    }
+}
+
+struct State<E, C> {
+    endpoints: HashMap<E, Endpoint<C>>,
+    next_ticket: u64,
+    closed: bool,
+}
+
+struct Shared<E, C> {
+    connector: Connector<E, C>,
+    health_check: Option<HealthCheck<C>>,
+    closer: Option<Closer<C>>,
+    max_connections: usize,
+    max_idle: Option<Duration>,
+    state: RefCell<State<E, C>>,
+    // Whether the reaper task is running.
+    reaping: Cell<bool>,
+}
+
+impl<E: Eq + Hash + Clone + 'static, C: 'static> Shared<E, C> {
+    fn is_expired(&self, idle: &IdleConn<C>, now: Instant) -> bool {
+        self.max_idle
+            .is_some_and(|max_idle| now.saturating_duration_since(idle.since) >= max_idle)
+    }
+
+    // Pop the most recent idle connection which isn't expired, for a caller
+    // holding a slot already. The expired ones are closed.
+    fn take_idle(&self, endpoint: &E) -> Option<C> {
+        let now = now();
+        let mut expired = vec![];
+        let conn = {
+            let mut state = self.state.borrow_mut();
+            let ep = state.endpoints.get_mut(endpoint)?;
+            loop {
+                match ep.idle.pop_back() {
+                    Some(idle) if self.is_expired(&idle, now) => expired.push(idle.conn),
+                    Some(idle) => break Some(idle.conn),
+                    None => break None,
+                }
+            }
+        };
+        for conn in expired {
+            self.close(conn);
+        }
+        conn
+    }
+
+    // Give the returned connection or the released slot to the first waiter
+    // of the endpoint. Otherwise, the connection becomes idle, or the slot is
+    // freed.
+    fn release(self: &Rc<Self>, endpoint: &E, handoff: Handoff<C>) {
+        let mut state = self.state.borrow_mut();
+        let closed = state.closed;
+        let ep = state
+            .endpoints
+            .get_mut(endpoint)
+            .expect("the endpoint should be tracked when it is busy");
+        // The waiters fail after the shutdown.
+        if !closed {
+            if let Some(waiter) = ep.waiters.values_mut().find(|w| w.handoff.is_none()) {
+                waiter.handoff = Some(handoff);
+                if let Some(waker) = waiter.waker.take() {
+                    waker.wake();
+                }
+                return;
+            }
+        }
+        ep.busy -= 1;
+        let conn = match handoff {
+            Handoff::Conn(conn) if !closed => {
+                ep.idle.push_back(IdleConn { conn, since: now() });
+                drop(state);
+                self.start_reaper();
+                return;
+            }
+            Handoff::Conn(conn) | Handoff::Idle(conn) => Some(conn),
+            Handoff::Slot => None,
+        };
+        if ep.is_empty() {
+            state.endpoints.remove(endpoint);
+        }
+        drop(state);
+        if let Some(conn) = conn {
+            self.close(conn);
+        }
+    }
+
+    fn close(&self, conn: C) {
+        if let Some(closer) = &self.closer {
+            crate::task::spawn(closer(conn)).detach();
+        }
+    }
+
+    // Spawn the task closing the expired idle connections, if not running. It
+    // exits when there is no idle connection, or the pool is dropped.
+    fn start_reaper(self: &Rc<Self>) {
+        let Some(max_idle) = self.max_idle else {
+            return;
+        };
+        if self.reaping.replace(true) {
+            return;
+        }
+        let shared = Rc::downgrade(self);
+        crate::task::spawn(reap(shared, max_idle)).detach();
+    }
+}
+
+async fn reap<E: Eq + Hash + Clone + 'static, C: 'static>(
+    shared: Weak<Shared<E, C>>,
+    max_idle: Duration,
+) {
+    loop {
+        let deadline = {
+            let Some(shared) = shared.upgrade() else {
+                return;
+            };
+            let now = now();
+            let mut expired = vec![];
+            let mut oldest = None::<Instant>;
+            {
+                let mut state = shared.state.borrow_mut();
+                for ep in state.endpoints.values_mut() {
+                    // The oldest ones are at the front.
+                    while let Some(idle) = ep.idle.front() {
+                        if !shared.is_expired(idle, now) {
+                            break;
+                        }
+                        expired.push(ep.idle.pop_front().unwrap().conn);
+                    }
+                    if let Some(idle) = ep.idle.front() {
+                        oldest = Some(oldest.map_or(idle.since, |t| t.min(idle.since)));
+                    }
+                }
+                state.endpoints.retain(|_, ep| !ep.is_empty());
+            }
+            for conn in expired {
+                shared.close(conn);
+            }
+            match oldest {
+                Some(since) => since + max_idle,
+                None => {
+                    shared.reaping.set(false);
+                    return;
+                }
+            }
+        };
+        sleep_until(deadline).await;
+    }
+}
+
+// Waits in order for an idle connection, a returned connection, or a slot.
+struct Acquire<'a, E: Eq + Hash + Clone + 'static, C: 'static> {
+    shared: &'a Rc<Shared<E, C>>,
+    endpoint: &'a E,
+    ticket: Option<u64>,
+}
+
+impl<E: Eq + Hash + Clone + 'static, C: 'static> Acquire<'_, E, C> {
+    fn poll(&mut self, waker: &Waker) -> Poll<io::Result<Handoff<C>>> {
+        let shared = self.shared;
+        let mut state = shared.state.borrow_mut();
+        if state.closed {
+            drop(state);
+            // Leave the queue, and pass on what is handed.
+            self.leave();
+            return Poll::Ready(Err(shutdown_error()));
+        }
+        let max_connections = shared.max_connections;
+        let ticket = match self.ticket {
+            Some(ticket) => ticket,
+            None => {
+                let ticket = state.next_ticket;
+                state.next_ticket += 1;
+                let ep = state
+                    .endpoints
+                    .entry(self.endpoint.clone())
+                    .or_insert_with(Endpoint::new);
+                // Don't overtake the waiters.
+                if ep.waiters.is_empty() {
+                    if let Some(idle) = ep.idle.pop_back() {
+                        ep.busy += 1;
+                        let handoff = if shared.is_expired(&idle, now()) {
+                            drop(state);
+                            shared.close(idle.conn);
+                            Handoff::Slot
+                        } else {
+                            Handoff::Idle(idle.conn)
+                        };
+                        return Poll::Ready(Ok(handoff));
+                    }
+                    if ep.busy < max_connections {
+                        ep.busy += 1;
+                        return Poll::Ready(Ok(Handoff::Slot));
+                    }
+                }
+                ep.waiters.insert(
+                    ticket,
+                    Waiter {
+                        waker: None,
+                        handoff: None,
+                    },
+                );
+                self.ticket = Some(ticket);
+                ticket
+            }
+        };
+        let ep = state.endpoints.get_mut(self.endpoint).unwrap();
+        let waiter = ep.waiters.get_mut(&ticket).unwrap();
+        match waiter.handoff.take() {
+            Some(handoff) => {
+                ep.waiters.remove(&ticket);
+                self.ticket = None;
+                Poll::Ready(Ok(handoff))
+            }
+            None => {
+                waiter.waker = Some(waker.clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<E: Eq + Hash + Clone + 'static, C: 'static> Acquire<'_, E, C> {
+    fn leave(&mut self) {
+        let Some(ticket) = self.ticket.take() else {
+            return;
+        };
+        let mut state = self.shared.state.borrow_mut();
+        let Some(ep) = state.endpoints.get_mut(self.endpoint) else {
+            return;
+        };
+        let handoff = ep.waiters.remove(&ticket).and_then(|w| w.handoff);
+        if ep.is_empty() {
+            state.endpoints.remove(self.endpoint);
+        }
+        drop(state);
+        // Cancelled after being handed one, pass it on.
+        if let Some(handoff) = handoff {
+            self.shared.release(self.endpoint, handoff);
+        }
+    }
+}
+
+impl<E: Eq + Hash + Clone + 'static, C: 'static> Drop for Acquire<'_, E, C> {
+    fn drop(&mut self) {
+        self.leave();
+    }
+}
+
+// A slot of the endpoint held while connecting or checking, released if
+// dropped before the connection is handed out.
+struct Slot<E: Eq + Hash + Clone + 'static, C: 'static> {
+    shared: Rc<Shared<E, C>>,
+    endpoint: Option<E>,
+}
+
+impl<E: Eq + Hash + Clone + 'static, C: 'static> Slot<E, C> {
+    fn into_pooled(mut self, conn: C) -> PooledConnection<E, C> {
+        PooledConnection {
+            shared: self.shared.clone(),
+            endpoint: self.endpoint.take().unwrap(),
+            conn: Some(conn),
+            broken: false,
+        }
+    }
+}
+
+impl<E: Eq + Hash + Clone + 'static, C: 'static> Drop for Slot<E, C> {
+    fn drop(&mut self) {
+        if let Some(endpoint) = &self.endpoint {
+            self.shared.release(endpoint, Handoff::Slot);
+        }
+    }
+}
+
+fn shutdown_error() -> io::Error {
+    io::Error::other("the connection pool is shut down")
+}
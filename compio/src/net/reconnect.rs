@@ -0,0 +1,378 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io,
+    time::Duration,
+};
+
+use futures_util::future::LocalBoxFuture;
+use socket2::SockAddr;
+
+use crate::{
+    buf::{IoBuf, IoBufMut},
+    net::{TcpStream, ToSockAddrs},
+    time::{sleep, timeout},
+    BufResult,
+};
+
+/// The policy of [`connect_retry`] and [`ReconnectingStream`].
+///
+/// The backoff before the `n`th retry is `initial_backoff * 2^n`, capped by
+/// `max_backoff`, and a random part of it up to the jitter ratio is
+/// subtracted, so that many clients don't retry at the same time.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use compio::net::RetryPolicy;
+///
+/// let policy = RetryPolicy::new()
+///     .max_attempts(Some(5))
+///     .initial_backoff(Duration::from_millis(100))
+///     .max_backoff(Duration::from_secs(1))
+///     .jitter(0.0);
+/// assert_eq!(policy.backoff(0), Duration::from_millis(100));
+/// assert_eq!(policy.backoff(3), Duration::from_millis(800));
+/// assert_eq!(policy.backoff(4), Duration::from_secs(1));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: Option<u32>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: f64,
+    attempt_timeout: Option<Duration>,
+    retry_io: bool,
+}
+
+impl RetryPolicy {
+    /// Create the policy with default config: at most 10 attempts, backoff
+    /// from 100 milliseconds to 10 seconds with half jitter, a 10 seconds
+    /// timeout for each attempt, and retrying the failed IO.
+    pub fn new() -> Self {
+        Self {
+            max_attempts: Some(10),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            jitter: 0.5,
+            attempt_timeout: Some(Duration::from_secs(10)),
+            retry_io: true,
+        }
+    }
+
+    /// Set the maximum count of the failed attempts in a row, including the
+    /// first one. For [`ReconnectingStream`], a lost connection counts as a
+    /// failed attempt, and a successful IO resets the count. `None` means
+    /// retrying forever.
+    pub fn max_attempts(mut self, attempts: Option<u32>) -> Self {
+        self.max_attempts = attempts;
+        self
+    }
+
+    /// Set the backoff before the first retry.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Set the maximum backoff.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Set the ratio of the backoff which is randomly subtracted, clamped to
+    /// `0.0..=1.0`. Zero means no jitter.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the timeout of each attempt, including the hook of
+    /// [`ReconnectingStream::on_reconnect`]. The operations of the attempt are
+    /// cancelled when it times out. `None` means no timeout.
+    pub fn attempt_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.attempt_timeout = timeout;
+        self
+    }
+
+    /// Set whether [`ReconnectingStream::send`] and
+    /// [`ReconnectingStream::recv`] reconnect and retry when the connection
+    /// is lost. Otherwise, the error is returned, and the next IO reconnects.
+    pub fn retry_io(mut self, retry: bool) -> Self {
+        self.retry_io = retry;
+        self
+    }
+
+    fn exhausted(&self, failures: u32) -> bool {
+        self.max_attempts.is_some_and(|max| failures >= max)
+    }
+
+    /// The backoff before the retry, counting from 0, with the jitter
+    /// applied.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        if self.jitter == 0.0 {
+            return backoff;
+        }
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(retry);
+        let random = hasher.finish() as f64 / u64::MAX as f64;
+        backoff.mul_f64(1.0 - self.jitter * random)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Connect to a remote host, and retry with backoff by the policy. Returns the
+/// error of the last attempt if all attempts fail.
+///
+/// ```no_run
+/// use compio::net::{connect_retry, RetryPolicy};
+///
+/// compio::task::block_on(async {
+///     let stream = connect_retry("127.0.0.1:8080", &RetryPolicy::new())
+///         .await
+///         .unwrap();
+///     stream.send_all("hello").await.0.unwrap();
+/// })
+/// ```
+pub async fn connect_retry(addr: impl ToSockAddrs, policy: &RetryPolicy) -> io::Result<TcpStream> {
+    let addrs = addr.to_sock_addrs()?.collect::<Vec<_>>();
+    connect_cycle(&addrs, policy, &mut None, &mut 0).await
+}
+
+// The hook to re-establish the state of the protocol on a new connection.
+type Hook = Box<dyn for<'a> FnMut(&'a TcpStream) -> LocalBoxFuture<'a, io::Result<()>>>;
+
+// Connect after `failures` failed attempts, which are increased on each
+// failure.
+async fn connect_cycle(
+    addrs: &[SockAddr],
+    policy: &RetryPolicy,
+    hook: &mut Option<Hook>,
+    failures: &mut u32,
+) -> io::Result<TcpStream> {
+    loop {
+        if *failures > 0 {
+            sleep(policy.backoff(*failures - 1)).await;
+        }
+        let attempt = async {
+            let stream = TcpStream::connect(addrs).await?;
+            if let Some(hook) = hook {
+                hook(&stream).await?;
+            }
+            Ok(stream)
+        };
+        let res = match policy.attempt_timeout {
+            Some(duration) => timeout(duration, attempt)
+                .await
+                .unwrap_or_else(|_| Err(io::Error::from(io::ErrorKind::TimedOut))),
+            None => attempt.await,
+        };
+        match res {
+            Ok(stream) => return Ok(stream),
+            Err(e) => {
+                *failures += 1;
+                if policy.exhausted(*failures) {
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+// The errors meaning that the connection is lost, and a new one is required.
+fn is_lost(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::UnexpectedEof
+    )
+}
+
+/// A TCP client which reconnects with backoff when the connection is lost.
+///
+/// It connects lazily on the first IO. The hook registered by
+/// [`ReconnectingStream::on_reconnect`] runs on each new connection, before
+/// it is used, e.g., to authenticate again.
+///
+/// The connection is lost if an IO fails with [`io::ErrorKind::BrokenPipe`],
+/// [`io::ErrorKind::ConnectionReset`], [`io::ErrorKind::ConnectionAborted`]
+/// or [`io::ErrorKind::UnexpectedEof`], or a receive returns 0. If the policy
+/// retries the IO, [`ReconnectingStream::send`] and
+/// [`ReconnectingStream::recv`] reconnect and perform again on the new
+/// connection, and the whole buffer is sent again. The other methods can't be
+/// retried, because the data transferred before is unknown, and the next IO
+/// reconnects.
+///
+/// ```no_run
+/// use compio::net::{ReconnectingStream, RetryPolicy};
+///
+/// compio::task::block_on(async {
+///     let mut stream = ReconnectingStream::new("127.0.0.1:8080", RetryPolicy::new())
+///         .unwrap()
+///         .on_reconnect(|stream| {
+///             Box::pin(async move {
+///                 stream.send_all("AUTH token\n").await.0?;
+///                 Ok(())
+///             })
+///         });
+///     stream.send_all("GET key\n").await.0.unwrap();
+///     let (res, reply) = stream.recv(Vec::with_capacity(1024)).await;
+///     res.unwrap();
+///     println!("{reply:?}");
+/// })
+/// ```
+pub struct ReconnectingStream {
+    addrs: Vec<SockAddr>,
+    policy: RetryPolicy,
+    hook: Option<Hook>,
+    stream: Option<TcpStream>,
+    connects: u64,
+    // The failed attempts and the lost connections since the last successful
+    // IO.
+    failures: u32,
+}
+
+impl ReconnectingStream {
+    /// Create the stream with the addresses of the remote host. It is not
+    /// connected yet.
+    pub fn new(addr: impl ToSockAddrs, policy: RetryPolicy) -> io::Result<Self> {
+        Ok(Self {
+            addrs: addr.to_sock_addrs()?.collect(),
+            policy,
+            hook: None,
+            stream: None,
+            connects: 0,
+            failures: 0,
+        })
+    }
+
+    /// Set the hook running on each new connection, including the first one.
+    /// If it fails, the attempt fails, and it is retried by the policy.
+    pub fn on_reconnect(
+        mut self,
+        f: impl for<'a> FnMut(&'a TcpStream) -> LocalBoxFuture<'a, io::Result<()>> + 'static,
+    ) -> Self {
+        self.hook = Some(Box::new(f));
+        self
+    }
+
+    /// The count of the established connections.
+    pub fn connects(&self) -> u64 {
+        self.connects
+    }
+
+    /// The current connection, if connected.
+    pub fn get_ref(&self) -> Option<&TcpStream> {
+        self.stream.as_ref()
+    }
+
+    /// Connect if not connected, and return the connection.
+    pub async fn connect(&mut self) -> io::Result<&TcpStream> {
+        if self.stream.is_none() {
+            let stream = connect_cycle(
+                &self.addrs,
+                &self.policy,
+                &mut self.hook,
+                &mut self.failures,
+            )
+            .await?;
+            self.connects += 1;
+            self.stream = Some(stream);
+        }
+        Ok(self.stream.as_ref().unwrap())
+    }
+
+    /// Drop the connection, and the next IO reconnects.
+    pub fn disconnect(&mut self) {
+        self.stream = None;
+    }
+
+    // Drop the connection if it is lost, and return whether to retry. A lost
+    // connection counts as a failed attempt, so that a peer accepting and
+    // closing at once is retried with backoff.
+    fn check<T>(&mut self, res: &io::Result<T>) -> bool {
+        match res {
+            Ok(_) => {
+                self.failures = 0;
+                false
+            }
+            Err(e) if is_lost(e) => {
+                self.stream = None;
+                self.failures += 1;
+                self.policy.retry_io && !self.policy.exhausted(self.failures)
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Receives a packet of data from the connection, see
+    /// [`TcpStream::recv`]. It returns 0 only if the buffer is empty.
+    pub async fn recv<T: IoBufMut>(&mut self, mut buffer: T) -> BufResult<usize, T> {
+        let empty = buffer.as_uninit_slice().is_empty();
+        loop {
+            let stream = match self.connect().await {
+                Ok(stream) => stream,
+                Err(e) => return (Err(e), buffer),
+            };
+            let res;
+            (res, buffer) = stream.recv(buffer).await;
+            let res = match res {
+                Ok(0) if !empty => Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+                res => res,
+            };
+            if !self.check(&res) {
+                return (res, buffer);
+            }
+        }
+    }
+
+    /// Receives the exact number of bytes to fill the buffer, see
+    /// [`TcpStream::recv_exact`].
+    pub async fn recv_exact<T: IoBufMut>(&mut self, buffer: T) -> BufResult<usize, T> {
+        let stream = match self.connect().await {
+            Ok(stream) => stream,
+            Err(e) => return (Err(e), buffer),
+        };
+        let (res, buffer) = stream.recv_exact(buffer).await;
+        self.check(&res);
+        (res, buffer)
+    }
+
+    /// Sends some data to the connection, see [`TcpStream::send`].
+    pub async fn send<T: IoBuf>(&mut self, mut buffer: T) -> BufResult<usize, T> {
+        loop {
+            let stream = match self.connect().await {
+                Ok(stream) => stream,
+                Err(e) => return (Err(e), buffer),
+            };
+            let res;
+            (res, buffer) = stream.send(buffer).await;
+            if !self.check(&res) {
+                return (res, buffer);
+            }
+        }
+    }
+
+    /// Sends all data to the connection, see [`TcpStream::send_all`].
+    pub async fn send_all<T: IoBuf>(&mut self, buffer: T) -> BufResult<usize, T> {
+        let stream = match self.connect().await {
+            Ok(stream) => stream,
+            Err(e) => return (Err(e), buffer),
+        };
+        let (res, buffer) = stream.send_all(buffer).await;
+        self.check(&res);
+        (res, buffer)
+    }
+}
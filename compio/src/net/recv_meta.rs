@@ -0,0 +1,261 @@
+use std::net::IpAddr;
+
+use socket2::SockAddr;
+
+use crate::net::RecvTimestamp;
+
+/// The flags of a received datagram, see [`RecvMeta`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RecvFlags {
+    bits: i32,
+}
+
+impl RecvFlags {
+    /// Whether the datagram is larger than the buffer, and the rest of it is
+    /// discarded, i.e., `MSG_TRUNC`.
+    pub fn truncated(&self) -> bool {
+        #[cfg(unix)]
+        {
+            self.bits & libc::MSG_TRUNC != 0
+        }
+        #[cfg(windows)]
+        {
+            false
+        }
+    }
+
+    /// Whether some control messages are discarded for the lack of space,
+    /// i.e., `MSG_CTRUNC`.
+    pub fn control_truncated(&self) -> bool {
+        #[cfg(unix)]
+        {
+            self.bits & libc::MSG_CTRUNC != 0
+        }
+        #[cfg(windows)]
+        {
+            false
+        }
+    }
+
+    /// The raw flags of the message header.
+    pub fn bits(&self) -> i32 {
+        self.bits
+    }
+}
+
+/// The metadata of a received datagram, see
+/// [`UdpSocket::recv_meta`](crate::net::UdpSocket::recv_meta).
+///
+/// The fields from the control messages are `None` if the platform doesn't
+/// support them, or they are not enabled on the socket.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct RecvMeta {
+    /// The bytes received into the buffer.
+    pub len: usize,
+    /// The source address.
+    pub addr: SockAddr,
+    /// The flags of the datagram.
+    pub flags: RecvFlags,
+    /// The ECN codepoint in the TOS or the traffic class field.
+    pub ecn: Option<u8>,
+    /// The TTL or the hop limit.
+    pub ttl: Option<u8>,
+    /// The destination address, i.e., the local address the datagram is sent
+    /// to, which tells the interface of a socket bound to the unspecified
+    /// address.
+    pub dst_addr: Option<IpAddr>,
+    /// The size of the datagrams coalesced into the buffer by GRO. The buffer
+    /// should be split every this many bytes, and the last one may be shorter.
+    pub gro_segment_size: Option<usize>,
+    /// The count of the datagrams coalesced into the buffer by GRO.
+    pub gro_segments: Option<usize>,
+    /// The kernel receive timestamp.
+    pub timestamp: Option<RecvTimestamp>,
+}
+
+impl RecvMeta {
+    #[cfg(feature = "runtime")]
+    pub(crate) fn new(len: usize, addr: SockAddr) -> Self {
+        Self {
+            len,
+            addr,
+            flags: RecvFlags::default(),
+            ecn: None,
+            ttl: None,
+            dst_addr: None,
+            gro_segment_size: None,
+            gro_segments: None,
+            timestamp: None,
+        }
+    }
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "freebsd"
+))]
+#[cfg_attr(not(feature = "runtime"), allow(dead_code, unused_imports))]
+mod sys {
+    use std::{
+        io,
+        mem::size_of,
+        net::{Ipv4Addr, Ipv6Addr},
+    };
+
+    use super::{RecvFlags, RecvMeta};
+    use crate::{driver::RawFd, syscall};
+
+    // The low 2 bits of the TOS or the traffic class.
+    const ECN_MASK: libc::c_int = 0b11;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const IP_DST_ADDR: libc::c_int = libc::IP_PKTINFO;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    const IP_DST_ADDR: libc::c_int = libc::IP_RECVDSTADDR;
+
+    fn set(fd: RawFd, level: libc::c_int, name: libc::c_int, value: libc::c_int) -> io::Result<()> {
+        syscall!(setsockopt(
+            fd,
+            level,
+            name,
+            std::ptr::addr_of!(value).cast(),
+            size_of::<libc::c_int>() as _,
+        ))?;
+        Ok(())
+    }
+
+    pub fn enable(fd: RawFd, v6: bool, enable: bool) -> io::Result<()> {
+        let value = enable as libc::c_int;
+        if v6 {
+            set(fd, libc::IPPROTO_IPV6, libc::IPV6_RECVTCLASS, value)?;
+            set(fd, libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT, value)?;
+            set(fd, libc::IPPROTO_IPV6, libc::IPV6_RECVPKTINFO, value)?;
+        }
+        // The IPv4 ones also apply to the IPv4 packets received by a
+        // dual-stack socket, if the platform supports them there.
+        for name in [libc::IP_RECVTOS, libc::IP_RECVTTL, IP_DST_ADDR] {
+            match set(fd, libc::IPPROTO_IP, name, value) {
+                Err(_) if v6 => {}
+                res => res?,
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn enable_gro(fd: RawFd, enable: bool) -> io::Result<()> {
+        set(fd, libc::SOL_UDP, libc::UDP_GRO, enable as libc::c_int)
+    }
+
+    /// The space of the control messages parsed.
+    pub fn control_len() -> usize {
+        let space = |len: usize| unsafe { libc::CMSG_SPACE(len as _) as usize };
+        // The TOS, the TTL, and the GRO size of both families, and the packet
+        // info.
+        space(size_of::<libc::c_int>()) * 5
+            + space(size_of::<libc::in6_pktinfo>()) * 2
+            + crate::net::timestamp::control_len()
+    }
+
+    // The integer in a control message, which is a byte for some types on
+    // some platforms.
+    #[allow(clippy::unnecessary_cast)]
+    unsafe fn read_int(cmsg: *const libc::cmsghdr) -> libc::c_int {
+        let data = libc::CMSG_DATA(cmsg);
+        let len = (*cmsg).cmsg_len as usize - (data as usize - cmsg as usize);
+        if len >= size_of::<libc::c_int>() {
+            std::ptr::read_unaligned(data.cast::<libc::c_int>())
+        } else {
+            *data as libc::c_int
+        }
+    }
+
+    pub fn parse(msg: &libc::msghdr, meta: &mut RecvMeta) {
+        meta.flags = RecvFlags {
+            bits: msg.msg_flags,
+        };
+        meta.timestamp = crate::net::timestamp::parse(msg);
+        let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(msg) };
+        while !cmsg.is_null() {
+            let (level, ty) = unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) };
+            match (level, ty) {
+                (libc::IPPROTO_IP, libc::IP_TOS | libc::IP_RECVTOS)
+                | (libc::IPPROTO_IPV6, libc::IPV6_TCLASS) => {
+                    meta.ecn = Some((unsafe { read_int(cmsg) } & ECN_MASK) as u8);
+                }
+                (libc::IPPROTO_IP, libc::IP_TTL | libc::IP_RECVTTL)
+                | (libc::IPPROTO_IPV6, libc::IPV6_HOPLIMIT) => {
+                    meta.ttl = Some(unsafe { read_int(cmsg) } as u8);
+                }
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                    let info = unsafe {
+                        std::ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<libc::in_pktinfo>())
+                    };
+                    meta.dst_addr = Some(Ipv4Addr::from(u32::from_be(info.ipi_addr.s_addr)).into());
+                }
+                #[cfg(not(any(target_os = "linux", target_os = "android")))]
+                (libc::IPPROTO_IP, libc::IP_RECVDSTADDR) => {
+                    let addr = unsafe {
+                        std::ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<libc::in_addr>())
+                    };
+                    meta.dst_addr = Some(Ipv4Addr::from(u32::from_be(addr.s_addr)).into());
+                }
+                (libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => {
+                    let info = unsafe {
+                        std::ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<libc::in6_pktinfo>())
+                    };
+                    meta.dst_addr = Some(Ipv6Addr::from(info.ipi6_addr.s6_addr).into());
+                }
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                (libc::SOL_UDP, libc::UDP_GRO) => {
+                    let size = unsafe { read_int(cmsg) } as usize;
+                    if size > 0 {
+                        meta.gro_segment_size = Some(size);
+                        meta.gro_segments = Some(meta.len.div_ceil(size));
+                    }
+                }
+                _ => {}
+            }
+            cmsg = unsafe { libc::CMSG_NXTHDR(msg, cmsg) };
+        }
+    }
+}
+
+#[cfg(all(
+    unix,
+    not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "freebsd"
+    ))
+))]
+#[cfg_attr(not(feature = "runtime"), allow(dead_code, unused_imports))]
+mod sys {
+    use std::io;
+
+    use super::{RecvFlags, RecvMeta};
+    use crate::driver::RawFd;
+
+    pub fn enable(_fd: RawFd, _v6: bool, _enable: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn control_len() -> usize {
+        crate::net::timestamp::control_len()
+    }
+
+    pub fn parse(msg: &libc::msghdr, meta: &mut RecvMeta) {
+        meta.flags = RecvFlags {
+            bits: msg.msg_flags,
+        };
+        meta.timestamp = crate::net::timestamp::parse(msg);
+    }
+}
+
+#[cfg(unix)]
+pub(crate) use sys::*;
@@ -0,0 +1,291 @@
+use std::{
+    ffi::{CStr, CString},
+    io,
+    net::SocketAddr,
+};
+
+use futures_util::Stream;
+use socket2::{Domain, Protocol, SockAddr, Type};
+
+use crate::{buf::IntoInner, driver::RawFd, op::BlockingBufOp, task::submit};
+
+/// The hints of [`resolve`], to filter the addresses.
+///
+/// ```
+/// use compio::net::{resolve, Hints};
+/// use socket2::{Domain, Type};
+///
+/// compio::task::block_on(async {
+///     let hints = Hints::new()
+///         .port(80)
+///         .family(Domain::IPV4)
+///         .socket_type(Type::STREAM);
+///     let resolved = resolve("localhost", hints).await.unwrap();
+///     for entry in resolved.entries() {
+///         assert_eq!(entry.family, Domain::IPV4);
+///         assert_eq!(entry.addr.port(), 80);
+///     }
+/// })
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Hints {
+    port: u16,
+    family: Option<Domain>,
+    socket_type: Option<Type>,
+    protocol: Option<Protocol>,
+    canonical_name: bool,
+}
+
+impl Hints {
+    /// Create [`Hints`] which accepts all addresses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The port of the resolved addresses. Defaults to 0.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Only resolve the addresses of the family, e.g., [`Domain::IPV6`].
+    pub fn family(mut self, family: Domain) -> Self {
+        self.family = Some(family);
+        self
+    }
+
+    /// Only resolve the addresses for the socket type. Otherwise, an address
+    /// may be returned once for each supported socket type.
+    pub fn socket_type(mut self, socket_type: Type) -> Self {
+        self.socket_type = Some(socket_type);
+        self
+    }
+
+    /// Only resolve the addresses for the protocol.
+    pub fn protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    /// Request the canonical name of the host.
+    pub fn canonical_name(mut self, canonical_name: bool) -> Self {
+        self.canonical_name = canonical_name;
+        self
+    }
+}
+
+/// An address resolved by [`resolve`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedAddr {
+    /// The socket address, with the port of [`Hints::port`].
+    pub addr: SocketAddr,
+    /// The address family.
+    pub family: Domain,
+    /// The socket type.
+    pub socket_type: Type,
+    /// The protocol.
+    pub protocol: Protocol,
+}
+
+/// The result of [`resolve`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Resolved {
+    canonical_name: Option<String>,
+    entries: Vec<ResolvedAddr>,
+}
+
+impl Resolved {
+    /// The canonical name of the host, if requested by
+    /// [`Hints::canonical_name`] and provided by the resolver.
+    pub fn canonical_name(&self) -> Option<&str> {
+        self.canonical_name.as_deref()
+    }
+
+    /// The resolved addresses, in the order given by the resolver. The order
+    /// of the system resolver follows RFC 6724, and is kept as is.
+    pub fn entries(&self) -> &[ResolvedAddr] {
+        &self.entries
+    }
+
+    /// Iterate the socket addresses.
+    pub fn addrs(&self) -> impl Iterator<Item = SocketAddr> + '_ {
+        self.entries.iter().map(|entry| entry.addr)
+    }
+}
+
+impl IntoIterator for Resolved {
+    type IntoIter = std::vec::IntoIter<ResolvedAddr>;
+    type Item = ResolvedAddr;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+/// Resolve the host with the hints.
+///
+/// It calls `getaddrinfo` on the blocking thread pool. If the future is
+/// dropped before the call returns, the result is discarded when it returns.
+/// The internationalized domain names are not supported, and should be
+/// converted to punycode first.
+pub async fn resolve(host: &str, hints: Hints) -> io::Result<Resolved> {
+    if !host.is_ascii() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "internationalized domain names are not supported, convert the host to punycode \
+             first",
+        ));
+    }
+    let host = CString::new(host).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the host should not contain nul bytes",
+        )
+    })?;
+    let op = BlockingBufOp::new(NO_FD, None, move |_, resolved| {
+        let res = getaddrinfo(&host, &hints)?;
+        let len = res.entries.len();
+        *resolved = Some(res);
+        Ok(len)
+    });
+    let (res, op) = submit(op).await;
+    res?;
+    Ok(op
+        .into_inner()
+        .expect("the result should be set on success"))
+}
+
+/// Resolve the host to the socket addresses with the port, for the stream
+/// sockets. The addresses are yielded in the order given by the resolver,
+/// see [`resolve`].
+///
+/// ```
+/// use compio::net::lookup_host;
+/// use futures_util::StreamExt;
+///
+/// compio::task::block_on(async {
+///     let addrs = lookup_host("localhost", 8080).await.unwrap();
+///     let addrs = addrs.collect::<Vec<_>>().await;
+///     assert!(addrs.iter().all(|addr| addr.ip().is_loopback()));
+/// })
+/// ```
+pub async fn lookup_host(
+    host: &str,
+    port: u16,
+) -> io::Result<impl Stream<Item = SocketAddr> + Unpin> {
+    let resolved = resolve(host, Hints::new().port(port).socket_type(Type::STREAM)).await?;
+    Ok(futures_util::stream::iter(
+        resolved.into_iter().map(|entry| entry.addr),
+    ))
+}
+
+// The blocking op doesn't use the fd.
+#[cfg(unix)]
+const NO_FD: RawFd = -1;
+#[cfg(windows)]
+const NO_FD: RawFd = std::ptr::null_mut();
+
+#[cfg(unix)]
+fn getaddrinfo(host: &CStr, hints: &Hints) -> io::Result<Resolved> {
+    use libc::{addrinfo, freeaddrinfo, getaddrinfo, gai_strerror, AF_UNSPEC, AI_CANONNAME};
+
+    let mut raw_hints: addrinfo = unsafe { std::mem::zeroed() };
+    raw_hints.ai_family = hints.family.map(i32::from).unwrap_or(AF_UNSPEC);
+    raw_hints.ai_socktype = hints.socket_type.map(i32::from).unwrap_or_default();
+    raw_hints.ai_protocol = hints.protocol.map(i32::from).unwrap_or_default();
+    if hints.canonical_name {
+        raw_hints.ai_flags = AI_CANONNAME;
+    }
+    let mut res = std::ptr::null_mut();
+    let code = unsafe { getaddrinfo(host.as_ptr(), std::ptr::null(), &raw_hints, &mut res) };
+    if code != 0 {
+        if code == libc::EAI_SYSTEM {
+            return Err(io::Error::last_os_error());
+        }
+        let detail = unsafe { CStr::from_ptr(gai_strerror(code)) };
+        return Err(io::Error::other(format!(
+            "failed to lookup address information: {}",
+            detail.to_string_lossy()
+        )));
+    }
+    let resolved = unsafe { collect_addrinfo(res, hints.port) };
+    unsafe { freeaddrinfo(res) };
+    Ok(resolved)
+}
+
+#[cfg(windows)]
+fn getaddrinfo(host: &CStr, hints: &Hints) -> io::Result<Resolved> {
+    use windows_sys::Win32::Networking::WinSock::{
+        freeaddrinfo, getaddrinfo, WSAStartup, ADDRINFOA, AF_UNSPEC, AI_CANONNAME, WSADATA,
+    };
+
+    // Winsock should be initialized before `getaddrinfo`. The initialization is
+    // reference counted, and never cleaned up, like `std`.
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| {
+        let mut data: WSADATA = unsafe { std::mem::zeroed() };
+        unsafe { WSAStartup(0x202, &mut data) };
+    });
+
+    let mut raw_hints: ADDRINFOA = unsafe { std::mem::zeroed() };
+    raw_hints.ai_family = hints.family.map(i32::from).unwrap_or(AF_UNSPEC as _);
+    raw_hints.ai_socktype = hints.socket_type.map(i32::from).unwrap_or_default();
+    raw_hints.ai_protocol = hints.protocol.map(i32::from).unwrap_or_default();
+    if hints.canonical_name {
+        raw_hints.ai_flags = AI_CANONNAME as _;
+    }
+    let mut res = std::ptr::null_mut();
+    let code = unsafe { getaddrinfo(host.as_ptr().cast(), std::ptr::null(), &raw_hints, &mut res) };
+    if code != 0 {
+        return Err(io::Error::from_raw_os_error(code));
+    }
+    let resolved = unsafe { collect_addrinfo(res, hints.port) };
+    unsafe { freeaddrinfo(res) };
+    Ok(resolved)
+}
+
+#[cfg(unix)]
+type AddrInfo = libc::addrinfo;
+#[cfg(windows)]
+type AddrInfo = windows_sys::Win32::Networking::WinSock::ADDRINFOA;
+
+/// # Safety
+///
+/// `res` should be the list returned by `getaddrinfo`.
+unsafe fn collect_addrinfo(mut res: *const AddrInfo, port: u16) -> Resolved {
+    let mut resolved = Resolved::default();
+    while let Some(info) = res.as_ref() {
+        res = info.ai_next;
+        if resolved.canonical_name.is_none() && !info.ai_canonname.is_null() {
+            let name = CStr::from_ptr(info.ai_canonname.cast());
+            resolved.canonical_name = Some(name.to_string_lossy().into_owned());
+        }
+        if info.ai_addr.is_null() {
+            continue;
+        }
+        let Ok(((), addr)) = SockAddr::try_init(|storage, len| {
+            let addr_len = (info.ai_addrlen as usize).min(std::mem::size_of_val(&*storage));
+            std::ptr::copy_nonoverlapping(
+                info.ai_addr.cast::<u8>(),
+                storage.cast::<u8>(),
+                addr_len,
+            );
+            *len = addr_len as _;
+            Ok(())
+        }) else {
+            continue;
+        };
+        // Skip the addresses of the other families.
+        let Some(mut addr) = addr.as_socket() else {
+            continue;
+        };
+        addr.set_port(port);
+        resolved.entries.push(ResolvedAddr {
+            addr,
+            family: Domain::from(info.ai_family),
+            socket_type: Type::from(info.ai_socktype),
+            protocol: Protocol::from(info.ai_protocol),
+        });
+    }
+    resolved
+}
@@ -0,0 +1,265 @@
+use std::{io, net::Shutdown};
+
+use crate::{
+    buf::{IntoInner, IoBuf},
+    driver::AsRawFd,
+    fs::File,
+    io::{AsyncRecv, AsyncSend},
+    net::{TcpStream, UnixStream},
+    BufResult,
+};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+mod sealed {
+    pub trait Sealed {
+        fn attach(&self) -> std::io::Result<()>;
+
+        fn shutdown(&self, how: std::net::Shutdown) -> std::io::Result<()>;
+
+        fn recv_queued(&self) -> std::io::Result<usize>;
+    }
+}
+
+/// A connected stream socket, which [`send_file`] sends to,
+/// [`copy_bidirectional`] copies between, and [`AdaptiveRecv`] receives from.
+///
+/// This trait is sealed, and implemented for [`TcpStream`] and [`UnixStream`].
+///
+/// [`copy_bidirectional`]: crate::net::copy_bidirectional
+/// [`AdaptiveRecv`]: crate::net::AdaptiveRecv
+pub trait StreamSocket: AsyncRecv + AsyncSend + AsRawFd + sealed::Sealed {}
+
+macro_rules! impl_stream_socket {
+    ($($t:ty),*) => {
+        $(
+            impl sealed::Sealed for $t {
+                fn attach(&self) -> io::Result<()> {
+                    <$t>::attach(self)
+                }
+
+                fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+                    <$t>::shutdown(self, how)
+                }
+
+                fn recv_queued(&self) -> io::Result<usize> {
+                    <$t>::recv_queued(self)
+                }
+            }
+
+            impl StreamSocket for $t {}
+        )*
+    };
+}
+
+impl_stream_socket!(TcpStream, UnixStream);
+
+/// Sends `len` bytes from the offset `offset` of `file` to `stream`, and
+/// returns the number of bytes sent.
+///
+/// It is less than `len` if the EOF of `file` is reached, or the peer closes
+/// the connection. Other errors are returned, and the bytes sent before are
+/// lost.
+///
+/// The data is sent in the kernel if possible:
+///
+/// * Linux: `sendfile`.
+/// * Windows: `TransmitFile`.
+///
+/// If the fast path is not supported, e.g., by the file or the socket, the
+/// data is read into two userspace buffers, and one is sent while the other is
+/// being filled.
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use compio::{
+///     fs::File,
+///     net::{send_file, TcpListener, TcpStream},
+/// };
+///
+/// compio::task::block_on(async {
+///     let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     let addr = listener.local_addr().unwrap();
+///     let (tx, (rx, _)) =
+///         futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+///
+///     let file = File::open("Cargo.toml").unwrap();
+///     let len = file.metadata().unwrap().len();
+///     let (sent, (received, buffer)) = futures_util::join!(
+///         send_file(&tx, &file, 0, len),
+///         rx.recv_exact(Vec::with_capacity(len as usize)),
+///     );
+///     assert_eq!(sent.unwrap(), len);
+///     assert_eq!(received.unwrap() as u64, len);
+///     assert_eq!(buffer, std::fs::read("Cargo.toml").unwrap());
+/// })
+/// ```
+pub async fn send_file(
+    stream: &impl StreamSocket,
+    file: &File,
+    offset: u64,
+    len: u64,
+) -> io::Result<u64> {
+    stream.attach()?;
+    let (sent, fallback) = send_file_fast(stream, file, offset, len).await?;
+    if !fallback {
+        return Ok(sent);
+    }
+    let rest = send_file_buffered(stream, file, offset + sent, len - sent).await?;
+    Ok(sent + rest)
+}
+
+// The peer closes the connection.
+fn is_closed_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+    )
+}
+
+/// Returns the bytes sent, and whether the rest should be sent by the
+/// userspace buffers.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "windows"))]
+async fn send_file_fast(
+    stream: &impl StreamSocket,
+    file: &File,
+    offset: u64,
+    len: u64,
+) -> io::Result<(u64, bool)> {
+    use crate::{op::SendFile, task::submit};
+
+    // `sendfile` shouldn't block the thread after the socket is writable.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    set_nonblocking(stream)?;
+
+    let mut sent = 0;
+    while sent < len {
+        let op = SendFile::new(
+            stream.as_raw_fd(),
+            file.as_raw_fd(),
+            offset + sent,
+            (len - sent).min(usize::MAX as u64) as usize,
+        );
+        match submit(op).await.0 {
+            // EOF of `file`.
+            Ok(0) => break,
+            Ok(n) => sent += n as u64,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+                ) => {}
+            Err(e) if is_closed_error(&e) => break,
+            Err(e) if sent == 0 && is_unsupported_error(&e) => return Ok((0, true)),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((sent, false))
+}
+
+/// The stream sockets which are not accepted are blocking with io-uring, so
+/// they are set nonblocking before calling the syscalls after polling.
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub(crate) fn set_nonblocking(stream: &impl StreamSocket) -> io::Result<()> {
+    let fd = stream.as_raw_fd();
+    let flags = crate::syscall!(fcntl(fd, libc::F_GETFL))?;
+    if flags & libc::O_NONBLOCK == 0 {
+        crate::syscall!(fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK))?;
+    }
+    Ok(())
+}
+
+/// There is no fast path.
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "windows")))]
+async fn send_file_fast(
+    _stream: &impl StreamSocket,
+    _file: &File,
+    _offset: u64,
+    _len: u64,
+) -> io::Result<(u64, bool)> {
+    Ok((0, true))
+}
+
+// The file or the socket doesn't support the fast path.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn is_unsupported_error(e: &io::Error) -> bool {
+    matches!(
+        e.raw_os_error(),
+        Some(libc::EINVAL | libc::ENOSYS | libc::EOPNOTSUPP)
+    )
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) fn is_unsupported_error(e: &io::Error) -> bool {
+    use windows_sys::Win32::{
+        Foundation::ERROR_NOT_SUPPORTED,
+        Networking::WinSock::{WSAEINVAL, WSAENOTSOCK, WSAEOPNOTSUPP},
+    };
+
+    let code = e.raw_os_error();
+    code == Some(ERROR_NOT_SUPPORTED as _)
+        || matches!(code, Some(WSAEINVAL | WSAENOTSOCK | WSAEOPNOTSUPP))
+}
+
+/// Send the whole buffer, and returns the bytes sent, which are fewer if the
+/// peer closes the connection.
+async fn send_all(stream: &impl StreamSocket, mut buffer: Vec<u8>) -> BufResult<usize, Vec<u8>> {
+    let mut sent = 0;
+    while sent < buffer.len() {
+        let (res, slice) = stream.send(buffer.slice(sent..)).await;
+        buffer = slice.into_inner();
+        match res {
+            Ok(0) => break,
+            Ok(n) => sent += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) if is_closed_error(&e) => break,
+            Err(e) => return (Err(e), buffer),
+        }
+    }
+    (Ok(sent), buffer)
+}
+
+/// Send through two buffers: one is sent while the other is being filled.
+async fn send_file_buffered(
+    stream: &impl StreamSocket,
+    file: &File,
+    offset: u64,
+    len: u64,
+) -> io::Result<u64> {
+    let chunk = len.min(CHUNK_SIZE as u64) as usize;
+    let mut sent = 0;
+    let (read, filled) = file
+        .read_at(Vec::with_capacity(chunk).slice(..chunk), offset)
+        .await;
+    let mut read = read?;
+    let mut filled = filled.into_inner();
+    let mut spare = Vec::with_capacity(chunk);
+    while read > 0 {
+        let next = sent + read as u64;
+        let read_next = async {
+            if next < len {
+                spare.clear();
+                let spare = std::mem::take(&mut spare);
+                let buffer = spare.slice(..(len - next).min(chunk as u64) as usize);
+                let (res, buffer) = file.read_at(buffer, offset + next).await;
+                (res, buffer.into_inner())
+            } else {
+                (Ok(0), std::mem::take(&mut spare))
+            }
+        };
+        let ((written, buffer), (res, next_buffer)) =
+            futures_util::join!(send_all(stream, filled), read_next);
+        let written = written?;
+        if written < read {
+            return Ok(sent + written as u64);
+        }
+        sent = next;
+        read = res?;
+        spare = buffer;
+        filled = next_buffer;
+    }
+    Ok(sent)
+}
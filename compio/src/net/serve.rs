@@ -0,0 +1,271 @@
+use std::{any::Any, future::Future, io, panic::AssertUnwindSafe, pin::pin, rc::Rc};
+
+use async_task::Task;
+use futures_util::{
+    future::{pending, Fuse, FusedFuture},
+    select,
+    stream::FuturesUnordered,
+    FutureExt, StreamExt,
+};
+use socket2::SockAddr;
+
+use crate::{
+    net::{
+        tcp::{is_connection_error, is_resource_error},
+        TcpListener, TcpStream,
+    },
+    task::{spawn, Semaphore},
+};
+
+type HandlerTask = Task<Result<(), Box<dyn Any + Send>>>;
+
+type PanicHook = Rc<dyn Fn(Box<dyn Any + Send>)>;
+
+type ErrorHook = Rc<dyn Fn(&io::Error)>;
+
+/// The options of [`TcpListener::serve`] and [`TcpListener::serve_until`].
+///
+/// ```
+/// use compio::net::ServeOptions;
+///
+/// let options = ServeOptions::new()
+///     .limit(Some(1024))
+///     .on_panic(|_| eprintln!("a handler panicked"))
+///     .on_error(|e| eprintln!("skipped a connection: {e}"));
+/// ```
+#[derive(Clone)]
+pub struct ServeOptions {
+    limit: Option<usize>,
+    on_panic: Option<PanicHook>,
+    on_error: Option<ErrorHook>,
+}
+
+impl ServeOptions {
+    /// Create the options with default config: unlimited connections, and the
+    /// panics and the errors are not reported.
+    pub fn new() -> Self {
+        Self {
+            limit: None,
+            on_panic: None,
+            on_error: None,
+        }
+    }
+
+    /// Set the maximum count of the live connections. If it is reached, no
+    /// more connections are accepted until some live ones are handled. `None`
+    /// means unlimited.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is `Some(0)`.
+    pub fn limit(mut self, limit: Option<usize>) -> Self {
+        assert_ne!(limit, Some(0), "the connection limit should be positive");
+        self.limit = limit;
+        self
+    }
+
+    /// Set the callback receiving the payload of a panic in the handler,
+    /// after the panic is reported by the panic hook of the process, see
+    /// [`std::panic::set_hook`].
+    pub fn on_panic(mut self, f: impl Fn(Box<dyn Any + Send>) + 'static) -> Self {
+        self.on_panic = Some(Rc::new(f));
+        self
+    }
+
+    /// Set the callback receiving the errors of accepting which don't stop
+    /// the loop, e.g., the aborted connections.
+    pub fn on_error(mut self, f: impl Fn(&io::Error) + 'static) -> Self {
+        self.on_error = Some(Rc::new(f));
+        self
+    }
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn report_error(on_error: &Option<ErrorHook>, e: &io::Error) {
+    if let Some(on_error) = on_error {
+        on_error(e);
+    }
+}
+
+impl TcpListener {
+    /// Accept the connections in a loop, and spawn `handler` for each of them.
+    ///
+    /// It runs until a non-transient error occurs. See
+    /// [`TcpListener::serve_until`] for details.
+    pub async fn serve<F, Fut>(&self, options: ServeOptions, handler: F) -> io::Result<()>
+    where
+        F: Fn(TcpStream, SockAddr) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.serve_until(options, handler, pending())
+            .await
+            .map(|_| ())
+    }
+
+    /// Accept the connections in a loop until `shutdown` completes, and spawn
+    /// `handler` for each of them.
+    ///
+    /// * Each live connection holds a permit of a [`Semaphore`] sized by the
+    ///   [limit](ServeOptions::limit), and the next connection is accepted
+    ///   after a permit is acquired.
+    /// * The aborted and reset connections are skipped. If the process runs
+    ///   out of resources, e.g., fds, it waits for a live connection to be
+    ///   handled before accepting again. These errors are reported to
+    ///   [`ServeOptions::on_error`]. Other errors stop the loop, and the live
+    ///   connections are cancelled.
+    /// * A panic in `handler` is caught and reported to
+    ///   [`ServeOptions::on_panic`]. It doesn't stop the loop.
+    ///
+    /// The live connections are returned after `shutdown` completes. Wait for
+    /// them with [`Connections::wait`] or [`Connections::shutdown`], and drop
+    /// it to cancel the remaining ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use compio::net::{ServeOptions, TcpListener, TcpStream};
+    ///
+    /// compio::task::block_on(async {
+    ///     let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    ///     let addr = listener.local_addr().unwrap();
+    ///     let (tx, rx) = futures_channel::oneshot::channel::<()>();
+    ///
+    ///     let client = compio::task::spawn(async move {
+    ///         let stream = TcpStream::connect(&addr).await.unwrap();
+    ///         stream.send_all("hello").await.0.unwrap();
+    ///         tx.send(()).unwrap();
+    ///     });
+    ///
+    ///     let connections = listener
+    ///         .serve_until(
+    ///             ServeOptions::new().limit(Some(16)),
+    ///             |stream, _| async move {
+    ///                 let (_, buf) = stream.recv_exact(Vec::with_capacity(5)).await;
+    ///                 assert_eq!(buf, b"hello");
+    ///             },
+    ///             async {
+    ///                 rx.await.unwrap();
+    ///             },
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///     connections.wait().await;
+    ///     client.await;
+    /// })
+    /// ```
+    pub async fn serve_until<F, Fut>(
+        &self,
+        options: ServeOptions,
+        handler: F,
+        shutdown: impl Future<Output = ()>,
+    ) -> io::Result<Connections>
+    where
+        F: Fn(TcpStream, SockAddr) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let semaphore = Semaphore::new(options.limit.unwrap_or(usize::MAX));
+        let mut connections = Connections {
+            tasks: FuturesUnordered::new(),
+            on_panic: options.on_panic,
+        };
+        let mut shutdown = pin!(shutdown.fuse());
+        let mut accept = pin!(Fuse::terminated());
+        // Whether to wait for a live connection before accepting.
+        let mut exhausted = false;
+        loop {
+            if accept.is_terminated() && !exhausted {
+                accept.set(
+                    async {
+                        let permit = semaphore.acquire().await;
+                        (self.accept().await, permit)
+                    }
+                    .fuse(),
+                );
+            }
+            select! {
+                (res, permit) = accept => match res {
+                    Ok((stream, addr)) => {
+                        let fut = AssertUnwindSafe(handler(stream, addr)).catch_unwind();
+                        connections.tasks.push(spawn(async move {
+                            let _permit = permit;
+                            fut.await
+                        }));
+                    }
+                    Err(e) if is_connection_error(&e) => report_error(&options.on_error, &e),
+                    Err(e) if is_resource_error(&e) && !connections.is_empty() => {
+                        report_error(&options.on_error, &e);
+                        exhausted = true;
+                    }
+                    Err(e) => return Err(e),
+                },
+                res = connections.tasks.select_next_some() => {
+                    connections.report_panic(res);
+                    exhausted = false;
+                }
+                () = shutdown => break,
+            }
+        }
+        Ok(connections)
+    }
+}
+
+/// The live connections of [`TcpListener::serve_until`].
+///
+/// Dropping it cancels the remaining handlers.
+pub struct Connections {
+    tasks: FuturesUnordered<HandlerTask>,
+    on_panic: Option<PanicHook>,
+}
+
+impl Connections {
+    /// The count of the live connections.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// If there are no live connections.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    fn report_panic(&self, res: Result<(), Box<dyn Any + Send>>) {
+        if let (Err(payload), Some(on_panic)) = (res, &self.on_panic) {
+            on_panic(payload);
+        }
+    }
+
+    /// Wait for all live connections to be handled.
+    pub async fn wait(mut self) {
+        while let Some(res) = self.tasks.next().await {
+            self.report_panic(res);
+        }
+    }
+
+    /// Wait for the live connections to be handled until `timeout` elapses,
+    /// and cancel the remaining ones. Returns the count of the cancelled
+    /// connections.
+    #[cfg(feature = "time")]
+    pub async fn shutdown(self, timeout: std::time::Duration) -> usize {
+        let deadline = crate::time::now() + timeout;
+        self.shutdown_at(deadline).await
+    }
+
+    /// Wait for the live connections to be handled until `deadline`, and
+    /// cancel the remaining ones. Returns the count of the cancelled
+    /// connections.
+    #[cfg(feature = "time")]
+    pub async fn shutdown_at(mut self, deadline: std::time::Instant) -> usize {
+        let wait = async {
+            while let Some(res) = self.tasks.next().await {
+                self.report_panic(res);
+            }
+        };
+        crate::time::timeout_at(deadline, wait).await.ok();
+        self.tasks.len()
+    }
+}
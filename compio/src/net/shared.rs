@@ -0,0 +1,245 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    future::Future,
+    io,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    buf::{IoBuf, IoBufMut},
+    net::TcpStream,
+    BufResult,
+};
+
+/// A [`TcpStream`] shared by the tasks of current runtime, e.g., a WebSocket
+/// connection where both the reader task and a heartbeat task send pings.
+/// The clones refer to the same stream.
+///
+/// The sends are serialized, so that the data of each send doesn't
+/// interleave with the others. The sends of a handle are in order if each is
+/// awaited before the next one. The sends from different handles, or
+/// different tasks, are performed in the order they start waiting, i.e.,
+/// first come, first served, which is best-effort because the order of the
+/// tasks is up to the scheduler.
+///
+/// Only one receive could be performed at a time. A receive while another one
+/// is in flight, from any handle, fails with [`io::ErrorKind::ResourceBusy`]
+/// instead of splitting the data between them.
+///
+/// If a send is cancelled, e.g., by a timeout, the data may be partially
+/// sent, and the peer should not expect the message any more.
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use compio::net::{SharedTcpStream, TcpListener, TcpStream};
+///
+/// compio::task::block_on(async {
+///     let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     let addr = listener.local_addr().unwrap();
+///     let (client, (server, _)) =
+///         futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+///
+///     let client = SharedTcpStream::new(client);
+///     let heartbeat = compio::task::spawn({
+///         let client = client.clone();
+///         async move { client.send_all("ping;").await.0 }
+///     });
+///     client.send_all("data;").await.0.unwrap();
+///     heartbeat.await.unwrap();
+///
+///     let (res, buffer) = server.recv_exact(Vec::with_capacity(10)).await;
+///     res.unwrap();
+///     assert!(buffer == b"ping;data;" || buffer == b"data;ping;");
+/// })
+/// ```
+#[derive(Clone)]
+pub struct SharedTcpStream {
+    inner: Rc<Shared>,
+}
+
+struct Shared {
+    stream: TcpStream,
+    sending: SendLock,
+    receiving: Cell<bool>,
+}
+
+impl SharedTcpStream {
+    /// Share the stream between the handles.
+    pub fn new(stream: TcpStream) -> Self {
+        Self {
+            inner: Rc::new(Shared {
+                stream,
+                sending: SendLock::default(),
+                receiving: Cell::new(false),
+            }),
+        }
+    }
+
+    /// The shared stream, e.g., to get the addresses or set the options. The
+    /// IO on it is not serialized.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.inner.stream
+    }
+
+    /// Sends some data to the socket from the buffer, returning the original
+    /// buffer and quantity of data sent, after the sends before it.
+    pub async fn send<T: IoBuf>(&self, buffer: T) -> BufResult<usize, T> {
+        let _guard = self.inner.sending.lock().await;
+        self.inner.stream.send(buffer).await
+    }
+
+    /// Sends all data to the socket, after the sends before it. No other send
+    /// is performed until it completes.
+    pub async fn send_all<T: IoBuf>(&self, buffer: T) -> BufResult<usize, T> {
+        let _guard = self.inner.sending.lock().await;
+        self.inner.stream.send_all(buffer).await
+    }
+
+    /// Sends some data to the socket from the buffers, returning the original
+    /// buffers and quantity of data sent, after the sends before it.
+    pub async fn send_vectored<T: IoBuf>(&self, buffer: Vec<T>) -> BufResult<usize, Vec<T>> {
+        let _guard = self.inner.sending.lock().await;
+        self.inner.stream.send_vectored(buffer).await
+    }
+
+    /// Receives a packet of data from the socket into the buffer, returning
+    /// the original buffer and quantity of data received. It fails if another
+    /// receive is in flight.
+    pub async fn recv<T: IoBufMut>(&self, buffer: T) -> BufResult<usize, T> {
+        let _guard = match self.receiving() {
+            Ok(guard) => guard,
+            Err(e) => return (Err(e), buffer),
+        };
+        self.inner.stream.recv(buffer).await
+    }
+
+    /// Receives exact number of bytes from the socket, i.e., until the buffer
+    /// is full. It fails if another receive is in flight.
+    pub async fn recv_exact<T: IoBufMut>(&self, buffer: T) -> BufResult<usize, T> {
+        let _guard = match self.receiving() {
+            Ok(guard) => guard,
+            Err(e) => return (Err(e), buffer),
+        };
+        self.inner.stream.recv_exact(buffer).await
+    }
+
+    /// Receives a packet of data from the socket into the buffers, returning
+    /// the original buffers and quantity of data received. It fails if
+    /// another receive is in flight.
+    pub async fn recv_vectored<T: IoBufMut>(&self, buffer: Vec<T>) -> BufResult<usize, Vec<T>> {
+        let _guard = match self.receiving() {
+            Ok(guard) => guard,
+            Err(e) => return (Err(e), buffer),
+        };
+        self.inner.stream.recv_vectored(buffer).await
+    }
+
+    fn receiving(&self) -> io::Result<Receiving<'_>> {
+        if self.inner.receiving.replace(true) {
+            Err(io::Error::new(
+                io::ErrorKind::ResourceBusy,
+                "another receive of the shared TCP stream is in flight",
+            ))
+        } else {
+            Ok(Receiving(&self.inner.receiving))
+        }
+    }
+}
+
+impl From<TcpStream> for SharedTcpStream {
+    fn from(stream: TcpStream) -> Self {
+        Self::new(stream)
+    }
+}
+
+// Marks the receive in flight until dropped.
+struct Receiving<'a>(&'a Cell<bool>);
+
+impl Drop for Receiving<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+// A first come, first served lock of the sends. Each waiter takes a ticket,
+// and the lock is handed to the smallest ticket still waiting.
+#[derive(Default)]
+struct SendLock {
+    next: Cell<u64>,
+    serving: Cell<u64>,
+    waiters: RefCell<BTreeMap<u64, Option<Waker>>>,
+}
+
+impl SendLock {
+    fn lock(&self) -> Lock<'_> {
+        let ticket = self.next.get();
+        self.next.set(ticket + 1);
+        self.waiters.borrow_mut().insert(ticket, None);
+        Lock {
+            lock: self,
+            ticket,
+            acquired: false,
+        }
+    }
+
+    // Hand the lock to the next waiter, skipping the cancelled ones.
+    fn unlock(&self) {
+        let waiters = self.waiters.borrow();
+        match waiters.iter().next() {
+            Some((ticket, waker)) => {
+                self.serving.set(*ticket);
+                if let Some(waker) = waker {
+                    waker.wake_by_ref();
+                }
+            }
+            None => self.serving.set(self.next.get()),
+        }
+    }
+}
+
+struct Lock<'a> {
+    lock: &'a SendLock,
+    ticket: u64,
+    acquired: bool,
+}
+
+impl<'a> Future for Lock<'a> {
+    type Output = SendGuard<'a>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mut waiters = this.lock.waiters.borrow_mut();
+        if this.lock.serving.get() == this.ticket {
+            waiters.remove(&this.ticket);
+            this.acquired = true;
+            Poll::Ready(SendGuard(this.lock))
+        } else {
+            waiters.insert(this.ticket, Some(cx.waker().clone()));
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Lock<'_> {
+    fn drop(&mut self) {
+        if !self.acquired {
+            self.lock.waiters.borrow_mut().remove(&self.ticket);
+            // Woken, but cancelled before taking the lock.
+            if self.lock.serving.get() == self.ticket {
+                self.lock.unlock();
+            }
+        }
+    }
+}
+
+struct SendGuard<'a>(&'a SendLock);
+
+impl Drop for SendGuard<'_> {
+    fn drop(&mut self) {
+        self.0.unlock();
+    }
+}
@@ -1,25 +1,60 @@
-use std::{io, net::Shutdown};
+#[cfg(feature = "runtime")]
+use std::{
+    cell::{Cell, OnceCell},
+    sync::Arc,
+};
+#[cfg(feature = "time")]
+use std::rc::Rc;
+use std::{io, net::Shutdown, time::Duration};
 
-use socket2::{Domain, Protocol, SockAddr, Socket as Socket2, Type};
+use socket2::{Domain, Protocol, SockAddr, Socket as Socket2, TcpKeepalive, Type};
 
-use crate::impl_raw_fd;
+use crate::{
+    impl_raw_fd,
+    net::{MtuDiscovery, SocketOpts, TcpInfo},
+};
 #[cfg(feature = "runtime")]
 use crate::{
     buf::{IntoInner, IoBuf, IoBufMut},
     buf_try,
-    driver::AsRawFd,
+    driver::{AsRawFd, OpCode},
     op::{
         Accept, BufResultExt, Connect, Recv, RecvFrom, RecvFromVectored, RecvResultExt,
         RecvVectored, Send, SendTo, SendToVectored, SendVectored,
     },
-    task::submit,
+    net::{MappedAddrPolicy, RecvMeta, RecvTimestamp, ZeroCopyStats, ZeroCopyTracker},
+    task::{op::fd_budget, submit},
     Attacher, BufResult,
 };
+#[cfg(feature = "time")]
+use crate::task::{
+    op::{scoped, InterruptScope},
+    RUNTIME,
+};
+#[cfg(all(
+    feature = "runtime",
+    any(target_os = "linux", target_os = "android", target_os = "windows")
+))]
+use crate::op::ConnectWithData;
 
 pub struct Socket {
     socket: Socket2,
     #[cfg(feature = "runtime")]
     attacher: Attacher,
+    #[cfg(feature = "runtime")]
+    recv_poll_first: Cell<bool>,
+    #[cfg(feature = "runtime")]
+    zerocopy: Cell<bool>,
+    #[cfg(feature = "runtime")]
+    zerocopy_tracker: Arc<ZeroCopyTracker>,
+    #[cfg(feature = "runtime")]
+    mapped_addrs: Cell<MappedAddrPolicy>,
+    // The addresses of a listener or a connection, which never change once
+    // known, see `cached_local_addr` and `cached_peer_addr`.
+    #[cfg(feature = "runtime")]
+    local_addr_cache: OnceCell<SockAddr>,
+    #[cfg(feature = "runtime")]
+    peer_addr_cache: OnceCell<SockAddr>,
 }
 
 impl Socket {
@@ -28,6 +63,18 @@ impl Socket {
             socket,
             #[cfg(feature = "runtime")]
             attacher: Attacher::new(),
+            #[cfg(feature = "runtime")]
+            recv_poll_first: Cell::new(false),
+            #[cfg(feature = "runtime")]
+            zerocopy: Cell::new(false),
+            #[cfg(feature = "runtime")]
+            zerocopy_tracker: Arc::default(),
+            #[cfg(feature = "runtime")]
+            mapped_addrs: Cell::default(),
+            #[cfg(feature = "runtime")]
+            local_addr_cache: OnceCell::new(),
+            #[cfg(feature = "runtime")]
+            peer_addr_cache: OnceCell::new(),
         }
     }
 
@@ -37,23 +84,81 @@ impl Socket {
     }
 
     pub fn try_clone(&self) -> io::Result<Self> {
+        // The duplicated sockets refer to the same socket object on Windows, which
+        // could only be attached to one completion port. Attach it here, and share
+        // the attach state.
+        #[cfg(all(feature = "runtime", windows))]
+        self.attach()?;
         Ok(Self {
             socket: self.socket.try_clone()?,
-            #[cfg(feature = "runtime")]
+            #[cfg(all(feature = "runtime", windows))]
             attacher: self.attacher.clone(),
+            #[cfg(all(feature = "runtime", unix))]
+            attacher: Attacher::new(),
+            #[cfg(feature = "runtime")]
+            recv_poll_first: self.recv_poll_first.clone(),
+            // The duplicated fds share the numbering of the sends.
+            #[cfg(feature = "runtime")]
+            zerocopy: self.zerocopy.clone(),
+            #[cfg(feature = "runtime")]
+            zerocopy_tracker: self.zerocopy_tracker.clone(),
+            #[cfg(feature = "runtime")]
+            mapped_addrs: self.mapped_addrs.clone(),
+            #[cfg(feature = "runtime")]
+            local_addr_cache: self.local_addr_cache.clone(),
+            #[cfg(feature = "runtime")]
+            peer_addr_cache: self.peer_addr_cache.clone(),
         })
     }
 
     pub fn peer_addr(&self) -> io::Result<SockAddr> {
+        crate::count_syscall!("getpeername");
         self.socket.peer_addr()
     }
 
     pub fn local_addr(&self) -> io::Result<SockAddr> {
+        crate::count_syscall!("getsockname");
         self.socket.local_addr()
     }
 
+    // The remote address of a connection, filled by the accepts and the
+    // connects, or retrieved once, e.g., for a stream created from std.
+    pub(crate) fn cached_peer_addr(&self) -> io::Result<SockAddr> {
+        #[cfg(feature = "runtime")]
+        if let Some(addr) = self.peer_addr_cache.get() {
+            return Ok(addr.clone());
+        }
+        let addr = self.peer_addr()?;
+        #[cfg(feature = "runtime")]
+        self.peer_addr_cache.set(addr.clone()).ok();
+        Ok(addr)
+    }
+
+    // The local address of a listener or a connection, retrieved once.
+    pub(crate) fn cached_local_addr(&self) -> io::Result<SockAddr> {
+        #[cfg(feature = "runtime")]
+        if let Some(addr) = self.local_addr_cache.get() {
+            return Ok(addr.clone());
+        }
+        let addr = self.local_addr()?;
+        #[cfg(feature = "runtime")]
+        self.local_addr_cache.set(addr.clone()).ok();
+        Ok(addr)
+    }
+
+    // Record the remote address known from an accept or a connect, which
+    // saves a `getpeername`.
+    #[cfg(feature = "runtime")]
+    fn set_peer_addr(&self, addr: &SockAddr) {
+        self.peer_addr_cache.set(addr.clone()).ok();
+    }
+
     pub fn new(domain: Domain, ty: Type, protocol: Option<Protocol>) -> io::Result<Self> {
-        let socket = Socket2::new(domain, ty, protocol)?;
+        Self::from_foreign(Socket2::new(domain, ty, protocol)?)
+    }
+
+    // Set up a socket created elsewhere, e.g., inherited from another process.
+    pub fn from_foreign(socket: Socket2) -> io::Result<Self> {
         // On Linux we use blocking socket
         // Newer kernels have the patch that allows to arm io_uring poll mechanism for
         // non blocking socket when there is no connections in listen queue
@@ -68,12 +173,59 @@ impl Socket {
         Ok(Self::from_socket2(socket))
     }
 
+    pub fn into_socket2(self) -> Socket2 {
+        self.socket
+    }
+
     pub fn bind(addr: &SockAddr, ty: Type, protocol: Option<Protocol>) -> io::Result<Self> {
+        Self::bind_with(addr, ty, protocol, &SocketOpts::default())
+    }
+
+    pub fn bind_with(
+        addr: &SockAddr,
+        ty: Type,
+        protocol: Option<Protocol>,
+        opts: &SocketOpts,
+    ) -> io::Result<Self> {
         let socket = Self::new(addr.domain(), ty, protocol)?;
+        opts.apply(&socket, addr.domain())?;
         socket.socket.bind(addr)?;
         Ok(socket)
     }
 
+    // Bind to the address shared with other sockets, and the kernel balances
+    // the connections among them.
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    pub fn bind_reuse_port(
+        addr: &SockAddr,
+        ty: Type,
+        protocol: Option<Protocol>,
+    ) -> io::Result<Self> {
+        let socket = Self::new(addr.domain(), ty, protocol)?;
+        socket.socket.set_reuse_address(true)?;
+        // `SO_REUSEPORT` on FreeBSD doesn't balance.
+        #[cfg(target_os = "freebsd")]
+        socket.socket.set_reuse_port_lb(true)?;
+        #[cfg(not(target_os = "freebsd"))]
+        socket.socket.set_reuse_port(true)?;
+        socket.socket.bind(addr)?;
+        Ok(socket)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_reuseport_cbpf(&self, filter: &[libc::sock_filter]) -> io::Result<()> {
+        // The value in `asm-generic`, which is not defined by `libc` on Linux.
+        const SO_ATTACH_REUSEPORT_CBPF: libc::c_int = 51;
+
+        let program = libc::sock_fprog {
+            len: filter.len().try_into().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "the filter is too long")
+            })?,
+            filter: filter.as_ptr() as *mut _,
+        };
+        self.setsockopt(libc::SOL_SOCKET, SO_ATTACH_REUSEPORT_CBPF, program)
+    }
+
     pub fn listen(&self, backlog: i32) -> io::Result<()> {
         self.socket.listen(backlog)
     }
@@ -82,6 +234,696 @@ impl Socket {
         self.socket.shutdown(how)
     }
 
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        self.socket.linger()
+    }
+
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        self.socket.set_linger(linger)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_fastopen(&self, queue_len: u32) -> io::Result<()> {
+        self.setsockopt(
+            libc::IPPROTO_TCP,
+            libc::TCP_FASTOPEN,
+            queue_len as libc::c_int,
+        )
+    }
+
+    #[cfg(target_os = "windows")]
+    pub fn set_tcp_fastopen(&self, queue_len: u32) -> io::Result<()> {
+        use windows_sys::Win32::Networking::WinSock::{IPPROTO_TCP, TCP_FASTOPEN};
+
+        self.setsockopt(IPPROTO_TCP, TCP_FASTOPEN, (queue_len > 0) as u32)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "windows")))]
+    pub fn set_tcp_fastopen(&self, _queue_len: u32) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "TCP Fast Open is not supported on this platform",
+        ))
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_tcp_defer_accept(&self, timeout: Option<Duration>) -> io::Result<()> {
+        // The kernel rounds the seconds up to the retransmission timeouts.
+        let secs = timeout.map_or(0, |timeout| {
+            timeout.as_secs().clamp(1, libc::c_int::MAX as u64) as libc::c_int
+        });
+        self.setsockopt(libc::IPPROTO_TCP, libc::TCP_DEFER_ACCEPT, secs)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub fn set_tcp_defer_accept(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "TCP_DEFER_ACCEPT is not supported on this platform",
+        ))
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn bind_device(&self, name: Option<&str>, _domain: Domain) -> io::Result<()> {
+        self.socket.bind_device(name.map(str::as_bytes))
+    }
+
+    #[cfg(any(
+        target_vendor = "apple",
+        target_os = "solaris",
+        target_os = "illumos",
+        target_os = "windows"
+    ))]
+    pub fn bind_device(&self, name: Option<&str>, domain: Domain) -> io::Result<()> {
+        let index = match name {
+            Some(name) => {
+                let name = std::ffi::CString::new(name)?;
+                #[cfg(unix)]
+                let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+                #[cfg(windows)]
+                let index = unsafe {
+                    windows_sys::Win32::NetworkManagement::IpHelper::if_nametoindex(
+                        name.as_ptr().cast(),
+                    )
+                };
+                if index == 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "the interface is not found",
+                    ));
+                }
+                index
+            }
+            None => 0,
+        };
+        self.bind_device_by_index(index, domain)
+    }
+
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "solaris",
+        target_os = "illumos",
+        target_os = "windows"
+    )))]
+    pub fn bind_device(&self, _name: Option<&str>, _domain: Domain) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "binding to a device is not supported on this platform",
+        ))
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "solaris",
+        target_os = "illumos"
+    ))]
+    pub fn bind_device_by_index(&self, index: u32, domain: Domain) -> io::Result<()> {
+        let index = std::num::NonZeroU32::new(index);
+        if domain == Domain::IPV6 {
+            self.socket.bind_device_by_index_v6(index)
+        } else {
+            self.socket.bind_device_by_index_v4(index)
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    pub fn bind_device_by_index(&self, index: u32, domain: Domain) -> io::Result<()> {
+        use windows_sys::Win32::Networking::WinSock::{
+            IPPROTO_IP, IPPROTO_IPV6, IPV6_UNICAST_IF, IP_UNICAST_IF,
+        };
+
+        // The index is in the network byte order for IPv4.
+        if domain == Domain::IPV6 {
+            self.setsockopt(IPPROTO_IPV6, IPV6_UNICAST_IF, index)
+        } else {
+            self.setsockopt(IPPROTO_IP, IP_UNICAST_IF, index.to_be())
+        }
+    }
+
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "solaris",
+        target_os = "illumos",
+        target_os = "windows"
+    )))]
+    pub fn bind_device_by_index(&self, _index: u32, _domain: Domain) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "binding to a device is not supported on this platform",
+        ))
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_freebind(&self, freebind: bool, domain: Domain) -> io::Result<()> {
+        if domain == Domain::IPV6 {
+            self.socket.set_freebind_v6(freebind)
+        } else {
+            self.socket.set_freebind_v4(freebind)
+        }
+    }
+
+    #[cfg(target_os = "freebsd")]
+    pub fn set_freebind(&self, freebind: bool, domain: Domain) -> io::Result<()> {
+        if domain == Domain::IPV6 {
+            self.setsockopt(
+                libc::IPPROTO_IPV6,
+                libc::IPV6_BINDANY,
+                freebind as libc::c_int,
+            )
+        } else {
+            self.setsockopt(libc::IPPROTO_IP, libc::IP_BINDANY, freebind as libc::c_int)
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+    pub fn set_freebind(&self, _freebind: bool, _domain: Domain) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "binding to a non-local address is not supported on this platform",
+        ))
+    }
+
+    pub fn set_only_v6(&self, only_v6: bool) -> io::Result<()> {
+        self.socket.set_only_v6(only_v6)
+    }
+
+    // Bind the local end of a socket to be connected.
+    pub fn bind_local(&self, addr: &SockAddr) -> io::Result<()> {
+        // Don't reserve a port at binding, so that the same port could be used
+        // with different remote addresses.
+        #[cfg(target_os = "linux")]
+        if addr.as_socket().is_some_and(|addr| addr.port() == 0) {
+            self.setsockopt(libc::IPPROTO_IP, libc::IP_BIND_ADDRESS_NO_PORT, 1)?;
+        }
+        self.socket.bind(addr)
+    }
+
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        self.socket.set_tcp_nodelay(nodelay)
+    }
+
+    pub fn set_keepalive(&self, keepalive: Option<&TcpKeepalive>) -> io::Result<()> {
+        match keepalive {
+            Some(keepalive) => self.socket.set_tcp_keepalive(keepalive),
+            None => self.socket.set_keepalive(false),
+        }
+    }
+
+    pub fn keepalive(&self) -> io::Result<bool> {
+        self.socket.keepalive()
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_user_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.socket.set_tcp_user_timeout(timeout)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn user_timeout(&self) -> io::Result<Option<Duration>> {
+        self.socket.tcp_user_timeout()
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub fn set_user_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the TCP user timeout is not supported on this platform",
+        ))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub fn user_timeout(&self) -> io::Result<Option<Duration>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the TCP user timeout is not supported on this platform",
+        ))
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_mark(&self, mark: u32) -> io::Result<()> {
+        self.socket.set_mark(mark)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub fn set_mark(&self, _mark: u32) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "marking the packets is not supported on this platform",
+        ))
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "macos",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "illumos"
+    ))]
+    pub fn set_tos(&self, tos: u32, domain: Domain) -> io::Result<()> {
+        if domain == Domain::IPV6 {
+            self.socket.set_tclass_v6(tos)
+        } else {
+            self.socket.set_tos_v4(tos)
+        }
+    }
+
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "macos",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "illumos"
+    )))]
+    pub fn set_tos(&self, tos: u32, domain: Domain) -> io::Result<()> {
+        if domain == Domain::IPV6 {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the traffic class of IPv6 is not supported on this platform",
+            ))
+        } else {
+            self.socket.set_tos_v4(tos)
+        }
+    }
+
+    #[cfg(feature = "runtime")]
+    pub fn set_mapped_addr_policy(&self, policy: MappedAddrPolicy) {
+        self.mapped_addrs.set(policy);
+    }
+
+    #[cfg(feature = "runtime")]
+    pub fn mapped_addr_policy(&self) -> MappedAddrPolicy {
+        self.mapped_addrs.get()
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn send_buffer_space(&self) -> io::Result<usize> {
+        use std::os::fd::AsRawFd;
+
+        let size = self.socket.send_buffer_size()?;
+        let mut queued: libc::c_int = 0;
+        crate::syscall!(ioctl(
+            self.socket.as_raw_fd(),
+            libc::TIOCOUTQ,
+            &mut queued
+        ))?;
+        Ok(size.saturating_sub(queued as usize))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub fn send_buffer_space(&self) -> io::Result<usize> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the queued bytes of the send buffer are not available on this platform",
+        ))
+    }
+
+    #[cfg(unix)]
+    pub fn recv_queued(&self) -> io::Result<usize> {
+        use std::os::fd::AsRawFd;
+
+        // `FIONREAD` is the same as `SIOCINQ` for the sockets on Linux.
+        let mut queued: libc::c_int = 0;
+        crate::syscall!(ioctl(self.socket.as_raw_fd(), libc::FIONREAD, &mut queued))?;
+        Ok(queued as usize)
+    }
+
+    #[cfg(target_os = "windows")]
+    pub fn recv_queued(&self) -> io::Result<usize> {
+        use std::os::windows::io::AsRawSocket;
+
+        use windows_sys::Win32::Networking::WinSock::{ioctlsocket, FIONREAD};
+
+        let mut queued = 0u32;
+        crate::syscall!(
+            SOCKET,
+            ioctlsocket(self.socket.as_raw_socket() as _, FIONREAD, &mut queued)
+        )?;
+        Ok(queued as usize)
+    }
+
+    #[cfg(unix)]
+    pub fn is_readable(&self) -> io::Result<bool> {
+        use std::os::fd::AsRawFd;
+
+        let mut pollfd = libc::pollfd {
+            fd: self.socket.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // Returns at once with the timeout 0. An error or a hang-up is also
+        // reported as readable, where a read returns at once.
+        let ready = crate::syscall!(poll(&mut pollfd, 1, 0))?;
+        Ok(ready > 0)
+    }
+
+    #[cfg(target_os = "windows")]
+    pub fn is_readable(&self) -> io::Result<bool> {
+        use std::os::windows::io::AsRawSocket;
+
+        use windows_sys::Win32::Networking::WinSock::{WSAPoll, POLLRDNORM, WSAPOLLFD};
+
+        let mut pollfd = WSAPOLLFD {
+            fd: self.socket.as_raw_socket() as _,
+            events: POLLRDNORM,
+            revents: 0,
+        };
+        let ready = crate::syscall!(SOCKET, WSAPoll(&mut pollfd, 1, 0))?;
+        Ok(ready > 0)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn tcp_info(&self) -> io::Result<TcpInfo> {
+        let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+        let len = self.getsockopt(libc::IPPROTO_TCP, libc::TCP_INFO, &mut info)?;
+        // The older kernels fill a shorter struct.
+        let delivery_rate_end = std::mem::offset_of!(libc::tcp_info, tcpi_delivery_rate)
+            + std::mem::size_of_val(&info.tcpi_delivery_rate);
+        Ok(TcpInfo {
+            rtt: Some(Duration::from_micros(info.tcpi_rtt as _)),
+            rtt_var: Some(Duration::from_micros(info.tcpi_rttvar as _)),
+            // In segments.
+            snd_cwnd: Some(info.tcpi_snd_cwnd as u64 * info.tcpi_snd_mss as u64),
+            retransmits: Some(info.tcpi_total_retrans as _),
+            delivery_rate: (len >= delivery_rate_end).then_some(info.tcpi_delivery_rate),
+        })
+    }
+
+    #[cfg(target_vendor = "apple")]
+    pub fn tcp_info(&self) -> io::Result<TcpInfo> {
+        let mut info: libc::tcp_connection_info = unsafe { std::mem::zeroed() };
+        self.getsockopt(libc::IPPROTO_TCP, libc::TCP_CONNECTION_INFO, &mut info)?;
+        Ok(TcpInfo {
+            rtt: Some(Duration::from_millis(info.tcpi_srtt as _)),
+            rtt_var: Some(Duration::from_millis(info.tcpi_rttvar as _)),
+            snd_cwnd: Some(info.tcpi_snd_cwnd as _),
+            retransmits: Some(info.tcpi_txretransmitpackets),
+            delivery_rate: None,
+        })
+    }
+
+    #[cfg(target_os = "windows")]
+    pub fn tcp_info(&self) -> io::Result<TcpInfo> {
+        use std::os::windows::io::AsRawSocket;
+
+        use windows_sys::Win32::Networking::WinSock::{WSAIoctl, SIO_TCP_INFO, TCP_INFO_v0};
+
+        let version = 0u32;
+        let mut info: TCP_INFO_v0 = unsafe { std::mem::zeroed() };
+        let mut len = 0u32;
+        crate::syscall!(
+            SOCKET,
+            WSAIoctl(
+                self.socket.as_raw_socket() as _,
+                SIO_TCP_INFO,
+                std::ptr::addr_of!(version).cast(),
+                std::mem::size_of_val(&version) as _,
+                std::ptr::addr_of_mut!(info).cast(),
+                std::mem::size_of_val(&info) as _,
+                &mut len,
+                std::ptr::null_mut(),
+                None,
+            )
+        )?;
+        Ok(TcpInfo {
+            rtt: Some(Duration::from_micros(info.RttUs as _)),
+            rtt_var: None,
+            snd_cwnd: Some(info.Cwnd as _),
+            retransmits: None,
+            delivery_rate: None,
+        })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_vendor = "apple", target_os = "windows")))]
+    pub fn tcp_info(&self) -> io::Result<TcpInfo> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the TCP info is not supported on this platform",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn set_congestion(&self, name: &str) -> io::Result<()> {
+        use std::os::fd::AsRawFd;
+
+        crate::syscall!(setsockopt(
+            self.socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_CONGESTION,
+            name.as_ptr().cast(),
+            name.len() as _,
+        ))?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn congestion(&self) -> io::Result<String> {
+        // `TCP_CA_NAME_MAX`
+        let mut name = [0u8; 16];
+        let len = self.getsockopt(libc::IPPROTO_TCP, libc::TCP_CONGESTION, &mut name)?;
+        let name = &name[..len];
+        let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        Ok(String::from_utf8_lossy(&name[..len]).into_owned())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn set_congestion(&self, _name: &str) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "choosing the congestion control is not supported on this platform",
+        ))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn congestion(&self) -> io::Result<String> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "choosing the congestion control is not supported on this platform",
+        ))
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_mtu_discovery(&self, discovery: MtuDiscovery, domain: Domain) -> io::Result<()> {
+        let (v4, v6) = match discovery {
+            MtuDiscovery::Want => (libc::IP_PMTUDISC_WANT, libc::IPV6_PMTUDISC_WANT),
+            MtuDiscovery::Do => (libc::IP_PMTUDISC_DO, libc::IPV6_PMTUDISC_DO),
+            MtuDiscovery::Dont => (libc::IP_PMTUDISC_DONT, libc::IPV6_PMTUDISC_DONT),
+            MtuDiscovery::Probe => (libc::IP_PMTUDISC_PROBE, libc::IPV6_PMTUDISC_PROBE),
+        };
+        if domain == Domain::IPV6 {
+            self.setsockopt(libc::IPPROTO_IPV6, libc::IPV6_MTU_DISCOVER, v6)?;
+        }
+        // The IPv4 one also applies to the IPv4 datagrams of a dual-stack
+        // socket.
+        match self.setsockopt(libc::IPPROTO_IP, libc::IP_MTU_DISCOVER, v4) {
+            Err(_) if domain == Domain::IPV6 => Ok(()),
+            res => res,
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn mtu_discovery(&self, domain: Domain) -> io::Result<MtuDiscovery> {
+        let mut value: libc::c_int = 0;
+        if domain == Domain::IPV6 {
+            self.getsockopt(libc::IPPROTO_IPV6, libc::IPV6_MTU_DISCOVER, &mut value)?;
+        } else {
+            self.getsockopt(libc::IPPROTO_IP, libc::IP_MTU_DISCOVER, &mut value)?;
+        }
+        // The values are the same for both families.
+        match value {
+            libc::IP_PMTUDISC_WANT => Ok(MtuDiscovery::Want),
+            libc::IP_PMTUDISC_DO => Ok(MtuDiscovery::Do),
+            libc::IP_PMTUDISC_DONT => Ok(MtuDiscovery::Dont),
+            libc::IP_PMTUDISC_PROBE => Ok(MtuDiscovery::Probe),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown MTU discovery mode {value}"),
+            )),
+        }
+    }
+
+    #[cfg(any(target_vendor = "apple", target_os = "freebsd", target_os = "windows"))]
+    pub fn set_mtu_discovery(&self, discovery: MtuDiscovery, domain: Domain) -> io::Result<()> {
+        #[cfg(unix)]
+        use libc::{IPPROTO_IP, IPPROTO_IPV6, IPV6_DONTFRAG, IP_DONTFRAG};
+        #[cfg(windows)]
+        use windows_sys::Win32::Networking::WinSock::{
+            IPPROTO_IP, IPPROTO_IPV6, IPV6_DONTFRAG, IP_DONTFRAGMENT as IP_DONTFRAG,
+        };
+
+        let value = matches!(discovery, MtuDiscovery::Do | MtuDiscovery::Probe) as i32;
+        if domain == Domain::IPV6 {
+            self.setsockopt(IPPROTO_IPV6, IPV6_DONTFRAG, value)?;
+        }
+        match self.setsockopt(IPPROTO_IP, IP_DONTFRAG, value) {
+            Err(_) if domain == Domain::IPV6 => Ok(()),
+            res => res,
+        }
+    }
+
+    #[cfg(any(target_vendor = "apple", target_os = "freebsd", target_os = "windows"))]
+    pub fn mtu_discovery(&self, domain: Domain) -> io::Result<MtuDiscovery> {
+        #[cfg(unix)]
+        use libc::{IPPROTO_IP, IPPROTO_IPV6, IPV6_DONTFRAG, IP_DONTFRAG};
+        #[cfg(windows)]
+        use windows_sys::Win32::Networking::WinSock::{
+            IPPROTO_IP, IPPROTO_IPV6, IPV6_DONTFRAG, IP_DONTFRAGMENT as IP_DONTFRAG,
+        };
+
+        let mut value = 0i32;
+        if domain == Domain::IPV6 {
+            self.getsockopt(IPPROTO_IPV6, IPV6_DONTFRAG, &mut value)?;
+        } else {
+            self.getsockopt(IPPROTO_IP, IP_DONTFRAG, &mut value)?;
+        }
+        Ok(if value != 0 {
+            MtuDiscovery::Do
+        } else {
+            MtuDiscovery::Dont
+        })
+    }
+
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "windows"
+    )))]
+    pub fn set_mtu_discovery(&self, _discovery: MtuDiscovery, _domain: Domain) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the path MTU discovery is not configurable on this platform",
+        ))
+    }
+
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "windows"
+    )))]
+    pub fn mtu_discovery(&self, _domain: Domain) -> io::Result<MtuDiscovery> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the path MTU discovery is not configurable on this platform",
+        ))
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "windows"))]
+    pub fn mtu(&self, domain: Domain) -> io::Result<u32> {
+        #[cfg(unix)]
+        use libc::{IPPROTO_IP, IPPROTO_IPV6, IPV6_MTU, IP_MTU};
+        #[cfg(windows)]
+        use windows_sys::Win32::Networking::WinSock::{IPPROTO_IP, IPPROTO_IPV6, IPV6_MTU, IP_MTU};
+
+        let mut mtu = 0i32;
+        if domain == Domain::IPV6 {
+            self.getsockopt(IPPROTO_IPV6, IPV6_MTU, &mut mtu)?;
+        } else {
+            self.getsockopt(IPPROTO_IP, IP_MTU, &mut mtu)?;
+        }
+        Ok(mtu as u32)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "windows")))]
+    pub fn mtu(&self, _domain: Domain) -> io::Result<u32> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "querying the path MTU is not supported on this platform",
+        ))
+    }
+
+    // Returns the length filled.
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "freebsd"
+    ))]
+    fn getsockopt<T>(
+        &self,
+        level: libc::c_int,
+        name: libc::c_int,
+        value: &mut T,
+    ) -> io::Result<usize> {
+        use std::os::fd::AsRawFd;
+
+        let mut len = std::mem::size_of::<T>() as libc::socklen_t;
+        crate::syscall!(getsockopt(
+            self.socket.as_raw_fd(),
+            level,
+            name,
+            (value as *mut T).cast(),
+            &mut len,
+        ))?;
+        Ok(len as _)
+    }
+
+    #[cfg(unix)]
+    fn setsockopt<T>(&self, level: libc::c_int, name: libc::c_int, value: T) -> io::Result<()> {
+        use std::os::fd::AsRawFd;
+
+        crate::syscall!(setsockopt(
+            self.socket.as_raw_fd(),
+            level,
+            name,
+            std::ptr::addr_of!(value).cast(),
+            std::mem::size_of::<T>() as _,
+        ))?;
+        Ok(())
+    }
+
+    // Returns the length filled.
+    #[cfg(target_os = "windows")]
+    fn getsockopt<T>(&self, level: i32, name: i32, value: &mut T) -> io::Result<usize> {
+        use std::os::windows::io::AsRawSocket;
+
+        use windows_sys::Win32::Networking::WinSock::getsockopt;
+
+        let mut len = std::mem::size_of::<T>() as i32;
+        crate::syscall!(
+            SOCKET,
+            getsockopt(
+                self.socket.as_raw_socket() as _,
+                level,
+                name,
+                (value as *mut T).cast(),
+                &mut len,
+            )
+        )?;
+        Ok(len as _)
+    }
+
+    #[cfg(target_os = "windows")]
+    fn setsockopt<T>(&self, level: i32, name: i32, value: T) -> io::Result<()> {
+        use std::os::windows::io::AsRawSocket;
+
+        use windows_sys::Win32::Networking::WinSock::setsockopt;
+
+        crate::syscall!(
+            SOCKET,
+            setsockopt(
+                self.socket.as_raw_socket() as _,
+                level,
+                name,
+                std::ptr::addr_of!(value).cast(),
+                std::mem::size_of::<T>() as _,
+            )
+        )?;
+        Ok(())
+    }
+
     pub fn connect(&self, addr: &SockAddr) -> io::Result<()> {
         self.socket.connect(addr)
     }
@@ -91,15 +933,59 @@ impl Socket {
         self.attach()?;
         let op = Connect::new(self.as_raw_fd(), addr.clone());
         let (res, _op) = submit(op).await;
+        res?;
+        #[cfg(target_os = "windows")]
+        _op.update_context()?;
+        self.set_peer_addr(addr);
+        Ok(())
+    }
+
+    /// Connect and send the data with the handshake, and fall back to connect
+    /// and then send if TCP Fast Open is not available.
+    #[cfg(feature = "runtime")]
+    pub async fn connect_with_data<T: IoBuf>(
+        &self,
+        addr: &SockAddr,
+        buffer: T,
+    ) -> BufResult<usize, T> {
+        let ((), buffer) = buf_try!(self.attach(), buffer);
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let buffer = {
+            let op = ConnectWithData::new(self.as_raw_fd(), addr.clone(), buffer);
+            let (res, buffer) = submit(op).await.into_inner().into_inner();
+            match res {
+                // The SYN carries no data with io-uring. Sending waits for the
+                // connection.
+                Err(e) if e.raw_os_error() == Some(libc::EINPROGRESS) => {
+                    return self.send(buffer).await;
+                }
+                // The client side is disabled by the sysctl.
+                Err(e) if e.raw_os_error() == Some(libc::EOPNOTSUPP) => buffer,
+                res => {
+                    if res.is_ok() {
+                        self.set_peer_addr(addr);
+                    }
+                    return (res, buffer);
+                }
+            }
+        };
         #[cfg(target_os = "windows")]
         {
-            res?;
-            _op.update_context()?;
-            Ok(())
+            // It's fine to fail on old systems, and the data is sent after the
+            // handshake.
+            self.set_tcp_fastopen(1).ok();
+            let op = ConnectWithData::new(self.as_raw_fd(), addr.clone(), buffer);
+            let (res, op) = submit(op).await;
+            let res = res.and_then(|n| op.update_context().map(|_| n));
+            if res.is_ok() {
+                self.set_peer_addr(addr);
+            }
+            (res, op.into_inner().into_inner())
         }
         #[cfg(unix)]
         {
-            res.map(|_| ())
+            let ((), buffer) = buf_try!(self.connect_async(addr).await, buffer);
+            self.send(buffer).await
         }
     }
 
@@ -114,31 +1000,163 @@ impl Socket {
         accept_sock.set_nonblocking(true)?;
         let accept_sock = Self::from_socket2(accept_sock);
         let addr = op.into_addr();
+        accept_sock.set_peer_addr(&addr);
+        let addr = self.mapped_addrs.get().apply(addr);
         Ok((accept_sock, addr))
     }
 
     #[cfg(all(feature = "runtime", target_os = "windows"))]
     pub async fn accept(&self) -> io::Result<(Self, SockAddr)> {
         self.attach()?;
-        let local_addr = self.local_addr()?;
-        let accept_sock = Self::new(
-            local_addr.domain(),
-            self.socket.r#type()?,
-            self.socket.protocol()?,
-        )?;
+        let accept_sock = self.new_accept_socket()?;
         let op = Accept::new(self.as_raw_fd(), accept_sock.as_raw_fd() as _);
         let (res, op) = submit(op).await;
         res?;
+        // The context is required by `getpeername` and `shutdown` on the
+        // accepted socket. The remote address is in the buffer of `AcceptEx`,
+        // which saves the `getpeername` anyway.
         op.update_context()?;
         let addr = op.into_addr()?;
+        accept_sock.set_peer_addr(&addr);
+        let addr = self.mapped_addrs.get().apply(addr);
         Ok((accept_sock, addr))
     }
 
+    // `AcceptEx` takes an unbound socket of the same kind as the listener.
+    #[cfg(all(feature = "runtime", target_os = "windows"))]
+    fn new_accept_socket(&self) -> io::Result<Self> {
+        let local_addr = self.cached_local_addr()?;
+        Self::new(
+            local_addr.domain(),
+            self.socket.r#type()?,
+            self.socket.protocol()?,
+        )
+    }
+
+    // Receive the first bytes of an accepted connection. The recv is cancelled
+    // if no data arrives within `timeout`, and nothing is received then.
+    #[cfg(all(feature = "time", unix))]
+    pub async fn recv_initial<T: IoBufMut>(
+        &self,
+        buffer: T,
+        timeout: Duration,
+    ) -> BufResult<usize, T> {
+        use futures_util::future::{select, Either};
+
+        let parent = RUNTIME.with(|runtime| runtime.current_scope());
+        let scope = Rc::new(InterruptScope::new(parent));
+        let recv = std::pin::pin!(scoped(scope.clone(), self.recv(buffer)));
+        let timer = std::pin::pin!(crate::time::sleep(timeout));
+        let recv = match select(recv, timer).await {
+            Either::Left((res, _)) => return res,
+            Either::Right(((), recv)) => recv,
+        };
+        RUNTIME.with(|runtime| runtime.interrupt_scope(&scope));
+        match recv.await {
+            (Err(e), buffer) if is_cancelled(&e) => (Ok(0), buffer),
+            res => res,
+        }
+    }
+
+    // Accept a connection with `AcceptEx`, which receives the first bytes with
+    // it. A connected client sending nothing is checked with
+    // `SO_CONNECT_TIME` after each `timeout`, and the accept is cancelled.
+    #[cfg(all(feature = "time", target_os = "windows"))]
+    pub async fn accept_with_data<T: IoBufMut>(
+        &self,
+        buffer: T,
+        timeout: Duration,
+    ) -> BufResult<(Self, SockAddr, usize), T> {
+        use futures_util::future::{select, Either};
+
+        use crate::op::AcceptWithData;
+
+        let ((), buffer) = buf_try!(self.attach(), buffer);
+        let (accept_sock, buffer) = buf_try!(self.new_accept_socket(), buffer);
+        let op = AcceptWithData::new(self.as_raw_fd(), accept_sock.as_raw_fd() as _, buffer);
+        let parent = RUNTIME.with(|runtime| runtime.current_scope());
+        let scope = Rc::new(InterruptScope::new(parent));
+        let mut accept = std::pin::pin!(scoped(scope.clone(), submit(op)));
+        let mut timed_out = false;
+        let (res, op) = loop {
+            let timer = std::pin::pin!(crate::time::sleep(timeout));
+            match select(accept.as_mut(), timer).await {
+                Either::Left((res, _)) => break res,
+                Either::Right(((), _)) => {
+                    if accept_sock.connect_time().is_ok_and(|secs| secs.is_some()) {
+                        timed_out = true;
+                        RUNTIME.with(|runtime| runtime.interrupt_scope(&scope));
+                        break accept.await;
+                    }
+                }
+            }
+        };
+        let received = match res {
+            Ok(received) => received,
+            Err(e) if timed_out && is_cancelled(&e) => {
+                return (
+                    Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "the connection sent nothing within the timeout",
+                    )),
+                    op.into_buffer(0),
+                );
+            }
+            Err(e) => return (Err(e), op.into_buffer(0)),
+        };
+        let res = op.update_context().and_then(|()| op.remote_addr());
+        let buffer = op.into_buffer(received);
+        let (addr, buffer) = buf_try!(res, buffer);
+        accept_sock.set_peer_addr(&addr);
+        let addr = self.mapped_addrs.get().apply(addr);
+        (Ok((accept_sock, addr, received)), buffer)
+    }
+
+    // The seconds since the socket is connected, or `None` if not connected.
+    #[cfg(all(feature = "time", target_os = "windows"))]
+    fn connect_time(&self) -> io::Result<Option<u32>> {
+        use std::os::windows::io::AsRawSocket;
+
+        use windows_sys::Win32::Networking::WinSock::{getsockopt, SOL_SOCKET, SO_CONNECT_TIME};
+
+        let mut secs = 0u32;
+        let mut len = std::mem::size_of::<u32>() as i32;
+        crate::syscall!(
+            SOCKET,
+            getsockopt(
+                self.socket.as_raw_socket() as _,
+                SOL_SOCKET,
+                SO_CONNECT_TIME,
+                std::ptr::addr_of_mut!(secs).cast(),
+                &mut len,
+            )
+        )?;
+        Ok((secs != u32::MAX).then_some(secs))
+    }
+
+    #[cfg(feature = "runtime")]
+    pub fn set_recv_poll_first(&self, poll_first: bool) {
+        self.recv_poll_first.set(poll_first);
+    }
+
+    #[cfg(feature = "runtime")]
+    pub fn recv_poll_first(&self) -> bool {
+        self.recv_poll_first.get()
+    }
+
+    // Submit an operation for the data of the socket, which waits for the
+    // next tick if the socket has used up its turns in this tick.
+    #[cfg(feature = "runtime")]
+    async fn submit_fair<T: OpCode + 'static>(&self, op: T) -> BufResult<usize, T> {
+        fd_budget(self.as_raw_fd()).await;
+        submit(op).await
+    }
+
     #[cfg(feature = "runtime")]
     pub async fn recv<T: IoBufMut>(&self, buffer: T) -> BufResult<usize, T> {
         let ((), buffer) = buf_try!(self.attach(), buffer);
-        let op = Recv::new(self.as_raw_fd(), buffer);
-        submit(op).await.into_inner().map_advanced().into_inner()
+        let op = Recv::new(self.as_raw_fd(), buffer).poll_first(self.recv_poll_first.get());
+        self.submit_fair(op).await.into_inner().map_advanced().into_inner()
     }
 
     #[cfg(feature = "runtime")]
@@ -161,18 +1179,93 @@ impl Socket {
         (res, buffer)
     }
 
+    #[cfg(feature = "runtime")]
+    pub async fn recv_grow(
+        &self,
+        mut buffer: Vec<u8>,
+        min_spare: usize,
+    ) -> BufResult<usize, Vec<u8>> {
+        // Reserve for all queued bytes, so that a message is not split. The
+        // spare part should not be empty, or zero is returned like EOF.
+        let queued = self.recv_queued().unwrap_or_default();
+        buffer.reserve(queued.max(min_spare).max(1));
+        let len = buffer.len();
+        self.recv(buffer.slice(len..)).await.into_inner()
+    }
+
+    #[cfg(feature = "runtime")]
+    pub async fn recv_to_end(&self, mut buffer: Vec<u8>) -> BufResult<usize, Vec<u8>> {
+        const MIN_SPARE: usize = 4096;
+
+        let mut total_read = 0;
+        let mut read;
+        loop {
+            (read, buffer) = buf_try!(self.recv_grow(buffer, MIN_SPARE).await);
+            if read == 0 {
+                break;
+            }
+            total_read += read;
+        }
+        (Ok(total_read), buffer)
+    }
+
     #[cfg(feature = "runtime")]
     pub async fn recv_vectored<T: IoBufMut>(&self, buffer: Vec<T>) -> BufResult<usize, Vec<T>> {
         let ((), buffer) = buf_try!(self.attach(), buffer);
-        let op = RecvVectored::new(self.as_raw_fd(), buffer);
-        submit(op).await.into_inner().map_advanced().into_inner()
+        let op =
+            RecvVectored::new(self.as_raw_fd(), buffer).poll_first(self.recv_poll_first.get());
+        self.submit_fair(op).await.into_inner().map_advanced().into_inner()
+    }
+
+    #[cfg(feature = "runtime")]
+    pub fn set_zerocopy(&self, zerocopy: bool) -> io::Result<()> {
+        #[cfg(any(
+            target_os = "android",
+            all(target_os = "linux", not(feature = "io-uring"))
+        ))]
+        match crate::net::enable_zerocopy(self.as_raw_fd(), zerocopy) {
+            Ok(()) => self.zerocopy.set(zerocopy),
+            // Not supported by the kernel or the socket.
+            Err(e) if matches!(e.raw_os_error(), Some(libc::ENOPROTOOPT | libc::EOPNOTSUPP)) => {
+                self.zerocopy.set(false)
+            }
+            Err(e) => return Err(e),
+        }
+        #[cfg(not(any(
+            target_os = "android",
+            all(target_os = "linux", not(feature = "io-uring"))
+        )))]
+        let _ = zerocopy;
+        Ok(())
+    }
+
+    #[cfg(feature = "runtime")]
+    pub fn zerocopy(&self) -> bool {
+        self.zerocopy.get()
+    }
+
+    #[cfg(feature = "runtime")]
+    pub fn zerocopy_stats(&self) -> ZeroCopyStats {
+        self.zerocopy_tracker.stats()
     }
 
     #[cfg(feature = "runtime")]
     pub async fn send<T: IoBuf>(&self, buffer: T) -> BufResult<usize, T> {
         let ((), buffer) = buf_try!(self.attach(), buffer);
+        #[cfg(any(
+            target_os = "android",
+            all(target_os = "linux", not(feature = "io-uring"))
+        ))]
+        if self.zerocopy.get() && buffer.buf_len() >= crate::net::ZEROCOPY_THRESHOLD {
+            let op = crate::op::SendZeroCopy::new(
+                self.as_raw_fd(),
+                buffer,
+                self.zerocopy_tracker.clone(),
+            );
+            return self.submit_fair(op).await.into_inner();
+        }
         let op = Send::new(self.as_raw_fd(), buffer);
-        submit(op).await.into_inner().into_inner()
+        self.submit_fair(op).await.into_inner().into_inner()
     }
 
     #[cfg(feature = "runtime")]
@@ -192,19 +1285,137 @@ impl Socket {
     pub async fn send_vectored<T: IoBuf>(&self, buffer: Vec<T>) -> BufResult<usize, Vec<T>> {
         let ((), buffer) = buf_try!(self.attach(), buffer);
         let op = SendVectored::new(self.as_raw_fd(), buffer);
-        submit(op).await.into_inner().into_inner()
+        self.submit_fair(op).await.into_inner().into_inner()
     }
 
     #[cfg(feature = "runtime")]
     pub async fn recv_from<T: IoBufMut>(&self, buffer: T) -> BufResult<(usize, SockAddr), T> {
         let ((), buffer) = buf_try!(self.attach(), buffer);
         let op = RecvFrom::new(self.as_raw_fd(), buffer);
-        submit(op)
+        let (res, buffer) = self
+            .submit_fair(op)
             .await
             .into_inner()
             .map_addr()
             .map_advanced()
+            .into_inner();
+        (self.unmap_addr(res), buffer)
+    }
+
+    #[cfg(feature = "runtime")]
+    pub async fn recv_from_timestamped<T: IoBufMut>(
+        &self,
+        buffer: T,
+    ) -> BufResult<(usize, SockAddr, RecvTimestamp), T> {
+        let ((), buffer) = buf_try!(self.attach(), buffer);
+        #[cfg(unix)]
+        let op = RecvFrom::with_control(
+            self.as_raw_fd(),
+            buffer,
+            vec![0; crate::net::timestamp::control_len()],
+        );
+        #[cfg(windows)]
+        let op = RecvFrom::new(self.as_raw_fd(), buffer);
+        let (res, op) = self.submit_fair(op).await;
+        #[cfg(unix)]
+        let timestamp = match &res {
+            Ok(_) => crate::net::timestamp::parse_received(op.msg()),
+            Err(_) => Ok(None),
+        };
+        #[cfg(windows)]
+        let timestamp = io::Result::Ok(None);
+        let now = RecvTimestamp::now();
+        let (res, buffer) = (res, op)
             .into_inner()
+            .map_addr()
+            .map_advanced()
+            .into_inner();
+        let addrs = self.mapped_addrs.get();
+        let res = res.and_then(|(n, addr)| {
+            let timestamp = timestamp?.unwrap_or(now);
+            Ok((n, addrs.apply(addr), timestamp))
+        });
+        (res, buffer)
+    }
+
+    #[cfg(feature = "runtime")]
+    pub async fn recv_meta<T: IoBufMut>(&self, buffer: T) -> BufResult<RecvMeta, T> {
+        let ((), buffer) = buf_try!(self.attach(), buffer);
+        #[cfg(unix)]
+        let op = RecvFrom::with_control(
+            self.as_raw_fd(),
+            buffer,
+            vec![0; crate::net::recv_meta::control_len()],
+        );
+        #[cfg(windows)]
+        let op = RecvFrom::new(self.as_raw_fd(), buffer);
+        #[allow(unused_mut)]
+        let (res, mut op) = self.submit_fair(op).await;
+        // The header points to the heap of the control buffer, which is kept
+        // alive after the op is consumed.
+        #[cfg(unix)]
+        let (msg, _control) = (*op.msg(), std::mem::take(&mut op.control));
+        let (res, buffer) = (res, op)
+            .into_inner()
+            .map_addr()
+            .map_advanced()
+            .into_inner();
+        let addrs = self.mapped_addrs.get();
+        let res = res.map(|(n, addr)| {
+            let mut meta = RecvMeta::new(n, addrs.apply(addr));
+            #[cfg(unix)]
+            {
+                crate::net::recv_meta::parse(&msg, &mut meta);
+                meta.dst_addr = meta.dst_addr.map(|ip| addrs.apply_ip(ip));
+            }
+            meta
+        });
+        (res, buffer)
+    }
+
+    #[cfg(all(feature = "runtime", any(target_os = "linux", target_os = "android")))]
+    pub fn set_recv_errors(&self, enable: bool, domain: Domain) -> io::Result<()> {
+        let value = enable as libc::c_int;
+        if domain == Domain::IPV6 {
+            self.setsockopt(libc::IPPROTO_IPV6, libc::IPV6_RECVERR, value)?;
+        }
+        match self.setsockopt(libc::IPPROTO_IP, libc::IP_RECVERR, value) {
+            Err(_) if domain == Domain::IPV6 => Ok(()),
+            res => res,
+        }
+    }
+
+    // Wait for an error in the error queue. The datagram causing it is not
+    // received, but its destination is.
+    #[cfg(all(feature = "runtime", any(target_os = "linux", target_os = "android")))]
+    pub async fn recv_error(&self) -> io::Result<crate::net::ErrorEvent> {
+        self.attach()?;
+        loop {
+            let op = RecvFrom::with_control(
+                self.as_raw_fd(),
+                Vec::<u8>::new(),
+                vec![0; crate::net::mtu::control_len()],
+            )
+            .with_flags(libc::MSG_ERRQUEUE);
+            let (res, mut op) = submit(op).await;
+            res?;
+            let (msg, _control) = (*op.msg(), std::mem::take(&mut op.control));
+            let (_, addr, len) = op.into_inner();
+            let addrs = self.mapped_addrs.get();
+            let destination = (len > 0)
+                .then(|| addrs.apply(unsafe { SockAddr::new(addr, len) }));
+            // Other notifications in the queue, e.g., the timestamps, are
+            // skipped.
+            if let Some(mut event) = crate::net::mtu::parse(&msg, destination) {
+                event.offender = event.offender.map(|addr| addrs.apply(addr));
+                return Ok(event);
+            }
+        }
+    }
+
+    #[cfg(feature = "runtime")]
+    fn unmap_addr(&self, res: io::Result<(usize, SockAddr)>) -> io::Result<(usize, SockAddr)> {
+        res.map(|(n, addr)| (n, self.mapped_addrs.get().apply(addr)))
     }
 
     #[cfg(feature = "runtime")]
@@ -214,19 +1425,21 @@ impl Socket {
     ) -> BufResult<(usize, SockAddr), Vec<T>> {
         let ((), buffer) = buf_try!(self.attach(), buffer);
         let op = RecvFromVectored::new(self.as_raw_fd(), buffer);
-        submit(op)
+        let (res, buffer) = self
+            .submit_fair(op)
             .await
             .into_inner()
             .map_addr()
             .map_advanced()
-            .into_inner()
+            .into_inner();
+        (self.unmap_addr(res), buffer)
     }
 
     #[cfg(feature = "runtime")]
     pub async fn send_to<T: IoBuf>(&self, buffer: T, addr: &SockAddr) -> BufResult<usize, T> {
         let ((), buffer) = buf_try!(self.attach(), buffer);
         let op = SendTo::new(self.as_raw_fd(), buffer, addr.clone());
-        submit(op).await.into_inner().into_inner()
+        self.submit_fair(op).await.into_inner().into_inner()
     }
 
     #[cfg(feature = "runtime")]
@@ -237,8 +1450,26 @@ impl Socket {
     ) -> BufResult<usize, Vec<T>> {
         let ((), buffer) = buf_try!(self.attach(), buffer);
         let op = SendToVectored::new(self.as_raw_fd(), buffer, addr.clone());
-        submit(op).await.into_inner().into_inner()
+        self.submit_fair(op).await.into_inner().into_inner()
     }
 }
 
-impl_raw_fd!(Socket, socket, attacher);
+impl_raw_fd!(
+    Socket,
+    socket,
+    attacher,
+    recv_poll_first,
+    zerocopy,
+    zerocopy_tracker,
+    mapped_addrs,
+    local_addr_cache,
+    peer_addr_cache
+);
+
+// The ops cancelled in the driver fail with `ETIMEDOUT` on Unix, and the ones
+// submitted after the interruption fail with the cancellation error.
+#[cfg(feature = "time")]
+fn is_cancelled(e: &io::Error) -> bool {
+    (cfg!(unix) && e.kind() == io::ErrorKind::TimedOut)
+        || e.raw_os_error() == crate::op::cancelled_error().raw_os_error()
+}
@@ -1,15 +1,32 @@
-use std::{io, net::Shutdown};
+#[cfg(feature = "runtime")]
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+    time::Instant,
+};
+use std::{
+    io,
+    net::{Shutdown, SocketAddrV6},
+    time::Duration,
+};
 
-use socket2::{Protocol, SockAddr, Type};
+use socket2::{Protocol, SockAddr, TcpKeepalive, Type};
 
 #[cfg(feature = "runtime")]
 use crate::{
     buf::{IoBuf, IoBufMut},
+    buf_try,
+    net::{map_unresponsive, DirectTcpListener, MappedAddrPolicy, ZeroCopyStats},
+    task::{
+        op::{scoped, InterruptScope},
+        RUNTIME,
+    },
     BufResult,
 };
 use crate::{
     impl_raw_fd,
-    net::{Socket, ToSockAddrs},
+    net::{Socket, SocketOpts, TcpConnectOpts, TcpInfo, ToSockAddrs},
 };
 
 /// A TCP socket server, listening for connections.
@@ -45,6 +62,10 @@ use crate::{
 /// ```
 pub struct TcpListener {
     inner: Socket,
+    #[cfg(feature = "runtime")]
+    stats: RefCell<AcceptRecorder>,
+    #[cfg(feature = "runtime")]
+    pause: RefCell<PauseState>,
 }
 
 impl TcpListener {
@@ -55,20 +76,190 @@ impl TcpListener {
     ///
     /// Binding with a port number of 0 will request that the OS assigns a port
     /// to this listener.
+    ///
+    /// The length of the accept queue is the maximum allowed by the system,
+    /// e.g., `net.core.somaxconn` on Linux.
     pub fn bind(addr: impl ToSockAddrs) -> io::Result<Self> {
+        Self::bind_with(addr, &SocketOpts::new())
+    }
+
+    /// Creates a new `TcpListener` like [`TcpListener::bind`], with the
+    /// options applied before the socket is bound.
+    ///
+    /// ```
+    /// use std::net::Ipv4Addr;
+    ///
+    /// use compio::net::{SocketOpts, TcpListener};
+    ///
+    /// let opts = SocketOpts::new().freebind(true);
+    /// # if cfg!(target_os = "linux") {
+    /// // The address is not configured on any interface.
+    /// let listener = TcpListener::bind_with((Ipv4Addr::new(192, 0, 2, 1), 0), &opts).unwrap();
+    /// # }
+    /// ```
+    pub fn bind_with(addr: impl ToSockAddrs, opts: &SocketOpts) -> io::Result<Self> {
         super::each_addr(addr, |addr| {
-            let socket = Socket::bind(&addr, Type::STREAM, Some(Protocol::TCP))?;
-            socket.listen(128)?;
-            Ok(Self { inner: socket })
+            let socket = Socket::bind_with(&addr, Type::STREAM, Some(Protocol::TCP), opts)?;
+            socket.listen(MAX_BACKLOG)?;
+            Ok(Self {
+                inner: socket,
+                #[cfg(feature = "runtime")]
+                stats: RefCell::default(),
+                #[cfg(feature = "runtime")]
+                pause: RefCell::default(),
+            })
+        })
+    }
+
+    /// Creates a new [`DirectTcpListener`] bound to the specified address.
+    ///
+    /// With io-uring, if the kernel supports the socket, bind and listen ops
+    /// (Linux 6.11), the socket is created, bound and listened by the ring as
+    /// a direct descriptor, and the connections are accepted as direct
+    /// descriptors by [`DirectTcpListener::accept_direct`]. Otherwise it falls
+    /// back to [`TcpListener::bind`].
+    ///
+    /// A direct descriptor has no `RawFd`, so the socket options could not be
+    /// set, and the local address could not be queried, e.g., the port chosen
+    /// by the kernel for port 0 is unknown.
+    #[cfg(feature = "runtime")]
+    pub async fn bind_direct(addr: impl ToSockAddrs) -> io::Result<DirectTcpListener> {
+        super::each_addr_async(addr, |addr| async move {
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            if let Some(listener) = DirectTcpListener::bind_direct(&addr, MAX_BACKLOG).await? {
+                return Ok(listener);
+            }
+            Self::bind(addr).map(DirectTcpListener::fallback)
+        })
+        .await
+    }
+
+    /// Creates a new IPv6-only `TcpListener` bound to the address, i.e.,
+    /// with [`SocketOpts::only_v6`], so that the IPv4 clients are refused on
+    /// all the platforms, and the accepted addresses are always IPv6.
+    pub fn bind_v6_only(addr: SocketAddrV6) -> io::Result<Self> {
+        Self::bind_with(addr, &SocketOpts::new().only_v6(true))
+    }
+
+    /// Creates `n` listeners bound to the same address, one for each worker
+    /// thread, and the kernel balances the incoming connections among them.
+    ///
+    /// The listeners are not attached to any driver. Send each of them to
+    /// its worker, and create a [`TcpListener`] with
+    /// [`TcpListener::from_std`] there. If the port is 0, all of them share the
+    /// port assigned to the first one.
+    ///
+    /// ## Platform specific
+    /// * Linux and most unix: `SO_REUSEPORT` and `SO_REUSEADDR`. Linux hashes
+    ///   the 4-tuple of the connection to pick a listener, unless a program is
+    ///   attached by [`TcpListener::set_reuseport_cbpf`].
+    /// * FreeBSD: `SO_REUSEPORT_LB`.
+    /// * Windows, Solaris and illumos: the port can't be shared with balance,
+    ///   so a single listener is returned. Accept on one worker, and dispatch
+    ///   the streams by [`TcpStream::into_std`] and [`TcpStream::from_std`].
+    ///
+    /// ```no_run
+    /// use compio::net::TcpListener;
+    ///
+    /// let listeners = TcpListener::bind_reuseport_sharded("127.0.0.1:8080", 4).unwrap();
+    /// let workers = listeners.into_iter().map(|listener| {
+    ///     std::thread::spawn(move || {
+    ///         compio::task::block_on(async {
+    ///             let listener = TcpListener::from_std(listener).unwrap();
+    ///             loop {
+    ///                 let (stream, _) = listener.accept().await.unwrap();
+    ///                 compio::task::spawn(async move {
+    ///                     stream.send_all("hello").await.0.unwrap();
+    ///                 })
+    ///                 .detach();
+    ///             }
+    ///         })
+    ///     })
+    /// });
+    /// for worker in workers.collect::<Vec<_>>() {
+    ///     worker.join().unwrap();
+    /// }
+    /// ```
+    pub fn bind_reuseport_sharded(
+        addr: impl ToSockAddrs,
+        n: usize,
+    ) -> io::Result<Vec<std::net::TcpListener>> {
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "at least one listener is required",
+            ));
+        }
+        super::each_addr(addr, |addr| {
+            cfg_if::cfg_if! {
+                if #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))] {
+                    let mut addr = addr;
+                    let mut listeners = Vec::with_capacity(n);
+                    for _ in 0..n {
+                        let socket =
+                            Socket::bind_reuse_port(&addr, Type::STREAM, Some(Protocol::TCP))?;
+                        socket.listen(MAX_BACKLOG)?;
+                        // Share the port assigned to the first one.
+                        addr = socket.local_addr()?;
+                        listeners.push(socket.into_socket2().into());
+                    }
+                    Ok(listeners)
+                } else {
+                    let socket = Socket::bind(&addr, Type::STREAM, Some(Protocol::TCP))?;
+                    socket.listen(MAX_BACKLOG)?;
+                    Ok(vec![socket.into_socket2().into()])
+                }
+            }
         })
     }
 
+    /// Creates a new `TcpListener` from a listening [`std::net::TcpListener`],
+    /// e.g., one inherited from another process, without binding again.
+    ///
+    /// The socket is switched to the mode required by the driver, e.g.,
+    /// non-blocking with polling.
+    pub fn from_std(listener: std::net::TcpListener) -> io::Result<Self> {
+        Ok(Self {
+            inner: Socket::from_foreign(listener.into())?,
+            #[cfg(feature = "runtime")]
+            stats: RefCell::default(),
+            #[cfg(feature = "runtime")]
+            pause: RefCell::default(),
+        })
+    }
+
+    /// Converts into a [`std::net::TcpListener`] without closing the socket,
+    /// e.g., to pass it to another process. The socket may be left in the
+    /// non-blocking mode.
+    ///
+    /// No accept is in flight, because they borrow the listener.
+    ///
+    /// ## Platform specific
+    /// * io-uring/polling: the socket is not registered to the driver when no
+    ///   op is in flight, so nothing is left in the driver.
+    /// * IOCP: a socket can't be detached from the completion port it is
+    ///   attached to. The returned socket is still attached, and its
+    ///   overlapped IO should only be performed with the driver of this
+    ///   thread. To hand it over to another process, duplicate it with
+    ///   `WSADuplicateSocketW`, and create a new socket from the protocol info
+    ///   in that process, which could be attached to its own driver.
+    pub fn into_std(self) -> std::net::TcpListener {
+        self.inner.into_socket2().into()
+    }
+
     /// Creates a new independently owned handle to the underlying socket.
     ///
-    /// It does not clear the attach state.
+    /// The new handle is attached to the driver of current thread lazily, on
+    /// its first IO. On Windows, both handles refer to the same socket object,
+    /// which is attached to the driver of current thread at once. The
+    /// statistics are not shared.
     pub fn try_clone(&self) -> io::Result<Self> {
         Ok(Self {
             inner: self.inner.try_clone()?,
+            #[cfg(feature = "runtime")]
+            stats: RefCell::default(),
+            #[cfg(feature = "runtime")]
+            pause: RefCell::default(),
         })
     }
 
@@ -77,17 +268,249 @@ impl TcpListener {
     /// This function will yield once a new TCP connection is established. When
     /// established, the corresponding [`TcpStream`] and the remote peer's
     /// address will be returned.
+    ///
+    /// It waits while the listener is paused by [`TcpListener::pause`].
     #[cfg(feature = "runtime")]
     pub async fn accept(&self) -> io::Result<(TcpStream, SockAddr)> {
-        let (socket, addr) = self.inner.accept().await?;
-        let stream = TcpStream { inner: socket };
-        Ok((stream, addr))
+        loop {
+            std::future::poll_fn(|cx| self.pause.borrow_mut().poll_resumed(cx)).await;
+            // The scope of this accept, nested in the current one, which is
+            // interrupted when paused.
+            let parent = RUNTIME.with(|runtime| runtime.current_scope());
+            let scope = Rc::new(InterruptScope::new(parent.clone()));
+            let _in_flight = InFlight::new(&self.pause, scope.clone());
+            self.stats.borrow_mut().start();
+            let res = scoped(scope.clone(), self.inner.accept()).await;
+            let paused = scope.is_interrupted() && !parent.is_some_and(|p| p.is_interrupted());
+            if paused && res.is_err() {
+                continue;
+            }
+            self.stats.borrow_mut().record(&res);
+            let (socket, addr) = res?;
+            let stream = TcpStream { inner: socket };
+            return Ok((stream, addr));
+        }
+    }
+
+    /// Accepts a new incoming connection as a direct descriptor, i.e., a slot
+    /// of the file table of the ring allocated by the kernel, which never
+    /// exists in the file descriptor table of the process, for the servers
+    /// with very many connections. If the table is full, it waits until a
+    /// direct socket is closed, see [`ProactorBuilder::direct_table_size`].
+    /// If the kernel doesn't support it (Linux 5.19), the connection is
+    /// accepted as a normal stream.
+    ///
+    /// It waits while the listener is paused by [`TcpListener::pause`], but
+    /// the statistics of the listener don't apply.
+    ///
+    /// [`ProactorBuilder::direct_table_size`]: crate::driver::ProactorBuilder::direct_table_size
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub async fn accept_direct(&self) -> io::Result<(super::DirectTcpStream, SockAddr)> {
+        std::future::poll_fn(|cx| self.pause.borrow_mut().poll_resumed(cx)).await;
+        super::DirectTcpStream::accept_from(self).await
+    }
+
+    /// Accepts a new incoming connection, and receives the first bytes of it
+    /// into the uninitialized part of `buffer`, e.g., to route it by the PROXY
+    /// protocol header or the TLS SNI. It returns the stream, the remote
+    /// address and the length of the received data.
+    ///
+    /// If the client sends nothing within `timeout`, nothing is received, and
+    /// the length is zero, as if the client closes the connection at once.
+    ///
+    /// ## Platform specific
+    /// * Windows: `AcceptEx` receives the data, and completes with it, which
+    ///   saves a recv. It waits for the connections without the timeout, and
+    ///   a connected client is checked after each `timeout`. `AcceptEx` can't
+    ///   complete without data, so a client sending nothing for about
+    ///   `timeout` to twice of it is aborted, and an error of
+    ///   [`io::ErrorKind::TimedOut`] is returned. The pause and the statistics
+    ///   of the listener don't apply.
+    /// * Others: it accepts with [`TcpListener::accept`], and receives at once.
+    ///   Enable [`TcpListener::set_defer_accept`] on Linux, so that the
+    ///   accept usually completes with the data ready.
+    #[cfg(feature = "time")]
+    pub async fn accept_with_buffer<T: IoBufMut>(
+        &self,
+        buffer: T,
+        timeout: Duration,
+    ) -> BufResult<(TcpStream, SockAddr, usize), T> {
+        #[cfg(unix)]
+        {
+            let ((stream, addr), buffer) = buf_try!(self.accept().await, buffer);
+            let (received, buffer) = buf_try!(stream.inner.recv_initial(buffer, timeout).await);
+            (Ok((stream, addr, received)), buffer)
+        }
+        #[cfg(windows)]
+        {
+            let ((socket, addr, received), buffer) =
+                buf_try!(self.inner.accept_with_data(buffer, timeout).await);
+            (Ok((TcpStream { inner: socket }, addr, received)), buffer)
+        }
+    }
+
+    /// Pause accepting. The accepts in flight are cancelled in the driver, and
+    /// it waits until they are given back. The accepts then wait until
+    /// [`TcpListener::resume`], and the connections stay in the accept queue
+    /// of the socket, e.g., to be accepted by another process after the
+    /// handover.
+    ///
+    /// The accepts in flight may still complete with a connection, if it is
+    /// accepted before the cancellation.
+    #[cfg(feature = "runtime")]
+    pub async fn pause(&self) {
+        let scopes = {
+            let mut pause = self.pause.borrow_mut();
+            pause.paused = true;
+            pause.scopes.clone()
+        };
+        for scope in scopes {
+            RUNTIME.with(|runtime| runtime.interrupt_scope(&scope));
+        }
+        std::future::poll_fn(|cx| self.pause.borrow_mut().poll_drained(cx)).await
+    }
+
+    /// Resume accepting after [`TcpListener::pause`]. The waiting accepts are
+    /// woken.
+    #[cfg(feature = "runtime")]
+    pub fn resume(&self) {
+        let wakers = {
+            let mut pause = self.pause.borrow_mut();
+            pause.paused = false;
+            std::mem::take(&mut pause.wakers)
+        };
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Whether the listener is paused by [`TcpListener::pause`].
+    #[cfg(feature = "runtime")]
+    pub fn is_paused(&self) -> bool {
+        self.pause.borrow().paused
+    }
+
+    /// The statistics of [`TcpListener::accept`] on this listener.
+    #[cfg(feature = "runtime")]
+    pub fn accept_stats(&self) -> AcceptStats {
+        self.stats.borrow().stats()
+    }
+
+    /// The count of the established connections waiting to be accepted, i.e.,
+    /// the length of the accept queue.
+    ///
+    /// ## Platform specific
+    /// * Linux: `tcpi_unacked` of `TCP_INFO` on the listening socket.
+    /// * Others: `None`, because there's no way to query it.
+    pub fn pending_connections(&self) -> io::Result<Option<usize>> {
+        #[cfg(target_os = "linux")]
+        {
+            use crate::{driver::AsRawFd, syscall};
+
+            let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+            let mut len = std::mem::size_of::<libc::tcp_info>() as libc::socklen_t;
+            syscall!(getsockopt(
+                self.as_raw_fd(),
+                libc::IPPROTO_TCP,
+                libc::TCP_INFO,
+                std::ptr::addr_of_mut!(info).cast(),
+                &mut len
+            ))?;
+            Ok(Some(info.tcpi_unacked as usize))
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            Ok(None)
+        }
+    }
+
+    /// Set how the IPv4-mapped addresses of the clients are given back by
+    /// [`TcpListener::accept`] and the others on a dual-stack listener.
+    /// Default to [`MappedAddrPolicy::Keep`].
+    #[cfg(feature = "runtime")]
+    pub fn set_mapped_addr_policy(&self, policy: MappedAddrPolicy) {
+        self.inner.set_mapped_addr_policy(policy)
+    }
+
+    /// Get the policy of the IPv4-mapped addresses, see
+    /// [`TcpListener::set_mapped_addr_policy`].
+    #[cfg(feature = "runtime")]
+    pub fn mapped_addr_policy(&self) -> MappedAddrPolicy {
+        self.inner.mapped_addr_policy()
+    }
+
+    /// Enables TCP Fast Open on this listener, with the maximum length of the
+    /// queue of the connections not completing the handshake. Zero disables
+    /// it.
+    ///
+    /// The data sent with the SYN, e.g., by [`TcpStream::connect_with_data`],
+    /// is received before the handshake completes.
+    ///
+    /// ## Platform specific
+    /// * Linux: `TCP_FASTOPEN`. The server side should be enabled by
+    ///   `net.ipv4.tcp_fastopen`.
+    /// * Windows: `TCP_FASTOPEN`, and the length is ignored.
+    /// * Others: an error with [`io::ErrorKind::Unsupported`].
+    pub fn set_fastopen(&self, queue_len: u32) -> io::Result<()> {
+        self.inner.set_tcp_fastopen(queue_len)
+    }
+
+    /// Sets `TCP_DEFER_ACCEPT` on this listener, so that the accept completes
+    /// only when the data arrives, or `timeout` elapses. `None` disables it.
+    ///
+    /// ## Platform specific
+    /// * Linux: the timeout is rounded to seconds, and then to the
+    ///   retransmission timeouts.
+    /// * Others: an error with [`io::ErrorKind::Unsupported`].
+    pub fn set_defer_accept(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_tcp_defer_accept(timeout)
+    }
+
+    /// Attaches a classic BPF program to the `SO_REUSEPORT` group of this
+    /// listener, which picks the listener of each connection. The program
+    /// returns the index of the listener in the order they were bound, e.g.,
+    /// the order returned by [`TcpListener::bind_reuseport_sharded`]. If the
+    /// index is out of range, the connection falls back to the hash.
+    ///
+    /// It could be attached to any listener of the group, and it replaces the
+    /// previous one.
+    ///
+    /// Pick the listener of the current CPU, so that a connection is accepted
+    /// by the worker on the CPU which handles its packets:
+    ///
+    /// ```no_run
+    /// use compio::net::TcpListener;
+    ///
+    /// let listeners = TcpListener::bind_reuseport_sharded("127.0.0.1:8080", 4).unwrap();
+    /// let listener = TcpListener::from_std(listeners[0].try_clone().unwrap()).unwrap();
+    /// let program = [
+    ///     // A = the current CPU
+    ///     libc::sock_filter {
+    ///         code: (libc::BPF_LD | libc::BPF_W | libc::BPF_ABS) as _,
+    ///         jt: 0,
+    ///         jf: 0,
+    ///         k: (libc::SKF_AD_OFF + libc::SKF_AD_CPU) as _,
+    ///     },
+    ///     // return A
+    ///     libc::sock_filter {
+    ///         code: (libc::BPF_RET | libc::BPF_A) as _,
+    ///         jt: 0,
+    ///         jf: 0,
+    ///         k: 0,
+    ///     },
+    /// ];
+    /// listener.set_reuseport_cbpf(&program).unwrap();
+    /// ```
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_reuseport_cbpf(&self, program: &[libc::sock_filter]) -> io::Result<()> {
+        self.inner.set_reuseport_cbpf(program)
     }
 
     /// Returns the local address that this listener is bound to.
     ///
     /// This can be useful, for example, when binding to port 0 to
     /// figure out which port was actually bound.
+    /// The address is retrieved once, and cached.
     ///
     /// # Examples
     ///
@@ -109,11 +532,194 @@ impl TcpListener {
     /// );
     /// ```
     pub fn local_addr(&self) -> io::Result<SockAddr> {
-        self.inner.local_addr()
+        self.inner.cached_local_addr()
     }
 }
 
-impl_raw_fd!(TcpListener, inner);
+impl_raw_fd!(TcpListener, inner, stats, pause);
+
+// The kernel clamps the backlog to `somaxconn`.
+#[cfg(unix)]
+const MAX_BACKLOG: i32 = -1;
+
+// `SOMAXCONN` of WinSock 2, which lets the provider choose the maximum.
+#[cfg(target_os = "windows")]
+const MAX_BACKLOG: i32 = 0x7fffffff;
+
+#[cfg(feature = "runtime")]
+#[derive(Default)]
+struct PauseState {
+    paused: bool,
+    // The scopes of the accepts in flight.
+    scopes: Vec<Rc<InterruptScope>>,
+    // The accepts waiting for resuming.
+    wakers: Vec<Waker>,
+    // The pauses waiting for the accepts in flight.
+    drain_wakers: Vec<Waker>,
+}
+
+#[cfg(feature = "runtime")]
+impl PauseState {
+    fn poll_resumed(&mut self, cx: &mut Context) -> Poll<()> {
+        if self.paused {
+            push_waker(&mut self.wakers, cx.waker());
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+
+    fn poll_drained(&mut self, cx: &mut Context) -> Poll<()> {
+        if self.scopes.is_empty() {
+            Poll::Ready(())
+        } else {
+            push_waker(&mut self.drain_wakers, cx.waker());
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(feature = "runtime")]
+fn push_waker(wakers: &mut Vec<Waker>, waker: &Waker) {
+    if !wakers.iter().any(|w| w.will_wake(waker)) {
+        wakers.push(waker.clone());
+    }
+}
+
+// An accept in flight, removed when it returns, or is dropped.
+#[cfg(feature = "runtime")]
+struct InFlight<'a> {
+    state: &'a RefCell<PauseState>,
+    scope: Rc<InterruptScope>,
+}
+
+#[cfg(feature = "runtime")]
+impl<'a> InFlight<'a> {
+    fn new(state: &'a RefCell<PauseState>, scope: Rc<InterruptScope>) -> Self {
+        state.borrow_mut().scopes.push(scope.clone());
+        Self { state, scope }
+    }
+}
+
+#[cfg(feature = "runtime")]
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        let wakers = {
+            let mut state = self.state.borrow_mut();
+            state.scopes.retain(|scope| !Rc::ptr_eq(scope, &self.scope));
+            if state.scopes.is_empty() {
+                std::mem::take(&mut state.drain_wakers)
+            } else {
+                vec![]
+            }
+        };
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// Statistics of [`TcpListener::accept`].
+#[cfg(feature = "runtime")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AcceptStats {
+    /// The count of the accepted connections.
+    pub accepts: u64,
+    /// The count of the connections aborted or reset before being accepted.
+    pub connection_errors: u64,
+    /// The count of the failures because of exhausted resources, e.g., fds.
+    pub resource_errors: u64,
+    /// The count of other failures.
+    pub other_errors: u64,
+    /// The total time from the driver completing the accept, i.e., a
+    /// connection being ready, to the accept returning.
+    pub accept_latency: Duration,
+    /// The maximum of the time above.
+    pub max_accept_latency: Duration,
+    /// The time since the first accept.
+    pub elapsed: Duration,
+}
+
+#[cfg(feature = "runtime")]
+impl AcceptStats {
+    /// The average accepted connections per second.
+    pub fn accepts_per_sec(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            0.0
+        } else {
+            self.accepts as f64 / self.elapsed.as_secs_f64()
+        }
+    }
+}
+
+#[cfg(feature = "runtime")]
+#[derive(Debug, Default)]
+struct AcceptRecorder {
+    started_at: Option<Instant>,
+    stats: AcceptStats,
+}
+
+#[cfg(feature = "runtime")]
+impl AcceptRecorder {
+    fn start(&mut self) {
+        self.started_at.get_or_insert_with(Instant::now);
+    }
+
+    fn record<T>(&mut self, res: &io::Result<T>) {
+        match res {
+            Ok(_) => {
+                self.stats.accepts += 1;
+                let polled_at = crate::task::RUNTIME.with(|runtime| runtime.polled_at());
+                if let Some(polled_at) = polled_at {
+                    let latency = polled_at.elapsed();
+                    self.stats.accept_latency += latency;
+                    self.stats.max_accept_latency = self.stats.max_accept_latency.max(latency);
+                }
+            }
+            Err(e) if is_connection_error(e) => self.stats.connection_errors += 1,
+            Err(e) if is_resource_error(e) => self.stats.resource_errors += 1,
+            Err(_) => self.stats.other_errors += 1,
+        }
+    }
+
+    fn stats(&self) -> AcceptStats {
+        AcceptStats {
+            elapsed: self
+                .started_at
+                .map(|started_at| started_at.elapsed())
+                .unwrap_or_default(),
+            ..self.stats
+        }
+    }
+}
+
+// The connection is aborted before being accepted.
+#[cfg(feature = "runtime")]
+pub(crate) fn is_connection_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::Interrupted
+    )
+}
+
+// The process runs out of resources.
+#[cfg(all(feature = "runtime", unix))]
+pub(crate) fn is_resource_error(e: &io::Error) -> bool {
+    matches!(
+        e.raw_os_error(),
+        Some(libc::EMFILE | libc::ENFILE | libc::ENOBUFS | libc::ENOMEM)
+    )
+}
+
+#[cfg(all(feature = "runtime", windows))]
+pub(crate) fn is_resource_error(e: &io::Error) -> bool {
+    use windows_sys::Win32::Networking::WinSock::{WSAEMFILE, WSAENOBUFS};
+
+    matches!(e.raw_os_error(), Some(WSAEMFILE | WSAENOBUFS))
+}
 
 /// A TCP stream between a local and a remote socket.
 ///
@@ -144,47 +750,170 @@ impl TcpStream {
     /// Opens a TCP connection to a remote host.
     #[cfg(feature = "runtime")]
     pub async fn connect(addr: impl ToSockAddrs) -> io::Result<Self> {
-        use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+        Self::connect_with(addr, &SocketOpts::new()).await
+    }
 
+    /// Opens a TCP connection to a remote host like [`TcpStream::connect`],
+    /// with the options applied before the socket is connected. The options
+    /// are either [`TcpConnectOpts`] or [`SocketOpts`].
+    #[cfg(feature = "runtime")]
+    pub async fn connect_with(
+        addr: impl ToSockAddrs,
+        opts: impl Into<TcpConnectOpts>,
+    ) -> io::Result<Self> {
+        let opts = &opts.into();
         super::each_addr_async(addr, |addr| async move {
-            let socket = if cfg!(target_os = "windows") {
-                let bind_addr = if addr.is_ipv4() {
-                    SockAddr::from(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))
+            let socket = Self::connect_socket(&addr, opts)?;
+            let connect = socket.connect_async(&addr);
+            #[cfg(feature = "time")]
+            if let Some(timeout) = opts.connect_timeout() {
+                crate::time::timeout(timeout, connect)
+                    .await
+                    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "connect timed out"))??;
+                return Ok(Self { inner: socket });
+            }
+            connect.await?;
+            Ok(Self { inner: socket })
+        })
+        .await
+    }
+
+    /// Opens a TCP connection to a remote host, and sends the data with the
+    /// handshake by TCP Fast Open. Returns the stream and the length of the
+    /// data sent, which may be less than the buffer.
+    ///
+    /// The stream could be used at once. If TCP Fast Open is not available,
+    /// it connects and then sends the data.
+    ///
+    /// ## Platform specific
+    /// * Linux: `sendto` with `MSG_FASTOPEN`. The client side should be enabled
+    ///   by `net.ipv4.tcp_fastopen`.
+    /// * Windows: `ConnectEx` with the send buffer, and `TCP_FASTOPEN`.
+    /// * Others: connect and then send.
+    ///
+    /// ```
+    /// use std::net::Ipv4Addr;
+    ///
+    /// use compio::net::{TcpListener, TcpStream};
+    ///
+    /// compio::task::block_on(async {
+    ///     let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    ///     listener.set_fastopen(16).ok();
+    ///     let addr = listener.local_addr().unwrap();
+    ///
+    ///     let (connected, accepted) = futures_util::join!(
+    ///         TcpStream::connect_with_data(&addr, "hello"),
+    ///         listener.accept()
+    ///     );
+    ///     let (res, _) = connected;
+    ///     let (_client, sent) = res.unwrap();
+    ///     assert_eq!(sent, 5);
+    ///     let (server, _) = accepted.unwrap();
+    ///     let (_, buf) = server.recv_exact(Vec::with_capacity(5)).await;
+    ///     assert_eq!(buf, b"hello");
+    /// })
+    /// ```
+    #[cfg(feature = "runtime")]
+    pub async fn connect_with_data<T: IoBuf>(
+        addr: impl ToSockAddrs,
+        buffer: T,
+    ) -> BufResult<(Self, usize), T> {
+        super::each_addr_async_buf(addr, buffer, |addr, buffer| async move {
+            let (socket, buffer) =
+                buf_try!(Self::connect_socket(&addr, &TcpConnectOpts::new()), buffer);
+            let (sent, buffer) = buf_try!(socket.connect_with_data(&addr, buffer).await);
+            (Ok((Self { inner: socket }, sent)), buffer)
+        })
+        .await
+    }
+
+    // The socket should be bound before `ConnectEx` on Windows.
+    #[cfg(feature = "runtime")]
+    fn connect_socket(addr: &SockAddr, opts: &TcpConnectOpts) -> io::Result<Socket> {
+        use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+
+        let local = match opts.local() {
+            Some(local) => Some(local),
+            None if cfg!(target_os = "windows") => {
+                if addr.is_ipv4() {
+                    Some(SockAddr::from(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)))
                 } else if addr.is_ipv6() {
-                    SockAddr::from(SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, 0, 0, 0))
+                    Some(SockAddr::from(SocketAddrV6::new(
+                        Ipv6Addr::UNSPECIFIED,
+                        0,
+                        0,
+                        0,
+                    )))
                 } else {
                     return Err(io::Error::new(
                         io::ErrorKind::AddrNotAvailable,
                         "Unsupported address domain.",
                     ));
-                };
-                Socket::bind(&bind_addr, Type::STREAM, Some(Protocol::TCP))?
-            } else {
-                Socket::new(addr.domain(), Type::STREAM, Some(Protocol::TCP))?
-            };
-            socket.connect_async(&addr).await?;
-            Ok(Self { inner: socket })
-        })
-        .await
+                }
+            }
+            None => None,
+        };
+        let socket = Socket::new(addr.domain(), Type::STREAM, Some(Protocol::TCP))?;
+        opts.apply(&socket, addr.domain())?;
+        if let Some(local) = local {
+            socket.bind_local(&local)?;
+        }
+        Ok(socket)
+    }
+
+    #[cfg(feature = "runtime")]
+    pub(crate) fn attach(&self) -> io::Result<()> {
+        self.inner.attach()
     }
 
     /// Creates a new independently owned handle to the underlying socket.
     ///
-    /// It does not clear the attach state.
+    /// The new handle is attached to the driver of current thread lazily, on
+    /// its first IO. On Windows, both handles refer to the same socket object,
+    /// which is attached to the driver of current thread at once.
     pub fn try_clone(&self) -> io::Result<Self> {
         Ok(Self {
             inner: self.inner.try_clone()?,
         })
     }
 
+    /// Creates a new `TcpStream` from a connected [`std::net::TcpStream`],
+    /// e.g., one dispatched from another thread.
+    ///
+    /// The socket is switched to the mode required by the driver, e.g.,
+    /// non-blocking with polling.
+    pub fn from_std(stream: std::net::TcpStream) -> io::Result<Self> {
+        Ok(Self {
+            inner: Socket::from_foreign(stream.into())?,
+        })
+    }
+
+    /// Converts into a [`std::net::TcpStream`] without closing the socket,
+    /// e.g., to dispatch it to another thread. The socket may be left in the
+    /// non-blocking mode.
+    ///
+    /// ## Platform specific
+    /// * IOCP: a socket can't be detached from the completion port it is
+    ///   attached to. An accepted stream is attached lazily on its first IO,
+    ///   so it should be converted before any IO to be used by another
+    ///   driver.
+    pub fn into_std(self) -> std::net::TcpStream {
+        self.inner.into_socket2().into()
+    }
+
     /// Returns the socket address of the remote peer of this TCP connection.
+    ///
+    /// The address is known from the accept or the connect, and is only
+    /// retrieved with `getpeername` once for a stream created otherwise, e.g.,
+    /// by [`TcpStream::from_std`].
     pub fn peer_addr(&self) -> io::Result<SockAddr> {
-        self.inner.peer_addr()
+        self.inner.cached_peer_addr()
     }
 
     /// Returns the socket address of the local half of this TCP connection.
+    /// It is retrieved once, and cached.
     pub fn local_addr(&self) -> io::Result<SockAddr> {
-        self.inner.local_addr()
+        self.inner.cached_local_addr()
     }
 
     /// Shuts down the read, write, or both halves of this connection.
@@ -196,45 +925,370 @@ impl TcpStream {
         self.inner.shutdown(how)
     }
 
+    /// Gets the value of the `SO_LINGER` option on this socket.
+    ///
+    /// See [`TcpStream::set_linger`].
+    pub fn linger(&self) -> io::Result<Option<Duration>> {
+        self.inner.linger()
+    }
+
+    /// Sets the value of the `SO_LINGER` option on this socket.
+    ///
+    /// With `Some(Duration::ZERO)`, the connection is reset when the socket is
+    /// closed, and the pending data is discarded. With other durations,
+    /// closing the socket may block the thread until the pending data is sent.
+    /// Use [`TcpStream::close_graceful`] to close the connection without
+    /// blocking.
+    pub fn set_linger(&self, linger: Option<Duration>) -> io::Result<()> {
+        self.inner.set_linger(linger)
+    }
+
+    /// Enables the keepalive probes with the parameters, or disables them
+    /// with `None`, i.e., `SO_KEEPALIVE`, and `TCP_KEEPIDLE`, `TCP_KEEPINTVL`
+    /// and `TCP_KEEPCNT` if supported. The parameters not set are the system
+    /// defaults, which are hours on most platforms.
+    ///
+    /// When the probes are not answered, the connection is torn down, and the
+    /// pending or the next receive or send fails with [`PeerUnresponsive`].
+    /// The probes are only sent when the connection is idle, so a peer gone
+    /// with unacknowledged data is detected by the retransmission timeout
+    /// instead, see [`TcpStream::set_user_timeout`].
+    ///
+    /// ```
+    /// use std::{net::Ipv4Addr, time::Duration};
+    ///
+    /// use compio::net::{TcpListener, TcpStream};
+    /// use socket2::TcpKeepalive;
+    ///
+    /// compio::task::block_on(async {
+    ///     let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    ///     let addr = listener.local_addr().unwrap();
+    ///     let (client, _) = futures_util::join!(TcpStream::connect(&addr), listener.accept());
+    ///     let client = client.unwrap();
+    ///     let keepalive = TcpKeepalive::new().with_time(Duration::from_secs(30));
+    ///     client.set_keepalive(Some(&keepalive)).unwrap();
+    ///     assert!(client.keepalive().unwrap());
+    /// })
+    /// ```
+    ///
+    /// [`PeerUnresponsive`]: crate::net::PeerUnresponsive
+    pub fn set_keepalive(&self, keepalive: Option<&TcpKeepalive>) -> io::Result<()> {
+        self.inner.set_keepalive(keepalive)
+    }
+
+    /// Whether the keepalive probes are enabled, see
+    /// [`TcpStream::set_keepalive`].
+    pub fn keepalive(&self) -> io::Result<bool> {
+        self.inner.keepalive()
+    }
+
+    /// Sets how long the data sent could remain unacknowledged before the
+    /// connection is torn down, i.e., `TCP_USER_TIMEOUT`. `None` or zero means
+    /// the system default, which retransmits for about 15 minutes.
+    ///
+    /// Then the pending or the next receive or send fails with
+    /// [`PeerUnresponsive`]. With the keepalive probes enabled, it also bounds
+    /// the time the probes are not answered.
+    ///
+    /// ## Platform specific
+    /// * Linux and Android: `TCP_USER_TIMEOUT`, in milliseconds.
+    /// * Others: an error with [`io::ErrorKind::Unsupported`].
+    ///
+    /// [`PeerUnresponsive`]: crate::net::PeerUnresponsive
+    pub fn set_user_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_user_timeout(timeout)
+    }
+
+    /// The user timeout, see [`TcpStream::set_user_timeout`].
+    pub fn user_timeout(&self) -> io::Result<Option<Duration>> {
+        self.inner.user_timeout()
+    }
+
+    /// Returns a hint of the bytes the send buffer could accept without
+    /// blocking, i.e., `SO_SNDBUF` minus the bytes queued but not acknowledged
+    /// by the peer. It is zero or small when the peer stops reading.
+    ///
+    /// The sends are already bounded by the send buffer, and it is only for
+    /// the advanced users, e.g., to choose the data to send in priority.
+    ///
+    /// ## Platform specific
+    /// * Linux: `SO_SNDBUF` and `SIOCOUTQ`. A part of `SO_SNDBUF` is used by
+    ///   the kernel for bookkeeping, so the hint is larger than the bytes
+    ///   actually accepted.
+    /// * Others: an error with [`io::ErrorKind::Unsupported`].
+    pub fn send_buffer_space(&self) -> io::Result<usize> {
+        self.inner.send_buffer_space()
+    }
+
+    /// Returns the RTT, the congestion window and other state of the
+    /// congestion control, e.g., to adapt the behavior to the network
+    /// condition. See [`TcpInfo`] for the fields available on each platform.
+    ///
+    /// ## Platform specific
+    /// * Others than Linux, macOS, iOS and Windows: an error with
+    ///   [`io::ErrorKind::Unsupported`].
+    ///
+    /// ```
+    /// use std::net::Ipv4Addr;
+    ///
+    /// use compio::net::{TcpListener, TcpStream};
+    ///
+    /// compio::task::block_on(async {
+    ///     let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    ///     let addr = listener.local_addr().unwrap();
+    ///     let (client, _) = futures_util::join!(TcpStream::connect(&addr), listener.accept());
+    ///     if let Ok(info) = client.unwrap().tcp_info() {
+    ///         println!("rtt: {:?}, cwnd: {:?}", info.rtt, info.snd_cwnd);
+    ///     }
+    /// })
+    /// ```
+    pub fn tcp_info(&self) -> io::Result<TcpInfo> {
+        self.inner.tcp_info()
+    }
+
+    /// Choose the congestion control algorithm, e.g., `"bbr"` or `"cubic"`,
+    /// i.e., `TCP_CONGESTION`. The algorithms not allowed by
+    /// `net.ipv4.tcp_allowed_congestion_control` require `CAP_NET_ADMIN`.
+    ///
+    /// ## Platform specific
+    /// * Linux: an unknown algorithm fails with `ENOENT`, and the module of it
+    ///   may be loaded with the capability.
+    /// * Others: an error with [`io::ErrorKind::Unsupported`].
+    pub fn set_congestion(&self, name: &str) -> io::Result<()> {
+        self.inner.set_congestion(name)
+    }
+
+    /// The congestion control algorithm, see [`TcpStream::set_congestion`].
+    pub fn congestion(&self) -> io::Result<String> {
+        self.inner.congestion()
+    }
+
+    /// Closes the connection gracefully, before `deadline`.
+    ///
+    /// The write half is shut down first, and the peer receives EOF after all
+    /// pending data. Then the data from the peer is received and discarded
+    /// until the peer closes its write half. Closing a socket with unread data
+    /// resets the connection, and the peer may lose the data not received
+    /// yet.
+    ///
+    /// The socket is closed when this function returns, or the future is
+    /// dropped. If the peer doesn't close before `deadline`, an error with
+    /// [`io::ErrorKind::TimedOut`] is returned.
+    ///
+    /// ```
+    /// use std::{
+    ///     net::Ipv4Addr,
+    ///     time::{Duration, Instant},
+    /// };
+    ///
+    /// use compio::net::{TcpListener, TcpStream};
+    ///
+    /// compio::task::block_on(async {
+    ///     let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    ///     let addr = listener.local_addr().unwrap();
+    ///     let (client, (server, _)) =
+    ///         futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    ///
+    ///     server.send_all("response").await.0.unwrap();
+    ///     let deadline = Instant::now() + Duration::from_secs(1);
+    ///     let (closed, (received, buffer)) = futures_util::join!(
+    ///         server.close_graceful(deadline),
+    ///         async {
+    ///             let res = client.recv_exact(Vec::with_capacity(8)).await;
+    ///             drop(client);
+    ///             res
+    ///         }
+    ///     );
+    ///     closed.unwrap();
+    ///     received.unwrap();
+    ///     assert_eq!(buffer, b"response");
+    /// })
+    /// ```
+    #[cfg(feature = "time")]
+    pub async fn close_graceful(self, deadline: Instant) -> io::Result<()> {
+        const DRAIN_SIZE: usize = 512;
+
+        self.inner.shutdown(Shutdown::Write)?;
+        let mut buffer = Vec::with_capacity(DRAIN_SIZE);
+        loop {
+            if crate::time::now() >= deadline {
+                break;
+            }
+            // The op is canceled if the deadline is reached.
+            let Ok((res, drained)) = crate::time::timeout_at(deadline, self.recv(buffer)).await
+            else {
+                break;
+            };
+            match res {
+                Ok(0) => return Ok(()),
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+            buffer = drained;
+            buffer.clear();
+        }
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "the peer didn't close before the deadline",
+        ))
+    }
+
+    /// Wait for the data before trying to receive in the receive methods of
+    /// this handle. It saves the first attempt on a mostly idle connection, and
+    /// costs a round trip through the poll on a busy one. Default to `false`.
+    ///
+    /// ## Platform specific
+    /// * io-uring: `IORING_RECVSEND_POLL_FIRST`, which requires Linux 5.19, and
+    ///   is ignored by the older kernels.
+    /// * IOCP/polling: it is ignored.
+    #[cfg(feature = "runtime")]
+    pub fn set_recv_poll_first(&self, poll_first: bool) {
+        self.inner.set_recv_poll_first(poll_first)
+    }
+
+    /// Whether to wait for the data before trying to receive. See
+    /// [`TcpStream::set_recv_poll_first`].
+    #[cfg(feature = "runtime")]
+    pub fn recv_poll_first(&self) -> bool {
+        self.inner.recv_poll_first()
+    }
+
+    /// Send the buffers not smaller than [`ZEROCOPY_THRESHOLD`] with
+    /// `MSG_ZEROCOPY` in [`TcpStream::send`] and the methods based on it.
+    /// Default to `false`.
+    ///
+    /// The kernel pins the pages of the buffer instead of copying it, and a
+    /// send completes only after the kernel notifies that the buffer is
+    /// released, which may be later than the data being acknowledged by the
+    /// peer. The smaller buffers are sent as usual.
+    ///
+    /// ## Platform specific
+    /// * polling on Linux/Android: `SO_ZEROCOPY`, which requires Linux 4.14.
+    ///   It is ignored by the older kernels.
+    /// * Others: it is ignored. Use [`TcpStream::zerocopy`] to check.
+    ///
+    /// [`ZEROCOPY_THRESHOLD`]: crate::net::ZEROCOPY_THRESHOLD
+    #[cfg(feature = "runtime")]
+    pub fn set_zerocopy(&self, zerocopy: bool) -> io::Result<()> {
+        self.inner.set_zerocopy(zerocopy)
+    }
+
+    /// Whether the large buffers are sent with `MSG_ZEROCOPY`. See
+    /// [`TcpStream::set_zerocopy`].
+    #[cfg(feature = "runtime")]
+    pub fn zerocopy(&self) -> bool {
+        self.inner.zerocopy()
+    }
+
+    /// The statistics of the notifications of the sends with `MSG_ZEROCOPY`.
+    #[cfg(feature = "runtime")]
+    pub fn zerocopy_stats(&self) -> ZeroCopyStats {
+        self.inner.zerocopy_stats()
+    }
+
     /// Receives a packet of data from the socket into the buffer, returning the
     /// original buffer and quantity of data received.
     #[cfg(feature = "runtime")]
     pub async fn recv<T: IoBufMut>(&self, buffer: T) -> BufResult<usize, T> {
-        self.inner.recv(buffer).await
+        unresponsive(self.inner.recv(buffer).await)
     }
 
     /// Receives exact number of bytes from the socket.
     #[cfg(feature = "runtime")]
     pub async fn recv_exact<T: IoBufMut>(&self, buffer: T) -> BufResult<usize, T> {
-        self.inner.recv_exact(buffer).await
+        unresponsive(self.inner.recv_exact(buffer).await)
+    }
+
+    /// Receives some data into the spare capacity of the [`Vec`], after its
+    /// initialized part, returning the buffer and quantity of data received.
+    ///
+    /// At least `min_spare` bytes are reserved before receiving. If more bytes
+    /// are already queued in the kernel, the reserve is enlarged to receive
+    /// them all at once, so a message is not split. Calling it repeatedly in a
+    /// parser loop lets the buffer grow to fit the largest message, and then
+    /// stay stable. Zero means EOF.
+    ///
+    /// ```
+    /// use std::net::Ipv4Addr;
+    ///
+    /// use compio::net::{TcpListener, TcpStream};
+    ///
+    /// compio::task::block_on(async {
+    ///     let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    ///     let addr = listener.local_addr().unwrap();
+    ///     let (client, (server, _)) =
+    ///         futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    ///
+    ///     client.send_all("hello").await.0.unwrap();
+    ///     let (res, buffer) = server.recv_grow(b"say ".to_vec(), 64).await;
+    ///     assert!(res.unwrap() > 0);
+    ///     assert!(buffer.starts_with(b"say h"));
+    /// })
+    /// ```
+    #[cfg(feature = "runtime")]
+    pub async fn recv_grow(&self, buffer: Vec<u8>, min_spare: usize) -> BufResult<usize, Vec<u8>> {
+        unresponsive(self.inner.recv_grow(buffer, min_spare).await)
+    }
+
+    /// Receives all data until EOF, appending to the [`Vec`] and growing it
+    /// as needed. The data received before an error is kept in the buffer.
+    #[cfg(feature = "runtime")]
+    pub async fn recv_to_end(&self, buffer: Vec<u8>) -> BufResult<usize, Vec<u8>> {
+        unresponsive(self.inner.recv_to_end(buffer).await)
+    }
+
+    /// The number of bytes received by the kernel and not read yet, with
+    /// `FIONREAD`.
+    pub fn recv_queued(&self) -> io::Result<usize> {
+        self.inner.recv_queued()
+    }
+
+    /// Whether a read would complete at once without waiting, i.e., data, the
+    /// EOF or an error has arrived. Nothing is consumed. An idle connection
+    /// of a request-response protocol shouldn't be readable, otherwise the
+    /// peer has closed it or sent something unexpected, see
+    /// [`PoolBuilder::check_readiness`](crate::net::pool::PoolBuilder::check_readiness).
+    ///
+    /// ## Platform specific
+    /// * Unix: `poll` with the timeout 0.
+    /// * Windows: `WSAPoll` with the timeout 0.
+    pub fn is_readable(&self) -> io::Result<bool> {
+        self.inner.is_readable()
     }
 
     /// Receives a packet of data from the socket into the buffer, returning the
     /// original buffer and quantity of data received.
     #[cfg(feature = "runtime")]
     pub async fn recv_vectored<T: IoBufMut>(&self, buffer: Vec<T>) -> BufResult<usize, Vec<T>> {
-        self.inner.recv_vectored(buffer).await
+        unresponsive(self.inner.recv_vectored(buffer).await)
     }
 
     /// Sends some data to the socket from the buffer, returning the original
     /// buffer and quantity of data sent.
     #[cfg(feature = "runtime")]
     pub async fn send<T: IoBuf>(&self, buffer: T) -> BufResult<usize, T> {
-        self.inner.send(buffer).await
+        unresponsive(self.inner.send(buffer).await)
     }
 
     /// Sends all data to the socket.
     #[cfg(feature = "runtime")]
     pub async fn send_all<T: IoBuf>(&self, buffer: T) -> BufResult<usize, T> {
-        self.inner.send_all(buffer).await
+        unresponsive(self.inner.send_all(buffer).await)
     }
 
     /// Sends some data to the socket from the buffer, returning the original
     /// buffer and quantity of data sent.
     #[cfg(feature = "runtime")]
     pub async fn send_vectored<T: IoBuf>(&self, buffer: Vec<T>) -> BufResult<usize, Vec<T>> {
-        self.inner.send_vectored(buffer).await
+        unresponsive(self.inner.send_vectored(buffer).await)
     }
 }
 
+// The connection is established, so a timeout means the peer is gone.
+#[cfg(feature = "runtime")]
+fn unresponsive<T>((res, buffer): BufResult<usize, T>) -> BufResult<usize, T> {
+    (res.map_err(map_unresponsive), buffer)
+}
+
 impl_raw_fd!(TcpStream, inner);
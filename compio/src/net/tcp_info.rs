@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+/// The state of the congestion control of a TCP connection, see
+/// [`TcpStream::tcp_info`](crate::net::TcpStream::tcp_info). A field is
+/// `None` if the platform doesn't report it.
+///
+/// ## Platform specific
+/// * Linux: `TCP_INFO`. The delivery rate requires Linux 4.9.
+/// * macOS and iOS: `TCP_CONNECTION_INFO`. The times are at millisecond
+///   granularity, and there is no delivery rate.
+/// * Windows: `SIO_TCP_INFO`. There is no RTT variance, retransmission count
+///   or delivery rate.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TcpInfo {
+    /// The smoothed round-trip time.
+    pub rtt: Option<Duration>,
+    /// The variance of the round-trip time.
+    pub rtt_var: Option<Duration>,
+    /// The congestion window in bytes.
+    pub snd_cwnd: Option<u64>,
+    /// The count of the segments retransmitted in total.
+    pub retransmits: Option<u64>,
+    /// The recent delivery rate in bytes per second.
+    pub delivery_rate: Option<u64>,
+}
@@ -0,0 +1,177 @@
+use std::time::SystemTime;
+
+/// Where a [`RecvTimestamp`] is taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimestampKind {
+    /// Taken by the kernel when the packet arrives at the network stack.
+    Software,
+    /// Taken by the network device, and reported by the kernel.
+    Hardware,
+    /// Taken by the runtime when the receive operation completes. It is later
+    /// than the kernel timestamps, and includes the scheduling delay.
+    Userspace,
+}
+
+/// The receive timestamp of a packet, see
+/// [`UdpSocket::recv_from_timestamped`](crate::net::UdpSocket::recv_from_timestamped).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RecvTimestamp {
+    /// The time when the packet is received, in the system clock.
+    pub time: SystemTime,
+    /// Where the timestamp is taken.
+    pub kind: TimestampKind,
+}
+
+impl RecvTimestamp {
+    #[cfg(feature = "runtime")]
+    pub(crate) fn now() -> Self {
+        Self {
+            time: SystemTime::now(),
+            kind: TimestampKind::Userspace,
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[cfg_attr(not(feature = "runtime"), allow(dead_code, unused_imports))]
+mod sys {
+    use std::{io, time::SystemTime};
+
+    use super::{RecvTimestamp, TimestampKind};
+    use crate::{driver::RawFd, syscall};
+
+    // Not exported by libc on Linux yet. The values are the same on all the
+    // architectures using the generic socket options.
+    const SO_TIMESTAMPING: libc::c_int = 37;
+    const SCM_TIMESTAMPING: libc::c_int = SO_TIMESTAMPING;
+
+    pub fn enable(fd: RawFd, enable: bool) -> io::Result<()> {
+        let flags = if enable {
+            libc::SOF_TIMESTAMPING_RX_SOFTWARE
+                | libc::SOF_TIMESTAMPING_SOFTWARE
+                | libc::SOF_TIMESTAMPING_RX_HARDWARE
+                | libc::SOF_TIMESTAMPING_RAW_HARDWARE
+        } else {
+            0
+        };
+        syscall!(setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            SO_TIMESTAMPING,
+            std::ptr::addr_of!(flags).cast(),
+            std::mem::size_of_val(&flags) as _,
+        ))?;
+        Ok(())
+    }
+
+    /// The space of the `SCM_TIMESTAMPING` control message.
+    pub fn control_len() -> usize {
+        unsafe { libc::CMSG_SPACE(std::mem::size_of::<[libc::timespec; 3]>() as _) as usize }
+    }
+
+    /// Find the timestamp in the control messages. The hardware one is
+    /// preferred.
+    pub fn parse(msg: &libc::msghdr) -> Option<RecvTimestamp> {
+        let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(msg) };
+        while !cmsg.is_null() {
+            let (level, ty) = unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) };
+            if level == libc::SOL_SOCKET && ty == SCM_TIMESTAMPING {
+                // `struct scm_timestamping`: the software timestamp, a deprecated
+                // one, and the raw hardware timestamp.
+                let ts = unsafe {
+                    std::ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<[libc::timespec; 3]>())
+                };
+                if let Some(time) = to_system_time(&ts[2]) {
+                    return Some(RecvTimestamp {
+                        time,
+                        kind: TimestampKind::Hardware,
+                    });
+                }
+                if let Some(time) = to_system_time(&ts[0]) {
+                    return Some(RecvTimestamp {
+                        time,
+                        kind: TimestampKind::Software,
+                    });
+                }
+            }
+            cmsg = unsafe { libc::CMSG_NXTHDR(msg, cmsg) };
+        }
+        None
+    }
+
+    fn to_system_time(ts: &libc::timespec) -> Option<SystemTime> {
+        if ts.tv_sec == 0 && ts.tv_nsec == 0 {
+            None
+        } else {
+            Some(
+                SystemTime::UNIX_EPOCH
+                    + std::time::Duration::new(ts.tv_sec as _, ts.tv_nsec as _),
+            )
+        }
+    }
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+#[cfg_attr(not(feature = "runtime"), allow(dead_code, unused_imports))]
+mod sys {
+    use std::{io, time::SystemTime};
+
+    use super::{RecvTimestamp, TimestampKind};
+    use crate::{driver::RawFd, syscall};
+
+    pub fn enable(fd: RawFd, enable: bool) -> io::Result<()> {
+        let enable = enable as libc::c_int;
+        syscall!(setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_TIMESTAMP,
+            std::ptr::addr_of!(enable).cast(),
+            std::mem::size_of_val(&enable) as _,
+        ))?;
+        Ok(())
+    }
+
+    /// The space of the `SCM_TIMESTAMP` control message.
+    pub fn control_len() -> usize {
+        unsafe { libc::CMSG_SPACE(std::mem::size_of::<libc::timeval>() as _) as usize }
+    }
+
+    pub fn parse(msg: &libc::msghdr) -> Option<RecvTimestamp> {
+        let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(msg) };
+        while !cmsg.is_null() {
+            let (level, ty) = unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) };
+            if level == libc::SOL_SOCKET && ty == libc::SCM_TIMESTAMP {
+                let tv = unsafe {
+                    std::ptr::read_unaligned(libc::CMSG_DATA(cmsg).cast::<libc::timeval>())
+                };
+                return Some(RecvTimestamp {
+                    time: SystemTime::UNIX_EPOCH
+                        + std::time::Duration::new(tv.tv_sec as _, tv.tv_usec as u32 * 1000),
+                    kind: TimestampKind::Software,
+                });
+            }
+            cmsg = unsafe { libc::CMSG_NXTHDR(msg, cmsg) };
+        }
+        None
+    }
+}
+
+#[cfg(unix)]
+pub(crate) use sys::*;
+
+/// Find the timestamp in the control messages received into a buffer of
+/// [`control_len`]. It fails if they are truncated, where the timestamp may be
+/// lost, e.g., taken by the other control messages enabled on the socket.
+// `msg_controllen` is `socklen_t` on some platforms.
+#[cfg(unix)]
+#[cfg_attr(not(feature = "runtime"), allow(dead_code))]
+#[allow(clippy::unnecessary_cast)]
+pub(crate) fn parse_received(msg: &libc::msghdr) -> std::io::Result<Option<RecvTimestamp>> {
+    if msg.msg_flags & libc::MSG_CTRUNC != 0 || msg.msg_controllen as usize > control_len() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "the control messages with the timestamp are truncated",
+        ));
+    }
+    Ok(parse(msg))
+}
@@ -1,15 +1,21 @@
-use std::io;
+use std::{io, net::SocketAddrV6};
 
 use socket2::{Protocol, SockAddr, Type};
 
+#[cfg(all(feature = "runtime", any(target_os = "linux", target_os = "android")))]
+use futures_util::Stream;
+
+#[cfg(all(feature = "runtime", any(target_os = "linux", target_os = "android")))]
+use crate::net::ErrorEvent;
 #[cfg(feature = "runtime")]
 use crate::{
     buf::{IoBuf, IoBufMut},
+    net::{map_too_large, MappedAddrPolicy, RecvMeta, RecvTimestamp},
     BufResult,
 };
 use crate::{
     impl_raw_fd,
-    net::{Socket, ToSockAddrs},
+    net::{MtuDiscovery, Socket, SocketOpts, ToSockAddrs},
 };
 
 /// A UDP socket.
@@ -104,13 +110,40 @@ pub struct UdpSocket {
 impl UdpSocket {
     /// Creates a new UDP socket and attempt to bind it to the addr provided.
     pub fn bind(addr: impl ToSockAddrs) -> io::Result<Self> {
+        Self::bind_with(addr, &SocketOpts::new())
+    }
+
+    /// Creates a new UDP socket like [`UdpSocket::bind`], with the options
+    /// applied before the socket is bound.
+    pub fn bind_with(addr: impl ToSockAddrs, opts: &SocketOpts) -> io::Result<Self> {
         super::each_addr(addr, |addr| {
             Ok(Self {
-                inner: Socket::bind(&addr, Type::DGRAM, Some(Protocol::UDP))?,
+                inner: Socket::bind_with(&addr, Type::DGRAM, Some(Protocol::UDP), opts)?,
             })
         })
     }
 
+    /// Creates a new IPv6-only UDP socket bound to the address, i.e., with
+    /// [`SocketOpts::only_v6`], so that the source addresses are always IPv6.
+    pub fn bind_v6_only(addr: SocketAddrV6) -> io::Result<Self> {
+        Self::bind_with(addr, &SocketOpts::new().only_v6(true))
+    }
+
+    /// Set how the IPv4-mapped source addresses are given back by
+    /// [`UdpSocket::recv_from`] and the others on a dual-stack socket.
+    /// Default to [`MappedAddrPolicy::Keep`].
+    #[cfg(feature = "runtime")]
+    pub fn set_mapped_addr_policy(&self, policy: MappedAddrPolicy) {
+        self.inner.set_mapped_addr_policy(policy)
+    }
+
+    /// Get the policy of the IPv4-mapped addresses, see
+    /// [`UdpSocket::set_mapped_addr_policy`].
+    #[cfg(feature = "runtime")]
+    pub fn mapped_addr_policy(&self) -> MappedAddrPolicy {
+        self.inner.mapped_addr_policy()
+    }
+
     /// Connects this UDP socket to a remote address, allowing the `send` and
     /// `recv` to be used to send data and also applies filters to only
     /// receive data from the specified address.
@@ -122,9 +155,16 @@ impl UdpSocket {
         super::each_addr(addr, |addr| self.inner.connect(&addr))
     }
 
+    #[cfg(feature = "time")]
+    pub(crate) fn attach(&self) -> io::Result<()> {
+        self.inner.attach()
+    }
+
     /// Creates a new independently owned handle to the underlying socket.
     ///
-    /// It does not clear the attach state.
+    /// The new handle is attached to the driver of current thread lazily, on
+    /// its first IO. On Windows, both handles refer to the same socket object,
+    /// which is attached to the driver of current thread at once.
     pub fn try_clone(&self) -> io::Result<Self> {
         Ok(Self {
             inner: self.inner.try_clone()?,
@@ -178,6 +218,139 @@ impl UdpSocket {
         self.inner.local_addr()
     }
 
+    /// Binds the socket to the interface of the name, or unbinds it with
+    /// `None`, see [`SocketOpts::bind_device`].
+    pub fn bind_device(&self, name: Option<&str>) -> io::Result<()> {
+        let domain = self.local_addr()?.domain();
+        self.inner.bind_device(name, domain)
+    }
+
+    /// Binds the socket to the interface of the index, or unbinds it with 0,
+    /// see [`SocketOpts::bind_device`].
+    pub fn bind_device_by_index(&self, index: u32) -> io::Result<()> {
+        let domain = self.local_addr()?.domain();
+        self.inner.bind_device_by_index(index, domain)
+    }
+
+    /// Enables or disables the kernel receive timestamps, which are reported
+    /// by [`UdpSocket::recv_from_timestamped`]. It has overhead for each
+    /// packet, and is disabled by default.
+    ///
+    /// On Windows, it does nothing.
+    pub fn set_recv_timestamps(&self, enable: bool) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            use crate::driver::AsRawFd;
+
+            super::timestamp::enable(self.as_raw_fd(), enable)
+        }
+        #[cfg(windows)]
+        {
+            let _ = enable;
+            Ok(())
+        }
+    }
+
+    /// Enables or disables the control messages of the ECN codepoint, the TTL
+    /// and the destination address, which are reported by
+    /// [`UdpSocket::recv_meta`]. They are disabled by default.
+    ///
+    /// On a dual-stack socket, the IPv4 ones are enabled too if the platform
+    /// supports them.
+    ///
+    /// ## Platform specific
+    /// * Linux: `IP_RECVTOS`, `IP_RECVTTL` and `IP_PKTINFO`, or the `IPV6_`
+    ///   ones.
+    /// * macOS and FreeBSD: `IP_RECVTOS`, `IP_RECVTTL` and `IP_RECVDSTADDR`,
+    ///   or the `IPV6_` ones.
+    /// * Other platforms: it does nothing.
+    pub fn set_recv_meta(&self, enable: bool) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            use crate::driver::AsRawFd;
+
+            let v6 = self.local_addr()?.is_ipv6();
+            super::recv_meta::enable(self.as_raw_fd(), v6, enable)
+        }
+        #[cfg(windows)]
+        {
+            let _ = enable;
+            Ok(())
+        }
+    }
+
+    /// Enables or disables the generic receive offload, i.e., `UDP_GRO`. The
+    /// datagrams of the same flow may be coalesced into one receive, and
+    /// their count and size are reported by [`UdpSocket::recv_meta`], so the
+    /// other receive methods should not be used then.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_gro(&self, enable: bool) -> io::Result<()> {
+        use crate::driver::AsRawFd;
+
+        super::recv_meta::enable_gro(self.as_raw_fd(), enable)
+    }
+
+    /// Sets how the path MTU is discovered, and whether the datagrams sent
+    /// could be fragmented. See [`MtuDiscovery`] for the options on each
+    /// platform.
+    ///
+    /// On a dual-stack socket, the IPv4 option is set too if the platform
+    /// supports it.
+    ///
+    /// ```
+    /// use std::net::Ipv4Addr;
+    ///
+    /// use compio::net::{MtuDiscovery, UdpSocket};
+    ///
+    /// let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    /// socket.set_mtu_discovery(MtuDiscovery::Do).unwrap();
+    /// assert_eq!(socket.mtu_discovery().unwrap(), MtuDiscovery::Do);
+    /// ```
+    pub fn set_mtu_discovery(&self, discovery: MtuDiscovery) -> io::Result<()> {
+        let domain = self.local_addr()?.domain();
+        self.inner.set_mtu_discovery(discovery, domain)
+    }
+
+    /// Gets how the path MTU is discovered, see
+    /// [`UdpSocket::set_mtu_discovery`]. On the platforms with only the
+    /// "don't fragment" option, it is either [`MtuDiscovery::Do`] or
+    /// [`MtuDiscovery::Dont`].
+    pub fn mtu_discovery(&self) -> io::Result<MtuDiscovery> {
+        let domain = self.local_addr()?.domain();
+        self.inner.mtu_discovery(domain)
+    }
+
+    /// Gets the path MTU known by the system for the connected peer. It fails
+    /// if the socket is not connected.
+    ///
+    /// ## Platform specific
+    /// * Linux and Windows: `IP_MTU` or `IPV6_MTU`.
+    /// * Other platforms: it is not supported.
+    pub fn mtu(&self) -> io::Result<u32> {
+        let domain = self.local_addr()?.domain();
+        self.inner.mtu(domain)
+    }
+
+    /// Enables `IP_RECVERR`, or `IPV6_RECVERR`, and returns the stream of
+    /// the errors of the datagrams sent, from the error queue of the socket,
+    /// e.g., the ICMP "packet too big" messages with the path MTU.
+    ///
+    /// With `IP_RECVERR`, the errors of an unconnected socket are also
+    /// reported by its next send or receive, like a connected one. The stream
+    /// only waits for the errors, and the datagrams could still be received
+    /// from other tasks at the same time.
+    #[cfg(all(feature = "runtime", any(target_os = "linux", target_os = "android")))]
+    pub fn error_events(
+        &self,
+    ) -> io::Result<impl Stream<Item = io::Result<ErrorEvent>> + Unpin + '_> {
+        let domain = self.local_addr()?.domain();
+        self.inner.set_recv_errors(true, domain)?;
+        Ok(Box::pin(futures_util::stream::unfold(
+            self,
+            |socket| async move { Some((socket.inner.recv_error().await, socket)) },
+        )))
+    }
+
     /// Receives a packet of data from the socket into the buffer, returning the
     /// original buffer and quantity of data received.
     #[cfg(feature = "runtime")]
@@ -194,16 +367,26 @@ impl UdpSocket {
 
     /// Sends some data to the socket from the buffer, returning the original
     /// buffer and quantity of data sent.
+    ///
+    /// A datagram too large to send fails with [`DatagramTooLarge`], with the
+    /// path MTU attached.
+    ///
+    /// [`DatagramTooLarge`]: crate::net::DatagramTooLarge
     #[cfg(feature = "runtime")]
     pub async fn send<T: IoBuf>(&self, buffer: T) -> BufResult<usize, T> {
-        self.inner.send(buffer).await
+        self.too_large(self.inner.send(buffer).await)
     }
 
     /// Sends some data to the socket from the buffer, returning the original
     /// buffer and quantity of data sent.
+    ///
+    /// A datagram too large to send fails with [`DatagramTooLarge`], with the
+    /// path MTU attached.
+    ///
+    /// [`DatagramTooLarge`]: crate::net::DatagramTooLarge
     #[cfg(feature = "runtime")]
     pub async fn send_vectored<T: IoBuf>(&self, buffer: Vec<T>) -> BufResult<usize, Vec<T>> {
-        self.inner.send_vectored(buffer).await
+        self.too_large(self.inner.send_vectored(buffer).await)
     }
 
     /// Receives a single datagram message on the socket. On success, returns
@@ -213,6 +396,100 @@ impl UdpSocket {
         self.inner.recv_from(buffer).await
     }
 
+    /// Receives a single datagram message on the socket. On success, returns
+    /// the number of bytes received, the origin, and the time it is received.
+    ///
+    /// The kernel timestamps are reported only if enabled by
+    /// [`UdpSocket::set_recv_timestamps`]; otherwise, or if the kernel doesn't
+    /// attach one, the time when the operation completes is reported, with
+    /// [`TimestampKind::Userspace`]. On Unix, it fails with
+    /// [`io::ErrorKind::InvalidData`] if the control messages are truncated,
+    /// e.g., by the others enabled on the socket, where the kernel timestamp
+    /// may be lost.
+    ///
+    /// ## Platform specific
+    /// * Linux: `SO_TIMESTAMPING`, and the hardware timestamp is preferred if
+    ///   the device supports it and it is enabled by `SIOCSHWTSTAMP`.
+    /// * Other Unix platforms: `SO_TIMESTAMP`, in microseconds.
+    /// * Windows: the kernel timestamps are not supported yet, and the
+    ///   userspace ones are always reported.
+    ///
+    /// ```
+    /// use std::{
+    ///     net::Ipv4Addr,
+    ///     time::{Duration, SystemTime},
+    /// };
+    ///
+    /// use compio::net::UdpSocket;
+    ///
+    /// compio::task::block_on(async {
+    ///     let receiver = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    ///     receiver.set_recv_timestamps(true).unwrap();
+    ///     let sender = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    ///     sender
+    ///         .send_to("hello", receiver.local_addr().unwrap())
+    ///         .await
+    ///         .0
+    ///         .unwrap();
+    ///
+    ///     let (res, buffer) = receiver
+    ///         .recv_from_timestamped(Vec::with_capacity(5))
+    ///         .await;
+    ///     let (_, _, timestamp) = res.unwrap();
+    ///     assert_eq!(buffer, b"hello");
+    ///     assert!(timestamp.time <= SystemTime::now());
+    /// })
+    /// ```
+    ///
+    /// [`TimestampKind::Userspace`]: crate::net::TimestampKind::Userspace
+    #[cfg(feature = "runtime")]
+    pub async fn recv_from_timestamped<T: IoBufMut>(
+        &self,
+        buffer: T,
+    ) -> BufResult<(usize, SockAddr, RecvTimestamp), T> {
+        self.inner.recv_from_timestamped(buffer).await
+    }
+
+    /// Receives a single datagram message on the socket. On success, returns
+    /// the [`RecvMeta`] of it, with the flags and the control messages.
+    ///
+    /// The ECN codepoint, the TTL and the destination address are reported if
+    /// enabled by [`UdpSocket::set_recv_meta`], the timestamp by
+    /// [`UdpSocket::set_recv_timestamps`], and the GRO segments on Linux by
+    /// `UdpSocket::set_gro`.
+    ///
+    /// ## Platform specific
+    /// * Windows: only the length and the source address are reported yet.
+    ///
+    /// ```
+    /// use std::net::Ipv4Addr;
+    ///
+    /// use compio::net::UdpSocket;
+    ///
+    /// compio::task::block_on(async {
+    ///     let receiver = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    ///     receiver.set_recv_meta(true).unwrap();
+    ///     let sender = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    ///     sender
+    ///         .send_to("hello", receiver.local_addr().unwrap())
+    ///         .await
+    ///         .0
+    ///         .unwrap();
+    ///
+    ///     let (res, buffer) = receiver.recv_meta(Vec::with_capacity(16)).await;
+    ///     let meta = res.unwrap();
+    ///     assert_eq!(buffer, b"hello");
+    ///     assert_eq!(meta.len, 5);
+    ///     assert_eq!(meta.addr, sender.local_addr().unwrap());
+    ///     assert!(!meta.flags.truncated());
+    ///     println!("ECN {:?}, TTL {:?}", meta.ecn, meta.ttl);
+    /// })
+    /// ```
+    #[cfg(feature = "runtime")]
+    pub async fn recv_meta<T: IoBufMut>(&self, buffer: T) -> BufResult<RecvMeta, T> {
+        self.inner.recv_meta(buffer).await
+    }
+
     /// Receives a single datagram message on the socket. On success, returns
     /// the number of bytes received and the origin.
     #[cfg(feature = "runtime")]
@@ -225,30 +502,48 @@ impl UdpSocket {
 
     /// Sends data on the socket to the given address. On success, returns the
     /// number of bytes sent.
+    ///
+    /// A datagram too large to send fails with [`DatagramTooLarge`]. The path
+    /// MTU is attached only if the socket is connected.
+    ///
+    /// [`DatagramTooLarge`]: crate::net::DatagramTooLarge
     #[cfg(feature = "runtime")]
     pub async fn send_to<T: IoBuf>(
         &self,
         buffer: T,
         addr: impl ToSockAddrs,
     ) -> BufResult<usize, T> {
-        super::each_addr_async_buf(addr, buffer, |addr, buffer| async move {
+        let res = super::each_addr_async_buf(addr, buffer, |addr, buffer| async move {
             self.inner.send_to(buffer, &addr).await
         })
-        .await
+        .await;
+        self.too_large(res)
     }
 
     /// Sends data on the socket to the given address. On success, returns the
     /// number of bytes sent.
+    ///
+    /// A datagram too large to send fails with [`DatagramTooLarge`]. The path
+    /// MTU is attached only if the socket is connected.
+    ///
+    /// [`DatagramTooLarge`]: crate::net::DatagramTooLarge
     #[cfg(feature = "runtime")]
     pub async fn send_to_vectored<T: IoBuf>(
         &self,
         buffer: Vec<T>,
         addr: impl ToSockAddrs,
     ) -> BufResult<usize, Vec<T>> {
-        super::each_addr_async_buf(addr, buffer, |addr, buffer| async move {
+        let res = super::each_addr_async_buf(addr, buffer, |addr, buffer| async move {
             self.inner.send_to_vectored(buffer, &addr).await
         })
-        .await
+        .await;
+        self.too_large(res)
+    }
+
+    // Attach the path MTU to the error of a datagram too large.
+    #[cfg(feature = "runtime")]
+    fn too_large<T>(&self, (res, buffer): BufResult<usize, T>) -> BufResult<usize, T> {
+        (res.map_err(|e| map_too_large(e, || self.mtu().ok())), buffer)
     }
 }
 
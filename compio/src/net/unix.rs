@@ -65,7 +65,9 @@ impl UnixListener {
 
     /// Creates a new independently owned handle to the underlying socket.
     ///
-    /// It does not clear the attach state.
+    /// The new handle is attached to the driver of current thread lazily, on
+    /// its first IO. On Windows, both handles refer to the same socket object,
+    /// which is attached to the driver of current thread at once.
     pub fn try_clone(&self) -> io::Result<Self> {
         Ok(Self {
             inner: self.inner.try_clone()?,
@@ -135,9 +137,16 @@ impl UnixStream {
         })
     }
 
+    #[cfg(feature = "runtime")]
+    pub(crate) fn attach(&self) -> io::Result<()> {
+        self.inner.attach()
+    }
+
     /// Creates a new independently owned handle to the underlying socket.
     ///
-    /// It does not clear the attach state.
+    /// The new handle is attached to the driver of current thread lazily, on
+    /// its first IO. On Windows, both handles refer to the same socket object,
+    /// which is attached to the driver of current thread at once.
     pub fn try_clone(&self) -> io::Result<Self> {
         Ok(Self {
             inner: self.inner.try_clone()?,
@@ -163,6 +172,12 @@ impl UnixStream {
         self.inner.shutdown(how)
     }
 
+    /// The number of bytes received by the kernel and not read yet, with
+    /// `FIONREAD`.
+    pub fn recv_queued(&self) -> io::Result<usize> {
+        self.inner.recv_queued()
+    }
+
     /// Receives a packet of data from the socket into the buffer, returning the
     /// original buffer and quantity of data received.
     #[cfg(feature = "runtime")]
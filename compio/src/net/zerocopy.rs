@@ -0,0 +1,157 @@
+use std::{collections::HashSet, sync::Mutex};
+
+/// The minimal length of a buffer sent with `MSG_ZEROCOPY`. Pinning the pages
+/// and waiting for the notification cost more than copying a smaller buffer.
+pub const ZEROCOPY_THRESHOLD: usize = 10 * 1024;
+
+/// Statistics of the `MSG_ZEROCOPY` sends on a socket, see
+/// [`TcpStream::set_zerocopy`](crate::net::TcpStream::set_zerocopy).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ZeroCopyStats {
+    /// The count of the sends whose buffers are released by the kernel.
+    pub completed: u64,
+    /// The count of the sends above, which the kernel copied anyway, e.g.,
+    /// to a loopback peer, or through a device without scatter-gather.
+    pub copied: u64,
+}
+
+/// The completion notifications of the `MSG_ZEROCOPY` sends on a socket.
+///
+/// The kernel numbers the successful sends on a socket from zero, and reports
+/// the released ranges of them on the error queue. A notification may be read
+/// by any pending send of the socket, so they are recorded here for the one
+/// waiting for it.
+#[derive(Debug, Default)]
+pub struct ZeroCopyTracker {
+    inner: Mutex<TrackerInner>,
+}
+
+#[derive(Debug, Default)]
+struct TrackerInner {
+    next_id: u32,
+    completed: HashSet<u32>,
+    stats: ZeroCopyStats,
+}
+
+#[cfg_attr(
+    not(any(
+        target_os = "android",
+        all(target_os = "linux", not(feature = "io-uring"))
+    )),
+    allow(dead_code)
+)]
+impl ZeroCopyTracker {
+    /// Get the statistics of the notifications.
+    pub fn stats(&self) -> ZeroCopyStats {
+        self.inner.lock().unwrap().stats
+    }
+
+    /// Take the id of a successful send.
+    pub(crate) fn next_id(&self) -> u32 {
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_id;
+        inner.next_id = id.wrapping_add(1);
+        id
+    }
+
+    /// Record the sends from `lo` to `hi`, inclusive, as completed. The range
+    /// may wrap around.
+    pub(crate) fn complete(&self, lo: u32, hi: u32, copied: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        let mut id = lo;
+        loop {
+            inner.completed.insert(id);
+            inner.stats.completed += 1;
+            if copied {
+                inner.stats.copied += 1;
+            }
+            if id == hi {
+                break;
+            }
+            id = id.wrapping_add(1);
+        }
+    }
+
+    /// Check and forget whether the send is completed.
+    pub(crate) fn take(&self, id: u32) -> bool {
+        self.inner.lock().unwrap().completed.remove(&id)
+    }
+}
+
+// Only the polling driver reads the notifications.
+#[cfg(any(
+    target_os = "android",
+    all(target_os = "linux", not(feature = "io-uring"))
+))]
+mod sys {
+    use std::io;
+
+    use super::ZeroCopyTracker;
+    use crate::{driver::RawFd, syscall};
+
+    // Not exported by libc on Linux yet. The values are the same on all the
+    // architectures using the generic socket options.
+    const SO_ZEROCOPY: libc::c_int = 60;
+    const SO_EE_ORIGIN_ZEROCOPY: u8 = 5;
+    const SO_EE_CODE_ZEROCOPY_COPIED: u8 = 1;
+
+    pub fn enable_zerocopy(fd: RawFd, enable: bool) -> io::Result<()> {
+        let enable = enable as libc::c_int;
+        syscall!(setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            SO_ZEROCOPY,
+            std::ptr::addr_of!(enable).cast(),
+            std::mem::size_of_val(&enable) as _,
+        ))?;
+        Ok(())
+    }
+
+    /// Read all the notifications on the error queue of the socket into the
+    /// tracker. The other messages on the error queue are dropped.
+    pub fn drain_zerocopy_notifications(fd: RawFd, tracker: &ZeroCopyTracker) -> io::Result<()> {
+        // Aligned for `cmsghdr`.
+        let mut control = [0u64; 16];
+        loop {
+            let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+            msg.msg_control = control.as_mut_ptr().cast();
+            msg.msg_controllen = std::mem::size_of_val(&control) as _;
+            match syscall!(recvmsg(
+                fd,
+                &mut msg,
+                libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT
+            )) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+                Ok(_) => {}
+            }
+            let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+            while !cmsg.is_null() {
+                let (level, ty) = unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) };
+                if (level == libc::SOL_IP && ty == libc::IP_RECVERR)
+                    || (level == libc::SOL_IPV6 && ty == libc::IPV6_RECVERR)
+                {
+                    let err = unsafe {
+                        std::ptr::read_unaligned(
+                            libc::CMSG_DATA(cmsg).cast::<libc::sock_extended_err>(),
+                        )
+                    };
+                    if err.ee_errno == 0 && err.ee_origin == SO_EE_ORIGIN_ZEROCOPY {
+                        tracker.complete(
+                            err.ee_info,
+                            err.ee_data,
+                            err.ee_code & SO_EE_CODE_ZEROCOPY_COPIED != 0,
+                        );
+                    }
+                }
+                cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+            }
+        }
+    }
+}
+
+#[cfg(any(
+    target_os = "android",
+    all(target_os = "linux", not(feature = "io-uring"))
+))]
+pub(crate) use sys::*;
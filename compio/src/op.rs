@@ -3,14 +3,47 @@
 //! The operation itself doesn't perform anything.
 //! You need to pass them to [`crate::driver::Proactor`], and poll the driver.
 
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+};
+
 use socket2::SockAddr;
 
 #[cfg(target_os = "windows")]
-pub use crate::driver::op::ConnectNamedPipe;
-pub use crate::driver::op::{Accept, RecvFromImpl, RecvImpl, SendImpl, SendToImpl};
+pub use crate::driver::op::{ConnectNamedPipe, ReadDirectoryChanges, WaitHandle};
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub use crate::driver::op::{FutexWait, FutexWake};
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub use crate::driver::op::{MkdirAt, OpenAt, RenameAt, Statx, SymlinkAt, UnlinkAt};
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub use crate::driver::op::{
+    AcceptDirect, BindDirect, CloseDirect, ListenDirect, RecvDirect, SendDirect, ShutdownDirect,
+    SocketDirect,
+};
+#[cfg(unix)]
+pub use crate::driver::op::{PollOnce, WaitProcess};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use crate::driver::op::Splice;
+#[cfg(any(
+    target_os = "android",
+    all(target_os = "linux", not(feature = "io-uring"))
+))]
+pub use crate::driver::op::SendZeroCopy;
+#[cfg(target_os = "windows")]
+pub use crate::driver::op::AcceptWithData;
+pub use crate::driver::op::{
+    Accept, RecvFromImpl, RecvImpl, SendImpl, SendToImpl, WriteVectoredAt,
+};
 use crate::{
     buf::{AsIoSlicesMut, BufWrapper, IntoInner, IoBuf, IoBufMut, VectoredBufWrapper, WrapBuf},
-    driver::{sockaddr_storage, socklen_t, RawFd},
+    driver::{
+        pool::{spawn_blocking, RunningThread},
+        sockaddr_storage, socklen_t, MessageSender, RawFd,
+    },
     BufResult,
 };
 
@@ -62,19 +95,30 @@ impl<T> RecvResultExt for BufResult<usize, (T, sockaddr_storage, socklen_t)> {
 #[derive(Debug)]
 pub struct ReadAt<T: IoBufMut> {
     pub(crate) fd: RawFd,
-    pub(crate) offset: usize,
+    pub(crate) offset: u64,
     pub(crate) buffer: BufWrapper<T>,
+    #[cfg(unix)]
+    pub(crate) rw_flags: libc::c_int,
 }
 
 impl<T: IoBufMut> ReadAt<T> {
     /// Create [`ReadAt`].
-    pub fn new(fd: RawFd, offset: usize, buffer: T) -> Self {
+    pub fn new(fd: RawFd, offset: u64, buffer: T) -> Self {
         Self {
             fd,
             offset,
             buffer: BufWrapper::new(buffer),
+            #[cfg(unix)]
+            rw_flags: 0,
         }
     }
+
+    /// Set the `RWF_*` flags of the operation.
+    #[cfg(target_os = "linux")]
+    pub fn with_rw_flags(mut self, flags: libc::c_int) -> Self {
+        self.rw_flags = flags;
+        self
+    }
 }
 
 impl<T: IoBufMut> IntoInner for ReadAt<T> {
@@ -89,19 +133,30 @@ impl<T: IoBufMut> IntoInner for ReadAt<T> {
 #[derive(Debug)]
 pub struct WriteAt<T: IoBuf> {
     pub(crate) fd: RawFd,
-    pub(crate) offset: usize,
+    pub(crate) offset: u64,
     pub(crate) buffer: BufWrapper<T>,
+    #[cfg(unix)]
+    pub(crate) rw_flags: libc::c_int,
 }
 
 impl<T: IoBuf> WriteAt<T> {
     /// Create [`WriteAt`].
-    pub fn new(fd: RawFd, offset: usize, buffer: T) -> Self {
+    pub fn new(fd: RawFd, offset: u64, buffer: T) -> Self {
         Self {
             fd,
             offset,
             buffer: BufWrapper::new(buffer),
+            #[cfg(unix)]
+            rw_flags: 0,
         }
     }
+
+    /// Set the `RWF_*` flags of the operation.
+    #[cfg(target_os = "linux")]
+    pub fn with_rw_flags(mut self, flags: libc::c_int) -> Self {
+        self.rw_flags = flags;
+        self
+    }
 }
 
 impl<T: IoBuf> IntoInner for WriteAt<T> {
@@ -147,6 +202,439 @@ impl Connect {
     }
 }
 
+/// Connect to a remote address, and send the data with the handshake.
+///
+/// The result is the length of the data sent. The connection may be still
+/// establishing after the operation completes, and it is fine to issue IO
+/// on the socket.
+///
+/// ## Platform specific
+///
+/// * io-uring: `sendmsg` with `MSG_FASTOPEN`. If the SYN carries no data, it
+///   fails with `EINPROGRESS`, and the connection is establishing.
+/// * polling: `sendto` with `MSG_FASTOPEN`. If the data isn't carried by
+///   the SYN, it is sent with `send` when the connection is established.
+/// * IOCP: `ConnectEx` with the send buffer. `fd` should be bound.
+///
+/// On Linux, it fails with `EOPNOTSUPP` if the client side of TCP Fast Open
+/// is disabled by `net.ipv4.tcp_fastopen`.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "windows"))]
+pub struct ConnectWithData<T: IoBuf> {
+    pub(crate) fd: RawFd,
+    pub(crate) addr: SockAddr,
+    pub(crate) buffer: BufWrapper<T>,
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub(crate) slice: libc::iovec,
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub(crate) msg: libc::msghdr,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "windows"))]
+impl<T: IoBuf> ConnectWithData<T> {
+    /// Create [`ConnectWithData`].
+    pub fn new(fd: RawFd, addr: SockAddr, buffer: T) -> Self {
+        Self {
+            fd,
+            addr,
+            buffer: BufWrapper::new(buffer),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            slice: unsafe { std::mem::zeroed() },
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            msg: unsafe { std::mem::zeroed() },
+        }
+    }
+
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub(crate) fn set_msg(&mut self) {
+        let slice = self.buffer.as_slice();
+        self.slice = libc::iovec {
+            iov_base: slice.as_ptr() as _,
+            iov_len: slice.len(),
+        };
+        self.msg.msg_name = self.addr.as_ptr() as _;
+        self.msg.msg_namelen = self.addr.len();
+        self.msg.msg_iov = &mut self.slice;
+        self.msg.msg_iovlen = 1;
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "windows"))]
+impl<T: IoBuf> IntoInner for ConnectWithData<T> {
+    type Inner = BufWrapper<T>;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+/// Post a message to another [`Proactor`], which is received by
+/// [`Proactor::pop_message`].
+///
+/// ## Platform specific
+///
+/// * io-uring: it is `IORING_OP_MSG_RING`, which posts an entry to the target
+///   ring directly.
+/// * IOCP/polling: it is synchronized operation, which pushes the message to
+///   the mailbox of the target and wakes it up.
+///
+/// [`Proactor`]: crate::driver::Proactor
+/// [`Proactor::pop_message`]: crate::driver::Proactor::pop_message
+pub struct MsgRing {
+    pub(crate) sender: MessageSender,
+    pub(crate) msg: u64,
+}
+
+impl MsgRing {
+    /// Create [`MsgRing`].
+    pub fn new(sender: MessageSender, msg: u64) -> Self {
+        Self { sender, msg }
+    }
+}
+
+/// Send a range of a file to a connected stream socket, in the kernel.
+///
+/// It may send fewer bytes than requested, and `0` means the EOF of the file.
+///
+/// ## Platform specific
+///
+/// * io-uring: it waits for the socket to be writable with
+///   `IORING_OP_POLL_ADD`, and calls `sendfile`. The socket should be
+///   nonblocking, otherwise the thread may be blocked.
+/// * polling: `sendfile` when the socket is writable.
+/// * IOCP: `TransmitFile`, which sends at most `i32::MAX - 1` bytes at once.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "windows"))]
+pub struct SendFile {
+    pub(crate) fd: RawFd,
+    pub(crate) file: RawFd,
+    pub(crate) offset: u64,
+    pub(crate) len: usize,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "windows"))]
+impl SendFile {
+    /// Create [`SendFile`], which sends `len` bytes from the offset `offset`
+    /// of `file` to the socket `fd`.
+    pub fn new(fd: RawFd, file: RawFd, offset: u64, len: usize) -> Self {
+        Self {
+            fd,
+            file,
+            offset,
+            len,
+        }
+    }
+}
+
+/// Fill a buffer with random bytes from the entropy source of the OS.
+///
+/// It may fill only a part of the buffer, see [`crate::fs::random`] to fill
+/// it fully.
+///
+/// ## Platform specific
+///
+/// * io-uring: `IORING_OP_READ` from `/dev/urandom`.
+/// * polling: `read` from `/dev/urandom`, which never blocks.
+/// * IOCP: `BCryptGenRandom` with the system preferred RNG, on the thread
+///   pool.
+///
+/// On Unix, `/dev/urandom` is opened once for the process, and kept open.
+pub struct GetRandom<T: IoBufMut> {
+    #[cfg(unix)]
+    pub(crate) fd: RawFd,
+    pub(crate) buffer: BufWrapper<T>,
+    #[cfg(windows)]
+    pub(crate) op: Option<BlockingBufOp<SendWrapper<(*mut u8, usize)>>>,
+}
+
+impl<T: IoBufMut> GetRandom<T> {
+    /// Create [`GetRandom`]. It fails if `/dev/urandom` can't be opened on
+    /// Unix.
+    pub fn new(buffer: T) -> io::Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            fd: urandom()?,
+            buffer: BufWrapper::new(buffer),
+            #[cfg(windows)]
+            op: None,
+        })
+    }
+}
+
+impl<T: IoBufMut> IntoInner for GetRandom<T> {
+    type Inner = BufWrapper<T>;
+
+    fn into_inner(self) -> Self::Inner {
+        self.buffer
+    }
+}
+
+// The fd of `/dev/urandom`, opened at the first use.
+#[cfg(unix)]
+pub(crate) fn urandom() -> io::Result<RawFd> {
+    use std::{os::fd::AsRawFd, sync::OnceLock};
+
+    static URANDOM: OnceLock<std::fs::File> = OnceLock::new();
+
+    if let Some(file) = URANDOM.get() {
+        return Ok(file.as_raw_fd());
+    }
+    let file = std::fs::File::open("/dev/urandom")?;
+    Ok(URANDOM.get_or_init(|| file).as_raw_fd())
+}
+
+type BlockingFn<B> = Box<dyn FnOnce(RawFd, &mut B) -> io::Result<usize> + std::marker::Send>;
+
+struct BlockingDone<B> {
+    result: Option<io::Result<usize>>,
+    buffer: Option<B>,
+}
+
+struct BlockingShared<B> {
+    cancelled: AtomicBool,
+    // The thread running the function, to be interrupted when cancelled.
+    running: Mutex<Option<RunningThread>>,
+    done: Mutex<BlockingDone<B>>,
+    cond: Condvar,
+}
+
+impl<B> BlockingShared<B> {
+    // Record the thread running the function, unless the op has been
+    // cancelled.
+    fn start(&self) -> bool {
+        let mut running = self.running.lock().unwrap();
+        if self.cancelled.load(Ordering::Acquire) {
+            return false;
+        }
+        *running = RunningThread::current().ok();
+        true
+    }
+
+    // The syscall interrupted by the cancellation fails with the cancelled
+    // error. Any other result is returned as is.
+    fn finish(&self, res: io::Result<usize>) -> io::Result<usize> {
+        self.running.lock().unwrap().take();
+        match res {
+            Err(e)
+                if e.kind() == io::ErrorKind::Interrupted
+                    && self.cancelled.load(Ordering::Acquire) =>
+            {
+                Err(cancelled_error())
+            }
+            res => res,
+        }
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+        if let Some(thread) = &*self.running.lock().unwrap() {
+            // Not cancellable if the thread isn't interrupted, and the real
+            // result is returned.
+            if !thread.interrupt() {
+                self.cancelled.store(false, Ordering::Release);
+            }
+        }
+    }
+}
+
+// Windows handles are pointers, which are not `Send`.
+pub(crate) struct SendWrapper<T>(pub T);
+
+unsafe impl<T> std::marker::Send for SendWrapper<T> {}
+
+impl<T: Copy> SendWrapper<T> {
+    pub fn get(&self) -> T {
+        self.0
+    }
+}
+
+/// Run a blocking function with the fd and a buffer on a thread pool.
+///
+/// It is a safe way to perform the syscalls without a dedicated op, e.g.,
+/// `ioctl` or `copy_file_range`. The buffer is moved to the function and
+/// returned by [`IntoInner::into_inner`] after completion.
+///
+/// When the op is cancelled, the function is skipped if it hasn't started,
+/// and the blocking syscall in a running function is interrupted: on Unix, it
+/// fails with `EINTR` by a signal, a real-time one on Linux and Android, or
+/// `SIGURG` otherwise, and on Windows, the synchronous IO fails with
+/// `ERROR_OPERATION_ABORTED` by `CancelSynchronousIo`. The op completes after
+/// the function returns, with `ECANCELED` on Unix if it fails with `EINTR`, or
+/// with its real result otherwise. The cancellation is best-effort: a function
+/// retrying on `EINTR`, or entering the syscall after the interruption, still
+/// blocks. If the process handles the signal by itself, the function is not
+/// interrupted, and the op is not cancellable once started. The other signals are blocked on the
+/// threads of the pool, so the blocking syscalls in the function don't fail
+/// with `EINTR` otherwise.
+///
+/// Unlike the other ops, the buffer is moved to a thread of the pool, so it
+/// must be [`Send`](std::marker::Send), and the buffers shared by `Rc` are
+/// rejected:
+///
+/// ```compile_fail
+/// use std::rc::Rc;
+///
+/// use compio::op::BlockingBufOp;
+///
+/// let op = BlockingBufOp::new(0, Rc::new(vec![0u8; 16]), |_fd, buffer| Ok(buffer.len()));
+/// ```
+///
+/// ```
+/// use compio::{buf::IntoInner, driver::AsRawFd, fs::File, op::BlockingBufOp};
+///
+/// compio::task::block_on(async {
+///     let file = File::open("Cargo.toml").unwrap();
+///     let op = BlockingBufOp::new(file.as_raw_fd(), vec![0u8; 16], |_fd, buffer| {
+///         buffer.copy_from_slice(b"0123456789abcdef");
+///         Ok(buffer.len())
+///     });
+///     let (res, op) = compio::task::submit(op).await;
+///     assert_eq!(res.unwrap(), 16);
+///     assert_eq!(op.into_inner(), b"0123456789abcdef");
+/// })
+/// ```
+pub struct BlockingBufOp<B> {
+    pub(crate) fd: RawFd,
+    job: Option<(BlockingFn<B>, B)>,
+    shared: Arc<BlockingShared<B>>,
+    #[cfg(unix)]
+    pub(crate) notifier: Option<Arc<crate::driver::Notifier>>,
+}
+
+impl<B: std::marker::Send + 'static> BlockingBufOp<B> {
+    /// Create [`BlockingBufOp`].
+    pub fn new(
+        fd: RawFd,
+        buffer: B,
+        f: impl FnOnce(RawFd, &mut B) -> io::Result<usize> + std::marker::Send + 'static,
+    ) -> Self {
+        Self {
+            fd,
+            job: Some((Box::new(f), buffer)),
+            shared: Arc::new(BlockingShared {
+                cancelled: AtomicBool::new(false),
+                running: Mutex::new(None),
+                done: Mutex::new(BlockingDone {
+                    result: None,
+                    buffer: None,
+                }),
+                cond: Condvar::new(),
+            }),
+            #[cfg(unix)]
+            notifier: None,
+        }
+    }
+
+    /// Spawn the function to the thread pool, and call `notify` after it
+    /// returns.
+    pub(crate) fn spawn(&mut self, notify: impl FnOnce() + std::marker::Send + 'static) {
+        let (f, mut buffer) = self.job.take().expect("the op should start only once");
+        let fd = SendWrapper(self.fd);
+        let shared = self.shared.clone();
+        spawn_blocking(move || {
+            let res = if shared.start() {
+                let res = f(fd.get(), &mut buffer);
+                shared.finish(res)
+            } else {
+                Err(cancelled_error())
+            };
+            let mut done = shared.done.lock().unwrap();
+            done.result = Some(res);
+            done.buffer = Some(buffer);
+            drop(done);
+            shared.cond.notify_all();
+            notify();
+        })
+    }
+
+    /// Mark the op as cancelled, so that the function is skipped if it hasn't
+    /// started, or interrupt the blocking syscall in it if it is running.
+    pub(crate) fn interrupt(&self) {
+        self.shared.cancel();
+    }
+
+    /// Take the result of the function, if it has returned.
+    pub(crate) fn take_result(&self) -> Option<io::Result<usize>> {
+        self.shared.done.lock().unwrap().result.take()
+    }
+
+    /// Store the result when the op fails before the function is spawned.
+    #[cfg_attr(not(all(target_os = "linux", feature = "io-uring")), allow(dead_code))]
+    pub(crate) fn set_failed(&mut self, e: io::Error) {
+        let (_, buffer) = self.job.take().expect("the op should start only once");
+        let mut done = self.shared.done.lock().unwrap();
+        done.result = Some(Err(e));
+        done.buffer = Some(buffer);
+    }
+}
+
+impl<B> IntoInner for BlockingBufOp<B> {
+    type Inner = B;
+
+    /// Get the buffer back. If the op fails in the driver when the function is
+    /// running, it blocks until the function returns.
+    fn into_inner(mut self) -> Self::Inner {
+        if let Some((_, buffer)) = self.job.take() {
+            return buffer;
+        }
+        let mut done = self.shared.done.lock().unwrap();
+        loop {
+            if let Some(buffer) = done.buffer.take() {
+                return buffer;
+            }
+            done = self.shared.cond.wait(done).unwrap();
+        }
+    }
+}
+
+impl<B> Drop for BlockingBufOp<B> {
+    fn drop(&mut self) {
+        self.shared.cancel();
+    }
+}
+
+/// Run a blocking function with the fd on a thread pool. See
+/// [`BlockingBufOp`] to pass a buffer.
+///
+/// ```
+/// use compio::{driver::AsRawFd, fs::File, op::BlockingOp};
+///
+/// compio::task::block_on(async {
+///     let file = File::open("Cargo.toml").unwrap();
+///     let op = BlockingOp::new(file.as_raw_fd(), |fd| {
+///         let file = std::mem::ManuallyDrop::new(unsafe {
+///             <std::fs::File as compio::driver::FromRawFd>::from_raw_fd(fd)
+///         });
+///         Ok(file.metadata()?.len() as usize)
+///     });
+///     let (res, _) = compio::task::submit(op).await;
+///     assert!(res.unwrap() > 0);
+/// })
+/// ```
+pub struct BlockingOp {
+    pub(crate) inner: BlockingBufOp<()>,
+}
+
+impl BlockingOp {
+    /// Create [`BlockingOp`].
+    pub fn new(
+        fd: RawFd,
+        f: impl FnOnce(RawFd) -> io::Result<usize> + std::marker::Send + 'static,
+    ) -> Self {
+        Self {
+            inner: BlockingBufOp::new(fd, (), move |fd, _| f(fd)),
+        }
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn cancelled_error() -> io::Error {
+    io::Error::from_raw_os_error(libc::ECANCELED)
+}
+
+#[cfg(windows)]
+pub(crate) fn cancelled_error() -> io::Error {
+    io::Error::from_raw_os_error(windows_sys::Win32::Foundation::ERROR_OPERATION_ABORTED as _)
+}
+
 /// Receive data with one buffer.
 pub type Recv<T> = RecvImpl<BufWrapper<T>>;
 /// Receive data with vectored buffer.
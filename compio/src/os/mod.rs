@@ -0,0 +1,4 @@
+//! Platform specific extensions.
+
+#[cfg(target_os = "windows")]
+pub mod windows;
@@ -0,0 +1,37 @@
+//! Windows specific extensions.
+
+use std::{
+    io,
+    os::windows::io::{AsRawHandle, BorrowedHandle},
+};
+
+use crate::{op::WaitHandle, task::submit};
+
+/// Wait for the handle to be signaled.
+///
+/// It could be any waitable handle, e.g., a process, an event shared with
+/// other processes, or a waitable timer. The future completes at once if the
+/// handle is already signaled. Multiple waits on the same handle are
+/// independent, and dropping the future cancels the wait.
+///
+/// ```
+/// use std::os::windows::io::{AsHandle, AsRawHandle, FromRawHandle, OwnedHandle};
+///
+/// use compio::os::windows::wait_handle;
+/// use windows_sys::Win32::System::Threading::{CreateEventW, SetEvent};
+///
+/// compio::task::block_on(async {
+///     let event = unsafe {
+///         OwnedHandle::from_raw_handle(
+///             CreateEventW(std::ptr::null(), 1, 0, std::ptr::null()) as _,
+///         )
+///     };
+///     unsafe { SetEvent(event.as_raw_handle() as _) };
+///     wait_handle(event.as_handle()).await.unwrap();
+/// })
+/// ```
+pub async fn wait_handle(handle: BorrowedHandle<'_>) -> io::Result<()> {
+    let op = WaitHandle::new(handle.as_raw_handle());
+    submit(op).await.0?;
+    Ok(())
+}
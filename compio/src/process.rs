@@ -0,0 +1,220 @@
+//! Asynchronous waiting for child processes.
+//!
+//! A [`Child`] wraps a spawned [`std::process::Child`], and waits for it in
+//! the driver instead of blocking the thread. The exit status is collected
+//! exactly once, and it is cached for the later and the concurrent waiters.
+//!
+//! A supervisor could wait with a timeout and escalate:
+//!
+//! ```no_run
+//! # #[cfg(all(unix, feature = "time"))]
+//! compio::task::block_on(async {
+//!     use std::time::Duration;
+//!
+//!     use compio::process::Child;
+//!
+//!     let child = Child::spawn(&mut std::process::Command::new("server")).unwrap();
+//!     // Ask it to stop, and kill it if it doesn't.
+//!     child.signal(libc::SIGTERM).unwrap();
+//!     let status = match child.wait_with_timeout(Duration::from_secs(5)).await.unwrap() {
+//!         Some(status) => status,
+//!         None => {
+//!             child.kill().unwrap();
+//!             child.wait().await.unwrap()
+//!         }
+//!     };
+//!     println!("{status}");
+//! })
+//! ```
+
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+#[cfg(windows)]
+use std::os::windows::io::AsRawHandle;
+use std::{
+    cell::RefCell,
+    io,
+    process::{Command, ExitStatus},
+};
+#[cfg(feature = "time")]
+use std::time::Duration;
+
+#[cfg(unix)]
+use crate::op::WaitProcess;
+#[cfg(windows)]
+use crate::op::WaitHandle;
+#[cfg(unix)]
+use crate::syscall;
+use crate::task::submit;
+
+/// A child process, which could be waited for asynchronously.
+///
+/// ## Platform specific
+/// * Linux: it waits for the pidfd to be readable. It requires Linux 5.3 or
+///   later.
+/// * macOS and BSD: it waits for a kqueue with `EVFILT_PROC` and `NOTE_EXIT`
+///   of the process.
+/// * Windows: it waits for the process handle on the system thread pool.
+///
+/// On unix, an exited process stays a zombie until it is waited for. If the
+/// [`Child`] is dropped while the process is running, a thread is spawned to
+/// reap it, unless [`Child::reap_on_drop`] turns it off.
+#[derive(Debug)]
+pub struct Child {
+    inner: RefCell<std::process::Child>,
+    // Readable after the process exits. It's `None` if the process had exited
+    // when it was created.
+    #[cfg(unix)]
+    waiter: Option<OwnedFd>,
+    #[cfg(unix)]
+    reap_on_drop: bool,
+}
+
+impl Child {
+    /// Spawn the command as a child process.
+    pub fn spawn(command: &mut Command) -> io::Result<Self> {
+        Self::from_std(command.spawn()?)
+    }
+
+    /// Wrap a spawned child process. It should not have been waited for.
+    pub fn from_std(child: std::process::Child) -> io::Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            waiter: open_waiter(child.id() as _)?,
+            #[cfg(unix)]
+            reap_on_drop: true,
+            inner: RefCell::new(child),
+        })
+    }
+
+    /// The OS-assigned process identifier.
+    pub fn id(&self) -> u32 {
+        self.inner.borrow().id()
+    }
+
+    /// Wait for the process to exit, and collect its exit status. It could be
+    /// called concurrently, and all waiters get the same status.
+    pub async fn wait(&self) -> io::Result<ExitStatus> {
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Ok(status);
+            }
+            #[cfg(unix)]
+            match &self.waiter {
+                Some(fd) => {
+                    submit(WaitProcess::new(fd.as_raw_fd())).await.0?;
+                }
+                // The process is a zombie, so it doesn't block.
+                None => return self.inner.borrow_mut().wait(),
+            }
+            #[cfg(windows)]
+            {
+                let handle = self.inner.borrow().as_raw_handle();
+                submit(WaitHandle::new(handle)).await.0?;
+            }
+        }
+    }
+
+    /// Collect the exit status if the process has exited, without waiting.
+    pub fn try_wait(&self) -> io::Result<Option<ExitStatus>> {
+        // The status is cached by `std::process::Child` once collected.
+        self.inner.borrow_mut().try_wait()
+    }
+
+    /// Wait for the process to exit, at most for `duration`. Returns `None`
+    /// if it times out, and the process keeps running.
+    #[cfg(feature = "time")]
+    pub async fn wait_with_timeout(&self, duration: Duration) -> io::Result<Option<ExitStatus>> {
+        match crate::time::timeout(duration, self.wait()).await {
+            Ok(res) => res.map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Kill the process, i.e., `SIGKILL` on unix, and `TerminateProcess` on
+    /// Windows. It doesn't wait for the process to exit.
+    pub fn kill(&self) -> io::Result<()> {
+        if self.try_wait()?.is_some() {
+            return Ok(());
+        }
+        self.inner.borrow_mut().kill()
+    }
+
+    /// Send a signal to the process, e.g., `SIGTERM`. It does nothing if the
+    /// process has exited and been collected.
+    ///
+    /// The process identifier can't be reused before the process is
+    /// collected, so the signal is never sent to another process.
+    #[cfg(unix)]
+    pub fn signal(&self, sig: i32) -> io::Result<()> {
+        if self.try_wait()?.is_some() {
+            return Ok(());
+        }
+        syscall!(kill(self.id() as _, sig))?;
+        Ok(())
+    }
+
+    /// Whether to reap the process in a background thread if the [`Child`]
+    /// is dropped while the process is running. It is `true` by default.
+    /// Otherwise, the process is left a zombie after it exits, unless it is
+    /// waited for by other means.
+    #[cfg(unix)]
+    pub fn reap_on_drop(&mut self, reap: bool) {
+        self.reap_on_drop = reap;
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Child {
+    fn drop(&mut self) {
+        if !self.reap_on_drop {
+            return;
+        }
+        let child = self.inner.get_mut();
+        if let Ok(None) = child.try_wait() {
+            let pid = child.id() as libc::pid_t;
+            std::thread::Builder::new()
+                .name("compio-reaper".into())
+                .spawn(move || loop {
+                    match syscall!(waitpid(pid, std::ptr::null_mut(), 0)) {
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                        _ => break,
+                    }
+                })
+                .ok();
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn open_waiter(pid: libc::pid_t) -> io::Result<Option<OwnedFd>> {
+    // The pidfd is opened with `O_CLOEXEC`. A zombie could be opened, as it
+    // hasn't been reaped.
+    let fd = syscall!(syscall(libc::SYS_pidfd_open, pid, 0))?;
+    Ok(Some(unsafe { OwnedFd::from_raw_fd(fd as _) }))
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn open_waiter(pid: libc::pid_t) -> io::Result<Option<OwnedFd>> {
+    let kqueue = unsafe { OwnedFd::from_raw_fd(syscall!(kqueue())?) };
+    syscall!(fcntl(kqueue.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC))?;
+    let mut event: libc::kevent = unsafe { std::mem::zeroed() };
+    event.ident = pid as _;
+    event.filter = libc::EVFILT_PROC;
+    // Not oneshot, so the kqueue stays readable for all waiters.
+    event.flags = libc::EV_ADD;
+    event.fflags = libc::NOTE_EXIT;
+    match syscall!(kevent(
+        kqueue.as_raw_fd(),
+        &event,
+        1,
+        std::ptr::null_mut(),
+        0,
+        std::ptr::null()
+    )) {
+        Ok(_) => Ok(Some(kqueue)),
+        // The process has exited, and it can't be registered.
+        Err(e) if e.raw_os_error() == Some(libc::ESRCH) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
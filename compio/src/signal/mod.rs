@@ -1,5 +1,8 @@
 //! Asynchronous signal handling.
 //!
+//! The signals are handled process-wide. Every listener, in any runtime, is
+//! notified for each occurrence of the signal.
+//!
 //! # Examples
 //!
 //! Print on "ctrl-c" notification.
@@ -1,98 +1,194 @@
 //! Unix-specific types for signal handling.
+//!
+//! The signal handler is process-global. It forwards the signals to a
+//! dispatching thread, which notifies all listeners in all runtimes, so the
+//! signal masks of the runtime threads don't matter.
 
+#[cfg(feature = "lazy_cell")]
+use std::sync::LazyLock;
 use std::{
-    cell::RefCell,
     collections::HashMap,
     io,
-    os::fd::{AsRawFd, RawFd},
+    os::fd::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd},
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        Mutex, OnceLock,
+    },
 };
 
-use crate::event::{Event, EventHandle};
+#[cfg(not(feature = "lazy_cell"))]
+use once_cell::sync::Lazy as LazyLock;
 
-thread_local! {
-    static HANDLER: RefCell<HashMap<i32, HashMap<RawFd, EventHandle>>> =
-        RefCell::new(HashMap::new());
+use crate::{
+    event::{Event, EventHandle},
+    syscall,
+};
+
+#[derive(Default)]
+struct Listeners {
+    // The keys are not reused, because a listener may unregister after being
+    // notified and removed.
+    next_key: u64,
+    handles: HashMap<i32, HashMap<u64, EventHandle>>,
 }
 
+static HANDLER: LazyLock<Mutex<Listeners>> = LazyLock::new(|| Mutex::new(Listeners::default()));
+
+// The write end of the pipe to the dispatching thread.
+static SENDER: AtomicI32 = AtomicI32::new(-1);
+
 unsafe extern "C" fn signal_handler(sig: i32) {
-    HANDLER.with(|handler| {
-        let mut handler = handler.borrow_mut();
-        if let Some(fds) = handler.get_mut(&sig) {
-            if !fds.is_empty() {
-                let fds = std::mem::take(fds);
-                for (_, fd) in fds {
-                    fd.notify().ok();
+    // Only async-signal-safe functions are called here.
+    let errno = *errno_location();
+    let fd = SENDER.load(Ordering::Acquire);
+    if fd >= 0 {
+        let data = sig as u8;
+        // The signal is coalesced if the pipe is full.
+        libc::write(fd, &data as *const u8 as *const _, 1);
+    }
+    *errno_location() = errno;
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "illumos"))]
+unsafe fn errno_location() -> *mut i32 {
+    libc::__errno_location()
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+unsafe fn errno_location() -> *mut i32 {
+    libc::__error()
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "illumos",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+)))]
+unsafe fn errno_location() -> *mut i32 {
+    libc::__errno()
+}
+
+fn dispatch(receiver: OwnedFd) {
+    let mut data = 0u8;
+    loop {
+        match syscall!(read(
+            receiver.as_raw_fd(),
+            &mut data as *mut u8 as *mut _,
+            1
+        )) {
+            Ok(0) => break,
+            Ok(_) => {
+                let mut handler = HANDLER.lock().unwrap();
+                if let Some(handles) = handler.handles.get_mut(&(data as i32)) {
+                    for (_, handle) in std::mem::take(handles) {
+                        handle.notify().ok();
+                    }
                 }
             }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(_) => break,
         }
+    }
+}
+
+// Start the dispatching thread once.
+fn init_dispatcher() -> io::Result<()> {
+    static DISPATCHER: OnceLock<Result<(), i32>> = OnceLock::new();
+    let res = DISPATCHER.get_or_init(|| {
+        let start = || -> io::Result<()> {
+            let mut fds = [-1, -1];
+            syscall!(pipe(fds.as_mut_ptr()))?;
+            let receiver = unsafe { OwnedFd::from_raw_fd(fds[0]) };
+            let sender = unsafe { OwnedFd::from_raw_fd(fds[1]) };
+            syscall!(fcntl(receiver.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC))?;
+            syscall!(fcntl(sender.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC))?;
+            syscall!(fcntl(sender.as_raw_fd(), libc::F_SETFL, libc::O_NONBLOCK))?;
+            std::thread::Builder::new()
+                .name("compio-signal".into())
+                .spawn(move || dispatch(receiver))?;
+            // The sender lives until the process exits.
+            SENDER.store(sender.into_raw_fd(), Ordering::Release);
+            Ok(())
+        };
+        start().map_err(|e| e.raw_os_error().unwrap_or(libc::EIO))
     });
+    res.map_err(io::Error::from_raw_os_error)
 }
 
-unsafe fn init(sig: i32) {
-    libc::signal(sig, signal_handler as *const () as usize);
+unsafe fn init(sig: i32) -> io::Result<()> {
+    let mut action: libc::sigaction = std::mem::zeroed();
+    action.sa_sigaction = signal_handler as *const () as usize;
+    action.sa_flags = libc::SA_RESTART;
+    libc::sigemptyset(&mut action.sa_mask);
+    syscall!(sigaction(sig, &action, std::ptr::null_mut()))?;
+    Ok(())
 }
 
 unsafe fn uninit(sig: i32) {
     libc::signal(sig, libc::SIG_DFL);
 }
 
-fn register(sig: i32, fd: &Event) -> io::Result<()> {
-    unsafe { init(sig) };
-    let raw_fd = fd.as_raw_fd();
+fn register(sig: i32, fd: &Event) -> io::Result<u64> {
+    if !(1..=u8::MAX as i32).contains(&sig) {
+        return Err(io::Error::from_raw_os_error(libc::EINVAL));
+    }
+    init_dispatcher()?;
     let handle = fd.handle()?;
-    HANDLER.with(|handler| {
-        handler
-            .borrow_mut()
-            .entry(sig)
-            .or_default()
-            .insert(raw_fd, handle)
-    });
-    Ok(())
+    let mut handler = HANDLER.lock().unwrap();
+    let key = handler.next_key;
+    let handles = handler.handles.entry(sig).or_default();
+    if handles.is_empty() {
+        unsafe { init(sig) }?;
+    }
+    handles.insert(key, handle);
+    handler.next_key += 1;
+    Ok(key)
 }
 
-fn unregister(sig: i32, fd: RawFd) {
-    let need_uninit = HANDLER.with(|handler| {
-        let mut handler = handler.borrow_mut();
-        if let Some(fds) = handler.get_mut(&sig) {
-            fds.remove(&fd);
-            if !fds.is_empty() {
-                return false;
-            }
+fn unregister(sig: i32, key: u64) {
+    let mut handler = HANDLER.lock().unwrap();
+    if let Some(handles) = handler.handles.get_mut(&sig) {
+        handles.remove(&key);
+        if handles.is_empty() {
+            unsafe { uninit(sig) };
         }
-        true
-    });
-    if need_uninit {
-        unsafe { uninit(sig) };
     }
 }
 
 /// Represents a listener to unix signal event.
 #[derive(Debug)]
-struct SignalFd {
+pub(crate) struct SignalFd {
     sig: i32,
+    key: u64,
     fd: Event,
 }
 
 impl SignalFd {
-    fn new(sig: i32) -> io::Result<Self> {
+    pub(crate) fn new(sig: i32) -> io::Result<Self> {
         let fd = Event::new()?;
-        register(sig, &fd)?;
-        Ok(Self { sig, fd })
+        let key = register(sig, &fd)?;
+        Ok(Self { sig, key, fd })
     }
 
-    async fn wait(&self) -> io::Result<()> {
+    pub(crate) async fn wait(&self) -> io::Result<()> {
         self.fd.wait().await
     }
 }
 
 impl Drop for SignalFd {
     fn drop(&mut self) {
-        unregister(self.sig, self.fd.as_raw_fd());
+        unregister(self.sig, self.key);
     }
 }
 
 /// Creates a new listener which will receive notifications when the current
 /// process receives the specified signal.
+///
+/// All listeners of the signal, in all runtimes, are notified for each
+/// occurrence, no matter which thread the signal is delivered to.
 pub async fn signal(sig: i32) -> io::Result<()> {
     let fd = SignalFd::new(sig)?;
     fd.wait().await
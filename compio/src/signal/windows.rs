@@ -10,7 +10,6 @@ use std::{
 
 #[cfg(not(feature = "lazy_cell"))]
 use once_cell::sync::Lazy as LazyLock;
-use slab::Slab;
 use windows_sys::Win32::{
     Foundation::BOOL,
     System::Console::{
@@ -24,14 +23,23 @@ use crate::{
     syscall,
 };
 
-static HANDLER: LazyLock<Mutex<HashMap<u32, Slab<EventHandle>>>> =
-    LazyLock::new(|| Mutex::new(HashMap::new()));
+#[derive(Default)]
+struct Listeners {
+    // The keys are not reused, because a listener may unregister after being
+    // notified and removed.
+    next_key: usize,
+    handles: HashMap<u32, HashMap<usize, EventHandle>>,
+}
+
+// The handler is process-global, and it notifies the listeners in all
+// runtimes.
+static HANDLER: LazyLock<Mutex<Listeners>> = LazyLock::new(|| Mutex::new(Listeners::default()));
 
 unsafe extern "system" fn ctrl_event_handler(ctrltype: u32) -> BOOL {
     let mut handler = HANDLER.lock().unwrap();
-    if let Some(handlers) = handler.get_mut(&ctrltype) {
+    if let Some(handlers) = handler.handles.get_mut(&ctrltype) {
         if !handlers.is_empty() {
-            let handlers = std::mem::replace(handlers, Slab::new());
+            let handlers = std::mem::take(handlers);
             for (_, handler) in handlers {
                 handler.notify().ok();
             }
@@ -51,15 +59,16 @@ fn init() -> io::Result<()> {
 fn register(ctrltype: u32, e: &Event) -> io::Result<usize> {
     let mut handler = HANDLER.lock().unwrap();
     let handle = e.handle()?;
-    Ok(handler.entry(ctrltype).or_default().insert(handle))
+    let key = handler.next_key;
+    handler.next_key += 1;
+    handler.handles.entry(ctrltype).or_default().insert(key, handle);
+    Ok(key)
 }
 
 fn unregister(ctrltype: u32, key: usize) {
     let mut handler = HANDLER.lock().unwrap();
-    if let Some(handlers) = handler.get_mut(&ctrltype) {
-        if handlers.contains(key) {
-            let _ = handlers.remove(key);
-        }
+    if let Some(handlers) = handler.handles.get_mut(&ctrltype) {
+        handlers.remove(&key);
     }
 }
 
@@ -0,0 +1,257 @@
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
+    task::{Context, Poll},
+};
+
+use futures_util::{future::LocalBoxFuture, FutureExt};
+
+use super::WaiterQueue;
+use crate::{
+    event::Event,
+    task::{
+        op::{unscoped, InterruptScope, ScopeGuard},
+        RUNTIME,
+    },
+};
+
+/// A token to cancel a tree of tasks, which could be shared between the
+/// runtimes of different threads.
+///
+/// A token is cancelled by [`CancellationToken::cancel`], or when its parent
+/// is cancelled. The clones share the same state.
+///
+/// The tasks observe the cancellation with [`CancellationToken::cancelled`],
+/// or stop their IO with the combinators:
+/// * [`CancellationToken::run_until_cancelled`] drops the future, and the ops
+///   in flight are cancelled in the driver. Their buffers are dropped when the
+///   driver gives them back.
+/// * [`CancellationExt::with_cancellation`] cancels the ops in flight in the
+///   driver, but still polls the future, so that the buffers are given back
+///   where they would have been. The ops submitted afterwards fail at once.
+///
+/// ```
+/// use compio::sync::{CancellationExt, CancellationToken};
+///
+/// compio::task::block_on(async {
+///     let token = CancellationToken::new();
+///     let child = token.child_token();
+///     let file = compio::fs::File::open("Cargo.toml").unwrap();
+///
+///     token.cancel();
+///     assert!(child.is_cancelled());
+///     // The cancelled future gives the buffer back.
+///     let (res, buffer) = file
+///         .read_at(Vec::with_capacity(1024), 0)
+///         .with_cancellation(&child)
+///         .await;
+///     assert!(res.is_err());
+///     assert_eq!(buffer.capacity(), 1024);
+/// })
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<TokenInner>,
+}
+
+#[derive(Debug, Default)]
+struct TokenInner {
+    cancelled: AtomicBool,
+    state: std::sync::Mutex<TokenState>,
+}
+
+#[derive(Default)]
+struct TokenState {
+    waiters: WaiterQueue,
+    children: Vec<Weak<TokenInner>>,
+}
+
+impl std::fmt::Debug for TokenState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenState").finish_non_exhaustive()
+    }
+}
+
+impl TokenInner {
+    fn cancel(&self) {
+        let children = {
+            let mut state = self.state.lock().unwrap();
+            if self.cancelled.swap(true, Ordering::AcqRel) {
+                return;
+            }
+            state.waiters.wake_all();
+            std::mem::take(&mut state.children)
+        };
+        for child in children {
+            if let Some(child) = child.upgrade() {
+                child.cancel();
+            }
+        }
+    }
+}
+
+impl CancellationToken {
+    /// Create [`CancellationToken`] which is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a child token, which is cancelled when this token is cancelled.
+    /// Cancelling the child doesn't affect this token.
+    pub fn child_token(&self) -> Self {
+        let child = Arc::new(TokenInner::default());
+        let mut state = self.inner.state.lock().unwrap();
+        if self.is_cancelled() {
+            child.cancelled.store(true, Ordering::Release);
+        } else {
+            state.children.retain(|child| child.strong_count() > 0);
+            state.children.push(Arc::downgrade(&child));
+        }
+        Self { inner: child }
+    }
+
+    /// Cancel this token and its children. The waiting tasks are woken.
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+
+    /// Whether the token is cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Wait until the token is cancelled.
+    ///
+    /// An error is returned only if the task fails to wait, e.g., the [`Event`]
+    /// cannot be created.
+    pub async fn cancelled(&self) -> io::Result<()> {
+        let (event, id) = {
+            let mut state = self.inner.state.lock().unwrap();
+            if self.is_cancelled() {
+                return Ok(());
+            }
+            let event = Event::new()?;
+            let id = state.waiters.push(event.handle()?);
+            (event, id)
+        };
+        let _waiting = Waiting {
+            inner: &self.inner,
+            id,
+        };
+        // The wait should not be interrupted by an outer `with_cancellation`.
+        unscoped(event.wait()).await
+    }
+
+    /// Run the future until it completes, or the token is cancelled. If the
+    /// token is cancelled first, the future is dropped, and [`None`] is
+    /// returned. The ops in flight of the future are cancelled in the driver.
+    ///
+    /// If the task fails to wait for the token, it is treated as cancelled.
+    pub async fn run_until_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        futures_util::select_biased! {
+            _ = self.cancelled().fuse() => None,
+            res = future.fuse() => Some(res),
+        }
+    }
+
+    /// Create a guard, which cancels the token when dropped.
+    pub fn drop_guard(self) -> DropGuard {
+        DropGuard { token: Some(self) }
+    }
+}
+
+// Remove the waiter if the future is dropped before notified.
+struct Waiting<'a> {
+    inner: &'a TokenInner,
+    id: u64,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.inner.state.lock().unwrap().waiters.remove(self.id);
+    }
+}
+
+/// A guard returned by [`CancellationToken::drop_guard`], which cancels the
+/// token when dropped.
+#[derive(Debug)]
+pub struct DropGuard {
+    token: Option<CancellationToken>,
+}
+
+impl DropGuard {
+    /// Get the token back, without cancelling it.
+    pub fn disarm(mut self) -> CancellationToken {
+        self.token
+            .take()
+            .expect("the token should exist before dropped")
+    }
+}
+
+impl Drop for DropGuard {
+    fn drop(&mut self) {
+        if let Some(token) = &self.token {
+            token.cancel();
+        }
+    }
+}
+
+/// An extension trait to cancel the IO of a future with
+/// [`CancellationToken`].
+pub trait CancellationExt: Future + Sized {
+    /// Cancel the ops of the future in the driver when the token is cancelled,
+    /// and still wait for the output of the future.
+    ///
+    /// The ops in flight are given back with the cancellation error, unless
+    /// they have completed, and the ops submitted afterwards fail at once. So
+    /// the future returns its buffers as it does on an IO error. The other
+    /// waits, e.g., timers or channels, are not interrupted, and the future
+    /// completes only when it handles the error. Use
+    /// [`CancellationToken::run_until_cancelled`] for such futures.
+    ///
+    /// If the task fails to wait for the token, it is treated as cancelled.
+    fn with_cancellation(self, token: &CancellationToken) -> WithCancellation<'_, Self> {
+        WithCancellation {
+            future: Box::pin(self),
+            cancelled: Some(token.cancelled().boxed_local()),
+            scope: None,
+        }
+    }
+}
+
+impl<F: Future> CancellationExt for F {}
+
+/// The future returned by [`CancellationExt::with_cancellation`].
+pub struct WithCancellation<'a, F> {
+    future: Pin<Box<F>>,
+    // `None` after the token is cancelled.
+    cancelled: Option<LocalBoxFuture<'a, io::Result<()>>>,
+    // Created in the first poll, nested in the current scope.
+    scope: Option<Rc<InterruptScope>>,
+}
+
+impl<F: Future> Future for WithCancellation<'_, F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let scope = this.scope.get_or_insert_with(|| {
+            let parent = RUNTIME.with(|runtime| runtime.current_scope());
+            Rc::new(InterruptScope::new(parent))
+        });
+        if let Some(cancelled) = &mut this.cancelled {
+            if cancelled.as_mut().poll(cx).is_ready() {
+                this.cancelled = None;
+                RUNTIME.with(|runtime| runtime.interrupt_scope(scope));
+            }
+        }
+        let _guard = ScopeGuard::enter(Some(scope.clone()));
+        this.future.as_mut().poll(cx)
+    }
+}
@@ -0,0 +1,372 @@
+//! Synchronization primitives shared between runtimes.
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use compio::sync::Mutex;
+//!
+//! let counter = Arc::new(Mutex::new(0));
+//! let threads = (0..4)
+//!     .map(|_| {
+//!         let counter = counter.clone();
+//!         std::thread::spawn(move || {
+//!             compio::task::block_on(async {
+//!                 for _ in 0..100 {
+//!                     *counter.lock().await.unwrap() += 1;
+//!                 }
+//!             })
+//!         })
+//!     })
+//!     .collect::<Vec<_>>();
+//! for thread in threads {
+//!     thread.join().unwrap();
+//! }
+//! assert_eq!(*counter.try_lock().unwrap(), 400);
+//! ```
+
+use std::{
+    cell::UnsafeCell,
+    collections::VecDeque,
+    fmt::Debug,
+    io,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use crate::event::{Event, EventHandle};
+
+mod cancel;
+pub use cancel::*;
+
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+// Locked, and there may be waiters.
+const CONTENDED: u32 = 2;
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod futex {
+    use std::sync::{atomic::AtomicU32, OnceLock};
+
+    use io_uring::{opcode, IoUring, Probe};
+
+    use crate::syscall;
+
+    /// Whether the futex ops are supported by the kernel, Linux 6.7 or later.
+    pub fn is_supported() -> bool {
+        static SUPPORTED: OnceLock<bool> = OnceLock::new();
+        *SUPPORTED.get_or_init(|| {
+            let Ok(ring) = IoUring::new(2) else {
+                return false;
+            };
+            let mut probe = Probe::new();
+            ring.submitter().register_probe(&mut probe).is_ok()
+                && probe.is_supported(opcode::FutexWait::CODE)
+                && probe.is_supported(opcode::FutexWake::CODE)
+        })
+    }
+
+    /// Wake a waiter synchronously. The waiters waiting with the ops are also
+    /// woken.
+    pub fn wake_one(futex: &AtomicU32) {
+        syscall!(syscall(
+            libc::SYS_futex,
+            futex.as_ptr(),
+            libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+            1
+        ))
+        .ok();
+    }
+}
+
+#[derive(Default)]
+struct WaiterQueue {
+    next_id: u64,
+    waiters: VecDeque<(u64, EventHandle)>,
+}
+
+impl WaiterQueue {
+    fn push(&mut self, handle: EventHandle) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.waiters.push_back((id, handle));
+        id
+    }
+
+    fn remove(&mut self, id: u64) -> bool {
+        if let Some(index) = self.waiters.iter().position(|(i, _)| *i == id) {
+            self.waiters.remove(index);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn wake_one(&mut self) {
+        if let Some((_, handle)) = self.waiters.pop_front() {
+            handle.notify().ok();
+        }
+    }
+
+    fn wake_all(&mut self) {
+        for (_, handle) in self.waiters.drain(..) {
+            handle.notify().ok();
+        }
+    }
+}
+
+/// An asynchronous mutual exclusion primitive, which could be shared between
+/// the runtimes of different threads.
+///
+/// The uncontended path only involves an atomic operation. The contended path
+/// parks the task through the driver of current runtime, instead of spinning
+/// or blocking the thread.
+///
+/// ## Platform specific
+/// * io-uring on Linux 6.7 or later: the waiters submit
+///   [`FutexWait`](crate::op::FutexWait), and the unlocking thread wakes one
+///   of them with `futex(2)`.
+/// * Others: the waiters are queued with [`Event`] handles, and the unlocking
+///   thread notifies one of them.
+///
+/// The fairness is best-effort.
+pub struct Mutex<T> {
+    state: Arc<AtomicU32>,
+    // `None` if the futex ops are used.
+    queue: Option<std::sync::Mutex<WaiterQueue>>,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    /// Create [`Mutex`] in an unlocked state.
+    pub fn new(value: T) -> Self {
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if futex::is_supported() {
+            return Self::with_queue(value, None);
+        }
+        Self::with_event_queue(value)
+    }
+
+    /// Create [`Mutex`] whose waiters wait with the futex ops, or `None` if
+    /// they are not supported. Only for testing both implementations.
+    #[doc(hidden)]
+    pub fn with_futex(value: T) -> Option<Self> {
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if futex::is_supported() {
+            return Some(Self::with_queue(value, None));
+        }
+        let _ = value;
+        None
+    }
+
+    /// Create [`Mutex`] whose waiters are queued with the [`Event`] handles,
+    /// even if the futex ops are supported. Only for testing both
+    /// implementations.
+    #[doc(hidden)]
+    pub fn with_event_queue(value: T) -> Self {
+        Self::with_queue(value, Some(Default::default()))
+    }
+
+    fn with_queue(value: T, queue: Option<std::sync::Mutex<WaiterQueue>>) -> Self {
+        Self {
+            state: Arc::new(AtomicU32::new(UNLOCKED)),
+            queue,
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Acquire the mutex, waiting asynchronously if it is locked.
+    ///
+    /// It is cancel safe. If the future is dropped while waiting, the wakeup
+    /// it may have received is passed to another waiter.
+    pub async fn lock(&self) -> io::Result<MutexGuard<'_, T>> {
+        if self
+            .state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            self.lock_contended().await?;
+        }
+        Ok(MutexGuard::new(self))
+    }
+
+    /// Try to acquire the mutex without waiting.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| MutexGuard::new(self))
+    }
+
+    /// Get the mutable reference of the inner value. No locking is needed
+    /// because of the mutable borrow.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    /// Consume the mutex and return the inner value.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+
+    async fn lock_contended(&self) -> io::Result<()> {
+        match &self.queue {
+            Some(queue) => loop {
+                let (event, id) = {
+                    let mut queue = queue.lock().unwrap();
+                    // Mark contended with the queue locked, so that the unlocking
+                    // thread finds this waiter in the queue.
+                    if self.state.swap(CONTENDED, Ordering::Acquire) == UNLOCKED {
+                        return Ok(());
+                    }
+                    let event = Event::new()?;
+                    let id = queue.push(event.handle()?);
+                    (event, id)
+                };
+                let waiting = Waiting::new(self, Some(id));
+                event.wait().await?;
+                waiting.disarm();
+            },
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            None => loop {
+                use crate::{op::FutexWait, task::submit};
+
+                if self.state.swap(CONTENDED, Ordering::Acquire) == UNLOCKED {
+                    return Ok(());
+                }
+                let waiting = Waiting::new(self, None);
+                let op = FutexWait::new(self.state.clone(), CONTENDED);
+                match submit(op).await.0 {
+                    Ok(_) => {}
+                    Err(e)
+                        if matches!(e.raw_os_error(), Some(libc::EAGAIN | libc::EINTR)) => {}
+                    Err(e) => return Err(e),
+                }
+                waiting.disarm();
+            },
+            #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+            None => unreachable!(),
+        }
+    }
+
+    fn unlock(&self) {
+        if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            self.wake_one();
+        }
+    }
+
+    fn wake_one(&self) {
+        match &self.queue {
+            Some(queue) => queue.lock().unwrap().wake_one(),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            None => futex::wake_one(&self.state),
+            #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+            None => unreachable!(),
+        }
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Debug> Debug for Mutex<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut d = f.debug_struct("Mutex");
+        match self.try_lock() {
+            Some(guard) => d.field("value", &&*guard),
+            None => d.field("value", &format_args!("<locked>")),
+        };
+        d.finish_non_exhaustive()
+    }
+}
+
+// Armed while a task is waiting. If the waiting future is dropped, the wakeup
+// it may have consumed is passed to another waiter.
+struct Waiting<'a, T> {
+    mutex: &'a Mutex<T>,
+    id: Option<u64>,
+    armed: bool,
+}
+
+impl<'a, T> Waiting<'a, T> {
+    fn new(mutex: &'a Mutex<T>, id: Option<u64>) -> Self {
+        Self {
+            mutex,
+            id,
+            armed: true,
+        }
+    }
+
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl<T> Drop for Waiting<'_, T> {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        match (&self.mutex.queue, self.id) {
+            (Some(queue), Some(id)) => {
+                let mut queue = queue.lock().unwrap();
+                // Not removed means that it has been notified.
+                if !queue.remove(id) {
+                    queue.wake_one();
+                }
+            }
+            _ => self.mutex.wake_one(),
+        }
+    }
+}
+
+/// A guard of [`Mutex`]. The mutex is unlocked when it is dropped.
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+    // Send and Sync like `&mut T`.
+    _p: PhantomData<&'a mut T>,
+}
+
+impl<'a, T> MutexGuard<'a, T> {
+    fn new(mutex: &'a Mutex<T>) -> Self {
+        Self {
+            mutex,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<T: Debug> Debug for MutexGuard<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
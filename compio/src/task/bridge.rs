@@ -0,0 +1,237 @@
+//! A compatibility shim to run the [`Send`] futures of the other libraries,
+//! which expect a multithreaded executor, e.g., they are woken from the
+//! threads of their own.
+//!
+//! The wakers of the compio tasks must not be called from other threads, so
+//! such a future can't be awaited in a compio task directly. [`spawn_send`]
+//! runs it on the blocking thread pool instead, and delivers the output back
+//! to the runtime with an [`Event`].
+//!
+//! ```
+//! use compio::task::bridge::spawn_send;
+//!
+//! compio::task::block_on(async {
+//!     let handle = spawn_send(async {
+//!         // Woken from another thread.
+//!         let (tx, rx) = futures_channel::oneshot::channel();
+//!         std::thread::spawn(move || tx.send(42).unwrap());
+//!         rx.await.unwrap()
+//!     });
+//!     assert_eq!(handle.await, 42);
+//! })
+//! ```
+
+use std::{
+    cell::Cell,
+    fmt::Debug,
+    future::Future,
+    io,
+    panic::{resume_unwind, AssertUnwindSafe},
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex},
+    task::{Context, Poll},
+    thread,
+};
+
+use async_task::{Runnable, Task};
+use futures_util::FutureExt;
+
+use crate::{driver::pool::spawn_blocking, event::Event, task::runtime::running_block_on};
+
+thread_local! {
+    // Whether a bridged future is being polled on this thread.
+    static ON_BRIDGE: Cell<bool> = const { Cell::new(false) };
+}
+
+// Each wake pushes the future to the queue of the blocking thread pool, so it
+// is polled by any idle thread of the pool.
+fn schedule(runnable: Runnable) {
+    spawn_blocking(move || {
+        ON_BRIDGE.set(true);
+        runnable.run();
+        ON_BRIDGE.set(false);
+    })
+}
+
+// The output of the future, and the condvar notified when it is stored.
+struct Slot<T> {
+    output: Mutex<Option<thread::Result<T>>>,
+    done: Condvar,
+}
+
+/// Run a [`Send`] future on the blocking thread pool shared by all the
+/// runtimes, and wait for its output in current runtime with the returned
+/// [`JoinHandle`].
+///
+/// The future is polled on any thread of the pool, and could be woken from
+/// any thread. A poll occupies a thread of the pool, so the future should not
+/// block for long, see [`block_in_place`].
+///
+/// # Panics
+///
+/// It panics if the event to deliver the output can't be created.
+pub fn spawn_send<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let event = Event::new().expect("cannot create the event of the bridged future");
+    let handle = event
+        .handle()
+        .expect("cannot create the event of the bridged future");
+    let slot = Arc::new(Slot {
+        output: Mutex::new(None),
+        done: Condvar::new(),
+    });
+    let result = slot.clone();
+    let future = async move {
+        let res = AssertUnwindSafe(future).catch_unwind().await;
+        *result.output.lock().unwrap() = Some(res);
+        result.done.notify_all();
+        handle.notify().ok();
+    };
+    let (runnable, task) = async_task::spawn(future, schedule);
+    runnable.schedule();
+    JoinHandle {
+        slot,
+        task: Some(task),
+        wait: Box::pin(async move { event.wait().await }),
+    }
+}
+
+/// The handle of a future spawned by [`spawn_send`], which should be awaited
+/// in the runtime it is spawned, or joined by [`JoinHandle::join_blocking`]
+/// outside of the runtimes. It resumes the panic of the future, if any.
+///
+/// Dropping the handle cancels the future. The future is dropped on the
+/// thread dropping the handle, unless it is being polled, in which case it is
+/// dropped by the pool after the poll.
+#[must_use = "dropping the handle cancels the future"]
+pub struct JoinHandle<T> {
+    slot: Arc<Slot<T>>,
+    task: Option<Task<()>>,
+    wait: Pin<Box<dyn Future<Output = io::Result<()>>>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Let the future run to the end without waiting for it.
+    pub fn detach(mut self) {
+        if let Some(task) = self.task.take() {
+            task.detach();
+        }
+    }
+
+    /// Whether the future has completed.
+    pub fn is_finished(&self) -> bool {
+        self.slot.output.lock().unwrap().is_some()
+    }
+
+    /// Block current thread until the future completes, and return its
+    /// output. It is for the threads not running a runtime, e.g., to call the
+    /// async code from a synchronous callback.
+    ///
+    /// ```
+    /// use compio::task::bridge::spawn_send;
+    ///
+    /// let handle = spawn_send(async { 42 });
+    /// assert_eq!(handle.join_blocking(), 42);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// It panics if called inside [`block_on`], because blocking the thread
+    /// stalls all the tasks of the runtime. Await the handle there instead.
+    ///
+    /// [`block_on`]: crate::task::block_on
+    pub fn join_blocking(mut self) -> T {
+        if let Some(location) = running_block_on() {
+            panic!(
+                "cannot call `join_blocking` in the `block_on` at {location}, which blocks the \
+                 runtime of current thread; await the handle instead"
+            );
+        }
+        let mut output = self.slot.output.lock().unwrap();
+        let res = loop {
+            match output.take() {
+                Some(res) => break res,
+                None => output = self.slot.done.wait(output).unwrap(),
+            }
+        };
+        drop(output);
+        self.task = None;
+        match res {
+            Ok(output) => output,
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = self.slot.output.lock().unwrap().take();
+        let res = match res {
+            Some(res) => res,
+            None => {
+                match self.wait.as_mut().poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(res) => res.expect("cannot wait for the bridged future"),
+                }
+                // The event is notified after the output is stored.
+                self.slot
+                    .output
+                    .lock()
+                    .unwrap()
+                    .take()
+                    .expect("the bridged future should have completed")
+            }
+        };
+        self.task = None;
+        match res {
+            Ok(output) => Poll::Ready(output),
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+}
+
+impl<T> Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JoinHandle")
+            .field("finished", &self.is_finished())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Run a blocking function in a future spawned by [`spawn_send`].
+///
+/// The runtime is single-threaded, and there is no other worker to take over
+/// the tasks of current thread, so a blocking function in a compio task
+/// stalls all the tasks and the driver. Running the driver reentrantly is not
+/// an option either, because the task calling it is being polled by the
+/// driver. Therefore it only runs `f` on the threads of the pool polling the
+/// bridged futures, where the other bridged futures are polled by the other
+/// threads. Elsewhere, e.g., in a compio task, it fails with
+/// [`io::ErrorKind::Unsupported`] without calling `f`; spawn `f` with
+/// [`spawn_send`] or [`BlockingBufOp`](crate::op::BlockingBufOp) there.
+///
+/// ```
+/// use compio::task::bridge::{block_in_place, spawn_send};
+///
+/// compio::task::block_on(async {
+///     assert!(block_in_place(|| ()).is_err());
+///
+///     let handle = spawn_send(async { block_in_place(|| 42) });
+///     assert_eq!(handle.await.unwrap(), 42);
+/// })
+/// ```
+pub fn block_in_place<R>(f: impl FnOnce() -> R) -> io::Result<R> {
+    if ON_BRIDGE.get() {
+        Ok(f())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "`block_in_place` is only supported in the futures spawned by `spawn_send`",
+        ))
+    }
+}
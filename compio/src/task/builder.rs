@@ -0,0 +1,118 @@
+use std::io;
+
+#[cfg(all(target_os = "windows", feature = "fault-injection"))]
+use super::SeededSequencer;
+use crate::driver::ProactorBuilder;
+
+/// Builder of the runtime of current thread, see [`init_with`].
+///
+/// ```
+/// use compio::{driver::ProactorBuilder, task::RuntimeBuilder};
+///
+/// std::thread::spawn(|| {
+///     RuntimeBuilder::new()
+///         .proactor(ProactorBuilder::new().capacity(64))
+///         .init()
+///         .unwrap();
+///     compio::task::block_on(async {
+///         // The runtime submits at most 64 operations at once.
+///     })
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+///
+/// [`init_with`]: crate::task::init_with
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeBuilder {
+    proactor: ProactorBuilder,
+    seed: Option<u64>,
+}
+
+impl RuntimeBuilder {
+    /// Create the builder with the default driver.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the driver with `builder`.
+    pub fn proactor(mut self, builder: ProactorBuilder) -> Self {
+        self.proactor = builder;
+        self
+    }
+
+    /// Make the nondeterministic choices of the runtime from `seed`, to
+    /// replay the interleaving of a bug. The seed is overridden by the
+    /// environment variable [`SEED_ENV`] if it is set, so that the seed
+    /// printed by a failed CI run could be replayed locally, see
+    /// [`RuntimeBuilder::seed`].
+    ///
+    /// The order of the runnable tasks, the delivery order of the completions
+    /// of a poll, and the firing order of the timers with the same deadline
+    /// are chosen by a [`SeededSequencer`]. The kernel drivers can't control
+    /// when the operations complete, so the mode requires the fault-injection
+    /// backend, which also injects `WSAEWOULDBLOCK` into one in 8 socket
+    /// receives and sends from the seed. They are performed again after a
+    /// backoff with the default `ProactorBuilder::transient_errors`, so the
+    /// latencies follow the seed, too.
+    ///
+    /// ## Platform specific
+    /// * Windows: requires the feature `fault-injection`.
+    /// * Others: [`init`] fails with [`io::ErrorKind::Unsupported`].
+    ///
+    /// ```
+    /// use compio::task::RuntimeBuilder;
+    ///
+    /// std::thread::spawn(|| {
+    ///     let builder = RuntimeBuilder::new().deterministic(42);
+    ///     match builder.init() {
+    ///         Ok(()) => println!("replay with COMPIO_SEED={}", builder.seed().unwrap()),
+    ///         Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::Unsupported),
+    ///     }
+    /// })
+    /// .join()
+    /// .unwrap();
+    /// ```
+    ///
+    /// [`SEED_ENV`]: crate::task::SEED_ENV
+    /// [`SeededSequencer`]: crate::task::SeededSequencer
+    /// [`init`]: RuntimeBuilder::init
+    ///
+    /// # Panics
+    ///
+    /// It panics if the environment variable is not a `u64`.
+    pub fn deterministic(mut self, seed: u64) -> Self {
+        self.seed = Some(super::sequencer::env_seed().unwrap_or(seed));
+        self
+    }
+
+    /// The seed of the deterministic mode, which should be printed when a test
+    /// fails, or `None` if the mode is not enabled.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Create the runtime of current thread. It fails like [`init_with`], or
+    /// with [`io::ErrorKind::Unsupported`] if the deterministic mode is not
+    /// available.
+    ///
+    /// [`init_with`]: crate::task::init_with
+    pub fn init(&self) -> io::Result<()> {
+        #[cfg(not(all(target_os = "windows", feature = "fault-injection")))]
+        if self.seed.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the deterministic mode requires the fault-injection backend",
+            ));
+        }
+        super::init_with(&self.proactor)?;
+        #[cfg(all(target_os = "windows", feature = "fault-injection"))]
+        if let Some(seed) = self.seed {
+            super::set_sequencer(SeededSequencer::new(seed));
+            // Another stream than the one of the sequencer.
+            let mut faults = SeededSequencer::new(!seed);
+            crate::driver::inject_seeded_errors(move || faults.next());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_util::Stream;
+
+use crate::task::RUNTIME;
+
+// Waits until the driver is polled after it is created.
+#[derive(Debug)]
+struct NextTick {
+    generation: u64,
+}
+
+impl NextTick {
+    fn new() -> Self {
+        Self {
+            generation: RUNTIME.with(|runtime| runtime.generation()),
+        }
+    }
+}
+
+impl Future for NextTick {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        RUNTIME.with(|runtime| runtime.poll_next_tick(cx, self.generation))
+    }
+}
+
+/// Wait until the driver is polled once, so that the completed IO and the
+/// expired timers wake their tasks, without blocking if nothing is completed.
+/// The task runs again after the tasks woken by that poll.
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use compio::net::UdpSocket;
+///
+/// compio::task::block_on(async {
+///     let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     socket.connect(socket.local_addr().unwrap()).unwrap();
+///     let recv = compio::task::spawn(async move { socket.recv(Vec::with_capacity(4)).await });
+///     // Let the spawned task submit the recv.
+///     compio::task::poll_driver_once().await;
+///     assert!(!recv.is_finished());
+/// })
+/// ```
+pub fn poll_driver_once() -> impl Future<Output = ()> {
+    NextTick::new()
+}
+
+/// Yield to the other tasks and the driver, e.g., in a long CPU-bound
+/// computation. The task is put to the back of the run queue, and it is
+/// guaranteed that the driver is polled before the task runs again, unlike a
+/// self-wake, which may run the task again before any IO progresses.
+///
+/// It is the same as [`poll_driver_once`].
+///
+/// ```
+/// compio::task::block_on(async {
+///     let mut sum = 0u64;
+///     for i in 0..1_000_000 {
+///         sum += i;
+///         if i % 10_000 == 0 {
+///             compio::task::yield_now().await;
+///         }
+///     }
+///     assert_eq!(sum, 499999500000);
+/// })
+/// ```
+pub fn yield_now() -> impl Future<Output = ()> {
+    NextTick::new()
+}
+
+/// Wrap an iterator into a [`Stream`], which yields with [`yield_now`] after
+/// every `every` items. The CPU-bound work on each item is interleaved with
+/// the other tasks and the driver.
+///
+/// # Panics
+///
+/// It panics if `every` is zero.
+///
+/// ```
+/// use futures_util::StreamExt;
+///
+/// compio::task::block_on(async {
+///     let data = vec![1u8; 1 << 20];
+///     let mut chunks = compio::task::cooperative(data.chunks(4096), 16);
+///     let mut checksum = 0u32;
+///     while let Some(chunk) = chunks.next().await {
+///         checksum = chunk
+///             .iter()
+///             .fold(checksum, |sum, b| sum.wrapping_add(*b as u32));
+///     }
+///     assert_eq!(checksum, 1 << 20);
+/// })
+/// ```
+pub fn cooperative<I: IntoIterator>(iter: I, every: usize) -> Cooperative<I::IntoIter> {
+    assert!(every > 0, "`every` should not be zero");
+    Cooperative {
+        iter: iter.into_iter(),
+        every,
+        count: 0,
+        tick: None,
+    }
+}
+
+/// The stream returned by [`cooperative`].
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Cooperative<I> {
+    iter: I,
+    every: usize,
+    count: usize,
+    tick: Option<NextTick>,
+}
+
+impl<I: Iterator + Unpin> Stream for Cooperative<I> {
+    type Item = I::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<I::Item>> {
+        if self.count == self.every {
+            let tick = self.tick.get_or_insert_with(NextTick::new);
+            if Pin::new(tick).poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.tick = None;
+            self.count = 0;
+        }
+        self.count += 1;
+        Poll::Ready(self.iter.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
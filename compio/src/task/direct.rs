@@ -0,0 +1,69 @@
+use std::task::{Context, Poll, Waker};
+
+/// The occupancy of the file table for the direct descriptors of the runtime,
+/// see [`ProactorBuilder::direct_table_size`].
+///
+/// [`ProactorBuilder::direct_table_size`]: crate::driver::ProactorBuilder::direct_table_size
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirectSlotStats {
+    /// The slots of the table.
+    pub capacity: u32,
+    /// The slots used now, including the ones of the sockets being closed.
+    pub used: u32,
+    /// The most slots used at the same time.
+    pub peak: u32,
+    /// The count of the sockets waiting for a free slot because the table
+    /// was full.
+    pub waits: u64,
+}
+
+// The slots reserved by the direct sockets. They are counted before the kernel
+// allocates them, so that the kernel never runs out of slots, and the sockets
+// wait for a free one instead.
+#[derive(Default)]
+pub(crate) struct DirectSlots {
+    used: u32,
+    peak: u32,
+    waits: u64,
+    waiters: Vec<Waker>,
+}
+
+impl DirectSlots {
+    // Reserve a slot, or wait until one is released. `waited` is set when it
+    // starts waiting, so that each wait is counted once.
+    pub fn poll_reserve(
+        &mut self,
+        capacity: u32,
+        cx: &mut Context<'_>,
+        waited: &mut bool,
+    ) -> Poll<()> {
+        if self.used < capacity {
+            self.used += 1;
+            self.peak = self.peak.max(self.used);
+            Poll::Ready(())
+        } else {
+            if !*waited {
+                *waited = true;
+                self.waits += 1;
+            }
+            self.waiters.push(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    // Release a slot, and give back the waiters to wake. All of them are
+    // woken, since some may have been cancelled.
+    pub fn release(&mut self) -> Vec<Waker> {
+        self.used -= 1;
+        std::mem::take(&mut self.waiters)
+    }
+
+    pub fn stats(&self, capacity: u32) -> DirectSlotStats {
+        DirectSlotStats {
+            capacity,
+            used: self.used,
+            peak: self.peak,
+            waits: self.waits,
+        }
+    }
+}
@@ -0,0 +1,504 @@
+//! A feed of the lifecycle events of the tasks and the operations of the
+//! runtime, to observe a live process from outside, e.g., with the
+//! `compio-top` example.
+//!
+//! [`serve`] publishes the events to the clients connected to a Unix socket.
+//! Each client gets [`Event::Hello`] with the version of the protocol, the
+//! tasks alive when it connects, and then the events as they happen. The
+//! events are recorded only when a client is connected, and each client has a
+//! bounded queue: the events are dropped if it can't keep up, which is
+//! reported by [`Event::Dropped`].
+//!
+//! Each event is framed by its length as `u32` in little endian, see
+//! [`Event::encode`] and [`read_event`].
+//!
+//! ```no_run
+//! compio::task::block_on(async {
+//!     compio::task::spawn(compio::task::instrument::serve("app.sock")).detach();
+//!     // Run the app.
+//! })
+//! ```
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashSet, VecDeque},
+    future::poll_fn,
+    io,
+    panic::Location,
+    path::Path,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    io::AsyncRead,
+    net::{UnixListener, UnixStream},
+    task::{spawn_with_priority, Priority, RUNTIME},
+};
+
+/// The version of the protocol, sent in [`Event::Hello`].
+pub const VERSION: u32 = 1;
+
+// The events queued for a client at most.
+const QUEUE_CAPACITY: usize = 4096;
+
+// The largest frame accepted by `read_event`.
+const MAX_FRAME: usize = 64 * 1024;
+
+/// An event of the runtime. The ids of the tasks and the operations are
+/// reused after they are completed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// The first event to a client.
+    Hello {
+        /// The version of the protocol.
+        version: u32,
+    },
+    /// A task is spawned, or alive when the client connects.
+    TaskSpawned {
+        /// The id of the task.
+        id: u64,
+        /// Where the task is spawned.
+        location: String,
+    },
+    /// A task is polled.
+    TaskPolled {
+        /// The id of the task.
+        id: u64,
+        /// How long the poll took.
+        busy: Duration,
+    },
+    /// A task is woken, and queued to be polled.
+    TaskWoken {
+        /// The id of the task.
+        id: u64,
+    },
+    /// A task is completed, or dropped before that.
+    TaskCompleted {
+        /// The id of the task.
+        id: u64,
+    },
+    /// An operation is submitted to the driver.
+    OpSubmitted {
+        /// The id of the operation.
+        id: u64,
+        /// The task submitting it, if any.
+        task: Option<u64>,
+        /// The type name of the operation.
+        name: String,
+    },
+    /// An operation is given back by the driver.
+    OpCompleted {
+        /// The id of the operation.
+        id: u64,
+        /// Whether it succeeded.
+        ok: bool,
+    },
+    /// An operation is cancelled, and waits for the driver to give it back.
+    OpCancelled {
+        /// The id of the operation.
+        id: u64,
+    },
+    /// The events dropped since the last event, because the client didn't
+    /// keep up.
+    Dropped {
+        /// The count of the events dropped.
+        count: u64,
+    },
+}
+
+impl Event {
+    /// Append the event to `buf`, framed by its length.
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        let start = buf.len();
+        buf.extend_from_slice(&[0; 4]);
+        let u64 = |buf: &mut Vec<u8>, value: u64| buf.extend_from_slice(&value.to_le_bytes());
+        match self {
+            Self::Hello { version } => {
+                buf.push(0);
+                buf.extend_from_slice(&version.to_le_bytes());
+            }
+            Self::TaskSpawned { id, location } => {
+                buf.push(1);
+                u64(buf, *id);
+                encode_str(buf, location);
+            }
+            Self::TaskPolled { id, busy } => {
+                buf.push(2);
+                u64(buf, *id);
+                u64(buf, busy.as_nanos().try_into().unwrap_or(u64::MAX));
+            }
+            Self::TaskWoken { id } => {
+                buf.push(3);
+                u64(buf, *id);
+            }
+            Self::TaskCompleted { id } => {
+                buf.push(4);
+                u64(buf, *id);
+            }
+            Self::OpSubmitted { id, task, name } => {
+                buf.push(5);
+                u64(buf, *id);
+                match task {
+                    Some(task) => {
+                        buf.push(1);
+                        u64(buf, *task);
+                    }
+                    None => buf.push(0),
+                }
+                encode_str(buf, name);
+            }
+            Self::OpCompleted { id, ok } => {
+                buf.push(6);
+                u64(buf, *id);
+                buf.push(*ok as u8);
+            }
+            Self::OpCancelled { id } => {
+                buf.push(7);
+                u64(buf, *id);
+            }
+            Self::Dropped { count } => {
+                buf.push(8);
+                u64(buf, *count);
+            }
+        }
+        let len = (buf.len() - start - 4) as u32;
+        buf[start..start + 4].copy_from_slice(&len.to_le_bytes());
+    }
+
+    /// Decode an event from a frame without the length.
+    ///
+    /// It fails with [`io::ErrorKind::InvalidData`] if the frame is malformed,
+    /// or of an unknown event.
+    pub fn decode(frame: &[u8]) -> io::Result<Self> {
+        let mut decoder = Decoder(frame);
+        let event = match decoder.u8()? {
+            0 => Self::Hello {
+                version: u32::from_le_bytes(decoder.take()?),
+            },
+            1 => Self::TaskSpawned {
+                id: decoder.u64()?,
+                location: decoder.string()?,
+            },
+            2 => Self::TaskPolled {
+                id: decoder.u64()?,
+                busy: Duration::from_nanos(decoder.u64()?),
+            },
+            3 => Self::TaskWoken { id: decoder.u64()? },
+            4 => Self::TaskCompleted { id: decoder.u64()? },
+            5 => Self::OpSubmitted {
+                id: decoder.u64()?,
+                task: match decoder.u8()? {
+                    0 => None,
+                    1 => Some(decoder.u64()?),
+                    _ => return Err(invalid_frame()),
+                },
+                name: decoder.string()?,
+            },
+            6 => Self::OpCompleted {
+                id: decoder.u64()?,
+                ok: decoder.u8()? != 0,
+            },
+            7 => Self::OpCancelled { id: decoder.u64()? },
+            8 => Self::Dropped {
+                count: decoder.u64()?,
+            },
+            _ => return Err(invalid_frame()),
+        };
+        if !decoder.0.is_empty() {
+            return Err(invalid_frame());
+        }
+        Ok(event)
+    }
+}
+
+fn encode_str(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn invalid_frame() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed event")
+}
+
+struct Decoder<'a>(&'a [u8]);
+
+impl Decoder<'_> {
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let (chunk, rest) = self.0.split_first_chunk::<N>().ok_or_else(invalid_frame)?;
+        self.0 = rest;
+        Ok(*chunk)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = u32::from_le_bytes(self.take()?) as usize;
+        if self.0.len() < len {
+            return Err(invalid_frame());
+        }
+        let (s, rest) = self.0.split_at(len);
+        self.0 = rest;
+        String::from_utf8(s.to_vec()).map_err(|_| invalid_frame())
+    }
+}
+
+/// Read the next event from a stream of the frames, or `None` on EOF before
+/// a frame. Wrap the stream in a [`BufReader`](crate::io::BufReader) to read
+/// the small frames efficiently.
+pub async fn read_event<R: AsyncRead + ?Sized>(reader: &mut R) -> io::Result<Option<Event>> {
+    let (res, len) = reader.read_exact(Vec::with_capacity(4)).await;
+    match res {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && len.is_empty() => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_le_bytes(len[..].try_into().unwrap()) as usize;
+    if len > MAX_FRAME {
+        return Err(invalid_frame());
+    }
+    let (res, frame) = reader.read_exact(Vec::with_capacity(len)).await;
+    res?;
+    Event::decode(&frame).map(Some)
+}
+
+/// Publish the events to the clients connecting to the Unix socket at `path`.
+/// It runs until accepting a client fails, so it should be spawned.
+///
+/// The clients are served by the tasks of [`Priority::Low`]. The tasks of the
+/// feed and their operations are not published, so that they don't feed
+/// themselves.
+pub async fn serve(path: impl AsRef<Path>) -> io::Result<()> {
+    RUNTIME.with(|runtime| runtime.instrument(Instrument::mute_current));
+    let listener = UnixListener::bind(path)?;
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let subscriber = RUNTIME.with(|runtime| runtime.subscribe());
+        spawn_with_priority(feed(stream, subscriber), Priority::Low).detach();
+    }
+}
+
+async fn feed(stream: UnixStream, subscriber: Rc<Subscriber>) -> io::Result<()> {
+    let _guard = Unsubscribe(subscriber.clone());
+    let mut buf = Vec::new();
+    loop {
+        poll_fn(|cx| subscriber.poll_ready(cx)).await;
+        subscriber.encode(&mut buf);
+        let (res, written) = stream.send_all(buf).await;
+        buf = written;
+        res?;
+        buf.clear();
+    }
+}
+
+struct Unsubscribe(Rc<Subscriber>);
+
+impl Drop for Unsubscribe {
+    fn drop(&mut self) {
+        RUNTIME
+            .try_with(|runtime| runtime.instrument(|i| i.unsubscribe(&self.0)))
+            .ok();
+    }
+}
+
+// The events queued for a client.
+pub(crate) struct Subscriber {
+    events: RefCell<VecDeque<Event>>,
+    dropped: Cell<u64>,
+    waker: Cell<Option<Waker>>,
+}
+
+impl Subscriber {
+    fn new() -> Self {
+        Self {
+            events: RefCell::default(),
+            dropped: Cell::new(0),
+            waker: Cell::new(None),
+        }
+    }
+
+    // Queue the event, and return the waker of the client to wake.
+    fn push(&self, event: Event) -> Option<Waker> {
+        let mut events = self.events.borrow_mut();
+        if events.len() < QUEUE_CAPACITY {
+            events.push_back(event);
+        } else {
+            self.dropped.set(self.dropped.get() + 1);
+        }
+        self.waker.take()
+    }
+
+    fn poll_ready(&self, cx: &mut Context) -> Poll<()> {
+        if self.events.borrow().is_empty() && self.dropped.get() == 0 {
+            self.waker.set(Some(cx.waker().clone()));
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+
+    // Encode the queued events into `buf`, after the count of the dropped ones.
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let count = self.dropped.replace(0);
+        if count > 0 {
+            Event::Dropped { count }.encode(buf);
+        }
+        for event in self.events.borrow_mut().drain(..) {
+            event.encode(buf);
+        }
+    }
+}
+
+// The state of the instrumentation in the runtime.
+#[derive(Default)]
+pub(crate) struct Instrument {
+    subscribers: Vec<Rc<Subscriber>>,
+    // The tasks of the feed, and their operations.
+    muted_tasks: HashSet<usize>,
+    muted_ops: HashSet<usize>,
+    // The task being polled.
+    current: Option<usize>,
+    // The clients to wake after the state is released, because waking them
+    // publishes the events again.
+    wakers: Vec<Waker>,
+}
+
+impl Instrument {
+    fn is_active(&self) -> bool {
+        !self.subscribers.is_empty()
+    }
+
+    fn is_muted(&self, task: usize) -> bool {
+        !self.muted_tasks.is_empty() && self.muted_tasks.contains(&task)
+    }
+
+    fn current_muted(&self) -> bool {
+        self.current.is_some_and(|task| self.is_muted(task))
+    }
+
+    fn publish(&mut self, event: Event) {
+        for subscriber in &self.subscribers {
+            if let Some(waker) = subscriber.push(event.clone()) {
+                self.wakers.push(waker);
+            }
+        }
+    }
+
+    pub fn take_wakers(&mut self) -> Option<Vec<Waker>> {
+        (!self.wakers.is_empty()).then(|| std::mem::take(&mut self.wakers))
+    }
+
+    pub fn restore_wakers(&mut self, wakers: Vec<Waker>) {
+        if self.wakers.is_empty() {
+            self.wakers = wakers;
+        }
+    }
+
+    pub fn task_spawned(&mut self, id: usize, location: &'static Location<'static>) {
+        if self.current_muted() {
+            self.muted_tasks.insert(id);
+        } else if self.is_active() {
+            self.publish(Event::TaskSpawned {
+                id: id as _,
+                location: location.to_string(),
+            });
+        }
+    }
+
+    pub fn task_woken(&mut self, id: usize) {
+        if self.is_active() && !self.is_muted(id) {
+            self.publish(Event::TaskWoken { id: id as _ });
+        }
+    }
+
+    // Returns when the poll starts if it is published.
+    pub fn poll_started(&mut self, id: usize) -> Option<Instant> {
+        self.current = Some(id);
+        (self.is_active() && !self.is_muted(id)).then(Instant::now)
+    }
+
+    pub fn poll_ended(&mut self, id: usize, started: Option<Instant>) {
+        self.current = None;
+        if let Some(started) = started {
+            if self.is_active() {
+                self.publish(Event::TaskPolled {
+                    id: id as _,
+                    busy: started.elapsed(),
+                });
+            }
+        }
+    }
+
+    pub fn task_dropped(&mut self, id: usize) {
+        if !self.muted_tasks.is_empty() && self.muted_tasks.remove(&id) {
+            return;
+        }
+        if self.is_active() {
+            self.publish(Event::TaskCompleted { id: id as _ });
+        }
+    }
+
+    pub fn op_submitted(&mut self, id: usize, name: &'static str) {
+        if self.current_muted() {
+            self.muted_ops.insert(id);
+        } else if self.is_active() {
+            self.publish(Event::OpSubmitted {
+                id: id as _,
+                task: self.current.map(|task| task as _),
+                name: name.to_string(),
+            });
+        }
+    }
+
+    pub fn op_completed(&mut self, id: usize, ok: bool) {
+        if !self.muted_ops.is_empty() && self.muted_ops.remove(&id) {
+            return;
+        }
+        if self.is_active() {
+            self.publish(Event::OpCompleted { id: id as _, ok });
+        }
+    }
+
+    pub fn op_cancelled(&mut self, id: usize) {
+        if self.is_active() && !self.muted_ops.contains(&id) {
+            self.publish(Event::OpCancelled { id: id as _ });
+        }
+    }
+
+    pub fn mute_current(&mut self) {
+        if let Some(task) = self.current {
+            self.muted_tasks.insert(task);
+        }
+    }
+
+    // Subscribe a client, with the tasks alive.
+    pub fn subscribe<'a>(
+        &mut self,
+        tasks: impl Iterator<Item = (usize, &'a Location<'static>)>,
+    ) -> Rc<Subscriber> {
+        let subscriber = Rc::new(Subscriber::new());
+        subscriber.push(Event::Hello { version: VERSION });
+        for (id, location) in tasks {
+            if !self.is_muted(id) {
+                subscriber.push(Event::TaskSpawned {
+                    id: id as _,
+                    location: location.to_string(),
+                });
+            }
+        }
+        self.subscribers.push(subscriber.clone());
+        subscriber
+    }
+
+    pub fn unsubscribe(&mut self, subscriber: &Rc<Subscriber>) {
+        self.subscribers.retain(|s| !Rc::ptr_eq(s, subscriber));
+    }
+}
@@ -0,0 +1,79 @@
+use std::{
+    io,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_util::Stream;
+
+use crate::{
+    driver::MessageSender,
+    op::MsgRing,
+    task::{submit, RUNTIME},
+};
+
+/// A handle to the runtime of a thread, which could be sent to other threads
+/// to post messages to it.
+#[derive(Debug, Clone)]
+pub struct RuntimeHandle {
+    sender: MessageSender,
+}
+
+impl RuntimeHandle {
+    /// Get the handle of the runtime of current thread.
+    pub fn current() -> io::Result<Self> {
+        Ok(Self {
+            sender: RUNTIME.with(|runtime| runtime.message_sender())?,
+        })
+    }
+
+    /// Post a message to the runtime, which is received from [`messages`] in
+    /// that thread. It is submitted through the runtime of current thread. See
+    /// [`MsgRing`].
+    pub async fn send_msg(&self, msg: u64) -> io::Result<()> {
+        let op = MsgRing::new(self.sender.clone(), msg);
+        submit(op).await.0?;
+        Ok(())
+    }
+}
+
+/// The stream of the messages posted by [`RuntimeHandle::send_msg`] to the
+/// runtime of current thread. It never ends.
+///
+/// Only one task should poll the messages at a time.
+///
+/// ```
+/// use compio::task::{messages, RuntimeHandle};
+/// use futures_util::StreamExt;
+///
+/// let (tx, rx) = std::sync::mpsc::channel();
+/// let thread = std::thread::spawn(move || {
+///     compio::task::block_on(async {
+///         tx.send(RuntimeHandle::current().unwrap()).unwrap();
+///         messages().next().await.unwrap()
+///     })
+/// });
+///
+/// let handle = rx.recv().unwrap();
+/// compio::task::block_on(handle.send_msg(42)).unwrap();
+/// assert_eq!(thread.join().unwrap(), 42);
+/// ```
+pub fn messages() -> Messages {
+    Messages { _p: PhantomData }
+}
+
+/// The stream returned by [`messages`].
+#[derive(Debug)]
+pub struct Messages {
+    // The messages belong to the runtime of current thread.
+    _p: PhantomData<*const ()>,
+}
+
+impl Stream for Messages {
+    type Item = u64;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        RUNTIME.with(|runtime| runtime.poll_message(cx)).map(Some)
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt::Display;
+
+// The buckets in each power of two, which bounds the relative error of the
+// recorded values to 1/16.
+const SUB_BUCKET_BITS: u32 = 4;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+
+/// A histogram of `u64` values with buckets growing exponentially, in the
+/// style of HDR histograms. The values below 32 are exact, and the larger
+/// ones are recorded with the relative error of at most 1/16.
+///
+/// ```
+/// use compio::task::Histogram;
+///
+/// let mut histogram = Histogram::new();
+/// for value in 1..=100 {
+///     histogram.record(value);
+/// }
+/// assert_eq!(histogram.count(), 100);
+/// assert_eq!(histogram.value_at_quantile(0.1), 10);
+/// // Within the bucket of 96 to 99.
+/// assert_eq!(histogram.value_at_quantile(0.99), 99);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    counts: Vec<u64>,
+    count: u64,
+    sum: u128,
+    min: u64,
+    max: u64,
+}
+
+impl Histogram {
+    /// Create an empty histogram.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a value.
+    pub fn record(&mut self, value: u64) {
+        let index = bucket_index(value);
+        if self.counts.len() <= index {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += 1;
+        if self.count == 0 || value < self.min {
+            self.min = value;
+        }
+        self.max = self.max.max(value);
+        self.count += 1;
+        self.sum += value as u128;
+    }
+
+    /// The count of the recorded values.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The smallest recorded value, or 0 if it is empty.
+    pub fn min(&self) -> u64 {
+        self.min
+    }
+
+    /// The largest recorded value, or 0 if it is empty.
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// The mean of the recorded values, or 0 if it is empty.
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum as f64 / self.count as f64
+        }
+    }
+
+    /// The value which the `quantile` of the recorded values are less than
+    /// or equal to, i.e., the upper bound of the bucket, but not larger than
+    /// [`Histogram::max`]. `quantile` is clamped to `0.0..=1.0`.
+    pub fn value_at_quantile(&self, quantile: f64) -> u64 {
+        let rank = (quantile.clamp(0.0, 1.0) * self.count as f64)
+            .ceil()
+            .max(1.0) as u64;
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return bucket_range(index).1.min(self.max);
+            }
+        }
+        self.max
+    }
+
+    /// The non-empty buckets, as the inclusive lower and upper bounds, and
+    /// the count of the values in each.
+    pub fn buckets(&self) -> impl Iterator<Item = (u64, u64, u64)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(index, count)| {
+                let (low, high) = bucket_range(index);
+                (low, high, *count)
+            })
+    }
+
+    /// Clear the recorded values.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl Display for Histogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "count={} min={} mean={:.1} p50={} p90={} p99={} p999={} max={}",
+            self.count,
+            self.min,
+            self.mean(),
+            self.value_at_quantile(0.5),
+            self.value_at_quantile(0.9),
+            self.value_at_quantile(0.99),
+            self.value_at_quantile(0.999),
+            self.max
+        )
+    }
+}
+
+// The values below `2 * SUB_BUCKETS` are exact. Above, each power of two is
+// split into `SUB_BUCKETS` buckets.
+fn bucket_index(value: u64) -> usize {
+    if value < 2 * SUB_BUCKETS {
+        return value as usize;
+    }
+    let shift = 63 - value.leading_zeros() - SUB_BUCKET_BITS;
+    ((shift as u64 + 1) * SUB_BUCKETS + ((value >> shift) - SUB_BUCKETS)) as usize
+}
+
+fn bucket_range(index: usize) -> (u64, u64) {
+    let index = index as u64;
+    if index < 2 * SUB_BUCKETS {
+        return (index, index);
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let low = (SUB_BUCKETS + index % SUB_BUCKETS) << shift;
+    (low, low + ((1 << shift) - 1))
+}
+
+/// The latency metrics of the runtime, enabled by
+/// [`ProactorBuilder::latency_metrics`], see [`latency_metrics`].
+///
+/// [`ProactorBuilder::latency_metrics`]: crate::driver::ProactorBuilder::latency_metrics
+/// [`latency_metrics`]: crate::task::latency_metrics
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LatencyMetrics {
+    /// The delay in nanoseconds from the driver giving back a completed
+    /// operation to its task taking the result, i.e., the time the completion
+    /// sits in the runtime while other tasks are polled.
+    pub completion_delay: Histogram,
+    /// The count of the task polls between two polls of the driver.
+    pub task_polls: Histogram,
+}
+
+/// The depths of the run queues, see [`run_queue_depths`].
+///
+/// [`run_queue_depths`]: crate::task::run_queue_depths
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RunQueueDepths {
+    /// The tasks of [`Priority::High`](crate::task::Priority::High).
+    pub high: usize,
+    /// The tasks of [`Priority::Normal`](crate::task::Priority::Normal).
+    pub normal: usize,
+    /// The tasks of [`Priority::Low`](crate::task::Priority::Low).
+    pub low: usize,
+}
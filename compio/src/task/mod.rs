@@ -13,25 +13,102 @@
 pub(crate) mod runtime;
 use runtime::Runtime;
 
+#[cfg(feature = "event")]
+pub mod bridge;
+mod builder;
+pub use builder::*;
+
+mod coop;
+pub use coop::*;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod direct;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub use direct::DirectSlotStats;
+#[cfg(feature = "instrumentation")]
+pub mod instrument;
+mod message;
+pub use message::*;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "metrics")]
+pub use metrics::*;
 pub(crate) mod op;
+mod priority;
+pub use priority::Priority;
+#[cfg(unix)]
+mod registration;
+#[cfg(unix)]
+pub use registration::*;
+mod scope;
+pub use scope::*;
+mod semaphore;
+pub use semaphore::*;
+mod sequencer;
+pub use sequencer::*;
+mod set;
+pub use set::*;
+mod stall;
+pub use stall::*;
 #[cfg(feature = "time")]
 pub(crate) mod time;
 
-use std::{future::Future, io};
+use std::{cell::RefCell, future::Future, io, panic::Location, time::Duration};
 
 use async_task::Task;
 
 use crate::{
-    driver::{OpCode, RawFd},
+    driver::{OpCode, OpPoolStats, PollStats, Proactor, ProactorBuilder, RawFd},
     BufResult,
 };
 
 thread_local! {
-    pub(crate) static RUNTIME: Runtime = Runtime::new().expect("cannot create compio runtime");
+    // The driver built by `init_with`, taken by the runtime when created.
+    static DRIVER: RefCell<Option<Proactor>> = const { RefCell::new(None) };
+
+    pub(crate) static RUNTIME: Runtime =
+        Runtime::new(DRIVER.take()).expect("cannot create compio runtime");
+}
+
+/// Create the runtime of current thread with the driver built by `builder`,
+/// e.g., to set the capacity of the queues. Otherwise, the runtime is created
+/// with the default driver on the first use.
+///
+/// It fails with [`io::ErrorKind::AlreadyExists`] if the runtime has been
+/// created, i.e., any function of the runtime has been called on this thread.
+///
+/// ```
+/// use compio::driver::ProactorBuilder;
+///
+/// std::thread::spawn(|| {
+///     compio::task::init_with(&ProactorBuilder::new().capacity(64)).unwrap();
+///     compio::task::block_on(async {
+///         // The runtime submits at most 64 operations at once.
+///     })
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+pub fn init_with(builder: &ProactorBuilder) -> io::Result<()> {
+    DRIVER.set(Some(builder.build()?));
+    RUNTIME.with(|_| {});
+    if DRIVER.take().is_some() {
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "the runtime of current thread has been created",
+        ))
+    } else {
+        Ok(())
+    }
 }
 
 /// Start a compio runtime and block on the future till it completes.
 ///
+/// # Panics
+///
+/// It panics if called when the runtime of current thread is running another
+/// `block_on` or [`scope_io`], e.g., in a spawned task. Spawn the future with
+/// [`spawn`], or await it directly there.
+///
 /// ```
 /// compio::task::block_on(async {
 ///     // Open a file
@@ -49,8 +126,10 @@ thread_local! {
 ///     println!("{:?}", &buf);
 /// })
 /// ```
+#[track_caller]
 pub fn block_on<F: Future>(future: F) -> F::Output {
-    RUNTIME.with(|runtime| runtime.block_on(future))
+    let location = Location::caller();
+    RUNTIME.with(|runtime| runtime.block_on(future, location))
 }
 
 /// Spawns a new asynchronous task, returning a [`Task`] for it.
@@ -68,21 +147,277 @@ pub fn block_on<F: Future>(future: F) -> F::Output {
 ///     assert_eq!(task.await, 42);
 /// })
 /// ```
+#[track_caller]
 pub fn spawn<F: Future + 'static>(future: F) -> Task<F::Output> {
-    RUNTIME.with(|runtime| runtime.spawn(future))
+    let location = Location::caller();
+    RUNTIME.with(|runtime| runtime.spawn(future, location, Priority::Normal))
+}
+
+/// Spawns a new asynchronous task with `priority`, returning a [`Task`] for
+/// it. See [`spawn`].
+///
+/// The tasks of a higher priority are polled first, and the driver is polled
+/// after a few low priority tasks, so that a flood of background work doesn't
+/// delay the completions of the latency-critical tasks. A lower priority is
+/// not starved, but polled after at most [`set_priority_ratio`] polls of the
+/// higher ones. The high and normal priority tasks waking themselves should
+/// still [`yield_now`] from time to time, to let the driver be polled.
+///
+/// ```
+/// use compio::task::Priority;
+///
+/// compio::task::block_on(async {
+///     let background = compio::task::spawn_with_priority(async { 1 }, Priority::Low);
+///     let request = compio::task::spawn_with_priority(async { 2 }, Priority::High);
+///     assert_eq!(background.await + request.await, 3);
+/// })
+/// ```
+#[track_caller]
+pub fn spawn_with_priority<F: Future + 'static>(
+    future: F,
+    priority: Priority,
+) -> Task<F::Output> {
+    let location = Location::caller();
+    RUNTIME.with(|runtime| runtime.spawn(future, location, priority))
+}
+
+/// Poll a task of a lower priority waiting in the queue at least after
+/// `ratio` polls of the higher ones, and poll the driver after `ratio` low
+/// priority tasks without others in between. It is 16 by default.
+///
+/// A smaller ratio is fairer to the lower priorities, and a larger one gives
+/// more turns to the higher priorities.
+///
+/// # Panics
+///
+/// It panics if `ratio` is zero.
+pub fn set_priority_ratio(ratio: u32) {
+    assert!(ratio > 0, "`ratio` should not be zero");
+    RUNTIME.with(|runtime| runtime.set_priority_ratio(ratio))
 }
 
 /// Attach a raw file descriptor/handle/socket to the runtime.
 ///
 /// You only need this when authoring your own high-level APIs. High-level
-/// resources in this crate are attached automatically.
+/// resources in this crate are attached automatically before their first
+/// operation.
+///
+/// It fails with [`io::ErrorKind::Other`] if the runtime of current thread is
+/// not running, i.e., it is not called in [`block_on`], e.g., under another
+/// executor, so the types attaching on creation fail there instead of their
+/// first operation.
 pub fn attach(fd: RawFd) -> io::Result<()> {
+    runtime::check_running()?;
     RUNTIME.with(|runtime| runtime.attach(fd))
 }
 
+/// Enable NAPI busy polling of the network devices in the runtime with
+/// `timeout`, or disable it with `None`. It lowers the receive latency by
+/// burning CPU, and is supposed to be enabled in the latency-critical phases.
+/// See [`ProactorBuilder::napi_busy_poll`] for the details.
+///
+/// [`ProactorBuilder::napi_busy_poll`]: crate::driver::ProactorBuilder::napi_busy_poll
+pub fn set_napi_busy_poll(
+    timeout: Option<std::time::Duration>,
+    prefer_busy_poll: bool,
+) -> io::Result<()> {
+    RUNTIME.with(|runtime| runtime.set_napi_busy_poll(timeout, prefer_busy_poll))
+}
+
+/// Watch the runtime for stalls, and call `callback` with a [`StallReport`]
+/// when it has waited for the driver longer than `threshold` without waking
+/// any task, while there are pending tasks and operations in flight. It
+/// replaces the previous detector.
+///
+/// The callback is called once for each stall, on the runtime thread. The
+/// runtime is not watched when it is idle, i.e., without operations in
+/// flight, even if it waits for a timer. However, it can't tell a deadlock
+/// from an operation legitimately waiting for long, e.g., accepting the
+/// connections, so `threshold` should be longer than the expected waiting
+/// time.
+///
+/// ```
+/// use std::{net::Ipv4Addr, time::Duration};
+///
+/// use compio::net::UdpSocket;
+///
+/// compio::task::set_stall_detector(Duration::from_secs(10), |report| {
+///     eprintln!("{report}");
+/// });
+/// compio::task::block_on(async {
+///     let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     let addr = socket.local_addr().unwrap();
+///     socket.send_to("ping", addr).await.0.unwrap();
+///     socket.recv(Vec::with_capacity(4)).await.0.unwrap();
+/// });
+/// compio::task::remove_stall_detector();
+/// ```
+pub fn set_stall_detector(threshold: Duration, callback: impl FnMut(&StallReport) + 'static) {
+    let detector = stall::StallDetector::new(threshold, Box::new(callback));
+    RUNTIME.with(|runtime| runtime.set_stall_detector(Some(detector)))
+}
+
+/// Stop watching the runtime for stalls, see [`set_stall_detector`].
+pub fn remove_stall_detector() {
+    RUNTIME.with(|runtime| runtime.set_stall_detector(None))
+}
+
+/// Make the ordering decisions of the runtime with `sequencer`, e.g., a
+/// [`SeededSequencer`] to reproduce a bug depending on the interleaving of
+/// the tasks. It replaces the previous sequencer.
+pub fn set_sequencer(sequencer: impl Sequencer + 'static) {
+    RUNTIME.with(|runtime| runtime.set_sequencer(Some(Box::new(sequencer))))
+}
+
+/// Restore the default order of the runtime, see [`Sequencer`].
+pub fn remove_sequencer() {
+    RUNTIME.with(|runtime| runtime.set_sequencer(None))
+}
+
+/// The statistics of the polls of the driver in the runtime, see
+/// [`Proactor::poll_stats`].
+pub fn poll_stats() -> PollStats {
+    RUNTIME.with(|runtime| runtime.poll_stats())
+}
+
+/// The most fds waited at once by the driver in the runtime, see
+/// [`Proactor::max_poll_fds`].
+pub fn max_poll_fds() -> usize {
+    RUNTIME.with(|runtime| runtime.max_poll_fds())
+}
+
+/// The statistics of the freelists of the operations in the runtime, see
+/// [`ProactorBuilder::op_pool_capacity`].
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use compio::net::UdpSocket;
+///
+/// compio::task::block_on(async {
+///     let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///     socket.connect(socket.local_addr().unwrap()).unwrap();
+///     for _ in 0..10 {
+///         socket.send("ping").await.0.unwrap();
+///         socket.recv(Vec::with_capacity(4)).await.0.unwrap();
+///     }
+///     let stats = compio::task::op_pool_stats();
+///     assert!(stats.hit_rate() > 0.5);
+/// })
+/// ```
+pub fn op_pool_stats() -> OpPoolStats {
+    RUNTIME.with(|runtime| runtime.op_pool_stats())
+}
+
+/// The occupancy of the file table for the direct descriptors in the
+/// runtime, e.g., to tell whether the accepts are waiting for free slots.
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub fn direct_slot_stats() -> DirectSlotStats {
+    RUNTIME.with(|runtime| runtime.direct_slot_stats())
+}
+
+/// The count of the operations registered in the runtime: the ones in flight,
+/// including the cancelled ones not given back by the driver yet, and the
+/// completed ones whose results are not taken by their futures yet. It
+/// returns to 0 once all the futures are completed or dropped, so it is
+/// useful to find the leaked operations in tests.
+///
+/// ```
+/// use compio::{driver::AsRawFd, fs::File, op::ReadAt};
+///
+/// compio::task::block_on(async {
+///     let file = File::open("Cargo.toml").unwrap();
+///     compio::task::attach(file.as_raw_fd()).unwrap();
+///     let read = compio::task::submit(ReadAt::new(file.as_raw_fd(), 0, Vec::with_capacity(4)));
+///     read.await.0.unwrap();
+///     assert_eq!(compio::task::registered_ops(), 0);
+/// })
+/// ```
+pub fn registered_ops() -> usize {
+    RUNTIME.with(|runtime| runtime.registered_ops())
+}
+
+/// The latency metrics of the runtime, or `None` if they are not enabled by
+/// [`ProactorBuilder::latency_metrics`] with [`init_with`]. They are useful to
+/// tune the spin budget of the driver.
+///
+/// ```
+/// use std::net::Ipv4Addr;
+///
+/// use compio::{driver::ProactorBuilder, net::UdpSocket};
+///
+/// std::thread::spawn(|| {
+///     compio::task::init_with(&ProactorBuilder::new().latency_metrics(true)).unwrap();
+///     compio::task::block_on(async {
+///         let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+///         socket.connect(socket.local_addr().unwrap()).unwrap();
+///         socket.send("ping").await.0.unwrap();
+///         socket.recv(Vec::with_capacity(4)).await.0.unwrap();
+///     });
+///     let metrics = compio::task::latency_metrics().unwrap();
+///     println!("completion delay (ns): {}", metrics.completion_delay);
+///     println!("task polls: {}", metrics.task_polls);
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+#[cfg(feature = "metrics")]
+pub fn latency_metrics() -> Option<LatencyMetrics> {
+    RUNTIME.with(|runtime| runtime.latency_metrics())
+}
+
+/// Clear the latency metrics of the runtime, e.g., after warming up. See
+/// [`latency_metrics`].
+#[cfg(feature = "metrics")]
+pub fn reset_latency_metrics() {
+    RUNTIME.with(|runtime| runtime.reset_latency_metrics())
+}
+
+/// The tasks waiting in the run queue of each [`Priority`] now, without the
+/// task calling it, which is being polled.
+///
+/// ```
+/// use compio::task::{Priority, RunQueueDepths};
+///
+/// compio::task::block_on(async {
+///     let task = compio::task::spawn_with_priority(async {}, Priority::Low);
+///     assert_eq!(compio::task::run_queue_depths(), RunQueueDepths {
+///         high: 0,
+///         normal: 0,
+///         low: 1,
+///     });
+///     task.await;
+/// })
+/// ```
+#[cfg(feature = "metrics")]
+pub fn run_queue_depths() -> RunQueueDepths {
+    RUNTIME.with(|runtime| runtime.run_queue_depths())
+}
+
 /// Submit an operation to the runtime.
 ///
 /// You only need this when authoring your own [`OpCode`].
+///
+/// If the driver is full, i.e., [`Proactor::is_full`], the task waits for a
+/// poll of the driver before submitting, and the waiting tasks submit in the
+/// order they arrived. The operation is submitted when the future is polled
+/// then, so it should be awaited.
+///
+/// It fails with [`io::ErrorKind::Other`] and gives the operation back if the
+/// runtime of current thread is not running, i.e., it is not called in
+/// [`block_on`], e.g., under another executor, where the operation would never
+/// complete.
 pub fn submit<T: OpCode + 'static>(op: T) -> impl Future<Output = BufResult<usize, T>> {
-    RUNTIME.with(|runtime| runtime.submit(op))
+    use futures_util::future::Either;
+
+    if let Err(e) = runtime::check_running() {
+        return Either::Right(std::future::ready((Err(e), op)));
+    }
+    Either::Left(RUNTIME.with(|runtime| runtime.submit(op)))
+}
+
+/// Submit the operations into a new [`OpSet`], which yields the results in
+/// the order of completion.
+pub fn submit_set<T: OpCode + 'static>(ops: impl IntoIterator<Item = T>) -> OpSet<T> {
+    ops.into_iter().collect()
 }
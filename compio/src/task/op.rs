@@ -1,74 +1,422 @@
 use std::{
-    collections::HashMap,
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
     future::Future,
     io,
     pin::Pin,
+    rc::Rc,
     task::{Context, Poll, Waker},
 };
 
+use slab::Slab;
+
 use crate::{
-    driver::{OpCode, RawOp},
+    driver::{OpCode, RawFd, RawOp},
     key::Key,
+    task::OpDump,
 };
 
-#[derive(Default)]
 pub(crate) struct RegisteredOp {
+    // The user-defined data in the driver, valid before the op is given back.
+    pub user_data: usize,
     pub op: Option<RawOp>,
-    pub waker: Option<Waker>,
+    pub waker: OpWaker,
+    // If set, the key is pushed into it on completion, instead of waking.
+    pub ready: Option<Rc<ReadyQueue>>,
     pub result: Option<io::Result<usize>>,
     pub cancelled: bool,
+    // The op uses the memory borrowed by `scope_io`, which waits for it.
+    pub borrowed: bool,
+    // The type name of the op, and the generation when it is submitted.
+    pub name: &'static str,
+    pub submitted: u64,
+    // The innermost scope when it is submitted.
+    pub scope: Option<Rc<InterruptScope>>,
+    // When the driver gives it back, if the latency metrics are enabled.
+    #[cfg(feature = "metrics")]
+    pub completed_at: Option<std::time::Instant>,
+}
+
+// How an op in flight wakes its future. An op polled directly by a task of
+// the runtime wakes it by its id, with the waker kept in the runtime, so that
+// registering it costs no clone of the waker. A waker of others, e.g., of a
+// combinator polling the future with its own waker, is cloned.
+#[derive(Default)]
+pub(crate) enum OpWaker {
+    #[default]
+    None,
+    Task(usize),
+    Waker(Waker),
 }
 
+// The ops submitted in a scope are cancelled together when it is interrupted,
+// but the futures still wait for them, and get the buffers back. The ops
+// submitted after the interruption fail at once.
+#[derive(Default)]
+pub(crate) struct InterruptScope {
+    parent: Option<Rc<InterruptScope>>,
+    interrupted: Cell<bool>,
+}
+
+impl InterruptScope {
+    pub fn new(parent: Option<Rc<InterruptScope>>) -> Self {
+        Self {
+            parent,
+            interrupted: Cell::new(false),
+        }
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.get() || self.parent.as_ref().is_some_and(|p| p.is_interrupted())
+    }
+
+    pub fn set_interrupted(&self) {
+        self.interrupted.set(true);
+    }
+
+    // Whether it is `scope`, or nested in it.
+    fn is_within(&self, scope: &InterruptScope) -> bool {
+        std::ptr::eq(self, scope) || self.parent.as_ref().is_some_and(|p| p.is_within(scope))
+    }
+}
+
+// The keys of the completed ops in an `OpSet`, in the order of completion.
+// The task of the set is woken without polling the pending ops.
+#[derive(Default)]
+pub(crate) struct ReadyQueue {
+    keys: RefCell<VecDeque<usize>>,
+    waker: RefCell<Option<Waker>>,
+}
+
+impl ReadyQueue {
+    // Returns the waker of the set to wake.
+    fn push(&self, key: usize) -> Option<Waker> {
+        self.keys.borrow_mut().push_back(key);
+        self.waker.borrow_mut().take()
+    }
+
+    pub fn pop(&self) -> Option<usize> {
+        self.keys.borrow_mut().pop_front()
+    }
+
+    pub fn update_waker(&self, waker: &Waker) {
+        let mut current = self.waker.borrow_mut();
+        match &*current {
+            Some(current) if current.will_wake(waker) => {}
+            _ => *current = Some(waker.clone()),
+        }
+    }
+}
+
+// Enter a scope while polling a future, and restore the previous one when
+// dropped, even on panic.
+pub(crate) struct ScopeGuard {
+    prev: Option<Rc<InterruptScope>>,
+}
+
+impl ScopeGuard {
+    pub fn enter(scope: Option<Rc<InterruptScope>>) -> Self {
+        Self {
+            prev: crate::task::RUNTIME.with(|runtime| runtime.enter_scope(scope)),
+        }
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        crate::task::RUNTIME.with(|runtime| runtime.enter_scope(prev));
+    }
+}
+
+// Poll the future out of any scope, so that its ops are never interrupted.
+#[cfg_attr(not(feature = "sync"), allow(dead_code))]
+pub(crate) async fn unscoped<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| {
+        let _guard = ScopeGuard::enter(None);
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+// Poll the future in the scope, nested in the current one by the caller.
+pub(crate) async fn scoped<F: Future>(scope: Rc<InterruptScope>, future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| {
+        let _guard = ScopeGuard::enter(Some(scope.clone()));
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+// The driver reuses the user-defined data once an op is given back, but the
+// result may not be taken by its future yet. So the ops are identified by the
+// keys here, which are released only after the results are taken.
 #[derive(Default)]
 pub(crate) struct OpRuntime {
-    ops: HashMap<usize, RegisteredOp>,
+    ops: Slab<RegisteredOp>,
+    keys: HashMap<usize, usize>,
 }
 
 impl OpRuntime {
-    pub fn update_waker(&mut self, key: usize, waker: Waker) {
-        self.ops.entry(key).or_default().waker = Some(waker);
+    pub fn insert(
+        &mut self,
+        user_data: usize,
+        name: &'static str,
+        submitted: u64,
+        scope: Option<Rc<InterruptScope>>,
+    ) -> usize {
+        let key = self.ops.insert(RegisteredOp {
+            user_data,
+            op: None,
+            waker: OpWaker::None,
+            ready: None,
+            result: None,
+            cancelled: false,
+            borrowed: false,
+            name,
+            submitted,
+            scope,
+            #[cfg(feature = "metrics")]
+            completed_at: None,
+        });
+        self.keys.insert(user_data, key);
+        key
     }
 
-    pub fn update_result(&mut self, key: usize, raw_op: RawOp, result: io::Result<usize>) {
-        let op = self.ops.entry(key).or_default();
-        if let Some(waker) = op.waker.take() {
-            waker.wake();
-        }
+    pub fn user_data(&self, key: usize) -> usize {
+        self.ops[key].user_data
+    }
+
+    pub fn waker_mut(&mut self, key: usize) -> &mut OpWaker {
+        &mut self.ops[key].waker
+    }
+
+    pub fn update_ready(&mut self, key: usize, ready: Rc<ReadyQueue>) {
+        self.ops[key].ready = Some(ready);
+    }
+
+    // Returns the waker to wake, after the runtime is released, because the
+    // woken code may use it.
+    #[must_use]
+    pub fn update_result(
+        &mut self,
+        user_data: usize,
+        raw_op: RawOp,
+        result: io::Result<usize>,
+    ) -> OpWaker {
+        let Some(key) = self.keys.remove(&user_data) else {
+            return OpWaker::None;
+        };
+        let op = &mut self.ops[key];
         op.op = Some(raw_op);
         op.result = Some(result);
         if op.cancelled {
             self.remove(key);
+            OpWaker::None
+        } else if let Some(ready) = &op.ready {
+            ready.push(key).map_or(OpWaker::None, OpWaker::Waker)
+        } else {
+            std::mem::take(&mut op.waker)
         }
     }
 
+    #[cfg(feature = "metrics")]
+    pub fn set_completed_at(&mut self, user_data: usize, instant: std::time::Instant) {
+        if let Some(&key) = self.keys.get(&user_data) {
+            self.ops[key].completed_at = Some(instant);
+        }
+    }
+
+    pub fn contains(&self, key: usize) -> bool {
+        self.ops.contains(key)
+    }
+
+    // The ops completed but not taken, or in flight.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
     pub fn has_result(&mut self, key: usize) -> bool {
         self.ops
-            .get_mut(&key)
+            .get(key)
             .map(|op| op.result.is_some())
             .unwrap_or_default()
     }
 
-    pub fn cancel(&mut self, key: usize) {
-        self.ops.entry(key).or_default().cancelled = true;
+    // Returns the waker of the op, which is never woken.
+    pub fn cancel(&mut self, key: usize) -> OpWaker {
+        let op = &mut self.ops[key];
+        op.cancelled = true;
+        std::mem::take(&mut op.waker)
+    }
+
+    // The user-defined data of the ops in flight within the scope, and not
+    // cancelled by dropping their futures.
+    pub fn in_scope(&self, scope: &InterruptScope) -> Vec<usize> {
+        self.keys
+            .iter()
+            .filter(|(_, &key)| {
+                let op = &self.ops[key];
+                !op.cancelled && op.scope.as_ref().is_some_and(|s| s.is_within(scope))
+            })
+            .map(|(&user_data, _)| user_data)
+            .collect()
+    }
+
+    // Stop waking the task by its id, which may be reused after the task is
+    // dropped with the futures of its ops forgotten.
+    pub fn detach_task(&mut self, id: usize) {
+        for (_, op) in self.ops.iter_mut() {
+            if matches!(op.waker, OpWaker::Task(task) if task == id) {
+                op.waker = OpWaker::None;
+            }
+        }
+    }
+
+    pub fn set_borrowed(&mut self, key: usize) {
+        self.ops[key].borrowed = true;
+    }
+
+    // The user-defined data of the ops in flight using the borrowed memory,
+    // including the cancelled ones.
+    pub fn borrowed_in_flight(&self) -> Vec<usize> {
+        self.keys
+            .iter()
+            .filter(|(_, &key)| self.ops[key].borrowed)
+            .map(|(&user_data, _)| user_data)
+            .collect()
     }
 
     pub fn remove(&mut self, key: usize) -> RegisteredOp {
-        self.ops.remove(&key).unwrap()
+        self.ops.remove(key)
+    }
+
+    // Whether there are ops not given back by the driver.
+    pub fn has_in_flight(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    pub fn dump_in_flight(&self) -> Vec<OpDump> {
+        let mut ops = self
+            .keys
+            .values()
+            .map(|&key| {
+                let op = &self.ops[key];
+                OpDump {
+                    name: op.name,
+                    submitted: op.submitted,
+                    cancelled: op.cancelled,
+                }
+            })
+            .collect::<Vec<_>>();
+        ops.sort_by_key(|op| op.submitted);
+        ops
+    }
+}
+
+// The tasks waiting for the driver to accept more ops. They are admitted in
+// the FIFO order, one at a time: the first one is woken after a poll of the
+// driver, and it wakes the next one when it leaves.
+#[derive(Default)]
+pub(crate) struct SubmitQueue {
+    waiters: Slab<Option<Waker>>,
+    order: VecDeque<usize>,
+}
+
+impl SubmitQueue {
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    pub fn insert(&mut self) -> usize {
+        let key = self.waiters.insert(None);
+        self.order.push_back(key);
+        key
+    }
+
+    pub fn is_first(&self, key: usize) -> bool {
+        self.order.front() == Some(&key)
+    }
+
+    pub fn update_waker(&mut self, key: usize, waker: &Waker) {
+        let current = &mut self.waiters[key];
+        match current {
+            Some(current) if current.will_wake(waker) => {}
+            _ => *current = Some(waker.clone()),
+        }
+    }
+
+    pub fn remove(&mut self, key: usize) {
+        self.waiters.remove(key);
+        self.order.retain(|k| *k != key);
+        self.wake_first();
+    }
+
+    pub fn wake_first(&mut self) {
+        if let Some(key) = self.order.front() {
+            if let Some(waker) = self.waiters[*key].take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+// Waits for a turn to submit, when the driver is full or other tasks are
+// waiting.
+pub(crate) struct SubmitSlot {
+    key: Option<usize>,
+}
+
+impl SubmitSlot {
+    pub fn new(key: usize) -> Self {
+        Self { key: Some(key) }
+    }
+}
+
+impl Future for SubmitSlot {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let key = self.key.expect("`SubmitSlot` polled after completion");
+        let res = crate::task::RUNTIME.with(|runtime| runtime.poll_submit_slot(cx, key));
+        if res.is_ready() {
+            self.key = None;
+        }
+        res
+    }
+}
+
+impl Drop for SubmitSlot {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            crate::task::RUNTIME
+                .try_with(|runtime| runtime.leave_submit_queue(key))
+                .ok();
+        }
     }
 }
 
+// Waits for a turn to submit an operation for the fd, when the operations
+// submitted for each fd in a tick are limited.
+pub(crate) fn fd_budget(fd: RawFd) -> impl Future<Output = ()> {
+    std::future::poll_fn(move |cx| {
+        crate::task::RUNTIME.with(|runtime| runtime.poll_fd_budget(cx, fd))
+    })
+}
+
+// The key is owned by the future until the result is taken. Then the slot may
+// be reused by another op at once, so the future never touches it again:
+// polling after completion panics, and dropping does nothing.
 #[derive(Debug)]
 pub struct OpFuture<T> {
-    user_data: Key<T>,
-    completed: bool,
+    key: Option<Key<T>>,
 }
 
 impl<T> OpFuture<T> {
-    pub fn new(user_data: Key<T>) -> Self {
-        Self {
-            user_data,
-            completed: false,
-        }
+    pub fn new(key: Key<T>) -> Self {
+        Self { key: Some(key) }
     }
 }
 
@@ -76,18 +424,25 @@ impl<T: OpCode> Future for OpFuture<T> {
     type Output = (io::Result<usize>, T);
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let res = crate::task::RUNTIME.with(|runtime| runtime.poll_task(cx, self.user_data));
+        let this = self.get_mut();
+        let key = this.key.expect("`OpFuture` polled after completion");
+        let res = crate::task::RUNTIME.with(|runtime| runtime.poll_task(cx, key));
         if res.is_ready() {
-            self.get_mut().completed = true;
+            this.key = None;
         }
         res
     }
 }
 
 impl<T> Drop for OpFuture<T> {
+    // It runs on panics too, e.g., when the task is unwinding with the result
+    // completed but not taken, and releases the slot.
     fn drop(&mut self) {
-        if !self.completed {
-            crate::task::RUNTIME.with(|runtime| runtime.cancel_op(self.user_data))
+        if let Some(key) = self.key.take() {
+            // The runtime may have been destroyed with its tasks.
+            crate::task::RUNTIME
+                .try_with(|runtime| runtime.cancel_op(key))
+                .ok();
         }
     }
 }
@@ -0,0 +1,98 @@
+use std::collections::VecDeque;
+
+use async_task::Runnable;
+
+// The tasks of a lower priority polled in each tick by default, at least
+// after this many polls of the higher ones.
+const DEFAULT_RATIO: u32 = 16;
+
+/// The priority of a task, see [`spawn_with_priority`].
+///
+/// The runtime keeps a run queue for each priority, and polls the tasks of
+/// the higher priorities first. A task is always scheduled back into the queue
+/// of its priority, whether it is woken by a completion, a timer, or itself.
+///
+/// [`spawn_with_priority`]: crate::task::spawn_with_priority
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Priority {
+    /// For the latency-critical tasks, e.g., serving the requests.
+    High,
+    /// The priority of the tasks spawned by [`spawn`](crate::task::spawn).
+    #[default]
+    Normal,
+    /// For the background tasks, e.g., compaction or the bulk transfers.
+    Low,
+}
+
+impl Priority {
+    const ALL: [Self; 3] = [Self::High, Self::Normal, Self::Low];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// The run queues of the priorities, each in FIFO order.
+pub(crate) struct RunQueues {
+    // Indexed by `Priority::index`, the highest first.
+    queues: [VecDeque<Runnable>; 3],
+    // The tasks of the higher priorities polled while each queue is waiting.
+    passed: [u32; 3],
+    ratio: u32,
+}
+
+impl Default for RunQueues {
+    fn default() -> Self {
+        Self {
+            queues: Default::default(),
+            passed: [0; 3],
+            ratio: DEFAULT_RATIO,
+        }
+    }
+}
+
+impl RunQueues {
+    pub fn push(&mut self, priority: Priority, runnable: Runnable) {
+        self.queues[priority.index()].push_back(runnable);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queues.iter().all(VecDeque::is_empty)
+    }
+
+    pub fn ratio(&self) -> u32 {
+        self.ratio
+    }
+
+    pub fn set_ratio(&mut self, ratio: u32) {
+        self.ratio = ratio;
+    }
+
+    // The queue to poll next: the highest non-empty one, unless a lower one
+    // has waited for `ratio` polls, and then the lowest of those.
+    pub fn next_queue(&mut self) -> Option<(Priority, &mut VecDeque<Runnable>)> {
+        let first = self.queues.iter().position(|queue| !queue.is_empty())?;
+        let index = (first + 1..self.queues.len())
+            .rev()
+            .find(|&i| !self.queues[i].is_empty() && self.passed[i] >= self.ratio)
+            .unwrap_or(first);
+        self.passed[index] = 0;
+        for i in index + 1..self.queues.len() {
+            if self.queues[i].is_empty() {
+                self.passed[i] = 0;
+            } else {
+                self.passed[i] += 1;
+            }
+        }
+        Some((Priority::ALL[index], &mut self.queues[index]))
+    }
+
+    pub fn pop_front(&mut self) -> Option<Runnable> {
+        self.queues.iter_mut().find_map(VecDeque::pop_front)
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn depth(&self, priority: Priority) -> usize {
+        self.queues[priority.index()].len()
+    }
+}
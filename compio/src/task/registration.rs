@@ -0,0 +1,130 @@
+use std::{cell::Cell, io};
+
+use crate::{
+    driver::{Interest, RawFd},
+    op::PollOnce,
+    task::submit,
+};
+
+/// The readiness of an fd owned by others, e.g., a socket managed by a
+/// readiness-based library, which is polled by the runtime.
+///
+/// It is the low-level counterpart of the IO types in this crate. It doesn't
+/// own the fd, and there could be many registrations of the same fd,
+/// independent of each other and of the operations of the runtime on it.
+/// Nothing is registered to the driver until [`Registration::ready`] is
+/// awaited, so dropping it needs no deregistration.
+///
+/// The readiness is cached after it is reported, until it is cleared by
+/// [`ReadyGuard::clear_ready`], or an IO in [`ReadyGuard::try_io`] would
+/// block. The driver is polled again then.
+///
+/// ## Platform specific
+/// * io-uring: a one-shot `IORING_OP_POLL_ADD` for each wait.
+/// * polling: the fd is waited with the other operations on it.
+/// * IOCP: not available. Only the sockets have readiness there, through the
+///   AFD poll mechanism, which the driver doesn't support.
+///
+/// ```
+/// use std::{
+///     io::{Read, Write},
+///     os::{fd::AsRawFd, unix::net::UnixStream},
+/// };
+///
+/// use compio::{driver::Interest, task::Registration};
+///
+/// compio::task::block_on(async {
+///     let (mut tx, mut rx) = UnixStream::pair().unwrap();
+///     rx.set_nonblocking(true).unwrap();
+///     let registration = Registration::new(rx.as_raw_fd(), Interest::Readable);
+///     tx.write_all(b"hello").unwrap();
+///
+///     let mut buffer = [0; 5];
+///     let len = loop {
+///         let mut guard = registration.ready(Interest::Readable).await.unwrap();
+///         match guard.try_io(|| rx.read(&mut buffer)) {
+///             Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+///             res => break res.unwrap(),
+///         }
+///     };
+///     assert_eq!(&buffer[..len], b"hello");
+/// })
+/// ```
+#[derive(Debug)]
+pub struct Registration {
+    fd: RawFd,
+    interest: Interest,
+    ready: Cell<bool>,
+}
+
+impl Registration {
+    /// Register the fd for the interest. It should stay open while the
+    /// registration is alive, and it should be non-blocking, otherwise the
+    /// IO after the readiness may block the runtime.
+    pub fn new(fd: RawFd, interest: Interest) -> Self {
+        Self {
+            fd,
+            interest,
+            ready: Cell::new(false),
+        }
+    }
+
+    /// The registered fd.
+    pub fn fd(&self) -> RawFd {
+        self.fd
+    }
+
+    /// The registered interest.
+    pub fn interest(&self) -> Interest {
+        self.interest
+    }
+
+    /// Wait for the fd to be ready for the interest, which should be the
+    /// registered one, otherwise it fails with
+    /// [`io::ErrorKind::InvalidInput`]. It returns at once if the readiness
+    /// is cached.
+    ///
+    /// The readiness may be spurious, and the IO should be tried then. It
+    /// could be awaited again after the returned guard is dropped.
+    pub async fn ready(&self, interest: Interest) -> io::Result<ReadyGuard<'_>> {
+        if interest != self.interest {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the interest is not registered",
+            ));
+        }
+        if !self.ready.get() {
+            submit(PollOnce::new(self.fd, interest)).await.0?;
+            self.ready.set(true);
+        }
+        Ok(ReadyGuard { registration: self })
+    }
+}
+
+/// The readiness reported by [`Registration::ready`].
+#[derive(Debug)]
+pub struct ReadyGuard<'a> {
+    registration: &'a Registration,
+}
+
+impl ReadyGuard<'_> {
+    /// Clear the cached readiness, so that the next
+    /// [`Registration::ready`] waits for the driver. It should be called
+    /// after an IO would block.
+    pub fn clear_ready(self) {
+        self.registration.ready.set(false);
+    }
+
+    /// Perform the IO on the fd. If it fails with
+    /// [`io::ErrorKind::WouldBlock`], the readiness is cleared, and the error
+    /// is returned for the caller to wait again.
+    pub fn try_io<R>(&mut self, f: impl FnOnce() -> io::Result<R>) -> io::Result<R> {
+        let res = f();
+        if let Err(e) = &res {
+            if e.kind() == io::ErrorKind::WouldBlock {
+                self.registration.ready.set(false);
+            }
+        }
+        res
+    }
+}
@@ -1,38 +1,163 @@
 use std::{
-    cell::RefCell,
-    collections::VecDeque,
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
     future::Future,
     io,
-    task::{Context, Poll},
+    panic::Location,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
 };
 
 use async_task::{Runnable, Task};
-use smallvec::SmallVec;
+use slab::Slab;
 
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+use crate::task::direct::{DirectSlotStats, DirectSlots};
+#[cfg(feature = "instrumentation")]
+use crate::task::instrument::{Instrument, Subscriber};
+#[cfg(feature = "metrics")]
+use crate::task::{LatencyMetrics, RunQueueDepths};
 #[cfg(feature = "time")]
 use crate::task::time::{TimerFuture, TimerRuntime};
 use crate::{
-    driver::{AsRawFd, Entry, OpCode, Proactor, RawFd},
-    task::op::{OpFuture, OpRuntime},
+    driver::{
+        AsRawFd, Entry, MessageSender, OpCode, OpPoolStats, PollStats, Proactor, PushEntry, RawFd,
+    },
+    task::{
+        op::{
+            InterruptScope, OpFuture, OpRuntime, OpWaker, ReadyQueue, SubmitQueue, SubmitSlot,
+        },
+        priority::RunQueues,
+        sequencer::{Choice, Sequencer},
+        stall::{StallDetector, TaskState, TrackedTask},
+        Priority, StallReport, TaskDump,
+    },
     BufResult, Key,
 };
 
+thread_local! {
+    // The caller of the `block_on` running on this thread. It isn't a field of
+    // the runtime, so that it could be checked without creating one.
+    static RUNNING: Cell<Option<&'static Location<'static>>> = const { Cell::new(None) };
+}
+
+/// The caller of the `block_on` running on current thread, if any.
+#[cfg(feature = "event")]
+pub(crate) fn running_block_on() -> Option<&'static Location<'static>> {
+    RUNNING.get()
+}
+
+/// Fail if no `block_on` is running on current thread, so that the ops
+/// submitted would never complete, e.g., under another executor. The runtime
+/// is alive while running, so it is accessed without panicking after the
+/// check.
+pub(crate) fn check_running() -> io::Result<()> {
+    if RUNNING.get().is_some() {
+        Ok(())
+    } else {
+        Err(io::Error::other(
+            "no compio runtime is running on this thread: the IO of compio should be awaited \
+             in `compio::task::block_on`, or in a task spawned by `compio::task::spawn` there",
+        ))
+    }
+}
+
+struct RunningGuard;
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        RUNNING.set(None);
+    }
+}
+
 pub(crate) struct Runtime {
     driver: RefCell<Proactor>,
-    runnables: RefCell<VecDeque<Runnable>>,
+    runnables: RefCell<RunQueues>,
     op_runtime: RefCell<OpRuntime>,
+    submit_queue: RefCell<SubmitQueue>,
     #[cfg(feature = "time")]
     timer_runtime: RefCell<TimerRuntime>,
+    messages: RefCell<VecDeque<u64>>,
+    message_waker: RefCell<Option<Waker>>,
+    // The entries completed in a poll of the driver, cleared but kept
+    // allocated between the polls.
+    entries: RefCell<Vec<Entry>>,
+    // When the driver gives back the latest completed ops.
+    polled_at: Cell<Option<Instant>>,
+    // Increased on each poll of the driver.
+    generation: Cell<u64>,
+    tasks: RefCell<Slab<TaskState>>,
+    // The task being polled, and its waker, valid during the poll.
+    current_task: Cell<Option<(usize, *const Waker)>>,
+    stall_detector: RefCell<Option<StallDetector>>,
+    sequencer: RefCell<Option<Box<dyn Sequencer>>>,
+    // The innermost scope of the future being polled.
+    scope: RefCell<Option<Rc<InterruptScope>>>,
+    // The operations submitted for each fd in this tick, if limited.
+    max_ops_per_fd: u32,
+    fd_ops: RefCell<HashMap<RawFd, u32>>,
+    // The tasks waiting for the next poll of the driver.
+    next_tick: RefCell<Vec<Waker>>,
+    // The wakers of `next_tick` being woken, swapped to reuse the allocation.
+    next_tick_spare: RefCell<Vec<Waker>>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<RefCell<LatencyMetrics>>,
+    // The tasks polled since the last poll of the driver.
+    #[cfg(feature = "metrics")]
+    task_polls: Cell<u64>,
+    #[cfg(feature = "instrumentation")]
+    instrument: RefCell<Instrument>,
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    direct_slots: RefCell<DirectSlots>,
 }
 
 impl Runtime {
-    pub fn new() -> io::Result<Self> {
+    pub fn new(driver: Option<Proactor>) -> io::Result<Self> {
+        let driver = match driver {
+            Some(driver) => driver,
+            None => Proactor::new()?,
+        };
+        #[cfg(feature = "metrics")]
+        let metrics = driver.latency_metrics().then(RefCell::default);
+        #[cfg(feature = "time")]
+        #[allow(unused_mut)]
+        let mut timer_runtime = TimerRuntime::new(driver.timer_resolution());
+        #[cfg(feature = "test-util")]
+        if driver.start_paused() {
+            timer_runtime.pause();
+        }
+        let max_ops_per_fd = driver.max_ops_per_fd();
+        let entries = Vec::with_capacity(driver.capacity());
         Ok(Self {
-            driver: RefCell::new(Proactor::new()?),
+            driver: RefCell::new(driver),
             runnables: RefCell::default(),
             op_runtime: RefCell::default(),
+            submit_queue: RefCell::default(),
             #[cfg(feature = "time")]
-            timer_runtime: RefCell::new(TimerRuntime::new()),
+            timer_runtime: RefCell::new(timer_runtime),
+            messages: RefCell::default(),
+            message_waker: RefCell::default(),
+            entries: RefCell::new(entries),
+            polled_at: Cell::default(),
+            generation: Cell::default(),
+            tasks: RefCell::default(),
+            current_task: Cell::default(),
+            stall_detector: RefCell::default(),
+            sequencer: RefCell::default(),
+            scope: RefCell::default(),
+            max_ops_per_fd,
+            fd_ops: RefCell::default(),
+            next_tick: RefCell::default(),
+            next_tick_spare: RefCell::default(),
+            #[cfg(feature = "metrics")]
+            metrics,
+            #[cfg(feature = "metrics")]
+            task_polls: Cell::default(),
+            #[cfg(feature = "instrumentation")]
+            instrument: RefCell::default(),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            direct_slots: RefCell::default(),
         })
     }
 
@@ -42,23 +167,70 @@ impl Runtime {
     }
 
     // Safety: the return runnable should be scheduled.
-    unsafe fn spawn_unchecked<F: Future>(&self, future: F) -> Task<F::Output> {
-        let schedule = move |runnable| self.runnables.borrow_mut().push_back(runnable);
+    unsafe fn spawn_unchecked<F: Future>(
+        &self,
+        future: F,
+        location: &'static Location<'static>,
+        priority: Priority,
+    ) -> Task<F::Output> {
+        let id = self.tasks.borrow_mut().insert(TaskState {
+            location,
+            last_polled: self.generation.get(),
+            waker: None,
+            ops: 0,
+        });
+        #[cfg(feature = "instrumentation")]
+        self.instrument(|i| i.task_spawned(id, location));
+        let future = TrackedTask::new(id, future);
+        // Woken into the queue of its priority.
+        let schedule = move |runnable| {
+            self.runnables.borrow_mut().push(priority, runnable);
+            #[cfg(feature = "instrumentation")]
+            self.instrument(|i| i.task_woken(id));
+        };
         let (runnable, task) = async_task::spawn_unchecked(future, schedule);
         runnable.schedule();
         task
     }
 
-    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+    pub fn block_on<F: Future>(
+        &self,
+        future: F,
+        location: &'static Location<'static>,
+    ) -> F::Output {
+        // The outer `block_on` is polling the task calling it, so the task
+        // would never be woken again, or the driver be polled reentrantly.
+        if let Some(outer) = RUNNING.get() {
+            panic!(
+                "cannot nest `block_on` at {location} in the `block_on` at {outer}: the runtime \
+                 of current thread is already running, spawn the future with \
+                 `compio::task::spawn` or await it directly instead"
+            );
+        }
+        RUNNING.set(Some(location));
+        let _guard = RunningGuard;
         let mut result = None;
-        unsafe { self.spawn_unchecked(async { result = Some(future.await) }) }.detach();
+        unsafe {
+            self.spawn_unchecked(
+                async { result = Some(future.await) },
+                location,
+                Priority::Normal,
+            )
+        }
+        .detach();
         loop {
-            loop {
-                let next_task = self.runnables.borrow_mut().pop_front();
-                if let Some(task) = next_task {
-                    task.run();
-                } else {
-                    break;
+            // Poll the driver after `ratio` low priority tasks, so that a flood
+            // of them doesn't delay the completions of the others.
+            let mut low_polls = 0;
+            while let Some((task, priority)) = self.next_runnable() {
+                #[cfg(feature = "metrics")]
+                self.task_polls.set(self.task_polls.get() + 1);
+                task.run();
+                if priority == Priority::Low {
+                    low_polls += 1;
+                    if low_polls >= self.runnables.borrow().ratio() {
+                        break;
+                    }
                 }
             }
             if let Some(result) = result.take() {
@@ -68,39 +240,492 @@ impl Runtime {
         }
     }
 
-    pub fn spawn<F: Future + 'static>(&self, future: F) -> Task<F::Output> {
-        unsafe { self.spawn_unchecked(future) }
+    fn next_runnable(&self) -> Option<(Runnable, Priority)> {
+        let mut runnables = self.runnables.borrow_mut();
+        let (priority, queue) = runnables.next_queue()?;
+        let index = self.choose(Choice::Task, queue.len());
+        queue.remove(index).map(|runnable| (runnable, priority))
+    }
+
+    // Choose the next one among `len` candidates, the first one without a
+    // sequencer.
+    fn choose(&self, choice: Choice, len: usize) -> usize {
+        if len <= 1 {
+            return 0;
+        }
+        let index = match self.sequencer.borrow_mut().as_mut() {
+            Some(sequencer) => sequencer.choose(choice, len),
+            None => return 0,
+        };
+        assert!(
+            index < len,
+            "the sequencer chose {index} among {len} candidates of {choice:?}"
+        );
+        index
+    }
+
+    pub fn set_sequencer(&self, sequencer: Option<Box<dyn Sequencer>>) {
+        *self.sequencer.borrow_mut() = sequencer;
+    }
+
+    pub fn spawn<F: Future + 'static>(
+        &self,
+        future: F,
+        location: &'static Location<'static>,
+        priority: Priority,
+    ) -> Task<F::Output> {
+        unsafe { self.spawn_unchecked(future, location, priority) }
+    }
+
+    pub fn set_priority_ratio(&self, ratio: u32) {
+        self.runnables.borrow_mut().set_ratio(ratio);
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn run_queue_depths(&self) -> RunQueueDepths {
+        let runnables = self.runnables.borrow();
+        RunQueueDepths {
+            high: runnables.depth(Priority::High),
+            normal: runnables.depth(Priority::Normal),
+            low: runnables.depth(Priority::Low),
+        }
+    }
+
+    // Returns the task polled before, which is restored by `leave_task`.
+    pub fn enter_task(&self, id: usize, waker: &Waker) -> Option<(usize, *const Waker)> {
+        if let Some(task) = self.tasks.borrow_mut().get_mut(id) {
+            task.last_polled = self.generation.get();
+        }
+        self.current_task.replace(Some((id, waker)))
+    }
+
+    // The waker of the task is released if no op wakes it by its id, e.g.,
+    // when it waits for a channel, as if the ops held the clones.
+    pub fn leave_task(&self, id: usize, prev: Option<(usize, *const Waker)>) {
+        self.current_task.set(prev);
+        let waker = match self.tasks.borrow_mut().get_mut(id) {
+            Some(task) if task.ops == 0 => task.waker.take(),
+            _ => None,
+        };
+        drop(waker);
+    }
+
+    pub fn task_dropped(&self, id: usize) {
+        let task = self.tasks.borrow_mut().try_remove(id);
+        // The ops of the forgotten futures still refer to the task.
+        if task.as_ref().is_some_and(|task| task.ops > 0) {
+            self.op_runtime.borrow_mut().detach_task(id);
+        }
+        drop(task);
+        #[cfg(feature = "instrumentation")]
+        self.instrument(|i| i.task_dropped(id));
+    }
+
+    // Update the state of the instrumentation, and wake the clients after it
+    // is released.
+    #[cfg(feature = "instrumentation")]
+    pub fn instrument<R>(&self, f: impl FnOnce(&mut Instrument) -> R) -> R {
+        let mut instrument = self.instrument.borrow_mut();
+        let res = f(&mut instrument);
+        let wakers = instrument.take_wakers();
+        drop(instrument);
+        if let Some(mut wakers) = wakers {
+            for waker in wakers.drain(..) {
+                waker.wake();
+            }
+            self.instrument.borrow_mut().restore_wakers(wakers);
+        }
+        res
+    }
+
+    #[cfg(feature = "instrumentation")]
+    pub fn subscribe(&self) -> Rc<Subscriber> {
+        let tasks = self.tasks.borrow();
+        self.instrument(|i| i.subscribe(tasks.iter().map(|(id, task)| (id, task.location))))
     }
 
     pub fn attach(&self, fd: RawFd) -> io::Result<()> {
         self.driver.borrow_mut().attach(fd)
     }
 
-    pub fn submit_raw<T: OpCode + 'static>(&self, op: T) -> Key<T> {
-        let user_data = self.driver.borrow_mut().push(op);
-        unsafe { Key::<T>::new(user_data) }
+    // The ops completed inline are given back directly, without being
+    // registered.
+    pub fn submit_raw<T: OpCode + 'static>(
+        &self,
+        op: T,
+    ) -> PushEntry<Key<T>, BufResult<usize, T>> {
+        let scope = self.scope.borrow().clone();
+        if scope.as_ref().is_some_and(|s| s.is_interrupted()) {
+            return PushEntry::Ready((Err(crate::op::cancelled_error()), op));
+        }
+        let res = self.driver.borrow_mut().push_entry(op);
+        res.map_pending(|user_data| {
+            #[cfg(feature = "instrumentation")]
+            self.instrument(|i| i.op_submitted(user_data, std::any::type_name::<T>()));
+            let key = self.op_runtime.borrow_mut().insert(
+                user_data,
+                std::any::type_name::<T>(),
+                self.generation.get(),
+                scope,
+            );
+            unsafe { Key::<T>::new(key) }
+        })
     }
 
+    // Replace the innermost scope, returning the previous one.
+    pub fn enter_scope(&self, scope: Option<Rc<InterruptScope>>) -> Option<Rc<InterruptScope>> {
+        self.scope.replace(scope)
+    }
+
+    pub fn current_scope(&self) -> Option<Rc<InterruptScope>> {
+        self.scope.borrow().clone()
+    }
+
+    // Cancel the ops in flight within the scope. They are given back with the
+    // cancellation error, unless they have completed.
+    pub fn interrupt_scope(&self, scope: &InterruptScope) {
+        scope.set_interrupted();
+        let ops = self.op_runtime.borrow().in_scope(scope);
+        let mut driver = self.driver.borrow_mut();
+        for user_data in ops {
+            driver.cancel(user_data);
+        }
+    }
+
+    // Submit an op of an `OpSet`, whose key is pushed into `ready` when
+    // completed.
+    pub fn submit_ready<T: OpCode + 'static>(
+        &self,
+        op: T,
+        ready: Rc<ReadyQueue>,
+    ) -> PushEntry<Key<T>, BufResult<usize, T>> {
+        let res = self.submit_raw(op);
+        if let PushEntry::Pending(key) = &res {
+            self.op_runtime.borrow_mut().update_ready(**key, ready);
+        }
+        res
+    }
+
+    // The task is parked if the driver is full, until the ops in flight are
+    // submitted by a poll.
     pub fn submit<T: OpCode + 'static>(&self, op: T) -> impl Future<Output = BufResult<usize, T>> {
-        let user_data = self.submit_raw(op);
-        OpFuture::new(user_data)
+        use futures_util::future::Either;
+
+        if !self.submit_queue.borrow().is_empty() || self.driver.borrow().is_full() {
+            let slot = SubmitSlot::new(self.submit_queue.borrow_mut().insert());
+            return Either::Right(async move {
+                slot.await;
+                match crate::task::RUNTIME.with(|runtime| runtime.submit_raw(op)) {
+                    PushEntry::Pending(key) => OpFuture::new(key).await,
+                    PushEntry::Ready(res) => res,
+                }
+            });
+        }
+        match self.submit_raw(op) {
+            PushEntry::Pending(key) => Either::Left(Either::Left(OpFuture::new(key))),
+            PushEntry::Ready(res) => Either::Left(Either::Right(std::future::ready(res))),
+        }
+    }
+
+    // The first waiter is admitted if the driver is not full. It should submit
+    // before yielding, and the next waiter is woken to check again.
+    pub fn poll_submit_slot(&self, cx: &mut Context, key: usize) -> Poll<()> {
+        let mut queue = self.submit_queue.borrow_mut();
+        if queue.is_first(key) && !self.driver.borrow().is_full() {
+            queue.remove(key);
+            Poll::Ready(())
+        } else {
+            queue.update_waker(key, cx.waker());
+            Poll::Pending
+        }
+    }
+
+    pub fn leave_submit_queue(&self, key: usize) {
+        self.submit_queue.borrow_mut().remove(key);
+    }
+
+    // Wait until the driver is polled after `generation`.
+    pub fn poll_next_tick(&self, cx: &mut Context, generation: u64) -> Poll<()> {
+        if self.generation.get() > generation {
+            Poll::Ready(())
+        } else {
+            self.next_tick.borrow_mut().push(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.get()
+    }
+
+    // Take a turn to submit an operation for the fd in this tick, or wait for
+    // the next tick if the fd has used up its turns.
+    pub fn poll_fd_budget(&self, cx: &mut Context, fd: RawFd) -> Poll<()> {
+        if self.max_ops_per_fd == 0 {
+            return Poll::Ready(());
+        }
+        let mut fd_ops = self.fd_ops.borrow_mut();
+        let ops = fd_ops.entry(fd).or_default();
+        if *ops < self.max_ops_per_fd {
+            *ops += 1;
+            Poll::Ready(())
+        } else {
+            self.next_tick.borrow_mut().push(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    pub fn poll_stats(&self) -> PollStats {
+        self.driver.borrow().poll_stats()
+    }
+
+    pub fn max_poll_fds(&self) -> usize {
+        self.driver.borrow().max_poll_fds()
+    }
+
+    pub fn registered_ops(&self) -> usize {
+        self.op_runtime.borrow().len()
+    }
+
+    pub fn op_pool_stats(&self) -> OpPoolStats {
+        self.driver.borrow().op_pool_stats()
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn latency_metrics(&self) -> Option<LatencyMetrics> {
+        self.metrics
+            .as_ref()
+            .map(|metrics| metrics.borrow().clone())
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn reset_latency_metrics(&self) {
+        if let Some(metrics) = &self.metrics {
+            *metrics.borrow_mut() = LatencyMetrics::default();
+        }
     }
 
     #[cfg(feature = "time")]
-    pub fn create_timer(&self, delay: std::time::Duration) -> impl Future<Output = ()> {
+    pub fn create_timer(
+        &self,
+        delay: std::time::Duration,
+        coarse: bool,
+    ) -> impl Future<Output = ()> {
         use futures_util::future::Either;
 
         let mut timer_runtime = self.timer_runtime.borrow_mut();
-        if let Some(key) = timer_runtime.insert(delay) {
+        if let Some(key) = timer_runtime.insert(delay, coarse) {
             Either::Left(TimerFuture::new(key))
         } else {
             Either::Right(std::future::ready(()))
         }
     }
 
-    pub fn cancel_op<T>(&self, user_data: Key<T>) {
-        self.driver.borrow_mut().cancel(*user_data);
-        self.op_runtime.borrow_mut().cancel(*user_data);
+    pub fn set_napi_busy_poll(
+        &self,
+        timeout: Option<std::time::Duration>,
+        prefer_busy_poll: bool,
+    ) -> io::Result<()> {
+        self.driver
+            .borrow_mut()
+            .set_napi_busy_poll(timeout, prefer_busy_poll)
+    }
+
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn supports_direct_sockets(&self) -> bool {
+        self.driver.borrow().supports_direct_sockets()
+    }
+
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn remove_direct(&self, fd: crate::driver::DirectFd) -> io::Result<()> {
+        self.driver.borrow_mut().remove_direct(fd)
+    }
+
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn supports_direct_accept(&self) -> bool {
+        self.driver.borrow().supports_direct_accept()
+    }
+
+    // Reserve a slot of the file table, registered if not yet, or wait until
+    // one is released.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn poll_direct_slot(&self, cx: &mut Context, waited: &mut bool) -> Poll<io::Result<()>> {
+        let capacity = {
+            let mut driver = self.driver.borrow_mut();
+            if let Err(e) = driver.register_direct_table() {
+                return Poll::Ready(Err(e));
+            }
+            driver.direct_table_size()
+        };
+        self.direct_slots
+            .borrow_mut()
+            .poll_reserve(capacity, cx, waited)
+            .map(Ok)
+    }
+
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn release_direct_slot(&self) {
+        let waiters = self.direct_slots.borrow_mut().release();
+        for waker in waiters {
+            waker.wake();
+        }
+    }
+
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    pub fn direct_slot_stats(&self) -> DirectSlotStats {
+        let capacity = self.driver.borrow().direct_table_size();
+        self.direct_slots.borrow().stats(capacity)
+    }
+
+    pub fn message_sender(&self) -> io::Result<MessageSender> {
+        self.driver.borrow().message_sender()
+    }
+
+    pub fn poll_message(&self, cx: &mut Context) -> Poll<u64> {
+        if let Some(msg) = self.messages.borrow_mut().pop_front() {
+            Poll::Ready(msg)
+        } else {
+            *self.message_waker.borrow_mut() = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    // The user-defined data in the driver of a pending op.
+    #[allow(dead_code)]
+    pub fn user_data(&self, key: usize) -> usize {
+        self.op_runtime.borrow().user_data(key)
+    }
+
+    // The woken tasks are run before the next poll, so it is when the result of
+    // an op just awaited is given back.
+    pub fn polled_at(&self) -> Option<Instant> {
+        self.polled_at.get()
+    }
+
+    pub fn cancel_op<T>(&self, key: Key<T>) {
+        let mut op_runtime = self.op_runtime.borrow_mut();
+        // The slot has been released, if the result was being taken when a
+        // panic happened.
+        if !op_runtime.contains(*key) {
+            return;
+        }
+        // The driver has given the op back, and its user-defined data may be
+        // reused already.
+        if op_runtime.has_result(*key) {
+            op_runtime.remove(*key);
+        } else {
+            let user_data = op_runtime.user_data(*key);
+            self.driver.borrow_mut().cancel(user_data);
+            let waker = op_runtime.cancel(*key);
+            drop(op_runtime);
+            self.release_op_waker(waker);
+            #[cfg(feature = "instrumentation")]
+            self.instrument(|i| i.op_cancelled(user_data));
+        }
+    }
+
+    // Register the waker of a pending op. The waker of the task being polled
+    // is kept in its state once for all of its ops, and the ops refer to it
+    // by the id. The others are cloned, unless the same one is registered.
+    fn update_op_waker(&self, key: usize, waker: &Waker) {
+        let task = self
+            .current_task
+            .get()
+            .filter(|(_, current)| unsafe { &**current }.will_wake(waker))
+            .map(|(id, _)| id);
+        let mut op_runtime = self.op_runtime.borrow_mut();
+        let current = op_runtime.waker_mut(key);
+        let prev = match (task, &*current) {
+            (Some(id), OpWaker::Task(prev)) if *prev == id => return,
+            (None, OpWaker::Waker(prev)) if prev.will_wake(waker) => return,
+            (Some(id), _) => {
+                let mut tasks = self.tasks.borrow_mut();
+                let task = &mut tasks[id];
+                task.waker.get_or_insert_with(|| waker.clone());
+                task.ops += 1;
+                std::mem::replace(current, OpWaker::Task(id))
+            }
+            (None, _) => std::mem::replace(current, OpWaker::Waker(waker.clone())),
+        };
+        drop(op_runtime);
+        self.release_op_waker(prev);
+    }
+
+    // The op no longer wakes the task by its id. The waker of the task is
+    // released after the task is polled.
+    fn release_op_waker(&self, waker: OpWaker) {
+        if let OpWaker::Task(id) = waker {
+            if let Some(task) = self.tasks.borrow_mut().get_mut(id) {
+                task.ops -= 1;
+            }
+        }
+    }
+
+    // Wake the future of an op completed.
+    fn wake_op(&self, waker: OpWaker) {
+        match waker {
+            OpWaker::None => {}
+            OpWaker::Task(id) => {
+                let mut tasks = self.tasks.borrow_mut();
+                if let Some(task) = tasks.get_mut(id) {
+                    task.ops -= 1;
+                    if let Some(waker) = &task.waker {
+                        waker.wake_by_ref();
+                    }
+                }
+            }
+            OpWaker::Waker(waker) => waker.wake(),
+        }
+    }
+
+    // Mark a pending op as using the memory borrowed by `scope_io`.
+    pub fn set_borrowed(&self, key: usize) {
+        self.op_runtime.borrow_mut().set_borrowed(key);
+    }
+
+    // Cancel the ops in flight using the borrowed memory, whether their
+    // futures are dropped or not, e.g., forgotten. Like the interrupted ops,
+    // the results are kept for the futures not dropped.
+    pub fn cancel_borrowed(&self) {
+        let ops = self.op_runtime.borrow().borrowed_in_flight();
+        let mut driver = self.driver.borrow_mut();
+        for user_data in ops {
+            driver.cancel(user_data);
+        }
+    }
+
+    pub fn has_borrowed_in_flight(&self) -> bool {
+        !self.op_runtime.borrow().borrowed_in_flight().is_empty()
+    }
+
+    #[cfg(feature = "time")]
+    pub fn now(&self) -> Instant {
+        self.timer_runtime.borrow().now()
+    }
+
+    // Move the paused clock forward, and wake the timers expired.
+    #[cfg(feature = "test-util")]
+    pub fn advance_time(&self, duration: Duration) {
+        let mut timer_runtime = self.timer_runtime.borrow_mut();
+        timer_runtime.advance(duration);
+        timer_runtime.wake(|len| self.choose(Choice::Timer, len));
+    }
+
+    // The wait of the driver for the timers. Instead of waiting, the paused
+    // clock jumps to the nearest timer if nothing else could wake the
+    // runtime, i.e., no task is ready and no op is in flight.
+    #[cfg(feature = "time")]
+    fn timer_timeout(&self, _idle: bool) -> Option<Duration> {
+        #[allow(unused_mut)]
+        let mut timer_runtime = self.timer_runtime.borrow_mut();
+        #[cfg(feature = "test-util")]
+        if timer_runtime.is_paused() {
+            if _idle && !self.op_runtime.borrow().has_in_flight() {
+                timer_runtime.advance_to_next();
+            }
+            return timer_runtime.min_timeout().filter(|timeout| timeout.is_zero());
+        }
+        timer_runtime.min_timeout()
     }
 
     #[cfg(feature = "time")]
@@ -111,49 +736,155 @@ impl Runtime {
     pub fn poll_task<T: OpCode>(
         &self,
         cx: &mut Context,
-        user_data: Key<T>,
+        key: Key<T>,
     ) -> Poll<(io::Result<usize>, T)> {
         let mut op_runtime = self.op_runtime.borrow_mut();
-        if op_runtime.has_result(*user_data) {
-            let op = op_runtime.remove(*user_data);
-            Poll::Ready((op.result.unwrap(), unsafe {
-                op.op
-                    .expect("`poll_task` called on dummy Op")
-                    .into_inner::<T>()
-            }))
+        if op_runtime.has_result(*key) {
+            drop(op_runtime);
+            Poll::Ready(self.take_result(key))
         } else {
-            op_runtime.update_waker(*user_data, cx.waker().clone());
+            drop(op_runtime);
+            self.update_op_waker(*key, cx.waker());
             Poll::Pending
         }
     }
 
+    // The op should be completed.
+    pub fn take_result<T: OpCode>(&self, key: Key<T>) -> (io::Result<usize>, T) {
+        let op = self.op_runtime.borrow_mut().remove(*key);
+        // A stale key would take the op of another type.
+        debug_assert_eq!(op.name, std::any::type_name::<T>());
+        #[cfg(feature = "metrics")]
+        if let (Some(metrics), Some(completed_at)) = (&self.metrics, op.completed_at) {
+            let delay = completed_at
+                .elapsed()
+                .as_nanos()
+                .try_into()
+                .unwrap_or(u64::MAX);
+            metrics.borrow_mut().completion_delay.record(delay);
+        }
+        (op.result.unwrap(), unsafe {
+            op.op
+                .expect("`take_result` called on dummy Op")
+                .into_inner::<T>()
+        })
+    }
+
     #[cfg(feature = "time")]
     pub fn poll_timer(&self, cx: &mut Context, key: usize) -> Poll<()> {
-        let mut timer_runtime = self.timer_runtime.borrow_mut();
-        if timer_runtime.contains(key) {
-            timer_runtime.update_waker(key, cx.waker().clone());
-            Poll::Pending
+        self.timer_runtime.borrow_mut().poll(cx, key)
+    }
+
+    pub fn set_stall_detector(&self, detector: Option<StallDetector>) {
+        *self.stall_detector.borrow_mut() = detector;
+    }
+
+    // The runtime is watched only if it waits for the ops.
+    fn stall_timeout(&self) -> Option<Duration> {
+        let mut detector = self.stall_detector.borrow_mut();
+        let detector = detector.as_mut()?;
+        if self.op_runtime.borrow().has_in_flight() && !self.tasks.borrow().is_empty() {
+            detector.timeout()
         } else {
-            Poll::Ready(())
+            detector.update(true);
+            None
+        }
+    }
+
+    fn check_stall(&self) {
+        let mut guard = self.stall_detector.borrow_mut();
+        let Some(detector) = guard.as_mut() else {
+            return;
+        };
+        let Some(parked) = detector.update(!self.runnables.borrow().is_empty()) else {
+            return;
+        };
+        let mut tasks = self
+            .tasks
+            .borrow()
+            .iter()
+            .map(|(_, task)| TaskDump {
+                location: task.location,
+                last_polled: task.last_polled,
+            })
+            .collect::<Vec<_>>();
+        tasks.sort_by_key(|task| task.last_polled);
+        let report = StallReport {
+            parked,
+            generation: self.generation.get(),
+            tasks,
+            ops: self.op_runtime.borrow().dump_in_flight(),
+        };
+        // The callback may use the runtime.
+        let Some(mut callback) = detector.callback.take() else {
+            return;
+        };
+        drop(guard);
+        callback(&report);
+        if let Some(detector) = self.stall_detector.borrow_mut().as_mut() {
+            detector.callback.get_or_insert(callback);
         }
     }
 
-    fn poll(&self) {
+    pub fn poll(&self) {
+        self.generation.set(self.generation.get() + 1);
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .borrow_mut()
+                .task_polls
+                .record(self.task_polls.replace(0));
+        }
+
+        // Don't wait if some tasks are waiting for the next tick, or left in the
+        // queues.
+        let idle = self.next_tick.borrow().is_empty() && self.runnables.borrow().is_empty();
         #[cfg(not(feature = "time"))]
-        let timeout = None;
+        let timeout: Option<Duration> = None;
         #[cfg(feature = "time")]
-        let timeout = self.timer_runtime.borrow().min_timeout();
+        let timeout = self.timer_timeout(idle);
+        let timeout = match (timeout, self.stall_timeout()) {
+            (Some(timeout), Some(stall)) => Some(timeout.min(stall)),
+            (timeout, stall) => timeout.or(stall),
+        };
+        let timeout = if idle {
+            timeout
+        } else {
+            Some(Duration::ZERO)
+        };
 
-        let mut entries = SmallVec::<[Entry; 1024]>::new();
+        let mut entries = self.entries.borrow_mut();
+        entries.clear();
         let mut driver = self.driver.borrow_mut();
-        match driver.poll(timeout, &mut entries) {
+        match driver.poll(timeout, &mut *entries) {
             Ok(_) => {
-                for (res, op) in driver.pop(&mut entries.into_iter()) {
-                    self.op_runtime.borrow_mut().update_result(
+                let now = Instant::now();
+                self.polled_at.set(Some(now));
+                if self.max_ops_per_fd > 0 && !entries.is_empty() {
+                    let len = entries.len();
+                    entries.rotate_left((self.generation.get() % len as u64) as usize);
+                }
+                if self.sequencer.borrow().is_some() {
+                    for i in 0..entries.len() {
+                        let j = i + self.choose(Choice::Completion, entries.len() - i);
+                        entries.swap(i, j);
+                    }
+                }
+                for (res, op) in driver.pop(&mut entries.drain(..)) {
+                    #[cfg(feature = "instrumentation")]
+                    self.instrument(|i| i.op_completed(op.user_data(), res.is_ok()));
+                    #[cfg(feature = "metrics")]
+                    if self.metrics.is_some() {
+                        self.op_runtime
+                            .borrow_mut()
+                            .set_completed_at(op.user_data(), now);
+                    }
+                    let waker = self.op_runtime.borrow_mut().update_result(
                         op.user_data(),
                         op.into_inner(),
                         res,
                     );
+                    self.wake_op(waker);
                 }
             }
             Err(e) => match e.kind() {
@@ -161,7 +892,63 @@ impl Runtime {
                 _ => panic!("{:?}", e),
             },
         }
+        let mut messages = self.messages.borrow_mut();
+        let len = messages.len();
+        messages.extend(std::iter::from_fn(|| driver.pop_message()));
+        if messages.len() > len {
+            if let Some(waker) = self.message_waker.borrow_mut().take() {
+                waker.wake();
+            }
+        }
+        #[cfg(feature = "time")]
+        self.timer_runtime
+            .borrow_mut()
+            .wake(|len| self.choose(Choice::Timer, len));
+        drop(messages);
+        drop(driver);
+        drop(entries);
+        // A new tick begins, after the tasks woken by the completions.
+        if self.max_ops_per_fd > 0 {
+            self.fd_ops.borrow_mut().clear();
+        }
+        let mut wakers = self.next_tick_spare.take();
+        std::mem::swap(&mut wakers, &mut *self.next_tick.borrow_mut());
+        for waker in wakers.drain(..) {
+            waker.wake();
+        }
+        *self.next_tick_spare.borrow_mut() = wakers;
+        // The ops pushed before have been submitted.
+        self.submit_queue.borrow_mut().wake_first();
+        self.check_stall();
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        // Dropping the last waker of a detached task schedules it. Drop the
+        // wakers while the queue is still alive, and then drop the tasks.
+        *self.op_runtime.get_mut() = OpRuntime::default();
+        *self.submit_queue.get_mut() = SubmitQueue::default();
+        self.next_tick.get_mut().clear();
         #[cfg(feature = "time")]
-        self.timer_runtime.borrow_mut().wake();
+        {
+            *self.timer_runtime.get_mut() = TimerRuntime::new(Duration::ZERO);
+        }
+        self.message_waker.get_mut().take();
+        let wakers = self
+            .tasks
+            .get_mut()
+            .iter_mut()
+            .filter_map(|(_, task)| task.waker.take())
+            .collect::<Vec<_>>();
+        drop(wakers);
+        // Don't hold the borrow, because dropping a task may schedule others.
+        loop {
+            let runnable = self.runnables.borrow_mut().pop_front();
+            match runnable {
+                Some(runnable) => drop(runnable),
+                None => break,
+            }
+        }
     }
 }
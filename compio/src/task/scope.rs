@@ -0,0 +1,234 @@
+use std::{
+    cell::Cell,
+    future::Future,
+    io,
+    marker::PhantomData,
+    rc::Rc,
+};
+
+use crate::{
+    buf::{IntoInner, IoBuf, IoBufMut},
+    driver::{AsRawFd, OpCode, PushEntry},
+    fs::File,
+    net::TcpStream,
+    op::{BufResultExt, ReadAt, Recv, Send, WriteAt},
+    task::{op::OpFuture, RUNTIME},
+    BufResult,
+};
+
+/// A borrowed buffer with the lifetime erased.
+///
+/// It is only constructed by [`ScopedIo`], which guarantees that the borrowed
+/// memory outlives the kernel access.
+struct ScopedBuf {
+    ptr: *const u8,
+    len: usize,
+}
+
+unsafe impl IoBuf for ScopedBuf {
+    fn as_buf_ptr(&self) -> *const u8 {
+        self.ptr
+    }
+
+    fn buf_len(&self) -> usize {
+        self.len
+    }
+
+    fn buf_capacity(&self) -> usize {
+        self.len
+    }
+}
+
+/// A mutably borrowed buffer with the lifetime erased.
+///
+/// The whole slice is treated as uninitialized, so that it could be filled by
+/// the read operations.
+struct ScopedBufMut {
+    ptr: *mut u8,
+    init: usize,
+    capacity: usize,
+}
+
+unsafe impl IoBuf for ScopedBufMut {
+    fn as_buf_ptr(&self) -> *const u8 {
+        self.ptr
+    }
+
+    fn buf_len(&self) -> usize {
+        self.init
+    }
+
+    fn buf_capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+unsafe impl IoBufMut for ScopedBufMut {
+    fn as_buf_mut_ptr(&mut self) -> *mut u8 {
+        self.ptr
+    }
+
+    unsafe fn set_buf_init(&mut self, len: usize) {
+        self.init += len;
+    }
+}
+
+#[derive(Default)]
+struct ScopeState {
+    closed: Cell<bool>,
+}
+
+impl ScopeState {
+    /// Cancel the operations in flight, and block until all of them are given
+    /// back by the driver.
+    ///
+    /// The futures of some operations may be forgotten instead of dropped, so
+    /// all the operations submitted through the handle are checked, not only
+    /// the cancelled ones. There is only one scope in a thread, as it couldn't
+    /// be nested.
+    fn wait_cancelled(&self) {
+        RUNTIME.with(|runtime| {
+            runtime.cancel_borrowed();
+            while runtime.has_borrowed_in_flight() {
+                runtime.poll();
+            }
+        })
+    }
+}
+
+/// A handle to submit operations with borrowed buffers, created by
+/// [`scope_io`].
+///
+/// All operations submitted through this handle are tracked. When the scope
+/// ends, the operations not completed are cancelled and waited, even if their
+/// futures are forgotten, so the borrowed buffers always outlive the kernel
+/// access. The handle couldn't submit operations after the scope ends.
+pub struct ScopedIo<'env> {
+    state: Rc<ScopeState>,
+    // Invariant over 'env, like `std::thread::Scope`.
+    _p: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'env> ScopedIo<'env> {
+    async fn submit<T: OpCode + 'static>(&self, op: T) -> BufResult<usize, T> {
+        if self.state.closed.get() {
+            return (Err(io::Error::other("the IO scope has ended")), op);
+        }
+        let key = match RUNTIME.with(|runtime| runtime.submit_raw(op)) {
+            PushEntry::Pending(key) => key,
+            PushEntry::Ready(res) => return res,
+        };
+        // If the future is dropped or forgotten before completion, the op is
+        // waited when the scope ends.
+        RUNTIME.with(|runtime| runtime.set_borrowed(*key));
+        OpFuture::new(key).await
+    }
+
+    /// Read some bytes at the specified offset from the file into the borrowed
+    /// buffer. See [`File::read_at`].
+    pub async fn read_at(
+        &self,
+        file: &File,
+        buffer: &'env mut [u8],
+        pos: u64,
+    ) -> io::Result<usize> {
+        file.attach()?;
+        let buffer = ScopedBufMut {
+            ptr: buffer.as_mut_ptr(),
+            init: 0,
+            capacity: buffer.len(),
+        };
+        let op = ReadAt::new(file.as_raw_fd(), pos, buffer);
+        self.submit(op).await.into_inner().map_advanced().0
+    }
+
+    /// Write the borrowed buffer into the file at the specified offset. See
+    /// [`File::write_at`].
+    pub async fn write_at(&self, file: &File, buffer: &'env [u8], pos: u64) -> io::Result<usize> {
+        file.attach()?;
+        let buffer = ScopedBuf {
+            ptr: buffer.as_ptr(),
+            len: buffer.len(),
+        };
+        let op = WriteAt::new(file.as_raw_fd(), pos, buffer);
+        self.submit(op).await.0
+    }
+
+    /// Receive some data from the stream into the borrowed buffer. See
+    /// [`TcpStream::recv`].
+    pub async fn recv(&self, stream: &TcpStream, buffer: &'env mut [u8]) -> io::Result<usize> {
+        stream.attach()?;
+        let buffer = ScopedBufMut {
+            ptr: buffer.as_mut_ptr(),
+            init: 0,
+            capacity: buffer.len(),
+        };
+        let op = Recv::new(stream.as_raw_fd(), buffer);
+        self.submit(op).await.into_inner().map_advanced().0
+    }
+
+    /// Send the borrowed buffer to the stream. See [`TcpStream::send`].
+    pub async fn send(&self, stream: &TcpStream, buffer: &'env [u8]) -> io::Result<usize> {
+        stream.attach()?;
+        let buffer = ScopedBuf {
+            ptr: buffer.as_ptr(),
+            len: buffer.len(),
+        };
+        let op = Send::new(stream.as_raw_fd(), buffer);
+        self.submit(op).await.0
+    }
+}
+
+struct WaitGuard(Rc<ScopeState>);
+
+impl Drop for WaitGuard {
+    fn drop(&mut self) {
+        self.0.closed.set(true);
+        self.0.wait_cancelled();
+    }
+}
+
+/// Run a future with a [`ScopedIo`] handle, which accepts borrowed buffers,
+/// and block until it completes.
+///
+/// Unlike [`block_on`], the operations submitted through the handle don't
+/// require owned buffers. It is sound because this function doesn't return,
+/// even on panic, until all the operations submitted through the handle are
+/// completed or fully cancelled. Like [`block_on`], it panics if called
+/// inside [`block_on`] or another `scope_io`.
+///
+/// The handle is passed by value, so the future should be an `async move`
+/// block. Borrow the buffers outside of the closure to make them live longer
+/// than the scope.
+///
+/// [`block_on`]: crate::task::block_on
+///
+/// ```
+/// use compio::fs::File;
+///
+/// let temp = tempfile::NamedTempFile::new().unwrap();
+/// let file = File::create(temp.path()).unwrap();
+/// let big_slice = vec![1u8; 4096];
+///
+/// let (file_ref, slice_ref) = (&file, big_slice.as_slice());
+/// let written =
+///     compio::task::scope_io(
+///         |io| async move { io.write_at(file_ref, slice_ref, 0).await.unwrap() },
+///     );
+/// assert_eq!(written, big_slice.len());
+/// ```
+#[track_caller]
+pub fn scope_io<'env, F, Fut>(f: F) -> Fut::Output
+where
+    F: FnOnce(ScopedIo<'env>) -> Fut,
+    Fut: Future,
+{
+    let state = Rc::new(ScopeState::default());
+    let _guard = WaitGuard(state.clone());
+    let io = ScopedIo {
+        state,
+        _p: PhantomData,
+    };
+    let location = std::panic::Location::caller();
+    RUNTIME.with(|runtime| runtime.block_on(f(io), location))
+}
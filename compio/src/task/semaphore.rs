@@ -0,0 +1,161 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+struct State {
+    permits: usize,
+    next_id: u64,
+    // The pending acquirers in FIFO order.
+    waiters: VecDeque<(u64, Waker)>,
+}
+
+impl State {
+    // Wake the first waiter if there are permits for it.
+    fn wake_first(&self) {
+        if self.permits > 0 {
+            if let Some((_, waker)) = self.waiters.front() {
+                waker.wake_by_ref();
+            }
+        }
+    }
+}
+
+/// A semaphore limiting the concurrency of the tasks of current runtime.
+///
+/// The permits are handed to the waiters in the order they started to
+/// acquire. Cloning it shares the permits.
+///
+/// ```
+/// use compio::task::Semaphore;
+///
+/// compio::task::block_on(async {
+///     let semaphore = Semaphore::new(2);
+///     let first = semaphore.acquire().await;
+///     let _second = semaphore.acquire().await;
+///     assert!(semaphore.try_acquire().is_none());
+///     drop(first);
+///     assert_eq!(semaphore.available_permits(), 1);
+/// })
+/// ```
+#[derive(Clone)]
+pub struct Semaphore {
+    state: Rc<RefCell<State>>,
+}
+
+impl Semaphore {
+    /// Create [`Semaphore`] with the count of the permits.
+    pub fn new(permits: usize) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(State {
+                permits,
+                next_id: 0,
+                waiters: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// The count of the permits not acquired.
+    pub fn available_permits(&self) -> usize {
+        self.state.borrow().permits
+    }
+
+    /// Acquire a permit if there is one and no one is waiting.
+    pub fn try_acquire(&self) -> Option<SemaphorePermit> {
+        let mut state = self.state.borrow_mut();
+        if state.permits > 0 && state.waiters.is_empty() {
+            state.permits -= 1;
+            Some(SemaphorePermit {
+                state: self.state.clone(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Wait for a permit. Dropping the future gives up the place in the
+    /// queue.
+    pub fn acquire(&self) -> Acquire {
+        Acquire {
+            state: self.state.clone(),
+            id: None,
+        }
+    }
+}
+
+/// The future of [`Semaphore::acquire`].
+pub struct Acquire {
+    state: Rc<RefCell<State>>,
+    // Set when queued.
+    id: Option<u64>,
+}
+
+impl Future for Acquire {
+    type Output = SemaphorePermit;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut state = this.state.borrow_mut();
+        let first = match this.id {
+            Some(id) => state.waiters.front().map(|(i, _)| *i) == Some(id),
+            None => state.waiters.is_empty(),
+        };
+        if first && state.permits > 0 {
+            state.permits -= 1;
+            if this.id.take().is_some() {
+                state.waiters.pop_front();
+            }
+            state.wake_first();
+            drop(state);
+            return Poll::Ready(SemaphorePermit {
+                state: this.state.clone(),
+            });
+        }
+        match this.id {
+            Some(id) => {
+                if let Some((_, waker)) = state.waiters.iter_mut().find(|(i, _)| *i == id) {
+                    waker.clone_from(cx.waker());
+                }
+            }
+            None => {
+                let id = state.next_id;
+                state.next_id += 1;
+                state.waiters.push_back((id, cx.waker().clone()));
+                this.id = Some(id);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            let mut state = self.state.borrow_mut();
+            if let Some(index) = state.waiters.iter().position(|(i, _)| *i == id) {
+                state.waiters.remove(index);
+                // It may have been woken for the permit.
+                if index == 0 {
+                    state.wake_first();
+                }
+            }
+        }
+    }
+}
+
+/// A permit of [`Semaphore`], released when dropped.
+pub struct SemaphorePermit {
+    state: Rc<RefCell<State>>,
+}
+
+impl Drop for SemaphorePermit {
+    fn drop(&mut self) {
+        let mut state = self.state.borrow_mut();
+        state.permits += 1;
+        state.wake_first();
+    }
+}
@@ -0,0 +1,134 @@
+use std::hash::{BuildHasher, Hasher};
+
+/// The environment variable to override the seed of
+/// [`SeededSequencer::from_env`] and [`RuntimeBuilder::deterministic`], e.g.,
+/// to replay the seed of a failed CI run.
+///
+/// [`RuntimeBuilder::deterministic`]: crate::task::RuntimeBuilder::deterministic
+pub const SEED_ENV: &str = "COMPIO_SEED";
+
+// The seed in the environment variable `SEED_ENV`, if set.
+pub(crate) fn env_seed() -> Option<u64> {
+    let seed = std::env::var(SEED_ENV).ok()?;
+    Some(
+        seed.trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("{SEED_ENV}={seed:?} is not a valid seed")),
+    )
+}
+
+/// An ordering decision of the runtime, see [`Sequencer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Choice {
+    /// The next task to run, among the runnable ones in the order they are
+    /// scheduled.
+    Task,
+    /// The next completion to deliver, among the ones given back by a poll of
+    /// the driver.
+    Completion,
+    /// The next timer to fire, among the expired ones with the same deadline.
+    Timer,
+}
+
+/// The ordering decisions of the runtime, set by [`set_sequencer`]. Without a
+/// sequencer, the runtime always chooses the first candidate, i.e., the tasks
+/// run in the order they are scheduled, and the completions and the timers
+/// are delivered in the order they are got.
+///
+/// The sequencer is called on the runtime thread while the runtime is
+/// borrowed, so it should not call the functions of the runtime.
+///
+/// [`set_sequencer`]: crate::task::set_sequencer
+pub trait Sequencer {
+    /// Choose the next one among `len` candidates, which is more than one. The
+    /// returned index should be less than `len`.
+    fn choose(&mut self, choice: Choice, len: usize) -> usize;
+}
+
+impl<F: FnMut(Choice, usize) -> usize> Sequencer for F {
+    fn choose(&mut self, choice: Choice, len: usize) -> usize {
+        self(choice, len)
+    }
+}
+
+/// A [`Sequencer`] making the choices pseudo-randomly from a seed, to explore
+/// the interleavings of the tasks, and to replay the one of a seed.
+///
+/// The same seed makes the same choices for the same candidates, so the
+/// interleavings of the tasks woken by each other, e.g., with the channels,
+/// are replayed exactly. However, the runtime can't control when the kernel
+/// completes the operations, so the interleavings depending on the IO or the
+/// clock may still differ between the runs.
+///
+/// ```
+/// use std::{cell::RefCell, rc::Rc};
+///
+/// use compio::task::SeededSequencer;
+///
+/// let sequencer = SeededSequencer::from_env();
+/// let seed = sequencer.seed();
+/// compio::task::set_sequencer(sequencer);
+/// let order = Rc::new(RefCell::new(vec![]));
+/// compio::task::block_on(async {
+///     let tasks = (0..4)
+///         .map(|i| {
+///             let order = order.clone();
+///             compio::task::spawn(async move { order.borrow_mut().push(i) })
+///         })
+///         .collect::<Vec<_>>();
+///     for task in tasks {
+///         task.await;
+///     }
+/// });
+/// compio::task::remove_sequencer();
+/// println!("seed {seed}: {:?}", order.borrow());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SeededSequencer {
+    seed: u64,
+    state: u64,
+}
+
+impl SeededSequencer {
+    /// Create the sequencer with `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: seed }
+    }
+
+    /// Create the sequencer with the seed in the environment variable
+    /// [`SEED_ENV`], or a random one if it is not set. The seed should be
+    /// printed when a test fails, see [`seed`](Self::seed).
+    ///
+    /// # Panics
+    ///
+    /// It panics if the environment variable is not a `u64`.
+    pub fn from_env() -> Self {
+        let seed = env_seed().unwrap_or_else(|| {
+            std::collections::hash_map::RandomState::new()
+                .build_hasher()
+                .finish()
+        });
+        Self::new(seed)
+    }
+
+    /// The seed of the sequencer.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // SplitMix64.
+    pub(crate) fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl Sequencer for SeededSequencer {
+    fn choose(&mut self, _choice: Choice, len: usize) -> usize {
+        ((self.next() as u128 * len as u128) >> 64) as usize
+    }
+}
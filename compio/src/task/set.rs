@@ -0,0 +1,179 @@
+use std::{
+    collections::VecDeque,
+    future::poll_fn,
+    marker::PhantomData,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use futures_util::Stream;
+
+use crate::{
+    driver::{OpCode, PushEntry},
+    task::{op::ReadyQueue, RUNTIME},
+    BufResult, Key,
+};
+
+/// A set of submitted operations, yielding the results in the order of
+/// completion.
+///
+/// Awaiting many [`submit`](super::submit) futures together, e.g., with
+/// `join_all`, wakes the task once per completion, and it may poll all the
+/// pending futures each time. The completions of an [`OpSet`] are routed to
+/// the set by the runtime, so only the completed operations are visited.
+///
+/// Dropping it cancels the pending operations.
+///
+/// ```
+/// use compio::{driver::AsRawFd, fs::File, op::ReadAt, task::submit_set};
+///
+/// compio::task::block_on(async {
+///     let file = File::open("Cargo.toml").unwrap();
+///     compio::task::attach(file.as_raw_fd()).unwrap();
+///     let mut set = submit_set(
+///         (0..16).map(|i| ReadAt::new(file.as_raw_fd(), i, Vec::with_capacity(1))),
+///     );
+///     let mut count = 0;
+///     while let Some((res, _)) = set.next().await {
+///         assert_eq!(res.unwrap(), 1);
+///         count += 1;
+///     }
+///     assert_eq!(count, 16);
+/// })
+/// ```
+pub struct OpSet<T> {
+    ready: Rc<ReadyQueue>,
+    // The operations completed inline, which are yielded first.
+    completed: VecDeque<BufResult<usize, T>>,
+    // Indexed by the keys of the runtime, which are small integers.
+    pending: Vec<bool>,
+    len: usize,
+    _p: PhantomData<fn() -> T>,
+}
+
+// The completed operations are never pinned.
+impl<T> Unpin for OpSet<T> {}
+
+impl<T: OpCode + 'static> OpSet<T> {
+    /// Create an empty [`OpSet`].
+    pub fn new() -> Self {
+        Self {
+            ready: Rc::default(),
+            completed: VecDeque::new(),
+            pending: Vec::new(),
+            len: 0,
+            _p: PhantomData,
+        }
+    }
+
+    /// Submit an operation into the set. It is yielded with the error of
+    /// [`submit`](super::submit) if the runtime of current thread is not
+    /// running.
+    pub fn push(&mut self, op: T) {
+        if let Err(e) = super::runtime::check_running() {
+            self.completed.push_back((Err(e), op));
+            self.len += 1;
+            return;
+        }
+        match RUNTIME.with(|runtime| runtime.submit_ready(op, self.ready.clone())) {
+            PushEntry::Pending(key) => {
+                if self.pending.len() <= *key {
+                    self.pending.resize(*key + 1, false);
+                }
+                self.pending[*key] = true;
+            }
+            PushEntry::Ready(res) => self.completed.push_back(res),
+        }
+        self.len += 1;
+    }
+
+    /// The count of the operations not yielded yet.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// If all operations are yielded.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Poll the next completed operation. Returns `None` if the set is
+    /// empty.
+    pub fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<BufResult<usize, T>>> {
+        if self.len == 0 {
+            return Poll::Ready(None);
+        }
+        if let Some(res) = self.completed.pop_front() {
+            self.len -= 1;
+            return Poll::Ready(Some(res));
+        }
+        match self.ready.pop() {
+            Some(key) => {
+                self.pending[key] = false;
+                self.len -= 1;
+                let key = unsafe { Key::<T>::new(key) };
+                Poll::Ready(Some(RUNTIME.with(|runtime| runtime.take_result(key))))
+            }
+            None => {
+                self.ready.update_waker(cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Wait for the next completed operation. Returns `None` if the set is
+    /// empty.
+    pub async fn next(&mut self) -> Option<BufResult<usize, T>> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+}
+
+impl<T: OpCode + 'static> Default for OpSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: OpCode + 'static> Extend<T> for OpSet<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for op in iter {
+            self.push(op);
+        }
+    }
+}
+
+impl<T: OpCode + 'static> FromIterator<T> for OpSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: OpCode + 'static> Stream for OpSet<T> {
+    type Item = BufResult<usize, T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
+    }
+}
+
+impl<T> Drop for OpSet<T> {
+    fn drop(&mut self) {
+        // The runtime may have been destroyed with its tasks.
+        RUNTIME
+            .try_with(|runtime| {
+                for (key, pending) in self.pending.iter().enumerate() {
+                    if *pending {
+                        runtime.cancel_op(unsafe { Key::<T>::new(key) });
+                    }
+                }
+            })
+            .ok();
+    }
+}
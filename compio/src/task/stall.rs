@@ -0,0 +1,200 @@
+use std::{
+    fmt::Display,
+    future::Future,
+    mem::ManuallyDrop,
+    panic::Location,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use crate::task::RUNTIME;
+
+/// The report of a stalled runtime, see [`set_stall_detector`].
+///
+/// The runtime counts the generations by its polls of the driver. The tasks
+/// and the operations are stamped with the generation when they are polled or
+/// submitted.
+///
+/// [`set_stall_detector`]: crate::task::set_stall_detector
+#[derive(Debug, Clone)]
+pub struct StallReport {
+    /// How long the runtime has been parked without waking any task.
+    pub parked: Duration,
+    /// The current generation.
+    pub generation: u64,
+    /// The pending tasks, the least recently polled first.
+    pub tasks: Vec<TaskDump>,
+    /// The operations in flight, the earliest submitted first.
+    pub ops: Vec<OpDump>,
+}
+
+/// A pending task in a [`StallReport`].
+#[derive(Debug, Clone, Copy)]
+pub struct TaskDump {
+    /// Where the task is spawned.
+    pub location: &'static Location<'static>,
+    /// The generation when the task was polled the last time.
+    pub last_polled: u64,
+}
+
+/// An operation in flight in a [`StallReport`].
+#[derive(Debug, Clone, Copy)]
+pub struct OpDump {
+    /// The type name of the operation.
+    pub name: &'static str,
+    /// The generation when the operation was submitted.
+    pub submitted: u64,
+    /// Whether the operation is cancelled, and waiting for the driver to give
+    /// it back.
+    pub cancelled: bool,
+}
+
+impl Display for StallReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "runtime parked for {:?} at generation {}",
+            self.parked, self.generation
+        )?;
+        writeln!(f, "{} pending tasks:", self.tasks.len())?;
+        for task in &self.tasks {
+            writeln!(
+                f,
+                "  spawned at {}, last polled at generation {}",
+                task.location, task.last_polled
+            )?;
+        }
+        writeln!(f, "{} operations in flight:", self.ops.len())?;
+        for op in &self.ops {
+            write!(f, "  {}, submitted at generation {}", op.name, op.submitted)?;
+            if op.cancelled {
+                f.write_str(", cancelled")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) struct TaskState {
+    pub location: &'static Location<'static>,
+    pub last_polled: u64,
+    // The waker of the task, kept while the ops registered by its id are in
+    // flight, instead of a clone in each of them.
+    pub waker: Option<Waker>,
+    // The ops in flight waking the task by its id.
+    pub ops: u32,
+}
+
+// Records the generation when the task is polled, and the task being polled,
+// so that its ops wake it by its id.
+pub(crate) struct TrackedTask<F> {
+    id: usize,
+    // Dropped before the task is removed, so that its ops release the id
+    // before it could be reused.
+    future: ManuallyDrop<F>,
+}
+
+impl<F> TrackedTask<F> {
+    pub fn new(id: usize, future: F) -> Self {
+        Self {
+            id,
+            future: ManuallyDrop::new(future),
+        }
+    }
+}
+
+impl<F: Future> Future for TrackedTask<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let id = self.id;
+        let _guard = PollGuard::enter(id, cx.waker());
+        #[cfg(feature = "instrumentation")]
+        let started = RUNTIME.with(|runtime| runtime.instrument(|i| i.poll_started(id)));
+        let res = unsafe { self.map_unchecked_mut(|this| &mut *this.future) }.poll(cx);
+        #[cfg(feature = "instrumentation")]
+        RUNTIME.with(|runtime| runtime.instrument(|i| i.poll_ended(id, started)));
+        res
+    }
+}
+
+// The task being polled, restored when dropped, even on panic.
+struct PollGuard {
+    id: usize,
+    prev: Option<(usize, *const Waker)>,
+}
+
+impl PollGuard {
+    fn enter(id: usize, waker: &Waker) -> Self {
+        let prev = RUNTIME.with(|runtime| runtime.enter_task(id, waker));
+        Self { id, prev }
+    }
+}
+
+impl Drop for PollGuard {
+    fn drop(&mut self) {
+        RUNTIME
+            .try_with(|runtime| runtime.leave_task(self.id, self.prev))
+            .ok();
+    }
+}
+
+impl<F> Drop for TrackedTask<F> {
+    fn drop(&mut self) {
+        // Dropped in place, as it is pinned.
+        unsafe { ManuallyDrop::drop(&mut self.future) };
+        // The runtime may have been destroyed with its tasks.
+        RUNTIME.try_with(|runtime| runtime.task_dropped(self.id)).ok();
+    }
+}
+
+type StallCallback = Box<dyn FnMut(&StallReport)>;
+
+pub(crate) struct StallDetector {
+    pub threshold: Duration,
+    pub callback: Option<StallCallback>,
+    // When the runtime begins waiting without waking any task.
+    pub parked_since: Option<Instant>,
+    // Report once for each stall.
+    pub reported: bool,
+}
+
+impl StallDetector {
+    pub fn new(threshold: Duration, callback: StallCallback) -> Self {
+        Self {
+            threshold,
+            callback: Some(callback),
+            parked_since: None,
+            reported: false,
+        }
+    }
+
+    // The longest time the driver could wait before the stall is reported.
+    pub fn timeout(&mut self) -> Option<Duration> {
+        let parked_since = *self.parked_since.get_or_insert_with(Instant::now);
+        if self.reported {
+            None
+        } else {
+            Some(self.threshold.saturating_sub(parked_since.elapsed()))
+        }
+    }
+
+    // Returns how long the runtime has been parked, if the stall should be
+    // reported.
+    pub fn update(&mut self, progressed: bool) -> Option<Duration> {
+        if progressed {
+            self.parked_since = None;
+            self.reported = false;
+            return None;
+        }
+        let parked = self.parked_since?.elapsed();
+        if !self.reported && parked >= self.threshold {
+            self.reported = true;
+            Some(parked)
+        } else {
+            None
+        }
+    }
+}
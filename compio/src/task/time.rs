@@ -28,55 +28,139 @@ impl PartialOrd for TimerEntry {
     }
 }
 
+// `BinaryHeap` is a max-heap, and the nearest timer should be on the top.
 impl Ord for TimerEntry {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.delay.cmp(&other.delay)
+        other.delay.cmp(&self.delay)
     }
 }
 
+// A timer in the runtime, until its future completes or is dropped. The key
+// of an expired timer is kept until then, so that it isn't reused by another
+// timer while the future still refers to it.
+struct TimerState {
+    delay: Duration,
+    waker: Option<Waker>,
+    expired: bool,
+}
+
+// The resolution of `sleep_coarse` if the runtime doesn't set one.
+const DEFAULT_COARSE_RESOLUTION: Duration = Duration::from_millis(10);
+
 pub struct TimerRuntime {
     time: Instant,
-    tasks: Slab<Option<Waker>>,
+    // The virtual time since `time` when the clock is paused, which only
+    // moves forward by `advance`.
+    paused: Option<Duration>,
+    // The deadlines are rounded up to the multiples of it since `time`, so
+    // that the timers close to each other expire at once.
+    resolution: Duration,
+    tasks: Slab<TimerState>,
     wheel: BinaryHeap<TimerEntry>,
+    // The wakers of the timers expired at the same deadline, kept allocated.
+    expired: Vec<Waker>,
 }
 
 impl TimerRuntime {
-    pub fn new() -> Self {
+    pub fn new(resolution: Duration) -> Self {
         Self {
             time: Instant::now(),
+            paused: None,
+            resolution,
             tasks: Slab::default(),
             wheel: BinaryHeap::default(),
+            expired: Vec::new(),
         }
     }
 
-    pub fn contains(&self, key: usize) -> bool {
-        self.tasks.contains(key)
-    }
-
-    pub fn insert(&mut self, mut delay: Duration) -> Option<usize> {
+    pub fn insert(&mut self, mut delay: Duration, coarse: bool) -> Option<usize> {
         if delay.is_zero() {
             return None;
         }
-        let elapsed = self.time.elapsed();
-        let key = self.tasks.insert(None);
-        delay += elapsed;
+        delay += self.elapsed();
+        let resolution = if !self.resolution.is_zero() {
+            self.resolution
+        } else if coarse {
+            DEFAULT_COARSE_RESOLUTION
+        } else {
+            Duration::ZERO
+        };
+        if !resolution.is_zero() {
+            delay = round_up(delay, resolution);
+        }
+        let key = self.tasks.insert(TimerState {
+            delay,
+            waker: None,
+            expired: false,
+        });
         let entry = TimerEntry { key, delay };
         self.wheel.push(entry);
         Some(key)
     }
 
-    pub fn update_waker(&mut self, key: usize, waker: Waker) {
-        if let Some(w) = self.tasks.get_mut(key) {
-            *w = Some(waker);
+    pub fn poll(&mut self, cx: &mut Context, key: usize) -> Poll<()> {
+        match self.tasks.get_mut(key) {
+            Some(timer) if !timer.expired => {
+                timer.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            _ => {
+                self.tasks.try_remove(key);
+                Poll::Ready(())
+            }
         }
     }
 
     pub fn cancel(&mut self, key: usize) {
-        self.tasks.remove(key);
+        self.tasks.try_remove(key);
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.paused.unwrap_or_else(|| self.time.elapsed())
+    }
+
+    pub fn now(&self) -> Instant {
+        self.time + self.elapsed()
+    }
+
+    #[cfg(feature = "test-util")]
+    pub fn pause(&mut self) {
+        self.paused.get_or_insert(self.time.elapsed());
+    }
+
+    #[cfg(feature = "test-util")]
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    #[cfg(feature = "test-util")]
+    pub fn advance(&mut self, duration: Duration) {
+        let elapsed = self
+            .paused
+            .as_mut()
+            .expect("the clock is not paused, see `ProactorBuilder::start_paused`");
+        *elapsed += duration;
+    }
+
+    // Move the paused clock to the deadline of the nearest timer, skipping the
+    // entries of the cancelled ones.
+    #[cfg(feature = "test-util")]
+    pub fn advance_to_next(&mut self) {
+        while let Some(entry) = self.wheel.peek() {
+            match self.tasks.get(entry.key) {
+                Some(timer) if !timer.expired && timer.delay == entry.delay => break,
+                _ => {
+                    self.wheel.pop();
+                }
+            }
+        }
+        if let (Some(elapsed), Some(entry)) = (self.paused.as_mut(), self.wheel.peek()) {
+            *elapsed = (*elapsed).max(entry.delay);
+        }
     }
 
     pub fn min_timeout(&self) -> Option<Duration> {
-        let elapsed = self.time.elapsed();
+        let elapsed = self.elapsed();
         self.wheel.peek().map(|entry| {
             if entry.delay > elapsed {
                 entry.delay - elapsed
@@ -86,34 +170,58 @@ impl TimerRuntime {
         })
     }
 
-    pub fn wake(&mut self) {
-        let elapsed = self.time.elapsed();
-        while let Some(entry) = self.wheel.pop() {
-            if entry.delay <= elapsed {
-                if self.tasks.contains(entry.key) {
-                    if let Some(waker) = self.tasks.remove(entry.key) {
-                        waker.wake();
+    // All the expired timers are woken in one pass, and their tasks run after
+    // it, so a batch of timers costs one wakeup of the driver. The timers with
+    // the same deadline are woken in the order chosen by `choose`, which picks
+    // the next one among the given count of them.
+    pub fn wake(&mut self, mut choose: impl FnMut(usize) -> usize) {
+        let elapsed = self.elapsed();
+        while let Some(deadline) = self.wheel.peek().map(|entry| entry.delay) {
+            if deadline > elapsed {
+                break;
+            }
+            while let Some(entry) = self.wheel.peek() {
+                if entry.delay != deadline {
+                    break;
+                }
+                let entry = self.wheel.pop().unwrap();
+                // The entry of a cancelled timer may refer to a key reused by
+                // another one, with another deadline.
+                if let Some(timer) = self.tasks.get_mut(entry.key) {
+                    if !timer.expired && timer.delay == entry.delay {
+                        timer.expired = true;
+                        self.expired.extend(timer.waker.take());
                     }
                 }
-            } else {
-                self.wheel.push(entry);
-                break;
+            }
+            for i in 0..self.expired.len() {
+                let j = i + choose(self.expired.len() - i);
+                self.expired.swap(i, j);
+            }
+            for waker in self.expired.drain(..) {
+                waker.wake();
             }
         }
     }
 }
 
+fn round_up(delay: Duration, resolution: Duration) -> Duration {
+    let resolution = resolution.as_nanos();
+    let delay = delay.as_nanos().div_ceil(resolution) * resolution;
+    Duration::new(
+        (delay / 1_000_000_000) as u64,
+        (delay % 1_000_000_000) as u32,
+    )
+}
+
 pub struct TimerFuture {
-    key: usize,
-    completed: bool,
+    // Released once the timer fires, like the key of `OpFuture`.
+    key: Option<usize>,
 }
 
 impl TimerFuture {
     pub fn new(key: usize) -> Self {
-        Self {
-            key,
-            completed: false,
-        }
+        Self { key: Some(key) }
     }
 }
 
@@ -121,9 +229,11 @@ impl Future for TimerFuture {
     type Output = ();
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let res = crate::task::RUNTIME.with(|runtime| runtime.poll_timer(cx, self.key));
+        let this = self.get_mut();
+        let key = this.key.expect("`TimerFuture` polled after completion");
+        let res = crate::task::RUNTIME.with(|runtime| runtime.poll_timer(cx, key));
         if res.is_ready() {
-            self.get_mut().completed = true;
+            this.key = None;
         }
         res
     }
@@ -131,8 +241,11 @@ impl Future for TimerFuture {
 
 impl Drop for TimerFuture {
     fn drop(&mut self) {
-        if !self.completed {
-            crate::task::RUNTIME.with(|runtime| runtime.cancel_timer(self.key));
+        if let Some(key) = self.key.take() {
+            // The runtime may have been destroyed with its tasks.
+            crate::task::RUNTIME
+                .try_with(|runtime| runtime.cancel_timer(key))
+                .ok();
         }
     }
 }
@@ -1,4 +1,11 @@
 //! Utilities for tracking time.
+//!
+//! The timers follow the clock of the runtime, which could be paused in the
+//! tests with the feature `test-util`, see
+//! [`ProactorBuilder::start_paused`]. Get the time of it by [`now`] instead of
+//! [`Instant::now`] to compute the deadlines.
+//!
+//! [`ProactorBuilder::start_paused`]: crate::driver::ProactorBuilder::start_paused
 
 use std::{
     error::Error,
@@ -9,13 +16,62 @@ use std::{
 
 use futures_util::{select, FutureExt};
 
+/// The current time of the clock of the timers, which is [`Instant::now`]
+/// unless the clock is paused, see [`ProactorBuilder::start_paused`].
+///
+/// [`ProactorBuilder::start_paused`]: crate::driver::ProactorBuilder::start_paused
+pub fn now() -> Instant {
+    crate::task::RUNTIME.with(|runtime| runtime.now())
+}
+
+/// Advance the paused clock by `duration`, and wait for the timers expired to
+/// be woken. It is the only way to move the clock while an operation is in
+/// flight.
+///
+/// # Panics
+///
+/// It panics if the clock is not paused, see
+/// [`ProactorBuilder::start_paused`].
+///
+/// [`ProactorBuilder::start_paused`]: crate::driver::ProactorBuilder::start_paused
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use compio::{driver::ProactorBuilder, time::advance};
+///
+/// std::thread::spawn(|| {
+///     compio::task::init_with(&ProactorBuilder::new().start_paused(true)).unwrap();
+///     compio::task::block_on(async {
+///         let timer = compio::task::spawn(compio::time::sleep(Duration::from_secs(60)));
+///         // Let the timer start.
+///         compio::task::yield_now().await;
+///         advance(Duration::from_secs(60)).await;
+///         assert!(timer.is_finished());
+///     })
+/// })
+/// .join()
+/// .unwrap();
+/// ```
+#[cfg(feature = "test-util")]
+pub async fn advance(duration: Duration) {
+    crate::task::RUNTIME.with(|runtime| runtime.advance_time(duration));
+    crate::task::yield_now().await
+}
+
 /// Waits until `duration` has elapsed.
 ///
-/// Equivalent to [`sleep_until(Instant::now() + duration)`](sleep_until). An
+/// Equivalent to [`sleep_until(now() + duration)`](sleep_until). An
 /// asynchronous analog to [`std::thread::sleep`].
 ///
 /// To run something regularly on a schedule, see [`interval`].
 ///
+/// If the runtime sets [`ProactorBuilder::timer_resolution`], the deadline is
+/// rounded up to it, e.g., `sleep(1ms)` with a 10ms resolution may wait for
+/// 10ms.
+///
+/// [`ProactorBuilder::timer_resolution`]: crate::driver::ProactorBuilder::timer_resolution
+///
 /// # Examples
 ///
 /// Wait 100ms and print "100 ms have elapsed".
@@ -32,7 +88,34 @@ use futures_util::{select, FutureExt};
 /// ```
 pub async fn sleep(duration: Duration) {
     crate::task::RUNTIME
-        .with(|runtime| runtime.create_timer(duration))
+        .with(|runtime| runtime.create_timer(duration, false))
+        .await
+}
+
+/// Waits until `duration` has elapsed, with a coarse deadline.
+///
+/// The deadline is rounded up to the resolution set by
+/// [`ProactorBuilder::timer_resolution`], or 10ms if it is not set, so that
+/// the coarse timers close to each other expire in one wakeup. It may wait at
+/// most one resolution longer than `duration`. It suits the timeouts which
+/// don't need to be precise, e.g., the idle timeouts of many connections.
+///
+/// [`ProactorBuilder::timer_resolution`]: crate::driver::ProactorBuilder::timer_resolution
+///
+/// ```
+/// use std::time::{Duration, Instant};
+///
+/// use compio::time::sleep_coarse;
+///
+/// compio::task::block_on(async {
+///     let start = Instant::now();
+///     sleep_coarse(Duration::from_millis(1)).await;
+///     assert!(start.elapsed() >= Duration::from_millis(1));
+/// })
+/// ```
+pub async fn sleep_coarse(duration: Duration) {
+    crate::task::RUNTIME
+        .with(|runtime| runtime.create_timer(duration, true))
         .await
 }
 
@@ -55,7 +138,7 @@ pub async fn sleep(duration: Duration) {
 /// })
 /// ```
 pub async fn sleep_until(deadline: Instant) {
-    sleep(deadline - Instant::now()).await
+    sleep(deadline - now()).await
 }
 
 /// Error returned by [`timeout`] or [`timeout_at`].
@@ -87,7 +170,7 @@ pub async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Outp
 /// If the future completes before the instant is reached, then the completed
 /// value is returned. Otherwise, an error is returned.
 pub async fn timeout_at<F: Future>(deadline: Instant, future: F) -> Result<F::Output, Elapsed> {
-    timeout(deadline - Instant::now(), future).await
+    timeout(deadline - now(), future).await
 }
 
 /// Interval returned by [`interval`] and [`interval_at`]
@@ -120,7 +203,7 @@ impl Interval {
             self.first_ticked = true;
             self.start
         } else {
-            let now = Instant::now();
+            let now = now();
             let next = now + self.period
                 - Duration::from_nanos(
                     ((now - self.start).as_nanos() % self.period.as_nanos()) as _,
@@ -138,7 +221,7 @@ impl Interval {
 /// be dropped. This cancels the interval.
 ///
 /// This function is equivalent to
-/// [`interval_at(Instant::now(), period)`](interval_at).
+/// [`interval_at(now(), period)`](interval_at).
 ///
 /// # Panics
 ///
@@ -195,7 +278,7 @@ impl Interval {
 /// [`sleep`]: crate::time::sleep()
 /// [`.tick().await`]: Interval::tick
 pub fn interval(period: Duration) -> Interval {
-    interval_at(Instant::now(), period)
+    interval_at(now(), period)
 }
 
 /// Creates new [`Interval`] that yields with interval of `period` with the
@@ -0,0 +1,102 @@
+use std::net::Ipv4Addr;
+
+use compio::net::{AdaptiveRecv, TcpListener, TcpStream};
+
+const SMALL: usize = 200;
+const LARGE: usize = 64 * 1024;
+
+async fn connect() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, (server, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    (client, server)
+}
+
+// One large message in every 20, the others small.
+fn message_len(i: usize) -> usize {
+    if i % 20 == 7 { LARGE } else { SMALL + i % 50 }
+}
+
+#[test]
+fn bimodal() {
+    compio::task::block_on(async {
+        let (client, server) = connect().await;
+        let mut server = AdaptiveRecv::new(server).max_size(LARGE);
+        let mut allocated = 0;
+        let mut recvs = 0;
+        let mut split = 0;
+        for i in 0..1000 {
+            let len = message_len(i);
+            client.send_all(vec![i as u8; len]).await.0.unwrap();
+            // The data is in the socket when the send completes on the
+            // loopback.
+            let mut received = 0;
+            let mut parts = 0;
+            while received < len {
+                let (res, buf) = server.recv().await;
+                let n = res.unwrap();
+                assert!(n > 0);
+                assert!(buf.iter().all(|b| *b == i as u8));
+                allocated += buf.capacity();
+                received += n;
+                parts += 1;
+            }
+            assert_eq!(received, len);
+            recvs += parts;
+            if parts > 1 {
+                split += 1;
+                // The rest is read at once.
+                assert_eq!(parts, 2, "message {i} of {len} bytes");
+            }
+            assert!(server.next_size() <= LARGE);
+        }
+        // Allocating the max size for each receive.
+        let naive = recvs * LARGE;
+        assert!(
+            allocated * 4 < naive,
+            "{allocated} bytes allocated, {naive} bytes if not adaptive"
+        );
+        // Only the large messages after the small ones are split.
+        assert!(split <= 50, "{split} messages split");
+    })
+}
+
+#[test]
+fn grow_and_shrink() {
+    compio::task::block_on(async {
+        let (client, server) = connect().await;
+        let mut server = AdaptiveRecv::new(server).min_size(128).max_size(LARGE);
+        assert_eq!(server.next_size(), 4096);
+        for _ in 0..20 {
+            client.send_all(vec![0u8; 100]).await.0.unwrap();
+            server.recv().await.0.unwrap();
+        }
+        assert_eq!(server.next_size(), 128);
+
+        // A filling read grows at once, to the queued bytes.
+        client.send_all(vec![0u8; 10000]).await.0.unwrap();
+        assert_eq!(server.recv().await.0.unwrap(), 128);
+        assert!(server.next_size() >= 10000 - 128);
+        assert_eq!(server.recv().await.0.unwrap(), 10000 - 128);
+
+        // And it shrinks by half at most for each receive.
+        let mut last = server.next_size();
+        for _ in 0..100 {
+            client.send_all(vec![0u8; 100]).await.0.unwrap();
+            server.recv().await.0.unwrap();
+            assert!(server.next_size() >= last / 2);
+            last = server.next_size();
+        }
+        assert_eq!(server.next_size(), 128);
+
+        // The growth is capped.
+        client.send_all(vec![0u8; LARGE * 4]).await.0.unwrap();
+        let mut received = 0;
+        while received < LARGE * 4 {
+            received += server.recv().await.0.unwrap();
+            assert!(server.next_size() <= LARGE);
+        }
+        assert_eq!(server.next_size(), LARGE);
+    })
+}
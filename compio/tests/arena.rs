@@ -0,0 +1,98 @@
+use std::net::Ipv4Addr;
+
+use compio::{
+    buf::{arena::BufArena, IoBuf},
+    net::{TcpListener, TcpStream},
+};
+
+#[test]
+fn bump() {
+    let arena = BufArena::with_capacity(100);
+    let a = arena.alloc(30);
+    let b = arena.alloc(50);
+    assert!(a.is_empty());
+    assert_eq!(a.capacity(), 30);
+    // Adjacent in the block.
+    assert_eq!(b.as_buf_ptr(), a.as_buf_ptr().wrapping_add(30));
+
+    // The block is full.
+    let c = arena.alloc(30);
+    assert!(!c.is_in_arena());
+    assert_eq!(c.capacity(), 30);
+
+    let stats = arena.stats();
+    assert_eq!(stats.used, 80);
+    assert_eq!(stats.live, 2);
+    assert_eq!(stats.allocations, 2);
+    assert_eq!(stats.overflows, 1);
+    assert_eq!(stats.occupancy(), 0.8);
+
+    // Recycled after the last buffer is dropped.
+    let ptr = a.as_buf_ptr();
+    drop(a);
+    assert_eq!(arena.stats().used, 80);
+    drop(c);
+    drop(b);
+    let stats = arena.stats();
+    assert_eq!(stats.used, 0);
+    assert_eq!(stats.live, 0);
+    assert_eq!(stats.recycles, 1);
+    assert_eq!(arena.alloc(100).as_buf_ptr(), ptr);
+}
+
+#[test]
+fn outlive_arena() {
+    let arena = BufArena::with_capacity(16);
+    let mut buf = arena.alloc(8);
+    drop(arena);
+    buf.extend_from_slice(b"hello");
+    assert_eq!(&*buf, b"hello");
+
+    // Copied out, and the block is released.
+    let arena = BufArena::with_capacity(16);
+    let mut buf = arena.alloc(8);
+    buf.extend_from_slice(b"hello");
+    let vec = buf.detach();
+    assert_eq!(vec, b"hello");
+    assert_eq!(vec.capacity(), 8);
+    assert_eq!(arena.stats().live, 0);
+}
+
+#[test]
+fn ops() {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, (server, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+
+        let arena = BufArena::with_capacity(64);
+        let echo = compio::task::spawn({
+            let arena = arena.clone();
+            async move {
+                let (res, header) = server.recv_exact(arena.alloc(2)).await;
+                res.unwrap();
+                let (res, body) = server.recv_exact(arena.alloc(header[1] as usize)).await;
+                res.unwrap();
+                let mut response = arena.alloc(header.len() + body.len());
+                response.extend_from_slice(&header);
+                response.extend_from_slice(&body);
+                server.send_all(response).await.0.unwrap();
+            }
+        });
+
+        let mut request = arena.alloc(7);
+        request.extend_from_slice(b"\x01\x05hello");
+        client.send_all(request).await.0.unwrap();
+        echo.await;
+        let (res, response) = client.recv_exact(arena.alloc(7)).await;
+        res.unwrap();
+        assert_eq!(&*response, b"\x01\x05hello");
+        drop(response);
+
+        let stats = arena.stats();
+        assert_eq!(stats.allocations, 5);
+        assert_eq!(stats.live, 0);
+        assert_eq!(stats.used, 0);
+    })
+}
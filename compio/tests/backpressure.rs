@@ -0,0 +1,126 @@
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+use compio::{
+    io::{
+        framed::{Framed, LengthDelimitedCodec},
+        AsyncRead, AsyncWrite, BufWriter,
+    },
+    net::{TcpListener, TcpStream},
+};
+
+// The data is much larger than the socket buffers of both sides.
+const CHUNK_LEN: usize = 4096;
+const CHUNKS: usize = 8192;
+const HIGH_WATER_MARK: usize = 64 * 1024;
+
+async fn pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, (server, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    (client, server)
+}
+
+// Wait until the writer stops making progress.
+async fn wait_stalled(progress: &Cell<usize>) -> usize {
+    let mut last = progress.get();
+    loop {
+        compio::time::sleep(Duration::from_millis(100)).await;
+        let current = progress.get();
+        if current == last {
+            return current;
+        }
+        last = current;
+    }
+}
+
+#[test]
+fn framed() {
+    compio::task::block_on(async {
+        let (client, server) = pair().await;
+        let probe = client.try_clone().unwrap();
+        let progress = Rc::new(Cell::new(0));
+        let max_buffered = Rc::new(Cell::new(0));
+
+        let writer = compio::task::spawn({
+            let progress = progress.clone();
+            let max_buffered = max_buffered.clone();
+            async move {
+                let mut framed = Framed::new(client, LengthDelimitedCodec::new());
+                framed.set_write_high_water_mark(HIGH_WATER_MARK);
+                for i in 0..CHUNKS {
+                    framed.feed(vec![i as u8; CHUNK_LEN]).await.unwrap();
+                    max_buffered.set(max_buffered.get().max(framed.write_buffer().len()));
+                    progress.set(i + 1);
+                }
+                framed.flush().await.unwrap();
+                assert!(framed.write_buffer().is_empty());
+            }
+        });
+
+        // The reader is stalled, and the writer waits with bounded memory.
+        assert!(wait_stalled(&progress).await < CHUNKS);
+        // The frames over the mark, and a frame with its length prefix.
+        assert!(max_buffered.get() <= HIGH_WATER_MARK + CHUNK_LEN + 4);
+        let stalled_space = probe.send_buffer_space();
+
+        let mut framed = Framed::new(server, LengthDelimitedCodec::new());
+        for i in 0..CHUNKS {
+            let frame = framed.next().await.unwrap().unwrap();
+            assert_eq!(frame.len(), CHUNK_LEN);
+            assert!(frame.iter().all(|b| *b == i as u8));
+        }
+        writer.await;
+        assert_eq!(progress.get(), CHUNKS);
+        assert!(max_buffered.get() <= HIGH_WATER_MARK + CHUNK_LEN + 4);
+        // The send buffer is drained.
+        if cfg!(any(target_os = "linux", target_os = "android")) {
+            assert!(stalled_space.unwrap() < probe.send_buffer_space().unwrap());
+        }
+    })
+}
+
+#[test]
+fn buf_writer() {
+    compio::task::block_on(async {
+        let (client, mut server) = pair().await;
+        let progress = Rc::new(Cell::new(0));
+
+        let writer = compio::task::spawn({
+            let progress = progress.clone();
+            async move {
+                let mut writer = BufWriter::with_capacity(HIGH_WATER_MARK, client);
+                for i in 0..CHUNKS {
+                    writer.write_all(vec![i as u8; CHUNK_LEN]).await.0.unwrap();
+                    assert!(writer.buffer().len() <= HIGH_WATER_MARK);
+                    progress.set(i + 1);
+                }
+                writer.flush().await.unwrap();
+                assert!(writer.buffer().is_empty());
+            }
+        });
+
+        assert!(wait_stalled(&progress).await < CHUNKS);
+
+        for i in 0..CHUNKS {
+            let (res, buf) = server.read_exact(Vec::with_capacity(CHUNK_LEN)).await;
+            res.unwrap();
+            assert!(buf.iter().all(|b| *b == i as u8));
+        }
+        writer.await;
+        assert_eq!(progress.get(), CHUNKS);
+    })
+}
+
+#[test]
+fn send_buffer_space() {
+    compio::task::block_on(async {
+        let (client, _server) = pair().await;
+        let space = client.send_buffer_space();
+        if cfg!(any(target_os = "linux", target_os = "android")) {
+            assert!(space.unwrap() > 0);
+        } else {
+            assert_eq!(space.unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+        }
+    })
+}
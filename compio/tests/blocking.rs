@@ -0,0 +1,96 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use compio::{
+    buf::IntoInner,
+    driver::AsRawFd,
+    fs::File,
+    op::{BlockingBufOp, BlockingOp},
+    task::submit,
+};
+use futures_util::FutureExt;
+
+#[test]
+fn blocking_op() {
+    compio::task::block_on(async {
+        let file = File::open("Cargo.toml").unwrap();
+        let len = file.metadata().unwrap().len() as usize;
+        let fd = file.as_raw_fd();
+        let (res, _) = submit(BlockingOp::new(fd, move |raw_fd| {
+            assert_eq!(raw_fd, fd);
+            Ok(len)
+        }))
+        .await;
+        assert_eq!(res.unwrap(), len);
+
+        let (res, _) = submit(BlockingOp::new(fd, |_| Err(io::Error::other("custom")))).await;
+        let err = res.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert_eq!(err.to_string(), "custom");
+    })
+}
+
+#[test]
+fn blocking_buf_op() {
+    compio::task::block_on(async {
+        let file = File::open("Cargo.toml").unwrap();
+        let op = BlockingBufOp::new(file.as_raw_fd(), Vec::new(), |_, buffer| {
+            buffer.extend_from_slice(b"compio");
+            Ok(buffer.len())
+        });
+        let (res, op) = submit(op).await;
+        assert_eq!(res.unwrap(), 6);
+        assert_eq!(op.into_inner(), b"compio");
+
+        // The buffer is returned without running the function.
+        let op = BlockingBufOp::new(file.as_raw_fd(), vec![1u8], |_, _| unreachable!());
+        assert_eq!(op.into_inner(), [1]);
+    })
+}
+
+#[test]
+fn concurrent() {
+    const COUNT: usize = 64;
+
+    compio::task::block_on(async {
+        let file = File::open("Cargo.toml").unwrap();
+        // All functions sleep at the same time if they run concurrently.
+        let ops = (0..COUNT).map(|i| {
+            submit(BlockingOp::new(file.as_raw_fd(), move |_| {
+                std::thread::sleep(Duration::from_millis(100));
+                Ok(i)
+            }))
+        });
+        let results = futures_util::future::join_all(ops).await;
+        for (i, (res, _)) in results.into_iter().enumerate() {
+            assert_eq!(res.unwrap(), i);
+        }
+    })
+}
+
+#[test]
+fn cancel() {
+    compio::task::block_on(async {
+        let file = File::open("Cargo.toml").unwrap();
+        let started = Arc::new(AtomicUsize::new(0));
+        let counter = started.clone();
+        let op = BlockingOp::new(file.as_raw_fd(), move |_| {
+            counter.fetch_add(1, Ordering::AcqRel);
+            std::thread::sleep(Duration::from_millis(100));
+            Ok(0)
+        });
+        // Submit and drop it before completion.
+        assert!(submit(op).now_or_never().is_none());
+
+        // The runtime still works after the cancellation.
+        let (res, _) = submit(BlockingOp::new(file.as_raw_fd(), |_| Ok(1))).await;
+        assert_eq!(res.unwrap(), 1);
+        assert!(started.load(Ordering::Acquire) <= 1);
+    })
+}
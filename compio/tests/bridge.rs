@@ -0,0 +1,158 @@
+use std::{
+    collections::HashSet,
+    future::Future,
+    io,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    thread::{self, ThreadId},
+    time::Duration,
+};
+
+use compio::task::bridge::{block_in_place, spawn_send};
+use futures_util::FutureExt;
+
+/// Like the futures of a multithreaded client, it is woken from a new thread
+/// on each poll, and records the threads polling it.
+struct CrossThread {
+    polls: usize,
+    threads: HashSet<ThreadId>,
+}
+
+impl Future for CrossThread {
+    type Output = HashSet<ThreadId>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.threads.insert(thread::current().id());
+        if self.polls == 0 {
+            return Poll::Ready(std::mem::take(&mut self.threads));
+        }
+        self.polls -= 1;
+        let waker = cx.waker().clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(1));
+            waker.wake();
+        });
+        Poll::Pending
+    }
+}
+
+#[test]
+fn cross_thread_wakers() {
+    compio::task::block_on(async {
+        let future: Pin<Box<dyn Future<Output = _> + Send>> = Box::pin(CrossThread {
+            polls: 16,
+            threads: HashSet::new(),
+        });
+        let threads = spawn_send(future).await;
+        assert!(!threads.contains(&thread::current().id()));
+
+        // The runtime keeps running the other tasks meanwhile.
+        let handles = (0..8)
+            .map(|_| {
+                spawn_send(CrossThread {
+                    polls: 4,
+                    threads: HashSet::new(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let local = compio::task::spawn(async { 42 });
+        assert_eq!(local.await, 42);
+        for handle in handles {
+            assert!(!handle.await.is_empty());
+        }
+    })
+}
+
+#[test]
+fn panic() {
+    compio::task::block_on(async {
+        let handle = spawn_send(async { panic!("bridged") });
+        let payload = AssertUnwindSafe(handle).catch_unwind().await.unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"bridged"));
+    })
+}
+
+struct Guard(Arc<AtomicBool>);
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+#[test]
+fn cancel() {
+    compio::task::block_on(async {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let guard = Guard(dropped.clone());
+        let handle = spawn_send(async move {
+            let _guard = guard;
+            std::future::pending::<()>().await
+        });
+        drop(handle);
+        for _ in 0..100 {
+            if dropped.load(Ordering::Acquire) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("the future is not dropped");
+    })
+}
+
+#[test]
+fn block_in_place_on_bridge() {
+    compio::task::block_on(async {
+        let err = block_in_place(|| unreachable!()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+
+        let handle = spawn_send(async {
+            block_in_place(|| {
+                thread::sleep(Duration::from_millis(10));
+                thread::current().id()
+            })
+        });
+        let id = handle.await.unwrap();
+        assert_ne!(id, thread::current().id());
+    })
+}
+
+#[test]
+fn join_blocking() {
+    // From a thread without a runtime.
+    let threads = thread::spawn(|| {
+        let handle = spawn_send(CrossThread {
+            polls: 4,
+            threads: HashSet::new(),
+        });
+        handle.join_blocking()
+    })
+    .join()
+    .unwrap();
+    assert!(!threads.is_empty());
+
+    let payload = thread::spawn(|| spawn_send(async { panic!("bridged") }).join_blocking())
+        .join()
+        .unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"bridged"));
+}
+
+#[test]
+fn join_blocking_in_block_on() {
+    let payload = thread::spawn(|| {
+        compio::task::block_on(async { spawn_send(async { 42 }).join_blocking() })
+    })
+    .join()
+    .unwrap_err();
+    let message = payload.downcast_ref::<String>().unwrap();
+    assert!(message.contains("cannot call `join_blocking`"), "{message}");
+
+    // It works when the runtime isn't running.
+    compio::task::block_on(async {});
+    assert_eq!(spawn_send(async { 42 }).join_blocking(), 42);
+}
@@ -0,0 +1,250 @@
+use std::{
+    net::Ipv4Addr,
+    time::{Duration, Instant},
+};
+
+use compio::{
+    driver::AsRawFd,
+    net::{TcpListener, TcpStream},
+    op::BlockingOp,
+    sync::{CancellationExt, CancellationToken},
+};
+
+async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, (server, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    (client, server)
+}
+
+// Cancel the token from another thread, while the runtime waits in the driver.
+fn cancel_later(token: &CancellationToken) -> std::thread::JoinHandle<()> {
+    let token = token.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        token.cancel();
+    })
+}
+
+#[test]
+fn tree() {
+    let parent = CancellationToken::new();
+    let child = parent.child_token();
+    let grandchild = child.child_token();
+    let other = parent.child_token();
+    drop(parent.child_token());
+
+    child.cancel();
+    assert!(child.is_cancelled());
+    assert!(grandchild.is_cancelled());
+    assert!(!parent.is_cancelled());
+    assert!(!other.is_cancelled());
+
+    parent.clone().cancel();
+    assert!(parent.is_cancelled());
+    assert!(other.is_cancelled());
+    assert!(parent.child_token().is_cancelled());
+}
+
+#[test]
+fn drop_guard() {
+    let token = CancellationToken::new();
+    let token = token.drop_guard().disarm();
+    assert!(!token.is_cancelled());
+
+    let child = token.child_token();
+    drop(token.drop_guard());
+    assert!(child.is_cancelled());
+}
+
+#[test]
+fn cancelled_threads() {
+    let token = CancellationToken::new();
+    let threads = (0..4)
+        .map(|_| {
+            let token = token.child_token();
+            std::thread::spawn(move || compio::task::block_on(token.cancelled()).unwrap())
+        })
+        .collect::<Vec<_>>();
+    cancel_later(&token).join().unwrap();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    // Returns at once after cancelled.
+    compio::task::block_on(token.cancelled()).unwrap();
+}
+
+#[test]
+fn run_until_cancelled() {
+    compio::task::block_on(async {
+        let (client, server) = tcp_pair().await;
+        let token = CancellationToken::new();
+        let canceller = cancel_later(&token);
+        let res = token
+            .run_until_cancelled(server.recv(Vec::with_capacity(16)))
+            .await;
+        assert!(res.is_none());
+        canceller.join().unwrap();
+
+        // The recv is cancelled in the driver, and doesn't take the data.
+        client.send_all("hello").await.0.unwrap();
+        let (res, buffer) = server.recv_exact(Vec::with_capacity(5)).await;
+        res.unwrap();
+        assert_eq!(buffer, b"hello");
+
+        let res = token.run_until_cancelled(async { 42 }).await;
+        assert_eq!(res, None);
+        let token = CancellationToken::new();
+        assert_eq!(token.run_until_cancelled(async { 42 }).await, Some(42));
+    })
+}
+
+#[test]
+fn with_cancellation() {
+    compio::task::block_on(async {
+        let (client, server) = tcp_pair().await;
+        let token = CancellationToken::new();
+        let canceller = cancel_later(&token);
+        let (res, buffer) = server
+            .recv(Vec::with_capacity(16))
+            .with_cancellation(&token)
+            .await;
+        assert!(res.is_err());
+        assert_eq!(buffer.capacity(), 16);
+        assert!(buffer.is_empty());
+        canceller.join().unwrap();
+
+        // The ops fail at once after cancelled.
+        let (res, buffer) = server.recv_exact(buffer).with_cancellation(&token).await;
+        assert!(res.is_err());
+        assert_eq!(buffer.capacity(), 16);
+
+        client.send_all("hello").await.0.unwrap();
+        let (res, buffer) = server.recv_exact(Vec::with_capacity(5)).await;
+        res.unwrap();
+        assert_eq!(buffer, b"hello");
+
+        // Completes normally if not cancelled.
+        let token = CancellationToken::new();
+        client.send_all("world").await.0.unwrap();
+        let (res, buffer) = server
+            .recv_exact(Vec::with_capacity(5))
+            .with_cancellation(&token)
+            .await;
+        res.unwrap();
+        assert_eq!(buffer, b"world");
+    })
+}
+
+#[test]
+fn with_cancellation_nested() {
+    compio::task::block_on(async {
+        let (_client, server) = tcp_pair().await;
+        let outer = CancellationToken::new();
+        let inner = CancellationToken::new();
+        let canceller = cancel_later(&outer);
+        let (res, buffer) = async {
+            server
+                .recv(Vec::with_capacity(16))
+                .with_cancellation(&inner)
+                .await
+        }
+        .with_cancellation(&outer)
+        .await;
+        assert!(res.is_err());
+        assert_eq!(buffer.capacity(), 16);
+        assert!(!inner.is_cancelled());
+        canceller.join().unwrap();
+    })
+}
+
+#[test]
+fn tree_of_tasks() {
+    const CONNECTIONS: usize = 16;
+
+    compio::task::block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = CancellationToken::new();
+
+        let mut clients = vec![];
+        let mut tasks = vec![];
+        for _ in 0..CONNECTIONS {
+            let (client, (server, _)) =
+                futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+            clients.push(client);
+            let token = token.child_token();
+            tasks.push(compio::task::spawn(async move {
+                let (res, buffer) = server
+                    .recv(Vec::with_capacity(16))
+                    .with_cancellation(&token)
+                    .await;
+                assert!(res.is_err());
+                buffer
+            }));
+        }
+
+        // The tasks are blocked in recv when the token is cancelled.
+        let start = Instant::now();
+        let canceller = cancel_later(&token);
+        for task in tasks {
+            assert_eq!(task.await.capacity(), 16);
+        }
+        assert!(start.elapsed() < Duration::from_secs(1));
+        canceller.join().unwrap();
+    })
+}
+
+// The blocking syscall of a running op is interrupted, and the op fails with
+// `ECANCELED`.
+#[cfg(unix)]
+#[test]
+fn blocking_op_interrupted() {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = CString::new(dir.path().join("fifo").as_os_str().as_bytes()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(path.as_ptr(), 0o600) }, 0);
+
+    compio::task::block_on(async {
+        let token = CancellationToken::new();
+        // Opening a FIFO without a writer blocks.
+        let op = BlockingOp::new(-1, move |_| {
+            let fd = unsafe { libc::open(path.as_ptr(), libc::O_RDONLY) };
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            unsafe { libc::close(fd) };
+            Ok(0)
+        });
+        let canceller = cancel_later(&token);
+        // Submitted within the cancellation scope.
+        let (res, _) = async { compio::task::submit(op).await }
+            .with_cancellation(&token)
+            .await;
+        assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::ECANCELED));
+        canceller.join().unwrap();
+    })
+}
+
+// The function not interrupted delivers its real result once.
+#[test]
+fn blocking_op_finished() {
+    compio::task::block_on(async {
+        let token = CancellationToken::new();
+        let file = compio::fs::File::open("Cargo.toml").unwrap();
+        // Sleeping is retried on `EINTR`.
+        let op = BlockingOp::new(file.as_raw_fd(), |_| {
+            std::thread::sleep(Duration::from_millis(100));
+            Ok(1)
+        });
+        let canceller = cancel_later(&token);
+        // Submitted within the cancellation scope.
+        let (res, _) = async { compio::task::submit(op).await }
+            .with_cancellation(&token)
+            .await;
+        assert_eq!(res.unwrap(), 1);
+        canceller.join().unwrap();
+    })
+}
@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use compio::fs::{changed_since, ChangeToken, File};
+
+#[test]
+fn detect_changes() {
+    compio::task::block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "answer = 42").unwrap();
+        let token = File::open(&path).unwrap().change_token().await.unwrap();
+
+        // Stable without changes.
+        assert!(!changed_since(&path, &token).await.unwrap());
+        let again = File::open(&path).unwrap().change_token().await.unwrap();
+        assert!(!again.changed_from(&token));
+
+        // The same size, so only the times tell. Wait for the coarse clock of
+        // the filesystem.
+        std::thread::sleep(Duration::from_millis(20));
+        std::fs::write(&path, "answer = 43").unwrap();
+        assert!(changed_since(&path, &token).await.unwrap());
+
+        let token = File::open(&path).unwrap().change_token().await.unwrap();
+        std::fs::write(&path, "answer = 4242").unwrap();
+        assert!(changed_since(&path, &token).await.unwrap());
+
+        let token = File::open(&path).unwrap().change_token().await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(changed_since(&path, &token).await.unwrap());
+    })
+}
+
+#[cfg(unix)]
+#[test]
+fn detect_replaced() {
+    compio::task::block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "answer = 42").unwrap();
+        let file = File::open(&path).unwrap();
+        let modified = file.metadata().unwrap().modified().unwrap();
+        let token = file.change_token().await.unwrap();
+
+        // The same size and modification time, but another file.
+        let other = dir.path().join("other.toml");
+        std::fs::write(&other, "answer = 43").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&other)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        std::fs::rename(&other, &path).unwrap();
+        assert!(changed_since(&path, &token).await.unwrap());
+    })
+}
+
+#[test]
+fn bytes() {
+    compio::task::block_on(async {
+        let file = File::open("Cargo.toml").unwrap();
+        let token = file.change_token().await.unwrap();
+        let bytes = token.to_bytes();
+        let decoded = ChangeToken::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, token);
+        assert!(!changed_since("Cargo.toml", &decoded).await.unwrap());
+
+        for len in 0..bytes.len() {
+            assert!(ChangeToken::from_bytes(&bytes[..len]).is_err());
+        }
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(ChangeToken::from_bytes(&longer).is_err());
+        let mut unknown = bytes.clone();
+        unknown[0] += 1;
+        assert!(ChangeToken::from_bytes(&unknown).is_err());
+    })
+}
+
+#[test]
+fn missing_fields() {
+    // Only the size, e.g., from a platform without the other fields.
+    let mut bytes = vec![1, 1];
+    bytes.extend_from_slice(&11u64.to_le_bytes());
+    let partial = ChangeToken::from_bytes(&bytes).unwrap();
+    assert_eq!(partial.to_bytes(), bytes);
+
+    compio::task::block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "answer = 42").unwrap();
+        let full = File::open(&path).unwrap().change_token().await.unwrap();
+        // Compared on the size only.
+        assert!(!full.changed_from(&partial));
+        assert!(!partial.changed_from(&full));
+        assert!(!changed_since(&path, &partial).await.unwrap());
+
+        std::fs::write(&path, "answer = 4242").unwrap();
+        assert!(changed_since(&path, &partial).await.unwrap());
+    })
+}
@@ -0,0 +1,113 @@
+use std::{hash::Hasher, net::Ipv4Addr};
+
+use compio::{
+    buf::{ChecksummingBuf, IntoInner, IoBuf, IoBufMut},
+    fs::File,
+    net::{TcpListener, TcpStream},
+};
+use tempfile::NamedTempFile;
+
+// FNV-1a, which is sensitive to the order and the boundaries of the bytes.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+fn fnv(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+#[test]
+fn read_at_appends() {
+    let data = (0..10000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let tempfile = NamedTempFile::new().unwrap();
+    std::fs::write(tempfile.path(), &data).unwrap();
+
+    compio::task::block_on(async {
+        let file = File::open(tempfile.path()).unwrap();
+        // Each read appends to the initialized bytes.
+        let mut buffer = ChecksummingBuf::new(Vec::with_capacity(data.len()), Fnv::default());
+        let mut pos = 0;
+        while pos < data.len() {
+            let end = (pos + 999).min(data.len());
+            let (read, buf) = file.read_at(buffer.slice(..end), pos as u64).await;
+            buffer = buf.into_inner();
+            pos += read.unwrap();
+        }
+        assert_eq!(buffer.get_ref(), &data);
+        assert_eq!(buffer.finish(), fnv(&data));
+    })
+}
+
+#[test]
+fn existing_bytes_not_hashed() {
+    let tempfile = NamedTempFile::new().unwrap();
+    std::fs::write(tempfile.path(), b"world").unwrap();
+
+    compio::task::block_on(async {
+        let file = File::open(tempfile.path()).unwrap();
+        let mut vec = Vec::with_capacity(16);
+        vec.extend_from_slice(b"hello ");
+        let (read, mut buffer) = file
+            .read_at(ChecksummingBuf::new(vec, Fnv::default()), 0)
+            .await;
+        assert_eq!(read.unwrap(), 5);
+        assert_eq!(buffer.filled(), b"hello world");
+        let (vec, hasher) = buffer.into_parts();
+        assert_eq!(vec, b"hello world");
+        assert_eq!(hasher.finish(), fnv(b"world"));
+    })
+}
+
+#[test]
+fn recv_vectored_partially_filled() {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, (rx, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+
+        tx.send_all(b"0123456789".to_vec()).await.0.unwrap();
+
+        let mut first = Vec::with_capacity(6);
+        first.extend_from_slice(b"ab");
+        let mut second = Vec::with_capacity(8);
+        second.extend_from_slice(b"cd");
+        let buffers = vec![
+            ChecksummingBuf::new(first, Fnv::default()),
+            ChecksummingBuf::new(second, Fnv::default()),
+        ];
+        // Only the uninitialized parts are filled: 4 bytes in the first buffer,
+        // and 6 in the second.
+        let (read, buffers) = rx.recv_vectored(buffers).await;
+        let read = read.unwrap();
+        assert!(read > 0);
+        let mut received = vec![];
+        for buffer in &buffers {
+            let (existing, new) = buffer.get_ref().split_at(2);
+            assert!(existing == b"ab" || existing == b"cd");
+            assert_eq!(buffer.finish(), fnv(new));
+            received.extend_from_slice(new);
+        }
+        assert_eq!(received.len(), read);
+        assert_eq!(received, &b"0123456789"[..read]);
+    })
+}
@@ -0,0 +1,272 @@
+use std::{
+    cell::{Cell, RefCell},
+    io::ErrorKind,
+    net::SocketAddr,
+    rc::Rc,
+    time::Duration,
+};
+
+use compio::{
+    net::{
+        TcpListener, TcpStream,
+        pool::{ConnectionPool, PoolBuilder},
+    },
+    task::{spawn, yield_now},
+    time::sleep,
+};
+use futures_util::future::join_all;
+
+// An echo server counting the accepted connections. It closes a connection
+// after the first reply if `once`.
+fn echo_server(once: bool) -> (SocketAddr, Rc<Cell<usize>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().as_socket().unwrap();
+    let accepts = Rc::new(Cell::new(0));
+    let counter = accepts.clone();
+    spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            counter.set(counter.get() + 1);
+            spawn(async move {
+                loop {
+                    let (res, buffer) = stream.recv(Vec::with_capacity(64)).await;
+                    if res.unwrap_or_default() == 0 {
+                        break;
+                    }
+                    if stream.send_all(buffer).await.0.is_err() || once {
+                        break;
+                    }
+                }
+            })
+            .detach();
+        }
+    })
+    .detach();
+    (addr, accepts)
+}
+
+fn pool_builder() -> PoolBuilder<SocketAddr, TcpStream> {
+    ConnectionPool::builder(|addr: &SocketAddr| Box::pin(TcpStream::connect(*addr)))
+}
+
+async fn ping(stream: &TcpStream) {
+    stream.send_all("ping").await.0.unwrap();
+    let (res, buffer) = stream.recv_exact(Vec::with_capacity(4)).await;
+    res.unwrap();
+    assert_eq!(buffer, b"ping");
+}
+
+#[test]
+fn reuse_at_limit() {
+    compio::task::block_on(async {
+        let (addr, accepts) = echo_server(false);
+        let pool = Rc::new(pool_builder().max_connections(2).build());
+        let tasks = (0..10)
+            .map(|_| {
+                let pool = pool.clone();
+                spawn(async move {
+                    let conn = pool.get(addr).await.unwrap();
+                    ping(&conn).await;
+                })
+            })
+            .collect::<Vec<_>>();
+        join_all(tasks).await;
+        assert_eq!(accepts.get(), 2);
+        assert_eq!(pool.connections(&addr), 2);
+        assert_eq!(pool.idle_connections(&addr), 2);
+
+        // A detached connection no longer counts.
+        let conn = pool.get(addr).await.unwrap().detach();
+        ping(&conn).await;
+        assert_eq!(pool.connections(&addr), 1);
+    })
+}
+
+#[test]
+fn fair_waiters() {
+    compio::task::block_on(async {
+        let (addr, accepts) = echo_server(false);
+        let pool = Rc::new(pool_builder().max_connections(1).build());
+        let conn = pool.get(addr).await.unwrap();
+        // A cancelled waiter passes on what is handed to it.
+        let mut cancelled = Box::pin(pool.get(addr));
+        assert!(futures_util::poll!(cancelled.as_mut()).is_pending());
+
+        let order = Rc::new(RefCell::new(vec![]));
+        let tasks = (0..3)
+            .map(|i| {
+                let (pool, order) = (pool.clone(), order.clone());
+                spawn(async move {
+                    let conn = pool.get(addr).await.unwrap();
+                    order.borrow_mut().push(i);
+                    ping(&conn).await;
+                })
+            })
+            .collect::<Vec<_>>();
+        yield_now().await;
+        drop(conn);
+        drop(cancelled);
+
+        join_all(tasks).await;
+        assert_eq!(*order.borrow(), [0, 1, 2]);
+        assert_eq!(accepts.get(), 1);
+        assert_eq!(pool.idle_connections(&addr), 1);
+    })
+}
+
+#[test]
+fn evict_broken() {
+    compio::task::block_on(async {
+        let (addr, accepts) = echo_server(false);
+        let pool = pool_builder().max_connections(1).build();
+        let mut conn = pool.get(addr).await.unwrap();
+        ping(&conn).await;
+        conn.mark_broken();
+        drop(conn);
+        assert_eq!(pool.connections(&addr), 0);
+
+        let conn = pool.get(addr).await.unwrap();
+        ping(&conn).await;
+        assert_eq!(accepts.get(), 2);
+    })
+}
+
+#[cfg(unix)]
+#[test]
+fn health_check() {
+    use std::os::fd::AsRawFd;
+
+    compio::task::block_on(async {
+        let (addr, accepts) = echo_server(true);
+        let checks = Rc::new(Cell::new(0));
+        let counter = checks.clone();
+        let pool = pool_builder()
+            .health_check(move |stream: &TcpStream| {
+                counter.set(counter.get() + 1);
+                // The peer has closed the connection if the peek returns 0.
+                let mut byte = 0u8;
+                let res = unsafe {
+                    libc::recv(
+                        stream.as_raw_fd(),
+                        &mut byte as *mut u8 as _,
+                        1,
+                        libc::MSG_PEEK | libc::MSG_DONTWAIT,
+                    )
+                };
+                Box::pin(async move { res != 0 })
+            })
+            .build();
+        let conn = pool.get(addr).await.unwrap();
+        ping(&conn).await;
+        drop(conn);
+        // Wait for the FIN.
+        sleep(Duration::from_millis(50)).await;
+
+        let conn = pool.get(addr).await.unwrap();
+        ping(&conn).await;
+        assert_eq!(checks.get(), 1);
+        assert_eq!(accepts.get(), 2);
+        assert_eq!(pool.connections(&addr), 1);
+    })
+}
+
+#[test]
+fn check_readiness() {
+    compio::task::block_on(async {
+        let (addr, accepts) = echo_server(false);
+        let pool = pool_builder().max_connections(1).check_readiness().build();
+        let conn = pool.get(addr).await.unwrap();
+        ping(&conn).await;
+        assert!(!conn.is_readable().unwrap());
+        drop(conn);
+        // Reused while nothing is received.
+        let conn = pool.get(addr).await.unwrap();
+        ping(&conn).await;
+        assert_eq!(accepts.get(), 1);
+
+        // The unexpected data left in the idle connection.
+        conn.send_all("ping").await.0.unwrap();
+        drop(conn);
+        sleep(Duration::from_millis(50)).await;
+        let conn = pool.get(addr).await.unwrap();
+        ping(&conn).await;
+        assert_eq!(accepts.get(), 2);
+        drop(conn);
+
+        // The connection closed by the peer.
+        let (addr, accepts) = echo_server(true);
+        let conn = pool.get(addr).await.unwrap();
+        ping(&conn).await;
+        drop(conn);
+        sleep(Duration::from_millis(50)).await;
+        let conn = pool.get(addr).await.unwrap();
+        ping(&conn).await;
+        assert_eq!(accepts.get(), 2);
+        assert_eq!(pool.connections(&addr), 1);
+    })
+}
+
+#[test]
+fn reap_idle() {
+    compio::task::block_on(async {
+        let (addr, accepts) = echo_server(false);
+        let pool = pool_builder()
+            .max_idle(Some(Duration::from_millis(50)))
+            .build();
+        let conn = pool.get(addr).await.unwrap();
+        ping(&conn).await;
+        drop(conn);
+        assert_eq!(pool.idle_connections(&addr), 1);
+
+        sleep(Duration::from_millis(200)).await;
+        assert_eq!(pool.connections(&addr), 0);
+        let conn = pool.get(addr).await.unwrap();
+        ping(&conn).await;
+        assert_eq!(accepts.get(), 2);
+    })
+}
+
+#[test]
+fn shutdown() {
+    compio::task::block_on(async {
+        let (idle_addr, _) = echo_server(false);
+        let (busy_addr, _) = echo_server(false);
+        let closes = Rc::new(Cell::new(0));
+        let counter = closes.clone();
+        let pool = Rc::new(
+            pool_builder()
+                .max_connections(1)
+                .close_with(move |stream| {
+                    let counter = counter.clone();
+                    Box::pin(async move {
+                        drop(stream);
+                        counter.set(counter.get() + 1);
+                    })
+                })
+                .build(),
+        );
+        drop(pool.get(idle_addr).await.unwrap());
+        let busy = pool.get(busy_addr).await.unwrap();
+        let waiter = {
+            let pool = pool.clone();
+            spawn(async move { pool.get(busy_addr).await.map(|_| ()) })
+        };
+        yield_now().await;
+
+        pool.shutdown().await;
+        assert!(pool.is_shutdown());
+        assert_eq!(closes.get(), 1);
+        assert_eq!(waiter.await.unwrap_err().kind(), ErrorKind::Other);
+        let Err(e) = pool.get(idle_addr).await else {
+            panic!("got a connection after the shutdown");
+        };
+        assert_eq!(e.kind(), ErrorKind::Other);
+
+        // The connection in use is closed when returned.
+        drop(busy);
+        yield_now().await;
+        assert_eq!(closes.get(), 2);
+        assert_eq!(pool.connections(&idle_addr), 0);
+        assert_eq!(pool.connections(&busy_addr), 0);
+    })
+}
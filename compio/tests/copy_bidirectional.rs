@@ -0,0 +1,135 @@
+use std::{
+    net::{Ipv4Addr, Shutdown},
+    time::Duration,
+};
+
+use compio::net::{copy_bidirectional_with, CopyOptions, TcpListener, TcpStream};
+
+// Larger than the buffers, so that the data is copied in many rounds.
+const LEN: usize = 300 * 1024;
+
+fn data(seed: u8) -> Vec<u8> {
+    (0..LEN).map(|i| (i as u8).wrapping_mul(seed)).collect()
+}
+
+/// The client is connected to `a`, and `b` is connected to the server.
+async fn connect() -> (TcpStream, TcpStream, TcpStream, TcpStream) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, (a, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    let (b, (server, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    (client, a, b, server)
+}
+
+async fn send_and_close(stream: &TcpStream, data: Vec<u8>) {
+    stream.send_all(data).await.0.unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+}
+
+async fn recv_to_end(stream: &TcpStream) -> Vec<u8> {
+    let (res, buffer) = stream.recv_to_end(vec![]).await;
+    res.unwrap();
+    buffer
+}
+
+fn options() -> [CopyOptions; 2] {
+    let options = CopyOptions::new().buffer_size(16 * 1024);
+    [options.clone(), options.splice(false)]
+}
+
+#[test]
+fn client_closes_first() {
+    compio::task::block_on(async {
+        for options in options() {
+            let (client, a, b, server) = connect().await;
+            let peers = async {
+                send_and_close(&client, data(3)).await;
+                // The request is complete before the response.
+                assert_eq!(recv_to_end(&server).await, data(3));
+                send_and_close(&server, data(5)).await;
+                assert_eq!(recv_to_end(&client).await, data(5));
+            };
+            let (copied, ()) =
+                futures_util::join!(copy_bidirectional_with(&a, &b, &options), peers);
+            assert_eq!(copied.unwrap(), (LEN as u64, LEN as u64));
+        }
+    })
+}
+
+#[test]
+fn server_closes_first() {
+    compio::task::block_on(async {
+        for options in options() {
+            let (client, a, b, server) = connect().await;
+            let peers = async {
+                send_and_close(&server, data(3)).await;
+                assert_eq!(recv_to_end(&client).await, data(3));
+                // The client could still send after the EOF.
+                send_and_close(&client, data(5)).await;
+                assert_eq!(recv_to_end(&server).await, data(5));
+            };
+            let (copied, ()) =
+                futures_util::join!(copy_bidirectional_with(&a, &b, &options), peers);
+            assert_eq!(copied.unwrap(), (LEN as u64, LEN as u64));
+        }
+    })
+}
+
+#[test]
+fn simultaneous_close() {
+    compio::task::block_on(async {
+        for options in options() {
+            let (client, a, b, server) = connect().await;
+            let peers = async {
+                let (client_received, server_received, (), ()) = futures_util::join!(
+                    recv_to_end(&client),
+                    recv_to_end(&server),
+                    send_and_close(&client, data(3)),
+                    send_and_close(&server, data(5)),
+                );
+                assert_eq!(client_received, data(5));
+                assert_eq!(server_received, data(3));
+            };
+            let (copied, ()) =
+                futures_util::join!(copy_bidirectional_with(&a, &b, &options), peers);
+            assert_eq!(copied.unwrap(), (LEN as u64, LEN as u64));
+        }
+    })
+}
+
+#[test]
+fn reset() {
+    compio::task::block_on(async {
+        for options in options() {
+            let (_client, a, b, server) = connect().await;
+            // The client sends nothing, but the reset of the server aborts the
+            // direction from the client.
+            server.set_linger(Some(Duration::ZERO)).unwrap();
+            drop(server);
+            let e = copy_bidirectional_with(&a, &b, &options).await.unwrap_err();
+            assert_eq!(e.kind(), std::io::ErrorKind::ConnectionReset);
+        }
+    })
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn idle_timeout() {
+    compio::task::block_on(async {
+        let (client, a, b, _server) = connect().await;
+        let options = CopyOptions::new().idle_timeout(Some(Duration::from_millis(200)));
+        let start = std::time::Instant::now();
+        let copy =
+            compio::task::spawn(async move { copy_bidirectional_with(&a, &b, &options).await });
+        // The data in time keeps the copy alive.
+        for _ in 0..3 {
+            compio::time::sleep(Duration::from_millis(100)).await;
+            client.send_all("ping").await.0.unwrap();
+        }
+        let e = copy.await.unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::TimedOut);
+        assert!(start.elapsed() >= Duration::from_millis(500));
+    })
+}
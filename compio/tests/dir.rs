@@ -0,0 +1,162 @@
+use std::{ffi::OsStr, future::Future, io::ErrorKind, path::Path, pin::Pin};
+
+use compio::fs::{Dir, OpenOptions};
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("compio-dir-{name}-{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+async fn write_file(dir: &Dir, name: impl AsRef<Path>, contents: &'static str) {
+    let opts = OpenOptions::new().write(true).create_new(true);
+    let file = dir.open_file_at(name, opts).await.unwrap();
+    file.write_all_at(contents, 0).await.0.unwrap();
+}
+
+async fn read_file(dir: &Dir, name: impl AsRef<Path>) -> Vec<u8> {
+    let file = dir
+        .open_file_at(name, OpenOptions::new().read(true))
+        .await
+        .unwrap();
+    let len = file.metadata().unwrap().len() as usize;
+    let (res, buffer) = file.read_to_end_at(Vec::with_capacity(len), 0).await;
+    res.unwrap();
+    buffer
+}
+
+// Copy the entries of `src` to `dst` recursively, only relative to the
+// directory handles.
+fn copy_tree<'a>(src: &'a Dir, dst: &'a Dir) -> Pin<Box<dyn Future<Output = ()> + 'a>> {
+    Box::pin(async move {
+        for name in src.read_dir().await.unwrap() {
+            let metadata = src.metadata_at(&name, false).await.unwrap();
+            if metadata.is_symlink() {
+                let target = src.read_link_at(&name).await.unwrap();
+                dst.symlink_at(target, &name).await.unwrap();
+            } else if metadata.is_dir() {
+                dst.create_dir_at(&name).await.unwrap();
+                let src = src.open_dir_at(&name).await.unwrap();
+                let dst = dst.open_dir_at(&name).await.unwrap();
+                copy_tree(&src, &dst).await;
+            } else {
+                let buffer = read_file(src, &name).await;
+                let opts = OpenOptions::new().write(true).create_new(true);
+                let file = dst.open_file_at(&name, opts).await.unwrap();
+                file.write_all_at(buffer, 0).await.0.unwrap();
+            }
+        }
+    })
+}
+
+#[test]
+#[cfg(unix)]
+fn recursive_copy() {
+    let root = temp_dir("copy");
+    compio::task::block_on(async {
+        // The only absolute path.
+        let root = Dir::open(&root).unwrap();
+        root.create_dir_at("src").await.unwrap();
+        let src = root.open_dir_at("src").await.unwrap();
+        write_file(&src, "a.txt", "alpha").await;
+        src.create_dir_at("nested").await.unwrap();
+        write_file(&src, "nested/b.txt", "beta").await;
+        src.create_dir_at("nested/deeper").await.unwrap();
+        write_file(&src, "nested/deeper/c.txt", "gamma").await;
+        src.symlink_at("nested/b.txt", "link").await.unwrap();
+
+        root.create_dir_at("dst").await.unwrap();
+        let dst = root.open_dir_at("dst").await.unwrap();
+        copy_tree(&src, &dst).await;
+
+        let mut names = dst.read_dir().await.unwrap();
+        names.sort();
+        assert_eq!(names, ["a.txt", "link", "nested"]);
+        assert_eq!(read_file(&dst, "a.txt").await, b"alpha");
+        assert_eq!(read_file(&dst, "nested/b.txt").await, b"beta");
+        assert_eq!(read_file(&dst, "nested/deeper/c.txt").await, b"gamma");
+        assert_eq!(
+            dst.read_link_at("link").await.unwrap(),
+            Path::new("nested/b.txt")
+        );
+        // Followed relative to the directory of the link.
+        assert_eq!(read_file(&dst, "link").await, b"beta");
+        assert!(dst.metadata_at("link", true).await.unwrap().is_file());
+        assert!(dst.metadata_at("link", false).await.unwrap().is_symlink());
+    });
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn rename_across_dirs() {
+    let root = temp_dir("rename");
+    compio::task::block_on(async {
+        let root = Dir::open(&root).unwrap();
+        root.create_dir_at("from").await.unwrap();
+        root.create_dir_at("to").await.unwrap();
+        let from = root.open_dir_at("from").await.unwrap();
+        let to = root.open_dir_at("to").await.unwrap();
+        write_file(&from, "file", "moved").await;
+
+        from.rename_at("file", &to, "renamed").await.unwrap();
+        assert!(from.read_dir().await.unwrap().is_empty());
+        assert_eq!(to.read_dir().await.unwrap(), [OsStr::new("renamed")]);
+        assert_eq!(read_file(&to, "renamed").await, b"moved");
+
+        // In the same directory.
+        to.rename_at("renamed", &to, "again").await.unwrap();
+        assert_eq!(read_file(&to, "again").await, b"moved");
+
+        let e = from.rename_at("missing", &to, "file").await.unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+    });
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn remove() {
+    let root = temp_dir("remove");
+    compio::task::block_on(async {
+        let dir = Dir::open(&root).unwrap();
+        dir.create_dir_at("sub").await.unwrap();
+        write_file(&dir, "sub/file", "").await;
+
+        let e = dir.create_dir_at("sub").await.unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::AlreadyExists);
+        // Not empty.
+        dir.remove_dir_at("sub").await.unwrap_err();
+        dir.remove_file_at("sub/file").await.unwrap();
+        dir.remove_dir_at("sub").await.unwrap();
+        assert!(dir.read_dir().await.unwrap().is_empty());
+
+        let e = dir.metadata_at("sub", true).await.unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::NotFound);
+    });
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn open_options() {
+    let root = temp_dir("options");
+    compio::task::block_on(async {
+        let dir = Dir::open(&root).unwrap();
+        write_file(&dir, "file", "hello").await;
+
+        let opts = OpenOptions::new().write(true).create_new(true);
+        let e = dir.open_file_at("file", opts).await.unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::AlreadyExists);
+
+        let opts = OpenOptions::new().write(true).truncate(true);
+        dir.open_file_at("file", opts).await.unwrap();
+        assert_eq!(read_file(&dir, "file").await, b"");
+
+        // The access mode is required, like `std`.
+        let e = dir
+            .open_file_at("file", OpenOptions::new())
+            .await
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+    });
+    std::fs::remove_dir_all(&root).unwrap();
+}
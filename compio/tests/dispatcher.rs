@@ -0,0 +1,151 @@
+use std::{
+    cell::Cell,
+    io,
+    panic::{AssertUnwindSafe, catch_unwind},
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use compio::dispatcher::{Dispatcher, WorkerError};
+
+#[test]
+fn lifecycle() {
+    let events = Arc::new(Mutex::new(vec![]));
+    let dispatcher = Dispatcher::builder()
+        .worker_threads(3)
+        .on_worker_start({
+            let events = events.clone();
+            move |ctx| {
+                events.lock().unwrap().push(format!("start {}", ctx.index()));
+                async { Rc::new(Cell::new(0)) }
+            }
+        })
+        .on_worker_stop({
+            let events = events.clone();
+            move |ctx, count: Rc<Cell<usize>>| {
+                let events = events.clone();
+                async move {
+                    let event = format!("stop {} after {} jobs", ctx.index(), count.get());
+                    events.lock().unwrap().push(event);
+                }
+            }
+        })
+        .build()
+        .unwrap();
+    assert_eq!(dispatcher.workers(), 3);
+    // All the workers have started.
+    assert_eq!(events.lock().unwrap().len(), 3);
+
+    // The jobs not awaited still complete before the workers stop.
+    for _ in 0..9 {
+        let handle = dispatcher
+            .dispatch(|count: &Rc<Cell<usize>>| {
+                let count = count.clone();
+                async move {
+                    compio::task::yield_now().await;
+                    count.set(count.get() + 1);
+                }
+            })
+            .unwrap();
+        drop(handle);
+    }
+    let names = (0..3)
+        .map(|_| {
+            dispatcher
+                .dispatch(|_: &Rc<Cell<usize>>| async {
+                    std::thread::current().name().unwrap().to_string()
+                })
+                .unwrap()
+                .join_blocking()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["compio-worker-0", "compio-worker-1", "compio-worker-2"]);
+    dispatcher.join().unwrap();
+
+    let mut events = events.lock().unwrap().clone();
+    events.sort();
+    assert_eq!(
+        events,
+        [
+            "start 0",
+            "start 1",
+            "start 2",
+            "stop 0 after 3 jobs",
+            "stop 1 after 3 jobs",
+            "stop 2 after 3 jobs",
+        ]
+    );
+}
+
+#[test]
+fn await_in_runtime() {
+    let dispatcher = Dispatcher::builder()
+        .worker_threads(2)
+        .thread_names(|index| format!("shard-{index}"))
+        .on_worker_start(|ctx| async move { ctx.index() * 10 })
+        .build()
+        .unwrap();
+    compio::task::block_on(async {
+        let a = dispatcher.dispatch(|shard: &usize| {
+            let shard = *shard;
+            async move { shard + 1 }
+        });
+        let b = dispatcher.dispatch(|shard: &usize| {
+            let shard = *shard;
+            async move { shard + 2 }
+        });
+        assert_eq!(a.unwrap().await + b.unwrap().await, 1 + 12);
+    });
+    dispatcher.join().unwrap();
+}
+
+#[test]
+fn start_panics() {
+    let stopped = Arc::new(Mutex::new(vec![]));
+    let e = Dispatcher::builder()
+        .worker_threads(3)
+        .on_worker_start(|ctx| async move {
+            if ctx.index() == 1 {
+                panic!("cannot open the shard");
+            }
+        })
+        .on_worker_stop({
+            let stopped = stopped.clone();
+            move |ctx, ()| {
+                stopped.lock().unwrap().push(ctx.index());
+                async {}
+            }
+        })
+        .build()
+        .unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::Other);
+    let e = e.get_ref().unwrap().downcast_ref::<WorkerError>().unwrap();
+    assert_eq!(e.index(), 1);
+    assert_eq!(e.message(), "cannot open the shard");
+
+    // The started workers are stopped.
+    let mut stopped = stopped.lock().unwrap().clone();
+    stopped.sort();
+    assert_eq!(stopped, [0, 2]);
+}
+
+#[test]
+fn job_panics() {
+    let dispatcher = Dispatcher::builder().worker_threads(1).build().unwrap();
+    let handle = dispatcher
+        .dispatch(|_: &()| async { panic!("job panics") })
+        .unwrap();
+    let payload = catch_unwind(AssertUnwindSafe(|| handle.join_blocking())).unwrap_err();
+    assert_eq!(payload.downcast_ref::<&str>(), Some(&"job panics"));
+
+    // The worker goes on.
+    let handle = dispatcher.dispatch(|_: &()| async { 42 }).unwrap();
+    assert_eq!(handle.join_blocking(), 42);
+    dispatcher.join().unwrap();
+}
+
+#[test]
+fn no_worker() {
+    let e = Dispatcher::builder().worker_threads(0).build().unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+}
@@ -2,9 +2,11 @@ use std::{io, time::Duration};
 
 use arrayvec::ArrayVec;
 use compio::{
-    driver::{AsRawFd, Entry, Proactor},
+    buf::IntoInner,
+    driver::{AsRawFd, Entry, OwnedOps, PollStats, Proactor, ProactorBuilder, PushEntry},
     fs::File,
-    op::ReadAt,
+    net::UdpSocket,
+    op::{ReadAt, Recv, Send, WriteAt},
 };
 
 #[test]
@@ -17,7 +19,14 @@ fn cancel_before_poll() {
     driver.cancel(0);
 
     let op = ReadAt::new(file.as_raw_fd(), 0, Vec::with_capacity(8));
-    let key = driver.push(op);
+    let key = match driver.push_entry(op) {
+        PushEntry::Pending(key) => key,
+        // The cancellation has no effect on the completed operation.
+        PushEntry::Ready((res, _)) => {
+            res.unwrap();
+            return;
+        }
+    };
 
     let mut entries = ArrayVec::<Entry, 1>::new();
     driver.poll(None, &mut entries).unwrap();
@@ -47,12 +56,446 @@ fn register_multiple() {
     let file = File::open("Cargo.toml").unwrap();
     driver.attach(file.as_raw_fd()).unwrap();
 
+    let mut pending = 0;
     for _i in 0..TASK_LEN {
-        driver.push(ReadAt::new(file.as_raw_fd(), 0, Vec::with_capacity(1024)));
+        match driver.push_entry(ReadAt::new(file.as_raw_fd(), 0, Vec::with_capacity(1024))) {
+            PushEntry::Pending(_) => pending += 1,
+            PushEntry::Ready((res, _)) => assert!(res.unwrap() > 0),
+        }
     }
 
     let mut entries = ArrayVec::<Entry, TASK_LEN>::new();
-    while entries.len() < TASK_LEN {
+    while entries.len() < pending {
         driver.poll(None, &mut entries).unwrap();
     }
 }
+
+#[test]
+fn push_entry_ready() {
+    let mut driver = Proactor::new().unwrap();
+
+    let file = File::open("Cargo.toml").unwrap();
+    driver.attach(file.as_raw_fd()).unwrap();
+
+    let res = driver.push_entry(ReadAt::new(file.as_raw_fd(), 0, Vec::with_capacity(8)));
+    if cfg!(all(unix, not(all(target_os = "linux", feature = "io-uring")))) {
+        // The file is read inline with polling.
+        let PushEntry::Ready((res, _)) = res else {
+            panic!("the read should complete inline");
+        };
+        assert_eq!(res.unwrap(), 8);
+    } else {
+        assert!(!res.is_ready());
+    }
+}
+
+#[test]
+#[allow(deprecated)]
+fn push_compat() {
+    let mut driver = Proactor::new().unwrap();
+
+    let file = File::open("Cargo.toml").unwrap();
+    driver.attach(file.as_raw_fd()).unwrap();
+
+    // The operation completed inline is returned from `poll` as before.
+    let key = driver.push(ReadAt::new(file.as_raw_fd(), 0, Vec::with_capacity(8)));
+    let mut entries = ArrayVec::<Entry, 1>::new();
+    while entries.is_empty() {
+        driver.poll(None, &mut entries).unwrap();
+    }
+    let (res, op) = driver.pop(&mut entries.into_iter()).next().unwrap();
+    assert_eq!(op.user_data(), key);
+    assert_eq!(res.unwrap(), 8);
+}
+
+#[test]
+fn spin_before_wait() {
+    let mut driver = ProactorBuilder::new()
+        .spin_before_wait(Duration::from_millis(100))
+        .build()
+        .unwrap();
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.connect(socket.local_addr().unwrap()).unwrap();
+    driver.attach(socket.as_raw_fd()).unwrap();
+
+    assert!(!driver
+        .push_entry(Send::new(socket.as_raw_fd(), "hello"))
+        .is_ready());
+    let mut entries = ArrayVec::<Entry, 1>::new();
+    while entries.is_empty() {
+        driver.poll(None, &mut entries).unwrap();
+    }
+    let stats = driver.poll_stats();
+    assert!(stats.spin_completions > 0);
+
+    // No operation completes, so it spins and then waits.
+    let err = driver
+        .poll(Some(Duration::from_millis(200)), &mut entries)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert_eq!(driver.poll_stats().blocking_waits, stats.blocking_waits + 1);
+}
+
+#[test]
+fn no_spin_by_default() {
+    let mut driver = Proactor::new().unwrap();
+
+    let mut entries = ArrayVec::<Entry, 1>::new();
+    driver
+        .poll(Some(Duration::from_millis(10)), &mut entries)
+        .unwrap_err();
+    assert_eq!(
+        driver.poll_stats(),
+        PollStats {
+            polls: 1,
+            spin_completions: 0,
+            blocking_waits: 1
+        }
+    );
+}
+
+#[test]
+fn napi_busy_poll() {
+    let res = ProactorBuilder::new()
+        .napi_busy_poll(Duration::from_micros(50), true)
+        .build();
+    if cfg!(all(target_os = "linux", feature = "io-uring")) {
+        let mut driver = match res {
+            Ok(driver) => driver,
+            // The kernel is too old.
+            Err(e) => {
+                assert_eq!(e.kind(), io::ErrorKind::Unsupported);
+                return;
+            }
+        };
+        driver.set_napi_busy_poll(None, false).unwrap();
+        driver.set_napi_busy_poll(None, false).unwrap();
+        driver
+            .set_napi_busy_poll(Some(Duration::from_micros(100)), false)
+            .unwrap();
+
+        // The sockets work with busy polling.
+        let rx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let tx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        tx.connect(rx.local_addr().unwrap()).unwrap();
+        driver.attach(rx.as_raw_fd()).unwrap();
+        driver.attach(tx.as_raw_fd()).unwrap();
+        assert!(!driver
+            .push_entry(Send::new(tx.as_raw_fd(), b"ping".to_vec()))
+            .is_ready());
+        assert!(!driver
+            .push_entry(Recv::new(rx.as_raw_fd(), Vec::with_capacity(4)))
+            .is_ready());
+        let mut entries = ArrayVec::<Entry, 2>::new();
+        while entries.len() < 2 {
+            driver.poll(None, &mut entries).unwrap();
+        }
+        for (res, _) in driver.pop(&mut entries.into_iter()) {
+            assert_eq!(res.unwrap(), 4);
+        }
+    } else {
+        let mut driver = res.unwrap();
+        let err = driver
+            .set_napi_busy_poll(Some(Duration::from_micros(100)), false)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        driver.set_napi_busy_poll(None, false).unwrap();
+    }
+}
+
+#[test]
+#[cfg(target_os = "windows")]
+fn foreign_completion() {
+    use std::{
+        os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle},
+        ptr::null_mut,
+        sync::{Arc, Mutex},
+    };
+
+    use windows_sys::Win32::{
+        Foundation::INVALID_HANDLE_VALUE,
+        System::IO::{CreateIoCompletionPort, PostQueuedCompletionStatus},
+    };
+
+    let port = unsafe { CreateIoCompletionPort(INVALID_HANDLE_VALUE, 0, 0, 0) };
+    assert_ne!(port, 0);
+    let port = unsafe { OwnedHandle::from_raw_handle(port as _) };
+    let foreign_port = port.try_clone().unwrap();
+
+    let packets = Arc::new(Mutex::new(vec![]));
+    let mut driver = ProactorBuilder::new()
+        .with_existing_port(port)
+        .on_foreign_completion({
+            let packets = packets.clone();
+            move |entry| {
+                packets.lock().unwrap().push((
+                    entry.lpCompletionKey,
+                    entry.lpOverlapped as usize,
+                    entry.dwNumberOfBytesTransferred,
+                ))
+            }
+        })
+        .build()
+        .unwrap();
+
+    // A crafted overlapped pointer, which should never be dereferenced.
+    for (key, overlapped, transferred) in [(0, 0, 1), (42, 0x1000, 2)] {
+        let res = unsafe {
+            PostQueuedCompletionStatus(
+                foreign_port.as_raw_handle() as _,
+                transferred,
+                key,
+                overlapped as *mut _,
+            )
+        };
+        assert_ne!(res, 0);
+    }
+
+    let file = File::open("Cargo.toml").unwrap();
+    driver.attach(file.as_raw_fd()).unwrap();
+    let PushEntry::Pending(key) =
+        driver.push_entry(ReadAt::new(file.as_raw_fd(), 0, Vec::with_capacity(8)))
+    else {
+        unreachable!("the file is read asynchronously")
+    };
+
+    let mut entries = ArrayVec::<Entry, 1>::new();
+    // The foreign packets don't produce entries.
+    while entries.is_empty() {
+        driver.poll(None, &mut entries).unwrap();
+    }
+    let (res, op) = driver.pop(&mut entries.into_iter()).next().unwrap();
+    assert_eq!(op.user_data(), key);
+    assert_eq!(res.unwrap(), 8);
+
+    assert_eq!(*packets.lock().unwrap(), [(0, 0, 1), (42, 0x1000, 2)]);
+}
+
+#[test]
+fn cq_entries() {
+    const OPS: usize = 64;
+
+    let mut driver = ProactorBuilder::new()
+        .capacity(8)
+        .cq_entries(OPS as _)
+        .build()
+        .unwrap();
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.connect(socket.local_addr().unwrap()).unwrap();
+    driver.attach(socket.as_raw_fd()).unwrap();
+
+    // More completions than the submission queue.
+    let mut completed = 0;
+    for _ in 0..OPS {
+        match driver.push_entry(Send::new(socket.as_raw_fd(), "hello")) {
+            PushEntry::Ready((res, _)) => {
+                res.unwrap();
+                completed += 1;
+            }
+            PushEntry::Pending(_) => {}
+        }
+    }
+    let mut entries = ArrayVec::<Entry, OPS>::new();
+    while completed + entries.len() < OPS {
+        driver.poll(None, &mut entries).unwrap();
+    }
+    for (res, _) in driver.pop(&mut entries.into_iter()) {
+        assert_eq!(res.unwrap(), 5);
+    }
+
+    // The completion queue should not be smaller than the submission queue.
+    let res = ProactorBuilder::new().capacity(64).cq_entries(8).build();
+    assert_eq!(
+        res.is_err(),
+        cfg!(all(target_os = "linux", feature = "io-uring"))
+    );
+}
+
+#[test]
+fn shared_workqueue() {
+    const THREADS: usize = 16;
+
+    // Count the kernel workers of this process.
+    fn kernel_workers() -> usize {
+        std::fs::read_dir("/proc/self/task")
+            .map(|tasks| {
+                tasks
+                    .filter_map(|task| std::fs::read(task.ok()?.path().join("comm")).ok())
+                    .filter(|comm| comm.starts_with(b"iou-wrk"))
+                    .count()
+            })
+            .unwrap_or_default()
+    }
+
+    let first = ProactorBuilder::new().max_kernel_workers(2, 2).build();
+    let first = match first {
+        Ok(first) => first,
+        // The kernel is too old.
+        Err(_) if cfg!(all(target_os = "linux", feature = "io-uring")) => return,
+        Err(e) => panic!("{e}"),
+    };
+    let builder = ProactorBuilder::new()
+        .share_workqueue_with(first.as_raw_fd())
+        .max_kernel_workers(2, 2);
+    let dir = tempfile::tempdir().unwrap();
+    let workers = std::thread::scope(|s| {
+        let threads = (0..THREADS)
+            .map(|i| {
+                let (builder, path) = (&builder, dir.path().join(i.to_string()));
+                s.spawn(move || {
+                    let mut driver = builder.build().unwrap();
+                    let file = std::fs::File::create(path).unwrap();
+                    driver.attach(file.as_raw_fd()).unwrap();
+                    let mut workers = 0;
+                    for n in 0..16u64 {
+                        let op = WriteAt::new(file.as_raw_fd(), n << 20, vec![i as u8; 1 << 20]);
+                        let res = match driver.push_entry(op) {
+                            PushEntry::Ready((res, _)) => res,
+                            PushEntry::Pending(_) => {
+                                let mut entries = ArrayVec::<Entry, 1>::new();
+                                while entries.is_empty() {
+                                    driver.poll(None, &mut entries).unwrap();
+                                }
+                                workers = workers.max(kernel_workers());
+                                driver.pop(&mut entries.into_iter()).next().unwrap().0
+                            }
+                        };
+                        assert_eq!(res.unwrap(), 1 << 20);
+                    }
+                    workers
+                })
+            })
+            .collect::<Vec<_>>();
+        threads
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .max()
+            .unwrap()
+    });
+    // At most 2 bounded workers per thread and NUMA node.
+    let nodes = std::fs::read_dir("/sys/devices/system/node")
+        .map(|dir| {
+            dir.filter_map(Result::ok)
+                .filter(|node| node.file_name().to_string_lossy().starts_with("node"))
+                .count()
+        })
+        .unwrap_or_default()
+        .max(1);
+    assert!(workers <= 2 * THREADS * nodes, "{workers}");
+    for i in 0..THREADS {
+        let data = std::fs::read(dir.path().join(i.to_string())).unwrap();
+        assert_eq!(data.len(), 16 << 20);
+        assert!(data.iter().all(|b| *b == i as u8));
+    }
+
+    // The fd should be an io-uring instance.
+    let file = File::open("Cargo.toml").unwrap();
+    let res = ProactorBuilder::new()
+        .share_workqueue_with(file.as_raw_fd())
+        .build();
+    assert_eq!(
+        res.is_err(),
+        cfg!(all(target_os = "linux", feature = "io-uring"))
+    );
+}
+#[test]
+fn owned_ops() {
+    let mut driver = Proactor::new().unwrap();
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.connect(socket.local_addr().unwrap()).unwrap();
+    driver.attach(socket.as_raw_fd()).unwrap();
+
+    // A send pushed into the proactor directly.
+    let PushEntry::Pending(send) = driver.push_entry(Send::new(socket.as_raw_fd(), "ping")) else {
+        unreachable!("sockets are always waited")
+    };
+    let recv = || Recv::new(socket.as_raw_fd(), Vec::with_capacity(4));
+    let mut ops = OwnedOps::new(&mut driver);
+    let PushEntry::Pending(first) = ops.submit(recv()) else {
+        unreachable!("sockets are always waited")
+    };
+    let mut entries = vec![];
+    while entries.len() < 2 {
+        ops.proactor().poll(None, &mut entries).unwrap();
+    }
+    let mut received = 0;
+    for entry in entries {
+        match ops.complete(entry) {
+            Ok((id, (res, op))) => {
+                assert_eq!(id, first);
+                assert_eq!(res.unwrap(), 4);
+                let mut buffer = op.into_inner().into_inner();
+                unsafe { buffer.set_len(4) };
+                assert_eq!(buffer, b"ping");
+                received += 1;
+            }
+            Err(entry) => {
+                assert_eq!(entry.user_data(), send);
+                // The send is popped from the proactor directly.
+                let mut entries = std::iter::once(entry);
+                ops.proactor().pop(&mut entries).for_each(drop);
+            }
+        }
+    }
+    assert_eq!(received, 1);
+    assert!(ops.is_empty());
+
+    // The recvs never complete, and are cancelled and drained on drop.
+    for _ in 0..4 {
+        ops.submit(recv());
+    }
+    assert_eq!(ops.len(), 4);
+    drop(ops);
+    let mut entries = ArrayVec::<Entry, 1>::new();
+    let res = driver.poll(Some(Duration::from_millis(10)), &mut entries);
+    assert_eq!(res.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    assert!(entries.is_empty());
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+fn registered_ring() {
+    fn read(driver: &mut Proactor) {
+        let file = File::open("Cargo.toml").unwrap();
+        let op = ReadAt::new(file.as_raw_fd(), 0, Vec::with_capacity(8));
+        let PushEntry::Pending(_) = driver.push_entry(op) else {
+            unreachable!("io-uring ops are completed in polls")
+        };
+        let mut entries = ArrayVec::<Entry, 1>::new();
+        while entries.is_empty() {
+            driver
+                .poll(Some(Duration::from_secs(10)), &mut entries)
+                .unwrap();
+        }
+        let (res, _) = driver.pop(&mut entries.into_iter()).next().unwrap();
+        assert_eq!(res.unwrap(), 8);
+        let mut entries = ArrayVec::<Entry, 1>::new();
+        let err = driver
+            .poll(Some(Duration::from_millis(1)), &mut entries)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    // The registrations are counted per thread.
+    std::thread::spawn(|| {
+        let registered = Proactor::new().unwrap().registered_ring();
+        println!("registered ring: {registered}");
+        // Released with the drivers, or a thread could only register 16.
+        for _ in 0..32 {
+            let mut driver = Proactor::new().unwrap();
+            assert_eq!(driver.registered_ring(), registered);
+            read(&mut driver);
+        }
+        // Fall back to the fd beyond the limit.
+        let mut drivers = (0..20)
+            .map(|_| Proactor::new().unwrap())
+            .collect::<Vec<_>>();
+        assert!(drivers[16..].iter().all(|driver| !driver.registered_ring()));
+        for driver in &mut drivers {
+            read(driver);
+        }
+    })
+    .join()
+    .unwrap();
+}
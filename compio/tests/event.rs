@@ -1,4 +1,14 @@
-use compio::event::Event;
+use std::{
+    future::Future,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+};
+
+use compio::event::{Event, OnceEvent, SharedOnceEvent};
 
 #[test]
 fn event_handle() {
@@ -11,3 +21,100 @@ fn event_handle() {
         event.wait().await.unwrap();
     });
 }
+
+// Count the wakeups.
+struct CountWaker(AtomicUsize);
+
+impl Wake for CountWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn once_event_wake_once() {
+    let event = OnceEvent::new();
+    let counter = Arc::new(CountWaker(AtomicUsize::new(0)));
+    let waker = Waker::from(counter.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut waits = (0..8).map(|_| Box::pin(event.wait())).collect::<Vec<_>>();
+    for _ in 0..2 {
+        for wait in &mut waits {
+            assert!(wait.as_mut().poll(&mut cx).is_pending());
+        }
+    }
+    // Dropped before set.
+    waits.truncate(6);
+    event.set(42).unwrap();
+    assert_eq!(counter.0.load(Ordering::Relaxed), 6);
+    for wait in &mut waits {
+        assert_eq!(wait.as_mut().poll(&mut cx), Poll::Ready(&42));
+    }
+    assert_eq!(event.set(43), Err(43));
+    assert_eq!(counter.0.load(Ordering::Relaxed), 6);
+}
+
+#[test]
+fn once_event_many_waiters() {
+    compio::task::block_on(async {
+        let event = Rc::new(OnceEvent::<String>::new());
+        let tasks = (0..100)
+            .map(|_| {
+                let event = event.clone();
+                compio::task::spawn(async move { event.wait().await.clone() })
+            })
+            .collect::<Vec<_>>();
+        compio::task::yield_now().await;
+        assert!(event.get().is_none());
+        event.set("shutdown".to_string()).unwrap();
+        for task in tasks {
+            assert_eq!(task.await, "shutdown");
+        }
+        // Set before waiting.
+        assert_eq!(event.wait().await, "shutdown");
+    })
+}
+
+#[test]
+fn shared_once_event() {
+    let event = SharedOnceEvent::new();
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    let threads = (0..4)
+        .map(|_| {
+            let event = event.clone();
+            let ready_tx = ready_tx.clone();
+            std::thread::spawn(move || {
+                compio::task::block_on(async {
+                    // A waiter dropped in the middle.
+                    let mut dropped = Box::pin(event.wait());
+                    assert!(futures_util::poll!(dropped.as_mut()).is_pending());
+                    drop(dropped);
+
+                    let waits = (0..4)
+                        .map(|_| {
+                            let event = event.clone();
+                            compio::task::spawn(async move { *event.wait().await.unwrap() })
+                        })
+                        .collect::<Vec<_>>();
+                    compio::task::yield_now().await;
+                    ready_tx.send(()).unwrap();
+                    let mut sum = 0;
+                    for wait in waits {
+                        sum += wait.await;
+                    }
+                    sum
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    for _ in 0..4 {
+        ready_rx.recv().unwrap();
+    }
+    event.set(42).unwrap();
+    assert_eq!(event.set(43), Err(43));
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), 42 * 4);
+    }
+    // Set before waiting.
+    compio::task::block_on(async { assert_eq!(event.wait().await.unwrap(), &42) });
+}
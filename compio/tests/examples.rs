@@ -0,0 +1,272 @@
+use std::{
+    io::BufRead,
+    net::{Shutdown, SocketAddr},
+    path::{Path, PathBuf},
+    process::{Child, Command, Output, Stdio},
+    str::FromStr,
+};
+
+use compio::{
+    io::{AsyncRead, AsyncWrite, BufReader},
+    net::TcpStream,
+};
+
+// The examples are built with the tests, next to the directory of the test
+// binaries.
+fn example(name: &str) -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    let path = exe
+        .parent()
+        .and_then(Path::parent)
+        .unwrap()
+        .join("examples")
+        .join(format!("{name}{}", std::env::consts::EXE_SUFFIX));
+    assert!(path.exists(), "{} is not built", path.display());
+    path
+}
+
+// A server example, killed when dropped.
+struct Server<A = SocketAddr> {
+    child: Child,
+    addr: A,
+}
+
+impl<A: FromStr> Server<A> {
+    // Run the example, and wait for the address it listens on.
+    fn spawn(name: &str, args: &[&Path]) -> Self {
+        let mut child = Command::new(example(name))
+            .args(args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        std::io::BufReader::new(child.stdout.as_mut().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let addr = line
+            .trim()
+            .rsplit(' ')
+            .next()
+            .and_then(|addr| addr.trim_start_matches("http://").parse().ok())
+            .unwrap_or_else(|| panic!("unexpected output of {name}: {line:?}"));
+        Self { child, addr }
+    }
+}
+
+impl<A> Drop for Server<A> {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+fn data(len: usize) -> Vec<u8> {
+    let mut state = 0x12345678u32;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+struct Response {
+    status: String,
+    keep_alive: bool,
+    body: Vec<u8>,
+}
+
+async fn get(reader: &mut BufReader<TcpStream>, path: &str, close: bool) -> Response {
+    let connection = if close { "close" } else { "keep-alive" };
+    let request =
+        format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: {connection}\r\n\r\n");
+    reader.write_all(request).await.0.unwrap();
+
+    let (res, status) = reader.read_line(String::new()).await;
+    res.unwrap();
+    let status = status
+        .trim_end()
+        .strip_prefix("HTTP/1.1 ")
+        .unwrap()
+        .to_string();
+    let mut keep_alive = true;
+    let mut content_len = None;
+    let mut chunked = false;
+    loop {
+        let (res, line) = reader.read_line(String::new()).await;
+        res.unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(": ").unwrap();
+        match name.to_ascii_lowercase().as_str() {
+            "connection" => keep_alive = value == "keep-alive",
+            "content-length" => content_len = Some(value.parse().unwrap()),
+            "transfer-encoding" => chunked = value == "chunked",
+            _ => {}
+        }
+    }
+    let mut body = vec![];
+    if chunked {
+        loop {
+            let (res, size) = reader.read_line(String::new()).await;
+            res.unwrap();
+            let size = usize::from_str_radix(size.trim_end(), 16).unwrap();
+            if size > 0 {
+                body.reserve_exact(size);
+                let (res, read) = reader.read_exact(body).await;
+                res.unwrap();
+                body = read;
+            }
+            let (res, end) = reader.read_line(String::new()).await;
+            res.unwrap();
+            assert_eq!(end, "\r\n");
+            if size == 0 {
+                break;
+            }
+        }
+    } else {
+        let (res, read) = reader
+            .read_exact(Vec::with_capacity(content_len.unwrap()))
+            .await;
+        res.unwrap();
+        body = read;
+    }
+    Response {
+        status,
+        keep_alive,
+        body,
+    }
+}
+
+#[test]
+fn http_hello() {
+    let root = tempfile::tempdir().unwrap();
+    // Several chunks, and a partial one.
+    let content = data(200 * 1024 + 7);
+    std::fs::write(root.path().join("data.bin"), &content).unwrap();
+    let server: Server = Server::spawn("http_hello", &["127.0.0.1:0".as_ref(), root.path()]);
+
+    compio::task::block_on(async {
+        let stream = TcpStream::connect(server.addr).await.unwrap();
+        let mut reader = BufReader::new(stream);
+
+        // The requests are served on the same connection.
+        let response = get(&mut reader, "/", false).await;
+        assert_eq!(response.status, "200 OK");
+        assert!(response.keep_alive);
+        assert_eq!(response.body, b"Hello, world!\n");
+
+        let response = get(&mut reader, "/data.bin", false).await;
+        assert_eq!(response.status, "200 OK");
+        assert_eq!(response.body, content);
+
+        let response = get(&mut reader, "/missing", false).await;
+        assert_eq!(response.status, "404 Not Found");
+        let response = get(&mut reader, "/../data.bin", false).await;
+        assert_eq!(response.status, "404 Not Found");
+
+        let response = get(&mut reader, "/data.bin", true).await;
+        assert!(!response.keep_alive);
+        assert_eq!(response.body, content);
+        let (res, rest) = reader.read(Vec::with_capacity(1)).await;
+        assert_eq!(res.unwrap(), 0);
+        assert!(rest.is_empty());
+        reader.get_ref().shutdown(Shutdown::Write).unwrap();
+    })
+}
+
+fn send(server: &Server, path: &Path, limit: Option<u64>) -> Output {
+    let mut command = Command::new(example("file_transfer"));
+    command.arg("send").arg(server.addr.to_string()).arg(path);
+    if let Some(limit) = limit {
+        command.arg(limit.to_string());
+    }
+    command.output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn file_transfer() {
+    let src = tempfile::tempdir().unwrap();
+    let dst = tempfile::tempdir().unwrap();
+    let path = src.path().join("data bin");
+    let content = data(3 * 1024 * 1024 + 11);
+    std::fs::write(&path, &content).unwrap();
+    let received = dst.path().join("data bin");
+    let server: Server = Server::spawn(
+        "file_transfer",
+        &["serve".as_ref(), "127.0.0.1:0".as_ref(), dst.path()],
+    );
+
+    // Interrupted after 1MiB.
+    let output = stdout(&send(&server, &path, Some(1024 * 1024)));
+    assert_eq!(output, "sent 1048576 bytes from offset 0\ninterrupted\n");
+
+    // Resumed from there. The server handles the transfers one at a time, so
+    // the partial file has been written.
+    let output = stdout(&send(&server, &path, None));
+    assert_eq!(
+        output,
+        format!(
+            "sent {} bytes from offset 1048576\nok\n",
+            content.len() - 1024 * 1024
+        )
+    );
+    assert_eq!(std::fs::read(&received).unwrap(), content);
+
+    // Nothing left to send.
+    let output = stdout(&send(&server, &path, None));
+    assert_eq!(output, "sent 0 bytes from offset 3145739\nok\n");
+
+    // A corrupted partial file fails the checksum, and the transfer restarts.
+    let mut corrupted = content[..1024].to_vec();
+    corrupted[100] ^= 0xff;
+    std::fs::write(&received, &corrupted).unwrap();
+    let output = send(&server, &path, None);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("checksum mismatch"));
+    let output = stdout(&send(&server, &path, None));
+    assert_eq!(
+        output,
+        format!("sent {} bytes from offset 0\nok\n", content.len())
+    );
+    assert_eq!(std::fs::read(&received).unwrap(), content);
+}
+
+#[cfg(all(unix, feature = "instrumentation", feature = "time"))]
+#[test]
+fn compio_top() {
+    let dir = tempfile::tempdir().unwrap();
+    let sock = dir.path().join("demo.sock");
+    let demo = Server::<PathBuf>::spawn("compio-top", &["demo".as_ref(), &sock]);
+    assert_eq!(demo.addr, sock);
+
+    let output = Command::new(example("compio-top"))
+        .arg(&sock)
+        .arg("3")
+        .output()
+        .unwrap();
+    let output = stdout(&output);
+    let frames = output.split("\n\n").collect::<Vec<_>>();
+    assert_eq!(frames.len(), 3, "{output}");
+    // The tasks of the demo are polled after the first frame.
+    let polled = frames[2]
+        .lines()
+        .skip(2)
+        .filter(|row| row.ends_with(|c: char| c.is_ascii_digit()) && row.contains("compio_top.rs"))
+        .filter(|row| row.split_whitespace().nth(2) != Some("0"))
+        .count();
+    assert!(polled >= 3, "{output}");
+}
@@ -0,0 +1,90 @@
+use std::{
+    net::Ipv4Addr,
+    time::{Duration, Instant},
+};
+
+use compio::{
+    driver::ProactorBuilder,
+    net::{TcpListener, TcpStream},
+};
+
+async fn pair(listener: &TcpListener) -> (TcpStream, TcpStream) {
+    let addr = listener.local_addr().unwrap();
+    let (client, accepted) = futures_util::join!(TcpStream::connect(&addr), listener.accept());
+    (client.unwrap(), accepted.unwrap().0)
+}
+
+async fn echo(stream: TcpStream) {
+    loop {
+        let (res, buf) = stream.recv(Vec::with_capacity(64)).await;
+        match res {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if stream.send_all(buf).await.0.is_err() {
+            break;
+        }
+    }
+}
+
+#[test]
+fn quiet_connection_not_starved() {
+    compio::task::init_with(&ProactorBuilder::new().max_ops_per_fd(4)).unwrap();
+    compio::task::block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let (hot, hot_server) = pair(&listener).await;
+        let (quiet, quiet_server) = pair(&listener).await;
+        compio::task::spawn(echo(hot_server)).detach();
+        compio::task::spawn(echo(quiet_server)).detach();
+
+        // Ping-pong one byte as fast as possible.
+        let hot_task = compio::task::spawn(async move {
+            let mut buf = Vec::with_capacity(1);
+            loop {
+                buf.clear();
+                buf.push(0);
+                let (res, b) = hot.send(buf).await;
+                if res.is_err() {
+                    break;
+                }
+                let (res, b) = hot.recv_exact(b).await;
+                if res.is_err() {
+                    break;
+                }
+                buf = b;
+            }
+        });
+
+        let mut latencies = Vec::new();
+        for _ in 0..50 {
+            compio::time::sleep(Duration::from_millis(10)).await;
+            let start = Instant::now();
+            quiet.send_all("quiet").await.0.unwrap();
+            let (res, _) = quiet.recv_exact(Vec::with_capacity(5)).await;
+            res.unwrap();
+            latencies.push(start.elapsed());
+        }
+        drop(hot_task);
+
+        latencies.sort();
+        let p99 = latencies[latencies.len() * 99 / 100];
+        assert!(p99 < Duration::from_millis(20), "p99 latency: {p99:?}");
+    })
+}
+
+#[test]
+fn over_budget_without_other_ops() {
+    compio::task::init_with(&ProactorBuilder::new().max_ops_per_fd(1)).unwrap();
+    compio::task::block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let (client, server) = pair(&listener).await;
+        // Only the first send of each tick is submitted at once, and the
+        // driver should not wait for a completion before the next one.
+        for _ in 0..16 {
+            client.send_all("x").await.0.unwrap();
+        }
+        let (res, buf) = server.recv_exact(Vec::with_capacity(16)).await;
+        res.unwrap();
+        assert_eq!(buf, [b'x'; 16]);
+    })
+}
@@ -1,6 +1,7 @@
-use std::io::prelude::*;
+use std::{io::prelude::*, pin::pin};
 
-use compio::fs::File;
+use compio::fs::{File, OpenOptions, ReadAtOpts, ReadRangesOptions, RwFlag, WriteAtOpts};
+use futures_util::StreamExt;
 use tempfile::NamedTempFile;
 
 const HELLO: &[u8] = b"hello world...";
@@ -71,6 +72,343 @@ fn drop_open() {
     });
 }
 
+#[test]
+fn copy() {
+    compio::task::block_on(async {
+        let data = pattern(300 * 1024);
+        let mut src = tempfile();
+        src.write_all(&data).unwrap();
+        let dst = tempfile();
+        std::fs::write(dst.path(), b"longer than the source is not kept").unwrap();
+
+        let copied = compio::fs::copy(src.path(), dst.path()).await.unwrap();
+        assert_eq!(copied, data.len() as u64);
+        assert_eq!(std::fs::read(dst.path()).unwrap(), data);
+
+        let empty = tempfile();
+        let copied = compio::fs::copy(empty.path(), dst.path()).await.unwrap();
+        assert_eq!(copied, 0);
+        assert!(std::fs::read(dst.path()).unwrap().is_empty());
+    });
+}
+
+#[test]
+fn copy_range_to() {
+    compio::task::block_on(async {
+        let data = pattern(100 * 1024);
+        let mut src = tempfile();
+        src.write_all(&data).unwrap();
+        let dst = tempfile();
+        std::fs::write(dst.path(), HELLO).unwrap();
+
+        let src = File::open(src.path()).unwrap();
+        let file = OpenOptions::new().write(true).open(dst.path()).unwrap();
+        let copied = src.copy_range_to(&file, 10, 5, 1000).await.unwrap();
+        assert_eq!(copied, 1000);
+        let result = std::fs::read(dst.path()).unwrap();
+        assert_eq!(result.len(), 1005);
+        assert_eq!(&result[..5], &HELLO[..5]);
+        assert_eq!(&result[5..], &data[10..1010]);
+
+        // Stops at the EOF of the source.
+        let copied = src
+            .copy_range_to(&file, data.len() as u64 - 100, 0, 1000)
+            .await
+            .unwrap();
+        assert_eq!(copied, 100);
+        let result = std::fs::read(dst.path()).unwrap();
+        assert_eq!(&result[..100], &data[data.len() - 100..]);
+        assert_eq!(result.len(), 1005);
+    });
+}
+
+// The offsets beyond 4GiB overflow a 32-bit `off_t`. The file is sparse, and
+// takes no space on the disk.
+#[test]
+fn large_offset() {
+    const OFFSET: u64 = 5 * 1024 * 1024 * 1024;
+
+    compio::task::block_on(async {
+        let src = tempfile();
+        src.as_file().set_len(OFFSET + 4096).unwrap();
+        let dst = tempfile();
+        dst.as_file().set_len(OFFSET + 4096).unwrap();
+
+        let data = pattern(1000);
+        let src = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(src.path())
+            .unwrap();
+        let (res, _) = src.write_all_at(data.clone(), OFFSET + 10).await;
+        res.unwrap();
+        let (res, buf) = src
+            .read_exact_at(Vec::with_capacity(data.len()), OFFSET + 10)
+            .await;
+        res.unwrap();
+        assert_eq!(buf, data);
+        // The data is not written to the offset truncated to 32 bits.
+        let (res, buf) = src.read_exact_at(Vec::with_capacity(4096), 0).await;
+        res.unwrap();
+        assert!(buf.iter().all(|b| *b == 0));
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(dst.path())
+            .unwrap();
+        let copied = src
+            .copy_range_to(&file, OFFSET + 10, OFFSET + 20, 1000)
+            .await
+            .unwrap();
+        assert_eq!(copied, 1000);
+        let (res, buf) = file
+            .read_exact_at(Vec::with_capacity(data.len()), OFFSET + 20)
+            .await;
+        res.unwrap();
+        assert_eq!(buf, data);
+    });
+}
+
+#[test]
+fn copy_range_fallback() {
+    compio::task::block_on(async {
+        let data = pattern(300 * 1024);
+        let mut tempfile = tempfile();
+        tempfile.write_all(&data).unwrap();
+
+        // The kernel refuses to copy the overlapped ranges of the same file.
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(tempfile.path())
+            .unwrap();
+        let len = data.len() - 100;
+        let copied = file.copy_range_to(&file, 100, 0, len as u64).await.unwrap();
+        assert_eq!(copied, len as u64);
+        let result = std::fs::read(tempfile.path()).unwrap();
+        assert_eq!(&result[..len], &data[100..]);
+        assert_eq!(result.len(), data.len());
+    });
+}
+
+#[test]
+fn read_chunks() {
+    compio::task::block_on(async {
+        let data = pattern(10 * 1024 + 7);
+        let mut tempfile = tempfile();
+        tempfile.write_all(&data).unwrap();
+        let file = File::open(tempfile.path()).unwrap();
+
+        let mut chunks = pin!(file.read_chunks(7, 1024));
+        let mut result = vec![];
+        let mut ptr = None;
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk.unwrap();
+            assert!(chunk.len() <= 1024);
+            // The dropped buffer is reused.
+            assert_eq!(*ptr.get_or_insert(chunk.as_ptr()), chunk.as_ptr());
+            result.extend_from_slice(&chunk);
+        }
+        assert_eq!(result, data[7..]);
+        assert!(chunks.next().await.is_none());
+
+        // The kept buffers are not reused.
+        let chunks = file.read_chunks(0, 1024);
+        let kept = chunks
+            .map(|chunk| chunk.unwrap().into_vec())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(kept.len(), 11);
+        assert_eq!(kept.concat(), data);
+    });
+}
+
+#[test]
+fn read_chunks_error() {
+    compio::task::block_on(async {
+        let tempfile = tempfile();
+        // Not opened for reading.
+        let file = OpenOptions::new().write(true).open(tempfile.path()).unwrap();
+        let mut chunks = pin!(file.read_chunks(0, 16));
+        assert!(chunks.next().await.unwrap().is_err());
+        assert!(chunks.next().await.is_none());
+    });
+}
+
+#[test]
+fn try_clone_write() {
+    let tempfile = tempfile();
+
+    compio::task::block_on(async {
+        let file = File::create(tempfile.path()).unwrap();
+        let clone = file.try_clone().unwrap();
+        let (first, second) = futures_util::join!(
+            file.write_all_at(vec![1u8; 4096], 0),
+            clone.write_all_at(vec![2u8; 4096], 4096),
+        );
+        assert_eq!(first.0.unwrap(), 4096);
+        assert_eq!(second.0.unwrap(), 4096);
+
+        // The clone works after the original is closed.
+        drop(file);
+        clone.write_all_at(vec![3u8; 10], 8192).await.0.unwrap();
+        clone.sync_all().await.unwrap();
+    });
+
+    let data = std::fs::read(tempfile.path()).unwrap();
+    assert_eq!(data.len(), 8202);
+    assert!(data[..4096].iter().all(|b| *b == 1));
+    assert!(data[4096..8192].iter().all(|b| *b == 2));
+    assert!(data[8192..].iter().all(|b| *b == 3));
+}
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn read_ranges() {
+    compio::task::block_on(async {
+        let mut tempfile = tempfile();
+        let content = (0..4096u32).map(|i| i as u8).collect::<Vec<_>>();
+        tempfile.write_all(&content).unwrap();
+        let file = File::open(tempfile.path()).unwrap();
+
+        let ranges = vec![
+            100..200,
+            0..10,
+            150..250,
+            300..300,
+            4000..5000,
+            5000..6000,
+            10..20,
+        ];
+        let expected = |range: &std::ops::Range<u64>| {
+            let start = (range.start as usize).min(content.len());
+            let end = (range.end as usize).min(content.len());
+            content[start..end].to_vec()
+        };
+        let options = [
+            ReadRangesOptions::new(),
+            ReadRangesOptions::new().coalesce_gap(100),
+            ReadRangesOptions::new()
+                .coalesce_gap(4096)
+                .max_coalesced(128),
+            ReadRangesOptions::new().coalesce_gap(4096).max_in_flight(1),
+        ];
+        for options in &options {
+            let results = file.read_ranges_with(ranges.clone(), options).await;
+            assert_eq!(results.len(), ranges.len());
+            for (range, res) in ranges.iter().zip(results) {
+                assert_eq!(res.unwrap(), expected(range), "{range:?} with {options:?}");
+            }
+        }
+    });
+}
+
+#[test]
+fn write_vectored_all() {
+    compio::task::block_on(async {
+        let tempfile = tempfile();
+        let file = File::create(tempfile.path()).unwrap();
+
+        // More buffers than a vectored write takes at once, with empty ones.
+        let buffers = (0..1500usize)
+            .map(|i| vec![i as u8; i % 7])
+            .collect::<Vec<_>>();
+        let (res, returned) = file.write_vectored_all_at(buffers.clone(), 16).await;
+        let expected = buffers.concat();
+        assert_eq!(res.unwrap(), expected.len());
+        assert_eq!(returned, buffers);
+
+        let content = std::fs::read(tempfile.path()).unwrap();
+        assert_eq!(&content[..16], &[0; 16]);
+        assert_eq!(&content[16..], expected);
+
+        let (res, _) = file.write_vectored_all_at(Vec::<Vec<u8>>::new(), 0).await;
+        assert_eq!(res.unwrap(), 0);
+    });
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn write_at_with_flags() {
+    compio::task::block_on(async {
+        let tempfile = tempfile();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(tempfile.path())
+            .unwrap();
+
+        let opts = WriteAtOpts::new().flag(RwFlag::DataSync);
+        let (res, _) = file.write_at_with(HELLO, 0, &opts).await;
+        assert_eq!(res.unwrap(), HELLO.len());
+        read_hello(&file).await;
+
+        // The offset is ignored, so the flag reaches the kernel.
+        let opts = WriteAtOpts::new().flag(RwFlag::Append);
+        let (res, _) = file.write_at_with(HELLO, 0, &opts).await;
+        assert_eq!(res.unwrap(), HELLO.len());
+        let content = std::fs::read(tempfile.path()).unwrap();
+        assert_eq!(content, [HELLO, HELLO].concat());
+
+        let opts = ReadAtOpts::new().flag(RwFlag::Append);
+        let (res, _) = file.read_at_with(Vec::with_capacity(1), 0, &opts).await;
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    });
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn read_at_with_nowait() {
+    use std::os::fd::AsRawFd;
+
+    compio::task::block_on(async {
+        let mut tempfile = tempfile();
+        tempfile.write_all(&pattern(1024 * 1024)).unwrap();
+        tempfile.as_file().sync_all().unwrap();
+
+        // Drop the clean pages from the page cache.
+        let fd = tempfile.as_file().as_raw_fd();
+        let res = unsafe { libc::posix_fadvise(fd, 0, 0, libc::POSIX_FADV_DONTNEED) };
+        assert_eq!(res, 0);
+
+        let file = File::open(tempfile.path()).unwrap();
+        let opts = ReadAtOpts::new().flag(RwFlag::NoWait);
+        let (res, _) = file
+            .read_at_with(Vec::with_capacity(4096), 512 * 1024, &opts)
+            .await;
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::WouldBlock);
+
+        // Cached by the read without the flag.
+        let (res, _) = file.read_at(Vec::with_capacity(4096), 512 * 1024).await;
+        assert_eq!(res.unwrap(), 4096);
+        let (res, buffer) = file
+            .read_at_with(Vec::with_capacity(4096), 512 * 1024, &opts)
+            .await;
+        assert_eq!(res.unwrap(), 4096);
+        assert_eq!(buffer, pattern(1024 * 1024)[512 * 1024..][..4096]);
+    });
+}
+
+#[test]
+#[cfg(not(target_os = "linux"))]
+fn rw_flags_unsupported() {
+    compio::task::block_on(async {
+        let tempfile = tempfile();
+        let file = File::create(tempfile.path()).unwrap();
+        let opts = WriteAtOpts::new().flag(RwFlag::DataSync);
+        let (res, _) = file.write_at_with(HELLO, 0, &opts).await;
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+
+        // No flags at all.
+        let (res, _) = file.write_at_with(HELLO, 0, &WriteAtOpts::new()).await;
+        assert_eq!(res.unwrap(), HELLO.len());
+    });
+}
+
 fn tempfile() -> NamedTempFile {
     NamedTempFile::new().unwrap()
 }
@@ -80,8 +418,11 @@ async fn poll_once(future: impl std::future::Future) {
 
     let mut future = pin!(future);
 
+    // The files are read inline with polling, and there is nothing to cancel.
+    let polling = cfg!(all(unix, not(all(target_os = "linux", feature = "io-uring"))));
     poll_fn(|cx| {
-        assert!(future.as_mut().poll(cx).is_pending());
+        let res = future.as_mut().poll(cx);
+        assert!(polling || res.is_pending());
         Poll::Ready(())
     })
     .await;
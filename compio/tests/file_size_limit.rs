@@ -0,0 +1,66 @@
+//! The file size limit is of the process, so the limited writes run in a child
+//! process, with the limit set before it starts.
+
+#![cfg(target_os = "linux")]
+
+use std::{
+    io::ErrorKind,
+    os::unix::process::CommandExt,
+    process::{Command, Stdio},
+};
+
+use compio::fs::File;
+use tempfile::NamedTempFile;
+
+const CHILD_ENV: &str = "COMPIO_FILE_SIZE_LIMIT_CHILD";
+
+#[test]
+fn write_vectored_all_partial() {
+    let mut command = Command::new(std::env::current_exe().unwrap());
+    command
+        .args(["limited_write", "--exact", "--nocapture"])
+        .env(CHILD_ENV, "1")
+        .stdout(Stdio::null());
+    unsafe {
+        command.pre_exec(|| {
+            // Get `EFBIG` instead of being killed. The ignored signal and the
+            // limit are kept across `exec`.
+            libc::signal(libc::SIGXFSZ, libc::SIG_IGN);
+            let limit = libc::rlimit {
+                rlim_cur: 1000,
+                rlim_max: libc::RLIM_INFINITY,
+            };
+            if libc::setrlimit(libc::RLIMIT_FSIZE, &limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    assert!(command.status().unwrap().success());
+}
+
+// Run in the child process only.
+#[test]
+fn limited_write() {
+    if std::env::var_os(CHILD_ENV).is_none() {
+        return;
+    }
+
+    compio::task::block_on(async {
+        let tempfile = NamedTempFile::new().unwrap();
+        let file = File::create(tempfile.path()).unwrap();
+
+        // The write stops in the middle of the third buffer, and the next one
+        // resumes from there.
+        let buffers = vec![vec![1u8; 400], vec![2u8; 400], vec![3u8; 400]];
+        let (res, returned) = file.write_vectored_all_at(buffers.clone(), 0).await;
+        let e = res.unwrap_err();
+        assert_eq!(e.written(), 1000);
+        assert_eq!(e.error().raw_os_error(), Some(libc::EFBIG));
+        assert_eq!(std::io::Error::from(e).kind(), ErrorKind::FileTooLarge);
+        assert_eq!(returned, buffers);
+
+        let content = std::fs::read(tempfile.path()).unwrap();
+        assert_eq!(content, buffers.concat()[..1000]);
+    });
+}
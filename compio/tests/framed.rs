@@ -0,0 +1,121 @@
+use std::io;
+
+use compio::{
+    io::framed::{Framed, LengthDelimitedCodec, LinesCodec},
+    net::{TcpListener, TcpStream},
+};
+
+async fn pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, (server, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    (client, server)
+}
+
+#[test]
+fn split_frames() {
+    compio::task::block_on(async {
+        let (client, server) = pair().await;
+        let mut bytes = vec![];
+        for frame in ["hello", "", "compio"] {
+            bytes.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(frame.as_bytes());
+        }
+        let writer = compio::task::spawn(async move {
+            // Send byte by byte, so that the frames are split across receives.
+            for byte in bytes {
+                client.send_all(vec![byte]).await.0.unwrap();
+            }
+        });
+
+        let mut framed = Framed::with_capacity(server, LengthDelimitedCodec::new(), 2);
+        assert_eq!(framed.next().await.unwrap().unwrap(), "hello");
+        assert_eq!(framed.next().await.unwrap().unwrap(), "");
+        assert_eq!(framed.next().await.unwrap().unwrap(), "compio");
+        writer.await;
+        assert!(framed.next().await.is_none());
+    })
+}
+
+#[test]
+fn large_frame() {
+    const LEN: usize = 1024 * 1024;
+
+    compio::task::block_on(async {
+        let (client, server) = pair().await;
+        let frame = (0..LEN).map(|i| i as u8).collect::<Vec<_>>();
+
+        let mut writer = Framed::new(client, LengthDelimitedCodec::new());
+        let mut reader = Framed::with_capacity(server, LengthDelimitedCodec::new(), 16);
+        let (sent, received) = futures_util::join!(writer.send(&frame), reader.next());
+        sent.unwrap();
+        assert_eq!(received.unwrap().unwrap(), frame);
+    })
+}
+
+#[test]
+fn max_buffer_len() {
+    compio::task::block_on(async {
+        let (client, server) = pair().await;
+        client.send_all(vec![b'a'; 64]).await.0.unwrap();
+
+        let mut framed = Framed::with_capacity(server, LinesCodec::new(), 16);
+        framed.set_max_buffer_len(32);
+        let err = framed.next().await.unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    })
+}
+
+#[test]
+fn max_frame_len() {
+    compio::task::block_on(async {
+        let (client, server) = pair().await;
+
+        let mut writer = Framed::new(client, LengthDelimitedCodec::new());
+        writer.send(vec![0u8; 64]).await.unwrap();
+        let mut reader = Framed::new(server, LengthDelimitedCodec::with_max_frame_len(32));
+        let err = reader.next().await.unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let mut writer = Framed::new(writer.into_inner().0, *reader.codec());
+        let err = writer.feed(vec![0u8; 64]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    })
+}
+
+#[test]
+fn eof_mid_frame() {
+    compio::task::block_on(async {
+        let (client, server) = pair().await;
+        let mut bytes = 8u32.to_be_bytes().to_vec();
+        bytes.extend_from_slice(b"half");
+        client.send_all(bytes).await.0.unwrap();
+        drop(client);
+
+        let mut framed = Framed::new(server, LengthDelimitedCodec::new());
+        let err = framed.next().await.unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(framed.next().await.is_none());
+    })
+}
+
+#[test]
+fn lines() {
+    compio::task::block_on(async {
+        let (client, server) = pair().await;
+        client
+            .send_all("first\r\nsecond\n\nlast")
+            .await
+            .0
+            .unwrap();
+        drop(client);
+
+        let mut framed = Framed::with_capacity(server, LinesCodec::new(), 4);
+        let mut lines = vec![];
+        while let Some(line) = framed.next().await {
+            lines.push(line.unwrap());
+        }
+        assert_eq!(lines, ["first", "second", "", "last"]);
+    })
+}
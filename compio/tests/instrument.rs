@@ -0,0 +1,149 @@
+use std::{path::PathBuf, time::Duration};
+
+use compio::{
+    io::BufReader,
+    net::{UnixListener, UnixStream},
+    task::instrument::{Event, VERSION, read_event, serve},
+};
+
+#[test]
+fn encode_decode() {
+    let events = [
+        Event::Hello { version: VERSION },
+        Event::TaskSpawned {
+            id: 1,
+            location: "src/main.rs:10:5".into(),
+        },
+        Event::TaskPolled {
+            id: 1,
+            busy: Duration::from_micros(42),
+        },
+        Event::TaskWoken { id: 1 },
+        Event::TaskCompleted { id: 1 },
+        Event::OpSubmitted {
+            id: 7,
+            task: Some(1),
+            name: "compio::op::Recv".into(),
+        },
+        Event::OpSubmitted {
+            id: 8,
+            task: None,
+            name: String::new(),
+        },
+        Event::OpCompleted { id: 7, ok: true },
+        Event::OpCancelled { id: 8 },
+        Event::Dropped { count: 3 },
+    ];
+    let mut buf = Vec::new();
+    for event in &events {
+        event.encode(&mut buf);
+    }
+
+    let mut rest = &buf[..];
+    for event in &events {
+        let (len, tail) = rest.split_first_chunk::<4>().unwrap();
+        let (frame, tail) = tail.split_at(u32::from_le_bytes(*len) as usize);
+        assert_eq!(&Event::decode(frame).unwrap(), event);
+        for len in 0..frame.len() {
+            assert!(Event::decode(&frame[..len]).is_err());
+        }
+        rest = tail;
+    }
+    assert!(rest.is_empty());
+    assert!(Event::decode(&[255]).is_err());
+}
+
+// Serve the feed, and subscribe a client in the same runtime. The reads of
+// the client are published too.
+async fn subscribe(path: PathBuf) -> BufReader<UnixStream> {
+    let listener = path.clone();
+    compio::task::spawn(async move { serve(listener).await.unwrap() }).detach();
+    while !path.exists() {
+        compio::task::yield_now().await;
+    }
+    let mut reader = BufReader::new(UnixStream::connect(&path).unwrap());
+    let hello = read_event(&mut reader).await.unwrap();
+    assert_eq!(hello, Some(Event::Hello { version: VERSION }));
+    reader
+}
+
+#[test]
+fn task_and_op_events() {
+    let dir = tempfile::tempdir().unwrap();
+    let sock = dir.path().join("accept.sock");
+    compio::task::block_on(async {
+        let mut events = subscribe(dir.path().join("instrument.sock")).await;
+
+        let listener = UnixListener::bind(&sock).unwrap();
+        let line = line!() + 1;
+        let task = compio::task::spawn(async move { listener.accept().await.unwrap() });
+        compio::task::yield_now().await;
+        let _client = UnixStream::connect(&sock).unwrap();
+        task.await;
+
+        let location = format!("{}:{line}:", file!());
+        let mut id = None;
+        let mut log = vec![];
+        while let Some(event) = read_event(&mut events).await.unwrap() {
+            match &event {
+                Event::TaskSpawned {
+                    id: task,
+                    location: l,
+                } if l.starts_with(&location) => {
+                    id = Some(*task);
+                }
+                Event::TaskCompleted { id: task } if Some(*task) == id => {
+                    log.push(event);
+                    break;
+                }
+                _ => {}
+            }
+            if id.is_some() {
+                log.push(event);
+            }
+        }
+        let id = id.unwrap();
+        let position = |f: &dyn Fn(&Event) -> bool| log.iter().position(f).unwrap();
+
+        let polled = position(&|e| matches!(e, Event::TaskPolled { id: t, .. } if *t == id));
+        assert!(position(&|e| *e == Event::TaskWoken { id }) < polled);
+        let submitted = position(&|e| {
+            matches!(e, Event::OpSubmitted { task, name, .. }
+                if *task == Some(id) && name.contains("Accept"))
+        });
+        let Event::OpSubmitted { id: op, .. } = log[submitted] else {
+            unreachable!()
+        };
+        let completed = position(&|e| *e == Event::OpCompleted { id: op, ok: true });
+        // A poll is published when it ends, after the op it submits.
+        assert!(submitted < polled && polled < completed, "{log:?}");
+        assert_eq!(log.last(), Some(&Event::TaskCompleted { id }));
+    });
+}
+
+#[test]
+fn dropped_events() {
+    let dir = tempfile::tempdir().unwrap();
+    compio::task::block_on(async {
+        let mut events = subscribe(dir.path().join("instrument.sock")).await;
+
+        // Far more events than the queue and the socket buffer hold, while
+        // the client doesn't read.
+        compio::task::spawn(async {
+            for _ in 0..20_000 {
+                compio::task::yield_now().await;
+            }
+        })
+        .await;
+
+        let mut received = 0;
+        let dropped = loop {
+            match read_event(&mut events).await.unwrap().unwrap() {
+                Event::Dropped { count } => break count,
+                _ => received += 1,
+            }
+            assert!(received < 100_000, "no events dropped");
+        };
+        assert!(dropped > 0);
+    });
+}
@@ -0,0 +1,82 @@
+//! The signal handler is of the process, so the test is in its own binary.
+
+#![cfg(unix)]
+
+use std::{
+    ffi::CString,
+    os::unix::ffi::OsStrExt,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use compio::{
+    op::BlockingOp,
+    sync::{CancellationExt, CancellationToken},
+};
+
+static URGENT: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn on_urgent(_: libc::c_int) {
+    URGENT.fetch_add(1, Ordering::Relaxed);
+}
+
+// The handler of the user is not called by the cancellation. On Linux, the
+// syscall is interrupted by a real-time signal instead. Otherwise, the op is
+// not cancellable, and completes with its real result.
+#[test]
+fn user_handler() {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_urgent as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        assert_eq!(
+            libc::sigaction(libc::SIGURG, &action, std::ptr::null_mut()),
+            0
+        );
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = CString::new(dir.path().join("fifo").as_os_str().as_bytes()).unwrap();
+    assert_eq!(unsafe { libc::mkfifo(path.as_ptr(), 0o600) }, 0);
+
+    compio::task::block_on(async {
+        let token = CancellationToken::new();
+        let canceller = {
+            let token = token.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                token.cancel();
+            })
+        };
+        // Open the writer later, if the reader is not interrupted.
+        let writer = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(500));
+                let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_NONBLOCK) };
+                if fd >= 0 {
+                    unsafe { libc::close(fd) };
+                }
+            })
+        };
+        // Opening a FIFO without a writer blocks.
+        let op = BlockingOp::new(-1, move |_| {
+            let fd = unsafe { libc::open(path.as_ptr(), libc::O_RDONLY) };
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            unsafe { libc::close(fd) };
+            Ok(0)
+        });
+        let (res, _) = async { compio::task::submit(op).await }
+            .with_cancellation(&token)
+            .await;
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::ECANCELED));
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        assert_eq!(res.unwrap(), 0);
+        canceller.join().unwrap();
+        writer.join().unwrap();
+    });
+    assert_eq!(URGENT.load(Ordering::Relaxed), 0);
+}
@@ -0,0 +1,166 @@
+use std::{io, net::Ipv4Addr};
+
+use compio::{
+    fs::OpenOptions,
+    io::{copy, AsyncRead, AsyncReadAt, AsyncWrite, AsyncWriteAt, BufReader},
+    net::{TcpListener, TcpStream, UdpSocket},
+};
+use tempfile::NamedTempFile;
+
+const HELLO: &[u8] = b"hello world...";
+
+async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, (server, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    (client, server)
+}
+
+// Generic over the traits, as the downstream code.
+async fn copy_exact(mut reader: impl AsyncRead, mut writer: impl AsyncWrite, len: usize) {
+    let (res, buf) = reader.read_exact(Vec::with_capacity(len)).await;
+    assert_eq!(res.unwrap(), len);
+    let (res, _) = writer.write_all(buf).await;
+    assert_eq!(res.unwrap(), len);
+    writer.flush().await.unwrap();
+}
+
+#[test]
+fn stream() {
+    compio::task::block_on(async {
+        let (client, server) = tcp_pair().await;
+
+        // By shared references.
+        (&client).write_all(HELLO).await.0.unwrap();
+        copy_exact(&server, &server, HELLO.len()).await;
+        let (res, buf) = (&client).read_exact(Vec::with_capacity(HELLO.len())).await;
+        res.unwrap();
+        assert_eq!(buf, HELLO);
+
+        // By values.
+        let mut client = client;
+        client.write_all(HELLO).await.0.unwrap();
+        drop(client);
+        let mut server = server;
+        let (res, buf) = server.read_exact(Vec::with_capacity(HELLO.len() + 1)).await;
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(buf, HELLO);
+    })
+}
+
+#[test]
+fn udp() {
+    compio::task::block_on(async {
+        let mut first = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let mut second = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        first.connect(second.local_addr().unwrap()).unwrap();
+        second.connect(first.local_addr().unwrap()).unwrap();
+
+        first.write(HELLO).await.0.unwrap();
+        let (res, buf) = second.read(Vec::with_capacity(64)).await;
+        assert_eq!(res.unwrap(), HELLO.len());
+        assert_eq!(buf, HELLO);
+    })
+}
+
+#[test]
+fn positional() {
+    async fn write_then_read(mut file: impl AsyncReadAt + AsyncWriteAt) {
+        file.write_all_at(HELLO, 4).await.0.unwrap();
+        let (res, buf) = file.read_exact_at(Vec::with_capacity(5), 4).await;
+        res.unwrap();
+        assert_eq!(buf, &HELLO[..5]);
+
+        let (res, buf) = file.read_exact_at(Vec::with_capacity(HELLO.len()), 8).await;
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(buf, &HELLO[4..]);
+    }
+
+    compio::task::block_on(async {
+        let tempfile = NamedTempFile::new().unwrap();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(tempfile.path())
+            .unwrap();
+
+        write_then_read(&file).await;
+        write_then_read(file).await;
+    })
+}
+
+#[test]
+fn buf_reader() {
+    compio::task::block_on(async {
+        let (mut client, server) = tcp_pair().await;
+        client
+            .write_all(&b"first line\nsecond\xff\nthird line\nrest of the stream"[..])
+            .await
+            .0
+            .unwrap();
+        drop(client);
+
+        // The lines span several fills of the buffer.
+        let mut reader = BufReader::with_capacity(4, server);
+        let (res, line) = reader.read_line("> ".to_string()).await;
+        assert_eq!(res.unwrap(), 11);
+        assert_eq!(line, "> first line\n");
+
+        // The invalid line is consumed, and the string is left unchanged.
+        let (res, line) = reader.read_line("> ".to_string()).await;
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(line, "> ");
+
+        let (res, line) = reader.read_until(b' ', vec![]).await;
+        assert_eq!(res.unwrap(), 6);
+        assert_eq!(line, b"third ");
+        assert_eq!(reader.fill_buf().await.unwrap(), b"lin");
+        reader.consume(2);
+
+        // The buffered bytes are read first, and the large reads bypass the
+        // buffer.
+        let (res, buf) = reader.read(Vec::with_capacity(64)).await;
+        assert_eq!(res.unwrap(), 1);
+        assert_eq!(buf, b"n");
+        let (res, buf) = reader.read_exact(Vec::with_capacity(7)).await;
+        res.unwrap();
+        assert_eq!(buf, b"e\nrest ");
+        assert!(reader.buffer().is_empty());
+
+        // The last line has no `\n`.
+        let (res, line) = reader.read_line(String::new()).await;
+        assert_eq!(res.unwrap(), 13);
+        assert_eq!(line, "of the stream");
+        let (res, line) = reader.read_line(String::new()).await;
+        assert_eq!(res.unwrap(), 0);
+        assert!(line.is_empty());
+    })
+}
+
+#[test]
+fn copy_stream() {
+    compio::task::block_on(async {
+        let (client, server) = tcp_pair().await;
+        let (first, second) = tcp_pair().await;
+        let data = (0..200_000).map(|i| i as u8).collect::<Vec<_>>();
+
+        let send = async {
+            (&client).write_all(data.clone()).await.0.unwrap();
+            client.shutdown(std::net::Shutdown::Write).unwrap();
+        };
+        let forward = async {
+            let copied = copy(&mut &server, &mut &first).await.unwrap();
+            first.shutdown(std::net::Shutdown::Write).unwrap();
+            copied
+        };
+        let receive = async {
+            let (res, buf) = second.recv_to_end(vec![]).await;
+            res.unwrap();
+            buf
+        };
+        let ((), copied, received) = futures_util::join!(send, forward, receive);
+        assert_eq!(copied, data.len() as u64);
+        assert_eq!(received, data);
+    })
+}
@@ -0,0 +1,192 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use compio::ipc::ShmChannel;
+use tempfile::TempDir;
+
+const PEER_ENV: &str = "COMPIO_IPC_PEER";
+const MESSAGES: u64 = 1_000_000;
+
+// Create the channel at `name`, and run `open` after it listens.
+async fn create_with<T>(
+    name: &Path,
+    capacity: usize,
+    open: impl std::future::Future<Output = T>,
+) -> (ShmChannel, T) {
+    let server = compio::task::spawn({
+        let name = name.to_path_buf();
+        async move { ShmChannel::create(name, capacity).await }
+    });
+    while !name.exists() {
+        compio::task::yield_now().await;
+    }
+    let opened = open.await;
+    (server.await.unwrap(), opened)
+}
+
+async fn pair(capacity: usize) -> (TempDir, ShmChannel, ShmChannel) {
+    let dir = tempfile::tempdir().unwrap();
+    let name = dir.path().join("ipc.sock");
+    let (server, client) = create_with(&name, capacity, ShmChannel::open(&name)).await;
+    (dir, server, client.unwrap())
+}
+
+fn message(i: usize) -> Vec<u8> {
+    (0..i % 61).map(|j| (i + j) as u8).collect()
+}
+
+#[test]
+fn wrap_around() {
+    compio::task::block_on(async {
+        let (_dir, mut server, mut client) = pair(256).await;
+        // The sizes are not multiples of the capacity, so the records wrap
+        // around the end at different offsets.
+        for i in 0..1000 {
+            let expected = message(i);
+            client.send(expected.clone()).await.0.unwrap();
+            let (res, buf) = server.recv(Vec::with_capacity(64)).await;
+            assert_eq!(res.unwrap(), expected.len());
+            assert_eq!(buf, expected);
+        }
+    })
+}
+
+#[test]
+fn message_len() {
+    compio::task::block_on(async {
+        let (_dir, mut server, mut client) = pair(64).await;
+        assert_eq!(client.capacity(), 64);
+        let e = client.send(vec![0u8; 57]).await.0.unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+
+        client.send(vec![1u8; 56]).await.0.unwrap();
+        let e = server.recv(Vec::with_capacity(16)).await.0.unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+        // The message is not consumed.
+        let (res, buf) = server.recv(Vec::with_capacity(56)).await;
+        assert_eq!(res.unwrap(), 56);
+        assert_eq!(buf, [1u8; 56]);
+    })
+}
+
+#[test]
+fn backpressure() {
+    compio::task::block_on(async {
+        let (_dir, server, mut client) = pair(128).await;
+        let (mut tx, _rx) = server.into_split();
+        // Each message takes 16 bytes of the ring.
+        let sender = compio::task::spawn(async move {
+            for i in 0..100u64 {
+                tx.send(i.to_le_bytes().to_vec()).await.0.unwrap();
+            }
+            tx
+        });
+        for i in 0..100u64 {
+            // Let the sender fill the ring.
+            compio::task::yield_now().await;
+            let (res, buf) = client.recv(Vec::with_capacity(8)).await;
+            res.unwrap();
+            assert_eq!(buf, i.to_le_bytes());
+        }
+        sender.await;
+    })
+}
+
+#[test]
+fn peer_closed() {
+    compio::task::block_on(async {
+        let (_dir, mut server, mut client) = pair(256).await;
+        client.send("bye").await.0.unwrap();
+        drop(client);
+        // The messages sent before closing are received.
+        let (res, buf) = server.recv(Vec::with_capacity(8)).await;
+        res.unwrap();
+        assert_eq!(buf, b"bye");
+        let e = server.recv(Vec::with_capacity(8)).await.0.unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::BrokenPipe);
+    })
+}
+
+fn spawn_peer(name: &Path, mode: &str) -> std::process::Child {
+    Command::new(std::env::current_exe().unwrap())
+        .args(["peer", "--exact", "--nocapture"])
+        .env(PEER_ENV, format!("{mode}:{}", name.display()))
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+// Run in the child processes only.
+#[test]
+fn peer() {
+    let Ok(arg) = std::env::var(PEER_ENV) else {
+        return;
+    };
+    let (mode, name) = arg.split_once(':').unwrap();
+    let name = PathBuf::from(name);
+    compio::task::block_on(async move {
+        let mut channel = ShmChannel::open(&name).await.unwrap();
+        match mode {
+            // Echo until the parent closes.
+            "echo" => loop {
+                let (res, buf) = channel.recv(Vec::with_capacity(64)).await;
+                match res {
+                    Ok(_) => {
+                        channel.send(buf).await.0.unwrap();
+                    }
+                    Err(e) if e.kind() == ErrorKind::BrokenPipe => break,
+                    Err(e) => panic!("{e}"),
+                }
+            },
+            "crash" => {
+                channel.recv(Vec::with_capacity(64)).await.0.unwrap();
+                std::process::abort();
+            }
+            _ => unreachable!(),
+        }
+    })
+}
+
+#[test]
+fn across_processes() {
+    let dir = tempfile::tempdir().unwrap();
+    let name = dir.path().join("ipc.sock");
+    compio::task::block_on(async {
+        let (channel, mut child) =
+            create_with(&name, 4096, async { spawn_peer(&name, "echo") }).await;
+        let (mut tx, mut rx) = channel.into_split();
+        let sender = compio::task::spawn(async move {
+            for i in 0..MESSAGES {
+                tx.send(i.to_le_bytes().to_vec()).await.0.unwrap();
+            }
+        });
+        let mut buf = Vec::with_capacity(8);
+        for i in 0..MESSAGES {
+            buf.clear();
+            let res;
+            (res, buf) = rx.recv(buf).await;
+            res.unwrap();
+            assert_eq!(buf, i.to_le_bytes());
+        }
+        sender.await;
+        drop(rx);
+        assert!(child.wait().unwrap().success());
+    })
+}
+
+#[test]
+fn peer_crash() {
+    let dir = tempfile::tempdir().unwrap();
+    let name = dir.path().join("ipc.sock");
+    compio::task::block_on(async {
+        let (mut channel, mut child) =
+            create_with(&name, 4096, async { spawn_peer(&name, "crash") }).await;
+        channel.send("crash").await.0.unwrap();
+        let e = channel.recv(Vec::with_capacity(8)).await.0.unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::BrokenPipe);
+        assert!(!child.wait().unwrap().success());
+    })
+}
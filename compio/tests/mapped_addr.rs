@@ -0,0 +1,116 @@
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+use compio::net::{MappedAddrPolicy, SocketOpts, TcpListener, TcpStream, UdpSocket};
+
+fn ipv6_available() -> bool {
+    let available = std::net::TcpListener::bind((Ipv6Addr::LOCALHOST, 0)).is_ok();
+    if !available {
+        println!("IPv6 is not available, skipped");
+    }
+    available
+}
+
+fn dual_stack() -> SocketOpts {
+    SocketOpts::new().only_v6(false)
+}
+
+fn v4_client(port: u16) -> SocketAddr {
+    SocketAddrV4::new(Ipv4Addr::LOCALHOST, port).into()
+}
+
+async fn accept_from_v4(policy: MappedAddrPolicy) -> (SocketAddr, SocketAddr) {
+    let listener = TcpListener::bind_with((Ipv6Addr::UNSPECIFIED, 0), &dual_stack()).unwrap();
+    listener.set_mapped_addr_policy(policy);
+    assert_eq!(listener.mapped_addr_policy(), policy);
+    let port = listener.local_addr().unwrap().as_socket().unwrap().port();
+    let (client, accepted) =
+        futures_util::join!(TcpStream::connect(v4_client(port)), listener.accept());
+    let client = client.unwrap().local_addr().unwrap().as_socket().unwrap();
+    (client, accepted.unwrap().1.as_socket().unwrap())
+}
+
+#[test]
+fn tcp_keep_mapped() {
+    if !ipv6_available() {
+        return;
+    }
+    compio::task::block_on(async {
+        let (client, accepted) = accept_from_v4(MappedAddrPolicy::Keep).await;
+        let SocketAddr::V6(accepted) = accepted else {
+            panic!("not mapped: {accepted}");
+        };
+        assert_eq!(accepted.ip().to_ipv4_mapped(), Some(Ipv4Addr::LOCALHOST));
+        assert_eq!(accepted.port(), client.port());
+    })
+}
+
+#[test]
+fn tcp_unmap() {
+    if !ipv6_available() {
+        return;
+    }
+    compio::task::block_on(async {
+        let (client, accepted) = accept_from_v4(MappedAddrPolicy::Unmap).await;
+        assert_eq!(accepted, client);
+    })
+}
+
+#[test]
+fn tcp_v6_only() {
+    if !ipv6_available() {
+        return;
+    }
+    compio::task::block_on(async {
+        let listener =
+            TcpListener::bind_v6_only(SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, 0, 0, 0)).unwrap();
+        let port = listener.local_addr().unwrap().as_socket().unwrap().port();
+        assert!(TcpStream::connect(v4_client(port)).await.is_err());
+
+        let (client, accepted) = futures_util::join!(
+            TcpStream::connect((Ipv6Addr::LOCALHOST, port)),
+            listener.accept()
+        );
+        let client = client.unwrap();
+        let (_, addr) = accepted.unwrap();
+        assert!(addr.is_ipv6());
+        assert_eq!(addr, client.local_addr().unwrap());
+    })
+}
+
+#[test]
+fn udp_policies() {
+    if !ipv6_available() {
+        return;
+    }
+    compio::task::block_on(async {
+        let socket = UdpSocket::bind_with((Ipv6Addr::UNSPECIFIED, 0), &dual_stack()).unwrap();
+        let port = socket.local_addr().unwrap().as_socket().unwrap().port();
+        let sender = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let sender_addr = sender.local_addr().unwrap().as_socket().unwrap();
+
+        for policy in [MappedAddrPolicy::Keep, MappedAddrPolicy::Unmap] {
+            socket.set_mapped_addr_policy(policy);
+            sender.send_to("hello", v4_client(port)).await.0.unwrap();
+            let (res, _) = socket.recv_from(Vec::with_capacity(8)).await;
+            let addr = res.unwrap().1.as_socket().unwrap();
+            match (policy, addr) {
+                (MappedAddrPolicy::Keep, SocketAddr::V6(addr)) => {
+                    assert_eq!(addr.ip().to_ipv4_mapped(), Some(Ipv4Addr::LOCALHOST));
+                    assert_eq!(addr.port(), sender_addr.port());
+                }
+                (MappedAddrPolicy::Unmap, addr) => assert_eq!(addr, sender_addr),
+                (_, addr) => panic!("unexpected address {addr}"),
+            }
+        }
+
+        // Native IPv6 addresses are not changed.
+        let sender = UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).unwrap();
+        sender
+            .send_to("hello", (Ipv6Addr::LOCALHOST, port))
+            .await
+            .0
+            .unwrap();
+        let (res, _) = socket.recv_from(Vec::with_capacity(8)).await;
+        assert_eq!(res.unwrap().1, sender.local_addr().unwrap());
+    })
+}
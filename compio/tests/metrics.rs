@@ -0,0 +1,74 @@
+use std::net::Ipv4Addr;
+
+use compio::{driver::ProactorBuilder, net::UdpSocket, task::Histogram};
+
+#[test]
+fn histogram() {
+    let mut histogram = Histogram::new();
+    assert_eq!(histogram.value_at_quantile(0.5), 0);
+    assert_eq!(histogram.mean(), 0.0);
+
+    for value in [3, 31, 32, 33, 1000, u64::MAX] {
+        histogram.record(value);
+    }
+    assert_eq!(histogram.count(), 6);
+    assert_eq!(histogram.min(), 3);
+    assert_eq!(histogram.max(), u64::MAX);
+    let buckets = histogram.buckets().collect::<Vec<_>>();
+    assert_eq!(buckets[0], (3, 3, 1));
+    assert_eq!(buckets[1], (31, 31, 1));
+    // Wider buckets above 32.
+    assert_eq!(buckets[2], (32, 33, 2));
+    assert_eq!(buckets[3], (992, 1023, 1));
+    assert_eq!(buckets[4].1, u64::MAX);
+    // The relative error is bounded.
+    for (low, high, _) in buckets {
+        assert!(high - low <= low / 16, "{low} - {high}");
+    }
+
+    assert_eq!(histogram.value_at_quantile(0.0), 3);
+    assert_eq!(histogram.value_at_quantile(0.5), 33);
+    assert_eq!(histogram.value_at_quantile(0.8), 1023);
+    assert_eq!(histogram.value_at_quantile(1.0), u64::MAX);
+
+    histogram.reset();
+    assert_eq!(histogram, Histogram::new());
+}
+
+async fn ping_pong(rounds: usize) {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    socket.connect(socket.local_addr().unwrap()).unwrap();
+    for _ in 0..rounds {
+        socket.send("ping").await.0.unwrap();
+        socket.recv(Vec::with_capacity(4)).await.0.unwrap();
+    }
+}
+
+#[test]
+fn runtime() {
+    std::thread::spawn(|| {
+        compio::task::init_with(&ProactorBuilder::new().latency_metrics(true)).unwrap();
+        compio::task::block_on(ping_pong(16));
+        let metrics = compio::task::latency_metrics().unwrap();
+        // The sends may complete inline with polling.
+        assert!(metrics.completion_delay.count() >= 16);
+        assert!(metrics.task_polls.count() >= 16);
+        assert!(metrics.task_polls.max() >= 1);
+
+        compio::task::reset_latency_metrics();
+        let metrics = compio::task::latency_metrics().unwrap();
+        assert_eq!(metrics.completion_delay.count(), 0);
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn disabled() {
+    std::thread::spawn(|| {
+        compio::task::block_on(ping_pong(1));
+        assert!(compio::task::latency_metrics().is_none());
+    })
+    .join()
+    .unwrap();
+}
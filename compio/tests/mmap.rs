@@ -0,0 +1,113 @@
+use std::{io::ErrorKind, net::Ipv4Addr};
+
+use compio::{
+    fs::{File, MapMode, MmapMut, OpenOptions},
+    net::{TcpListener, TcpStream},
+};
+use tempfile::NamedTempFile;
+
+fn open(tempfile: &NamedTempFile, len: u64) -> File {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(tempfile.path())
+        .unwrap();
+    file.set_len(len).unwrap();
+    file
+}
+
+#[test]
+fn recv_into_mapping() {
+    compio::task::block_on(async {
+        let tempfile = NamedTempFile::new().unwrap();
+        let file = open(&tempfile, 4096 * 3);
+        let mut map = unsafe { MmapMut::map(&file, 4096 * 3, MapMode::Shared) }.unwrap();
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, (server, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+
+        // Across the page boundary.
+        let data = (0..6000).map(|i| i as u8).collect::<Vec<_>>();
+        client.send_all(data.clone()).await.0.unwrap();
+        let (res, region) = server.recv_exact(map.region(4000..10000)).await;
+        res.unwrap();
+        assert_eq!(region.offset(), 4000);
+        drop(region);
+
+        map.flush_range(4000, 6000).await.unwrap();
+        let content = std::fs::read(tempfile.path()).unwrap();
+        assert_eq!(&content[4000..10000], data);
+        assert!(content[..4000].iter().all(|b| *b == 0));
+        assert_eq!(&map.as_slice().unwrap()[4000..10000], data);
+
+        map.as_mut_slice().unwrap()[0] = 1;
+        map.start_flush_range(0, 1).unwrap();
+        map.flush_range(0, 0).await.unwrap();
+    })
+}
+
+#[test]
+fn remap() {
+    compio::task::block_on(async {
+        let tempfile = NamedTempFile::new().unwrap();
+        let file = open(&tempfile, 5);
+        let mut map = unsafe { MmapMut::map(&file, 5, MapMode::Shared) }.unwrap();
+        map.as_mut_slice().unwrap().copy_from_slice(b"hello");
+
+        // The region refers to the old mapping.
+        let region = map.region(..);
+        assert!(map.as_slice().is_none());
+        file.set_len(11).unwrap();
+        let e = map.remap(11).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::ResourceBusy);
+        assert_eq!(map.len(), 5);
+        drop(region);
+
+        map.remap(11).unwrap();
+        assert_eq!(map.len(), 11);
+        map.as_mut_slice().unwrap()[5..].copy_from_slice(b" world");
+        map.flush_range(0, 11).await.unwrap();
+        assert_eq!(std::fs::read(tempfile.path()).unwrap(), b"hello world");
+
+        let e = map.remap(12).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+        assert_eq!(map.len(), 11);
+    })
+}
+
+#[test]
+fn copy_on_write() {
+    compio::task::block_on(async {
+        let tempfile = NamedTempFile::new().unwrap();
+        std::fs::write(tempfile.path(), b"hello").unwrap();
+        let file = File::open(tempfile.path()).unwrap();
+        let mut map = unsafe { MmapMut::map(&file, 5, MapMode::CopyOnWrite) }.unwrap();
+        assert_eq!(map.as_slice().unwrap(), b"hello");
+
+        map.as_mut_slice().unwrap().copy_from_slice(b"world");
+        map.flush_range(0, 5).await.unwrap();
+        assert_eq!(map.as_slice().unwrap(), b"world");
+        assert_eq!(std::fs::read(tempfile.path()).unwrap(), b"hello");
+    })
+}
+
+#[test]
+fn out_of_range() {
+    compio::task::block_on(async {
+        let tempfile = NamedTempFile::new().unwrap();
+        let file = open(&tempfile, 5);
+        let e = unsafe { MmapMut::map(&file, 6, MapMode::Shared) }.unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+
+        let map = unsafe { MmapMut::map(&file, 5, MapMode::Shared) }.unwrap();
+        let e = map.flush_range(1, 5).await.unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput);
+
+        let empty = unsafe { MmapMut::map(&file, 0, MapMode::Shared) }.unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.as_slice().unwrap(), b"");
+        empty.flush_range(0, 0).await.unwrap();
+    })
+}
@@ -0,0 +1,98 @@
+use std::{
+    future::Future,
+    io,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::Thread,
+};
+
+use compio::{
+    driver::AsRawFd,
+    fs::File,
+    net::TcpStream,
+    op::ReadAt,
+    task::{submit, submit_set},
+};
+
+// A minimal executor parking the thread, as the ones of the other crates.
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        std::thread::park();
+    }
+}
+
+fn assert_no_runtime(e: io::Error) {
+    assert_eq!(e.kind(), io::ErrorKind::Other);
+    assert!(
+        e.to_string().contains("compio::task::block_on"),
+        "unexpected error: {e}"
+    );
+}
+
+#[test]
+fn file() {
+    let file = File::open("Cargo.toml").unwrap();
+    let (res, buffer) = block_on(file.read_at(Vec::with_capacity(16), 0));
+    assert_no_runtime(res.unwrap_err());
+    assert_eq!(buffer.capacity(), 16);
+
+    // It works in the runtime of the same thread.
+    let (res, buffer) = compio::task::block_on(file.read_at(buffer, 0));
+    assert_eq!(res.unwrap(), 16);
+    assert_eq!(buffer, std::fs::read("Cargo.toml").unwrap()[..16]);
+}
+
+#[test]
+fn tcp() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    match block_on(TcpStream::connect(addr)) {
+        Ok(_) => panic!("connected without a runtime"),
+        Err(e) => assert_no_runtime(e),
+    }
+}
+
+#[test]
+fn op() {
+    let file = File::open("Cargo.toml").unwrap();
+    assert_no_runtime(compio::task::attach(file.as_raw_fd()).unwrap_err());
+
+    // The op is given back.
+    let op = ReadAt::new(file.as_raw_fd(), 0, Vec::with_capacity(16));
+    let (res, _) = block_on(submit(op));
+    assert_no_runtime(res.unwrap_err());
+
+    let mut set = submit_set((0..2).map(|i| ReadAt::new(file.as_raw_fd(), i, vec![])));
+    for _ in 0..2 {
+        let (res, _) = block_on(set.next()).unwrap();
+        assert_no_runtime(res.unwrap_err());
+    }
+    assert!(block_on(set.next()).is_none());
+}
+
+#[test]
+fn other_thread() {
+    // A thread which never runs a compio runtime.
+    std::thread::spawn(|| {
+        let file = File::open("Cargo.toml").unwrap();
+        let (res, _) = block_on(file.read_at(Vec::with_capacity(16), 0));
+        assert_no_runtime(res.unwrap_err());
+    })
+    .join()
+    .unwrap();
+}
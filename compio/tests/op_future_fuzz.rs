@@ -0,0 +1,185 @@
+//! Drive the op futures with random sequences of polls, drops and panics, and
+//! check that the runtime stays consistent.
+
+use std::{
+    future::Future,
+    net::{Ipv4Addr, UdpSocket as StdUdpSocket},
+    panic::{catch_unwind, AssertUnwindSafe},
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use compio::{driver::AsRawFd, net::UdpSocket, op::Recv, BufResult};
+use futures_util::task::noop_waker_ref;
+
+type RecvFuture = Pin<Box<dyn Future<Output = BufResult<usize, Recv<Vec<u8>>>>>>;
+
+// A xorshift generator, so that a failed sequence could be replayed by the
+// seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+enum Slot {
+    Pending(RecvFuture),
+    // The future has returned `Ready`, and is kept to be polled again.
+    Completed(RecvFuture),
+}
+
+struct Harness {
+    socket: UdpSocket,
+    sender: StdUdpSocket,
+    slots: Vec<Slot>,
+    received: usize,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let sender = StdUdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        sender
+            .connect(socket.local_addr().unwrap().as_socket().unwrap())
+            .unwrap();
+        compio::task::attach(socket.as_raw_fd()).unwrap();
+        Self {
+            socket,
+            sender,
+            slots: vec![],
+            received: 0,
+        }
+    }
+
+    fn submit(&mut self) {
+        let op = Recv::new(self.socket.as_raw_fd(), Vec::with_capacity(8));
+        self.slots
+            .push(Slot::Pending(Box::pin(compio::task::submit(op))));
+    }
+
+    fn poll(&mut self, index: usize) {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let slot = &mut self.slots[index];
+        match slot {
+            Slot::Pending(future) => {
+                if let Poll::Ready((res, _)) = future.as_mut().poll(&mut cx) {
+                    assert_eq!(res.unwrap(), 4);
+                    self.received += 1;
+                    let Slot::Pending(future) = self.slots.swap_remove(index) else {
+                        unreachable!()
+                    };
+                    self.slots.push(Slot::Completed(future));
+                }
+            }
+            Slot::Completed(future) => {
+                let e = catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(&mut cx)))
+                    .err()
+                    .expect("polled after completion");
+                let message = e
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| e.downcast_ref::<String>().map(String::as_str))
+                    .unwrap();
+                assert!(message.contains("after completion"), "{message}");
+            }
+        }
+    }
+
+    fn drop_slot(&mut self, index: usize) {
+        self.slots.swap_remove(index);
+    }
+
+    // Drop the future while unwinding, e.g., when the task panics with the
+    // result completed but not taken.
+    fn panic_with_slot(&mut self, index: usize) {
+        let slot = self.slots.swap_remove(index);
+        catch_unwind(AssertUnwindSafe(move || {
+            let _slot = slot;
+            panic!("panicked while holding the future");
+        }))
+        .unwrap_err();
+    }
+
+    fn send(&self) {
+        self.sender.send(b"ping").unwrap();
+    }
+
+    async fn step(&mut self, rng: &mut Rng) {
+        let len = self.slots.len();
+        match rng.below(8) {
+            0 | 1 => self.submit(),
+            2 | 3 if len > 0 => self.poll(rng.below(len)),
+            4 if len > 0 => self.drop_slot(rng.below(len)),
+            5 if len > 0 => self.panic_with_slot(rng.below(len)),
+            6 => self.send(),
+            _ => compio::task::yield_now().await,
+        }
+    }
+
+    // Drop all the futures, and wait for the cancelled ops to be given back.
+    async fn finish(mut self) {
+        self.slots.clear();
+        let start = Instant::now();
+        while compio::task::registered_ops() > 0 {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "{} ops leaked",
+                compio::task::registered_ops()
+            );
+            compio::task::yield_now().await;
+        }
+    }
+}
+
+#[test]
+fn adversarial_sequences() {
+    compio::task::block_on(async {
+        let mut received = 0;
+        for seed in 1..=200 {
+            let mut rng = Rng(seed);
+            let mut harness = Harness::new();
+            for _ in 0..200 {
+                harness.step(&mut rng).await;
+            }
+            received += harness.received;
+            harness.finish().await;
+        }
+        // The sequences are not all trivial.
+        assert!(received > 100, "only {received} ops completed");
+    })
+}
+
+// The main future panics with a completed op, and the panic unwinds out of
+// `block_on`. The runtime is still usable after that.
+#[test]
+fn panic_in_block_on() {
+    let harness = compio::task::block_on(async { Harness::new() });
+    catch_unwind(AssertUnwindSafe(|| {
+        compio::task::block_on(async {
+            harness.send();
+            let op = Recv::new(harness.socket.as_raw_fd(), Vec::with_capacity(8));
+            let mut future = std::pin::pin!(compio::task::submit(op));
+            // Submit, and let the driver complete it.
+            let mut cx = Context::from_waker(noop_waker_ref());
+            assert!(future.as_mut().poll(&mut cx).is_pending());
+            for _ in 0..10 {
+                compio::task::yield_now().await;
+            }
+            assert_eq!(compio::task::registered_ops(), 1);
+            panic!("panicked while holding the future");
+        })
+    }))
+    .unwrap_err();
+    assert_eq!(compio::task::registered_ops(), 0);
+    compio::task::block_on(harness.finish());
+}
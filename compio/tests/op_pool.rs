@@ -0,0 +1,86 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    net::Ipv4Addr,
+};
+
+use compio::{
+    driver::{OpPoolStats, ProactorBuilder},
+    net::UdpSocket,
+};
+
+// Count the allocations of the current thread only, because the runtimes run
+// on their own threads.
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const ROUNDS: u64 = 1000;
+
+async fn round_trip(socket: &UdpSocket, buffer: Vec<u8>) -> Vec<u8> {
+    let (res, buffer) = socket.send(buffer).await;
+    res.unwrap();
+    let (res, buffer) = socket.recv(buffer).await;
+    res.unwrap();
+    buffer
+}
+
+// The allocations of each round trip on a connected socket, after warming up.
+async fn allocations_per_round() -> f64 {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    socket.connect(socket.local_addr().unwrap()).unwrap();
+    let mut buffer = b"ping".to_vec();
+    for _ in 0..10 {
+        buffer = round_trip(&socket, buffer).await;
+    }
+    let before = ALLOCATIONS.with(Cell::get);
+    for _ in 0..ROUNDS {
+        buffer = round_trip(&socket, buffer).await;
+    }
+    (ALLOCATIONS.with(Cell::get) - before) as f64 / ROUNDS as f64
+}
+
+fn run(builder: ProactorBuilder) -> (f64, OpPoolStats) {
+    std::thread::spawn(move || {
+        compio::task::init_with(&builder).unwrap();
+        compio::task::block_on(async {
+            let allocations = allocations_per_round().await;
+            (allocations, compio::task::op_pool_stats())
+        })
+    })
+    .join()
+    .unwrap()
+}
+
+#[test]
+fn reuse_allocations() {
+    let (pooled, stats) = run(ProactorBuilder::new());
+    assert!(stats.hit_rate() > 0.9, "{stats:?}");
+    assert!(stats.cached > 0);
+
+    let (unpooled, stats) = run(ProactorBuilder::new().op_pool_capacity(0));
+    assert_eq!(stats.hits, 0);
+    assert_eq!(stats.cached, 0);
+
+    // The send and the receive don't allocate for the ops.
+    assert!(
+        unpooled - pooled >= 1.9,
+        "{pooled} allocations per round with the pool, {unpooled} without"
+    );
+}
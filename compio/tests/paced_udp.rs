@@ -0,0 +1,110 @@
+use std::{
+    io,
+    net::Ipv4Addr,
+    time::{Duration, Instant},
+};
+
+use compio::net::{PacedUdpSender, UdpSocket};
+
+const INTERVAL: Duration = Duration::from_millis(20);
+
+#[test]
+fn timer_paced_spacing() {
+    compio::task::block_on(async {
+        let receiver = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let mut sender =
+            PacedUdpSender::with_timer(UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap());
+        assert!(!sender.is_kernel_paced());
+
+        let start = Instant::now() + INTERVAL;
+        let send = async {
+            for i in 0..5u8 {
+                sender
+                    .send_scheduled(vec![i], &addr, start + INTERVAL * i as u32)
+                    .await
+                    .unwrap();
+            }
+            sender.flush().await.unwrap();
+        };
+        let recv = async {
+            let mut arrivals = vec![];
+            for i in 0..5u8 {
+                let (res, buffer) = receiver.recv(Vec::with_capacity(1)).await;
+                res.unwrap();
+                assert_eq!(buffer, [i]);
+                arrivals.push(Instant::now());
+            }
+            arrivals
+        };
+        let ((), arrivals) = futures_util::join!(send, recv);
+
+        assert!(arrivals[0] >= start);
+        for pair in arrivals.windows(2) {
+            let spacing = pair[1] - pair[0];
+            assert!(
+                spacing >= INTERVAL - Duration::from_millis(2)
+                    && spacing <= INTERVAL + Duration::from_millis(15),
+                "unexpected spacing {spacing:?}"
+            );
+        }
+    })
+}
+
+#[test]
+fn timer_paced_batch() {
+    compio::task::block_on(async {
+        let receiver = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let mut sender =
+            PacedUdpSender::with_timer(UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap());
+
+        let deadline = Instant::now() + INTERVAL;
+        for i in 0..3u8 {
+            sender
+                .send_scheduled(vec![i], &addr, deadline)
+                .await
+                .unwrap();
+        }
+        // Nothing is sent before the batch is flushed.
+        assert!(Instant::now() < deadline);
+        sender.flush().await.unwrap();
+        assert!(Instant::now() >= deadline);
+
+        for i in 0..3u8 {
+            let (res, buffer) = receiver.recv(Vec::with_capacity(1)).await;
+            res.unwrap();
+            assert_eq!(buffer, [i]);
+        }
+    })
+}
+
+#[test]
+fn send_time_passed() {
+    compio::task::block_on(async {
+        let receiver = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = receiver.local_addr().unwrap();
+        for mut sender in [
+            PacedUdpSender::new(UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap()),
+            PacedUdpSender::with_timer(UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap()),
+        ] {
+            let send_time = Instant::now() - Duration::from_millis(100);
+            let err = sender
+                .send_scheduled(vec![0u8], &addr, send_time)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+            sender.set_tolerance(Duration::from_secs(1));
+            sender
+                .send_scheduled(vec![1u8], &addr, send_time)
+                .await
+                .unwrap();
+            sender.flush().await.unwrap();
+
+            let (res, buffer) = receiver.recv(Vec::with_capacity(1)).await;
+            res.unwrap();
+            assert_eq!(buffer, [1]);
+        }
+    })
+}
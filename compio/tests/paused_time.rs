@@ -0,0 +1,105 @@
+use std::{
+    future::Future,
+    io::ErrorKind,
+    net::{Ipv4Addr, TcpListener},
+    time::{Duration, Instant},
+};
+
+use compio::{
+    driver::ProactorBuilder,
+    net::{RetryPolicy, UdpSocket, connect_retry},
+    time::{Elapsed, advance, interval, now, sleep, timeout},
+};
+
+// Run the future on a new thread, whose runtime starts with the clock paused.
+fn paused<F: Future<Output = ()>>(f: impl FnOnce() -> F + Send + 'static) {
+    std::thread::spawn(move || {
+        compio::task::init_with(&ProactorBuilder::new().start_paused(true)).unwrap();
+        compio::task::block_on(f())
+    })
+    .join()
+    .unwrap()
+}
+
+#[test]
+fn auto_advance() {
+    paused(|| async {
+        let wall = Instant::now();
+        let start = now();
+        sleep(Duration::from_secs(3600)).await;
+        assert_eq!(now() - start, Duration::from_secs(3600));
+
+        let mut interval = interval(Duration::from_secs(60));
+        let first = interval.tick().await;
+        interval.tick().await;
+        let third = interval.tick().await;
+        assert_eq!(third - first, Duration::from_secs(120));
+        assert!(wall.elapsed() < Duration::from_secs(10));
+    })
+}
+
+#[test]
+fn advance_wakes_timers() {
+    paused(|| async {
+        let timer = compio::task::spawn(sleep(Duration::from_secs(10)));
+        compio::task::yield_now().await;
+        advance(Duration::from_secs(5)).await;
+        assert!(!timer.is_finished());
+        advance(Duration::from_secs(5)).await;
+        assert!(timer.is_finished());
+        timer.await;
+    })
+}
+
+#[test]
+fn timeout_of_ops() {
+    paused(|| async {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        socket.connect(socket.local_addr().unwrap()).unwrap();
+
+        // The real op completes normally.
+        socket.send("ping").await.0.unwrap();
+        let (res, buffer) = timeout(
+            Duration::from_secs(10),
+            socket.recv(Vec::with_capacity(4)),
+        )
+        .await
+        .unwrap();
+        res.unwrap();
+        assert_eq!(buffer, b"ping");
+
+        // The clock doesn't jump while the recv is in flight.
+        let (res, ()) = futures_util::join!(
+            timeout(
+                Duration::from_secs(10),
+                socket.recv(Vec::with_capacity(4))
+            ),
+            advance(Duration::from_secs(10)),
+        );
+        assert_eq!(res.unwrap_err(), Elapsed);
+    })
+}
+
+#[test]
+fn reconnect_backoff() {
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    paused(move || async move {
+        let policy = RetryPolicy::new()
+            .max_attempts(Some(10))
+            .initial_backoff(Duration::from_secs(1))
+            .max_backoff(Duration::from_secs(60))
+            .jitter(0.0);
+        let wall = Instant::now();
+        let start = now();
+        let Err(e) = connect_retry(addr, &policy).await else {
+            panic!("connected to a closed port");
+        };
+        assert_eq!(e.kind(), ErrorKind::ConnectionRefused);
+        // 1 + 2 + 4 + 8 + 16 + 32 + 60 * 3 seconds between the 10 attempts.
+        assert!(now() - start >= Duration::from_secs(243));
+        assert!(wall.elapsed() < Duration::from_secs(10));
+    })
+}
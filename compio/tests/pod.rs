@@ -0,0 +1,92 @@
+use std::io;
+
+use bytemuck::{Pod, Zeroable};
+use compio::{
+    buf::{IoBuf, PodBuf, PodVec},
+    fs::File,
+};
+use tempfile::NamedTempFile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+#[repr(C, packed)]
+struct Header {
+    magic: [u8; 4],
+    version: u16,
+    flags: u8,
+    len: u64,
+}
+
+const HEADER: Header = Header {
+    magic: *b"PODB",
+    version: 3,
+    flags: 0x80,
+    len: 0x0102_0304_0506_0708,
+};
+
+fn temp_file(data: &[u8]) -> NamedTempFile {
+    let file = NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), data).unwrap();
+    file
+}
+
+#[test]
+fn header_round_trip() {
+    let temp = NamedTempFile::new().unwrap();
+    compio::task::block_on(async {
+        let file = File::create(temp.path()).unwrap();
+        let data = bytemuck::bytes_of(&HEADER).to_vec();
+        file.write_all_at(data, 0).await.0.unwrap();
+        file.sync_all().await.unwrap();
+
+        let file = File::open(temp.path()).unwrap();
+        let (res, buffer) = file.read_exact_at(PodBuf::<Header>::new(), 0).await;
+        assert_eq!(res.unwrap(), std::mem::size_of::<Header>());
+        assert_eq!(buffer.into_inner().unwrap(), HEADER);
+    })
+}
+
+#[test]
+fn partial_read() {
+    let temp = temp_file(&bytemuck::bytes_of(&HEADER)[..5]);
+    compio::task::block_on(async {
+        let file = File::open(temp.path()).unwrap();
+        let (res, buffer) = file.read_exact_at(PodBuf::<Header>::new(), 0).await;
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(
+            buffer.into_inner().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    })
+}
+
+#[test]
+fn read_vec() {
+    let values = [1u32, 2, 3, 0xdeadbeef];
+    let temp = temp_file(bytemuck::cast_slice(&values));
+    compio::task::block_on(async {
+        let file = File::open(temp.path()).unwrap();
+        let (res, buffer) = file.read_exact_at(PodVec::<u32>::new(4), 0).await;
+        res.unwrap();
+        assert_eq!(buffer.into_inner().unwrap(), values);
+
+        let (res, buffer) = file
+            .read_exact_at(PodVec::<u32>::with_align(4, 512), 0)
+            .await;
+        res.unwrap();
+        assert_eq!(buffer.into_inner().unwrap(), values);
+    })
+}
+
+#[test]
+fn alignment() {
+    let buffer = PodBuf::<u64>::new();
+    assert_eq!(
+        buffer.as_buf_ptr() as usize % std::mem::align_of::<u64>(),
+        0
+    );
+    assert_eq!(buffer.buf_capacity(), 8);
+
+    let buffer = PodBuf::<Header>::with_align(4096);
+    assert_eq!(buffer.as_buf_ptr() as usize % 4096, 0);
+    assert_eq!(buffer.buf_capacity(), std::mem::size_of::<Header>());
+}
@@ -0,0 +1,101 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    rc::Rc,
+};
+
+use compio::{driver::ProactorBuilder, fs::File};
+
+// Count the allocations of the current thread only, because the runtimes run
+// on their own threads.
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocations() -> u64 {
+    ALLOCATIONS.with(Cell::get)
+}
+
+// Each yield is a tick without completions.
+#[test]
+fn idle_ticks() {
+    std::thread::spawn(|| {
+        compio::task::block_on(async {
+            for _ in 0..10 {
+                compio::task::yield_now().await;
+            }
+            let before = allocations();
+            for _ in 0..1000 {
+                compio::task::yield_now().await;
+            }
+            assert_eq!(allocations() - before, 0);
+        })
+    })
+    .join()
+    .unwrap()
+}
+
+// More completions in a tick than the default capacity.
+const TASKS: usize = 4096;
+const ROUNDS: usize = 20;
+
+#[test]
+fn wide_ticks() {
+    std::thread::spawn(|| {
+        // Cache the allocations of all the ops in flight.
+        let builder = ProactorBuilder::new()
+            .capacity(TASKS as _)
+            .op_pool_capacity(TASKS);
+        compio::task::init_with(&builder).unwrap();
+        compio::task::block_on(async {
+            let file = Rc::new(File::open("Cargo.toml").unwrap());
+            let warmed = Rc::new(Cell::new(0));
+            let started = Rc::new(Cell::new(false));
+            let tasks = (0..TASKS)
+                .map(|_| {
+                    let file = file.clone();
+                    let warmed = warmed.clone();
+                    let started = started.clone();
+                    compio::task::spawn(async move {
+                        let mut buffer = Vec::with_capacity(16);
+                        buffer = file.read_at(buffer, 0).await.1;
+                        warmed.set(warmed.get() + 1);
+                        while !started.get() {
+                            compio::task::yield_now().await;
+                        }
+                        for _ in 0..ROUNDS {
+                            buffer = file.read_at(buffer, 0).await.1;
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            while warmed.get() < TASKS {
+                compio::task::yield_now().await;
+            }
+            let before = allocations();
+            started.set(true);
+            for task in tasks {
+                task.await;
+            }
+            assert_eq!(allocations() - before, 0);
+        })
+    })
+    .join()
+    .unwrap()
+}
@@ -0,0 +1,197 @@
+use std::{io::ErrorKind, net::Ipv4Addr, time::Duration};
+
+use compio::{
+    driver::{PollingMode, Proactor, ProactorBuilder},
+    net::{TcpListener, TcpStream, UdpSocket},
+    task::{messages, RuntimeHandle},
+};
+use futures_util::StreamExt;
+
+const MODES: [PollingMode; 5] = [
+    PollingMode::Auto,
+    PollingMode::Epoll,
+    PollingMode::Kqueue,
+    PollingMode::EventPorts,
+    PollingMode::Poll,
+];
+
+// The mode of `Auto`, or `None` if the driver is completion-based.
+fn native() -> Option<PollingMode> {
+    Proactor::new().unwrap().polling_mode()
+}
+
+fn is_available(mode: PollingMode) -> bool {
+    match native() {
+        Some(native) => [PollingMode::Auto, PollingMode::Poll, native].contains(&mode),
+        None => mode == PollingMode::Auto,
+    }
+}
+
+async fn echo_tcp() {
+    const CLIENTS: usize = 32;
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = compio::task::spawn(async move {
+        for _ in 0..CLIENTS {
+            let (stream, _) = listener.accept().await.unwrap();
+            compio::task::spawn(async move {
+                loop {
+                    let (res, buffer) = stream.recv(Vec::with_capacity(64)).await;
+                    if res.unwrap() == 0 {
+                        break;
+                    }
+                    stream.send_all(buffer).await.0.unwrap();
+                }
+            })
+            .detach();
+        }
+    });
+    let addr = &addr;
+    let clients = (0..CLIENTS).map(|i| async move {
+        let stream = TcpStream::connect(addr).await.unwrap();
+        for round in 0..3 {
+            let message = format!("client {i} round {round}").into_bytes();
+            stream.send_all(message.clone()).await.0.unwrap();
+            let (res, back) = stream.recv_exact(Vec::with_capacity(message.len())).await;
+            res.unwrap();
+            assert_eq!(back, message);
+        }
+    });
+    futures_util::future::join_all(clients).await;
+    server.await;
+}
+
+async fn echo_udp() {
+    let server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = server.local_addr().unwrap();
+    let echo = async {
+        let (res, buffer) = server.recv_from(Vec::with_capacity(16)).await;
+        let (_, from) = res.unwrap();
+        server.send_to(buffer, from).await.0.unwrap();
+    };
+    let ping = async {
+        client.send_to("ping", addr).await.0.unwrap();
+        let (res, buffer) = client.recv(Vec::with_capacity(16)).await;
+        res.unwrap();
+        assert_eq!(buffer, b"ping");
+    };
+    futures_util::join!(echo, ping);
+}
+
+// The driver is woken from another thread, and the cancelled ops are removed
+// from the poller.
+async fn wake_and_cancel() {
+    let handle = RuntimeHandle::current().unwrap();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        compio::task::block_on(handle.send_msg(42)).unwrap();
+    });
+    assert_eq!(messages().next().await, Some(42));
+
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    {
+        let recv = std::pin::pin!(socket.recv(Vec::with_capacity(4)));
+        assert!(futures_util::poll!(recv).is_pending());
+    }
+    // The cancelled op may still take a datagram with io-uring.
+    for _ in 0..2 {
+        socket
+            .send_to("done", socket.local_addr().unwrap())
+            .await
+            .0
+            .unwrap();
+    }
+    let (res, buffer) = socket.recv(Vec::with_capacity(4)).await;
+    res.unwrap();
+    assert_eq!(buffer, b"done");
+}
+
+#[test]
+fn build() {
+    let native = native();
+    for mode in MODES {
+        let res = ProactorBuilder::new().polling_mode(mode).build();
+        if is_available(mode) {
+            let expected = match mode {
+                PollingMode::Auto => native,
+                _ => Some(mode),
+            };
+            assert_eq!(res.unwrap().polling_mode(), expected, "{mode:?}");
+        } else {
+            let e = res.err().unwrap();
+            assert_eq!(e.kind(), ErrorKind::Unsupported, "{mode:?}");
+        }
+    }
+}
+
+#[test]
+fn echo() {
+    for mode in MODES.into_iter().filter(|mode| is_available(*mode)) {
+        std::thread::spawn(move || {
+            compio::task::init_with(&ProactorBuilder::new().polling_mode(mode)).unwrap();
+            compio::task::block_on(async {
+                echo_tcp().await;
+                echo_udp().await;
+                wake_and_cancel().await;
+            })
+        })
+        .join()
+        .unwrap();
+    }
+}
+
+// The notifications more than a pipe could hold, posted while the driver
+// isn't waiting, neither block the senders nor get lost.
+#[test]
+fn many_messages() {
+    const COUNT: u64 = 20000;
+
+    for mode in MODES.into_iter().filter(|mode| is_available(*mode)) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            compio::task::init_with(&ProactorBuilder::new().polling_mode(mode)).unwrap();
+            tx.send(RuntimeHandle::current().unwrap()).unwrap();
+            done_rx.recv().unwrap();
+            compio::task::block_on(async {
+                let mut messages = messages();
+                for i in 0..COUNT {
+                    assert_eq!(messages.next().await, Some(i), "{mode:?}");
+                }
+            })
+        });
+
+        let handle = rx.recv().unwrap();
+        compio::task::block_on(async {
+            for i in 0..COUNT {
+                handle.send_msg(i).await.unwrap();
+            }
+        });
+        done_tx.send(()).unwrap();
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+fn max_poll_fds() {
+    for mode in MODES.into_iter().filter(|mode| is_available(*mode)) {
+        std::thread::spawn(move || {
+            compio::task::init_with(&ProactorBuilder::new().polling_mode(mode)).unwrap();
+            compio::task::block_on(async {
+                assert_eq!(compio::task::max_poll_fds(), 0);
+                echo_tcp().await;
+                // The native `poll` of `polling` is not counted.
+                let max = compio::task::max_poll_fds();
+                if mode == PollingMode::Poll && native() != Some(PollingMode::Poll) {
+                    assert!(max > 0);
+                } else {
+                    assert_eq!(max, 0);
+                }
+            })
+        })
+        .join()
+        .unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+use std::{cell::RefCell, net::Ipv4Addr};
+
+use compio::{
+    buf::{
+        pool::{self, PooledBuf},
+        IoBuf,
+    },
+    net::{TcpListener, TcpStream, UdpSocket},
+};
+
+#[test]
+fn reuse() {
+    let buf = pool::acquire(100);
+    assert_eq!(buf.capacity(), 128);
+    assert!(buf.is_empty());
+    let ptr = buf.as_buf_ptr();
+    drop(buf);
+    assert_eq!(pool::stats().pooled_bytes, 128);
+
+    // The same size class.
+    let buf = pool::acquire(65);
+    assert_eq!(buf.as_buf_ptr(), ptr);
+    // Another size class.
+    let other = pool::acquire(1);
+    assert_eq!(other.capacity(), 64);
+
+    let stats = pool::stats();
+    assert_eq!(stats.hits, 1);
+    assert_eq!(stats.misses, 2);
+    assert_eq!(stats.pooled_bytes, 0);
+    assert_eq!(stats.hit_rate(), 1.0 / 3.0);
+
+    // The storage is taken out of the pool.
+    let vec = buf.into_vec();
+    assert_eq!(vec.capacity(), 128);
+    drop(vec);
+    assert_eq!(pool::stats().pooled_bytes, 0);
+}
+
+#[test]
+fn limit() {
+    pool::set_max_pooled_bytes(128);
+    let bufs = (0..3).map(|_| pool::acquire(64)).collect::<Vec<_>>();
+    drop(bufs);
+    let stats = pool::stats();
+    assert_eq!(stats.pooled_bytes, 128);
+    assert_eq!(stats.discards, 1);
+
+    // The pooled buffers are freed.
+    pool::set_max_pooled_bytes(64);
+    assert_eq!(pool::stats().pooled_bytes, 64);
+    pool::set_max_pooled_bytes(0);
+    assert_eq!(pool::stats().pooled_bytes, 0);
+}
+
+#[test]
+fn ops() {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, (server, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+
+        // Held across the awaits, and returned on another tick.
+        let echo = compio::task::spawn(async move {
+            let (res, mut buf) = server.recv(pool::acquire(5)).await;
+            assert_eq!(res.unwrap(), 5);
+            compio::task::spawn(async {}).await;
+            buf.copy_from_slice(b"world");
+            server.send_all(buf).await.0.unwrap();
+        });
+
+        let mut buf = pool::acquire(64);
+        buf.extend_from_slice(b"hello");
+        let (res, mut buf) = client.send_all(buf).await;
+        res.unwrap();
+        buf.clear();
+        echo.await;
+        let (res, mut buf) = client.recv(buf).await;
+        assert_eq!(res.unwrap(), 5);
+        assert_eq!(&*buf, b"world");
+        buf.clear();
+
+        // Vectored.
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        socket.connect(socket.local_addr().unwrap()).unwrap();
+        socket.send("ping").await.0.unwrap();
+        let (res, bufs) = socket.recv_vectored(vec![pool::acquire(2), buf]).await;
+        assert_eq!(res.unwrap(), 4);
+        assert_eq!(&*bufs[0], b"ping");
+        assert!(bufs[1].is_empty());
+    });
+    assert!(pool::stats().hits > 0);
+}
+
+thread_local! {
+    static HOLDER: RefCell<Option<PooledBuf>> = const { RefCell::new(None) };
+}
+
+#[test]
+fn drop_after_pool() {
+    std::thread::spawn(|| {
+        // Initialized before the pool, and destroyed after it.
+        HOLDER.with(|holder| *holder.borrow_mut() = Some(pool::acquire(64)));
+    })
+    .join()
+    .unwrap();
+}
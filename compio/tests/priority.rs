@@ -0,0 +1,189 @@
+use std::{
+    cell::{Cell, RefCell},
+    future::poll_fn,
+    net::Ipv4Addr,
+    rc::Rc,
+    task::Poll,
+    time::{Duration, Instant},
+};
+
+use compio::{
+    net::{TcpListener, TcpStream},
+    task::{spawn_with_priority, Priority},
+};
+
+// Wake itself, so that the task is queued again at once, unlike `yield_now`,
+// which waits for the driver.
+async fn self_wake() {
+    let mut woken = false;
+    poll_fn(|cx| {
+        if woken {
+            Poll::Ready(())
+        } else {
+            woken = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+#[test]
+fn ratio() {
+    compio::task::set_priority_ratio(4);
+    let log = Rc::new(RefCell::new(String::new()));
+    compio::task::block_on(async {
+        let task = |c: char, polls: usize, priority: Priority| {
+            let log = log.clone();
+            spawn_with_priority(
+                async move {
+                    for _ in 0..polls {
+                        log.borrow_mut().push(c);
+                        self_wake().await;
+                    }
+                },
+                priority,
+            )
+        };
+        let high = [task('H', 20, Priority::High), task('H', 20, Priority::High)];
+        let low = task('L', 5, Priority::Low);
+        for task in high {
+            task.await;
+        }
+        low.await;
+    });
+    compio::task::set_priority_ratio(16);
+
+    let log = log.borrow();
+    assert!(log.starts_with('H'), "{log}");
+    let last_low = log.rfind('L').unwrap();
+    // Interleaved with the high priority tasks.
+    assert!(last_low < log.rfind('H').unwrap(), "{log}");
+    for run in log[..last_low].split('L') {
+        assert!(run.len() <= 4, "{log}");
+    }
+}
+
+#[test]
+fn low_not_starved() {
+    compio::task::block_on(async {
+        let done = Rc::new(Cell::new(false));
+        let flood = (0..8)
+            .map(|_| {
+                let done = done.clone();
+                spawn_with_priority(
+                    async move {
+                        while !done.get() {
+                            self_wake().await;
+                        }
+                    },
+                    Priority::High,
+                )
+            })
+            .collect::<Vec<_>>();
+        spawn_with_priority(
+            async {
+                for _ in 0..100 {
+                    self_wake().await;
+                }
+            },
+            Priority::Low,
+        )
+        .await;
+        done.set(true);
+        for task in flood {
+            task.await;
+        }
+    })
+}
+
+async fn round_trips(client: &TcpStream, rounds: usize) -> Duration {
+    let mut latencies = Vec::with_capacity(rounds);
+    let mut buf = Vec::with_capacity(8);
+    for _ in 0..rounds {
+        buf.clear();
+        buf.extend_from_slice(b"ping");
+        let start = Instant::now();
+        let (res, b) = client.send_all(buf).await;
+        res.unwrap();
+        let (res, b) = client.recv_exact(b).await;
+        res.unwrap();
+        latencies.push(start.elapsed());
+        buf = b;
+    }
+    latencies.sort();
+    latencies[rounds * 99 / 100]
+}
+
+#[test]
+fn high_priority_echo() {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, server) = futures_util::join!(TcpStream::connect(&addr), listener.accept());
+        let (client, server) = (client.unwrap(), server.unwrap().0);
+        spawn_with_priority(
+            async move {
+                let mut buf = Vec::with_capacity(8);
+                loop {
+                    buf.clear();
+                    let (res, b) = server.recv(buf).await;
+                    match res {
+                        Ok(0) | Err(_) => break,
+                        Ok(_) => {}
+                    }
+                    let (res, b) = server.send_all(b).await;
+                    if res.is_err() {
+                        break;
+                    }
+                    buf = b;
+                }
+            },
+            Priority::High,
+        )
+        .detach();
+        let client = Rc::new(client);
+        let measure = |rounds| {
+            let client = client.clone();
+            spawn_with_priority(
+                async move { round_trips(&client, rounds).await },
+                Priority::High,
+            )
+        };
+        let quiet = measure(200).await;
+
+        // A flood of low priority tasks burning CPU on every poll.
+        let done = Rc::new(Cell::new(false));
+        let polls = Rc::new(Cell::new(0u64));
+        let flood = (0..64)
+            .map(|_| {
+                let done = done.clone();
+                let polls = polls.clone();
+                spawn_with_priority(
+                    async move {
+                        while !done.get() {
+                            let start = Instant::now();
+                            while start.elapsed() < Duration::from_micros(10) {
+                                std::hint::spin_loop();
+                            }
+                            polls.set(polls.get() + 1);
+                            self_wake().await;
+                        }
+                    },
+                    Priority::Low,
+                )
+            })
+            .collect::<Vec<_>>();
+        let flooded = measure(200).await;
+        done.set(true);
+        for task in flood {
+            task.await;
+        }
+
+        assert!(polls.get() > 64, "low priority tasks starved");
+        assert!(
+            flooded < quiet + Duration::from_millis(5),
+            "p99 latency: {quiet:?} quiet, {flooded:?} flooded"
+        );
+    })
+}
@@ -0,0 +1,147 @@
+use std::process::Command;
+#[cfg(feature = "time")]
+use std::time::Duration;
+
+use compio::process::Child;
+
+#[cfg(unix)]
+fn sleep(secs: u32) -> Child {
+    Child::spawn(Command::new("sleep").arg(secs.to_string())).unwrap()
+}
+
+#[cfg(windows)]
+fn sleep(secs: u32) -> Child {
+    let count = (secs + 1).to_string();
+    Child::spawn(Command::new("ping").args(["-n", &count, "127.0.0.1"])).unwrap()
+}
+
+#[cfg(unix)]
+fn exit(code: i32) -> Child {
+    Child::spawn(Command::new("sh").args(["-c", &format!("exit {code}")])).unwrap()
+}
+
+#[cfg(windows)]
+fn exit(code: i32) -> Child {
+    Child::spawn(Command::new("cmd").args(["/c", &format!("exit {code}")])).unwrap()
+}
+
+// Whether the process exists, including a zombie.
+#[cfg(unix)]
+fn exists(pid: u32) -> bool {
+    unsafe { libc::kill(pid as _, 0) == 0 }
+}
+
+#[test]
+fn wait() {
+    compio::task::block_on(async {
+        let child = exit(3);
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.code(), Some(3));
+        // The status is cached.
+        assert_eq!(child.try_wait().unwrap(), Some(status));
+        assert_eq!(child.wait().await.unwrap(), status);
+    })
+}
+
+#[test]
+fn kill() {
+    compio::task::block_on(async {
+        let child = sleep(10);
+        assert!(child.try_wait().unwrap().is_none());
+        child.kill().unwrap();
+        let status = child.wait().await.unwrap();
+        assert!(!status.success());
+        #[cfg(unix)]
+        assert_eq!(
+            std::os::unix::process::ExitStatusExt::signal(&status),
+            Some(libc::SIGKILL)
+        );
+        // Killing an exited process does nothing.
+        child.kill().unwrap();
+    })
+}
+
+#[test]
+fn concurrent_waiters() {
+    compio::task::block_on(async {
+        let child = exit(7);
+        let statuses = futures_util::future::join_all((0..4).map(|_| child.wait())).await;
+        for status in statuses {
+            assert_eq!(status.unwrap().code(), Some(7));
+        }
+        // Collected exactly once, and no zombie is left.
+        #[cfg(unix)]
+        assert!(!exists(child.id()));
+    })
+}
+
+#[test]
+#[cfg(feature = "time")]
+fn wait_with_timeout() {
+    compio::task::block_on(async {
+        let child = sleep(10);
+        let start = std::time::Instant::now();
+        let status = child
+            .wait_with_timeout(Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert!(status.is_none());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // Escalate: terminate first, and then kill.
+        #[cfg(unix)]
+        {
+            child.signal(libc::SIGTERM).unwrap();
+            let status = child
+                .wait_with_timeout(Duration::from_secs(5))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                std::os::unix::process::ExitStatusExt::signal(&status),
+                Some(libc::SIGTERM)
+            );
+        }
+        #[cfg(windows)]
+        {
+            child.kill().unwrap();
+            let status = child.wait_with_timeout(Duration::from_secs(5)).await;
+            assert!(status.unwrap().is_some());
+        }
+
+        // Exited before the timeout.
+        let child = exit(0);
+        let status = child.wait_with_timeout(Duration::from_secs(5)).await;
+        assert!(status.unwrap().unwrap().success());
+    })
+}
+
+#[test]
+#[cfg(unix)]
+fn reap_on_drop() {
+    let wait_gone = |pid| {
+        let start = std::time::Instant::now();
+        while exists(pid) {
+            assert!(start.elapsed() < std::time::Duration::from_secs(5));
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    };
+
+    // Reaped by the background thread.
+    let child = Child::spawn(Command::new("sleep").arg("0.1")).unwrap();
+    let pid = child.id();
+    drop(child);
+    wait_gone(pid);
+
+    // Left a zombie, until it is reaped by hand.
+    let mut child = exit(0);
+    child.reap_on_drop(false);
+    let pid = child.id();
+    drop(child);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(exists(pid));
+    let res = unsafe { libc::waitpid(pid as _, std::ptr::null_mut(), 0) };
+    assert_eq!(res, pid as _);
+    wait_gone(pid);
+}
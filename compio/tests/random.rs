@@ -0,0 +1,39 @@
+use compio::{buf::IntoInner, fs::random, op::GetRandom};
+
+#[test]
+fn fill_fully() {
+    compio::task::block_on(async {
+        let (res, first) = random(Vec::with_capacity(4096)).await;
+        assert_eq!(res.unwrap(), 4096);
+        assert_eq!(first.len(), 4096);
+
+        let (res, second) = random(Vec::with_capacity(4096)).await;
+        assert_eq!(res.unwrap(), 4096);
+        assert_ne!(first, second);
+
+        // Only the uninitialized part is filled.
+        let mut buffer = Vec::with_capacity(64);
+        buffer.extend_from_slice(b"prefix");
+        let (res, buffer) = random(buffer).await;
+        assert_eq!(res.unwrap(), 58);
+        assert_eq!(buffer.len(), 64);
+        assert!(buffer.starts_with(b"prefix"));
+
+        let (res, buffer) = random(Vec::<u8>::new()).await;
+        assert_eq!(res.unwrap(), 0);
+        assert!(buffer.is_empty());
+    })
+}
+
+#[test]
+fn submit_op() {
+    compio::task::block_on(async {
+        let op = GetRandom::new(Vec::with_capacity(16)).unwrap();
+        let (res, op) = compio::task::submit(op).await;
+        let n = res.unwrap();
+        assert!(n > 0 && n <= 16);
+        let mut buffer = op.into_inner().into_inner();
+        unsafe { buffer.set_len(n) };
+        assert!(buffer.iter().any(|b| *b != 0));
+    })
+}
@@ -0,0 +1,129 @@
+use std::{
+    future::Future,
+    io,
+    path::Path,
+    pin::pin,
+    task::{Context, Poll},
+};
+
+use compio::fs::{read, read_to_string, write, write_atomic};
+
+fn tempdir() -> tempfile::TempDir {
+    tempfile::Builder::new()
+        .prefix("compio-read-write-tests")
+        .tempdir()
+        .unwrap()
+}
+
+fn entries(dir: &Path) -> Vec<String> {
+    let mut entries = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    entries.sort();
+    entries
+}
+
+#[test]
+fn read_write() {
+    compio::task::block_on(async {
+        let dir = tempdir();
+        let path = dir.path().join("file");
+
+        write(&path, "hello world").await.unwrap();
+        assert_eq!(read(&path).await.unwrap(), b"hello world");
+        write(&path, "hello").await.unwrap();
+        assert_eq!(read_to_string(&path).await.unwrap(), "hello");
+
+        write(&path, vec![0xff, 0xfe]).await.unwrap();
+        let err = read_to_string(&path).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = read(dir.path().join("nonexistent")).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    })
+}
+
+#[test]
+fn atomic() {
+    compio::task::block_on(async {
+        let dir = tempdir();
+        let path = dir.path().join("config");
+
+        write_atomic(&path, "old").await.unwrap();
+        assert_eq!(read_to_string(&path).await.unwrap(), "old");
+        write_atomic(&path, "new contents").await.unwrap();
+        assert_eq!(read_to_string(&path).await.unwrap(), "new contents");
+        assert_eq!(entries(dir.path()), ["config"]);
+
+        let err = write_atomic(dir.path().join("nonexistent/config"), "new")
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    })
+}
+
+#[test]
+#[cfg(unix)]
+fn atomic_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    compio::task::block_on(async {
+        let dir = tempdir();
+        let path = dir.path().join("script");
+        std::fs::write(&path, "old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o751)).unwrap();
+
+        write_atomic(&path, "new").await.unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o751);
+        assert_eq!(read_to_string(&path).await.unwrap(), "new");
+    })
+}
+
+// Drop the future after it waits for the `n`-th time, i.e., between the
+// steps. Returns whether it completes before that.
+async fn run_until_pending(future: impl Future<Output = io::Result<()>>, n: usize) -> bool {
+    let mut future = pin!(future);
+    let mut pending = 0;
+    std::future::poll_fn(|cx: &mut Context| match future.as_mut().poll(cx) {
+        Poll::Ready(res) => {
+            res.unwrap();
+            Poll::Ready(true)
+        }
+        Poll::Pending if pending == n => Poll::Ready(false),
+        Poll::Pending => {
+            pending += 1;
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+#[test]
+fn atomic_interrupted() {
+    const OLD: &[u8] = b"old contents";
+
+    compio::task::block_on(async {
+        let dir = tempdir();
+        let path = dir.path().join("config");
+        let new = vec![b'x'; 1024 * 1024];
+        std::fs::write(&path, OLD).unwrap();
+
+        for n in 0.. {
+            let completed = run_until_pending(write_atomic(&path, new.clone()), n).await;
+            // The target is never partially written.
+            let contents = std::fs::read(&path).unwrap();
+            assert!(contents == OLD || contents == new, "{}", contents.len());
+            if completed {
+                assert_eq!(contents, new);
+                break;
+            }
+            std::fs::write(&path, OLD).unwrap();
+        }
+
+        // The rename of a dropped future may be still running.
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert_eq!(entries(dir.path()), ["config"]);
+    })
+}
@@ -0,0 +1,166 @@
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use compio::net::{connect_retry, ReconnectingStream, RetryPolicy};
+
+// Serve the connections in order by `f` with their indices, and return the
+// accepted instants.
+fn serve(
+    count: usize,
+    mut f: impl FnMut(usize, std::net::TcpStream) + Send + 'static,
+) -> (SocketAddr, JoinHandle<Vec<Instant>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = std::thread::spawn(move || {
+        let mut accepted = vec![];
+        for i in 0..count {
+            let (stream, _) = listener.accept().unwrap();
+            accepted.push(Instant::now());
+            f(i, stream);
+        }
+        accepted
+    });
+    (addr, handle)
+}
+
+fn policy() -> RetryPolicy {
+    RetryPolicy::new()
+        .initial_backoff(Duration::from_millis(5))
+        .max_backoff(Duration::from_millis(20))
+        .jitter(0.0)
+        .attempt_timeout(Some(Duration::from_secs(5)))
+}
+
+#[test]
+fn backoff() {
+    let policy = policy();
+    let backoffs = (0..5).map(|i| policy.backoff(i)).collect::<Vec<_>>();
+    assert_eq!(backoffs, [5, 10, 20, 20, 20].map(Duration::from_millis));
+    // Saturated instead of overflowing.
+    assert_eq!(policy.backoff(u32::MAX), Duration::from_millis(20));
+
+    let policy = policy.jitter(0.5);
+    for i in 0..16 {
+        let backoff = policy.backoff(4);
+        assert!(backoff >= Duration::from_millis(10), "{i}: {backoff:?}");
+        assert!(backoff <= Duration::from_millis(20), "{i}: {backoff:?}");
+    }
+}
+
+#[test]
+fn flaky_server() {
+    // Accept and close at once for 3 times, and then reply the greeting.
+    let (addr, server) = serve(4, |i, mut stream| {
+        if i == 3 {
+            let mut greeting = [0; 5];
+            stream.read_exact(&mut greeting).unwrap();
+            assert_eq!(&greeting, b"hello");
+            stream.write_all(b"world").unwrap();
+        }
+    });
+    compio::task::block_on(async {
+        let greetings = Arc::new(Mutex::new(0));
+        let mut stream = ReconnectingStream::new(addr, policy())
+            .unwrap()
+            .on_reconnect({
+                let greetings = greetings.clone();
+                move |stream| {
+                    *greetings.lock().unwrap() += 1;
+                    Box::pin(async move {
+                        stream.send_all("hello").await.0?;
+                        Ok(())
+                    })
+                }
+            });
+        let (res, buf) = stream.recv(Vec::with_capacity(5)).await;
+        assert_eq!(res.unwrap(), 5);
+        assert_eq!(buf, b"world");
+        assert_eq!(stream.connects(), 4);
+        assert_eq!(*greetings.lock().unwrap(), 4);
+    });
+
+    // Reconnected with the backoff.
+    let accepted = server.join().unwrap();
+    for (i, backoff) in [5, 10, 20].into_iter().enumerate() {
+        let elapsed = accepted[i + 1] - accepted[i];
+        assert!(elapsed >= Duration::from_millis(backoff), "{i}: {elapsed:?}");
+    }
+}
+
+#[test]
+fn surface_lost_connection() {
+    let (addr, server) = serve(2, |i, mut stream| {
+        if i == 1 {
+            stream.write_all(b"world").unwrap();
+        }
+    });
+    compio::task::block_on(async {
+        let mut stream = ReconnectingStream::new(addr, policy().retry_io(false)).unwrap();
+        // Returned to the caller, and the next IO reconnects.
+        let (res, _) = stream.recv(Vec::with_capacity(5)).await;
+        let kind = res.unwrap_err().kind();
+        assert!(
+            matches!(kind, ErrorKind::UnexpectedEof | ErrorKind::ConnectionReset),
+            "{kind:?}"
+        );
+        assert!(stream.get_ref().is_none());
+        let (res, buf) = stream.recv_exact(Vec::with_capacity(5)).await;
+        res.unwrap();
+        assert_eq!(buf, b"world");
+        assert_eq!(stream.connects(), 2);
+    });
+    server.join().unwrap();
+}
+
+#[test]
+fn exhausted() {
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    compio::task::block_on(async {
+        let policy = policy().max_attempts(Some(3));
+        let start = Instant::now();
+        let Err(e) = connect_retry(addr, &policy).await else {
+            panic!("connected to a closed port");
+        };
+        assert_eq!(e.kind(), ErrorKind::ConnectionRefused);
+        // Backoff twice between the 3 attempts.
+        assert!(start.elapsed() >= Duration::from_millis(15));
+    })
+}
+
+#[test]
+fn attempt_timeout() {
+    // The first connection never replies the greeting, and the client gives
+    // up on it.
+    let (addr, server) = serve(2, |i, mut stream| {
+        if i == 0 {
+            let mut buf = [0; 1];
+            assert_eq!(stream.read(&mut buf).unwrap(), 0);
+        } else {
+            stream.write_all(b"hi").unwrap();
+        }
+    });
+    compio::task::block_on(async {
+        let policy = policy().attempt_timeout(Some(Duration::from_millis(50)));
+        let mut stream = ReconnectingStream::new(addr, policy)
+            .unwrap()
+            .on_reconnect(|stream| {
+                Box::pin(async move {
+                    let (res, buf) = stream.recv_exact(Vec::with_capacity(2)).await;
+                    res?;
+                    assert_eq!(buf, b"hi");
+                    Ok(())
+                })
+            });
+        stream.connect().await.unwrap();
+        assert_eq!(stream.connects(), 1);
+    });
+    server.join().unwrap();
+}
@@ -0,0 +1,114 @@
+#![cfg(unix)]
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::Ipv4Addr,
+    os::{fd::AsRawFd, unix::net::UnixStream},
+};
+
+use compio::{
+    driver::Interest,
+    net::{TcpListener, TcpStream},
+    task::Registration,
+};
+use futures_util::FutureExt;
+
+fn pair() -> (UnixStream, UnixStream) {
+    let (tx, rx) = UnixStream::pair().unwrap();
+    tx.set_nonblocking(true).unwrap();
+    rx.set_nonblocking(true).unwrap();
+    (tx, rx)
+}
+
+// Read as a readiness-based library does.
+async fn read_ready(registration: &Registration, mut stream: &UnixStream) -> Vec<u8> {
+    let mut buffer = [0; 64];
+    loop {
+        let mut guard = registration.ready(Interest::Readable).await.unwrap();
+        match guard.try_io(|| stream.read(&mut buffer)) {
+            Err(e) if e.kind() == ErrorKind::WouldBlock => continue,
+            res => return buffer[..res.unwrap()].to_vec(),
+        }
+    }
+}
+
+#[test]
+fn coexist() {
+    compio::task::block_on(async {
+        let (mut tx, rx) = pair();
+        let registration = Registration::new(rx.as_raw_fd(), Interest::Readable);
+        let reader = read_ready(&registration, &rx);
+
+        // The runtime works on its own sockets, while the foreign one waits.
+        let tcp = async {
+            let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            let addr = listener.local_addr().unwrap();
+            let (client, (server, _)) =
+                futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+            client.send_all("ping").await.0.unwrap();
+            let (res, buf) = server.recv_exact(Vec::with_capacity(4)).await;
+            res.unwrap();
+            assert_eq!(buf, b"ping");
+            // Wake the foreign reader.
+            tx.write_all(b"hello").unwrap();
+        };
+        let (read, ()) = futures_util::join!(reader, tcp);
+        assert_eq!(read, b"hello");
+    })
+}
+
+#[test]
+fn independent_registrations() {
+    compio::task::block_on(async {
+        let (mut tx, rx) = pair();
+        let readable = Registration::new(rx.as_raw_fd(), Interest::Readable);
+        let other = Registration::new(rx.as_raw_fd(), Interest::Readable);
+        let writable = Registration::new(rx.as_raw_fd(), Interest::Writable);
+
+        // Not registered for writing.
+        let res = readable.ready(Interest::Writable).await;
+        assert_eq!(res.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        // Writable at once, while the reads are waiting on the same fd. Both
+        // reads are woken, though one consumes the data.
+        let (guard, read, other_guard, ()) = futures_util::join!(
+            writable.ready(Interest::Writable),
+            read_ready(&readable, &rx),
+            other.ready(Interest::Readable),
+            async { tx.write_all(b"hello").unwrap() },
+        );
+        guard.unwrap();
+        assert_eq!(read, b"hello");
+        other_guard.unwrap();
+    })
+}
+
+#[test]
+fn clear_ready() {
+    compio::task::block_on(async {
+        let (mut tx, rx) = pair();
+        let registration = Registration::new(rx.as_raw_fd(), Interest::Readable);
+        tx.write_all(b"a").unwrap();
+        registration.ready(Interest::Readable).await.unwrap();
+        // Cached.
+        let mut guard = registration
+            .ready(Interest::Readable)
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        let mut buffer = [0; 4];
+        assert_eq!(guard.try_io(|| (&rx).read(&mut buffer)).unwrap(), 1);
+        let res = guard.try_io(|| (&rx).read(&mut buffer));
+        assert_eq!(res.unwrap_err().kind(), ErrorKind::WouldBlock);
+
+        // Cleared, and it waits for the driver again.
+        assert!(registration
+            .ready(Interest::Readable)
+            .now_or_never()
+            .is_none());
+        tx.write_all(b"b").unwrap();
+        let mut guard = registration.ready(Interest::Readable).await.unwrap();
+        assert_eq!(guard.try_io(|| (&rx).read(&mut buffer)).unwrap(), 1);
+        assert_eq!(buffer[0], b'b');
+    })
+}
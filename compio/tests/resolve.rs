@@ -0,0 +1,77 @@
+use std::{io, net::IpAddr};
+
+use compio::net::{lookup_host, resolve, Hints};
+use futures_util::{FutureExt, StreamExt};
+use socket2::{Domain, Protocol, Type};
+
+#[test]
+fn lookup() {
+    compio::task::block_on(async {
+        let addrs = lookup_host("127.0.0.1", 8080).await.unwrap();
+        let addrs = addrs.collect::<Vec<_>>().await;
+        assert_eq!(addrs, ["127.0.0.1:8080".parse().unwrap()]);
+
+        let addrs = lookup_host("localhost", 80).await.unwrap();
+        let addrs = addrs.collect::<Vec<_>>().await;
+        assert!(!addrs.is_empty());
+        assert!(addrs.iter().all(|addr| addr.ip().is_loopback()));
+        assert!(addrs.iter().all(|addr| addr.port() == 80));
+    })
+}
+
+#[test]
+fn hints() {
+    compio::task::block_on(async {
+        let hints = Hints::new()
+            .family(Domain::IPV4)
+            .socket_type(Type::DGRAM)
+            .canonical_name(true);
+        let resolved = resolve("localhost", hints).await.unwrap();
+        assert!(resolved.canonical_name().is_some());
+        assert!(!resolved.entries().is_empty());
+        for entry in resolved.entries() {
+            assert_eq!(entry.family, Domain::IPV4);
+            assert_eq!(entry.socket_type, Type::DGRAM);
+            assert_eq!(entry.protocol, Protocol::UDP);
+            assert!(matches!(entry.addr.ip(), IpAddr::V4(ip) if ip.is_loopback()));
+        }
+    })
+}
+
+#[test]
+fn order() {
+    compio::task::block_on(async {
+        let hints = Hints::new().socket_type(Type::STREAM);
+        let resolved = resolve("localhost", hints).await.unwrap();
+        let expected = std::net::ToSocketAddrs::to_socket_addrs(&("localhost", 0))
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(resolved.addrs().collect::<Vec<_>>(), expected);
+    })
+}
+
+#[test]
+fn invalid_host() {
+    compio::task::block_on(async {
+        let err = resolve("bücher.example", Hints::new()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("punycode"));
+
+        let err = resolve("local\0host", Hints::new()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        assert!(resolve("nonexistent.invalid", Hints::new()).await.is_err());
+    })
+}
+
+#[test]
+fn cancel() {
+    compio::task::block_on(async {
+        // Drop the futures while `getaddrinfo` may be running on the pool.
+        for _ in 0..16 {
+            assert!(resolve("localhost", Hints::new()).now_or_never().is_none());
+        }
+        let resolved = resolve("localhost", Hints::new()).await.unwrap();
+        assert!(!resolved.entries().is_empty());
+    })
+}
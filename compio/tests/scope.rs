@@ -0,0 +1,130 @@
+use std::{future::Future, net::Ipv4Addr, panic::AssertUnwindSafe};
+
+use compio::{
+    fs::File,
+    net::{TcpListener, TcpStream},
+    task::scope_io,
+};
+use tempfile::NamedTempFile;
+
+const HELLO: &[u8] = b"hello world...";
+
+fn stream_pair() -> (TcpStream, TcpStream) {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, (rx, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+        (tx, rx)
+    })
+}
+
+#[test]
+fn borrowed_read_write() {
+    let tempfile = NamedTempFile::new().unwrap();
+    let file = File::create(tempfile.path()).unwrap();
+
+    let data = HELLO.to_vec();
+    let (file_ref, data_ref) = (&file, data.as_slice());
+    let written = scope_io(|io| async move { io.write_at(file_ref, data_ref, 0).await.unwrap() });
+    assert_eq!(written, HELLO.len());
+
+    let file = File::open(tempfile.path()).unwrap();
+    let mut buffer = [0u8; 32];
+    let (file_ref, buffer_ref) = (&file, &mut buffer);
+    let read = scope_io(|io| async move { io.read_at(file_ref, buffer_ref, 0).await.unwrap() });
+    assert_eq!(&buffer[..read], HELLO);
+}
+
+#[test]
+fn borrowed_send_recv() {
+    let (tx, rx) = stream_pair();
+
+    let mut buffer = [0u8; 32];
+    let (tx_ref, rx_ref, buffer_ref) = (&tx, &rx, &mut buffer);
+    let received = scope_io(|io| async move {
+        let (sent, received) =
+            futures_util::join!(io.send(tx_ref, HELLO), io.recv(rx_ref, buffer_ref));
+        assert_eq!(sent.unwrap(), HELLO.len());
+        received.unwrap()
+    });
+    assert_eq!(&buffer[..received], HELLO);
+}
+
+#[test]
+fn cancel_on_exit() {
+    let (_tx, rx) = stream_pair();
+
+    let mut buffer = [0u8; 32];
+    let (rx_ref, buffer_ref) = (&rx, &mut buffer);
+    // The peer never sends, so the scope must wait for the cancellation.
+    scope_io(|io| async move {
+        poll_once(io.recv(rx_ref, buffer_ref)).await;
+    });
+    buffer.fill(1);
+}
+
+#[test]
+fn cancel_on_panic() {
+    let (_tx, rx) = stream_pair();
+
+    let mut buffer = [0u8; 32];
+    let (rx_ref, buffer_ref) = (&rx, &mut buffer);
+    let res = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        scope_io(|io| async move {
+            poll_once(io.recv(rx_ref, buffer_ref)).await;
+            panic!("panic in scope");
+        })
+    }));
+    assert!(res.is_err());
+    buffer.fill(1);
+}
+
+#[test]
+fn cancel_forgotten() {
+    let (tx, rx) = stream_pair();
+
+    let mut buffer = [0u8; 32];
+    let (rx_ref, buffer_ref) = (&rx, &mut buffer);
+    // The future is forgotten instead of dropped, so it never cancels the op.
+    scope_io(|io| async move {
+        let mut recv = Box::pin(io.recv(rx_ref, buffer_ref));
+        assert!(futures_util::poll!(recv.as_mut()).is_pending());
+        std::mem::forget(recv);
+    });
+    compio::task::block_on(async {
+        tx.send_all(HELLO).await.0.unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        compio::task::yield_now().await;
+    });
+    assert_eq!(buffer, [0; 32]);
+
+    // The data goes to the next receive.
+    compio::task::block_on(async {
+        let (res, received) = rx.recv_exact(Vec::with_capacity(HELLO.len())).await;
+        res.unwrap();
+        assert_eq!(received, HELLO);
+    });
+}
+
+#[test]
+fn submit_after_exit() {
+    let tempfile = NamedTempFile::new().unwrap();
+    let file = File::create(tempfile.path()).unwrap();
+
+    let io = scope_io(|io| async move { io });
+    let res = compio::task::block_on(io.write_at(&file, HELLO, 0));
+    assert!(res.is_err());
+}
+
+async fn poll_once(future: impl Future) {
+    use std::{future::poll_fn, pin::pin, task::Poll};
+
+    let mut future = pin!(future);
+
+    poll_fn(|cx| {
+        assert!(future.as_mut().poll(cx).is_pending());
+        Poll::Ready(())
+    })
+    .await;
+}
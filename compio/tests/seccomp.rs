@@ -0,0 +1,62 @@
+#![cfg(target_os = "linux")]
+
+use std::collections::BTreeMap;
+
+use compio::driver::{required_syscalls, Proactor, SetupError};
+use seccompiler::{BpfProgram, SeccompAction, SeccompFilter};
+
+// Build a driver in a new thread, with the syscall failing with EPERM. The
+// filter only applies to the thread.
+fn build_without(syscall: libc::c_long) -> std::io::Result<()> {
+    std::thread::spawn(move || {
+        let filter = SeccompFilter::new(
+            BTreeMap::from([(syscall, vec![])]),
+            SeccompAction::Allow,
+            SeccompAction::Errno(libc::EPERM as u32),
+            std::env::consts::ARCH.try_into().unwrap(),
+        )
+        .unwrap();
+        let program: BpfProgram = filter.try_into().unwrap();
+        seccompiler::apply_filter(&program).unwrap();
+        Proactor::new().map(drop)
+    })
+    .join()
+    .unwrap()
+}
+
+fn assert_blocked(syscall: libc::c_long, name: &str) {
+    let e = build_without(syscall).expect_err(name);
+    assert_eq!(e.kind(), std::io::ErrorKind::PermissionDenied);
+    let inner = e
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<SetupError>())
+        .expect("not a setup error");
+    assert_eq!(inner.syscall(), name);
+    assert_eq!(inner.raw_os_error(), Some(libc::EPERM));
+    assert!(e.to_string().contains("seccomp"));
+    assert!(required_syscalls().contains(&name));
+}
+
+#[test]
+fn allowed() {
+    build_without(libc::SYS_mknodat).unwrap();
+}
+
+#[test]
+fn eventfd() {
+    assert_blocked(libc::SYS_eventfd2, "eventfd2");
+}
+
+#[test]
+#[cfg(feature = "io-uring")]
+fn io_uring() {
+    assert_blocked(libc::SYS_io_uring_setup, "io_uring_setup");
+    assert_blocked(libc::SYS_io_uring_register, "io_uring_register");
+    assert_blocked(libc::SYS_io_uring_enter, "io_uring_enter");
+}
+
+#[test]
+#[cfg(not(feature = "io-uring"))]
+fn polling() {
+    assert_blocked(libc::SYS_epoll_create1, "epoll_create1");
+}
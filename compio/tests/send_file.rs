@@ -0,0 +1,134 @@
+use std::net::Ipv4Addr;
+
+use compio::{
+    fs::File,
+    net::{send_file, TcpListener, TcpStream, UnixListener, UnixStream},
+};
+use tempfile::NamedTempFile;
+
+async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, (rx, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    (tx, rx)
+}
+
+#[test]
+fn range() {
+    let data = pattern(1024 * 1024);
+    let tempfile = tempfile(&data);
+
+    compio::task::block_on(async {
+        let (tx, rx) = tcp_pair().await;
+        let file = File::open(tempfile.path()).unwrap();
+        let (sent, (received, buffer)) = futures_util::join!(
+            send_file(&tx, &file, 1000, 500_000),
+            rx.recv_exact(Vec::with_capacity(500_000)),
+        );
+        assert_eq!(sent.unwrap(), 500_000);
+        assert_eq!(received.unwrap(), 500_000);
+        assert_eq!(buffer, &data[1000..501_000]);
+    })
+}
+
+#[test]
+fn beyond_eof() {
+    let data = pattern(100_000);
+    let tempfile = tempfile(&data);
+
+    compio::task::block_on(async {
+        let (tx, rx) = tcp_pair().await;
+        let file = File::open(tempfile.path()).unwrap();
+        let sent = send_file(&tx, &file, 10, u64::MAX).await.unwrap();
+        assert_eq!(sent, 99_990);
+        let sent = send_file(&tx, &file, 200_000, 10).await.unwrap();
+        assert_eq!(sent, 0);
+        drop(tx);
+
+        let (res, buffer) = rx.recv_exact(Vec::with_capacity(99_990)).await;
+        res.unwrap();
+        assert_eq!(buffer, &data[10..]);
+        let (res, _) = rx.recv(Vec::with_capacity(1)).await;
+        assert_eq!(res.unwrap(), 0);
+    })
+}
+
+#[test]
+fn unix_stream() {
+    let data = pattern(300_000);
+    let tempfile = tempfile(&data);
+
+    compio::task::block_on(async {
+        let dir = tempfile::Builder::new()
+            .prefix("compio-uds-tests")
+            .tempdir()
+            .unwrap();
+        let sock_path = dir.path().join("send_file.sock");
+        let listener = UnixListener::bind(&sock_path).unwrap();
+        let tx = UnixStream::connect(&sock_path).unwrap();
+        let (rx, _) = listener.accept().await.unwrap();
+
+        let file = File::open(tempfile.path()).unwrap();
+        let (sent, (received, buffer)) = futures_util::join!(
+            send_file(&tx, &file, 0, data.len() as u64),
+            rx.recv_exact(Vec::with_capacity(data.len())),
+        );
+        assert_eq!(sent.unwrap(), data.len() as u64);
+        assert_eq!(received.unwrap(), data.len());
+        assert_eq!(buffer, data);
+    })
+}
+
+// `sendfile` doesn't support the files of procfs, and the userspace buffers are
+// used instead.
+#[test]
+#[cfg(target_os = "linux")]
+fn fallback() {
+    const PATH: &str = "/proc/self/limits";
+
+    compio::task::block_on(async {
+        let (tx, rx) = tcp_pair().await;
+        let file = File::open(PATH).unwrap();
+        let sent = send_file(&tx, &file, 0, u64::MAX).await.unwrap();
+        drop(tx);
+
+        let expected = std::fs::read(PATH).unwrap();
+        assert_eq!(sent, expected.len() as u64);
+        let (res, buffer) = rx.recv_exact(Vec::with_capacity(expected.len())).await;
+        res.unwrap();
+        assert_eq!(buffer, expected);
+    })
+}
+
+#[test]
+fn peer_closes() {
+    const LEN: usize = 32 * 1024 * 1024;
+
+    let tempfile = tempfile(&pattern(LEN));
+
+    compio::task::block_on(async {
+        let (tx, rx) = tcp_pair().await;
+        let file = File::open(tempfile.path()).unwrap();
+        let close = async move {
+            let (res, _) = rx.recv_exact(Vec::with_capacity(1024)).await;
+            res.unwrap();
+            // Close with the data unread, and the connection is reset.
+            drop(rx);
+        };
+        let (sent, ()) = futures_util::join!(send_file(&tx, &file, 0, LEN as u64), close);
+        let sent = sent.unwrap();
+        assert!(sent >= 1024);
+        assert!(sent < LEN as u64);
+    })
+}
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+fn tempfile(data: &[u8]) -> NamedTempFile {
+    let tempfile = NamedTempFile::new().unwrap();
+    std::fs::write(tempfile.path(), data).unwrap();
+    tempfile
+}
@@ -0,0 +1,89 @@
+use std::{net::Ipv4Addr, rc::Rc, sync::Arc};
+
+use compio::{
+    buf::IoBuf,
+    net::{TcpListener, TcpStream},
+};
+use futures_util::future::join_all;
+
+async fn tcp_pairs(n: usize) -> Vec<(TcpStream, TcpStream)> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut pairs = vec![];
+    for _ in 0..n {
+        let (tx, (rx, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+        pairs.push((tx, rx));
+    }
+    pairs
+}
+
+// Write the clones of the message to all pairs concurrently, and return what
+// each one receives.
+async fn broadcast<B: IoBuf + Clone>(
+    message: &B,
+    pairs: &[(TcpStream, TcpStream)],
+) -> Vec<Vec<u8>> {
+    let len = message.buf_len();
+    let sends = join_all(pairs.iter().map(|(tx, _)| tx.send_all(message.clone())));
+    let recvs = join_all(
+        pairs
+            .iter()
+            .map(|(_, rx)| rx.recv_exact(Vec::with_capacity(len))),
+    );
+    let (sent, received) = futures_util::join!(sends, recvs);
+    for (res, _) in sent {
+        assert_eq!(res.unwrap(), len);
+    }
+    received
+        .into_iter()
+        .map(|(res, buf)| {
+            res.unwrap();
+            buf
+        })
+        .collect()
+}
+
+#[test]
+fn rc() {
+    compio::task::block_on(async {
+        let pairs = tcp_pairs(16).await;
+        let data = (0..100_000).map(|i| i as u8).collect::<Vec<_>>();
+
+        let message = Rc::new(data.clone());
+        for received in broadcast(&message, &pairs).await {
+            assert_eq!(received, data);
+        }
+        // All clones are dropped after the ops complete.
+        assert_eq!(Rc::strong_count(&message), 1);
+
+        let message = Rc::<[u8]>::from(&data[..]);
+        for received in broadcast(&message, &pairs).await {
+            assert_eq!(received, data);
+        }
+        assert_eq!(Rc::strong_count(&message), 1);
+
+        // A part of the shared message.
+        let message = Rc::new(data.clone()).slice(1000..2000);
+        for received in broadcast(&message, &pairs).await {
+            assert_eq!(received, &data[1000..2000]);
+        }
+    })
+}
+
+#[test]
+fn arc() {
+    compio::task::block_on(async {
+        let pairs = tcp_pairs(4).await;
+        let data = b"hello world".to_vec();
+
+        let message = Arc::new(data.clone());
+        for received in broadcast(&message, &pairs).await {
+            assert_eq!(received, data);
+        }
+        let message = Arc::<[u8]>::from(&data[..]).slice(6..);
+        for received in broadcast(&message, &pairs).await {
+            assert_eq!(received, b"world");
+        }
+    })
+}
@@ -0,0 +1,107 @@
+use std::net::Ipv4Addr;
+
+use compio::net::{SharedTcpStream, TcpListener, TcpStream};
+
+async fn pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, (server, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    (client, server)
+}
+
+#[test]
+fn sends_not_interleaved() {
+    // Large enough to be sent partially.
+    const LEN: usize = 256 * 1024;
+    const COUNT: usize = 16;
+
+    compio::task::block_on(async {
+        let (client, server) = pair().await;
+        let client = SharedTcpStream::new(client);
+        let senders = [b'a', b'b']
+            .map(|pattern| {
+                let client = client.clone();
+                compio::task::spawn(async move {
+                    for _ in 0..COUNT {
+                        let (res, _) = client.send_all(vec![pattern; LEN]).await;
+                        assert_eq!(res.unwrap(), LEN);
+                    }
+                })
+            })
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let mut counts = [0; 2];
+        let mut buffer = Vec::with_capacity(LEN);
+        for _ in 0..COUNT * 2 {
+            buffer.clear();
+            let (res, b) = server.recv_exact(buffer).await;
+            res.unwrap();
+            let pattern = b[0];
+            assert!(b.iter().all(|x| *x == pattern), "interleaved message");
+            counts[(pattern - b'a') as usize] += 1;
+            buffer = b;
+        }
+        assert_eq!(counts, [COUNT; 2]);
+        for sender in senders {
+            sender.await;
+        }
+    })
+}
+
+#[test]
+fn concurrent_recv_rejected() {
+    compio::task::block_on(async {
+        let (client, server) = pair().await;
+        let server = SharedTcpStream::new(server);
+        let reader = compio::task::spawn({
+            let server = server.clone();
+            async move { server.recv(Vec::with_capacity(8)).await }
+        });
+        compio::task::yield_now().await;
+
+        let (res, _) = server.recv(Vec::with_capacity(8)).await;
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::ResourceBusy);
+
+        client.send_all("hello").await.0.unwrap();
+        let (res, buffer) = reader.await;
+        assert_eq!(res.unwrap(), 5);
+        assert_eq!(buffer, b"hello");
+
+        // Another receive could be performed after it completes.
+        client.send_all("world").await.0.unwrap();
+        let (res, buffer) = server.recv_exact(Vec::with_capacity(5)).await;
+        res.unwrap();
+        assert_eq!(buffer, b"world");
+    })
+}
+
+#[test]
+fn cancelled_send_releases() {
+    use futures_util::FutureExt;
+
+    compio::task::block_on(async {
+        let (client, server) = pair().await;
+        let client = SharedTcpStream::new(client);
+
+        // Waiting behind a send in flight, which the peer doesn't read yet,
+        // and cancelled.
+        let blocker = compio::task::spawn({
+            let client = client.clone();
+            async move { client.send_all(vec![0u8; 64 * 1024 * 1024]).await.0 }
+        });
+        compio::task::yield_now().await;
+        assert!(client.send_all("lost").now_or_never().is_none());
+
+        drop(blocker);
+        let reader = compio::task::spawn(async move { server.recv_to_end(vec![]).await });
+        let (res, _) = client.send_all("hello").await;
+        assert_eq!(res.unwrap(), 5);
+
+        drop(client);
+        let (res, buffer) = reader.await;
+        res.unwrap();
+        assert!(buffer.ends_with(b"\0hello"));
+    })
+}
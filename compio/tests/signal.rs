@@ -0,0 +1,40 @@
+#![cfg(unix)]
+
+use std::{
+    pin::pin,
+    sync::{Arc, Barrier},
+};
+
+use compio::signal::unix::signal;
+use futures_util::poll;
+
+#[test]
+fn multiple_runtimes() {
+    const RUNTIMES: usize = 2;
+
+    let barrier = Arc::new(Barrier::new(RUNTIMES + 1));
+    let threads = (0..RUNTIMES)
+        .map(|_| {
+            let barrier = barrier.clone();
+            std::thread::spawn(move || {
+                compio::task::block_on(async {
+                    let mut first = pin!(signal(libc::SIGUSR1));
+                    let mut second = pin!(signal(libc::SIGUSR1));
+                    // Register the listeners.
+                    assert!(poll!(first.as_mut()).is_pending());
+                    assert!(poll!(second.as_mut()).is_pending());
+                    barrier.wait();
+                    first.await.unwrap();
+                    second.await.unwrap();
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    barrier.wait();
+    // The signal is delivered to the current thread, which runs no runtime.
+    unsafe { libc::raise(libc::SIGUSR1) };
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
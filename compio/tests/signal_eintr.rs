@@ -0,0 +1,84 @@
+//! The interval timer and the signal handler are of the process, so the test
+//! is in its own binary.
+
+#![cfg(unix)]
+
+use std::{
+    io::Write,
+    os::{fd::AsRawFd, unix::net::UnixStream},
+    time::{Duration, Instant},
+};
+
+use compio::{buf::IntoInner, fs::File, op::BlockingBufOp};
+
+extern "C" fn on_alarm(_: libc::c_int) {}
+
+// Deliver `SIGALRM` every millisecond, without `SA_RESTART`, so that the
+// blocking syscalls fail with `EINTR`.
+fn start_ticker() {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_alarm as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        assert_eq!(
+            libc::sigaction(libc::SIGALRM, &action, std::ptr::null_mut()),
+            0
+        );
+        let tick = libc::timeval {
+            tv_sec: 0,
+            tv_usec: 1000,
+        };
+        let timer = libc::itimerval {
+            it_interval: tick,
+            it_value: tick,
+        };
+        assert_eq!(
+            libc::setitimer(libc::ITIMER_REAL, &timer, std::ptr::null_mut()),
+            0
+        );
+    }
+}
+
+#[test]
+fn interrupted_by_signals() {
+    start_ticker();
+    compio::task::block_on(async {
+        for _ in 0..10 {
+            let duration = Duration::from_millis(20);
+            let start = Instant::now();
+            compio::time::sleep(duration).await;
+            let elapsed = start.elapsed();
+            assert!(elapsed >= duration, "woke early: {elapsed:?}");
+            assert!(elapsed < duration * 3, "woke late: {elapsed:?}");
+        }
+
+        let expected = std::fs::read("Cargo.toml").unwrap();
+        let file = File::open("Cargo.toml").unwrap();
+        for _ in 0..100 {
+            let (res, buf) = file
+                .read_to_end_at(Vec::with_capacity(expected.len()), 0)
+                .await;
+            res.unwrap();
+            assert_eq!(buf, expected);
+        }
+
+        // A blocking read on the thread pool.
+        let (reader, mut writer) = UnixStream::pair().unwrap();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            writer.write_all(b"ping").unwrap();
+        });
+        let op = BlockingBufOp::new(reader.as_raw_fd(), [0u8; 4], |fd, buf| {
+            let res = unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) };
+            if res < 0 {
+                Err(std::io::Error::last_os_error())
+            } else {
+                Ok(res as _)
+            }
+        });
+        let (res, op) = compio::task::submit(op).await;
+        assert_eq!(res.unwrap(), 4);
+        assert_eq!(op.into_inner(), *b"ping");
+        writer.join().unwrap();
+    })
+}
@@ -0,0 +1,75 @@
+#![cfg(target_os = "linux")]
+
+use std::{io::ErrorKind, net::Ipv4Addr};
+
+use compio::net::{SocketOpts, TcpListener, TcpStream, UdpSocket};
+
+fn loopback_index() -> u32 {
+    unsafe { libc::if_nametoindex(c"lo".as_ptr()) }
+}
+
+#[test]
+fn udp_bind_device() {
+    compio::task::block_on(async {
+        let opts = SocketOpts::new().bind_device(Some("lo"));
+        let socket = UdpSocket::bind_with((Ipv4Addr::LOCALHOST, 0), &opts).unwrap();
+        let other = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        other.bind_device_by_index(loopback_index()).unwrap();
+        socket.connect(other.local_addr().unwrap()).unwrap();
+        other.connect(socket.local_addr().unwrap()).unwrap();
+
+        socket.send("ping").await.0.unwrap();
+        let (res, buf) = other.recv(Vec::with_capacity(4)).await;
+        assert_eq!(res.unwrap(), 4);
+        assert_eq!(buf, b"ping");
+
+        // Unbound, and the traffic still flows.
+        socket.bind_device(None).unwrap();
+        other.send("pong").await.0.unwrap();
+        let (res, buf) = socket.recv(Vec::with_capacity(4)).await;
+        assert_eq!(res.unwrap(), 4);
+        assert_eq!(buf, b"pong");
+
+        let opts = SocketOpts::new().bind_device(Some("compio-none"));
+        let Err(e) = UdpSocket::bind_with((Ipv4Addr::LOCALHOST, 0), &opts) else {
+            panic!("bound to a missing device");
+        };
+        assert_eq!(e.raw_os_error(), Some(libc::ENODEV));
+    })
+}
+
+#[test]
+fn tcp_bind_device() {
+    compio::task::block_on(async {
+        let opts = SocketOpts::new().bind_device(Some("lo"));
+        let listener = TcpListener::bind_with((Ipv4Addr::LOCALHOST, 0), &opts).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let opts = SocketOpts::new().bind_device_by_index(loopback_index());
+        let (client, (server, _)) =
+            futures_util::try_join!(TcpStream::connect_with(&addr, &opts), listener.accept())
+                .unwrap();
+        client.send_all("hello").await.0.unwrap();
+        let (res, buf) = server.recv_exact(Vec::with_capacity(5)).await;
+        res.unwrap();
+        assert_eq!(buf, b"hello");
+    })
+}
+
+#[test]
+fn freebind() {
+    // The documentation address is not configured on any interface.
+    let addr = (Ipv4Addr::new(192, 0, 2, 1), 0);
+    let Err(e) = UdpSocket::bind(addr) else {
+        panic!("bound to a non-local address");
+    };
+    assert_eq!(e.kind(), ErrorKind::AddrNotAvailable);
+
+    let opts = SocketOpts::new().freebind(true);
+    let socket = UdpSocket::bind_with(addr, &opts).unwrap();
+    assert_eq!(
+        socket.local_addr().unwrap().as_socket().unwrap().ip(),
+        Ipv4Addr::new(192, 0, 2, 1)
+    );
+    TcpListener::bind_with(addr, &opts).unwrap();
+}
@@ -0,0 +1,63 @@
+use std::{pin::pin, sync::Arc};
+
+use compio::sync::Mutex;
+use futures_util::poll;
+
+fn contended_counter(counter: Mutex<usize>) {
+    const THREADS: usize = 4;
+    const INCREMENTS: usize = 250_000;
+
+    let counter = Arc::new(counter);
+    let threads = (0..THREADS)
+        .map(|_| {
+            let counter = counter.clone();
+            std::thread::spawn(move || {
+                compio::task::block_on(async {
+                    for _ in 0..INCREMENTS {
+                        *counter.lock().await.unwrap() += 1;
+                    }
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    let counter = Arc::into_inner(counter).unwrap();
+    assert_eq!(counter.into_inner(), THREADS * INCREMENTS);
+}
+
+#[test]
+fn contended_counter_futex() {
+    // The futex ops are only supported by io-uring on Linux 6.7 or later.
+    if let Some(counter) = Mutex::with_futex(0) {
+        contended_counter(counter);
+    }
+}
+
+#[test]
+fn contended_counter_event_queue() {
+    contended_counter(Mutex::with_event_queue(0));
+}
+
+#[test]
+fn cancel_waiting() {
+    compio::task::block_on(async {
+        let mutex = Arc::new(Mutex::new(()));
+        let guard = mutex.lock().await.unwrap();
+
+        let thread = std::thread::spawn({
+            let mutex = mutex.clone();
+            move || compio::task::block_on(async { drop(mutex.lock().await.unwrap()) })
+        });
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        {
+            // The cancelled waiter shouldn't consume the wakeup.
+            let mut fut = pin!(mutex.lock());
+            assert!(poll!(fut.as_mut()).is_pending());
+        }
+        drop(guard);
+        thread.join().unwrap();
+        assert!(mutex.try_lock().is_some());
+    })
+}
@@ -0,0 +1,130 @@
+//! The syscalls of a fixed workload are compared with the baselines of each
+//! driver, to catch the regressions. Update the baselines consciously when a
+//! change is expected to perform more syscalls, and lower them when fewer.
+
+#![cfg(unix)]
+
+use std::net::Ipv4Addr;
+
+use compio::{
+    driver::{SyscallCounts, reset_syscall_counts, syscall_counts},
+    fs::File,
+    net::{TcpListener, TcpStream},
+};
+
+const ROUND_TRIPS: u64 = 100;
+const FILE_READS: u64 = 100;
+
+// The upper bounds of the syscalls per round trip and per file read. The
+// baselines of IOCP are not recorded yet.
+cfg_if::cfg_if! {
+    if #[cfg(all(target_os = "linux", feature = "io-uring"))] {
+        // The client and the server ops are submitted together in 2 waits.
+        // The registered ring fd saves the lookups, not the syscalls.
+        const ECHO_BASELINE: f64 = 2.1;
+        const READ_BASELINE: f64 = 1.0;
+    } else {
+        // 4 registrations and removals of the readiness, 4 waits, and 2 reads
+        // and writes.
+        const ECHO_BASELINE: f64 = 16.1;
+        // The files are read inline.
+        const READ_BASELINE: f64 = 1.0;
+    }
+}
+
+fn check(name: &str, counts: &SyscallCounts, times: u64, baseline: f64) {
+    let per_op = counts.total() as f64 / times as f64;
+    println!("{name}: {per_op:.2} syscalls per op\n{counts}");
+    assert!(
+        per_op <= baseline,
+        "{name}: {per_op:.2} syscalls per op, more than the baseline {baseline:.2}\n{counts}"
+    );
+}
+
+#[test]
+fn echo() {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, accepted) = futures_util::join!(TcpStream::connect(&addr), listener.accept());
+        let client = client.unwrap();
+        let (server, _) = accepted.unwrap();
+        compio::task::spawn(async move {
+            let mut buffer = Vec::with_capacity(16);
+            loop {
+                buffer.clear();
+                let res;
+                (res, buffer) = server.recv(buffer).await;
+                if !matches!(res, Ok(n) if n > 0) {
+                    break;
+                }
+                let res;
+                (res, buffer) = server.send_all(buffer).await;
+                res.unwrap();
+            }
+        })
+        .detach();
+
+        reset_syscall_counts();
+        let mut buffer = Vec::with_capacity(4);
+        for _ in 0..ROUND_TRIPS {
+            client.send_all("ping").await.0.unwrap();
+            buffer.clear();
+            let res;
+            (res, buffer) = client.recv_exact(buffer).await;
+            res.unwrap();
+        }
+        check("echo", &syscall_counts(), ROUND_TRIPS, ECHO_BASELINE);
+    })
+}
+
+#[test]
+fn accepted_peer_addr() {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        reset_syscall_counts();
+        let (client, accepted) = futures_util::join!(TcpStream::connect(&addr), listener.accept());
+        let client = client.unwrap();
+        let (server, remote) = accepted.unwrap();
+        for _ in 0..3 {
+            assert_eq!(server.peer_addr().unwrap(), remote);
+            assert_eq!(client.peer_addr().unwrap(), addr);
+            assert_eq!(listener.local_addr().unwrap(), addr);
+        }
+        assert_eq!(client.local_addr().unwrap(), remote);
+        assert_eq!(server.local_addr().unwrap(), client.peer_addr().unwrap());
+        let counts = syscall_counts();
+        println!("{counts}");
+        // Known from the accept and the connect.
+        assert_eq!(counts.get("getpeername"), 0);
+        // The local addresses of the streams are retrieved once each.
+        assert_eq!(counts.get("getsockname"), 2);
+
+        // Retrieved once for a stream from std.
+        let server = TcpStream::from_std(server.into_std()).unwrap();
+        reset_syscall_counts();
+        for _ in 0..3 {
+            assert_eq!(server.peer_addr().unwrap(), remote);
+        }
+        assert_eq!(syscall_counts().get("getpeername"), 1);
+    })
+}
+
+#[test]
+fn file_read() {
+    compio::task::block_on(async {
+        let file = File::open("Cargo.toml").unwrap();
+        let mut buffer = Vec::with_capacity(1024);
+
+        reset_syscall_counts();
+        for _ in 0..FILE_READS {
+            buffer.clear();
+            let res;
+            (res, buffer) = file.read_at(buffer, 0).await;
+            res.unwrap();
+        }
+        check("read", &syscall_counts(), FILE_READS, READ_BASELINE);
+    })
+}
@@ -1,3 +1,5 @@
+use std::{cell::Cell, rc::Rc};
+
 use compio::net::{TcpListener, TcpStream, ToSockAddrs};
 
 async fn test_impl(addr: impl ToSockAddrs) {
@@ -33,3 +35,276 @@ test_accept! {
     (str_port_tuple, ("127.0.0.1", 0)),
     (ip_port_tuple, ("127.0.0.1".parse::<std::net::IpAddr>().unwrap(), 0)),
 }
+
+#[test]
+fn pending_and_stats() {
+    const CLIENTS: usize = 8;
+
+    compio::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Fill the accept queue without accepting.
+        let mut clients = vec![];
+        for _ in 0..CLIENTS {
+            clients.push(TcpStream::connect(&addr).await.unwrap());
+        }
+        let pending = listener.pending_connections().unwrap();
+        if cfg!(target_os = "linux") {
+            assert_eq!(pending, Some(CLIENTS));
+        } else {
+            assert_eq!(pending, None);
+        }
+
+        for _ in 0..CLIENTS {
+            listener.accept().await.unwrap();
+        }
+        if cfg!(target_os = "linux") {
+            assert_eq!(listener.pending_connections().unwrap(), Some(0));
+        }
+        let stats = listener.accept_stats();
+        assert_eq!(stats.accepts, CLIENTS as u64);
+        assert_eq!(stats.connection_errors + stats.resource_errors + stats.other_errors, 0);
+        assert!(stats.max_accept_latency <= stats.accept_latency);
+        assert!(stats.accepts_per_sec() > 0.0);
+    })
+}
+
+// Go through the driver once, so that the cancellations are processed.
+async fn round_trip() {
+    let file = compio::fs::File::open("Cargo.toml").unwrap();
+    file.read_at(Vec::with_capacity(1), 0).await.0.unwrap();
+}
+
+#[test]
+fn pause_resume() {
+    compio::task::block_on(async {
+        let listener = Rc::new(TcpListener::bind("127.0.0.1:0").unwrap());
+        let addr = listener.local_addr().unwrap();
+        let accepted = Rc::new(Cell::new(0));
+        let task = compio::task::spawn({
+            let (listener, accepted) = (listener.clone(), accepted.clone());
+            async move {
+                for _ in 0..2 {
+                    listener.accept().await.unwrap();
+                    accepted.set(accepted.get() + 1);
+                }
+            }
+        });
+        round_trip().await;
+
+        // The accept in flight is cancelled, and the connection is queued.
+        listener.pause().await;
+        assert!(listener.is_paused());
+        round_trip().await;
+        let _client = TcpStream::connect(&addr).await.unwrap();
+        round_trip().await;
+        assert_eq!(accepted.get(), 0);
+        if cfg!(target_os = "linux") {
+            assert_eq!(listener.pending_connections().unwrap(), Some(1));
+        }
+
+        listener.resume();
+        assert!(!listener.is_paused());
+        let _client = TcpStream::connect(&addr).await.unwrap();
+        task.await;
+        assert_eq!(accepted.get(), 2);
+        let stats = listener.accept_stats();
+        assert_eq!(stats.accepts, 2);
+        assert_eq!(stats.other_errors, 0);
+    })
+}
+
+#[test]
+fn std_round_trip() {
+    compio::task::block_on(async {
+        // Not bound again.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let listener = TcpListener::from_std(listener).unwrap();
+        assert_eq!(listener.local_addr().unwrap().as_socket(), Some(addr));
+        let (client, (server, _)) =
+            futures_util::try_join!(TcpStream::connect(addr), listener.accept()).unwrap();
+        assert_eq!(client.local_addr().unwrap(), server.peer_addr().unwrap());
+
+        // The socket is still open, and listening.
+        let listener = listener.into_std();
+        listener.set_nonblocking(false).unwrap();
+        let client = std::net::TcpStream::connect(addr).unwrap();
+        let (_, peer) = listener.accept().unwrap();
+        assert_eq!(client.local_addr().unwrap(), peer);
+    })
+}
+
+#[test]
+#[cfg(unix)]
+fn accept_cloexec() {
+    use std::os::fd::AsRawFd;
+
+    compio::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_client, (server, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+        // Not leaked to the child processes.
+        let flags = unsafe { libc::fcntl(server.as_raw_fd(), libc::F_GETFD) };
+        assert_eq!(flags & libc::FD_CLOEXEC, libc::FD_CLOEXEC);
+    })
+}
+
+#[test]
+fn reuseport_sharded() {
+    use std::io::Read;
+
+    let listeners = TcpListener::bind_reuseport_sharded("127.0.0.1:0", 4).unwrap();
+    let addr = listeners[0].local_addr().unwrap();
+    if cfg!(any(windows, target_os = "solaris", target_os = "illumos")) {
+        assert_eq!(listeners.len(), 1);
+    } else {
+        assert_eq!(listeners.len(), 4);
+    }
+    for listener in &listeners {
+        assert_eq!(listener.local_addr().unwrap(), addr);
+    }
+    let shards = listeners.len();
+
+    // Each worker replies its index.
+    for (index, listener) in listeners.into_iter().enumerate() {
+        std::thread::spawn(move || {
+            compio::task::block_on(async move {
+                let listener = TcpListener::from_std(listener).unwrap();
+                loop {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    stream.send_all(vec![index as u8]).await.0.unwrap();
+                }
+            })
+        });
+    }
+
+    let mut counts = vec![0; shards];
+    for _ in 0..64 {
+        let mut stream = std::net::TcpStream::connect(addr).unwrap();
+        let mut index = [0];
+        stream.read_exact(&mut index).unwrap();
+        counts[index[0] as usize] += 1;
+    }
+    assert_eq!(counts.iter().sum::<usize>(), 64);
+    if shards > 1 {
+        // Spread by the hash of the 4-tuple.
+        assert!(counts.iter().filter(|count| **count > 0).count() > 1, "{counts:?}");
+    }
+}
+
+#[test]
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn reuseport_cbpf() {
+    compio::task::block_on(async {
+        let listeners = TcpListener::bind_reuseport_sharded("127.0.0.1:0", 2)
+            .unwrap()
+            .into_iter()
+            .map(|listener| TcpListener::from_std(listener).unwrap())
+            .collect::<Vec<_>>();
+        let addr = listeners[0].local_addr().unwrap();
+        // Always the second one.
+        let program = [libc::sock_filter {
+            code: (libc::BPF_RET | libc::BPF_K) as _,
+            jt: 0,
+            jf: 0,
+            k: 1,
+        }];
+        listeners[0].set_reuseport_cbpf(&program).unwrap();
+
+        let mut clients = vec![];
+        for _ in 0..8 {
+            let (client, (server, _)) =
+                futures_util::try_join!(TcpStream::connect(&addr), listeners[1].accept())
+                    .unwrap();
+            assert_eq!(client.local_addr().unwrap(), server.peer_addr().unwrap());
+            clients.push(client);
+        }
+        assert_eq!(listeners[0].pending_connections().unwrap(), Some(0));
+    })
+}
+
+#[test]
+fn dispatch_stream() {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, (server, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+        // Served by another thread before any IO.
+        let server = server.into_std();
+        std::thread::spawn(move || {
+            compio::task::block_on(async {
+                let server = TcpStream::from_std(server).unwrap();
+                server.send_all("hello").await.0.unwrap();
+            })
+        })
+        .join()
+        .unwrap();
+        let (res, buf) = client.recv_exact(Vec::with_capacity(5)).await;
+        res.unwrap();
+        assert_eq!(buf, b"hello");
+    })
+}
+
+#[cfg(feature = "time")]
+async fn accept_with_buffer(delay: Option<std::time::Duration>) -> (TcpStream, Vec<u8>) {
+    use std::{net::Ipv4Addr, time::Duration};
+
+    use compio::time::sleep;
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = async {
+        let cli = TcpStream::connect(&addr).await.unwrap();
+        if let Some(delay) = delay {
+            sleep(delay).await;
+            cli.send_all(b"PROXY TCP4".as_slice()).await.0.unwrap();
+        }
+        cli
+    };
+    let server = listener.accept_with_buffer(Vec::with_capacity(64), Duration::from_millis(200));
+    let (cli, (res, buffer)) = futures_util::join!(client, server);
+    let (srv, peer, received) = res.unwrap();
+    assert_eq!(peer, cli.local_addr().unwrap());
+    assert_eq!(srv.peer_addr().unwrap(), cli.local_addr().unwrap());
+    assert_eq!(received, buffer.len());
+    srv.shutdown(std::net::Shutdown::Write).unwrap();
+    (cli, buffer)
+}
+
+#[test]
+#[cfg(feature = "time")]
+fn accept_with_immediate_data() {
+    compio::task::block_on(async {
+        let (_cli, buffer) = accept_with_buffer(Some(std::time::Duration::ZERO)).await;
+        assert_eq!(buffer, b"PROXY TCP4");
+    })
+}
+
+#[test]
+#[cfg(feature = "time")]
+fn accept_with_delayed_data() {
+    compio::task::block_on(async {
+        let (_cli, buffer) = accept_with_buffer(Some(std::time::Duration::from_millis(50))).await;
+        assert_eq!(buffer, b"PROXY TCP4");
+    })
+}
+
+#[test]
+#[cfg(all(feature = "time", unix))]
+fn accept_without_data() {
+    compio::task::block_on(async {
+        let start = std::time::Instant::now();
+        let (cli, buffer) = accept_with_buffer(None).await;
+        assert!(buffer.is_empty());
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+        // The stream is still usable.
+        let (res, buffer) = cli.recv(Vec::with_capacity(1)).await;
+        assert_eq!(res.unwrap(), 0);
+        assert!(buffer.is_empty());
+    })
+}
@@ -0,0 +1,88 @@
+use std::{
+    io,
+    net::Ipv4Addr,
+    time::{Duration, Instant},
+};
+
+use compio::net::{TcpListener, TcpStream};
+
+async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, (server, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    (client, server)
+}
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+// The server doesn't read the whole request, and closes after writing the
+// response. The connection would be reset if it closes at once, and the slow
+// client loses the response.
+#[test]
+fn slow_reader() {
+    const LEN: usize = 8 * 1024 * 1024;
+
+    let response = pattern(LEN);
+
+    compio::task::block_on(async {
+        let (client, server) = tcp_pair().await;
+        client.send_all(vec![1u8; 4096]).await.0.unwrap();
+
+        let serve = async {
+            server.send_all(response.clone()).await.0.unwrap();
+            let deadline = Instant::now() + Duration::from_secs(10);
+            server.close_graceful(deadline).await
+        };
+        let read = async {
+            let mut received = Vec::with_capacity(LEN);
+            loop {
+                compio::time::sleep(Duration::from_millis(1)).await;
+                let (res, buffer) = client.recv(Vec::with_capacity(64 * 1024)).await;
+                match res.unwrap() {
+                    0 => break,
+                    _ => received.extend_from_slice(&buffer),
+                }
+            }
+            drop(client);
+            received
+        };
+        let (closed, received) = futures_util::join!(serve, read);
+        closed.unwrap();
+        assert_eq!(received.len(), LEN);
+        assert!(received == response);
+    })
+}
+
+#[test]
+fn deadline() {
+    compio::task::block_on(async {
+        let (client, server) = tcp_pair().await;
+
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(100);
+        let err = server.close_graceful(deadline).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // The server closes the socket.
+        let (res, _) = client.recv(Vec::with_capacity(1)).await;
+        assert_eq!(res.unwrap(), 0);
+    })
+}
+
+#[test]
+fn linger() {
+    compio::task::block_on(async {
+        let (client, server) = tcp_pair().await;
+        server.set_linger(Some(Duration::ZERO)).unwrap();
+        assert_eq!(server.linger().unwrap(), Some(Duration::ZERO));
+        drop(server);
+
+        let (res, _) = client.recv(Vec::with_capacity(1)).await;
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::ConnectionReset);
+    })
+}
@@ -1,4 +1,8 @@
-use std::net::{IpAddr, SocketAddr};
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
 
 use compio::net::{TcpListener, TcpStream, ToSockAddrs};
 
@@ -101,3 +105,119 @@ fn connect_invalid_dst() {
         assert!(TcpStream::connect("127.0.0.1:1").await.is_err());
     })
 }
+
+#[cfg(unix)]
+#[test]
+fn reuse_fd_number() {
+    use std::os::fd::AsRawFd;
+
+    async fn ping(tx: &TcpStream, rx: &TcpStream) {
+        // The receive waits for the data, so that the fd is registered in the
+        // poller of the polling driver.
+        let ((res, buffer), sent) =
+            futures_util::join!(rx.recv_exact(Vec::with_capacity(4)), tx.send_all("ping"));
+        sent.0.unwrap();
+        res.unwrap();
+        assert_eq!(buffer, b"ping");
+    }
+
+    compio::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (tx, (rx, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+        ping(&tx, &rx).await;
+
+        // Close the socket of `rx`, and reuse its fd number for another one.
+        let (tx, (other, _)) =
+            futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+        assert_ne!(unsafe { libc::dup2(other.as_raw_fd(), rx.as_raw_fd()) }, -1);
+        ping(&tx, &rx).await;
+    })
+}
+
+async fn connect_with_data_impl(listener: &TcpListener) {
+    let addr = listener.local_addr().unwrap();
+
+    let (connected, accepted) = futures_util::join!(
+        TcpStream::connect_with_data(&addr, b"hello".to_vec()),
+        listener.accept()
+    );
+    let (res, buf) = connected;
+    let (client, sent) = res.unwrap();
+    assert_eq!(buf, b"hello");
+    assert_eq!(sent, 5);
+    let (server, _) = accepted.unwrap();
+    let (res, buf) = server.recv_exact(Vec::with_capacity(5)).await;
+    res.unwrap();
+    assert_eq!(buf, b"hello");
+
+    // The stream works after the data.
+    client.send_all(" world").await.0.unwrap();
+    let (res, buf) = server.recv_exact(Vec::with_capacity(6)).await;
+    res.unwrap();
+    assert_eq!(buf, b" world");
+    server.send_all("bye").await.0.unwrap();
+    let (res, buf) = client.recv_exact(Vec::with_capacity(3)).await;
+    res.unwrap();
+    assert_eq!(buf, b"bye");
+}
+
+#[test]
+fn connect_with_data() {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let res = listener.set_fastopen(16);
+        if cfg!(any(target_os = "linux", target_os = "windows")) {
+            res.unwrap();
+        } else {
+            assert_eq!(res.unwrap_err().kind(), io::ErrorKind::Unsupported);
+        }
+        // The first connection gets the cookie, and the second one sends the data
+        // with the SYN.
+        connect_with_data_impl(&listener).await;
+        connect_with_data_impl(&listener).await;
+    })
+}
+
+#[test]
+fn connect_with_data_fallback() {
+    // Without Fast Open on the listener, the data is sent after the handshake.
+    // If the client side is disabled by `net.ipv4.tcp_fastopen`, it connects and
+    // then sends.
+    compio::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        connect_with_data_impl(&listener).await;
+    })
+}
+
+#[test]
+fn connect_with_data_refused() {
+    compio::task::block_on(async {
+        let (res, buf) = TcpStream::connect_with_data("127.0.0.1:1", "hello").await;
+        assert_eq!(buf, "hello");
+        // With a cached cookie, the data is sent with the SYN, and the error is
+        // reported by the next IO.
+        if let Ok((stream, _)) = res {
+            let (res, _) = stream.recv(Vec::with_capacity(1)).await;
+            assert!(res.is_err());
+        }
+    })
+}
+
+#[test]
+fn defer_accept() {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let res = listener.set_defer_accept(Some(Duration::from_secs(1)));
+        if cfg!(target_os = "linux") {
+            res.unwrap();
+        } else {
+            assert_eq!(res.unwrap_err().kind(), io::ErrorKind::Unsupported);
+            return;
+        }
+        // The accept completes with the data.
+        connect_with_data_impl(&listener).await;
+    })
+}
@@ -0,0 +1,134 @@
+use std::{
+    io::ErrorKind,
+    net::{Ipv4Addr, SocketAddr},
+};
+
+use compio::net::{SocketOpts, TcpConnectOpts, TcpListener, TcpStream};
+
+async fn connect(opts: &TcpConnectOpts) -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+    let port = listener.local_addr().unwrap().as_socket().unwrap().port();
+    let (client, accepted) = futures_util::join!(
+        TcpStream::connect_with((Ipv4Addr::LOCALHOST, port), opts),
+        listener.accept()
+    );
+    (client.unwrap(), accepted.unwrap().0)
+}
+
+#[cfg(unix)]
+fn getsockopt(stream: &TcpStream, level: libc::c_int, name: libc::c_int) -> libc::c_int {
+    use std::os::fd::AsRawFd;
+
+    let mut value: libc::c_int = 0;
+    let mut len = std::mem::size_of_val(&value) as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            level,
+            name,
+            std::ptr::addr_of_mut!(value).cast(),
+            &mut len,
+        )
+    };
+    assert_eq!(res, 0);
+    value
+}
+
+#[test]
+fn local_addr() {
+    // Only Linux routes the whole 127.0.0.0/8 to the loopback by default.
+    let local: SocketAddr = (Ipv4Addr::new(127, 0, 0, 2), 0).into();
+    if std::net::TcpListener::bind(local).is_err() {
+        println!("127.0.0.2 is not available, skipped");
+        return;
+    }
+    compio::task::block_on(async {
+        let opts = TcpConnectOpts::new().local_addr(local);
+        // The options are reused.
+        for _ in 0..2 {
+            let (client, server) = connect(&opts).await;
+            let addr = client.local_addr().unwrap().as_socket().unwrap();
+            assert_eq!(addr.ip(), local.ip());
+            assert_eq!(server.peer_addr().unwrap(), client.local_addr().unwrap());
+        }
+    })
+}
+
+#[test]
+fn local_addr_not_available() {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        // The documentation address is not configured on any interface.
+        let opts = TcpConnectOpts::new().local_addr((Ipv4Addr::new(192, 0, 2, 1), 0).into());
+        let e = TcpStream::connect_with(&addr, &opts)
+            .await
+            .err()
+            .expect("bound to a non-local address");
+        assert_eq!(e.kind(), ErrorKind::AddrNotAvailable);
+    })
+}
+
+#[test]
+#[cfg(unix)]
+fn nodelay_and_tos() {
+    compio::task::block_on(async {
+        let (client, _server) = connect(&TcpConnectOpts::new()).await;
+        assert_eq!(getsockopt(&client, libc::IPPROTO_TCP, libc::TCP_NODELAY), 0);
+
+        let opts = TcpConnectOpts::new().nodelay(true).tos(0x10);
+        let (client, _server) = connect(&opts).await;
+        assert_ne!(getsockopt(&client, libc::IPPROTO_TCP, libc::TCP_NODELAY), 0);
+        assert_eq!(getsockopt(&client, libc::IPPROTO_IP, libc::IP_TOS), 0x10);
+    })
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn socket_opts() {
+    compio::task::block_on(async {
+        // Both kinds of the options are accepted.
+        let opts = SocketOpts::new().bind_device(Some("lo"));
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, accepted) =
+            futures_util::join!(TcpStream::connect_with(&addr, &opts), listener.accept());
+        client.unwrap();
+        accepted.unwrap();
+
+        let opts = TcpConnectOpts::from(opts).bind_device(Some("compio-none"));
+        let e = TcpStream::connect_with(&addr, &opts)
+            .await
+            .err()
+            .expect("bound to a missing device");
+        assert_eq!(e.raw_os_error(), Some(libc::ENODEV));
+    })
+}
+
+#[test]
+#[cfg(all(target_os = "linux", feature = "time"))]
+fn timeout() {
+    use std::time::{Duration, Instant};
+
+    use socket2::{Domain, Socket, Type};
+
+    // The SYNs are dropped when the accept queue is full.
+    let listener = Socket::new(Domain::IPV4, Type::STREAM, None).unwrap();
+    listener
+        .bind(&SocketAddr::from((Ipv4Addr::LOCALHOST, 0)).into())
+        .unwrap();
+    listener.listen(0).unwrap();
+    let addr = listener.local_addr().unwrap().as_socket().unwrap();
+    let _queued = std::net::TcpStream::connect(addr).unwrap();
+
+    compio::task::block_on(async {
+        let opts = TcpConnectOpts::new().timeout(Duration::from_millis(100));
+        let start = Instant::now();
+        let e = TcpStream::connect_with(addr, &opts)
+            .await
+            .err()
+            .expect("connected to a full listener");
+        assert_eq!(e.kind(), ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    })
+}
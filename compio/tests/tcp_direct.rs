@@ -0,0 +1,216 @@
+use std::net::{Ipv4Addr, Shutdown, SocketAddr};
+
+use compio::net::{TcpListener, TcpStream};
+
+// The port of a direct listener can't be queried, so take a free one first.
+fn free_addr() -> SocketAddr {
+    std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+#[test]
+fn echo() {
+    compio::task::block_on(async {
+        let addr = free_addr();
+        let listener = TcpListener::bind_direct(addr).await.unwrap();
+        if !listener.is_direct() {
+            println!("Direct sockets are not supported, testing the fallback...");
+        }
+
+        let server = compio::task::spawn(async move {
+            let (stream, _) = listener.accept_direct().await.unwrap();
+            assert_eq!(stream.is_direct(), listener.is_direct());
+            loop {
+                let (res, buf) = stream.recv(Vec::with_capacity(64)).await;
+                if res.unwrap() == 0 {
+                    break;
+                }
+                stream.send_all(buf).await.0.unwrap();
+            }
+        });
+
+        let client = TcpStream::connect(addr).await.unwrap();
+        for msg in ["hello", "direct", "descriptors"] {
+            client.send_all(msg).await.0.unwrap();
+            let (res, buf) = client.recv_exact(Vec::with_capacity(msg.len())).await;
+            res.unwrap();
+            assert_eq!(buf, msg.as_bytes());
+        }
+        drop(client);
+        server.await;
+    })
+}
+
+#[test]
+fn accept_remote_addr() {
+    compio::task::block_on(async {
+        let addr = free_addr();
+        let listener = TcpListener::bind_direct(addr).await.unwrap();
+        let (client, accepted) =
+            futures_util::join!(TcpStream::connect(addr), listener.accept_direct());
+        let client = client.unwrap();
+        let (_stream, remote) = accepted.unwrap();
+        assert_eq!(remote, client.local_addr().unwrap());
+    })
+}
+
+#[test]
+fn many_connections() {
+    compio::task::block_on(async {
+        let addr = free_addr();
+        let listener = TcpListener::bind_direct(addr).await.unwrap();
+        // The slots of the closed streams are reused.
+        for i in 0..64u8 {
+            let (client, accepted) =
+                futures_util::join!(TcpStream::connect(addr), listener.accept_direct());
+            let client = client.unwrap();
+            let (stream, _) = accepted.unwrap();
+            stream.send_all(vec![i]).await.0.unwrap();
+            let (res, buf) = client.recv_exact(Vec::with_capacity(1)).await;
+            res.unwrap();
+            assert_eq!(buf, [i]);
+        }
+    })
+}
+
+#[test]
+fn shutdown_and_close() {
+    compio::task::block_on(async {
+        let addr = free_addr();
+        let listener = TcpListener::bind_direct(addr).await.unwrap();
+        let (client, accepted) =
+            futures_util::join!(TcpStream::connect(addr), listener.accept_direct());
+        let client = client.unwrap();
+        let (stream, _) = accepted.unwrap();
+
+        stream.send_all("bye").await.0.unwrap();
+        stream.shutdown(Shutdown::Write).await.unwrap();
+        let (res, buf) = client.recv_to_end(vec![]).await;
+        res.unwrap();
+        assert_eq!(buf, b"bye");
+
+        // Still readable after shutting down the write half.
+        client.send_all("ok").await.0.unwrap();
+        let (res, buf) = stream.recv(Vec::with_capacity(2)).await;
+        assert_eq!(res.unwrap(), 2);
+        assert_eq!(buf, b"ok");
+        stream.close().await.unwrap();
+    })
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod slots {
+    use std::net::Ipv4Addr;
+
+    use compio::{
+        driver::ProactorBuilder,
+        net::{TcpListener, TcpStream},
+        task::direct_slot_stats,
+    };
+
+    // Run on a new thread with a file table of `size` slots.
+    fn with_table<F: std::future::Future<Output = ()>>(
+        size: u32,
+        f: impl FnOnce() -> F + Send + 'static,
+    ) {
+        std::thread::spawn(move || {
+            compio::task::init_with(&ProactorBuilder::new().direct_table_size(size)).unwrap();
+            compio::task::block_on(f())
+        })
+        .join()
+        .unwrap()
+    }
+
+    #[test]
+    fn accept_waits_for_slot() {
+        with_table(4, || async {
+            let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            let addr = listener.local_addr().unwrap().as_socket().unwrap();
+            let mut clients = vec![];
+            let mut streams = vec![];
+            for _ in 0..4 {
+                clients.push(TcpStream::connect(addr).await.unwrap());
+                let (stream, _) = listener.accept_direct().await.unwrap();
+                if !stream.is_direct() {
+                    println!("Direct accept is not supported, skipping...");
+                    return;
+                }
+                streams.push(stream);
+            }
+            let stats = direct_slot_stats();
+            assert_eq!((stats.capacity, stats.used, stats.waits), (4, 4, 0));
+
+            // The table is full, so the next accept waits instead of failing.
+            clients.push(TcpStream::connect(addr).await.unwrap());
+            let accept = compio::task::spawn(async move {
+                let (stream, _) = listener.accept_direct().await.unwrap();
+                stream
+            });
+            while direct_slot_stats().waits == 0 {
+                compio::task::yield_now().await;
+            }
+            assert_eq!(direct_slot_stats().used, 4);
+
+            streams.pop().unwrap().close().await.unwrap();
+            let stream = accept.await;
+            assert!(stream.is_direct());
+            stream.send_all("hello").await.0.unwrap();
+            let (res, buf) = clients[4].recv_exact(Vec::with_capacity(5)).await;
+            res.unwrap();
+            assert_eq!(buf, b"hello");
+
+            drop(stream);
+            drop(streams);
+            let stats = direct_slot_stats();
+            assert_eq!((stats.used, stats.peak, stats.waits), (0, 4, 1));
+        })
+    }
+
+    #[test]
+    fn slot_recycling() {
+        const CONNECTIONS: usize = 20_000;
+        const BATCH: usize = 200;
+
+        with_table(64, || async {
+            let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            let addr = listener.local_addr().unwrap().as_socket().unwrap();
+            let clients = compio::task::spawn(async move {
+                for _ in 0..CONNECTIONS / BATCH {
+                    let batch = (0..BATCH)
+                        .map(|_| {
+                            compio::task::spawn(async move {
+                                let client = TcpStream::connect(addr).await.unwrap();
+                                let (res, buf) = client.recv_to_end(vec![]).await;
+                                res.unwrap();
+                                assert_eq!(buf, b"hi");
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    for task in batch {
+                        task.await;
+                    }
+                }
+            });
+
+            for i in 0..CONNECTIONS {
+                let (stream, _) = listener.accept_direct().await.unwrap();
+                if i == 0 && !stream.is_direct() {
+                    println!("Direct accept is not supported, skipping...");
+                    return;
+                }
+                compio::task::spawn(async move {
+                    stream.send_all("hi").await.0.unwrap();
+                    stream.close().await.unwrap();
+                })
+                .detach();
+            }
+            clients.await;
+
+            let stats = direct_slot_stats();
+            assert_eq!(stats.used, 0);
+            assert!(stats.peak <= 64);
+        })
+    }
+}
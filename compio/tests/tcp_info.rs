@@ -0,0 +1,61 @@
+use std::net::Ipv4Addr;
+
+use compio::net::{TcpListener, TcpStream};
+
+async fn connect() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, accepted) = futures_util::join!(TcpStream::connect(&addr), listener.accept());
+    (client.unwrap(), accepted.unwrap().0)
+}
+
+#[test]
+#[cfg(any(target_os = "linux", target_vendor = "apple", windows))]
+fn rtt_after_round_trips() {
+    compio::task::block_on(async {
+        let (client, server) = connect().await;
+        for _ in 0..10 {
+            client.send_all("ping").await.0.unwrap();
+            let (res, buf) = server.recv_exact(Vec::with_capacity(4)).await;
+            res.unwrap();
+            server.send_all(buf).await.0.unwrap();
+            let (res, _) = client.recv_exact(Vec::with_capacity(4)).await;
+            res.unwrap();
+        }
+
+        let info = client.tcp_info().unwrap();
+        let rtt = info.rtt.expect("no RTT");
+        assert!(!rtt.is_zero(), "{info:?}");
+        assert!(info.snd_cwnd.unwrap() > 0, "{info:?}");
+        #[cfg(target_os = "linux")]
+        {
+            assert!(info.rtt_var.is_some());
+            assert_eq!(info.retransmits, Some(0));
+            assert!(info.delivery_rate.is_some());
+        }
+    })
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn congestion() {
+    compio::task::block_on(async {
+        let (client, _server) = connect().await;
+        // "reno" is always built in and allowed.
+        client.set_congestion("reno").unwrap();
+        assert_eq!(client.congestion().unwrap(), "reno");
+
+        let e = client.set_congestion("compio-none").unwrap_err();
+        assert_eq!(e.raw_os_error(), Some(libc::ENOENT));
+    })
+}
+
+#[test]
+#[cfg(not(target_os = "linux"))]
+fn congestion_unsupported() {
+    compio::task::block_on(async {
+        let (client, _server) = connect().await;
+        let e = client.set_congestion("reno").unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::Unsupported);
+    })
+}
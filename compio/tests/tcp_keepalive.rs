@@ -0,0 +1,137 @@
+use std::{
+    io::ErrorKind,
+    net::{Ipv4Addr, SocketAddr},
+    rc::Rc,
+    time::Duration,
+};
+
+use compio::net::{PeerUnresponsive, TcpListener, TcpStream};
+use socket2::TcpKeepalive;
+
+async fn pair(addr: SocketAddr) -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind(addr).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, (server, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    (client, server)
+}
+
+#[test]
+fn options() {
+    compio::task::block_on(async {
+        let (client, _server) = pair((Ipv4Addr::LOCALHOST, 0).into()).await;
+        assert!(!client.keepalive().unwrap());
+        let keepalive = TcpKeepalive::new().with_time(Duration::from_secs(30));
+        client.set_keepalive(Some(&keepalive)).unwrap();
+        assert!(client.keepalive().unwrap());
+        client.set_keepalive(None).unwrap();
+        assert!(!client.keepalive().unwrap());
+
+        if cfg!(any(target_os = "linux", target_os = "android")) {
+            assert_eq!(client.user_timeout().unwrap(), None);
+            client
+                .set_user_timeout(Some(Duration::from_millis(1500)))
+                .unwrap();
+            assert_eq!(
+                client.user_timeout().unwrap(),
+                Some(Duration::from_millis(1500))
+            );
+            client.set_user_timeout(None).unwrap();
+            assert_eq!(client.user_timeout().unwrap(), None);
+        } else {
+            let e = client.set_user_timeout(None).unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::Unsupported);
+        }
+    })
+}
+
+// Run `f` in a thread of a new network namespace, where the packets to
+// 127.0.0.2 could be dropped by `drop_packets`. It requires `CAP_NET_ADMIN`
+// and `ip`, and is skipped without them.
+#[cfg(target_os = "linux")]
+fn in_netns(f: impl FnOnce() + Send + 'static) {
+    std::thread::spawn(move || {
+        if unsafe { libc::unshare(libc::CLONE_NEWNET) } != 0 {
+            println!("cannot create a network namespace, skipped");
+            return;
+        }
+        if !ip(&["link", "set", "lo", "up"]) {
+            println!("cannot run `ip`, skipped");
+            return;
+        }
+        f()
+    })
+    .join()
+    .unwrap()
+}
+
+// The child process is in the namespace of the calling thread.
+#[cfg(target_os = "linux")]
+fn ip(args: &[&str]) -> bool {
+    std::process::Command::new("ip")
+        .args(args)
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(target_os = "linux")]
+fn drop_packets() {
+    assert!(ip(&["route", "add", "blackhole", "127.0.0.2/32", "table", "local"]));
+}
+
+#[cfg(target_os = "linux")]
+fn assert_unresponsive(e: std::io::Error) {
+    assert_eq!(e.kind(), ErrorKind::TimedOut);
+    let inner = e
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<PeerUnresponsive>())
+        .expect("not an unresponsive peer");
+    assert_eq!(inner.raw_os_error(), Some(libc::ETIMEDOUT));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn user_timeout() {
+    in_netns(|| {
+        compio::task::block_on(async {
+            let (client, _server) = pair((Ipv4Addr::new(127, 0, 0, 2), 0).into()).await;
+            client
+                .set_user_timeout(Some(Duration::from_millis(500)))
+                .unwrap();
+            let client = Rc::new(client);
+            let recv = compio::task::spawn({
+                let client = client.clone();
+                async move { client.recv(Vec::with_capacity(8)).await.0 }
+            });
+            compio::task::yield_now().await;
+
+            drop_packets();
+            // The data is never acknowledged.
+            client.send_all("hello").await.0.unwrap();
+            assert_unresponsive(recv.await.unwrap_err());
+            // Reported once.
+            let e = client.send("hello").await.0.unwrap_err();
+            assert_eq!(e.kind(), ErrorKind::BrokenPipe);
+        })
+    })
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn keepalive() {
+    in_netns(|| {
+        compio::task::block_on(async {
+            let (client, _server) = pair((Ipv4Addr::new(127, 0, 0, 2), 0).into()).await;
+            let keepalive = TcpKeepalive::new()
+                .with_time(Duration::from_secs(1))
+                .with_interval(Duration::from_secs(1))
+                .with_retries(1);
+            client.set_keepalive(Some(&keepalive)).unwrap();
+            let recv = client.recv(Vec::with_capacity(8));
+
+            drop_packets();
+            // The connection is idle, and the probes are not answered.
+            assert_unresponsive(recv.await.0.unwrap_err());
+        })
+    })
+}
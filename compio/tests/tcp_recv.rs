@@ -0,0 +1,142 @@
+use std::{net::Ipv4Addr, time::Duration};
+
+use compio::net::{TcpListener, TcpStream};
+
+async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, (server, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    (client, server)
+}
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+// Wait until the bytes arrive in the receive queue.
+fn wait_queued(stream: &TcpStream, len: usize) {
+    for _ in 0..100 {
+        if stream.recv_queued().unwrap() >= len {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("the data doesn't arrive");
+}
+
+#[test]
+fn grow_append() {
+    compio::task::block_on(async {
+        let (client, server) = tcp_pair().await;
+        let mut expected = b"prefix".to_vec();
+        let mut buffer = expected.clone();
+        for len in [1, 100, 5000, 3, 70000, 10] {
+            let data = pattern(len);
+            client.send_all(data.clone()).await.0.unwrap();
+            expected.extend_from_slice(&data);
+
+            let mut received = 0;
+            while received < len {
+                let read;
+                (read, buffer) = server.recv_grow(buffer, 16).await;
+                let read = read.unwrap();
+                assert!(read > 0);
+                received += read;
+                assert_eq!(buffer, expected[..buffer.len()]);
+            }
+            assert_eq!(received, len);
+        }
+        assert_eq!(buffer, expected);
+    })
+}
+
+#[test]
+fn grow_queued() {
+    const LEN: usize = 60000;
+
+    compio::task::block_on(async {
+        let (client, server) = tcp_pair().await;
+        let data = pattern(LEN);
+        client.send_all(data.clone()).await.0.unwrap();
+        wait_queued(&server, LEN);
+
+        // All queued bytes are received at once, though the spare is small.
+        let (res, buffer) = server.recv_grow(Vec::new(), 1).await;
+        assert_eq!(res.unwrap(), LEN);
+        assert_eq!(buffer, data);
+        assert_eq!(server.recv_queued().unwrap(), 0);
+    })
+}
+
+#[test]
+fn grow_full() {
+    compio::task::block_on(async {
+        let (client, server) = tcp_pair().await;
+        client.send_all("hello").await.0.unwrap();
+
+        // There is no spare capacity, and it is reserved anyway.
+        let mut buffer = Vec::with_capacity(3);
+        buffer.extend_from_slice(b"abc");
+        let (res, buffer) = server.recv_grow(buffer, 0).await;
+        let read = res.unwrap();
+        assert!(read > 0);
+        assert_eq!(buffer, b"abchello"[..3 + read]);
+    })
+}
+
+#[test]
+fn to_end() {
+    const LEN: usize = 1024 * 1024;
+
+    compio::task::block_on(async {
+        let (client, server) = tcp_pair().await;
+        let data = pattern(LEN);
+        let (sent, (res, buffer)) = futures_util::join!(
+            async move {
+                let res = client.send_all(data).await;
+                drop(client);
+                res
+            },
+            server.recv_to_end(b"prefix".to_vec())
+        );
+        sent.0.unwrap();
+        assert_eq!(res.unwrap(), LEN);
+        assert_eq!(&buffer[..6], b"prefix");
+        assert_eq!(buffer[6..], pattern(LEN));
+    })
+}
+
+#[test]
+fn poll_first() {
+    compio::task::block_on(async {
+        let (client, server) = tcp_pair().await;
+        assert!(!server.recv_poll_first());
+        server.set_recv_poll_first(true);
+        assert!(server.recv_poll_first());
+
+        // The recv waits for the data.
+        let (res, buffer) = futures_util::join!(
+            server.recv_exact(Vec::with_capacity(5)),
+            client.send_all("hello")
+        )
+        .0;
+        res.unwrap();
+        assert_eq!(buffer, b"hello");
+
+        // The data is already there.
+        client.send_all(pattern(64)).await.0.unwrap();
+        wait_queued(&server, 64);
+        let buffers = vec![Vec::with_capacity(16), Vec::with_capacity(48)];
+        let (res, buffers) = server.recv_vectored(buffers).await;
+        assert_eq!(res.unwrap(), 64);
+        assert_eq!(buffers.concat(), pattern(64));
+
+        // The clone keeps the setting.
+        let server = server.try_clone().unwrap();
+        assert!(server.recv_poll_first());
+        drop(client);
+        let (res, _) = server.recv(Vec::with_capacity(16)).await;
+        assert_eq!(res.unwrap(), 0);
+    })
+}
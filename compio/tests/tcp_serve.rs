@@ -0,0 +1,273 @@
+use std::{
+    cell::Cell,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+};
+
+use compio::net::{ServeOptions, TcpListener, TcpStream};
+use futures_util::FutureExt;
+
+#[test]
+fn hammer_and_shutdown() {
+    const CLIENTS: usize = 200;
+    const LIMIT: usize = 8;
+
+    compio::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = futures_channel::oneshot::channel::<()>();
+
+        let live = Rc::new(Cell::new(0usize));
+        let max_live = Rc::new(Cell::new(0usize));
+        let handled = Rc::new(Cell::new(0usize));
+
+        let client = compio::task::spawn(async move {
+            for i in 0..CLIENTS {
+                let stream = TcpStream::connect(&addr).await.unwrap();
+                let buf = (i as u32).to_le_bytes().to_vec();
+                stream.send_all(buf.clone()).await.0.unwrap();
+                let (res, back) = stream.recv_exact(Vec::with_capacity(4)).await;
+                res.unwrap();
+                assert_eq!(back, buf);
+            }
+            tx.send(()).unwrap();
+        });
+
+        let connections = {
+            let live = live.clone();
+            let max_live = max_live.clone();
+            let handled = handled.clone();
+            listener
+                .serve_until(
+                    ServeOptions::new().limit(Some(LIMIT)),
+                    move |stream, _| {
+                        let live = live.clone();
+                        let max_live = max_live.clone();
+                        let handled = handled.clone();
+                        live.set(live.get() + 1);
+                        max_live.set(max_live.get().max(live.get()));
+                        async move {
+                            let (res, buf) = stream.recv_exact(Vec::with_capacity(4)).await;
+                            res.unwrap();
+                            stream.send_all(buf).await.0.unwrap();
+                            live.set(live.get() - 1);
+                            handled.set(handled.get() + 1);
+                        }
+                    },
+                    async {
+                        rx.await.unwrap();
+                    },
+                )
+                .await
+                .unwrap()
+        };
+        connections.wait().await;
+        client.await;
+
+        assert_eq!(handled.get(), CLIENTS);
+        assert_eq!(live.get(), 0);
+        assert!(max_live.get() <= LIMIT);
+    })
+}
+
+#[test]
+fn limit_and_cancel() {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = futures_channel::oneshot::channel::<()>();
+
+        let accepted = Rc::new(Cell::new(0usize));
+        let client = compio::task::spawn(async move {
+            // The handlers never complete, so only the first one is accepted.
+            let first = TcpStream::connect(&addr).await.unwrap();
+            first.recv_exact(Vec::with_capacity(1)).await.0.unwrap();
+            let second = TcpStream::connect(&addr).await.unwrap();
+            tx.send(()).unwrap();
+            (first, second)
+        });
+
+        let connections = {
+            let accepted = accepted.clone();
+            listener
+                .serve_until(
+                    ServeOptions::new().limit(Some(1)),
+                    move |stream, _| {
+                        accepted.set(accepted.get() + 1);
+                        async move {
+                            stream.send_all(vec![0u8]).await.0.unwrap();
+                            futures_util::future::pending::<()>().await;
+                        }
+                    },
+                    async {
+                        rx.await.unwrap();
+                    },
+                )
+                .await
+                .unwrap()
+        };
+        assert_eq!(accepted.get(), 1);
+        assert_eq!(connections.len(), 1);
+        // Cancel the live handler.
+        drop(connections);
+        client.await;
+    })
+}
+
+#[test]
+fn handler_panic() {
+    compio::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = futures_channel::oneshot::channel::<()>();
+
+        let handled = Rc::new(Cell::new(0usize));
+        let panics = Rc::new(Cell::new(0usize));
+        let client = compio::task::spawn(async move {
+            for _ in 0..4 {
+                let stream = TcpStream::connect(&addr).await.unwrap();
+                stream.send_all(vec![0u8]).await.0.unwrap();
+                // Wait for the handler to complete.
+                stream.recv(Vec::with_capacity(1)).await.0.ok();
+            }
+            tx.send(()).unwrap();
+        });
+
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+        let res = AssertUnwindSafe(async {
+            let handled = handled.clone();
+            let connections = listener
+                .serve_until(
+                    ServeOptions::new().on_panic({
+                        let panics = panics.clone();
+                        move |payload| {
+                            assert_eq!(payload.downcast_ref(), Some(&"handler panics"));
+                            panics.set(panics.get() + 1);
+                        }
+                    }),
+                    move |stream, _| {
+                        let handled = handled.clone();
+                        async move {
+                            let (res, _) = stream.recv_exact(Vec::with_capacity(1)).await;
+                            res.unwrap();
+                            let count = handled.get();
+                            handled.set(count + 1);
+                            if count.is_multiple_of(2) {
+                                panic!("handler panics");
+                            }
+                        }
+                    },
+                    async {
+                        rx.await.unwrap();
+                    },
+                )
+                .await
+                .unwrap();
+            connections.wait().await;
+        })
+        .catch_unwind()
+        .await;
+        panic::set_hook(hook);
+
+        assert!(res.is_ok());
+        assert_eq!(handled.get(), 4);
+        assert_eq!(panics.get(), 2);
+        client.await;
+    })
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn hammer_during_shutdown() {
+    use std::{cell::RefCell, time::Duration};
+
+    const CLIENTS: usize = 16;
+    const LIMIT: usize = 4;
+
+    compio::task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = futures_channel::oneshot::channel::<()>();
+        let tx = Rc::new(RefCell::new(Some(tx)));
+
+        let stop = Rc::new(Cell::new(false));
+        let rounds = Rc::new(Cell::new(0usize));
+        let clients = (0..CLIENTS)
+            .map(|_| {
+                let stop = stop.clone();
+                let rounds = rounds.clone();
+                let tx = tx.clone();
+                let addr = addr.clone();
+                compio::task::spawn(async move {
+                    // Keep connecting until the listener is closed.
+                    while !stop.get() {
+                        let Ok(stream) = TcpStream::connect(&addr).await else {
+                            break;
+                        };
+                        if stream.send_all(vec![1u8; 4]).await.0.is_err() {
+                            break;
+                        }
+                        match stream.recv_exact(Vec::with_capacity(4)).await.0 {
+                            Ok(_) => {}
+                            Err(_) => break,
+                        }
+                        rounds.set(rounds.get() + 1);
+                        // Trigger the shutdown while the others are connecting.
+                        if rounds.get() == 100 {
+                            tx.borrow_mut().take().unwrap().send(()).unwrap();
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let live = Rc::new(Cell::new(0usize));
+        let max_live = Rc::new(Cell::new(0usize));
+        let started = Rc::new(Cell::new(0usize));
+        let handled = Rc::new(Cell::new(0usize));
+        let connections = {
+            let live = live.clone();
+            let max_live = max_live.clone();
+            let started = started.clone();
+            let handled = handled.clone();
+            listener
+                .serve_until(
+                    ServeOptions::new().limit(Some(LIMIT)),
+                    move |stream, _| {
+                        let live = live.clone();
+                        let max_live = max_live.clone();
+                        let handled = handled.clone();
+                        started.set(started.get() + 1);
+                        live.set(live.get() + 1);
+                        max_live.set(max_live.get().max(live.get()));
+                        async move {
+                            let (res, buf) = stream.recv_exact(Vec::with_capacity(4)).await;
+                            res.unwrap();
+                            stream.send_all(buf).await.0.unwrap();
+                            live.set(live.get() - 1);
+                            handled.set(handled.get() + 1);
+                        }
+                    },
+                    async {
+                        rx.await.unwrap();
+                    },
+                )
+                .await
+                .unwrap()
+        };
+        // Reset the connections in the backlog.
+        stop.set(true);
+        drop(listener);
+        let cancelled = connections.shutdown(Duration::from_secs(10)).await;
+        for client in clients {
+            client.await;
+        }
+
+        assert_eq!(cancelled, 0);
+        assert!(rounds.get() >= 100);
+        assert_eq!(handled.get(), started.get());
+        assert_eq!(live.get(), 0);
+        assert!(max_live.get() <= LIMIT);
+    })
+}
@@ -0,0 +1,63 @@
+use compio::fs::{tempfile_in, File};
+
+const HELLO: &[u8] = b"hello world...";
+
+fn tempdir() -> tempfile::TempDir {
+    tempfile::Builder::new()
+        .prefix("compio-tempfile-tests")
+        .tempdir()
+        .unwrap()
+}
+
+#[test]
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn persist() {
+    compio::task::block_on(async {
+        let dir = tempdir();
+        let path = dir.path().join("persisted");
+
+        let file = tempfile_in(dir.path()).unwrap();
+        file.write_all_at(HELLO, 0).await.0.unwrap();
+        file.persist(&path).unwrap();
+        file.write_all_at(HELLO, HELLO.len() as u64).await.0.unwrap();
+        file.sync_all().await.unwrap();
+        drop(file);
+
+        let file = File::open(&path).unwrap();
+        let (res, buffer) = file.read_to_end_at(Vec::with_capacity(64), 0).await;
+        res.unwrap();
+        assert_eq!(buffer, [HELLO, HELLO].concat());
+    })
+}
+
+#[test]
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn persist_exists() {
+    let dir = tempdir();
+    let path = dir.path().join("exists");
+    std::fs::write(&path, HELLO).unwrap();
+
+    let file = tempfile_in(dir.path()).unwrap();
+    let err = file.persist(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    drop(file);
+
+    assert_eq!(std::fs::read(&path).unwrap(), HELLO);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn not_persisted() {
+    compio::task::block_on(async {
+        let dir = tempdir();
+
+        let file = tempfile_in(dir.path()).unwrap();
+        file.write_all_at(HELLO, 0).await.0.unwrap();
+        let (res, buffer) = file.read_to_end_at(Vec::with_capacity(64), 0).await;
+        res.unwrap();
+        assert_eq!(buffer, HELLO);
+        drop(file);
+
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    })
+}
@@ -0,0 +1,132 @@
+#![cfg(unix)]
+
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+use compio::io::{Terminal, TerminalSize};
+
+// Open a pseudo terminal, and return the master and the slave.
+fn pty() -> (OwnedFd, OwnedFd) {
+    let (mut master, mut slave) = (-1, -1);
+    let res = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            std::ptr::null(),
+        )
+    };
+    assert_eq!(res, 0, "{}", std::io::Error::last_os_error());
+    unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) }
+}
+
+fn resize(master: &OwnedFd, columns: u16, rows: u16) {
+    let winsize = libc::winsize {
+        ws_row: rows,
+        ws_col: columns,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let res = unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &winsize) };
+    assert_eq!(res, 0, "{}", std::io::Error::last_os_error());
+}
+
+fn lflag(fd: &OwnedFd) -> libc::tcflag_t {
+    let mut termios = unsafe { std::mem::zeroed() };
+    assert_eq!(unsafe { libc::tcgetattr(fd.as_raw_fd(), &mut termios) }, 0);
+    termios.c_lflag
+}
+
+#[test]
+fn tty() {
+    let (master, slave) = pty();
+    let terminal = unsafe { Terminal::borrow_raw(slave.as_raw_fd()) };
+    assert!(terminal.is_tty());
+    resize(&master, 100, 40);
+    assert_eq!(
+        terminal.size().unwrap(),
+        TerminalSize {
+            columns: 100,
+            rows: 40
+        }
+    );
+
+    let file = std::fs::File::open("Cargo.toml").unwrap();
+    let file = unsafe { Terminal::borrow_raw(file.as_raw_fd()) };
+    assert!(!file.is_tty());
+    assert!(file.size().is_err());
+    assert!(file.enable_raw_mode().is_err());
+}
+
+#[test]
+fn raw_mode() {
+    let (master, slave) = pty();
+    let terminal = unsafe { Terminal::borrow_raw(slave.as_raw_fd()) };
+    let cooked = lflag(&slave);
+    assert_ne!(cooked & libc::ICANON, 0);
+
+    let guard = terminal.enable_raw_mode().unwrap();
+    assert_eq!(lflag(&slave) & (libc::ICANON | libc::ECHO | libc::ISIG), 0);
+    compio::task::block_on(async {
+        // A key is read without a newline.
+        let res = unsafe { libc::write(master.as_raw_fd(), b"q".as_ptr() as _, 1) };
+        assert_eq!(res, 1);
+        let (res, key) = terminal.read(Vec::with_capacity(16)).await;
+        res.unwrap();
+        assert_eq!(key, b"q");
+    });
+    drop(guard);
+    assert_eq!(lflag(&slave), cooked);
+}
+
+#[test]
+fn read_line() {
+    let (master, slave) = pty();
+    let terminal = unsafe { Terminal::borrow_raw(slave.as_raw_fd()) };
+    compio::task::block_on(async {
+        // The line is written after the read is pending, so the read doesn't
+        // block the runtime.
+        let write = async {
+            let res = unsafe { libc::write(master.as_raw_fd(), b"hello\n".as_ptr() as _, 6) };
+            assert_eq!(res, 6);
+        };
+        let ((res, line), ()) = futures_util::join!(terminal.read(Vec::with_capacity(16)), write);
+        res.unwrap();
+        assert_eq!(line, b"hello\n");
+    });
+}
+
+#[test]
+#[cfg(feature = "signal")]
+fn resize_events() {
+    let (master, slave) = pty();
+    let terminal = unsafe { Terminal::borrow_raw(slave.as_raw_fd()) };
+    resize(&master, 80, 24);
+    compio::task::block_on(async {
+        let mut events = terminal.resize_events().unwrap();
+        // A burst of resizes is reported once, with the latest size.
+        for columns in 81..=90 {
+            resize(&master, columns, 24);
+            unsafe { libc::kill(libc::getpid(), libc::SIGWINCH) };
+        }
+        let size = events.next_size().await.unwrap();
+        assert_eq!(
+            size,
+            TerminalSize {
+                columns: 90,
+                rows: 24
+            }
+        );
+
+        let next = compio::task::spawn(async move { events.next_size().await });
+        resize(&master, 120, 50);
+        unsafe { libc::kill(libc::getpid(), libc::SIGWINCH) };
+        assert_eq!(
+            next.await.unwrap(),
+            TerminalSize {
+                columns: 120,
+                rows: 50
+            }
+        );
+    });
+}
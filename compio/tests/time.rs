@@ -0,0 +1,97 @@
+use std::time::{Duration, Instant};
+
+use compio::{
+    driver::ProactorBuilder,
+    time::{interval, sleep, sleep_coarse},
+};
+
+// Run `f` on a new runtime, and return the count of its driver wakeups.
+fn driver_waits<F: std::future::Future<Output = ()>>(
+    builder: ProactorBuilder,
+    f: impl FnOnce() -> F + Send + 'static,
+) -> u64 {
+    std::thread::spawn(move || {
+        compio::task::init_with(&builder).unwrap();
+        compio::task::block_on(f());
+        compio::task::poll_stats().blocking_waits
+    })
+    .join()
+    .unwrap()
+}
+
+async fn staggered_timers() {
+    let tasks = (0..10_000)
+        .map(|i| {
+            compio::task::spawn(async move {
+                sleep(Duration::from_millis(100) + Duration::from_micros(i * 50)).await
+            })
+        })
+        .collect::<Vec<_>>();
+    for task in tasks {
+        task.await;
+    }
+}
+
+#[test]
+fn coarse_resolution() {
+    let fine = driver_waits(ProactorBuilder::new(), staggered_timers);
+    let coarse = driver_waits(
+        ProactorBuilder::new().timer_resolution(Duration::from_millis(10)),
+        staggered_timers,
+    );
+    assert!(coarse * 10 <= fine, "{coarse} vs {fine}");
+}
+
+#[test]
+fn coarse_sleep() {
+    compio::task::block_on(async {
+        let start = Instant::now();
+        sleep_coarse(Duration::from_millis(1)).await;
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(1));
+        assert!(elapsed < Duration::from_millis(100), "{elapsed:?}");
+    })
+}
+
+#[test]
+fn coarse_interval() {
+    const PERIOD: Duration = Duration::from_millis(15);
+
+    std::thread::spawn(|| {
+        compio::task::init_with(
+            &ProactorBuilder::new().timer_resolution(Duration::from_millis(10)),
+        )
+        .unwrap();
+        compio::task::block_on(async {
+            let mut interval = interval(PERIOD);
+            let start = interval.tick().await;
+            for i in 1..=10 {
+                // The ticks are late within a resolution, but are not delayed
+                // by the previous ones.
+                assert_eq!(interval.tick().await, start + PERIOD * i);
+            }
+            let elapsed = start.elapsed();
+            assert!(elapsed < PERIOD * 10 + Duration::from_millis(50), "{elapsed:?}");
+        })
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn cancelled_timer_reused() {
+    compio::task::block_on(async {
+        // Registered, and cancelled before it expires.
+        let cancelled = compio::task::spawn(sleep(Duration::from_millis(20)));
+        compio::task::yield_now().await;
+        drop(cancelled);
+        compio::task::yield_now().await;
+
+        // Reuse the key of the cancelled timer, which shouldn't expire at the
+        // deadline of it.
+        let start = Instant::now();
+        sleep(Duration::from_millis(100)).await;
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(100), "{elapsed:?}");
+    })
+}
@@ -1,4 +1,9 @@
-use compio::net::UdpSocket;
+use std::{
+    net::{Ipv4Addr, Ipv6Addr},
+    time::{Duration, SystemTime},
+};
+
+use compio::net::{TimestampKind, UdpSocket};
 
 #[test]
 fn connect() {
@@ -69,3 +74,430 @@ fn send_to() {
         );
     })
 }
+
+#[cfg(unix)]
+fn link_local_addr() -> Option<std::net::SocketAddrV6> {
+    use std::net::{Ipv6Addr, SocketAddrV6};
+
+    let mut ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut ifaddrs) } != 0 {
+        return None;
+    }
+    let mut res = None;
+    let mut current = ifaddrs;
+    while let Some(ifaddr) = unsafe { current.as_ref() } {
+        current = ifaddr.ifa_next;
+        let addr = ifaddr.ifa_addr;
+        if addr.is_null() || unsafe { (*addr).sa_family } as i32 != libc::AF_INET6 {
+            continue;
+        }
+        let addr = unsafe { &*addr.cast::<libc::sockaddr_in6>() };
+        let ip = Ipv6Addr::from(addr.sin6_addr.s6_addr);
+        if ip.segments()[0] & 0xffc0 == 0xfe80 && addr.sin6_scope_id != 0 {
+            res = Some(SocketAddrV6::new(ip, 0, 0, addr.sin6_scope_id));
+            break;
+        }
+    }
+    unsafe { libc::freeifaddrs(ifaddrs) };
+    res
+}
+
+#[cfg(unix)]
+#[test]
+fn link_local_scope() {
+    let Some(addr) = link_local_addr() else {
+        println!("No link-local address, skipped.");
+        return;
+    };
+
+    compio::task::block_on(async {
+        const MSG: &str = "foo bar baz";
+
+        let (Ok(passive), Ok(active)) = (UdpSocket::bind(addr), UdpSocket::bind(addr)) else {
+            println!("Cannot bind to {addr}, skipped.");
+            return;
+        };
+        let passive_addr = passive.local_addr().unwrap();
+
+        active.send_to(MSG, &passive_addr).await.0.unwrap();
+        let (res, buffer) = passive.recv_from(Vec::with_capacity(20)).await;
+        let (_, remote) = res.unwrap();
+        assert_eq!(buffer, MSG.as_bytes());
+        let remote_v6 = remote.as_socket_ipv6().unwrap();
+        assert_eq!(remote_v6.scope_id(), addr.scope_id());
+
+        // Echo with the received address.
+        passive.send_to(buffer, &remote).await.0.unwrap();
+        let (res, buffer) = active.recv_from(Vec::with_capacity(20)).await;
+        let (_, remote) = res.unwrap();
+        assert_eq!(buffer, MSG.as_bytes());
+        assert_eq!(remote.as_socket_ipv6().unwrap().scope_id(), addr.scope_id());
+    })
+}
+
+// Lease the flow label for the sends of the socket, which are then sent with
+// the flow info of the destinations.
+#[cfg(target_os = "linux")]
+fn lease_flow_label(socket: &UdpSocket, label: u32) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // `struct in6_flowlabel_req`, not exported by libc.
+    #[repr(C)]
+    struct FlowLabelReq {
+        dst: [u8; 16],
+        label: u32,
+        action: u8,
+        share: u8,
+        flags: u16,
+        expires: u16,
+        linger: u16,
+        pad: u32,
+    }
+    const IPV6_FL_A_GET: u8 = 0;
+    const IPV6_FL_S_EXCL: u8 = 1;
+    const IPV6_FL_F_CREATE: u16 = 1;
+
+    let setsockopt = |name, value: *const libc::c_void, len: usize| {
+        let res = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                libc::IPPROTO_IPV6,
+                name,
+                value,
+                len as _,
+            )
+        };
+        if res == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    };
+    let req = FlowLabelReq {
+        dst: Ipv6Addr::LOCALHOST.octets(),
+        label: label.to_be(),
+        action: IPV6_FL_A_GET,
+        share: IPV6_FL_S_EXCL,
+        flags: IPV6_FL_F_CREATE,
+        expires: 0,
+        linger: 0,
+        pad: 0,
+    };
+    setsockopt(
+        libc::IPV6_FLOWLABEL_MGR,
+        std::ptr::addr_of!(req).cast(),
+        std::mem::size_of_val(&req),
+    )?;
+    let enable: libc::c_int = 1;
+    setsockopt(
+        libc::IPV6_FLOWINFO_SEND,
+        std::ptr::addr_of!(enable).cast(),
+        std::mem::size_of_val(&enable),
+    )
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn flow_info() {
+    use std::net::SocketAddrV6;
+
+    // The leases linger after the sockets are closed, so the labels differ
+    // across the processes.
+    let label = 0x10000 + std::process::id() % 0x10000;
+
+    compio::task::block_on(async {
+        let (Ok(passive), Ok(active)) = (
+            UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)),
+            UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)),
+        ) else {
+            println!("No IPv6 loopback, skipped.");
+            return;
+        };
+        if let Err(e) = lease_flow_label(&active, label) {
+            println!("Cannot lease the flow label: {e}, skipped.");
+            return;
+        }
+        let port = passive.local_addr().unwrap().as_socket().unwrap().port();
+        // The flow info is in the network byte order.
+        let dest = SocketAddrV6::new(Ipv6Addr::LOCALHOST, port, label.to_be(), 0);
+
+        // The kernel rejects a flow label not leased, so the send fails if the
+        // flow info is lost.
+        active.send_to("flow", dest).await.0.unwrap();
+        let (res, buffer) = passive.recv_from(Vec::with_capacity(8)).await;
+        res.unwrap();
+        assert_eq!(buffer, b"flow");
+        let other = SocketAddrV6::new(Ipv6Addr::LOCALHOST, port, (label + 1).to_be(), 0);
+        let e = active.send_to("flow", other).await.0.unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+
+        active.connect(dest).unwrap();
+        let peer = active.peer_addr().unwrap();
+        assert_eq!(peer.as_socket_ipv6().unwrap().flowinfo(), label.to_be());
+    })
+}
+
+#[test]
+fn recv_timestamps() {
+    compio::task::block_on(async {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        receiver.set_recv_timestamps(true).unwrap();
+        // The kernel may enable the timestamps of all packets lazily, after
+        // the first socket asks for them, so the first packets may have none.
+        let mut tries = 0;
+        let (sent_at, completed_at, timestamp) = loop {
+            let sent_at = SystemTime::now();
+            sender.send_to("kernel", &addr).await.0.unwrap();
+            let (res, buffer) = receiver.recv_from_timestamped(Vec::with_capacity(8)).await;
+            let completed_at = SystemTime::now();
+            let (_, _, timestamp) = res.unwrap();
+            assert_eq!(buffer, b"kernel");
+            tries += 1;
+            if cfg!(windows) || timestamp.kind != TimestampKind::Userspace || tries == 100 {
+                break (sent_at, completed_at, timestamp);
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        if cfg!(unix) {
+            assert_ne!(timestamp.kind, TimestampKind::Userspace);
+            assert!(timestamp.time >= sent_at);
+            let delay = completed_at.duration_since(timestamp.time).unwrap();
+            assert!(delay < Duration::from_secs(10), "{delay:?}");
+        } else {
+            assert_eq!(timestamp.kind, TimestampKind::Userspace);
+            assert!(timestamp.time <= completed_at);
+        }
+
+        receiver.set_recv_timestamps(false).unwrap();
+        let sent_at = SystemTime::now();
+        sender.send_to("userspace", &addr).await.0.unwrap();
+        let (res, buffer) = receiver.recv_from_timestamped(Vec::with_capacity(16)).await;
+        let (_, _, timestamp) = res.unwrap();
+        assert_eq!(buffer, b"userspace");
+        assert_eq!(timestamp.kind, TimestampKind::Userspace);
+        assert!(timestamp.time >= sent_at);
+        assert!(timestamp.time <= SystemTime::now());
+    })
+}
+
+// The timestamp may be lost in the truncated control messages.
+#[cfg(target_os = "linux")]
+#[test]
+fn recv_timestamps_truncated() {
+    compio::task::block_on(async {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_recv_timestamps(true).unwrap();
+        receiver.set_recv_meta(true).unwrap();
+
+        sender.send_to("meta", &addr).await.0.unwrap();
+        let (res, buffer) = receiver.recv_from_timestamped(Vec::with_capacity(8)).await;
+        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(buffer, b"meta");
+    })
+}
+
+#[test]
+fn recv_meta() {
+    compio::task::block_on(async {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_recv_meta(true).unwrap();
+        // ECT(0).
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::fd::{AsRawFd, BorrowedFd};
+
+            let fd = unsafe { BorrowedFd::borrow_raw(sender.as_raw_fd()) };
+            socket2::SockRef::from(&fd).set_tos_v4(0b10).unwrap();
+        }
+
+        sender.send_to("hello", &addr).await.0.unwrap();
+        let (res, buffer) = receiver.recv_meta(Vec::with_capacity(16)).await;
+        let meta = res.unwrap();
+        assert_eq!(buffer, b"hello");
+        assert_eq!(meta.len, 5);
+        assert_eq!(meta.addr, sender.local_addr().unwrap());
+        assert!(!meta.flags.truncated());
+        assert!(!meta.flags.control_truncated());
+        assert_eq!(meta.gro_segments, None);
+        assert_eq!(meta.timestamp, None);
+        if cfg!(unix) {
+            assert!(meta.ttl.is_some_and(|ttl| ttl > 0), "{meta:?}");
+            assert_eq!(meta.dst_addr, Some(Ipv4Addr::LOCALHOST.into()));
+        }
+        if cfg!(target_os = "linux") {
+            assert_eq!(meta.ecn, Some(0b10));
+        }
+
+        // Disabled, and truncated.
+        receiver.set_recv_meta(false).unwrap();
+        sender.send_to("truncated", &addr).await.0.unwrap();
+        let (res, buffer) = receiver.recv_meta(Vec::with_capacity(4)).await;
+        let meta = res.unwrap();
+        assert_eq!(buffer, b"trun");
+        assert_eq!(meta.len, 4);
+        assert_eq!(meta.ttl, None);
+        assert_eq!(meta.dst_addr, None);
+        if cfg!(unix) {
+            assert!(meta.flags.truncated());
+        }
+    })
+}
+
+#[test]
+#[cfg(unix)]
+fn recv_meta_v6() {
+    if std::net::UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).is_err() {
+        println!("IPv6 is not available, skipped");
+        return;
+    }
+    compio::task::block_on(async {
+        let receiver = UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let sender = UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).unwrap();
+        receiver.set_recv_meta(true).unwrap();
+
+        sender.send_to("hello", &addr).await.0.unwrap();
+        let (res, _) = receiver.recv_meta(Vec::with_capacity(16)).await;
+        let meta = res.unwrap();
+        assert_eq!(meta.addr, sender.local_addr().unwrap());
+        assert!(meta.ttl.is_some_and(|ttl| ttl > 0), "{meta:?}");
+        assert_eq!(meta.ecn, Some(0));
+        assert_eq!(meta.dst_addr, Some(Ipv6Addr::LOCALHOST.into()));
+    })
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn recv_meta_dual_stack() {
+    use compio::net::{MappedAddrPolicy, SocketOpts};
+
+    if std::net::UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).is_err() {
+        println!("IPv6 is not available, skipped");
+        return;
+    }
+    compio::task::block_on(async {
+        let receiver =
+            UdpSocket::bind_with((Ipv6Addr::UNSPECIFIED, 0), &SocketOpts::new().only_v6(false))
+                .unwrap();
+        receiver.set_mapped_addr_policy(MappedAddrPolicy::Unmap);
+        receiver.set_recv_meta(true).unwrap();
+        let port = receiver.local_addr().unwrap().as_socket().unwrap().port();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+        sender
+            .send_to("hello", (Ipv4Addr::LOCALHOST, port))
+            .await
+            .0
+            .unwrap();
+        let (res, _) = receiver.recv_meta(Vec::with_capacity(16)).await;
+        let meta = res.unwrap();
+        assert_eq!(meta.addr, sender.local_addr().unwrap());
+        assert!(meta.ttl.is_some(), "{meta:?}");
+        assert_eq!(meta.dst_addr, Some(Ipv4Addr::LOCALHOST.into()));
+    })
+}
+
+#[test]
+fn mtu_discovery() {
+    use compio::net::MtuDiscovery;
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let modes = if cfg!(any(target_os = "linux", target_os = "android")) {
+        &[
+            MtuDiscovery::Want,
+            MtuDiscovery::Do,
+            MtuDiscovery::Dont,
+            MtuDiscovery::Probe,
+        ][..]
+    } else {
+        &[MtuDiscovery::Do, MtuDiscovery::Dont][..]
+    };
+    for mode in modes {
+        socket.set_mtu_discovery(*mode).unwrap();
+        assert_eq!(socket.mtu_discovery().unwrap(), *mode);
+    }
+
+    if std::net::UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).is_err() {
+        println!("IPv6 is not available, skipped");
+        return;
+    }
+    let socket = UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).unwrap();
+    for mode in modes {
+        socket.set_mtu_discovery(*mode).unwrap();
+        assert_eq!(socket.mtu_discovery().unwrap(), *mode);
+    }
+}
+
+#[test]
+fn datagram_too_large() {
+    use compio::net::DatagramTooLarge;
+
+    compio::task::block_on(async {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = receiver.local_addr().unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        // Larger than any UDP datagram.
+        let huge = vec![0u8; 70000];
+
+        let (res, huge) = sender.send_to(huge, &addr).await;
+        let e = res.unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+        let too_large = e.get_ref().unwrap().downcast_ref::<DatagramTooLarge>();
+        let too_large = too_large.unwrap();
+        assert!(too_large.raw_os_error().is_some());
+        assert_eq!(too_large.mtu(), None);
+
+        sender.connect(&addr).unwrap();
+        let (res, _) = sender.send(huge).await;
+        let e = res.unwrap_err();
+        let too_large = e.get_ref().unwrap().downcast_ref::<DatagramTooLarge>();
+        if cfg!(any(target_os = "linux", target_os = "windows")) {
+            let mtu = sender.mtu().unwrap();
+            assert!(mtu >= 1280);
+            assert_eq!(too_large.unwrap().mtu(), Some(mtu));
+        }
+
+        // Other errors are not wrapped.
+        sender.send("hello").await.0.unwrap();
+        let (res, _) = receiver.recv(Vec::with_capacity(8)).await;
+        assert_eq!(res.unwrap(), 5);
+    })
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn error_events() {
+    use compio::net::ErrorOrigin;
+    use futures_util::StreamExt;
+
+    compio::task::block_on(async {
+        // A port nobody listens on.
+        let closed = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = closed.local_addr().unwrap();
+        drop(closed);
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut events = socket.error_events().unwrap();
+        socket.send_to("hello", &addr).await.0.unwrap();
+
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(event.errno, libc::ECONNREFUSED);
+        assert_eq!(event.error().kind(), std::io::ErrorKind::ConnectionRefused);
+        assert_eq!(event.origin, ErrorOrigin::Icmp);
+        // Destination unreachable, port unreachable.
+        assert_eq!((event.icmp_type, event.icmp_code), (3, 3));
+        assert_eq!(event.mtu, None);
+        assert_eq!(event.destination, Some(addr));
+        assert_eq!(
+            event.offender.and_then(|addr| addr.as_socket()).map(|addr| addr.ip()),
+            Some(Ipv4Addr::LOCALHOST.into())
+        );
+    })
+}
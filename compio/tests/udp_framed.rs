@@ -0,0 +1,157 @@
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr},
+};
+
+use compio::{
+    io::framed::{ConnectedUdpFramed, DatagramTruncated, LinesCodec, UdpFramed},
+    net::UdpSocket,
+};
+use futures_util::{SinkExt, StreamExt};
+
+fn bind() -> (UdpSocket, SocketAddr) {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = socket.local_addr().unwrap().as_socket().unwrap();
+    (socket, addr)
+}
+
+// Reply the sum of the numbers in each request, until `bye`.
+async fn serve(mut server: UdpFramed<LinesCodec>) -> Vec<io::Error> {
+    let mut errors = vec![];
+    while let Some(res) = server.next().await {
+        let (line, addr) = match res {
+            Ok(frame) => frame,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        if line == "bye" {
+            break;
+        }
+        let sum = line
+            .split_whitespace()
+            .map(|n| n.parse::<i64>().unwrap())
+            .sum::<i64>();
+        server.send((sum.to_string(), addr)).await.unwrap();
+    }
+    errors
+}
+
+#[test]
+fn request_response() {
+    compio::task::block_on(async {
+        let (server, server_addr) = bind();
+        let (client, client_addr) = bind();
+        let mut server = UdpFramed::new(server, LinesCodec::new());
+        server.set_max_datagram_size(1024);
+        let server = compio::task::spawn(serve(server));
+
+        let mut client = UdpFramed::new(client, LinesCodec::new());
+        for (request, response) in [("1 2", "3"), ("", "0"), ("-5 10 20", "25")] {
+            client.send((request, server_addr)).await.unwrap();
+            let (line, addr) = client.next().await.unwrap().unwrap();
+            assert_eq!(line, response);
+            assert_eq!(addr, server_addr);
+        }
+
+        // The oversized datagram is rejected, and the server keeps working.
+        let large = "1 ".repeat(1000);
+        client.send((large, server_addr)).await.unwrap();
+        client.send(("4 5", server_addr)).await.unwrap();
+        let (line, _) = client.next().await.unwrap().unwrap();
+        assert_eq!(line, "9");
+
+        client.send(("bye", server_addr)).await.unwrap();
+        let errors = server.await;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind(), io::ErrorKind::InvalidData);
+        let e = errors[0]
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<DatagramTruncated>())
+            .expect("not a truncation error");
+        assert_eq!(e.max_size(), 1024);
+        // Windows fails the receive without the origin.
+        if cfg!(unix) {
+            assert_eq!(e.addr(), Some(client_addr));
+        }
+    })
+}
+
+#[test]
+fn exact_max_size() {
+    compio::task::block_on(async {
+        let (server, server_addr) = bind();
+        let (client, _) = bind();
+        let mut server = UdpFramed::new(server, LinesCodec::new());
+        server.set_max_datagram_size(16);
+        let mut client = UdpFramed::new(client, LinesCodec::new());
+
+        // 15 bytes and the line ending.
+        client.send(("a".repeat(15), server_addr)).await.unwrap();
+        let (line, _) = server.next().await.unwrap().unwrap();
+        assert_eq!(line.len(), 15);
+    })
+}
+
+#[test]
+fn batched() {
+    const COUNT: usize = 200;
+
+    compio::task::block_on(async {
+        let (server, server_addr) = bind();
+        let (client, _) = bind();
+        let mut server = UdpFramed::new(server, LinesCodec::new());
+        let mut client = UdpFramed::new(client, LinesCodec::new());
+
+        // Several lines in a datagram are all decoded.
+        client.send(("a\nb", server_addr)).await.unwrap();
+        assert_eq!(server.next().await.unwrap().unwrap().0, "a");
+        assert_eq!(server.next().await.unwrap().unwrap().0, "b");
+
+        for i in 0..COUNT {
+            client.feed((i.to_string(), server_addr)).await.unwrap();
+        }
+        SinkExt::<(String, SocketAddr)>::flush(&mut client)
+            .await
+            .unwrap();
+        let mut received = vec![];
+        for _ in 0..COUNT {
+            let (line, _) = server.next().await.unwrap().unwrap();
+            received.push(line.parse::<usize>().unwrap());
+        }
+        // Loopback doesn't drop or reorder them in practice, but the order in
+        // a batch is not guaranteed.
+        received.sort_unstable();
+        assert_eq!(received, (0..COUNT).collect::<Vec<_>>());
+    })
+}
+
+#[test]
+fn connected() {
+    compio::task::block_on(async {
+        let (first, first_addr) = bind();
+        let (second, second_addr) = bind();
+        first.connect(second_addr).unwrap();
+        second.connect(first_addr).unwrap();
+        let mut first = ConnectedUdpFramed::new(first, LinesCodec::new());
+        let mut second = ConnectedUdpFramed::new(second, LinesCodec::new());
+        second.set_max_datagram_size(8);
+
+        first.send("ping").await.unwrap();
+        assert_eq!(second.next().await.unwrap().unwrap(), "ping");
+        second.send("pong").await.unwrap();
+        assert_eq!(first.next().await.unwrap().unwrap(), "pong");
+
+        first.send("too long to fit").await.unwrap();
+        let e = second.next().await.unwrap().unwrap_err();
+        let e = e
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<DatagramTruncated>())
+            .expect("not a truncation error");
+        assert_eq!(e.addr(), None);
+
+        let (socket, _) = second.into_inner();
+        assert_eq!(socket.peer_addr().unwrap().as_socket(), Some(first_addr));
+    })
+}
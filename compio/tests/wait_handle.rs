@@ -0,0 +1,73 @@
+#![cfg(target_os = "windows")]
+
+use std::{
+    os::windows::io::{AsHandle, AsRawHandle, FromRawHandle, OwnedHandle},
+    ptr::null,
+    time::Duration,
+};
+
+use compio::os::windows::wait_handle;
+use futures_util::FutureExt;
+use windows_sys::Win32::System::Threading::{CreateEventW, ResetEvent, SetEvent};
+
+fn event(manual_reset: bool) -> OwnedHandle {
+    let handle = unsafe { CreateEventW(null(), manual_reset as _, 0, null()) };
+    assert_ne!(handle, 0);
+    unsafe { OwnedHandle::from_raw_handle(handle as _) }
+}
+
+fn set(event: &OwnedHandle) {
+    assert_ne!(unsafe { SetEvent(event.as_raw_handle() as _) }, 0);
+}
+
+#[test]
+fn already_signaled() {
+    compio::task::block_on(async {
+        let event = event(true);
+        set(&event);
+        wait_handle(event.as_handle()).await.unwrap();
+        wait_handle(event.as_handle()).await.unwrap();
+    })
+}
+
+#[test]
+fn signaled_later() {
+    compio::task::block_on(async {
+        let event = event(false);
+        let raw = event.as_raw_handle() as isize;
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            unsafe { SetEvent(raw) };
+        });
+        wait_handle(event.as_handle()).await.unwrap();
+        thread.join().unwrap();
+    })
+}
+
+#[test]
+fn concurrent_waits() {
+    compio::task::block_on(async {
+        let event = event(true);
+        let waits = (0..8).map(|_| wait_handle(event.as_handle()));
+        let waits = futures_util::future::join_all(waits);
+        let waits = std::pin::pin!(waits);
+        let mut waits = waits.fuse();
+        assert!((&mut waits).now_or_never().is_none());
+        set(&event);
+        for res in waits.await {
+            res.unwrap();
+        }
+    })
+}
+
+#[test]
+fn cancel() {
+    compio::task::block_on(async {
+        let event = event(true);
+        // Register the wait and drop it.
+        assert!(wait_handle(event.as_handle()).now_or_never().is_none());
+        set(&event);
+        wait_handle(event.as_handle()).await.unwrap();
+        unsafe { ResetEvent(event.as_raw_handle() as _) };
+    })
+}
@@ -0,0 +1,163 @@
+use std::path::{Path, PathBuf};
+
+use compio::fs::{WalkError, WalkOptions, walk_dir};
+use futures_util::StreamExt;
+
+// A tree of `width` directories and files at each level, down to `depth`.
+fn make_tree(root: &Path, width: usize, depth: usize) {
+    if depth == 0 {
+        return;
+    }
+    for i in 0..width {
+        std::fs::write(root.join(format!("file{i}")), i.to_string()).unwrap();
+        let dir = root.join(format!("dir{i}"));
+        std::fs::create_dir(&dir).unwrap();
+        make_tree(&dir, width, depth - 1);
+    }
+}
+
+// A recursive walk with the names sorted.
+fn std_walk(path: &Path, depth: usize, paths: &mut Vec<(PathBuf, usize)>) {
+    paths.push((path.to_path_buf(), depth));
+    if std::fs::symlink_metadata(path).unwrap().is_dir() {
+        let mut entries = std::fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        entries.sort();
+        for entry in entries {
+            std_walk(&entry, depth + 1, paths);
+        }
+    }
+}
+
+async fn walk(root: &Path, opts: WalkOptions) -> Vec<(PathBuf, usize)> {
+    walk_dir(root, opts)
+        .map(|entry| {
+            let entry = entry.unwrap();
+            let depth = entry.depth();
+            (entry.into_path(), depth)
+        })
+        .collect()
+        .await
+}
+
+#[test]
+fn same_as_std() {
+    compio::task::block_on(async {
+        let root = tempfile::tempdir().unwrap();
+        make_tree(root.path(), 4, 4);
+
+        let mut expected = vec![];
+        std_walk(root.path(), 0, &mut expected);
+        expected.sort();
+
+        for concurrency in [1, 3, 16] {
+            let mut paths = walk(root.path(), WalkOptions::new().concurrency(concurrency)).await;
+            paths.sort();
+            assert_eq!(paths, expected);
+        }
+    })
+}
+
+#[test]
+fn deterministic() {
+    compio::task::block_on(async {
+        let root = tempfile::tempdir().unwrap();
+        make_tree(root.path(), 3, 4);
+
+        let mut expected = vec![];
+        std_walk(root.path(), 0, &mut expected);
+
+        for concurrency in [1, 2, 8, 64] {
+            let opts = WalkOptions::new().sorted(true).concurrency(concurrency);
+            assert_eq!(walk(root.path(), opts).await, expected);
+        }
+    })
+}
+
+#[test]
+fn depth_and_filter() {
+    compio::task::block_on(async {
+        let root = tempfile::tempdir().unwrap();
+        make_tree(root.path(), 2, 3);
+
+        let paths = walk(root.path(), WalkOptions::new().min_depth(1).max_depth(2)).await;
+        assert_eq!(paths.len(), 4 + 8);
+        assert!(paths.iter().all(|(_, depth)| (1..=2).contains(depth)));
+
+        // The skipped directories are not descended.
+        let opts = WalkOptions::new()
+            .min_depth(1)
+            .filter(|entry| entry.file_name() != "dir0");
+        let paths = walk(root.path(), opts).await;
+        assert!(
+            paths
+                .iter()
+                .all(|(path, _)| !path.starts_with(root.path().join("dir0")))
+        );
+        assert!(!paths.contains(&(root.path().join("dir1/dir0/file1"), 3)));
+        assert!(paths.contains(&(root.path().join("dir1/dir1/file1"), 3)));
+        assert_eq!(paths.len(), 3 + 3 + 3);
+    })
+}
+
+#[test]
+fn errors_not_abort() {
+    compio::task::block_on(async {
+        let root = tempfile::tempdir().unwrap();
+        make_tree(root.path(), 1, 1);
+
+        let missing = root.path().join("missing");
+        let mut entries = walk_dir(&missing, WalkOptions::new());
+        let e = entries.next().await.unwrap().unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+        let e = e.get_ref().unwrap().downcast_ref::<WalkError>().unwrap();
+        assert_eq!(e.path(), missing);
+        assert!(entries.next().await.is_none());
+    })
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_loop() {
+    compio::task::block_on(async {
+        let root = tempfile::tempdir().unwrap();
+        make_tree(root.path(), 1, 2);
+        std::os::unix::fs::symlink(root.path(), root.path().join("dir0/up")).unwrap();
+        std::os::unix::fs::symlink("nowhere", root.path().join("broken")).unwrap();
+
+        // Not followed.
+        let entries = walk_dir(root.path(), WalkOptions::new().sorted(true))
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(entries.len(), 7);
+        let up = entries.iter().find(|e| e.file_name() == "up").unwrap();
+        assert!(up.path_is_symlink() && up.file_type().is_symlink());
+
+        // Followed, with the loop reported and the walk going on.
+        let entries = walk_dir(
+            root.path(),
+            WalkOptions::new().follow_links(true).sorted(true),
+        )
+        .collect::<Vec<_>>()
+        .await;
+        assert_eq!(entries.len(), 8);
+        let mut errors = entries.iter().filter_map(|e| e.as_ref().err());
+        let e = errors.next().unwrap();
+        assert!(errors.next().is_none());
+        let e = e.get_ref().unwrap().downcast_ref::<WalkError>().unwrap();
+        assert_eq!(e.path(), root.path().join("dir0/up"));
+        assert_eq!(e.loop_ancestor(), Some(root.path()));
+
+        let entries = entries
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect::<Vec<_>>();
+        let up = entries.iter().find(|e| e.file_name() == "up").unwrap();
+        assert!(up.path_is_symlink() && up.file_type().is_dir());
+        let broken = entries.iter().find(|e| e.file_name() == "broken").unwrap();
+        assert!(broken.path_is_symlink() && broken.file_type().is_symlink());
+    })
+}
@@ -0,0 +1,80 @@
+use compio::fs::{watch, WatchEvent, WatchStream};
+
+// The platforms may report additional events, e.g. a modification after
+// creation, so skip the events until the expected one arrives.
+async fn expect_event(stream: &mut WatchStream, expected: WatchEvent) {
+    loop {
+        let event = stream.next_event().await.unwrap();
+        if event == expected {
+            break;
+        }
+    }
+}
+
+#[test]
+fn create_modify_remove() {
+    compio::task::block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("foo");
+        let mut stream = watch(dir.path(), false).unwrap();
+
+        std::fs::write(&path, "foo").unwrap();
+        expect_event(&mut stream, WatchEvent::Created(path.clone())).await;
+
+        std::fs::write(&path, "bar").unwrap();
+        expect_event(&mut stream, WatchEvent::Modified(path.clone())).await;
+
+        std::fs::remove_file(&path).unwrap();
+        expect_event(&mut stream, WatchEvent::Removed(path)).await;
+    })
+}
+
+#[test]
+fn rename() {
+    compio::task::block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("foo");
+        let to = dir.path().join("bar");
+        std::fs::write(&from, "foo").unwrap();
+        let mut stream = watch(dir.path(), false).unwrap();
+
+        std::fs::rename(&from, &to).unwrap();
+        if cfg!(any(target_os = "linux", target_os = "android", windows)) {
+            expect_event(&mut stream, WatchEvent::Renamed { from, to }).await;
+        } else {
+            expect_event(&mut stream, WatchEvent::Removed(from)).await;
+            expect_event(&mut stream, WatchEvent::Created(to)).await;
+        }
+    })
+}
+
+#[test]
+fn recursive() {
+    compio::task::block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        let mut stream = watch(dir.path(), true).unwrap();
+
+        std::fs::create_dir(&sub).unwrap();
+        expect_event(&mut stream, WatchEvent::Created(sub.clone())).await;
+
+        let path = sub.join("foo");
+        std::fs::write(&path, "foo").unwrap();
+        expect_event(&mut stream, WatchEvent::Created(path)).await;
+    })
+}
+
+#[test]
+fn single_file() {
+    compio::task::block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("foo");
+        std::fs::write(&path, "foo").unwrap();
+        let mut stream = watch(&path, false).unwrap();
+
+        std::fs::write(dir.path().join("bar"), "bar").unwrap();
+        std::fs::write(&path, "bar").unwrap();
+        let event = stream.next_event().await.unwrap();
+        assert_eq!(event, WatchEvent::Modified(path));
+    })
+}
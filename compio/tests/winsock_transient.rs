@@ -0,0 +1,227 @@
+#![cfg(windows)]
+
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use compio::{
+    driver::{
+        ProactorBuilder, TransientError, TransientErrors, clear_injected_errors,
+        inject_syscall_error,
+    },
+    net::{TcpListener, TcpStream, UdpSocket},
+    task::RuntimeBuilder,
+};
+use futures_util::FutureExt;
+use windows_sys::Win32::{
+    Foundation::ERROR_NO_SYSTEM_RESOURCES,
+    Networking::WinSock::{WSAEACCES, WSAEINTR, WSAENOBUFS, WSAEWOULDBLOCK},
+};
+
+// Run the future on a new thread, with the policy of the transient errors.
+fn with_policy<F: std::future::Future<Output = ()>>(
+    policy: TransientErrors,
+    f: impl FnOnce() -> F + Send + 'static,
+) {
+    std::thread::spawn(move || {
+        compio::task::init_with(&ProactorBuilder::new().transient_errors(policy)).unwrap();
+        compio::task::block_on(f())
+    })
+    .join()
+    .unwrap()
+}
+
+fn udp_pair() -> (UdpSocket, UdpSocket, SocketAddr) {
+    let receiver = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let sender = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = receiver.local_addr().unwrap();
+    (sender, receiver, addr)
+}
+
+async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, (server, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    (client, server)
+}
+
+fn transient(e: &io::Error) -> &TransientError {
+    e.get_ref().unwrap().downcast_ref::<TransientError>().unwrap()
+}
+
+#[test]
+fn retried() {
+    with_policy(TransientErrors::default(), || async {
+        let (sender, receiver, addr) = udp_pair();
+
+        inject_syscall_error("WSASendTo", WSAENOBUFS, 2);
+        sender.send_to("ping", addr).await.0.unwrap();
+        let (res, buffer) = receiver.recv_from(Vec::with_capacity(4)).await;
+        res.unwrap();
+        assert_eq!(buffer, b"ping");
+
+        inject_syscall_error("WSARecvFrom", WSAEWOULDBLOCK, 3);
+        sender.send_to("pong", addr).await.0.unwrap();
+        let (res, buffer) = receiver.recv_from(Vec::with_capacity(4)).await;
+        res.unwrap();
+        assert_eq!(buffer, b"pong");
+    })
+}
+
+#[test]
+fn retries_exhausted() {
+    let policy = TransientErrors::Retry {
+        attempts: 2,
+        backoff: Duration::from_millis(1),
+    };
+    with_policy(policy, || async {
+        let (sender, receiver, addr) = udp_pair();
+
+        inject_syscall_error("WSASendTo", WSAENOBUFS, 3);
+        let e = sender.send_to("ping", addr).await.0.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(transient(&e).raw_os_error(), Some(WSAENOBUFS));
+        assert_eq!(transient(&e).retries(), 2);
+
+        // The next one is not affected.
+        sender.send_to("ping", addr).await.0.unwrap();
+        let (res, buffer) = receiver.recv_from(Vec::with_capacity(4)).await;
+        res.unwrap();
+        assert_eq!(buffer, b"ping");
+    })
+}
+
+#[test]
+fn mapped() {
+    with_policy(TransientErrors::Map, || async {
+        let (sender, receiver, addr) = udp_pair();
+
+        let table = [
+            (WSAEWOULDBLOCK, io::ErrorKind::WouldBlock),
+            (WSAENOBUFS, io::ErrorKind::WouldBlock),
+            (WSAEINTR, io::ErrorKind::Interrupted),
+        ];
+        for (code, kind) in table {
+            inject_syscall_error("WSARecvFrom", code, 1);
+            let e = receiver
+                .recv_from(Vec::with_capacity(4))
+                .await
+                .0
+                .unwrap_err();
+            assert_eq!(e.kind(), kind);
+            assert_eq!(transient(&e).raw_os_error(), Some(code));
+            assert_eq!(transient(&e).retries(), 0);
+
+            inject_syscall_error("WSASendTo", code, 1);
+            let e = sender.send_to("ping", addr).await.0.unwrap_err();
+            assert_eq!(e.kind(), kind);
+        }
+        clear_injected_errors();
+    })
+}
+
+#[test]
+fn insufficient_resources_of_sends() {
+    with_policy(TransientErrors::Map, || async {
+        let (client, server) = tcp_pair().await;
+        let code = ERROR_NO_SYSTEM_RESOURCES as i32;
+
+        inject_syscall_error("WSASend", code, 1);
+        let e = client.send("ping").await.0.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(transient(&e).raw_os_error(), Some(code));
+
+        // Not transient for the receives.
+        inject_syscall_error("WSARecv", code, 1);
+        let e = server.recv(Vec::with_capacity(4)).await.0.unwrap_err();
+        assert_eq!(e.raw_os_error(), Some(code));
+        assert!(e.get_ref().is_none());
+    })
+}
+
+#[test]
+fn raw() {
+    with_policy(TransientErrors::Raw, || async {
+        let (sender, _receiver, addr) = udp_pair();
+
+        inject_syscall_error("WSASendTo", WSAENOBUFS, 1);
+        let e = sender.send_to("ping", addr).await.0.unwrap_err();
+        assert_eq!(e.raw_os_error(), Some(WSAENOBUFS));
+        assert!(e.get_ref().is_none());
+    })
+}
+
+#[test]
+fn not_transient() {
+    with_policy(TransientErrors::default(), || async {
+        let (sender, _receiver, addr) = udp_pair();
+
+        inject_syscall_error("WSASendTo", WSAEACCES, 1);
+        let e = sender.send_to("ping", addr).await.0.unwrap_err();
+        assert_eq!(e.raw_os_error(), Some(WSAEACCES));
+        assert!(e.get_ref().is_none());
+    })
+}
+
+#[test]
+fn cancel_waiting_retry() {
+    let policy = TransientErrors::Retry {
+        attempts: 1,
+        backoff: Duration::from_secs(60),
+    };
+    with_policy(policy, || async {
+        let (sender, receiver, addr) = udp_pair();
+        let start = Instant::now();
+
+        // Cancelled while waiting for the retry.
+        inject_syscall_error("WSASendTo", WSAENOBUFS, 1);
+        assert!(sender.send_to("ping", addr).now_or_never().is_none());
+
+        sender.send_to("pong", addr).await.0.unwrap();
+        let (res, buffer) = receiver.recv_from(Vec::with_capacity(4)).await;
+        res.unwrap();
+        assert_eq!(buffer, b"pong");
+        assert!(start.elapsed() < Duration::from_secs(60));
+    })
+}
+
+// The sends failed by the seeded transient errors, with them mapped.
+fn seeded_failures(seed: u64) -> Vec<bool> {
+    std::thread::spawn(move || {
+        RuntimeBuilder::new()
+            .proactor(ProactorBuilder::new().transient_errors(TransientErrors::Map))
+            .deterministic(seed)
+            .init()
+            .unwrap();
+        compio::task::block_on(async {
+            let (sender, _receiver, addr) = udp_pair();
+            let mut failures = vec![];
+            for _ in 0..64 {
+                match sender.send_to("ping", addr).await.0 {
+                    Ok(_) => failures.push(false),
+                    Err(e) => {
+                        assert_eq!(transient(&e).raw_os_error(), Some(WSAEWOULDBLOCK));
+                        failures.push(true);
+                    }
+                }
+            }
+            failures
+        })
+    })
+    .join()
+    .unwrap()
+}
+
+#[test]
+fn deterministic() {
+    if std::env::var_os(compio::task::SEED_ENV).is_some() {
+        return;
+    }
+    let failures = seeded_failures(42);
+    assert!(failures.contains(&true));
+    assert_eq!(seeded_failures(42), failures);
+    assert_ne!(seeded_failures(43), failures);
+}
@@ -0,0 +1,93 @@
+use std::{
+    cell::Cell,
+    net::Ipv4Addr,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use compio::net::{TcpListener, TcpStream};
+use futures_util::StreamExt;
+
+fn spin(duration: Duration) {
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        std::hint::spin_loop();
+    }
+}
+
+async fn echo_client() -> TcpStream {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    compio::task::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        loop {
+            let (res, buf) = stream.recv(Vec::with_capacity(16)).await;
+            if !matches!(res, Ok(n) if n > 0) {
+                break;
+            }
+            stream.send_all(buf).await.0.unwrap();
+        }
+    })
+    .detach();
+    TcpStream::connect(addr).await.unwrap()
+}
+
+// Counts the round trips until `done` is set.
+async fn ping_until(client: TcpStream, done: Rc<Cell<bool>>) -> usize {
+    let mut rounds = 0;
+    while !done.get() {
+        client.send_all("ping").await.0.unwrap();
+        let (res, _) = client.recv_exact(Vec::with_capacity(4)).await;
+        res.unwrap();
+        rounds += 1;
+    }
+    rounds
+}
+
+#[test]
+fn yield_now_polls_driver() {
+    compio::task::block_on(async {
+        let client = echo_client().await;
+        let done = Rc::new(Cell::new(false));
+
+        let timer = compio::task::spawn(async {
+            compio::time::sleep(Duration::from_millis(10)).await;
+            Instant::now()
+        });
+        let echo = compio::task::spawn(ping_until(client, done.clone()));
+        let spinner = compio::task::spawn(async move {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(100) {
+                spin(Duration::from_millis(1));
+                compio::task::yield_now().await;
+            }
+            done.set(true);
+            Instant::now()
+        });
+
+        let spin_end = spinner.await;
+        let fired_at = timer.await;
+        let rounds = echo.await;
+        assert!(fired_at < spin_end);
+        assert!(rounds >= 10, "only {rounds} round trips while spinning");
+    })
+}
+
+#[test]
+fn cooperative_stream() {
+    compio::task::block_on(async {
+        let client = echo_client().await;
+        let done = Rc::new(Cell::new(false));
+        let echo = compio::task::spawn(ping_until(client, done.clone()));
+
+        let mut items = compio::task::cooperative(0..100, 2);
+        let mut sum = 0;
+        while let Some(i) = items.next().await {
+            spin(Duration::from_micros(500));
+            sum += i;
+        }
+        done.set(true);
+        assert_eq!(sum, 4950);
+        assert!(echo.await >= 10);
+    })
+}
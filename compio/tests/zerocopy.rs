@@ -0,0 +1,60 @@
+use std::net::Ipv4Addr;
+
+use compio::net::{TcpListener, TcpStream, ZEROCOPY_THRESHOLD};
+
+async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, (rx, _)) =
+        futures_util::try_join!(TcpStream::connect(&addr), listener.accept()).unwrap();
+    (tx, rx)
+}
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn large_send() {
+    compio::task::block_on(async {
+        let (tx, rx) = tcp_pair().await;
+        tx.set_zerocopy(true).unwrap();
+        if !tx.zerocopy() {
+            // Not supported by the driver or the kernel.
+            return;
+        }
+
+        let data = pattern(4 * 1024 * 1024);
+        let (sent, (received, buffer)) = futures_util::join!(
+            tx.send_all(data.clone()),
+            rx.recv_exact(Vec::with_capacity(data.len())),
+        );
+        assert_eq!(sent.0.unwrap(), data.len());
+        assert_eq!(received.unwrap(), data.len());
+        assert_eq!(buffer, data);
+
+        // The buffers are released before the sends complete, and the kernel
+        // always copies the data to a loopback peer.
+        let stats = tx.zerocopy_stats();
+        assert!(stats.completed > 0);
+        assert_eq!(stats.copied, stats.completed);
+    })
+}
+
+#[test]
+fn small_send() {
+    compio::task::block_on(async {
+        let (tx, rx) = tcp_pair().await;
+        tx.set_zerocopy(true).unwrap();
+
+        let data = pattern(ZEROCOPY_THRESHOLD - 1);
+        let (sent, (received, buffer)) = futures_util::join!(
+            tx.send_all(data.clone()),
+            rx.recv_exact(Vec::with_capacity(data.len())),
+        );
+        assert_eq!(sent.0.unwrap(), data.len());
+        assert_eq!(received.unwrap(), data.len());
+        assert_eq!(buffer, data);
+        assert_eq!(tx.zerocopy_stats().completed, 0);
+    })
+}